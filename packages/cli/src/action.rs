@@ -1,10 +1,11 @@
 use nixblitzlib::{
-    app_option_data::option_data::OptionDataChangeNotification, apps::SupportedApps,
+    app_option_data::option_data::{OptionDataChangeNotification, OptionId},
+    apps::SupportedApps,
 };
 use serde::{Deserialize, Serialize};
 use strum::Display;
 
-use crate::constants::FocusableComponent;
+use crate::{constants::FocusableComponent, quick_actions::QuickActionOutput};
 
 #[derive(Debug, Clone, PartialEq, Display, Serialize, Deserialize)]
 pub enum Action {
@@ -20,17 +21,31 @@ pub enum Action {
     NavAppsTab,
     NavSettingsTab,
     NavActionsTab,
+    NavLogsTab,
     NavHelpTab,
     NavUp,
     NavDown,
     NavLeft,
     NavRight,
+    /// Jumps to the first entry of the focused list
+    NavFirst,
+    /// Jumps to the last entry of the focused list
+    NavLast,
     Enter,
     Esc,
     PageUp,
     PageDown,
     FocusRequest(FocusableComponent),
     TogglePasswordVisibility,
+    /// Opens or closes the user defined quick actions menu
+    ToggleQuickActions,
+    /// Opens the fuzzy finder for the options of all apps
+    ToggleSearch,
+    /// A line printed by a running quick action, shown on the logs page
+    QuickActionOutput(QuickActionOutput),
+    /// Sent by components whose content changed without user input,
+    /// e.g. when new log entries arrived. Triggers a redraw.
+    Refresh,
 
     /// A modal is opened.
     ///
@@ -52,9 +67,19 @@ pub enum Action {
     AppTabAppSelected(SupportedApps),
     /// Action sent by the option view when an option is changed
     /// This is then processed by the project, which will then
-    /// trigger a `AppTabOptionChangeAccepted` to be sent
+    /// trigger a `AppTabOptionsChanged` to be sent
     AppTabOptionChangeProposal(OptionDataChangeNotification),
-    /// Action sent when the option view needs to be updated
-    /// (e.g. when the project accepts a change)
+    /// Action sent when all rows of the option view need to be updated
+    /// (e.g. when the project rejected a change)
     AppTabOptionChangeAccepted,
+    /// Action sent when the project accepted a change. Holds the options
+    /// whose data changed, only their rows are updated.
+    AppTabOptionsChanged(Vec<OptionId>),
+    /// Sent by the search when an option was picked. Selects its app and
+    /// the option in the apps tab.
+    JumpToOption(OptionId),
+
+    /// Sent by the file watcher when a project file was modified outside
+    /// of the TUI. The project is reloaded instead of overwriting the edit.
+    ConfigChangedOnDisk,
 }
@@ -1,9 +1,10 @@
 use std::{cell::RefCell, collections::HashMap, path::PathBuf, rc::Rc};
 
-use cli_log::{error, trace};
+use cli_log::{error, trace, warn};
 use crossterm::event::KeyEvent;
 use error_stack::{Report, Result, ResultExt};
-use nixblitzlib::project::Project;
+use nixblitzlib::{errors::ProjectError, nix_format::take_fallbacks, project::Project};
+use notify::RecommendedWatcher;
 use ratatui::{
     layout::{Constraint, Direction, Layout},
     prelude::Rect,
@@ -17,15 +18,19 @@ use crate::{
     action::Action,
     app_contexts::{RenderContext, UpdateContext},
     components::{
+        error_popup::ErrorPopup,
         menu::Menu,
+        quick_actions_popup::QuickActionsPopup,
+        search_popup::SearchPopup,
         theme::{self, ThemeData},
         title::Title,
         Component,
     },
-    config::Config,
+    config::{get_config_dir, Config},
     errors::CliError,
+    file_watcher::watch_project_files,
     pages::{
-        actions_page::ActionsPage, apps_page::AppsPage, help_page::HelpPage,
+        actions_page::ActionsPage, apps_page::AppsPage, help_page::HelpPage, logs_page::LogsPage,
         settings_page::SettingsPage,
     },
     tui::{Event, Tui},
@@ -41,7 +46,8 @@ pub struct App {
     should_quit: bool,
     should_suspend: bool,
     mode: Mode,
-    last_tick_key_events: Vec<KeyEvent>,
+    /// The keys typed so far of a multi-key binding like `<g><g>`
+    pending_key_events: Vec<KeyEvent>,
     action_tx: mpsc::UnboundedSender<Action>,
     action_rx: mpsc::UnboundedReceiver<Action>,
     home_page: ComponentIndex,
@@ -55,6 +61,12 @@ pub struct App {
     /// Tracks whether this modal has a text area
     /// this will direct all input to this modal
     exclusive_input_component_shown: bool,
+
+    /// Watches the project files for manual edits, None if watching failed
+    _watcher: Option<RecommendedWatcher>,
+
+    /// The project files changed while a modal was open
+    reload_pending: bool,
 }
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -70,16 +82,30 @@ enum ComponentIndex {
     AppsPage,
     SettingsPage,
     ActionsPage,
+    LogsPage,
     HelpPage,
+    QuickActions,
+    Search,
+    ErrorPopup,
 }
 
 impl App {
     pub fn new(tick_rate: f64, frame_rate: f64, work_dir: PathBuf) -> Result<Self, CliError> {
         let project =
-            Project::load(work_dir).change_context(CliError::UnableToInitProjectStruct)?;
+            Project::load(work_dir.clone()).change_context(CliError::UnableToInitProjectStruct)?;
         let project = Rc::new(RefCell::new(project));
 
         let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let watcher = match watch_project_files(&work_dir, action_tx.clone()) {
+            Ok(w) => Some(w),
+            Err(e) => {
+                warn!(
+                    "Manual edits of the project files won't be detected: {:?}",
+                    e
+                );
+                None
+            }
+        };
         let mut map: HashMap<ComponentIndex, Box<dyn Component>> = HashMap::new();
         map.insert(
             ComponentIndex::Title,
@@ -95,7 +121,14 @@ impl App {
         );
         map.insert(ComponentIndex::SettingsPage, Box::new(SettingsPage::new()));
         map.insert(ComponentIndex::ActionsPage, Box::new(ActionsPage::new()));
+        map.insert(ComponentIndex::LogsPage, Box::new(LogsPage::new()));
         map.insert(ComponentIndex::HelpPage, Box::new(HelpPage::new()));
+        map.insert(
+            ComponentIndex::QuickActions,
+            Box::new(QuickActionsPopup::new()),
+        );
+        map.insert(ComponentIndex::Search, Box::new(SearchPopup::new()));
+        map.insert(ComponentIndex::ErrorPopup, Box::new(ErrorPopup::new()));
 
         Ok(Self {
             tick_rate,
@@ -104,16 +137,23 @@ impl App {
             should_quit: false,
             should_suspend: false,
             config: Config::new()
-                .attach_printable_lazy(|| "Unable to create new config")
+                .attach_printable_lazy(|| {
+                    format!(
+                        "Unable to load the config from {}",
+                        get_config_dir().display()
+                    )
+                })
                 .change_context(CliError::Unknown)?,
             mode: Mode::Home,
-            last_tick_key_events: Vec::new(),
+            pending_key_events: Vec::new(),
             action_tx,
             action_rx,
             home_page: ComponentIndex::AppsPage,
             project,
             modal_open: false,
             exclusive_input_component_shown: false,
+            _watcher: watcher,
+            reload_pending: false,
             dirty: true,
             theme: Rc::new(RefCell::new(ThemeData::default())),
         })
@@ -208,6 +248,7 @@ impl App {
 
         match keymap.get(&vec![key]) {
             Some(action) => {
+                self.pending_key_events.clear();
                 self.action_tx
                     .clone()
                     .send(action.clone())
@@ -216,14 +257,21 @@ impl App {
             _ => {
                 // If the key was not handled as a single key action,
                 // then consider it for multi-key combinations.
-                self.last_tick_key_events.push(key);
+                self.pending_key_events.push(key);
 
                 // Check for multi-key combinations
-                if let Some(action) = keymap.get(&self.last_tick_key_events) {
+                if let Some(action) = keymap.get(&self.pending_key_events) {
                     self.action_tx
                         .clone()
                         .send(action.clone())
                         .change_context(CliError::Unknown)?;
+                    self.pending_key_events.clear();
+                } else if !keymap
+                    .keys()
+                    .any(|keys| keys.starts_with(&self.pending_key_events))
+                {
+                    // like vim, start over with the next key
+                    self.pending_key_events.clear();
                 }
             }
         }
@@ -241,9 +289,6 @@ impl App {
             }
 
             match action.clone() {
-                Action::Tick => {
-                    self.last_tick_key_events.drain(..);
-                }
                 Action::Quit => self.on_quit(),
                 Action::Suspend => self.should_suspend = true,
                 Action::Resume => self.should_suspend = false,
@@ -252,6 +297,7 @@ impl App {
                 Action::NavAppsTab
                 | Action::NavSettingsTab
                 | Action::NavActionsTab
+                | Action::NavLogsTab
                 | Action::NavHelpTab => {
                     // Don't navigate or forward the event if a modal is opened
                     if self.modal_open {
@@ -262,16 +308,45 @@ impl App {
                 }
                 Action::PushModal(_) | Action::PopModal(_) => self.handle_modal_change(&action)?,
                 Action::AppTabOptionChangeProposal(opt) => {
-                    let updated = self
-                        .project
-                        .borrow_mut()
-                        .on_option_changed(opt)
-                        .change_context(CliError::Unknown)?;
-
-                    if updated {
+                    let res = self.project.borrow_mut().on_option_changed(opt);
+                    // the save went through, but the file wasn't formatted
+                    for fallback in take_fallbacks() {
+                        warn!("{}", fallback);
+                        self.action_tx
+                            .send(Action::Error(fallback.to_string()))
+                            .change_context(CliError::UnableToSendViaUnboundedSender)?;
+                    }
+                    let update = match res {
+                        Ok(changed) if changed.is_empty() => None,
+                        Ok(changed) => Some(Action::AppTabOptionsChanged(changed)),
+                        Err(e) => match e.current_context() {
+                            ProjectError::ChangedOnDisk(_) => {
+                                self.action_tx
+                                    .send(Action::Error(e.current_context().to_string()))
+                                    .change_context(CliError::UnableToSendViaUnboundedSender)?;
+                                self.action_tx
+                                    .send(Action::ConfigChangedOnDisk)
+                                    .change_context(CliError::UnableToSendViaUnboundedSender)?;
+                                None
+                            }
+                            ProjectError::DependencyConflict(_)
+                            | ProjectError::ConfigConflict(_)
+                            | ProjectError::InvalidCode(_, _)
+                            | ProjectError::InvalidValue(_, _) => {
+                                // Let the user know and reset the option view to the project state
+                                self.action_tx
+                                    .send(Action::Error(e.current_context().to_string()))
+                                    .change_context(CliError::UnableToSendViaUnboundedSender)?;
+                                Some(Action::AppTabOptionChangeAccepted)
+                            }
+                            _ => return Err(e.change_context(CliError::Unknown)),
+                        },
+                    };
+
+                    if let Some(update) = update {
                         self.dirty = true;
                         self.action_tx
-                            .send(Action::AppTabOptionChangeAccepted)
+                            .send(update)
                             .change_context(CliError::UnableToSendViaUnboundedSender)?;
                         self.action_tx
                             .send(Action::Render)
@@ -288,6 +363,13 @@ impl App {
                     self.project.borrow_mut().set_selected_app(app);
                     self.dirty = true;
                 }
+                Action::JumpToOption(ref id) => {
+                    self.home_page = ComponentIndex::AppsPage;
+                    self.project.borrow_mut().set_selected_app(id.app);
+                    self.dirty = true;
+                }
+                Action::ConfigChangedOnDisk => self.reload_project()?,
+                Action::Refresh => self.dirty = true,
                 _ => {}
             }
 
@@ -312,6 +394,42 @@ impl App {
         Ok(())
     }
 
+    /// Reloads the project if its files were edited outside of the TUI.
+    ///
+    /// Open modals might be editing an option, the reload waits until they are closed.
+    fn reload_project(&mut self) -> Result<(), CliError> {
+        if self.modal_open {
+            self.reload_pending = true;
+            return Ok(());
+        }
+
+        self.reload_pending = false;
+        if self.project.borrow().changed_on_disk().is_empty() {
+            return Ok(());
+        }
+
+        if let Err(e) = self.project.borrow_mut().reload() {
+            error!("{:?}", e);
+            self.action_tx
+                .send(Action::Error(format!(
+                    "The project files were changed on disk and can't be loaded: {}",
+                    e.current_context()
+                )))
+                .change_context(CliError::UnableToSendViaUnboundedSender)?;
+            return Ok(());
+        }
+
+        self.dirty = true;
+        self.action_tx
+            .send(Action::AppTabOptionChangeAccepted)
+            .change_context(CliError::UnableToSendViaUnboundedSender)?;
+        self.action_tx
+            .send(Action::Render)
+            .change_context(CliError::UnableToSendViaUnboundedSender)?;
+
+        Ok(())
+    }
+
     fn on_quit(&mut self) {
         if self.modal_open {
             return;
@@ -325,6 +443,7 @@ impl App {
             Action::NavAppsTab => self.home_page = ComponentIndex::AppsPage,
             Action::NavSettingsTab => self.home_page = ComponentIndex::SettingsPage,
             Action::NavActionsTab => self.home_page = ComponentIndex::ActionsPage,
+            Action::NavLogsTab => self.home_page = ComponentIndex::LogsPage,
             Action::NavHelpTab => self.home_page = ComponentIndex::HelpPage,
             _ => (),
         }
@@ -390,6 +509,11 @@ impl App {
                 .get_mut(&ComponentIndex::ActionsPage)
                 .unwrap()
                 .draw(frame, area, ctx)?;
+        } else if self.home_page == ComponentIndex::LogsPage {
+            self.components_map
+                .get_mut(&ComponentIndex::LogsPage)
+                .unwrap()
+                .draw(frame, area, ctx)?;
         } else if self.home_page == ComponentIndex::HelpPage {
             self.components_map
                 .get_mut(&ComponentIndex::HelpPage)
@@ -420,6 +544,34 @@ impl App {
             if let Err(e) = res {
                 error!("{}", e);
             }
+
+            // The quick actions popup is drawn on top of everything else
+            let res = self
+                .components_map
+                .get_mut(&ComponentIndex::QuickActions)
+                .unwrap()
+                .draw(frame, main_layout[1], &ctx);
+            if let Err(e) = res {
+                error!("{}", e);
+            }
+
+            let res = self
+                .components_map
+                .get_mut(&ComponentIndex::Search)
+                .unwrap()
+                .draw(frame, main_layout[1], &ctx);
+            if let Err(e) = res {
+                error!("{}", e);
+            }
+
+            let res = self
+                .components_map
+                .get_mut(&ComponentIndex::ErrorPopup)
+                .unwrap()
+                .draw(frame, main_layout[1], &ctx);
+            if let Err(e) = res {
+                error!("{}", e);
+            }
             frame.render_widget(theme::block::no_border(&ctx), main_layout[2]);
         })
         .attach_printable_lazy(|| "Unable to draw the frame")
@@ -440,6 +592,11 @@ impl App {
             Action::PopModal(_success) => {
                 self.modal_open = false;
                 self.exclusive_input_component_shown = false;
+                if self.reload_pending {
+                    self.action_tx
+                        .send(Action::ConfigChangedOnDisk)
+                        .change_context(CliError::UnableToSendViaUnboundedSender)?;
+                }
             }
             _ => Err(Report::new(CliError::Unknown)
                 .attach_printable(format!("Receives action wrong {}", action)))?,
@@ -1,9 +1,45 @@
 use std::path::PathBuf;
 
 use clap::Subcommand;
+use get::OutputFormat;
+use nixblitzlib::{defaults::config_sync::ATTEMPTS, journal::LogLevel, platform::SystemPlatform};
+use recover::RecoverStrategy;
+use ssh_keys::KeyHost;
+use vm::PortForward;
 
+pub mod apply;
+pub mod archival;
+pub mod backup;
+pub mod bundle;
+pub mod diff;
+pub mod disk_bench;
+pub mod doctor;
+pub mod factory_reset;
+pub mod generations;
+pub mod get;
 pub mod init;
+pub mod iso;
+pub mod logs;
+pub mod man;
+pub mod node;
+pub mod prompt;
+pub mod recover;
+pub mod reset;
+pub mod review;
+pub mod schema;
+pub mod search;
+pub mod secrets;
+pub mod set;
+pub mod setup;
+pub mod snapshot;
+pub mod ssh_keys;
+pub mod status;
+pub mod sync_config;
+pub mod system_backup;
+pub mod tls;
 pub mod tui;
+pub mod upgrade_templates;
+pub mod vm;
 
 #[derive(Debug, Subcommand)]
 pub enum Commands {
@@ -28,9 +64,497 @@ pub enum Commands {
         work_dir: PathBuf,
 
         /// Whether to force overwrite existing files
-        #[arg(short, long)]
+        #[arg(short, long, conflicts_with = "from_git")]
         force: bool,
+
+        /// Clones an existing project from the given git remote instead of
+        /// creating a default one. The work dir must be empty.
+        #[arg(long, value_name = "URL")]
+        from_git: Option<String>,
+
+        /// The branch to check out, defaults to the default branch of the remote
+        #[arg(short, long, value_name = "BRANCH", requires = "from_git")]
+        branch: Option<String>,
+
+        /// The platform of this machine, detected if omitted. Only stored
+        /// locally, other clones of the project keep their own.
+        #[arg(long, value_name = "SYSTEM", requires = "from_git")]
+        platform: Option<SystemPlatform>,
+    },
+    /// Walks through the first settings of a new node: the password, the
+    /// time zone, the lightning implementation, pruning and Tor. Offers to
+    /// apply them at the end.
+    Setup {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
     },
     /// Analyze the project for common problems
-    Doctor {},
+    Doctor {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// Also checks that the enabled lightning nodes answer.
+        /// Only works on the node itself.
+        #[arg(long)]
+        online: bool,
+    },
+    /// Shows the health of the services of the enabled apps, the sync
+    /// progress of Bitcoin Core, whether the lightning nodes answer, when a
+    /// custom TLS certificate expires and how long the last apply took.
+    /// Only works on the node itself.
+    Status {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// Prints the status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Builds the configuration and switches the node to it. Records how
+    /// long it took to spot rebuilds that get slower. Needs root.
+    Apply {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// Skips the detection of the host platform, e.g. x86_64-linux
+        #[arg(long, value_name = "SYSTEM")]
+        platform: Option<SystemPlatform>,
+
+        /// Only builds the configuration to catch errors, the running system
+        /// is left alone
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Prints the progress of the rebuild as newline delimited JSON
+        /// events instead of the log of nixos-rebuild
+        #[arg(long, conflicts_with = "dry_run")]
+        json_progress: bool,
+    },
+    /// Lists the generations of the system and the commits of the project
+    /// they were applied from
+    Generations {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Switches the node back to an older generation and checks out the
+    /// project files it was applied from. Needs root.
+    Rollback {
+        /// The generation to switch to, see `generations`. Defaults to the
+        /// one before the current.
+        generation: Option<u64>,
+
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Upgrades a pruned node to an archival node. Explains what the reindex
+    /// involves before pruning is switched off.
+    Archival {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// Reindexes in the maintenance window of the node, the schedule of
+        /// the chain verification, instead of right after the next apply
+        #[arg(long, conflicts_with = "at")]
+        maintenance_window: bool,
+
+        /// Reindexes at the given systemd calendar event, e.g.
+        /// "Sat *-*-* 02:00:00"
+        #[arg(long, value_name = "WHEN")]
+        at: Option<String>,
+
+        /// Shows the progress of the reindex. Only works on the node itself.
+        #[arg(long, conflicts_with_all = ["maintenance_window", "at"])]
+        progress: bool,
+    },
+    /// Serves the HTTPS routes with your own certificate instead of one from
+    /// Let's Encrypt. The files are stored with the secret backend.
+    ImportTls {
+        /// The PEM certificate, including the intermediate certificates
+        #[arg(long, value_name = "FILE")]
+        cert: PathBuf,
+
+        /// The unencrypted PEM private key
+        #[arg(long, value_name = "FILE")]
+        key: PathBuf,
+
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Authorizes the public SSH keys of a GitHub or GitLab user to log in
+    /// as the admin user. Shows their fingerprints and asks before adding them.
+    ImportSshKeys {
+        /// The user name on GitHub or GitLab
+        user: String,
+
+        /// Where to fetch the keys from
+        #[arg(long, value_enum, default_value_t = KeyHost::Github)]
+        from: KeyHost,
+
+        /// Adds the keys without asking
+        #[arg(short, long)]
+        yes: bool,
+
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Shows the logs of the services behind an app. Only works on the node
+    /// itself.
+    Logs {
+        /// The app, e.g. bitcoind or lnd
+        app: String,
+
+        /// Keeps printing new entries until interrupted
+        #[arg(short, long)]
+        follow: bool,
+
+        /// The number of past entries to show
+        #[arg(short = 'n', long, value_name = "NUMBER", default_value_t = 100)]
+        lines: usize,
+
+        /// Leaves out entries less severe than this: error, warning, notice,
+        /// info or debug
+        #[arg(short, long, value_name = "LEVEL", default_value = "debug")]
+        priority: LogLevel,
+    },
+    /// Repairs a project whose files are missing or can't be parsed
+    Recover {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// How to repair the broken files
+        #[arg(short, long, value_enum, default_value_t = RecoverStrategy::Defaults)]
+        strategy: RecoverStrategy,
+
+        /// The backup to restore from. Either a single backup or a directory
+        /// holding multiple backups, in which case the newest one is used.
+        #[arg(long, value_name = "PATH")]
+        from: Option<PathBuf>,
+    },
+    /// Shows the offline documentation
+    Man {
+        /// The topic to show, lists all topics if omitted
+        topic: Option<String>,
+
+        /// Searches all topics for the given text
+        #[arg(short, long, value_name = "TEXT")]
+        search: Option<String>,
+    },
+    /// Snapshots the service data and the project files if they are on ZFS or btrfs
+    Snapshot {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// Lists the existing snapshots instead of creating one
+        #[arg(short, long)]
+        list: bool,
+    },
+    /// Reverts the service data to a snapshot. The services are stopped
+    /// meanwhile, the name of the snapshot must be typed to confirm.
+    RollbackData {
+        /// The snapshot to revert to, see `snapshot --list`
+        snapshot: String,
+
+        /// Also rolls back the data of LND or Core Lightning. An old channel
+        /// state lets the peers claim the funds of all channels.
+        #[arg(long)]
+        allow_lightning: bool,
+
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Bundles the project repository and prunes old backups.
+    /// Run it periodically, e.g. from a systemd timer or cron job.
+    Backup {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// Lists the existing backups instead of creating one
+        #[arg(short, long)]
+        list: bool,
+    },
+    /// Restores the files of a backup into the working tree
+    RestoreBackup {
+        /// The backup to restore, see `backup --list`
+        backup: String,
+
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Packs the settings of all apps into a single file, e.g. to move the
+    /// config to another machine
+    Export {
+        /// The file to write the bundle to
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            default_value = "nixblitz-config.json"
+        )]
+        output: PathBuf,
+
+        /// Also includes the passwords the services need in plain text and
+        /// the encrypted secrets
+        #[arg(long)]
+        secrets: bool,
+
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Applies the settings of an exported bundle onto the project in the
+    /// work dir and renders its nix files again
+    Import {
+        /// The bundle written by `export`
+        file: PathBuf,
+
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Runs the encrypted restic or borg backup of the System Backup app now.
+    /// Only works on the node itself.
+    SystemBackup {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// Checks the integrity of the backup repository instead
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Merges changed templates of a new nixblitz release into the nix files,
+    /// keeping manual edits. Asks how to resolve conflicts and commits the result.
+    UpgradeTemplates {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// Only lists the files that would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Benchmarks the disk the node is installed to and warns if it is too
+    /// slow for the initial block download. Run it from the installer.
+    BenchDisk {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// A directory on the disk to benchmark, e.g. where it is mounted
+        #[arg(short, long, value_name = "PATH", default_value = "/mnt")]
+        target: PathBuf,
+    },
+    /// Copies the project to the installed system and verifies that all
+    /// files arrived. Failed copies are retried. Run it from the installer.
+    SyncConfig {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// Where the installed system is mounted
+        #[arg(short, long, value_name = "PATH", default_value = "/mnt")]
+        root: PathBuf,
+
+        /// How often to try before giving up
+        #[arg(short, long, value_name = "NUMBER", default_value_t = ATTEMPTS)]
+        attempts: u32,
+    },
+    /// Generates the secrets of the enabled apps via nix-bitcoin and lists
+    /// the ones that are still missing. Only works on the node as root.
+    SetupSecrets {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Prints the value of an option, or of all options of an app
+    Get {
+        /// The app, e.g. bitcoind or lnd
+        app: String,
+
+        /// The option, e.g. rpc_port
+        #[arg(required_unless_present = "all")]
+        option: Option<String>,
+
+        /// Prints all options of the app
+        #[arg(long, conflicts_with = "option")]
+        all: bool,
+
+        /// Explains the option instead: its type, default, allowed values,
+        /// the apps it needs and where it ends up in the nix config
+        #[arg(long, conflicts_with = "all")]
+        explain: bool,
+
+        /// The output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Plain)]
+        format: OutputFormat,
+
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Finds options of all apps by a fuzzy match of their names, titles and
+    /// current values. Prints their `app.option` identifiers.
+    Search {
+        /// What to look for, e.g. rpcport or 9735
+        query: String,
+
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Sets many options at once from a JSON or TOML file that maps the
+    /// apps to their options and values. Nothing is changed unless all
+    /// values are accepted.
+    Set {
+        /// The file with the changes, in the format `get --format json` or
+        /// `get --format toml` prints
+        #[arg(long, value_name = "FILE")]
+        from_file: PathBuf,
+
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Sets an option back to its default
+    Unset {
+        /// The app, e.g. bitcoind or lnd
+        app: String,
+
+        /// The option, e.g. rpc_port
+        option: String,
+
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Sets an option, or all options of an app, back to their defaults.
+    /// Passwords are kept.
+    Reset {
+        /// The app, e.g. bitcoind or lnd
+        app: String,
+
+        /// The option, e.g. rpc_port
+        #[arg(required_unless_present = "all")]
+        option: Option<String>,
+
+        /// Resets all options of the app
+        #[arg(long, conflicts_with = "option")]
+        all: bool,
+
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Lists every option that differs from its default for a final review
+    /// before the install. Fails if the install is aborted.
+    Review {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Prints the JSON Schema of the options of an app, or of all apps
+    Schema {
+        /// The app, e.g. bitcoind or lnd
+        app: Option<String>,
+    },
+    /// Lists the options changed since the last commit of the project,
+    /// i.e. what switching to the configuration would change
+    Diff {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+    /// Disables all apps and resets their options to the defaults, e.g.
+    /// before the hardware is sold. Asks for confirmation multiple times.
+    FactoryReset {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// Also stops the services and deletes the data directories of the
+        /// apps, including the lightning wallets. Only works on the node as root.
+        #[arg(long)]
+        wipe_data: bool,
+    },
+    /// Builds an installer image with the project preloaded
+    BuildIso {
+        /// The platform of the machine the image is for. x86_64-linux builds
+        /// an installer ISO, aarch64-linux the SD image of the Raspberry Pi.
+        #[arg(short, long, value_name = "SYSTEM", default_value = "x86_64-linux")]
+        platform: SystemPlatform,
+
+        /// The project to put on the image, a default project if omitted
+        #[arg(short, long, value_name = "PATH")]
+        embed_config: Option<PathBuf>,
+
+        /// Where the built image is linked to
+        #[arg(short, long, value_name = "PATH", default_value = "result-iso")]
+        out_link: PathBuf,
+    },
+    /// Previews the project in a local virtual machine
+    Vm {
+        #[command(subcommand)]
+        command: VmCommands,
+    },
+    /// Checks the LND node
+    Lnd {
+        #[command(subcommand)]
+        command: NodeCommands,
+    },
+    /// Checks the Core Lightning node
+    Cln {
+        #[command(subcommand)]
+        command: NodeCommands,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum NodeCommands {
+    /// Checks the RPC connection and credentials and prints the node info
+    Ping {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum VmCommands {
+    /// Builds the x86_64 VM configuration of the project and boots it in QEMU
+    Run {
+        /// The working directory to operate on
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        work_dir: PathBuf,
+
+        /// Memory of the VM in MiB
+        #[arg(short, long, value_name = "MIB", default_value_t = 4096)]
+        memory: u32,
+
+        /// Number of CPU cores of the VM
+        #[arg(short, long, value_name = "NUM", default_value_t = 2)]
+        cores: u32,
+
+        /// Forwards a host port into the VM, can be given multiple times.
+        /// Defaults to 2222:22 (SSH) and 8080:80 (web UI and API).
+        #[arg(short, long, value_name = "HOST:GUEST")]
+        forward: Vec<PortForward>,
+
+        /// Skips the detection of the host platform, e.g. x86_64-linux
+        #[arg(long, value_name = "SYSTEM")]
+        platform: Option<SystemPlatform>,
+    },
 }
@@ -0,0 +1,317 @@
+use std::{
+    io::{self, BufRead, BufReader},
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+    time::{Duration, Instant},
+};
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    activation::ensure_idle,
+    apply_history::{self, closure_size, ApplyRecord, ApplyTrend},
+    apps::SupportedApps,
+    defaults::nix_bitcoin::SECRETS_DIR,
+    dependencies::enable_option_id,
+    errors::describe,
+    generations::{current_generation, project_commit},
+    nix_base_config::{self, NixBaseConfig},
+    platform::{local_platform, resolve_platform, SystemPlatform},
+    project::Project,
+    rebuild_progress::{ProgressEvent, ProgressParser},
+    registry::registration,
+    secrets::missing_secrets,
+    utils::load_json_file,
+    validation::validate,
+};
+
+use crate::errors::CliError;
+
+use super::snapshot::create_snapshots;
+
+/// The system the node currently runs
+const CURRENT_SYSTEM: &str = "/run/current-system";
+
+/// Builds the configuration of the project and switches the node to it
+///
+/// The host platform is detected unless `platform` is given or stored for
+/// the work dir. The duration and the closure size of the new system are
+/// recorded, and a warning is printed if the apply took much longer than
+/// the ones before. Nothing is switched if the project has conflicting
+/// settings or secrets are missing, see [check_project]. The service data and
+/// the project are snapshotted first if they are on ZFS or btrfs. With `dry_run` the configuration is only built, see
+/// [Project::check]. With `json_progress` only [ProgressEvent]s are printed
+/// to stdout, one JSON object per line.
+pub fn apply_cmd(
+    work_dir: &Path,
+    platform: Option<SystemPlatform>,
+    dry_run: bool,
+    json_progress: bool,
+) -> Result<(), CliError> {
+    let platform = resolve_platform(platform.or_else(|| local_platform(work_dir)))
+        .change_context(CliError::ApplyError)?;
+    if dry_run {
+        return dry_run_cmd(work_dir, &platform);
+    }
+
+    let nix_base = NixBaseConfig::from_json(
+        &load_json_file(&work_dir.join(nix_base_config::JSON_FILE_NAME))
+            .change_context(CliError::ApplyError)?,
+    )
+    .change_context(CliError::ApplyError)?;
+    ensure_idle().change_context(CliError::ApplyError)?;
+
+    let project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    let problems = check_project(&project);
+    if !problems.is_empty() {
+        if json_progress {
+            for message in problems.iter().cloned() {
+                emit(&ProgressEvent::Warning { message });
+            }
+        } else {
+            println!("Found {} problem(s), nothing was applied:", problems.len());
+            for problem in problems.iter() {
+                println!("  - {}", problem);
+            }
+        }
+        return Err(Report::new(CliError::ConfigConflicts(problems.len()))
+            .attach_printable("Run `nixblitz doctor --online` after fixing them"));
+    }
+
+    let flake = format!(
+        "{}#{}",
+        work_dir.join("src").display(),
+        nix_base.configuration(&platform)
+    );
+    let snapshots = create_snapshots(work_dir, &nix_base)
+        .attach_printable("Set the number of snapshots to keep to 0 to apply without them")?;
+    if !json_progress {
+        for line in snapshots {
+            println!("{}", line);
+        }
+        println!("Switching to {}", flake);
+    }
+
+    let started = Instant::now();
+    let mut rebuild = Command::new("nixos-rebuild");
+    rebuild.args(["switch", "--flake", &flake]);
+    let status = if json_progress {
+        rebuild_with_progress(rebuild)
+    } else {
+        rebuild.status()
+    }
+    .change_context(CliError::ApplyError)
+    .attach_printable("Unable to run nixos-rebuild, is it installed?")?;
+    let duration = started.elapsed();
+
+    let (size, generation) = if status.success() {
+        (
+            closure_size(Path::new(CURRENT_SYSTEM)),
+            current_generation(),
+        )
+    } else {
+        (None, None)
+    };
+    let record = ApplyRecord {
+        generation,
+        commit: project_commit(work_dir),
+        ..ApplyRecord::finished_now(duration.as_secs(), size, status.success())
+    };
+    let mut warnings = vec![];
+    if let Err(e) = apply_history::record(work_dir, &record) {
+        warnings.push(format!("the apply was not recorded: {}", e));
+    }
+    if status.success() {
+        if let Some(trend) = apply_history::trend(&apply_history::load(work_dir)) {
+            warnings.extend(slow_warning(&trend));
+        }
+    }
+
+    if json_progress {
+        for message in warnings {
+            emit(&ProgressEvent::Warning { message });
+        }
+        emit(&ProgressEvent::Finished {
+            success: status.success(),
+            duration_secs: duration.as_secs(),
+        });
+    } else {
+        if status.success() {
+            println!("Applied in {}", format_duration(duration));
+        }
+        for warning in &warnings {
+            println!("Warning: {}", warning);
+        }
+    }
+
+    if !status.success() {
+        return Err(Report::new(CliError::ApplyError)
+            .attach_printable(format!("nixos-rebuild exited with {}", status)));
+    }
+
+    Ok(())
+}
+
+/// Checks the project before it is applied, like `nixblitz doctor --online`
+/// does
+///
+/// # Returns
+/// The conflicting settings and the secrets missing on the node. The secrets
+/// of apps enabled since the last apply are only created during the switch,
+/// they aren't checked. Neither are any secrets before the first apply.
+fn check_project(project: &Project) -> Vec<String> {
+    let mut problems: Vec<String> = validate(project).iter().map(|c| c.to_string()).collect();
+
+    let secrets_dir = Path::new(SECRETS_DIR);
+    if !secrets_dir.exists() {
+        return problems;
+    }
+
+    let newly_enabled: Vec<SupportedApps> = match project.pending_changes() {
+        Ok(pending) => pending
+            .iter()
+            .flat_map(|a| a.changes.iter())
+            .filter(|c| enable_option_id(c.id.app).as_ref() == Some(&c.id))
+            .map(|c| c.id.app)
+            .collect(),
+        // nothing is known to be applied, e.g. the project isn't committed
+        Err(_) => return problems,
+    };
+    let applied: Vec<SupportedApps> = project
+        .enabled_apps()
+        .into_iter()
+        .filter(|a| !newly_enabled.contains(a))
+        .collect();
+    match missing_secrets(&applied, secrets_dir) {
+        Ok(missing) => problems.extend(
+            missing
+                .iter()
+                .map(|m| format!("{}, run `nixblitz setup-secrets`", m)),
+        ),
+        Err(e) => problems.push(describe(&e)),
+    }
+
+    problems
+}
+
+/// Runs the rebuild and prints the progress parsed from its log as JSON
+/// lines. nix logs to stderr, the stdout of nixos-rebuild is dropped to
+/// keep the output parseable.
+fn rebuild_with_progress(mut rebuild: Command) -> io::Result<ExitStatus> {
+    let mut child = rebuild
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut parser = ProgressParser::new();
+    if let Some(stderr) = child.stderr.take() {
+        for line in BufReader::new(stderr).lines() {
+            for event in parser.parse_line(&line?) {
+                emit(&event);
+            }
+        }
+    }
+
+    child.wait()
+}
+
+/// Prints an event as a single line of JSON
+fn emit(event: &ProgressEvent) {
+    if let Ok(json) = serde_json::to_string(event) {
+        println!("{}", json);
+    }
+}
+
+/// Builds the configuration without switching to it and explains why nix
+/// can't build it
+fn dry_run_cmd(work_dir: &Path, platform: &SystemPlatform) -> Result<(), CliError> {
+    let project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    println!("Building the configuration for {}", platform);
+
+    let Some(issue) = project
+        .check(platform)
+        .change_context(CliError::ApplyError)?
+    else {
+        println!("The configuration builds, nothing was changed");
+        return Ok(());
+    };
+
+    eprintln!("{}", issue.output.trim_end());
+    println!();
+    println!("The configuration can't be built: {}", issue.message);
+    match (&issue.file, issue.line) {
+        (Some(file), Some(line)) => println!("  in {}, line {}", file, line),
+        (Some(file), None) => println!("  in {}", file),
+        _ => {}
+    }
+    if let Some(app) = issue.app {
+        let name = registration(app).name();
+        match &issue.option {
+            Some(option) => println!(
+                "  caused by the option {} of {}, see `nixblitz get {} {} --explain`",
+                option, name, name, option
+            ),
+            None => println!("  caused by the config of {}", name),
+        }
+    }
+
+    Err(Report::new(CliError::ApplyError).attach_printable("The configuration can't be built"))
+}
+
+/// Formats a duration as minutes and seconds, e.g. "4m 05s"
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        return format!("{}s", secs);
+    }
+
+    format!("{}m {:02}s", secs / 60, secs % 60)
+}
+
+/// Explains a slow apply, None if it took the usual time
+pub fn slow_warning(trend: &ApplyTrend) -> Option<String> {
+    if !trend.is_slow() {
+        return None;
+    }
+
+    Some(format!(
+        "the last apply took {}, {:.1} times as long as usual ({}). \
+         Check the disk with `nixblitz bench-disk` and whether the binary cache is reachable.",
+        format_duration(Duration::from_secs(trend.last.duration_secs)),
+        trend.ratio(),
+        format_duration(Duration::from_secs(trend.usual_secs)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(245)), "4m 05s");
+    }
+
+    #[test]
+    fn test_slow_warning() {
+        let record = |duration_secs| ApplyRecord {
+            finished: 0,
+            duration_secs,
+            closure_size: None,
+            success: true,
+            generation: None,
+            commit: None,
+        };
+        let records: Vec<ApplyRecord> = [300, 280, 320, 900].into_iter().map(record).collect();
+        let trend = apply_history::trend(&records).unwrap();
+        assert!(slow_warning(&trend)
+            .unwrap()
+            .starts_with("the last apply took 15m 00s, 3.0 times as long as usual (5m 00s)"));
+
+        let trend = apply_history::trend(&records[..3]).unwrap();
+        assert_eq!(slow_warning(&trend), None);
+    }
+}
@@ -0,0 +1,89 @@
+use std::{io, path::Path};
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    archival::{plan, progress, ReindexProgress},
+    bitcoind::REINDEX_NOW,
+    project::Project,
+};
+
+use crate::errors::CliError;
+
+use super::prompt::confirm;
+
+/// Walks the user through the upgrade from a pruned to an archival node
+///
+/// Explains the consequences and asks for confirmation before pruning is
+/// switched off. The reindex runs right after the next apply, in the
+/// maintenance window with `maintenance_window` or at the calendar event
+/// `at`. With `show_progress` only the progress of a running reindex is
+/// shown.
+pub fn archival_cmd(
+    work_dir: &Path,
+    maintenance_window: bool,
+    at: Option<&str>,
+    show_progress: bool,
+) -> Result<(), CliError> {
+    let mut project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    if show_progress {
+        return progress_cmd(&project);
+    }
+
+    let plan = plan(&project).change_context(CliError::ArchivalError)?;
+    let schedule = match (at, maintenance_window) {
+        (Some(at), _) => at.trim().to_string(),
+        (None, true) => plan.maintenance_window.clone().ok_or_else(|| {
+            Report::new(CliError::ArchivalError).attach_printable(
+                "The node has no maintenance window, set the chain verification schedule \
+                 of Bitcoin Core or pass --at",
+            )
+        })?,
+        (None, false) => REINDEX_NOW.to_string(),
+    };
+
+    println!("Upgrading Bitcoin Core to an archival node:");
+    for line in plan.consequences() {
+        println!("  - {}", line);
+    }
+    if schedule == REINDEX_NOW {
+        println!("The reindex starts a minute after the next apply.");
+    } else {
+        println!("The reindex starts at {}.", schedule);
+    }
+
+    if !confirm(
+        &mut io::stdin().lock(),
+        "Switch pruning off?",
+        CliError::ArchivalError,
+    )? {
+        println!("Aborted, nothing was changed.");
+        return Ok(());
+    }
+
+    project
+        .upgrade_to_archival(&schedule)
+        .change_context(CliError::ArchivalError)?;
+    println!("Pruning is off. Apply the configuration, then follow the reindex with");
+    println!("`nixblitz archival --progress`.");
+
+    Ok(())
+}
+
+/// Shows where the reindex stands
+fn progress_cmd(project: &Project) -> Result<(), CliError> {
+    match progress(project).change_context(CliError::ArchivalError)? {
+        ReindexProgress::Scheduled(schedule) if schedule == REINDEX_NOW => {
+            println!("The reindex starts a minute after the next apply")
+        }
+        ReindexProgress::Scheduled(schedule) => {
+            println!("The reindex is scheduled for {}", schedule)
+        }
+        ReindexProgress::Running(sync) => println!("Reindexing, {}", sync),
+        ReindexProgress::Done(sync) => {
+            println!("Bitcoin Core has the whole chain, {}", sync)
+        }
+    }
+
+    Ok(())
+}
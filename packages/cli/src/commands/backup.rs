@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use error_stack::{Result, ResultExt};
+use nixblitzlib::{
+    backups::{create_backup, list_backups, prune_backups, restore_backup},
+    nix_base_config::{self, NixBaseConfig},
+    number_value::NumberValue,
+    utils::load_json_file,
+};
+
+use crate::errors::CliError;
+
+/// Bundles the project repository and prunes old backups
+pub fn backup_cmd(work_dir: &Path, list: bool) -> Result<(), CliError> {
+    if list {
+        for backup in list_backups(work_dir).change_context(CliError::BackupError)? {
+            println!("{}", backup.name);
+        }
+        return Ok(());
+    }
+
+    let json = load_json_file(&work_dir.join(nix_base_config::JSON_FILE_NAME))
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    let config =
+        NixBaseConfig::from_json(&json).change_context(CliError::UnableToInitProjectStruct)?;
+
+    let backup = create_backup(work_dir).change_context(CliError::BackupError)?;
+    println!("Created backup {}", backup.name);
+
+    let deleted = prune_backups(
+        work_dir,
+        keep(config.backup_keep_daily.value()),
+        keep(config.backup_keep_weekly.value()),
+    )
+    .change_context(CliError::BackupError)?;
+    for backup in deleted {
+        println!("Deleted backup {}", backup.name);
+    }
+
+    Ok(())
+}
+
+/// Restores the files of the given backup into the working tree
+pub fn restore_backup_cmd(work_dir: &Path, backup: &str) -> Result<(), CliError> {
+    restore_backup(work_dir, backup).change_context(CliError::BackupError)?;
+    println!(
+        "Restored {} into {}, review and commit the changes",
+        backup,
+        work_dir.display()
+    );
+
+    Ok(())
+}
+
+fn keep(value: &NumberValue) -> usize {
+    match value {
+        NumberValue::UInt(Some(keep)) => *keep,
+        _ => 0,
+    }
+}
@@ -0,0 +1,54 @@
+use std::{fs, path::Path};
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    bundle::{self, ConfigBundle},
+    project::Project,
+};
+
+use crate::errors::CliError;
+
+/// Writes the settings of all apps into a config bundle
+pub fn export_cmd(work_dir: &Path, output: &Path, include_secrets: bool) -> Result<(), CliError> {
+    if output.exists() {
+        return Err(Report::new(CliError::ArgumentError)
+            .attach_printable(format!("{} already exists", output.display())));
+    }
+
+    let project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    let data = project
+        .export_bundle(include_secrets)
+        .and_then(|b| b.to_bytes())
+        .change_context(CliError::BundleError)?;
+    fs::write(output, data)
+        .change_context(CliError::BundleError)
+        .attach_printable_lazy(|| format!("Unable to write {}", output.display()))?;
+
+    println!("Exported the config to {}", output.display());
+    if include_secrets {
+        println!("It contains passwords, keep it somewhere safe");
+    } else {
+        println!("Plain text passwords were left out, set them again after the import");
+    }
+
+    Ok(())
+}
+
+/// Applies a config bundle onto the project in the work dir
+pub fn import_cmd(work_dir: &Path, file: &Path) -> Result<(), CliError> {
+    let data = fs::read(file)
+        .change_context(CliError::BundleError)
+        .attach_printable_lazy(|| format!("Unable to read {}", file.display()))?;
+    let bundle = ConfigBundle::from_bytes(&data).change_context(CliError::BundleError)?;
+    bundle::import(work_dir, &bundle).change_context(CliError::BundleError)?;
+
+    println!(
+        "Imported the settings of {} apps into {}",
+        bundle.apps.len(),
+        work_dir.display()
+    );
+    println!("Review them with nixblitz diff before switching");
+
+    Ok(())
+}
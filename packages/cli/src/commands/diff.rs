@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use error_stack::{Result, ResultExt};
+use nixblitzlib::{
+    pending_changes::OptionChange, project::Project, registry, strings::OPTION_TITLES,
+};
+
+use crate::errors::CliError;
+
+/// Lists the options that changed since the last commit of the project,
+/// grouped by app
+pub fn diff_cmd(work_dir: &Path) -> Result<(), CliError> {
+    let project = Project::load(work_dir.to_path_buf()).change_context(CliError::DiffError)?;
+    let apps = project
+        .pending_changes()
+        .change_context(CliError::DiffError)?;
+
+    if apps.is_empty() {
+        println!("No pending changes, the project matches its last commit.");
+        return Ok(());
+    }
+
+    for app in apps.iter() {
+        println!("{}", registry::registration(app.app).title);
+        for change in app.changes.iter() {
+            print_change(change);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_change(change: &OptionChange) {
+    let title = OPTION_TITLES
+        .get(&change.id)
+        .map(|t| t.to_string())
+        .unwrap_or_else(|| change.id.option.clone());
+    let old = change.old.as_deref();
+
+    if old.is_some_and(|o| o.contains('\n')) || change.new.contains('\n') {
+        println!("  {}:", title);
+        for line in old.unwrap_or_default().lines() {
+            println!("    - {}", line);
+        }
+        for line in change.new.lines() {
+            println!("    + {}", line);
+        }
+        return;
+    }
+
+    match old {
+        Some(old) => println!("  {}: {} → {}", title, old, change.new),
+        None => println!("  {}: {} (new option)", title, change.new),
+    }
+}
@@ -0,0 +1,32 @@
+use std::path::Path;
+
+use error_stack::{Result, ResultExt};
+use nixblitzlib::disk_bench;
+
+use crate::errors::CliError;
+
+/// Benchmarks the disk the target directory is on and stores the result in
+/// the project. Warns if the disk is too slow for the initial block download.
+pub fn bench_disk_cmd(work_dir: &Path, target: &Path) -> Result<(), CliError> {
+    println!(
+        "Benchmarking the disk at {}, this takes about half a minute...",
+        target.display()
+    );
+    let bench = disk_bench::run(target).change_context(CliError::DiskBenchmarkError)?;
+    println!(
+        "Random 4k reads: {} IOPS, writes: {} IOPS",
+        bench.read_iops, bench.write_iops
+    );
+
+    let warnings = bench.warnings();
+    if warnings.is_empty() {
+        println!("The disk is fast enough for the initial block download.");
+    }
+    for warning in warnings.iter() {
+        println!("Warning: {}", warning);
+    }
+
+    bench
+        .save(work_dir)
+        .change_context(CliError::DiskBenchmarkError)
+}
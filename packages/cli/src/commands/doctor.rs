@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    apps::SupportedApps, defaults::nix_bitcoin::SECRETS_DIR, disk_bench::DiskBenchmark,
+    errors::describe, maintenance::failed_tasks, nix_format::unformatted_files, node_check::ping,
+    project::Project, secrets::missing_secrets, validation::validate,
+};
+
+use crate::errors::CliError;
+
+/// Checks the project in the given work dir for conflicting settings
+///
+/// Every conflict found is printed and the command fails if there is at least one.
+/// With `online` the enabled lightning nodes are pinged, the secrets of
+/// the enabled apps are looked for and the last runs of the scheduled
+/// integrity checks are inspected as well. A slow disk
+/// found by `bench-disk` and files that couldn't be formatted are reported
+/// as warnings only.
+pub fn doctor_cmd(work_dir: &Path, online: bool) -> Result<(), CliError> {
+    let project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+
+    let mut problems: Vec<String> = validate(&project).iter().map(|c| c.to_string()).collect();
+
+    if online {
+        match missing_secrets(&project.enabled_apps(), Path::new(SECRETS_DIR)) {
+            Ok(missing) => problems.extend(
+                missing
+                    .iter()
+                    .map(|m| format!("{}, run `nixblitz setup-secrets`", m)),
+            ),
+            Err(e) => problems.push(describe(&e)),
+        }
+
+        for app in [SupportedApps::LND, SupportedApps::CoreLightning] {
+            if !project.is_app_enabled(app) {
+                continue;
+            }
+
+            match ping(&project, app) {
+                Ok(info) => println!("{} is reachable at block {}", app, info.block_height),
+                Err(e) => problems.push(describe(&e)),
+            }
+        }
+
+        match failed_tasks(&project) {
+            Ok(failed) => problems.extend(failed.iter().map(|f| f.to_string())),
+            Err(e) => problems.push(describe(&e)),
+        }
+    }
+
+    match DiskBenchmark::load(work_dir) {
+        Ok(Some(bench)) => {
+            for warning in bench.warnings() {
+                println!("Warning: {}", warning);
+            }
+        }
+        Ok(None) => (),
+        Err(e) => problems.push(describe(&e)),
+    }
+
+    for file in unformatted_files(work_dir) {
+        println!(
+            "Warning: {} can't be formatted, it may be broken: {}",
+            file.file, file.error
+        );
+    }
+
+    if problems.is_empty() {
+        println!("No problems found.");
+        return Ok(());
+    }
+
+    println!("Found {} problem(s):", problems.len());
+    for problem in problems.iter() {
+        println!("  - {}", problem);
+    }
+
+    Err(Report::new(CliError::ConfigConflicts(problems.len())))
+}
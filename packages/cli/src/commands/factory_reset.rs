@@ -0,0 +1,71 @@
+use std::{io, path::Path};
+
+use error_stack::{Result, ResultExt};
+use nixblitzlib::factory_reset::{plan, reset};
+
+use crate::errors::CliError;
+
+use super::prompt::{confirm, confirm_typed};
+
+/// What has to be typed to confirm the reset
+const CONFIRM_RESET: &str = "RESET";
+
+/// What has to be typed to confirm that the data is wiped
+const CONFIRM_WIPE: &str = "WIPE";
+
+/// Resets the project to the defaults after asking for confirmation
+/// multiple times
+pub fn factory_reset_cmd(work_dir: &Path, wipe_data: bool) -> Result<(), CliError> {
+    let plan = plan(work_dir).change_context(CliError::FactoryResetError)?;
+
+    println!("This resets the project in {}:", work_dir.display());
+    if plan.enabled_apps.is_empty() {
+        println!("  - no app is enabled");
+    }
+    for app in plan.enabled_apps.iter() {
+        println!("  - disables {}", app);
+    }
+    println!("  - resets the options of all apps to their defaults");
+    println!("  - removes the extra nix config and the stored secrets of all apps");
+    println!("The Nix OS settings are kept, the login keeps working.");
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    if !confirm(&mut input, "Continue?", CliError::FactoryResetError)? {
+        println!("Aborted, nothing was changed.");
+        return Ok(());
+    }
+
+    if wipe_data {
+        println!("\nThe services are stopped and these directories are deleted:");
+        for dir in plan.data_dirs.iter() {
+            println!("  - {}", dir.display());
+        }
+        println!("This includes the wallets and channels of the lightning nodes.");
+        println!("Make sure their funds are moved and the seeds are backed up.");
+        if !confirm_typed(
+            &mut input,
+            "Delete the data?",
+            CONFIRM_WIPE,
+            CliError::FactoryResetError,
+        )? {
+            println!("Aborted, nothing was changed.");
+            return Ok(());
+        }
+    }
+
+    if !confirm_typed(
+        &mut input,
+        "Reset the project?",
+        CONFIRM_RESET,
+        CliError::FactoryResetError,
+    )? {
+        println!("Aborted, nothing was changed.");
+        return Ok(());
+    }
+
+    reset(work_dir, &plan, wipe_data).change_context(CliError::FactoryResetError)?;
+    println!("The project was reset. Switch to the configuration to apply it.");
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::path::Path;
+
+use error_stack::{Result, ResultExt};
+use nixblitzlib::generations::{list_generations, rollback};
+
+use crate::errors::CliError;
+
+/// The length commits are abbreviated to
+const SHORT_COMMIT: usize = 8;
+
+/// Lists the generations of the system with the commit of the project they
+/// were applied from
+pub fn generations_cmd(work_dir: &Path) -> Result<(), CliError> {
+    let generations = list_generations(work_dir).change_context(CliError::RollbackError)?;
+    if generations.is_empty() {
+        println!("No generations found.");
+        return Ok(());
+    }
+
+    let created_width = generations
+        .iter()
+        .map(|g| g.created.len())
+        .fold("Created".len(), usize::max);
+    println!("{:>10}  {:created_width$}  Commit", "Generation", "Created");
+    for g in generations.iter() {
+        let commit = g
+            .commit
+            .as_deref()
+            .map(|c| &c[..c.len().min(SHORT_COMMIT)])
+            .unwrap_or("-");
+        println!(
+            "{:>10}  {:created_width$}  {}{}",
+            g.number,
+            g.created,
+            commit,
+            if g.current { "  (current)" } else { "" }
+        );
+    }
+
+    Ok(())
+}
+
+/// Switches the node back to the given generation, or the one before the
+/// current, and checks out the project files it was applied from
+pub fn rollback_cmd(work_dir: &Path, generation: Option<u64>) -> Result<(), CliError> {
+    let target = rollback(work_dir, generation).change_context(CliError::RollbackError)?;
+
+    println!("The node runs generation {} now", target.number);
+    match target.commit {
+        Some(commit) => println!(
+            "The project files of commit {} were checked out, review them with `git diff` \
+             and commit them",
+            &commit[..commit.len().min(SHORT_COMMIT)]
+        ),
+        None => println!(
+            "The generation wasn't applied with nixblitz, the project files were left alone"
+        ),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    app_option_data::option_data::GetOptionId,
+    explain::{explain, Explanation},
+    option_values::{display_value, dump, json_value, DumpFormat},
+    project::Project,
+    registry::{self, registration_by_name},
+};
+
+use crate::errors::CliError;
+
+/// The formats the values can be printed in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// The value as is, or one `name=value` line per option
+    #[default]
+    Plain,
+    Json,
+    Toml,
+}
+
+impl From<OutputFormat> for DumpFormat {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Plain => DumpFormat::Plain,
+            OutputFormat::Json => DumpFormat::Json,
+            OutputFormat::Toml => DumpFormat::Toml,
+        }
+    }
+}
+
+/// Prints the value of a single option, or of all options of the app if
+/// `option` is `None`. With `explain` everything known about the option is
+/// printed instead, see [explain].
+pub fn get_cmd(
+    work_dir: &Path,
+    app: &str,
+    option: Option<&str>,
+    explain_option: bool,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    let Some(reg) = registration_by_name(app) else {
+        let names: Vec<&str> = registry::APPS.iter().map(|r| r.name()).collect();
+        return Err(
+            Report::new(CliError::ArgumentError).attach_printable(format!(
+                "Unknown app {}, use one of {}",
+                app,
+                names.join(", ")
+            )),
+        );
+    };
+
+    let mut project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    project.set_selected_app(reg.app);
+    let options = project
+        .get_app_options()
+        .change_context(CliError::GetError)?;
+
+    let Some(name) = option else {
+        println!(
+            "{}",
+            dump(&options, format.into()).change_context(CliError::GetError)?
+        );
+        return Ok(());
+    };
+
+    let Some(option) = options.iter().find(|o| o.id().option == name) else {
+        let names: Vec<&str> = options.iter().map(|o| o.id().option.as_str()).collect();
+        return Err(
+            Report::new(CliError::ArgumentError).attach_printable(format!(
+                "{} has no option {}, use one of {}",
+                reg.name(),
+                name,
+                names.join(", ")
+            )),
+        );
+    };
+
+    if explain_option {
+        let Some(explanation) = explain(reg, &options, name) else {
+            return Err(Report::new(CliError::GetError));
+        };
+        match format {
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&explanation).change_context(CliError::GetError)?
+            ),
+            _ => print_explanation(&explanation),
+        }
+        return Ok(());
+    }
+
+    match format {
+        OutputFormat::Plain => println!("{}", display_value(option)),
+        OutputFormat::Json => println!("{}", json_value(option)),
+        OutputFormat::Toml => println!(
+            "{}",
+            dump(std::slice::from_ref(option), DumpFormat::Toml)
+                .change_context(CliError::GetError)?
+        ),
+    }
+
+    Ok(())
+}
+
+/// Prints one `key: value` line per field, TOML isn't a good fit for the
+/// nested data so it is printed like plain
+fn print_explanation(e: &Explanation) {
+    println!("option:   {}.{}", e.app, e.option);
+    if let Some(title) = e.title {
+        println!("title:    {}", title);
+    }
+    println!("type:     {}", e.kind);
+    println!("value:    {}", e.value);
+    println!("default:  {}", e.default);
+    println!("schema:   {}", e.schema);
+    if !e.requires.is_empty() {
+        println!("requires: {}", e.requires.join(", "));
+    }
+    println!("template: {}", e.template);
+    for u in e.uses.iter() {
+        match &u.nix_path {
+            Some(path) => println!("  line {}: {} ({})", u.line, u.text, path),
+            None => println!("  line {}: {}", u.line, u.text),
+        }
+    }
+}
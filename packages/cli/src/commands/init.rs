@@ -1,7 +1,7 @@
 use std::path::Path;
 
 use error_stack::{Result, ResultExt};
-use nixblitzlib::utils::init_default_project;
+use nixblitzlib::{clone::clone_project, platform::SystemPlatform, utils::init_default_project};
 
 use crate::errors::CliError;
 
@@ -10,3 +10,38 @@ pub fn init_default_project_cmd(work_dir: &Path, force: bool) -> Result<(), CliE
         .change_context(CliError::UnableToInitProjectStruct)?;
     Ok(())
 }
+
+/// Clones an existing project and reports what has to be done before it is
+/// switched to on this machine
+pub fn init_from_git_cmd(
+    work_dir: &Path,
+    url: &str,
+    branch: Option<&str>,
+    platform: Option<SystemPlatform>,
+) -> Result<(), CliError> {
+    let res = clone_project(work_dir, url, branch, platform)
+        .change_context(CliError::UnableToInitProjectStruct)?;
+
+    println!("Cloned {} into {}", url, work_dir.display());
+    println!(
+        "This machine uses the {} configuration ({})",
+        res.configuration, res.platform
+    );
+
+    if !res.conflicts.is_empty() {
+        println!("\nThe configuration has conflicts:");
+        for conflict in res.conflicts.iter() {
+            println!("  - {}", conflict);
+        }
+    }
+
+    if !res.pending_upgrades.is_empty() {
+        println!("\nThe templates of this version change these files:");
+        for path in res.pending_upgrades.iter() {
+            println!("  - {}", path.display());
+        }
+        println!("Run `nixblitz upgrade-templates` before switching to the configuration.");
+    }
+
+    Ok(())
+}
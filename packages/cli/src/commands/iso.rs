@@ -0,0 +1,124 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{platform::SystemPlatform, utils::init_default_project};
+
+use crate::errors::CliError;
+
+/// Where the default project is created if no config is embedded, inside the temp dir
+const DEFAULT_PROJECT_DIR: &str = "nixblitz-iso";
+
+/// The flake output building the image for the given target platform
+///
+/// x86_64 machines boot the installer ISO, the Raspberry Pi boots the SD
+/// image of the node configuration directly.
+fn image_flake_output(platform: &SystemPlatform) -> Result<&'static str, CliError> {
+    match platform {
+        SystemPlatform::X86_64 => Ok("images.iso"),
+        SystemPlatform::Aarch64 => Ok("images.pi"),
+        SystemPlatform::Unsupported(system) => Err(Report::new(CliError::IsoError)
+            .attach_printable(format!("No image available for {}", system))),
+    }
+}
+
+/// Builds an installer image for the given platform.
+///
+/// The image contains the project in `embed_config`, it is found in
+/// `/etc/nixblitz` on the installer. Without a project a default one is used.
+pub fn build_iso_cmd(
+    platform: &SystemPlatform,
+    embed_config: Option<&Path>,
+    out_link: &Path,
+) -> Result<(), CliError> {
+    let output = image_flake_output(platform)?;
+    let work_dir = match embed_config {
+        Some(dir) => dir.to_path_buf(),
+        None => {
+            let dir = env::temp_dir().join(DEFAULT_PROJECT_DIR);
+            init_default_project(&dir, Some(true))
+                .change_context(CliError::UnableToInitProjectStruct)?;
+            dir
+        }
+    };
+
+    let flake_dir = work_dir.join("src");
+    println!(
+        "Building the {} image from {}",
+        platform,
+        flake_dir.display()
+    );
+
+    let status = Command::new("nix")
+        .arg("build")
+        .arg(format!("{}#{}", flake_dir.display(), output))
+        .arg("--out-link")
+        .arg(out_link)
+        .status()
+        .change_context(CliError::IsoError)
+        .attach_printable("Unable to run nix, is it installed?")?;
+    if !status.success() {
+        return Err(Report::new(CliError::IsoError).attach_printable("nix build failed"));
+    }
+
+    println!("Image written to {}", find_image(out_link)?.display());
+
+    Ok(())
+}
+
+/// Finds the image file in the `iso` or `sd-image` dir of the build result
+fn find_image(out_link: &Path) -> Result<PathBuf, CliError> {
+    ["iso", "sd-image"]
+        .iter()
+        .map(|d| out_link.join(d))
+        .filter_map(|d| fs::read_dir(d).ok())
+        .flat_map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()))
+        .find(|p| is_image(p))
+        .ok_or_else(|| {
+            Report::new(CliError::IsoError)
+                .attach_printable(format!("No image found in {}", out_link.display()))
+        })
+}
+
+fn is_image(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.ends_with(".iso") || n.contains(".img"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_find_image() {
+        let temp_dir = tempdir().unwrap();
+        let out_link = temp_dir.path();
+        assert!(find_image(out_link).is_err());
+
+        let iso_dir = out_link.join("iso");
+        fs::create_dir_all(&iso_dir).unwrap();
+        fs::write(iso_dir.join("nix-support"), "").unwrap();
+        fs::write(iso_dir.join("nixos-minimal-x86_64-linux.iso"), "").unwrap();
+
+        assert_eq!(
+            find_image(out_link).unwrap(),
+            iso_dir.join("nixos-minimal-x86_64-linux.iso")
+        );
+    }
+
+    #[test]
+    fn test_image_flake_output() {
+        assert_eq!(
+            image_flake_output(&SystemPlatform::X86_64).unwrap(),
+            "images.iso"
+        );
+        assert!(image_flake_output(&SystemPlatform::Unsupported("riscv64-linux".into())).is_err());
+    }
+}
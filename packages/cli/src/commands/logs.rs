@@ -0,0 +1,29 @@
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    journal::{show, LogLevel},
+    registry::{self, registration_by_name},
+};
+
+use crate::errors::CliError;
+
+/// Prints the journal of the services behind an app, e.g. `lnd` or
+/// `bitcoind`, so the unit names don't have to be known. Entries less
+/// severe than `level` are left out.
+pub fn logs_cmd(app: &str, follow: bool, lines: usize, level: LogLevel) -> Result<(), CliError> {
+    let Some(reg) = registration_by_name(app) else {
+        let names: Vec<&str> = registry::APPS
+            .iter()
+            .filter(|r| !r.units.is_empty())
+            .map(|r| r.name())
+            .collect();
+        return Err(
+            Report::new(CliError::ArgumentError).attach_printable(format!(
+                "Unknown app {}, use one of {}",
+                app,
+                names.join(", ")
+            )),
+        );
+    };
+
+    show(reg.app, lines, follow, level).change_context(CliError::LogsError)
+}
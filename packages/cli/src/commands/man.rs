@@ -0,0 +1,39 @@
+use error_stack::{Report, Result};
+use nixblitzlib::docs::{page, pages, search};
+
+use crate::{errors::CliError, markdown::to_ansi};
+
+/// Prints the bundled documentation
+///
+/// Without a topic or search query all available topics are listed.
+pub fn man_cmd(topic: &Option<String>, query: &Option<String>) -> Result<(), CliError> {
+    if let Some(query) = query {
+        let hits = search(query);
+        if hits.is_empty() {
+            println!("No results for \"{}\".", query);
+        }
+
+        for hit in hits {
+            println!("{}:{}: {}", hit.slug, hit.line, hit.text);
+        }
+
+        return Ok(());
+    }
+
+    if let Some(topic) = topic {
+        let page = page(topic).ok_or_else(|| {
+            Report::new(CliError::DocTopicNotFound(topic.clone()))
+                .attach_printable("Use `nixblitz man` to list all topics")
+        })?;
+        println!("{}", to_ansi(&page.content));
+
+        return Ok(());
+    }
+
+    println!("Available topics:");
+    for page in pages() {
+        println!("  {:<20} {}", page.slug, page.title);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,17 @@
+use std::path::Path;
+
+use error_stack::{Result, ResultExt};
+use nixblitzlib::{apps::SupportedApps, node_check::ping, project::Project};
+
+use crate::errors::CliError;
+
+/// Checks that the given lightning node answers and prints its info
+pub fn ping_cmd(work_dir: &Path, app: SupportedApps) -> Result<(), CliError> {
+    let project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+
+    let info = ping(&project, app).change_context(CliError::NodeCheckError)?;
+    println!("{}", info);
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use std::io::{self, BufRead, Write};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::errors::CliError;
+
+/// Asks a yes/no question, no is the default
+pub fn confirm(
+    input: &mut impl BufRead,
+    question: &str,
+    error: CliError,
+) -> Result<bool, CliError> {
+    confirm_default(input, question, false, error)
+}
+
+/// Asks a yes/no question, an empty answer is the default
+pub fn confirm_default(
+    input: &mut impl BufRead,
+    question: &str,
+    default: bool,
+    error: CliError,
+) -> Result<bool, CliError> {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    let answer = read_answer(input, &format!("{} {} ", question, hint), error)?;
+
+    Ok(match answer.to_lowercase().as_str() {
+        "" => default,
+        a => matches!(a, "y" | "yes"),
+    })
+}
+
+/// Asks the user to type the given word, it must match exactly
+pub fn confirm_typed(
+    input: &mut impl BufRead,
+    question: &str,
+    word: &str,
+    error: CliError,
+) -> Result<bool, CliError> {
+    let answer = read_answer(
+        input,
+        &format!("{} Type {} to confirm: ", question, word),
+        error,
+    )?;
+    Ok(answer == word)
+}
+
+/// Prints the question and reads the trimmed answer.
+///
+/// # Errors
+/// `error` if stdin can't be read or was closed before an answer was given.
+pub fn read_answer(
+    input: &mut impl BufRead,
+    question: &str,
+    error: CliError,
+) -> Result<String, CliError> {
+    print!("{}", question);
+    io::stdout().flush().change_context(error.clone())?;
+
+    let mut answer = String::new();
+    let read = input.read_line(&mut answer).change_context(error.clone())?;
+    if read == 0 {
+        return Err(Report::new(error).attach_printable("Aborted, nothing was changed"));
+    }
+
+    Ok(answer.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirm() {
+        let ask = |answer: &str| confirm(&mut answer.as_bytes(), "", CliError::SetupError);
+        assert!(ask("y\n").unwrap());
+        assert!(ask("YES\n").unwrap());
+        assert!(!ask("\n").unwrap());
+        assert!(!ask("n\n").unwrap());
+
+        // stdin closed
+        assert!(ask("").is_err());
+    }
+
+    #[test]
+    fn test_confirm_default() {
+        let ask =
+            |answer: &str| confirm_default(&mut answer.as_bytes(), "", true, CliError::SetupError);
+        assert!(ask("\n").unwrap());
+        assert!(ask(" y \n").unwrap());
+        assert!(!ask("no\n").unwrap());
+    }
+
+    #[test]
+    fn test_confirm_typed() {
+        let ask = |answer: &str, word: &str| {
+            confirm_typed(&mut answer.as_bytes(), "", word, CliError::SnapshotError)
+        };
+        assert!(ask("RESET\n", "RESET").unwrap());
+        assert!(!ask("reset\n", "RESET").unwrap());
+        assert!(!ask("y\n", "nixblitz-1700000100").unwrap());
+        assert!(ask("nixblitz-1700000100\n", "nixblitz-1700000100").unwrap());
+        assert!(ask("", "RESET").is_err());
+    }
+}
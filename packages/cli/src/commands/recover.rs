@@ -0,0 +1,78 @@
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::recovery::{
+    find_backup, find_broken_files, reset_to_defaults, restore_from_backup, restore_from_git,
+};
+
+use crate::errors::CliError;
+
+/// The ways a broken project can be repaired
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RecoverStrategy {
+    /// Replace the broken files with the defaults, keeping a copy of the broken ones
+    Defaults,
+    /// Restore the broken files from the last git commit
+    Git,
+    /// Restore the broken files from a backup
+    Backup,
+}
+
+pub fn recover_cmd(
+    work_dir: &Path,
+    strategy: RecoverStrategy,
+    from: &Option<PathBuf>,
+) -> Result<(), CliError> {
+    let broken = find_broken_files(work_dir);
+    if broken.is_empty() {
+        println!("All project files are fine, nothing to recover.");
+        return Ok(());
+    }
+
+    println!("Found {} broken file(s):", broken.len());
+    for file in broken.iter() {
+        println!("  - {} ({})", file.path.display(), file.reason);
+    }
+
+    match strategy {
+        RecoverStrategy::Defaults => {
+            let backups =
+                reset_to_defaults(work_dir, &broken).change_context(CliError::RecoveryError)?;
+            for backup in backups {
+                println!("Moved broken file to {}", backup.display());
+            }
+        }
+        RecoverStrategy::Git => {
+            restore_from_git(work_dir, &broken).change_context(CliError::RecoveryError)?
+        }
+        RecoverStrategy::Backup => {
+            let Some(from) = from else {
+                return Err(Report::new(CliError::ArgumentError)
+                    .attach_printable("The backup strategy requires --from <PATH>"));
+            };
+
+            let backup = find_backup(from).change_context(CliError::RecoveryError)?;
+            println!("Restoring from {}", backup.display());
+            restore_from_backup(work_dir, &backup, &broken)
+                .change_context(CliError::RecoveryError)?;
+        }
+    }
+
+    let still_broken = find_broken_files(work_dir);
+    if !still_broken.is_empty() {
+        return Err(
+            Report::new(CliError::RecoveryError).attach_printable(format!(
+                "Files still broken: {}",
+                still_broken
+                    .iter()
+                    .map(|f| f.path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        );
+    }
+
+    println!("Recovery finished.");
+    Ok(())
+}
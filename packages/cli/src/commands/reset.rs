@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    app_option_data::option_data::{GetOptionId, OptionId},
+    option_values::display_value,
+    project::Project,
+    registry::{self, registration, registration_by_name},
+};
+
+use crate::errors::CliError;
+
+/// Sets an option back to its default
+pub fn unset_cmd(work_dir: &Path, app: &str, option: &str) -> Result<(), CliError> {
+    reset_cmd(work_dir, app, Some(option))
+}
+
+/// Sets an option back to its default, or all options of the app if
+/// `option` is `None`. Passwords are kept. Prints the options that changed,
+/// including those of other apps that followed.
+pub fn reset_cmd(work_dir: &Path, app: &str, option: Option<&str>) -> Result<(), CliError> {
+    let Some(reg) = registration_by_name(app) else {
+        let names: Vec<&str> = registry::APPS.iter().map(|r| r.name()).collect();
+        return Err(
+            Report::new(CliError::ArgumentError).attach_printable(format!(
+                "Unknown app {}, use one of {}",
+                app,
+                names.join(", ")
+            )),
+        );
+    };
+
+    let mut project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    let changed = match option {
+        Some(name) => project.reset_option(&OptionId::new(reg.app, name.to_string())),
+        None => project.reset_app(reg.app),
+    }
+    .change_context(CliError::ResetError)?;
+
+    if changed.is_empty() {
+        println!("Nothing to reset, the options already have their defaults");
+        return Ok(());
+    }
+
+    for id in changed.iter() {
+        let value = project
+            .get_options_for(id.app)
+            .iter()
+            .find(|o| o.id() == id)
+            .map(display_value)
+            .unwrap_or_default();
+        println!(
+            "{}.{} = {}",
+            registration(id.app).name(),
+            id.option,
+            value.replace('\n', "\\n")
+        );
+    }
+    println!("Review the changes with nixblitz diff before switching");
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    pending_changes::{AppChanges, OptionChange},
+    project::Project,
+    registry,
+    strings::OPTION_TITLES,
+};
+
+use crate::errors::CliError;
+
+use super::tui::start_tui;
+
+/// Same as the defaults of `nixblitz tui`
+const TICK_RATE: f64 = 4.0;
+const FRAME_RATE: f64 = 60.0;
+
+/// What to do after the review
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReviewAnswer {
+    Continue,
+    Edit,
+    Abort,
+}
+
+/// Lists every option that differs from its default and asks whether to
+/// continue with the install. Editing opens the TUI and shows the list again
+/// afterwards. Aborting fails the command, so install scripts can stop.
+pub async fn review_cmd(work_dir: &Path) -> Result<(), CliError> {
+    loop {
+        let project =
+            Project::load(work_dir.to_path_buf()).change_context(CliError::ReviewError)?;
+        print_summary(&project.non_default_options());
+
+        let answer = ask(&mut io::stdin().lock())?;
+        match answer {
+            ReviewAnswer::Continue => return Ok(()),
+            ReviewAnswer::Edit => start_tui(TICK_RATE, FRAME_RATE, work_dir.to_path_buf()).await?,
+            ReviewAnswer::Abort => {
+                return Err(Report::new(CliError::ReviewError)
+                    .attach_printable("Aborted, nothing was installed"))
+            }
+        }
+    }
+}
+
+fn print_summary(apps: &[AppChanges]) {
+    if apps.is_empty() {
+        println!("All options have their default values.");
+        return;
+    }
+
+    let rows: Vec<(String, &OptionChange)> = apps
+        .iter()
+        .flat_map(|a| a.changes.iter())
+        .map(|c| (title(c), c))
+        .collect();
+    let title_width = rows
+        .iter()
+        .map(|(t, _)| t.chars().count())
+        .fold("Option".len(), usize::max);
+    let default_width = rows
+        .iter()
+        .map(|(_, c)| short_value(c.old.as_deref()).chars().count())
+        .fold("Default".len(), usize::max);
+
+    println!("These options differ from their defaults:");
+    for app in apps.iter() {
+        println!("\n{}", registry::registration(app.app).title);
+        println!(
+            "  {:title_width$}  {:default_width$}  Value",
+            "Option", "Default"
+        );
+        for change in app.changes.iter() {
+            println!(
+                "  {:title_width$}  {:default_width$}  {}",
+                title(change),
+                short_value(change.old.as_deref()),
+                short_value(Some(&change.new)),
+            );
+        }
+    }
+    println!();
+}
+
+fn title(change: &OptionChange) -> String {
+    OPTION_TITLES
+        .get(&change.id)
+        .map(|t| t.to_string())
+        .unwrap_or_else(|| change.id.option.clone())
+}
+
+/// Fits a value into a table cell, multi-line values only show their size
+fn short_value(value: Option<&str>) -> String {
+    match value {
+        None => "-".to_string(),
+        Some("") => "(empty)".to_string(),
+        Some(v) if v.contains('\n') => format!("({} lines)", v.lines().count()),
+        Some(v) => v.to_string(),
+    }
+}
+
+fn ask(input: &mut impl BufRead) -> Result<ReviewAnswer, CliError> {
+    loop {
+        print!("[c]ontinue with the install, [e]dit in the TUI or [a]bort? ");
+        io::stdout().flush().change_context(CliError::ReviewError)?;
+
+        let mut answer = String::new();
+        let read = input
+            .read_line(&mut answer)
+            .change_context(CliError::ReviewError)?;
+        if read == 0 {
+            return Ok(ReviewAnswer::Abort);
+        }
+
+        match answer.trim() {
+            "c" => return Ok(ReviewAnswer::Continue),
+            "e" => return Ok(ReviewAnswer::Edit),
+            "a" => return Ok(ReviewAnswer::Abort),
+            _ => continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ask() {
+        assert_eq!(ask(&mut "c\n".as_bytes()).unwrap(), ReviewAnswer::Continue);
+        assert_eq!(ask(&mut "x\ne\n".as_bytes()).unwrap(), ReviewAnswer::Edit);
+        assert_eq!(ask(&mut "a\n".as_bytes()).unwrap(), ReviewAnswer::Abort);
+
+        // stdin closed
+        assert_eq!(ask(&mut "".as_bytes()).unwrap(), ReviewAnswer::Abort);
+    }
+
+    #[test]
+    fn test_short_value() {
+        assert_eq!(short_value(None), "-");
+        assert_eq!(short_value(Some("")), "(empty)");
+        assert_eq!(short_value(Some("a\nb")), "(2 lines)");
+        assert_eq!(short_value(Some("true")), "true");
+    }
+}
@@ -0,0 +1,35 @@
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    registry::{self, registration_by_name},
+    schema::{all_schemas, app_schema},
+};
+
+use crate::errors::CliError;
+
+/// Prints the JSON Schema of the options of an app, or of all apps if `app`
+/// is `None`
+pub fn schema_cmd(app: Option<&str>) -> Result<(), CliError> {
+    let schema = match app {
+        Some(app) => {
+            let Some(reg) = registration_by_name(app) else {
+                let names: Vec<&str> = registry::APPS.iter().map(|r| r.name()).collect();
+                return Err(
+                    Report::new(CliError::ArgumentError).attach_printable(format!(
+                        "Unknown app {}, use one of {}",
+                        app,
+                        names.join(", ")
+                    )),
+                );
+            };
+            app_schema(reg)
+        }
+        None => all_schemas(),
+    };
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&schema).change_context(CliError::SchemaError)?
+    );
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use std::path::Path;
+
+use error_stack::{Result, ResultExt};
+use nixblitzlib::{project::Project, search::SearchIndex};
+
+use crate::errors::CliError;
+
+/// The longest value printed, longer ones are cut
+const MAX_VALUE_LEN: usize = 40;
+
+/// Prints the options of all apps matching the query, the best match first.
+/// See [search](nixblitzlib::search) for how they are matched.
+pub fn search_cmd(work_dir: &Path, query: &str) -> Result<(), CliError> {
+    let project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    let index = SearchIndex::new(&project);
+    let matches = index.search(query);
+
+    if matches.is_empty() {
+        println!("No option matches {}", query);
+        return Ok(());
+    }
+
+    let width = matches
+        .iter()
+        .map(|e| e.identifier.len())
+        .max()
+        .unwrap_or_default();
+    for entry in matches {
+        println!(
+            "{:width$}  {} = {}",
+            entry.identifier,
+            entry.title.unwrap_or(entry.id.option.as_str()),
+            entry.short_value(MAX_VALUE_LEN),
+        );
+    }
+
+    Ok(())
+}
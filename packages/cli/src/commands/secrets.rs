@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use error_stack::{Result, ResultExt};
+use nixblitzlib::{defaults::nix_bitcoin::SECRETS_DIR, project::Project, secrets};
+
+use crate::errors::CliError;
+
+/// Runs the secrets generation of nix-bitcoin and checks that every enabled
+/// app got its secrets. Only works on the node itself, as root.
+pub fn setup_secrets_cmd(work_dir: &Path) -> Result<(), CliError> {
+    let project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+
+    secrets::generate(&project.enabled_apps(), Path::new(SECRETS_DIR))
+        .change_context(CliError::SecretsError)?;
+    println!("All secrets exist in {}", SECRETS_DIR);
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use std::{fs, path::Path};
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    option_batch::{parse, ChangesFormat},
+    project::Project,
+    registry::registration,
+};
+
+use crate::errors::CliError;
+
+/// Applies the option changes in the file, see
+/// [option_batch](nixblitzlib::option_batch). Prints whether each change was
+/// accepted and fails without changing anything if one wasn't.
+pub fn set_cmd(work_dir: &Path, from_file: &Path) -> Result<(), CliError> {
+    let format = ChangesFormat::from_path(from_file).ok_or_else(|| {
+        Report::new(CliError::ArgumentError).attach_printable(format!(
+            "Unknown format of {}, use a .json or .toml file",
+            from_file.display()
+        ))
+    })?;
+    let text = fs::read_to_string(from_file)
+        .change_context(CliError::SetError)
+        .attach_printable_lazy(|| format!("Unable to read {}", from_file.display()))?;
+    let changes = parse(&text, format).change_context(CliError::SetError)?;
+
+    let mut project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    let report = project
+        .set_options(&changes)
+        .change_context(CliError::SetError)?;
+
+    for result in report.results.iter() {
+        let name = format!(
+            "{}.{}",
+            registration(result.id.app).name(),
+            result.id.option
+        );
+        match &result.error {
+            None => println!("ok      {}", name),
+            Some(error) => println!("failed  {}: {}", name, error),
+        }
+    }
+
+    if !report.applied {
+        return Err(Report::new(CliError::SetError)
+            .attach_printable("Nothing was changed, fix the failed options and try again"));
+    }
+    println!(
+        "Set {} options, review them with nixblitz diff before switching",
+        report.results.len()
+    );
+
+    Ok(())
+}
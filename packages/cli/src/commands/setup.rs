@@ -0,0 +1,329 @@
+use std::{
+    io::{self, BufRead, IsTerminal},
+    path::Path,
+    process::Command,
+};
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    app_option_data::option_data::{GetOptionId, OptionId, ToOptionId},
+    apps::SupportedApps,
+    bitcoind::{BitcoindConfigOption, PruneOptions},
+    cln::ClnConfigOption,
+    defaults::bitcoind::PRUNE_SIZE,
+    lnd::LndConfigOption,
+    nix_base_config::NixBaseConfigOption,
+    option_batch::OptionValue,
+    option_values::json_value,
+    project::Project,
+    registry::registration,
+    timezones::TIMEZONES,
+    tor::TorConfigOption,
+};
+use serde_json::Value;
+
+use crate::errors::CliError;
+
+use super::{
+    apply::apply_cmd,
+    prompt::{confirm, confirm_default, read_answer},
+};
+
+/// Bitcoin Core refuses smaller prune targets
+const MIN_PRUNE_SIZE: usize = 551;
+
+/// The lightning implementations to choose from
+const LIGHTNING: [&str; 3] = ["LND", "Core Lightning", "None"];
+
+/// Walks a new user through the settings every node needs: the password of
+/// the admin user, the time zone, the lightning implementation, pruning and
+/// Tor. The answers are written like `set --from-file`, nothing is changed
+/// unless all of them are accepted. Offers to apply the configuration at
+/// the end.
+pub fn setup_cmd(work_dir: &Path) -> Result<(), CliError> {
+    let mut project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+
+    println!("Welcome to your node. Press Enter to keep the value in brackets.\n");
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let changes = ask_changes(&mut input, &project, stdin.is_terminal())?;
+    if changes.is_empty() {
+        println!("\nNothing to change.");
+    } else {
+        println!("\nThese options will be changed:");
+        for change in changes.iter() {
+            println!("  {}", describe(change));
+        }
+        if !confirm(&mut input, "Save the changes?", CliError::SetupError)? {
+            println!("Aborted, nothing was changed.");
+            return Ok(());
+        }
+
+        let report = project
+            .set_options(&changes)
+            .change_context(CliError::SetupError)?;
+        if !report.applied {
+            let rejected: Vec<String> = report
+                .results
+                .iter()
+                .filter_map(|r| r.error.as_ref().map(|e| format!("{}: {}", r.id, e)))
+                .collect();
+            return Err(Report::new(CliError::SetupError).attach_printable(format!(
+                "Nothing was changed, these answers were rejected: {}",
+                rejected.join("; ")
+            )));
+        }
+        println!("Saved.");
+    }
+
+    if confirm(
+        &mut input,
+        "Apply the configuration now? This needs root",
+        CliError::SetupError,
+    )? {
+        return apply_cmd(work_dir, None, false, false);
+    }
+    println!("Run `nixblitz apply` to switch to the configuration later.");
+
+    Ok(())
+}
+
+/// Asks for the settings and returns the options whose value changes
+///
+/// # Arguments
+/// * `hide_input` - whether to hide the password while it is typed
+fn ask_changes(
+    input: &mut impl BufRead,
+    project: &Project,
+    hide_input: bool,
+) -> Result<Vec<OptionValue>, CliError> {
+    let mut changes = vec![];
+    let mut change = |id: OptionId, value: Value| {
+        if current(project, &id).as_ref() != Some(&value) {
+            changes.push(OptionValue { id, value });
+        }
+    };
+
+    println!("1. Password of the admin user, also used for SSH logins");
+    if let Some(password) = ask_password(input, hide_input)? {
+        change(
+            NixBaseConfigOption::InitialPassword.to_option_id(),
+            Value::from(password),
+        );
+    }
+
+    println!("\n2. Time zone, e.g. Europe/Berlin or America/New_York");
+    let time_zone = NixBaseConfigOption::TimeZone.to_option_id();
+    let default = current(project, &time_zone)
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let time_zone_value = loop {
+        let answer = ask(input, "Time zone", &default)?;
+        match TIMEZONES.iter().find(|t| t.eq_ignore_ascii_case(&answer)) {
+            Some(tz) => break tz.to_string(),
+            None => println!("Unknown time zone {}", answer),
+        }
+    };
+    change(time_zone, Value::from(time_zone_value));
+
+    println!("\n3. Lightning implementation");
+    let default = if project.is_app_enabled(SupportedApps::LND) {
+        0
+    } else if project.is_app_enabled(SupportedApps::CoreLightning) {
+        1
+    } else {
+        2
+    };
+    let lightning = choose(input, &LIGHTNING, default)?;
+    // disable first, so the other one isn't enabled next to it
+    let mut enable = [
+        (LndConfigOption::Enable.to_option_id(), lightning == 0),
+        (ClnConfigOption::Enable.to_option_id(), lightning == 1),
+    ];
+    enable.sort_by_key(|(_, on)| *on);
+    for (id, on) in enable {
+        change(id, Value::from(on));
+    }
+
+    println!("\n4. Pruning deletes verified blocks to save space. A pruned node needs");
+    println!("   a few GB instead of more than 700 GB, but can't serve old blocks,");
+    println!("   e.g. to Electrs or Fulcrum. Undoing it needs a reindex of the chain.");
+    let (prune, _) = project.prune_state();
+    let pruned = !matches!(prune, PruneOptions::Disable);
+    if confirm_default(
+        input,
+        "Prune the block chain?",
+        pruned,
+        CliError::SetupError,
+    )? {
+        let default = match prune {
+            PruneOptions::Automatic { prune_at } => prune_at.to_string(),
+            _ => PRUNE_SIZE.to_string(),
+        };
+        let size = loop {
+            let answer = ask(input, "Keep this many MiB of blocks", &default)?;
+            match answer.parse::<usize>() {
+                Ok(size) if size >= MIN_PRUNE_SIZE => break size,
+                _ => println!("Enter a number of at least {}", MIN_PRUNE_SIZE),
+            }
+        };
+        change(
+            BitcoindConfigOption::Prune.to_option_id(),
+            Value::from("Automatic"),
+        );
+        change(
+            BitcoindConfigOption::PruneSize.to_option_id(),
+            Value::from(size),
+        );
+    } else if pruned {
+        // the pruned blocks must be downloaded again, see archival_cmd
+        println!("Keeping the current mode, run `nixblitz archival` to stop pruning.");
+    }
+
+    println!("\n5. Tor hides the IP address of the node and makes the apps reachable");
+    println!("   via onion addresses without opening ports.");
+    let tor = confirm_default(
+        input,
+        "Enable Tor?",
+        project.is_app_enabled(SupportedApps::Tor),
+        CliError::SetupError,
+    )?;
+    change(TorConfigOption::Enable.to_option_id(), Value::from(tor));
+
+    Ok(changes)
+}
+
+/// The value of the option like `get --format json` prints it
+fn current(project: &Project, id: &OptionId) -> Option<Value> {
+    project
+        .get_options_for(id.app)
+        .iter()
+        .find(|o| o.id() == id)
+        .map(json_value)
+}
+
+/// Formats a change for the summary, passwords are masked
+fn describe(change: &OptionValue) -> String {
+    let name = format!(
+        "{}.{}",
+        registration(change.id.app).name(),
+        change.id.option
+    );
+    if change.id == NixBaseConfigOption::InitialPassword.to_option_id() {
+        return format!("{} = (new password)", name);
+    }
+
+    format!("{} = {}", name, change.value)
+}
+
+/// Asks for a new password twice, None if the user keeps the current one
+fn ask_password(input: &mut impl BufRead, hide_input: bool) -> Result<Option<String>, CliError> {
+    loop {
+        let password = read_secret(
+            input,
+            "New password, empty to keep the current one: ",
+            hide_input,
+        )?;
+        if password.is_empty() {
+            return Ok(None);
+        }
+
+        let repeated = read_secret(input, "Repeat the password: ", hide_input)?;
+        if password == repeated {
+            return Ok(Some(password));
+        }
+        println!("The passwords don't match, try again");
+    }
+}
+
+/// Reads a line without echoing it if `hide_input` is set, the terminal is
+/// switched back even if the read fails
+fn read_secret(
+    input: &mut impl BufRead,
+    prompt: &str,
+    hide_input: bool,
+) -> Result<String, CliError> {
+    if !hide_input {
+        return read_answer(input, prompt, CliError::SetupError);
+    }
+
+    let _ = Command::new("stty").arg("-echo").status();
+    let res = read_answer(input, prompt, CliError::SetupError);
+    let _ = Command::new("stty").arg("echo").status();
+    println!();
+
+    res
+}
+
+/// Asks for a value, returns the default if the answer is empty
+fn ask(input: &mut impl BufRead, question: &str, default: &str) -> Result<String, CliError> {
+    let answer = read_answer(
+        input,
+        &format!("{} [{}]: ", question, default),
+        CliError::SetupError,
+    )?;
+    if answer.is_empty() {
+        return Ok(default.to_string());
+    }
+
+    Ok(answer)
+}
+
+/// Lets the user pick one of the choices by its number
+///
+/// # Returns
+/// The index of the choice
+fn choose(input: &mut impl BufRead, choices: &[&str], default: usize) -> Result<usize, CliError> {
+    for (i, choice) in choices.iter().enumerate() {
+        println!("  {}) {}", i + 1, choice);
+    }
+
+    loop {
+        let answer = ask(input, "Choose", &(default + 1).to_string())?;
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=choices.len()).contains(&n) => return Ok(n - 1),
+            _ => println!("Enter a number from 1 to {}", choices.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use nixblitzlib::utils::init_default_project;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_ask_changes() {
+        let temp_dir = tempdir().unwrap();
+        init_default_project(temp_dir.path(), Some(false)).unwrap();
+        let project = Project::load(temp_dir.path().to_path_buf()).unwrap();
+
+        // password, mismatch and retry, unknown time zone and retry, CLN,
+        // prune with an invalid and a valid size, Tor off
+        let answers = "secret123\nother\nsecret123\nsecret123\nMars/Base\neurope/berlin\n\
+                       2\ny\n100\n4000\nn\n";
+        let changes = ask_changes(&mut Cursor::new(answers), &project, false).unwrap();
+        let ids: Vec<String> = changes.iter().map(describe).collect();
+        assert_eq!(
+            ids,
+            [
+                "nix_base_config.initial_password = (new password)",
+                "nix_base_config.time_zone = \"Europe/Berlin\"",
+                "cln.enable = true",
+                "bitcoind.prune = \"Automatic\"",
+                "bitcoind.prune_size = 4000",
+            ]
+        );
+
+        // keeping every default changes nothing
+        let changes = ask_changes(&mut Cursor::new("\n\n\n\n\n"), &project, false).unwrap();
+        assert!(changes.is_empty());
+
+        assert!(ask_changes(&mut Cursor::new(""), &project, false).is_err());
+    }
+}
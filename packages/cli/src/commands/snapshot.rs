@@ -0,0 +1,207 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    nix_base_config::{self, NixBaseConfig},
+    number_value::NumberValue,
+    project::Project,
+    snapshots::{
+        lightning_data_in, start_units, stop_units, units_of_enabled_apps, SnapshotBackend,
+    },
+    utils::load_json_file,
+};
+
+use crate::errors::CliError;
+
+use super::prompt::confirm_typed;
+
+/// Snapshots the service data and the project files and prunes old snapshots
+///
+/// Directories on filesystems without snapshot support are skipped.
+pub fn snapshot_cmd(work_dir: &Path, list: bool) -> Result<(), CliError> {
+    let config = load_config(work_dir)?;
+
+    if list {
+        let mut skipped = vec![];
+        let backends = backends(&[data_dir(&config), work_dir.to_path_buf()], &mut skipped)?;
+        for line in skipped {
+            println!("{}", line);
+        }
+        for (dir, backend) in backends.iter() {
+            println!("{}:", dir.display());
+            for name in backend.list().change_context(CliError::SnapshotError)? {
+                println!("  {}", name);
+            }
+        }
+        return Ok(());
+    }
+
+    if snapshot_keep(&config) == 0 {
+        println!("Snapshots are disabled, the number of snapshots to keep is 0.");
+        return Ok(());
+    }
+
+    for line in create_snapshots(work_dir, &config)? {
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+/// Snapshots the service data and the project files unless snapshots are
+/// disabled, e.g. before an apply
+///
+/// # Returns
+/// What was done, one line per step
+pub fn create_snapshots(work_dir: &Path, config: &NixBaseConfig) -> Result<Vec<String>, CliError> {
+    let keep = snapshot_keep(config);
+    if keep == 0 {
+        return Ok(vec![]);
+    }
+
+    let mut lines = vec![];
+    let backends = backends(&[data_dir(config), work_dir.to_path_buf()], &mut lines)?;
+    for (dir, backend) in backends.iter() {
+        let name = backend.create().change_context(CliError::SnapshotError)?;
+        lines.push(format!("Created snapshot {} of {}", name, dir.display()));
+
+        for name in backend
+            .prune(keep)
+            .change_context(CliError::SnapshotError)?
+        {
+            lines.push(format!("Deleted snapshot {}", name));
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Reverts the service data to the given snapshot after the user typed its
+/// name. The services of the enabled apps are stopped during the rollback.
+///
+/// # Arguments
+/// * `allow_lightning` - rolls back even if the data of a lightning node is
+///   in the snapshot. Its old channel state can cost all channel funds.
+pub fn rollback_data_cmd(
+    work_dir: &Path,
+    snapshot: &str,
+    allow_lightning: bool,
+) -> Result<(), CliError> {
+    let config = load_config(work_dir)?;
+    let project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    let dir = data_dir(&config);
+    let mut skipped = vec![];
+    let Some((_, backend)) = backends(&[dir.clone()], &mut skipped)?.pop() else {
+        return Err(
+            Report::new(CliError::SnapshotError).attach_printable(format!(
+                "{} is not on a filesystem supporting snapshots",
+                dir.display()
+            )),
+        );
+    };
+
+    let lightning = lightning_data_in(&project, &dir);
+    if !lightning.is_empty() {
+        let dirs = lightning
+            .iter()
+            .map(|d| d.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if !allow_lightning {
+            return Err(Report::new(CliError::SnapshotError)
+                .attach_printable(format!(
+                    "The snapshot contains the lightning data in {}",
+                    dirs
+                ))
+                .attach_printable(
+                    "An old channel state lets the peers claim the funds of all channels",
+                )
+                .attach_printable(
+                    "Pass --allow-lightning if the node never ran since the snapshot",
+                ));
+        }
+
+        println!(
+            "WARNING: the lightning data in {} is rolled back as well.",
+            dirs
+        );
+        println!("WARNING: if the node updated a channel since the snapshot, the peer can");
+        println!("WARNING: claim all of its funds with a penalty transaction.\n");
+    }
+
+    let units = units_of_enabled_apps(&project);
+    println!(
+        "This reverts {} to the snapshot {}.",
+        dir.display(),
+        snapshot
+    );
+    println!("All changes since then are lost, newer snapshots of nixblitz are deleted.");
+    if !units.is_empty() {
+        println!("These services are stopped meanwhile: {}", units.join(", "));
+    }
+    let stdin = io::stdin();
+    if !confirm_typed(
+        &mut stdin.lock(),
+        "Roll back?",
+        snapshot,
+        CliError::SnapshotError,
+    )? {
+        println!("Aborted, nothing was changed.");
+        return Ok(());
+    }
+
+    stop_units(&units).change_context(CliError::SnapshotError)?;
+    let res = backend.rollback(snapshot);
+    // bring the services back, even if the rollback failed
+    start_units(&units).change_context(CliError::SnapshotError)?;
+    res.change_context(CliError::SnapshotError)?;
+    println!("Rolled back {} to {}", dir.display(), snapshot);
+
+    Ok(())
+}
+
+fn snapshot_keep(config: &NixBaseConfig) -> usize {
+    match config.snapshot_keep.value() {
+        NumberValue::UInt(Some(keep)) => *keep,
+        _ => 0,
+    }
+}
+
+fn load_config(work_dir: &Path) -> Result<NixBaseConfig, CliError> {
+    let json = load_json_file(&work_dir.join(nix_base_config::JSON_FILE_NAME))
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    NixBaseConfig::from_json(&json).change_context(CliError::UnableToInitProjectStruct)
+}
+
+fn data_dir(config: &NixBaseConfig) -> PathBuf {
+    PathBuf::from(config.snapshot_data_dir.value())
+}
+
+/// Detects the snapshot backends of the given directories.
+///
+/// Directories sharing a dataset or subvolume are only returned once. A line
+/// is added to `skipped` for each directory without snapshot support.
+fn backends(
+    dirs: &[PathBuf],
+    skipped: &mut Vec<String>,
+) -> Result<Vec<(PathBuf, SnapshotBackend)>, CliError> {
+    let mut res: Vec<(PathBuf, SnapshotBackend)> = vec![];
+    for dir in dirs {
+        match SnapshotBackend::detect(dir).change_context(CliError::SnapshotError)? {
+            Some(backend) if !res.iter().any(|(_, b)| *b == backend) => {
+                res.push((dir.clone(), backend))
+            }
+            Some(_) => (),
+            None => skipped.push(format!(
+                "{} is not on ZFS or btrfs, skipping snapshots",
+                dir.display()
+            )),
+        }
+    }
+
+    Ok(res)
+}
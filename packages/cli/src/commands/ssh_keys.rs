@@ -0,0 +1,84 @@
+use std::{io, path::Path};
+
+use clap::ValueEnum;
+use error_stack::{Result, ResultExt};
+use nixblitzlib::{
+    project::Project,
+    ssh_keys::{fetch, same_key, KeySource},
+};
+
+use crate::errors::CliError;
+
+use super::prompt::confirm;
+
+/// The sites the public keys of a user can be fetched from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KeyHost {
+    /// https://github.com/<user>.keys
+    Github,
+    /// https://gitlab.com/<user>.keys
+    Gitlab,
+}
+
+impl From<KeyHost> for KeySource {
+    fn from(host: KeyHost) -> Self {
+        match host {
+            KeyHost::Github => KeySource::GitHub,
+            KeyHost::Gitlab => KeySource::GitLab,
+        }
+    }
+}
+
+/// Fetches the public SSH keys of a GitHub or GitLab user and adds them to
+/// the authorized keys of the admin user
+///
+/// The fingerprints are shown before anything is changed, so the user can
+/// compare them with their own keys. `yes` skips the confirmation.
+pub fn import_ssh_keys_cmd(
+    work_dir: &Path,
+    user: &str,
+    host: KeyHost,
+    yes: bool,
+) -> Result<(), CliError> {
+    let mut project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+
+    let source = KeySource::from(host);
+    let keys = fetch(source, user).change_context(CliError::SshKeyError)?;
+    let known = project.ssh_auth_keys();
+    let new: Vec<_> = keys
+        .iter()
+        .filter(|k| !known.iter().any(|a| same_key(a, &k.key)))
+        .collect();
+
+    println!("The public keys of {} on {}:", user, source);
+    for key in &keys {
+        let state = if new.contains(&key) {
+            ""
+        } else {
+            " (already authorized)"
+        };
+        println!("  {}{}", key.fingerprint, state);
+    }
+    if new.is_empty() {
+        println!("Nothing to do, all keys are authorized already.");
+        return Ok(());
+    }
+
+    let question = format!("Allow {} new key(s) to log in via SSH?", new.len());
+    if !yes && !confirm(&mut io::stdin().lock(), &question, CliError::SshKeyError)? {
+        println!("Aborted, nothing was changed.");
+        return Ok(());
+    }
+
+    let keys: Vec<String> = new.iter().map(|k| k.key.clone()).collect();
+    let added = project
+        .add_ssh_auth_keys(&keys)
+        .change_context(CliError::SshKeyError)?;
+    println!(
+        "Added {} key(s), they are authorized after the next apply",
+        added
+    );
+
+    Ok(())
+}
@@ -0,0 +1,271 @@
+use std::{
+    io::{self, IsTerminal},
+    path::Path,
+    time::Duration,
+};
+
+use crossterm::style::{Color, Stylize};
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    disk_usage::DataDirUsage,
+    initial_sync::NodePhase,
+    project::Project,
+    registry::registration,
+    status::{collect, ChainSync, NodeStatus, UnitHealth, UnitStatus},
+};
+
+use crate::errors::CliError;
+
+use super::apply::{format_duration, slow_warning};
+
+/// Prints the health of the services of all enabled apps, the sync progress
+/// of Bitcoin Core with tips during the first sync, whether the lightning
+/// nodes answer, the expiry of a custom TLS certificate, the space their data
+/// takes and how long the last apply took. Fails if anything is unhealthy,
+/// so scripts can act on it.
+pub fn status_cmd(work_dir: &Path, json: bool) -> Result<(), CliError> {
+    let project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    let status = collect(&project).change_context(CliError::StatusError)?;
+
+    if json {
+        let json = serde_json::to_string_pretty(&status).change_context(CliError::StatusError)?;
+        println!("{}", json);
+    } else {
+        print_status(&status, io::stdout().is_terminal());
+    }
+
+    if status.is_healthy() {
+        Ok(())
+    } else {
+        Err(Report::new(CliError::StatusError).attach_printable("Not all services are healthy"))
+    }
+}
+
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// The first sync gets a section of its own, the other apps wait for it
+fn print_initial_sync(sync: &ChainSync, eta_secs: Option<u64>, tips: &[&str], colored: bool) {
+    println!(
+        "\nBitcoin Core is syncing the block chain for the first time: {}",
+        paint(sync.to_string(), Color::Yellow, colored)
+    );
+    match eta_secs {
+        Some(secs) => println!("About {} left", format_eta(secs)),
+        None => println!("The time left is estimated once the sync has made some progress"),
+    }
+    for tip in tips {
+        println!("  - {}", tip);
+    }
+}
+
+/// Formats the time left as days and hours, e.g. "2d 05h"
+fn format_eta(secs: u64) -> String {
+    let (days, hours) = (secs / 86400, secs / 3600 % 24);
+    match (days, hours) {
+        (0, 0) => format_duration(Duration::from_secs(secs)),
+        (0, h) => format!("{}h {:02}m", h, secs / 60 % 60),
+        (d, h) => format!("{}d {:02}h", d, h),
+    }
+}
+
+/// Colors the text if the output is a terminal
+fn paint(text: String, color: Color, colored: bool) -> String {
+    if colored {
+        text.with(color).to_string()
+    } else {
+        text
+    }
+}
+
+fn print_status(status: &NodeStatus, colored: bool) {
+    if status.units.is_empty() {
+        println!("No services are enabled.");
+    } else {
+        print_units(&status.units, colored);
+    }
+
+    if let NodePhase::SyncingInitialBlockchain {
+        sync,
+        eta_secs,
+        tips,
+    } = &status.phase
+    {
+        print_initial_sync(sync, *eta_secs, tips, colored);
+    } else if let Some(chain) = &status.chain {
+        let title = registration(chain.app).title;
+        match (&chain.info, &chain.error) {
+            (Some(sync), _) => {
+                let color = if sync.initial_block_download {
+                    Color::Yellow
+                } else {
+                    Color::Green
+                };
+                println!(
+                    "\n{} is synced to {}",
+                    title,
+                    paint(sync.to_string(), color, colored)
+                );
+            }
+            (None, error) => println!(
+                "\n{}",
+                paint(error.clone().unwrap_or_default(), Color::Red, colored)
+            ),
+        }
+    }
+
+    for node in status.lightning.iter() {
+        let title = registration(node.app).title;
+        match (&node.info, &node.error) {
+            (Some(info), _) => println!(
+                "{} {} at block {}{}",
+                title,
+                paint("is reachable".into(), Color::Green, colored),
+                info.block_height,
+                if info.synced_to_chain {
+                    ""
+                } else {
+                    ", not synced to the chain yet"
+                }
+            ),
+            (None, error) => println!(
+                "{}",
+                paint(error.clone().unwrap_or_default(), Color::Red, colored)
+            ),
+        }
+    }
+
+    if let Some(cert) = &status.certificate {
+        match (&cert.info, &cert.error) {
+            (Some(expiry), _) if expiry.expired => println!(
+                "\nThe TLS certificate {}",
+                paint(
+                    format!("expired on {}", expiry.not_after),
+                    Color::Red,
+                    colored
+                )
+            ),
+            (Some(expiry), _) if expiry.expires_soon => println!(
+                "\nThe TLS certificate {}",
+                paint(
+                    format!("expires on {}, renew it soon", expiry.not_after),
+                    Color::Yellow,
+                    colored
+                )
+            ),
+            (Some(expiry), _) => println!(
+                "\nThe TLS certificate is {} until {}",
+                paint("valid".into(), Color::Green, colored),
+                expiry.not_after
+            ),
+            (None, error) => println!(
+                "\n{}",
+                paint(error.clone().unwrap_or_default(), Color::Red, colored)
+            ),
+        }
+    }
+
+    if !status.disk.is_empty() {
+        println!();
+        print_disk(&status.disk, colored);
+    }
+
+    if let Some(trend) = &status.apply {
+        println!(
+            "\nThe last apply took {}, usually {}",
+            format_duration(Duration::from_secs(trend.last.duration_secs)),
+            format_duration(Duration::from_secs(trend.usual_secs))
+        );
+        if let Some(size) = trend.last.closure_size {
+            println!("The system closure is {:.2} GiB", size as f64 / GIB);
+        }
+        if let Some(warning) = slow_warning(trend) {
+            println!(
+                "{}",
+                paint(format!("Warning: {}", warning), Color::Yellow, colored)
+            );
+        }
+    }
+}
+
+fn print_units(units: &[UnitStatus], colored: bool) {
+    let app_width = units
+        .iter()
+        .map(|u| registration(u.app).title.chars().count())
+        .fold("App".len(), usize::max);
+    let unit_width = units
+        .iter()
+        .map(|u| u.unit.chars().count())
+        .fold("Unit".len(), usize::max);
+    let state_width = units
+        .iter()
+        .map(|u| u.state.chars().count())
+        .fold("State".len(), usize::max);
+
+    println!(
+        "{:app_width$}  {:unit_width$}  {:state_width$}  Since",
+        "App", "Unit", "State"
+    );
+    for unit in units.iter() {
+        let color = match unit.health {
+            h if h.is_ok() => Color::Green,
+            UnitHealth::Changing => Color::Yellow,
+            _ => Color::Red,
+        };
+        // pad before coloring, the escape codes would count as characters
+        let state = format!("{:state_width$}", unit.state);
+        println!(
+            "{:app_width$}  {:unit_width$}  {}  {}",
+            registration(unit.app).title,
+            unit.unit,
+            paint(state, color, colored),
+            unit.since
+        );
+    }
+}
+
+fn print_disk(usage: &[DataDirUsage], colored: bool) {
+    let app_width = usage
+        .iter()
+        .map(|u| registration(u.app).title.chars().count())
+        .fold("App".len(), usize::max);
+    let dir_width = usage
+        .iter()
+        .map(|u| u.dir.to_string_lossy().chars().count())
+        .fold("Data".len(), usize::max);
+
+    println!(
+        "{:app_width$}  {:dir_width$}  {:>9}  Disk",
+        "App", "Data", "Size"
+    );
+    for u in usage.iter() {
+        let size = match u.bytes {
+            Some(bytes) => format!("{:.1} GiB", bytes as f64 / GIB),
+            None => "-".to_string(),
+        };
+        let disk = match u.disk_percent() {
+            Some(percent) => format!("{}% full", percent),
+            None => "-".to_string(),
+        };
+        let color = if u.alert { Color::Red } else { Color::Green };
+        println!(
+            "{:app_width$}  {:dir_width$}  {:>9}  {}",
+            registration(u.app).title,
+            u.dir.display(),
+            size,
+            paint(disk, color, colored)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_eta() {
+        assert_eq!(format_eta(125), "2m 05s");
+        assert_eq!(format_eta(3 * 3600 + 7 * 60), "3h 07m");
+        assert_eq!(format_eta(2 * 86400 + 5 * 3600 + 59), "2d 05h");
+    }
+}
@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use error_stack::{Result, ResultExt};
+use nixblitzlib::config_sync::{self, SyncOptions, SyncProgress};
+
+use crate::errors::CliError;
+
+/// Copies the project to the system mounted at `root` and verifies the copy
+pub fn sync_config_cmd(work_dir: &Path, root: &Path, attempts: u32) -> Result<(), CliError> {
+    let target = config_sync::target_dir(root);
+    println!("Copying the project to {}", target.display());
+
+    let options = SyncOptions {
+        attempts,
+        ..Default::default()
+    };
+    let report = config_sync::sync(work_dir, &target, options, |progress| match progress {
+        SyncProgress::Attempt { attempt, attempts } if attempt > 1 => {
+            println!("Attempt {} of {}", attempt, attempts)
+        }
+        SyncProgress::Attempt { .. } => {}
+        SyncProgress::Copied(file) => println!("  {}", file),
+        SyncProgress::Retry { reason, delay } => {
+            println!("{}", reason);
+            println!("Retrying in {} seconds...", delay.as_secs());
+        }
+        SyncProgress::Verified(files) => println!("Verified {} files", files),
+    })
+    .change_context(CliError::ConfigSyncError)?;
+
+    println!(
+        "The project is in {}, it took {} attempt(s).",
+        report.target.display(),
+        report.attempts
+    );
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use std::path::Path;
+
+use error_stack::{Result, ResultExt};
+use nixblitzlib::{
+    system_backup::{self, SystemBackupService},
+    utils::load_json_file,
+};
+
+use crate::errors::CliError;
+
+/// Runs the encrypted system backup now or checks its repository.
+/// Only works on the node itself.
+pub fn system_backup_cmd(work_dir: &Path, verify: bool) -> Result<(), CliError> {
+    let json = load_json_file(&work_dir.join(system_backup::JSON_FILE_NAME))
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    let service = SystemBackupService::from_json(&json)
+        .change_context(CliError::UnableToInitProjectStruct)?;
+
+    if verify {
+        service
+            .verify()
+            .change_context(CliError::SystemBackupError)?;
+        println!("The {} repository is fine", service.tool());
+        return Ok(());
+    }
+
+    service
+        .run_now()
+        .change_context(CliError::SystemBackupError)?;
+    println!("Created a {} backup", service.tool());
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::{fs, path::Path};
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    defaults::reverse_proxy::CERT_WARN_DAYS, errors::describe, project::Project, tls_cert,
+};
+
+use crate::errors::CliError;
+
+/// Imports a custom certificate and its key into the secret store and
+/// serves the HTTPS routes of nginx with it
+///
+/// Expired certificates are refused. If openssl isn't available the expiry
+/// isn't checked.
+pub fn import_tls_cmd(work_dir: &Path, cert: &Path, key: &Path) -> Result<(), CliError> {
+    let read = |path: &Path| {
+        fs::read_to_string(path)
+            .change_context(CliError::TlsError)
+            .attach_printable_lazy(|| format!("Unable to read {}", path.display()))
+    };
+    let certificate = read(cert)?;
+    let key = read(key)?;
+
+    match tls_cert::expiry(&certificate, CERT_WARN_DAYS) {
+        Ok(expiry) if expiry.expired => {
+            return Err(Report::new(CliError::TlsError)
+                .attach_printable(format!("The certificate expired on {}", expiry.not_after)));
+        }
+        Ok(expiry) if expiry.expires_soon => println!(
+            "Warning: the certificate expires on {}, renew it soon",
+            expiry.not_after
+        ),
+        Ok(expiry) => println!("The certificate is valid until {}", expiry.not_after),
+        Err(e) => println!("Warning: the expiry wasn't checked. {}", describe(&e)),
+    }
+
+    let mut project = Project::load(work_dir.to_path_buf())
+        .change_context(CliError::UnableToInitProjectStruct)?;
+    project
+        .import_tls_certificate(&certificate, &key)
+        .change_context(CliError::TlsError)?;
+
+    println!("Imported the certificate, nginx uses it after the next apply");
+
+    Ok(())
+}
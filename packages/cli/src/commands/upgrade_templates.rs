@@ -0,0 +1,94 @@
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::template_upgrade::{apply_upgrade, plan_upgrade, Resolution};
+
+use crate::errors::CliError;
+
+/// Merges the output of the current templates into the nix files of the
+/// project, asking how to resolve each conflict
+pub fn upgrade_templates_cmd(work_dir: &Path, dry_run: bool) -> Result<(), CliError> {
+    let upgrades = plan_upgrade(work_dir).change_context(CliError::UpgradeError)?;
+    if upgrades.is_empty() {
+        println!("All nix files are up to date.");
+        return Ok(());
+    }
+
+    for upgrade in upgrades.iter() {
+        println!(
+            "{}: {} conflict(s)",
+            upgrade.path.display(),
+            upgrade.conflicts()
+        );
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    let stdin = io::stdin();
+    let mut files = vec![];
+    for upgrade in upgrades.iter() {
+        let mut failed = None;
+        let merged = upgrade.resolve(|current, new| {
+            if failed.is_some() {
+                return Resolution::Current;
+            }
+
+            println!("\n--- {} (on disk)\n{}", upgrade.path.display(), current);
+            println!("+++ {} (new template)\n{}", upgrade.path.display(), new);
+            match ask_resolution(&mut stdin.lock()) {
+                Ok(res) => res,
+                Err(e) => {
+                    failed = Some(e);
+                    Resolution::Current
+                }
+            }
+        });
+        if let Some(e) = failed {
+            return Err(e);
+        }
+
+        files.push((upgrade, merged));
+    }
+
+    apply_upgrade(
+        work_dir,
+        &files,
+        &format!(
+            "Upgrade templates to nixblitz {}",
+            env!("CARGO_PKG_VERSION")
+        ),
+    )
+    .change_context(CliError::UpgradeError)?;
+    println!("Upgraded and committed {} file(s).", files.len());
+
+    Ok(())
+}
+
+fn ask_resolution(input: &mut impl BufRead) -> Result<Resolution, CliError> {
+    loop {
+        print!("Keep [c]urrent, use [n]ew or keep [b]oth? ");
+        io::stdout()
+            .flush()
+            .change_context(CliError::UpgradeError)?;
+
+        let mut answer = String::new();
+        let read = input
+            .read_line(&mut answer)
+            .change_context(CliError::UpgradeError)?;
+        if read == 0 {
+            return Err(Report::new(CliError::UpgradeError)
+                .attach_printable("Aborted, no file was changed"));
+        }
+
+        match answer.trim() {
+            "c" => return Ok(Resolution::Current),
+            "n" => return Ok(Resolution::New),
+            "b" => return Ok(Resolution::Both),
+            _ => continue,
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::platform::{local_platform, resolve_platform, SystemPlatform};
+
+use crate::errors::CliError;
+
+/// The flake output building the VM of the x86_64 configuration
+const VM_FLAKE_OUTPUT: &str = "nixosConfigurations.nixblitzvm.config.system.build.vm";
+
+/// Where the built VM is linked to, relative to the work dir
+const VM_OUT_LINK: &str = "result-vm";
+
+/// Ports forwarded if none are given: SSH and nginx, which serves the web UI and the API
+pub const DEFAULT_FORWARDS: [PortForward; 2] = [
+    PortForward {
+        host: 2222,
+        guest: 22,
+    },
+    PortForward {
+        host: 8080,
+        guest: 80,
+    },
+];
+
+/// A TCP port on the host forwarded into the VM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortForward {
+    pub host: u16,
+    pub guest: u16,
+}
+
+impl FromStr for PortForward {
+    type Err = String;
+
+    /// Parses `HOST:GUEST`, e.g. `8080:80`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (host, guest) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected HOST:GUEST, got \"{}\"", s))?;
+        let parse = |p: &str| {
+            p.trim()
+                .parse::<u16>()
+                .map_err(|e| format!("invalid port \"{}\": {}", p, e))
+        };
+
+        Ok(Self {
+            host: parse(host)?,
+            guest: parse(guest)?,
+        })
+    }
+}
+
+impl fmt::Display for PortForward {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.guest)
+    }
+}
+
+/// Builds the `QEMU_NET_OPTS` value for the given forwards
+fn qemu_net_opts(forwards: &[PortForward]) -> String {
+    forwards
+        .iter()
+        .map(|f| format!("hostfwd=tcp::{}-:{}", f.host, f.guest))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Builds the VM configuration of the project in the given work dir and boots it in QEMU
+///
+/// The NixOS VM runner only works on x86_64 Linux hosts with nix installed.
+/// The host platform is detected unless `platform` is given or stored for
+/// the work dir, see [local_platform].
+/// The flake only sees files tracked by git, new files must be added first.
+pub fn vm_run_cmd(
+    work_dir: &Path,
+    memory: u32,
+    cores: u32,
+    forwards: &[PortForward],
+    platform: Option<SystemPlatform>,
+) -> Result<(), CliError> {
+    let platform = resolve_platform(platform.or_else(|| local_platform(work_dir)))
+        .change_context(CliError::VmError)?;
+    if platform != SystemPlatform::X86_64 {
+        return Err(Report::new(CliError::VmError).attach_printable(format!(
+            "The VM needs a {} host, this one is {}",
+            SystemPlatform::X86_64,
+            platform
+        )));
+    }
+
+    let flake_dir = work_dir.join("src");
+    let out_link = work_dir.join(VM_OUT_LINK);
+    println!("Building the VM from {}", flake_dir.display());
+
+    let status = Command::new("nix")
+        .arg("build")
+        .arg(format!("{}#{}", flake_dir.display(), VM_FLAKE_OUTPUT))
+        .arg("--out-link")
+        .arg(&out_link)
+        .status()
+        .change_context(CliError::VmError)
+        .attach_printable("Unable to run nix, is it installed?")?;
+    if !status.success() {
+        return Err(Report::new(CliError::VmError).attach_printable("nix build failed"));
+    }
+
+    let runner = find_runner(&out_link)?;
+    let forwards = if forwards.is_empty() {
+        &DEFAULT_FORWARDS[..]
+    } else {
+        forwards
+    };
+    for f in forwards {
+        println!(
+            "Forwarding localhost:{} to port {} of the VM",
+            f.host, f.guest
+        );
+    }
+
+    let status = Command::new(&runner)
+        .current_dir(work_dir)
+        .env("QEMU_NET_OPTS", qemu_net_opts(forwards))
+        .env("QEMU_OPTS", format!("-m {} -smp {}", memory, cores))
+        .status()
+        .change_context(CliError::VmError)
+        .attach_printable_lazy(|| format!("Unable to start {}", runner.display()))?;
+    if !status.success() {
+        return Err(Report::new(CliError::VmError)
+            .attach_printable(format!("The VM exited with {}", status)));
+    }
+
+    Ok(())
+}
+
+/// Finds the `run-<hostname>-vm` script of the built VM
+fn find_runner(out_link: &Path) -> Result<PathBuf, CliError> {
+    let bin = out_link.join("bin");
+    let entries = fs::read_dir(&bin)
+        .change_context(CliError::VmError)
+        .attach_printable_lazy(|| format!("Unable to read {}", bin.display()))?;
+
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("run-") && n.ends_with("-vm"))
+        })
+        .ok_or_else(|| {
+            Report::new(CliError::VmError)
+                .attach_printable(format!("No VM runner found in {}", bin.display()))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_port_forward() {
+        assert_eq!(
+            PortForward::from_str("8080:80"),
+            Ok(PortForward {
+                host: 8080,
+                guest: 80
+            })
+        );
+        assert!(PortForward::from_str("8080").is_err());
+        assert!(PortForward::from_str("8080:http").is_err());
+    }
+
+    #[test]
+    fn test_qemu_net_opts() {
+        assert_eq!(
+            qemu_net_opts(&DEFAULT_FORWARDS),
+            "hostfwd=tcp::2222-:22,hostfwd=tcp::8080-:80"
+        );
+    }
+}
@@ -14,9 +14,12 @@ use crate::{
 pub mod app_list;
 pub mod app_options;
 pub mod default_theme;
+pub mod error_popup;
 pub mod list_options;
 pub mod menu;
 pub mod password_input;
+pub mod quick_actions_popup;
+pub mod search_popup;
 pub mod theme;
 pub mod title;
 
@@ -9,7 +9,7 @@ use crate::{
 use crossterm::event::{MouseButton, MouseEventKind};
 use error_stack::Result;
 
-use nixblitzlib::apps::SupportedApps;
+use nixblitzlib::{apps::SupportedApps, registry};
 use ratatui::{prelude::*, widgets::*};
 use tokio::sync::mpsc::UnboundedSender;
 
@@ -66,10 +66,14 @@ impl AppList {
             self.send_selected_action(0);
         }
 
-        if *action == Action::NavUp {
-            self.state.select_previous();
-        } else if *action == Action::NavDown {
-            self.state.select_next();
+        match action {
+            Action::NavUp => self.state.select_previous(),
+            Action::NavDown => self.state.select_next(),
+            Action::NavFirst => self.state.select_first(),
+            Action::NavLast => self
+                .state
+                .select(SupportedApps::as_string_list().len().checked_sub(1)),
+            _ => (),
         }
 
         if pos != self.state.selected() {
@@ -145,7 +149,13 @@ impl Component for AppList {
 
     fn update(&mut self, ctx: &UpdateContext) -> Result<Option<Action>, CliError> {
         match ctx.action {
-            Action::NavUp | Action::NavDown => self.kb_select_item(&ctx.action),
+            Action::NavUp | Action::NavDown | Action::NavFirst | Action::NavLast => {
+                self.kb_select_item(&ctx.action)
+            }
+            // the project already selected the app, only the list follows
+            Action::JumpToOption(ref id) => self
+                .state
+                .select(registry::APPS.iter().position(|r| r.app == id.app)),
             _ => {}
         }
 
@@ -3,9 +3,11 @@ use std::{cell::RefCell, rc::Rc};
 
 use super::{
     list_options::{
-        base_option::OptionListItem, bool::BoolOptionComponent,
-        net_address::NetAddressOptionComponent, number::NumberOptionComponent,
-        password::PasswordOptionComponent, string_list::StringListOptionComponent,
+        amount::AmountOptionComponent, base_option::OptionListItem, bool::BoolOptionComponent,
+        code::CodeOptionComponent, manual_string_list::ManualStringListOptionComponent,
+        net_address::NetAddressOptionComponent, net_address_list::NetAddressListOptionComponent,
+        number::NumberOptionComponent, password::PasswordOptionComponent,
+        percent::PercentOptionComponent, string_list::StringListOptionComponent,
         text::TextOptionComponent,
     },
     theme::block,
@@ -24,7 +26,7 @@ use error_stack::{Report, Result, ResultExt};
 
 use indexmap::IndexMap;
 use nixblitzlib::{
-    app_option_data::option_data::{GetOptionId, OptionData},
+    app_option_data::option_data::{GetOptionId, OptionData, OptionId},
     apps::SupportedApps,
     project::Project,
 };
@@ -38,7 +40,12 @@ enum _Comp<'a> {
     Password(PasswordOptionComponent<'a>),
     Number(NumberOptionComponent<'a>),
     NetAddress(NetAddressOptionComponent<'a>),
+    NetAddressList(NetAddressListOptionComponent<'a>),
     Port(PortOptionComponent<'a>),
+    ManualStringList(ManualStringListOptionComponent<'a>),
+    Code(CodeOptionComponent<'a>),
+    Percent(PercentOptionComponent<'a>),
+    Amount(AmountOptionComponent<'a>),
 }
 
 impl<'a> fmt::Display for _Comp<'a> {
@@ -50,7 +57,12 @@ impl<'a> fmt::Display for _Comp<'a> {
             _Comp::Password(_) => write!(f, "_Comp::Password"),
             _Comp::Number(_) => write!(f, "_Comp::Number"),
             _Comp::NetAddress(_) => write!(f, "_Comp::NetAddress"),
+            _Comp::NetAddressList(_) => write!(f, "_Comp::NetAddressList"),
             _Comp::Port(_) => write!(f, "_Comp::Port"),
+            _Comp::ManualStringList(_) => write!(f, "_Comp::ManualStringList"),
+            _Comp::Code(_) => write!(f, "_Comp::Code"),
+            _Comp::Percent(_) => write!(f, "_Comp::Percent"),
+            _Comp::Amount(_) => write!(f, "_Comp::Amount"),
         }
     }
 }
@@ -116,6 +128,18 @@ impl<'a> _Comp<'a> {
         }
     }
 
+    fn get_net_address_list_mut(
+        &mut self,
+    ) -> Result<&mut NetAddressListOptionComponent<'a>, CliError> {
+        match self {
+            _Comp::NetAddressList(ref mut val) => Ok(val),
+            _ => Err(Report::new(CliError::OptionTypeMismatch(
+                "_Comp::NetAddressList".to_string(),
+                format!("{}", self),
+            ))),
+        }
+    }
+
     fn get_port_mut(&mut self) -> Result<&mut PortOptionComponent<'a>, CliError> {
         match self {
             _Comp::Port(ref mut val) => Ok(val),
@@ -126,6 +150,48 @@ impl<'a> _Comp<'a> {
         }
     }
 
+    fn get_manual_string_list_mut(
+        &mut self,
+    ) -> Result<&mut ManualStringListOptionComponent<'a>, CliError> {
+        match self {
+            _Comp::ManualStringList(ref mut val) => Ok(val),
+            _ => Err(Report::new(CliError::OptionTypeMismatch(
+                "_Comp::ManualStringList".to_string(),
+                format!("{}", self),
+            ))),
+        }
+    }
+
+    fn get_code_mut(&mut self) -> Result<&mut CodeOptionComponent<'a>, CliError> {
+        match self {
+            _Comp::Code(ref mut val) => Ok(val),
+            _ => Err(Report::new(CliError::OptionTypeMismatch(
+                "_Comp::Code".to_string(),
+                format!("{}", self),
+            ))),
+        }
+    }
+
+    fn get_percent_mut(&mut self) -> Result<&mut PercentOptionComponent<'a>, CliError> {
+        match self {
+            _Comp::Percent(ref mut val) => Ok(val),
+            _ => Err(Report::new(CliError::OptionTypeMismatch(
+                "_Comp::Percent".to_string(),
+                format!("{}", self),
+            ))),
+        }
+    }
+
+    fn get_amount_mut(&mut self) -> Result<&mut AmountOptionComponent<'a>, CliError> {
+        match self {
+            _Comp::Amount(ref mut val) => Ok(val),
+            _ => Err(Report::new(CliError::OptionTypeMismatch(
+                "_Comp::Amount".to_string(),
+                format!("{}", self),
+            ))),
+        }
+    }
+
     fn set_selected(&mut self, selected: bool) {
         match self {
             _Comp::Bool(comp) => comp.set_selected(selected),
@@ -134,7 +200,12 @@ impl<'a> _Comp<'a> {
             _Comp::Password(comp) => comp.set_selected(selected),
             _Comp::Number(comp) => comp.set_selected(selected),
             _Comp::NetAddress(comp) => comp.set_selected(selected),
+            _Comp::NetAddressList(comp) => comp.set_selected(selected),
             _Comp::Port(comp) => comp.set_selected(selected),
+            _Comp::ManualStringList(comp) => comp.set_selected(selected),
+            _Comp::Code(comp) => comp.set_selected(selected),
+            _Comp::Percent(comp) => comp.set_selected(selected),
+            _Comp::Amount(comp) => comp.set_selected(selected),
         }
     }
 }
@@ -177,7 +248,16 @@ impl<'a> OptionMap<'a> {
             _Comp::Password(password_option_component) => Ok(password_option_component),
             _Comp::Number(unum_option_component) => Ok(unum_option_component),
             _Comp::NetAddress(net_address_option_component) => Ok(net_address_option_component),
+            _Comp::NetAddressList(net_address_list_option_component) => {
+                Ok(net_address_list_option_component)
+            }
             _Comp::Port(port_option_component) => Ok(port_option_component),
+            _Comp::ManualStringList(manual_string_list_option_component) => {
+                Ok(manual_string_list_option_component)
+            }
+            _Comp::Code(code_option_component) => Ok(code_option_component),
+            _Comp::Percent(percent_option_component) => Ok(percent_option_component),
+            _Comp::Amount(amount_option_component) => Ok(amount_option_component),
         }
     }
 
@@ -192,7 +272,16 @@ impl<'a> OptionMap<'a> {
                 _Comp::Password(password_option_component) => password_option_component,
                 _Comp::Number(unum_option_component) => unum_option_component,
                 _Comp::NetAddress(net_address_option_component) => net_address_option_component,
+                _Comp::NetAddressList(net_address_list_option_component) => {
+                    net_address_list_option_component
+                }
                 _Comp::Port(port_option_component) => port_option_component,
+                _Comp::ManualStringList(manual_string_list_option_component) => {
+                    manual_string_list_option_component
+                }
+                _Comp::Code(code_option_component) => code_option_component,
+                _Comp::Percent(percent_option_component) => percent_option_component,
+                _Comp::Amount(amount_option_component) => amount_option_component,
             })
             .collect())
     }
@@ -214,7 +303,16 @@ impl<'a> OptionMap<'a> {
             _Comp::Password(password_option_component) => Ok(password_option_component),
             _Comp::Number(unum_option_component) => Ok(unum_option_component),
             _Comp::NetAddress(net_address_option_component) => Ok(net_address_option_component),
+            _Comp::NetAddressList(net_address_list_option_component) => {
+                Ok(net_address_list_option_component)
+            }
             _Comp::Port(port_option_component) => Ok(port_option_component),
+            _Comp::ManualStringList(manual_string_list_option_component) => {
+                Ok(manual_string_list_option_component)
+            }
+            _Comp::Code(code_option_component) => Ok(code_option_component),
+            _Comp::Percent(percent_option_component) => Ok(percent_option_component),
+            _Comp::Amount(amount_option_component) => Ok(amount_option_component),
         }
     }
 }
@@ -302,6 +400,13 @@ impl<'a> AppOptions<'a> {
                             index == selected,
                         )?)),
                     ),
+                    OptionData::NetAddressList(opt) => (
+                        opt.id().to_string(),
+                        Box::new(_Comp::NetAddressList(NetAddressListOptionComponent::new(
+                            opt,
+                            index == selected,
+                        )?)),
+                    ),
                     OptionData::Port(opt) => (
                         opt.id().to_string(),
                         Box::new(_Comp::Port(PortOptionComponent::new(
@@ -309,6 +414,33 @@ impl<'a> AppOptions<'a> {
                             index == selected,
                         )?)),
                     ),
+                    OptionData::ManualStringList(opt) => (
+                        opt.id().to_string(),
+                        Box::new(_Comp::ManualStringList(
+                            ManualStringListOptionComponent::new(opt, index == selected)?,
+                        )),
+                    ),
+                    OptionData::Code(opt) => (
+                        opt.id().to_string(),
+                        Box::new(_Comp::Code(CodeOptionComponent::new(
+                            opt,
+                            index == selected,
+                        )?)),
+                    ),
+                    OptionData::Percent(opt) => (
+                        opt.id().to_string(),
+                        Box::new(_Comp::Percent(PercentOptionComponent::new(
+                            opt,
+                            index == selected,
+                        )?)),
+                    ),
+                    OptionData::Amount(opt) => (
+                        opt.id().to_string(),
+                        Box::new(_Comp::Amount(AmountOptionComponent::new(
+                            opt,
+                            index == selected,
+                        )?)),
+                    ),
                 };
 
                 Ok(component)
@@ -320,6 +452,51 @@ impl<'a> AppOptions<'a> {
         Ok(OptionMap::new(list_of_options))
     }
 
+    /// Creates the rows for the options of the selected app
+    fn rebuild_option_items(
+        &mut self,
+        project: Rc<RefCell<Project>>,
+        selected: usize,
+    ) -> Result<(), CliError> {
+        self.options = Self::build_option_items(project, selected)?;
+        self.constraints = (0..self.options.map.len())
+            .map(|_| Constraint::Length(2))
+            .collect();
+
+        if let Some(tx) = &self.command_tx {
+            for c in self.options.get_components_mut()? {
+                c.register_action_handler(tx.clone())?;
+            }
+        } else {
+            error!("unable to set action sender to new component");
+        }
+
+        Ok(())
+    }
+
+    /// Shows the options of the app of the given option with the option
+    /// selected and scrolled into view
+    fn jump_to_option(
+        &mut self,
+        project: Rc<RefCell<Project>>,
+        id: &OptionId,
+    ) -> Result<(), CliError> {
+        let selected = project
+            .borrow_mut()
+            .get_app_options()
+            .change_context(CliError::Unknown)?
+            .iter()
+            .position(|o| o.id() == id)
+            .unwrap_or_default();
+
+        self.rebuild_option_items(project, selected)?;
+        self.selected = selected;
+        self.offset = selected.saturating_sub(self.max_num_items.saturating_sub(1));
+        self.update_title();
+
+        Ok(())
+    }
+
     fn update_option_items(&mut self, project: Rc<RefCell<Project>>) -> Result<(), CliError> {
         let app_option_list = project
             .clone()
@@ -329,36 +506,85 @@ impl<'a> AppOptions<'a> {
             .attach_printable("Unable to get app options")?;
 
         for option_data in app_option_list.iter() {
-            let option_id = &option_data.id().to_string();
-            let option_comp = self
-                .options
-                .map
-                .get_mut(option_id)
-                .ok_or(Report::new(CliError::OptionRetrievalError(
-                    option_id.to_string(),
-                )))?
-                .as_mut();
-
-            match option_data {
-                OptionData::Bool(data) => {
-                    option_comp.get_bool_mut()?.set_data(data);
-                }
-                OptionData::StringList(data) => {
-                    option_comp.get_string_list_mut()?.set_data(data);
-                }
-                OptionData::TextEdit(data) => {
-                    option_comp.get_edit_text_mut()?.set_data(data);
-                }
-                OptionData::PasswordEdit(data) => {
-                    option_comp.get_password_mut()?.set_data(data);
-                }
-                OptionData::NumberEdit(data) => {
-                    option_comp.get_number_mut()?.set_data(data);
-                }
-                OptionData::NetAddress(data) => option_comp.get_net_address_mut()?.set_data(data),
-                OptionData::Port(data) => {
-                    option_comp.get_port_mut()?.set_data(data);
-                }
+            self.set_option_data(option_data)?;
+        }
+
+        Ok(())
+    }
+
+    /// Updates only the rows of the given options. Options of other apps are
+    /// skipped, if none of them is shown the project isn't asked at all.
+    fn update_changed_option_items(
+        &mut self,
+        project: Rc<RefCell<Project>>,
+        changed: &[OptionId],
+    ) -> Result<(), CliError> {
+        if !changed
+            .iter()
+            .any(|id| self.options.map.contains_key(&id.to_string()))
+        {
+            return Ok(());
+        }
+
+        let app_option_list = project
+            .clone()
+            .borrow_mut()
+            .get_app_options()
+            .change_context(CliError::Unknown)
+            .attach_printable("Unable to get app options")?;
+
+        for option_data in app_option_list.iter().filter(|o| changed.contains(o.id())) {
+            self.set_option_data(option_data)?;
+        }
+
+        Ok(())
+    }
+
+    fn set_option_data(&mut self, option_data: &OptionData) -> Result<(), CliError> {
+        let option_id = &option_data.id().to_string();
+        let option_comp = self
+            .options
+            .map
+            .get_mut(option_id)
+            .ok_or(Report::new(CliError::OptionRetrievalError(
+                option_id.to_string(),
+            )))?
+            .as_mut();
+
+        match option_data {
+            OptionData::Bool(data) => {
+                option_comp.get_bool_mut()?.set_data(data);
+            }
+            OptionData::StringList(data) => {
+                option_comp.get_string_list_mut()?.set_data(data);
+            }
+            OptionData::TextEdit(data) => {
+                option_comp.get_edit_text_mut()?.set_data(data);
+            }
+            OptionData::PasswordEdit(data) => {
+                option_comp.get_password_mut()?.set_data(data);
+            }
+            OptionData::NumberEdit(data) => {
+                option_comp.get_number_mut()?.set_data(data);
+            }
+            OptionData::NetAddress(data) => option_comp.get_net_address_mut()?.set_data(data),
+            OptionData::NetAddressList(data) => {
+                option_comp.get_net_address_list_mut()?.set_data(data);
+            }
+            OptionData::Port(data) => {
+                option_comp.get_port_mut()?.set_data(data);
+            }
+            OptionData::ManualStringList(data) => {
+                option_comp.get_manual_string_list_mut()?.set_data(data);
+            }
+            OptionData::Code(data) => {
+                option_comp.get_code_mut()?.set_data(data);
+            }
+            OptionData::Percent(data) => {
+                option_comp.get_percent_mut()?.set_data(data);
+            }
+            OptionData::Amount(data) => {
+                option_comp.get_amount_mut()?.set_data(data);
             }
         }
 
@@ -381,6 +607,19 @@ impl<'a> AppOptions<'a> {
         match action {
             Action::NavUp => Ok(self.select_previous()?),
             Action::NavDown => Ok(self.select_next()?),
+            Action::NavFirst => {
+                // step through the rows, so the scroll offset follows
+                while self.selected > 0 {
+                    self.select_previous()?;
+                }
+                Ok(())
+            }
+            Action::NavLast => {
+                while self.selected + 1 < self.options.len() {
+                    self.select_next()?;
+                }
+                Ok(())
+            }
             _ => Ok(()),
         }
     }
@@ -556,7 +795,12 @@ impl<'a> AppOptions<'a> {
             _Comp::Password(c) => Ok(c.draw(frame, index, ctx)?),
             _Comp::Number(c) => Ok(c.draw(frame, index, ctx)?),
             _Comp::NetAddress(c) => Ok(c.draw(frame, index, ctx)?),
+            _Comp::NetAddressList(c) => Ok(c.draw(frame, index, ctx)?),
             _Comp::Port(c) => Ok(c.draw(frame, index, ctx)?),
+            _Comp::ManualStringList(c) => Ok(c.draw(frame, index, ctx)?),
+            _Comp::Code(c) => Ok(c.draw(frame, index, ctx)?),
+            _Comp::Percent(c) => Ok(c.draw(frame, index, ctx)?),
+            _Comp::Amount(c) => Ok(c.draw(frame, index, ctx)?),
         }
     }
 }
@@ -601,7 +845,7 @@ impl<'a> Component for AppOptions<'a> {
             && ctx.action != Action::TogglePasswordVisibility
         {
             match ctx.action {
-                Action::NavUp | Action::NavDown => {
+                Action::NavUp | Action::NavDown | Action::NavFirst | Action::NavLast => {
                     self.kb_select_item(&ctx.action)?;
                     return Ok(None);
                 }
@@ -613,20 +857,16 @@ impl<'a> Component for AppOptions<'a> {
                     self.update_option_items(ctx.project.clone())?;
                     return Ok(None);
                 }
+                Action::AppTabOptionsChanged(ref changed) => {
+                    self.update_changed_option_items(ctx.project.clone(), changed)?;
+                    return Ok(None);
+                }
                 Action::AppTabAppSelected(_) => {
-                    self.options = Self::build_option_items(ctx.project.clone(), 0)?;
-                    self.constraints = (0..self.options.map.len())
-                        .map(|_| Constraint::Length(2))
-                        .collect();
-
-                    if let Some(tx) = &self.command_tx {
-                        for c in self.options.get_components_mut()? {
-                            c.register_action_handler(tx.clone())?;
-                        }
-                    } else {
-                        error!("unable to set action sender to new component");
-                    }
-
+                    self.rebuild_option_items(ctx.project.clone(), 0)?;
+                    return Ok(None);
+                }
+                Action::JumpToOption(ref id) => {
+                    self.jump_to_option(ctx.project.clone(), id)?;
                     return Ok(None);
                 }
                 _ => return Ok(None),
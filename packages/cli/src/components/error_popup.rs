@@ -0,0 +1,94 @@
+use error_stack::{Result, ResultExt};
+use ratatui::{
+    layout::Rect,
+    widgets::{Clear, Paragraph, Wrap},
+    Frame,
+};
+use ratatui_macros::constraint;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    action::Action,
+    app_contexts::{RenderContext, UpdateContext},
+    components::{list_options::popup::center, theme::popup, Component},
+    errors::CliError,
+};
+
+/// Popup presenting an [Action::Error] message to the user.
+///
+/// Enter or Esc closes the popup.
+#[derive(Debug, Default)]
+pub struct ErrorPopup {
+    /// The message to show, the popup is open while this is set
+    message: Option<String>,
+
+    /// The sender for actions
+    action_tx: Option<UnboundedSender<Action>>,
+}
+
+impl ErrorPopup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn send(&self, action: Action) -> Result<(), CliError> {
+        let tx = self
+            .action_tx
+            .as_ref()
+            .ok_or(CliError::UnableToFindUnboundedSender)?;
+        tx.send(action)
+            .change_context(CliError::UnableToSendViaUnboundedSender)?;
+
+        Ok(())
+    }
+}
+
+impl Component for ErrorPopup {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<(), CliError> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn update(&mut self, ctx: &UpdateContext) -> Result<Option<Action>, CliError> {
+        match &ctx.action {
+            Action::Error(msg) => {
+                if self.message.is_none() {
+                    self.send(Action::PushModal(false))?;
+                }
+                self.message = Some(msg.clone());
+            }
+            Action::Enter | Action::Esc if self.message.is_some() => {
+                self.message = None;
+                self.send(Action::PopModal(false))?;
+            }
+            _ => (),
+        }
+
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, _: Rect, ctx: &RenderContext) -> Result<(), CliError> {
+        let Some(message) = &self.message else {
+            return Ok(());
+        };
+
+        let rect = frame.area();
+        let width = message
+            .lines()
+            .map(|l| l.len())
+            .max()
+            .unwrap_or(0)
+            .saturating_add(4)
+            .min(rect.width.saturating_sub(4) as usize) as u16;
+        let height = (message.lines().count() as u16).saturating_add(4);
+        let poparea = center(rect, constraint!(==width), constraint!(==height));
+        let paragraph = Paragraph::new(message.as_str())
+            .block(popup::block_focused(" Error ".into(), ctx))
+            .wrap(Wrap { trim: false });
+
+        frame.render_widget(Clear, poparea);
+        frame.render_widget(paragraph, poparea);
+
+        Ok(())
+    }
+}
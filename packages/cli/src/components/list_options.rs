@@ -1,10 +1,16 @@
+pub mod amount;
 pub mod base_option;
 pub mod bool;
+pub mod code;
+pub mod manual_string_list;
 pub mod net_address;
+pub mod net_address_list;
 pub mod number;
 pub mod number_popup;
 pub mod password;
 pub mod password_confirm_popup;
+pub mod percent;
+pub mod percent_popup;
 pub mod popup;
 pub mod popup_confirm_btn_bar;
 pub mod port;
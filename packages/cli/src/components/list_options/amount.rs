@@ -0,0 +1,184 @@
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    app_option_data::{
+        amount_data::{parse_amount, AmountOptionChangeData, AmountOptionData, AmountUnit},
+        option_data::{GetOptionId, OptionDataChangeNotification},
+    },
+    strings::OPTION_TITLES,
+};
+use ratatui::{layout::Rect, Frame};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    action::Action,
+    app_contexts::{RenderContext, UpdateContext},
+    components::Component,
+    errors::CliError,
+};
+
+use super::{
+    base_option::{draw_item, OptionListItem},
+    text_popup::TextInputPopup,
+};
+
+#[derive(Debug, Default)]
+pub struct AmountOptionComponent<'a> {
+    data: AmountOptionData,
+    title: &'a str,
+    subtitle: String,
+    selected: bool,
+    editing: bool,
+    action_tx: Option<UnboundedSender<Action>>,
+    popup: Option<Box<TextInputPopup<'a>>>,
+}
+
+impl<'a> AmountOptionComponent<'a> {
+    pub fn new(data: &AmountOptionData, selected: bool) -> Result<Self, CliError> {
+        let subtitle = data.formatted();
+        let title = OPTION_TITLES
+            .get(data.id())
+            .ok_or(CliError::OptionTitleRetrievalError(data.id().to_string()))?;
+
+        Ok(Self {
+            data: data.clone(),
+            title,
+            subtitle,
+            selected,
+            editing: false,
+            ..Default::default()
+        })
+    }
+
+    fn reset_popup(&mut self) {
+        self.popup = None;
+    }
+
+    /// The amount is edited as text with its unit, e.g. "0.5 BTC". Entering
+    /// it in the other unit switches the unit of the option.
+    fn build_popup(&mut self) -> Result<(), CliError> {
+        let mut pop = TextInputPopup::new(self.title, vec![self.data.formatted()], 1)?
+            .with_validator(|lines| {
+                let line = lines.first().map(String::as_str).unwrap_or_default();
+                match parse_amount(line, AmountUnit::Sats) {
+                    Some(_) => Ok(()),
+                    None => Err("Enter an amount like 50000 sats or 0.5 BTC".to_string()),
+                }
+            });
+        if let Some(h) = &self.action_tx {
+            pop.register_action_handler(h.clone())?;
+        }
+        self.popup = Some(Box::new(pop));
+
+        Ok(())
+    }
+
+    fn update_subtitle(&mut self) {
+        self.subtitle = self.data.formatted();
+    }
+
+    pub fn set_data(&mut self, data: &AmountOptionData) {
+        self.data = data.clone();
+        self.update_subtitle();
+    }
+}
+
+impl<'a> OptionListItem for AmountOptionComponent<'a> {
+    fn selected(&self) -> bool {
+        self.selected
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.data.dirty()
+    }
+
+    fn on_edit(&mut self) -> std::result::Result<(), Report<CliError>> {
+        if !self.editing {
+            self.editing = !self.editing;
+            self.build_popup()?;
+            if let Some(tx) = &self.action_tx {
+                let _ = tx.send(Action::PushModal(true));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Component for AmountOptionComponent<'a> {
+    fn update(&mut self, ctx: &UpdateContext) -> Result<Option<Action>, CliError> {
+        if ctx.action == Action::Esc && self.editing {
+            if let Some(ref mut p) = self.popup {
+                p.update(ctx)?;
+            }
+        } else if ctx.action == Action::PopModal(true) && self.editing {
+            self.editing = false;
+            if let Some(ref mut p) = self.popup {
+                let line = p.get_result().first().cloned().unwrap_or_default();
+                // the range is checked when the change is applied
+                if let Some((sats, unit)) = parse_amount(&line, self.data.unit()) {
+                    if let Some(tx) = &self.action_tx {
+                        tx.send(Action::AppTabOptionChangeProposal(
+                            OptionDataChangeNotification::Amount(AmountOptionChangeData::new(
+                                self.data.id().clone(),
+                                sats,
+                                unit,
+                            )),
+                        ))
+                        .change_context(CliError::Unknown)?
+                    }
+                }
+            }
+
+            self.update_subtitle();
+            self.reset_popup();
+        } else if ctx.action == Action::PopModal(false) && self.editing {
+            self.editing = false;
+            self.reset_popup();
+        }
+
+        Ok(None)
+    }
+
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<(), CliError> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn handle_key_event(
+        &mut self,
+        key: crossterm::event::KeyEvent,
+    ) -> Result<Option<Action>, CliError> {
+        if !self.editing {
+            return Ok(None);
+        }
+
+        if let Some(ref mut p) = self.popup {
+            return p.handle_key_event(key);
+        }
+
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, ctx: &RenderContext) -> Result<(), CliError> {
+        draw_item(
+            self.selected,
+            self.title,
+            &self.subtitle,
+            self.data.dirty(),
+            frame,
+            area,
+        )
+        .change_context(CliError::UnableToDrawComponent)
+        .attach_printable_lazy(|| format!("Drawing list item titled {}", self.title))?;
+
+        if let Some(ref mut p) = self.popup {
+            p.draw(frame, area, ctx)?;
+        }
+
+        Ok(())
+    }
+}
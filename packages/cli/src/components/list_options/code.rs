@@ -0,0 +1,185 @@
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    app_option_data::{
+        code_data::{CodeOptionChangeData, CodeOptionData},
+        option_data::{GetOptionId, OptionDataChangeNotification},
+    },
+    strings::OPTION_TITLES,
+};
+use ratatui::{layout::Rect, Frame};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    action::Action,
+    app_contexts::{RenderContext, UpdateContext},
+    components::Component,
+    errors::CliError,
+};
+
+use super::{
+    base_option::{draw_item, OptionListItem},
+    text_popup::TextInputPopup,
+};
+
+/// A multiline code or config snippet. The lib lints the snippet when the
+/// change is proposed and rejects it with an error popup if it is invalid.
+#[derive(Debug, Default)]
+pub struct CodeOptionComponent<'a> {
+    data: CodeOptionData,
+    title: &'a str,
+    subtitle: String,
+    selected: bool,
+    editing: bool,
+    action_tx: Option<UnboundedSender<Action>>,
+    popup: Option<Box<TextInputPopup<'a>>>,
+}
+
+impl<'a> CodeOptionComponent<'a> {
+    pub fn new(data: &CodeOptionData, selected: bool) -> Result<Self, CliError> {
+        let title = OPTION_TITLES
+            .get(data.id())
+            .ok_or(CliError::OptionTitleRetrievalError(data.id().to_string()))?;
+
+        let mut comp = Self {
+            data: data.clone(),
+            title,
+            selected,
+            editing: false,
+            ..Default::default()
+        };
+        comp.update_subtitle();
+
+        Ok(comp)
+    }
+
+    fn reset_popup(&mut self) {
+        self.popup = None;
+    }
+
+    fn build_popup(&mut self) -> Result<(), CliError> {
+        let mut pop = TextInputPopup::new(
+            &format!("{} ({})", self.title, self.data.language()),
+            self.data.value().lines().map(String::from).collect(),
+            self.data.max_lines(),
+        )?
+        .with_line_numbers();
+        if let Some(h) = &self.action_tx {
+            pop.register_action_handler(h.clone())?;
+        }
+        self.popup = Some(Box::new(pop));
+
+        Ok(())
+    }
+
+    /// Shows the first line and the number of lines of the snippet
+    fn update_subtitle(&mut self) {
+        let num_lines = self.data.value().lines().count();
+        self.subtitle = match self.data.value().lines().next() {
+            Some(first) if num_lines > 1 => format!("{} (+{} lines)", first, num_lines - 1),
+            Some(first) => first.to_string(),
+            None => String::new(),
+        };
+    }
+
+    pub fn set_data(&mut self, data: &CodeOptionData) {
+        self.data = data.clone();
+        self.update_subtitle();
+    }
+}
+
+impl<'a> OptionListItem for CodeOptionComponent<'a> {
+    fn selected(&self) -> bool {
+        self.selected
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.data.dirty()
+    }
+
+    fn on_edit(&mut self) -> std::result::Result<(), Report<CliError>> {
+        if !self.editing {
+            self.editing = !self.editing;
+            self.build_popup()?;
+            if let Some(tx) = &self.action_tx {
+                let _ = tx.send(Action::PushModal(true));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Component for CodeOptionComponent<'a> {
+    fn update(&mut self, ctx: &UpdateContext) -> Result<Option<Action>, CliError> {
+        if ctx.action == Action::Esc && self.editing {
+            if let Some(ref mut p) = self.popup {
+                p.update(ctx)?;
+            }
+        } else if ctx.action == Action::PopModal(true) && self.editing {
+            self.editing = false;
+            if let Some(ref mut p) = self.popup {
+                // the value is only applied once the project accepted it,
+                // set_data is called with the new value afterwards
+                if let Some(tx) = &self.action_tx {
+                    tx.send(Action::AppTabOptionChangeProposal(
+                        OptionDataChangeNotification::Code(CodeOptionChangeData::new(
+                            self.data.id().clone(),
+                            p.get_result().join("\n"),
+                        )),
+                    ))
+                    .change_context(CliError::Unknown)?
+                }
+            }
+
+            self.reset_popup();
+        } else if ctx.action == Action::PopModal(false) && self.editing {
+            self.editing = false;
+            self.reset_popup();
+        }
+
+        Ok(None)
+    }
+
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<(), CliError> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn handle_key_event(
+        &mut self,
+        key: crossterm::event::KeyEvent,
+    ) -> Result<Option<Action>, CliError> {
+        if !self.editing {
+            return Ok(None);
+        }
+
+        if let Some(ref mut p) = self.popup {
+            return p.handle_key_event(key);
+        }
+
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, ctx: &RenderContext) -> Result<(), CliError> {
+        draw_item(
+            self.selected,
+            self.title,
+            &self.subtitle,
+            self.data.dirty(),
+            frame,
+            area,
+        )
+        .change_context(CliError::UnableToDrawComponent)
+        .attach_printable_lazy(|| format!("Drawing list item titled {}", self.title))?;
+
+        if let Some(ref mut p) = self.popup {
+            p.draw(frame, area, ctx)?;
+        }
+
+        Ok(())
+    }
+}
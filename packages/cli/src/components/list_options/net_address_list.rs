@@ -0,0 +1,223 @@
+use std::str::FromStr;
+
+use error_stack::{Report, Result, ResultExt};
+use nixblitzlib::{
+    app_option_data::{
+        net_address_list_data::{
+            NetAddressListOptionChangeData, NetAddressListOptionData, NetAddressRange,
+        },
+        option_data::{GetOptionId, OptionDataChangeNotification},
+    },
+    strings::OPTION_TITLES,
+};
+use ratatui::{layout::Rect, Frame};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    action::Action,
+    app_contexts::{RenderContext, UpdateContext},
+    components::Component,
+    errors::CliError,
+};
+
+use super::{
+    base_option::{draw_item, OptionListItem},
+    text_popup::TextInputPopup,
+};
+
+/// Edits a list of IP addresses and networks in CIDR notation, one entry
+/// per line. The popup stays open until all entries are valid.
+#[derive(Debug, Default)]
+pub struct NetAddressListOptionComponent<'a> {
+    data: NetAddressListOptionData,
+    title: &'a str,
+    subtitle: String,
+    selected: bool,
+    editing: bool,
+    action_tx: Option<UnboundedSender<Action>>,
+    popup: Option<Box<TextInputPopup<'a>>>,
+}
+
+/// Parses the non empty lines of the popup
+fn parse_lines(lines: &[String]) -> std::result::Result<Vec<NetAddressRange>, String> {
+    lines
+        .iter()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            NetAddressRange::from_str(l)
+                .map_err(|_| format!("{} is not an IP address or a network like 10.0.0.0/8", l))
+        })
+        .collect()
+}
+
+impl<'a> NetAddressListOptionComponent<'a> {
+    pub fn new(data: &NetAddressListOptionData, selected: bool) -> Result<Self, CliError> {
+        let title = OPTION_TITLES
+            .get(data.id())
+            .ok_or(CliError::OptionTitleRetrievalError(data.id().to_string()))?;
+
+        let mut comp = Self {
+            data: data.clone(),
+            title,
+            selected,
+            editing: false,
+            ..Default::default()
+        };
+        comp.update_subtitle();
+
+        Ok(comp)
+    }
+
+    fn reset_popup(&mut self) {
+        self.popup = None;
+    }
+
+    fn build_popup(&mut self) -> Result<(), CliError> {
+        let lines = self.data.value().iter().map(|v| v.to_string()).collect();
+        let mut pop = TextInputPopup::new(self.title, lines, u16::MAX)?
+            .with_validator(|lines| parse_lines(lines).map(|_| ()));
+        if let Some(h) = &self.action_tx {
+            pop.register_action_handler(h.clone())?;
+        }
+        self.popup = Some(Box::new(pop));
+
+        Ok(())
+    }
+
+    fn update_subtitle(&mut self) {
+        self.subtitle = match self.data.value().len() {
+            0 => "none".to_string(),
+            1 => self.data.value()[0].to_string(),
+            n => format!("{} (+{} more)", self.data.value()[0], n - 1),
+        };
+    }
+
+    pub fn set_data(&mut self, data: &NetAddressListOptionData) {
+        self.data = data.clone();
+        self.update_subtitle();
+    }
+}
+
+impl<'a> OptionListItem for NetAddressListOptionComponent<'a> {
+    fn selected(&self) -> bool {
+        self.selected
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.data.dirty()
+    }
+
+    fn on_edit(&mut self) -> std::result::Result<(), Report<CliError>> {
+        if !self.editing {
+            self.editing = !self.editing;
+            self.build_popup()?;
+            if let Some(tx) = &self.action_tx {
+                let _ = tx.send(Action::PushModal(true));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Component for NetAddressListOptionComponent<'a> {
+    fn update(&mut self, ctx: &UpdateContext) -> Result<Option<Action>, CliError> {
+        if ctx.action == Action::Esc && self.editing {
+            if let Some(ref mut p) = self.popup {
+                p.update(ctx)?;
+            }
+        } else if ctx.action == Action::PopModal(true) && self.editing {
+            self.editing = false;
+            if let Some(ref mut p) = self.popup {
+                let value = parse_lines(&p.get_result())
+                    .map_err(CliError::StringParseError)
+                    .attach_printable_lazy(|| format!("Editing {}", self.title))?;
+                self.data.set_value(value);
+
+                if let Some(tx) = &self.action_tx {
+                    tx.send(Action::AppTabOptionChangeProposal(
+                        OptionDataChangeNotification::NetAddressList(
+                            NetAddressListOptionChangeData::new(
+                                self.data.id().clone(),
+                                self.data.value().clone(),
+                            ),
+                        ),
+                    ))
+                    .change_context(CliError::Unknown)?
+                }
+            }
+
+            self.update_subtitle();
+            self.reset_popup();
+        } else if ctx.action == Action::PopModal(false) && self.editing {
+            self.editing = false;
+            self.reset_popup();
+        }
+
+        Ok(None)
+    }
+
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<(), CliError> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn handle_key_event(
+        &mut self,
+        key: crossterm::event::KeyEvent,
+    ) -> Result<Option<Action>, CliError> {
+        if !self.editing {
+            return Ok(None);
+        }
+
+        if let Some(ref mut p) = self.popup {
+            return p.handle_key_event(key);
+        }
+
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, ctx: &RenderContext) -> Result<(), CliError> {
+        draw_item(
+            self.selected,
+            self.title,
+            &self.subtitle,
+            self.data.dirty(),
+            frame,
+            area,
+        )
+        .change_context(CliError::UnableToDrawComponent)
+        .attach_printable_lazy(|| format!("Drawing list item titled {}", self.title))?;
+
+        if let Some(ref mut p) = self.popup {
+            p.draw(frame, area, ctx)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lines() {
+        let lines = vec![
+            "192.168.0.0/16".to_string(),
+            "".to_string(),
+            " 127.0.0.1 ".to_string(),
+        ];
+        let parsed = parse_lines(&lines).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[1].to_string(), "127.0.0.1");
+
+        let err = parse_lines(&["10.0.0.0/33".to_string()]).unwrap_err();
+        assert!(err.contains("10.0.0.0/33"));
+    }
+}
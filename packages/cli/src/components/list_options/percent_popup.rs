@@ -0,0 +1,132 @@
+use crossterm::event::KeyCode;
+use error_stack::Result;
+use nixblitzlib::app_option_data::percent_data::{PercentOptionData, MAX_PERCENT};
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    widgets::{Clear, Gauge},
+    Frame,
+};
+use ratatui_macros::constraint;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    action::Action,
+    app_contexts::{RenderContext, UpdateContext},
+    components::{theme::popup, Component},
+    errors::CliError,
+};
+
+use super::popup::center;
+
+/// A slider for percent values.
+///
+/// The arrow keys move it by the step of the option, digits type a value.
+#[derive(Debug)]
+pub struct PercentInputPopup {
+    title: String,
+    data: PercentOptionData,
+    value: u8,
+
+    /// Whether the next digit starts a new value instead of appending
+    typing: bool,
+    action_tx: Option<UnboundedSender<Action>>,
+}
+
+impl PercentInputPopup {
+    pub fn new(title: &str, data: &PercentOptionData) -> Result<Self, CliError> {
+        Ok(Self {
+            title: format!(" {} ", title),
+            data: data.clone(),
+            value: data.value(),
+            typing: false,
+            action_tx: None,
+        })
+    }
+
+    pub fn get_result(&self) -> u8 {
+        self.value
+    }
+
+    fn _on_popup_confirm(&self, accepted: bool) {
+        if let Some(action_tx) = &self.action_tx {
+            let _ = action_tx.send(Action::PopModal(accepted));
+        }
+    }
+
+    fn on_digit(&mut self, digit: u8) {
+        let value = if self.typing {
+            u16::from(self.value) * 10 + u16::from(digit)
+        } else {
+            u16::from(digit)
+        };
+
+        self.value = value.min(u16::from(MAX_PERCENT)) as u8;
+        self.typing = true;
+    }
+}
+
+impl Component for PercentInputPopup {
+    fn update(&mut self, ctx: &UpdateContext) -> Result<Option<Action>, CliError> {
+        if ctx.action == Action::Esc {
+            if let Some(action_tx) = &self.action_tx {
+                let _ = action_tx.send(Action::PopModal(false));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<(), CliError> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn handle_key_event(
+        &mut self,
+        key: crossterm::event::KeyEvent,
+    ) -> Result<Option<Action>, CliError> {
+        match key.code {
+            KeyCode::Enter => self._on_popup_confirm(true),
+            KeyCode::Right | KeyCode::Up => {
+                self.value = self.data.stepped(self.value, true);
+                self.typing = false;
+            }
+            KeyCode::Left | KeyCode::Down => {
+                self.value = self.data.stepped(self.value, false);
+                self.typing = false;
+            }
+            KeyCode::Home => self.value = 0,
+            KeyCode::End => self.value = MAX_PERCENT,
+            KeyCode::Backspace => {
+                self.value /= 10;
+                self.typing = true;
+            }
+            KeyCode::Char(c) => {
+                if let Some(digit) = c.to_digit(10) {
+                    self.on_digit(digit as u8);
+                }
+            }
+            _ => {}
+        }
+
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, _: Rect, ctx: &RenderContext) -> Result<(), CliError> {
+        let rect = frame.area();
+        let poparea = center(frame.area(), constraint!(<=rect.width-10), constraint!(==3));
+
+        let colors = ctx.theme_data.clone().borrow().colors.clone();
+        let gauge = Gauge::default()
+            .block(popup::block_focused(self.title.clone(), ctx))
+            .gauge_style(Style::new().fg(colors.primary).bg(colors.surface_container))
+            .percent(u16::from(self.value))
+            .label(format!("{}%", self.value));
+
+        frame.render_widget(Clear, poparea);
+        frame.render_widget(gauge, poparea);
+
+        Ok(())
+    }
+}
@@ -116,6 +116,8 @@ impl Component for StringListPopup {
         match ctx.action {
             Action::NavUp => self.state.select_previous(),
             Action::NavDown => self.state.select_next(),
+            Action::NavFirst => self.state.select_first(),
+            Action::NavLast => self.state.select(self.options.len().checked_sub(1)),
             Action::PageUp => self.state.scroll_up_by(10),
             Action::PageDown => self.state.scroll_down_by(10),
             Action::Enter => self.handle_accept()?,
@@ -2,6 +2,7 @@ use crossterm::event::KeyCode;
 use error_stack::{Result, ResultExt};
 use ratatui::{
     layout::{Margin, Rect},
+    style::{Modifier, Style},
     widgets::{Clear, Scrollbar, ScrollbarOrientation, ScrollbarState},
     Frame,
 };
@@ -26,6 +27,10 @@ enum PopupFocus {
     Cancel,
 }
 
+/// Checks the lines before they are accepted, returns the message to show
+/// if they are invalid
+pub type LineValidator = fn(&[String]) -> std::result::Result<(), String>;
+
 /// Represents a text input widget for single and multi line strings.
 #[derive(Debug, Default)]
 pub struct TextInputPopup<'a> {
@@ -37,6 +42,8 @@ pub struct TextInputPopup<'a> {
     action_tx: Option<UnboundedSender<Action>>,
     cursor_pos: usize,
     focus: PopupFocus,
+    validator: Option<LineValidator>,
+    error_text: String,
 }
 
 impl<'a> TextInputPopup<'a> {
@@ -50,6 +57,19 @@ impl<'a> TextInputPopup<'a> {
         })
     }
 
+    /// Shows line numbers in front of each line, e.g. for code snippets
+    pub fn with_line_numbers(mut self) -> Self {
+        self.text_area
+            .set_line_number_style(Style::default().add_modifier(Modifier::DIM));
+        self
+    }
+
+    /// Keeps the popup open until the validator accepts the lines
+    pub fn with_validator(mut self, validator: LineValidator) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
     pub fn get_result(&mut self) -> Vec<String> {
         self.text_area.lines().to_vec()
     }
@@ -71,7 +91,16 @@ impl<'a> TextInputPopup<'a> {
         }
     }
 
-    fn _on_popup_confirm(&self, accepted: bool) {
+    fn _on_popup_confirm(&mut self, accepted: bool) {
+        if accepted {
+            if let Some(validator) = self.validator {
+                if let Err(e) = validator(self.text_area.lines()) {
+                    self.error_text = e;
+                    return;
+                }
+            }
+        }
+
         if let Some(action_tx) = &self.action_tx {
             let _ = action_tx.send(Action::PopModal(accepted));
         }
@@ -117,6 +146,7 @@ impl Component for TextInputPopup<'_> {
             return Ok(None);
         }
 
+        self.error_text.clear();
         self.text_area.input(key);
         let (row, _) = self.text_area.cursor();
         self.cursor_pos = row;
@@ -198,6 +228,25 @@ impl Component for TextInputPopup<'_> {
             )?;
         }
 
+        if !self.error_text.is_empty() {
+            let y = if self.max_lines > 1 {
+                poparea.bottom() + 1
+            } else {
+                poparea.bottom()
+            };
+            if y < rect.bottom() {
+                frame.render_widget(
+                    popup::error_text::default(&self.error_text, ctx),
+                    Rect {
+                        x: poparea.left(),
+                        y,
+                        width: poparea.width,
+                        height: 1,
+                    },
+                );
+            }
+        }
+
         Ok(())
     }
 }
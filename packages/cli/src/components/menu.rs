@@ -20,6 +20,7 @@ pub enum MenuItem {
     Apps,
     Settings,
     Actions,
+    Logs,
     Help,
 }
 
@@ -29,7 +30,8 @@ impl From<MenuItem> for usize {
             MenuItem::Apps => 0,
             MenuItem::Settings => 1,
             MenuItem::Actions => 2,
-            MenuItem::Help => 3,
+            MenuItem::Logs => 3,
+            MenuItem::Help => 4,
         }
     }
 }
@@ -40,6 +42,7 @@ impl From<&str> for MenuItem {
             "Apps" => MenuItem::Apps,
             "Settings" => MenuItem::Settings,
             "Actions" => MenuItem::Actions,
+            "Logs" => MenuItem::Logs,
             "Help" => MenuItem::Help,
             _ => MenuItem::Apps,
         }
@@ -49,9 +52,9 @@ impl From<&str> for MenuItem {
 // We know that the height of a hitbox of a menu entry is only
 // one line, so we store only the start and the end of the entry
 //
-// RaspiBlitz | Apps | Settings | Actions | Help
+// RaspiBlitz | Apps | Settings | Actions | Logs | Help
 //1234567890123
-//   Offset   |M    M|M        M|M       M|M    M
+//   Offset   |M    M|M        M|M       M|M    M|M    M
 #[derive(Debug, Default)]
 struct Hitbox {
     start: u16,
@@ -77,7 +80,7 @@ pub struct Menu {
 impl Menu {
     pub fn new(offset: u16) -> Self {
         let mut instance = Self::default();
-        let entries = ["Apps", "Settings", "Actions", "Help"];
+        let entries = ["Apps", "Settings", "Actions", "Logs", "Help"];
 
         let mut curr = offset;
         for entry in entries {
@@ -125,6 +128,7 @@ impl Component for Menu {
             Action::NavAppsTab => self.set_active_item(MenuItem::Apps),
             Action::NavSettingsTab => self.set_active_item(MenuItem::Settings),
             Action::NavActionsTab => self.set_active_item(MenuItem::Actions),
+            Action::NavLogsTab => self.set_active_item(MenuItem::Logs),
             Action::NavHelpTab => self.set_active_item(MenuItem::Help),
             _ => {}
         }
@@ -161,6 +165,7 @@ impl Component for Menu {
                                     MenuItem::Apps => Action::NavAppsTab,
                                     MenuItem::Settings => Action::NavSettingsTab,
                                     MenuItem::Actions => Action::NavActionsTab,
+                                    MenuItem::Logs => Action::NavLogsTab,
                                     MenuItem::Help => Action::NavHelpTab,
                                 })
                                 .attach_printable_lazy(|| "Unable to send mouse action")
@@ -0,0 +1,163 @@
+use cli_log::error;
+use error_stack::{Result, ResultExt};
+use ratatui::{
+    layout::Rect,
+    widgets::{Clear, List, ListState, Paragraph},
+    Frame,
+};
+use ratatui_macros::constraint;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    action::Action,
+    app_contexts::{RenderContext, UpdateContext},
+    components::{list_options::popup::center, theme::popup, Component},
+    config::get_config_dir,
+    errors::CliError,
+    quick_actions::{load_quick_actions, QuickAction, QUICK_ACTIONS_FILE_NAME},
+};
+
+/// Popup menu listing the user defined quick actions.
+///
+/// Selecting an action runs it in the background and switches to the logs
+/// page, which shows its output while it runs. Esc closes the popup.
+#[derive(Debug, Default)]
+pub struct QuickActionsPopup {
+    /// Whether the popup is currently shown
+    open: bool,
+
+    /// The actions loaded from the config directory
+    actions: Vec<QuickAction>,
+
+    /// Maintains the current selection state within the list
+    state: ListState,
+
+    /// The sender for actions
+    action_tx: Option<UnboundedSender<Action>>,
+}
+
+impl QuickActionsPopup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn send(&self, action: Action) -> Result<(), CliError> {
+        let tx = self
+            .action_tx
+            .as_ref()
+            .ok_or(CliError::UnableToFindUnboundedSender)?;
+        tx.send(action)
+            .change_context(CliError::UnableToSendViaUnboundedSender)?;
+
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<(), CliError> {
+        // Reload every time so edits to the file are picked up without a restart
+        self.actions = match load_quick_actions(&get_config_dir()) {
+            Ok(a) => a,
+            Err(e) => {
+                error!("{:?}", e);
+                return self.send(Action::Error(e.to_string()));
+            }
+        };
+        self.state.select(Some(0));
+        self.open = true;
+        self.send(Action::PushModal(false))
+    }
+
+    fn close(&mut self) -> Result<(), CliError> {
+        self.open = false;
+        self.send(Action::PopModal(false))
+    }
+
+    fn run_selected(&mut self) -> Result<(), CliError> {
+        let Some(action) = self.state.selected().and_then(|i| self.actions.get(i)) else {
+            return Ok(());
+        };
+
+        let tx = self
+            .action_tx
+            .clone()
+            .ok_or(CliError::UnableToFindUnboundedSender)?;
+        // the task keeps running on its own, the output arrives as actions
+        action.spawn(tx);
+        self.close()?;
+        self.send(Action::NavLogsTab)
+    }
+}
+
+impl Component for QuickActionsPopup {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<(), CliError> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn update(&mut self, ctx: &UpdateContext) -> Result<Option<Action>, CliError> {
+        if !self.open {
+            if ctx.action == Action::ToggleQuickActions && !ctx.modal_open {
+                self.open()?;
+            }
+
+            return Ok(None);
+        }
+
+        match ctx.action {
+            Action::ToggleQuickActions | Action::Esc => self.close()?,
+            Action::Enter => self.run_selected()?,
+            Action::NavUp => self.state.select_previous(),
+            Action::NavDown => self.state.select_next(),
+            Action::NavFirst => self.state.select_first(),
+            Action::NavLast => self.state.select(self.actions.len().checked_sub(1)),
+            _ => (),
+        }
+
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, _: Rect, ctx: &RenderContext) -> Result<(), CliError> {
+        if !self.open {
+            return Ok(());
+        }
+
+        let rect = frame.area();
+        if self.actions.is_empty() {
+            let msg = format!(
+                "No quick actions defined.\nAdd them to {}",
+                get_config_dir().join(QUICK_ACTIONS_FILE_NAME).display()
+            );
+            let width = msg.lines().map(|l| l.len()).max().unwrap_or(0) as u16 + 4;
+            let poparea = center(rect, constraint!(==width), constraint!(==4));
+            let paragraph =
+                Paragraph::new(msg).block(popup::block_focused(" Quick Actions ".into(), ctx));
+
+            frame.render_widget(Clear, poparea);
+            frame.render_widget(paragraph, poparea);
+
+            return Ok(());
+        }
+
+        let max_len = self
+            .actions
+            .iter()
+            .map(|a| a.label.len())
+            .max()
+            .unwrap_or(0);
+        let height = u16::try_from(self.actions.len())
+            .change_context(CliError::ArgumentError)?
+            .saturating_add(2);
+        let width = u16::try_from(max_len)
+            .change_context(CliError::MaxDisplayNameLengthReached)?
+            .saturating_add(12);
+
+        let poparea = center(rect, constraint!(==width), constraint!(==height));
+        let list = List::new(self.actions.iter().map(|a| a.label.clone()))
+            .block(popup::block_focused(" Quick Actions ".into(), ctx))
+            .highlight_symbol("> ");
+
+        frame.render_widget(Clear, poparea);
+        frame.render_stateful_widget(list, poparea, &mut self.state);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use error_stack::{Result, ResultExt};
+use nixblitzlib::search::{SearchEntry, SearchIndex};
+use ratatui::{
+    layout::{Direction, Layout, Rect},
+    text::Line,
+    widgets::{Clear, List, ListState, Paragraph},
+    Frame,
+};
+use ratatui_macros::{constraint, constraints};
+use tokio::sync::mpsc::UnboundedSender;
+use tui_textarea::TextArea;
+
+use crate::{
+    action::Action,
+    app_contexts::{RenderContext, UpdateContext},
+    components::{list_options::popup::center, theme::popup, Component},
+    errors::CliError,
+};
+
+/// The longest value shown next to an option, longer ones are cut
+const MAX_VALUE_LEN: usize = 30;
+
+/// Fuzzy finder for the options of all apps.
+///
+/// The results update while typing, Enter jumps to the selected option in
+/// the apps tab. The index is built when the popup opens, so it shows the
+/// current values.
+#[derive(Debug, Default)]
+pub struct SearchPopup<'a> {
+    /// Whether the popup is currently shown
+    open: bool,
+
+    /// The query typed so far
+    input: TextArea<'a>,
+
+    /// The options of all apps
+    index: SearchIndex,
+
+    /// The entries matching the query, the best match first
+    results: Vec<SearchEntry>,
+
+    /// Maintains the current selection state within the results
+    state: ListState,
+
+    /// The sender for actions
+    action_tx: Option<UnboundedSender<Action>>,
+}
+
+impl SearchPopup<'_> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn send(&self, action: Action) -> Result<(), CliError> {
+        let tx = self
+            .action_tx
+            .as_ref()
+            .ok_or(CliError::UnableToFindUnboundedSender)?;
+        tx.send(action)
+            .change_context(CliError::UnableToSendViaUnboundedSender)?;
+
+        Ok(())
+    }
+
+    fn open(&mut self, ctx: &UpdateContext) -> Result<(), CliError> {
+        self.index = SearchIndex::new(&ctx.project.borrow());
+        self.input = TextArea::default();
+        self.filter();
+        self.open = true;
+        self.send(Action::PushModal(true))
+    }
+
+    fn close(&mut self, accepted: bool) -> Result<(), CliError> {
+        self.open = false;
+        self.results.clear();
+        self.send(Action::PopModal(accepted))
+    }
+
+    fn filter(&mut self) {
+        let query = self.input.lines().join(" ");
+        self.results = self.index.search(&query).into_iter().cloned().collect();
+        self.state.select((!self.results.is_empty()).then_some(0));
+    }
+
+    fn jump_to_selected(&mut self) -> Result<(), CliError> {
+        let Some(entry) = self.state.selected().and_then(|i| self.results.get(i)) else {
+            return Ok(());
+        };
+
+        let id = entry.id.clone();
+        self.close(true)?;
+        self.send(Action::JumpToOption(id))
+    }
+}
+
+impl Component for SearchPopup<'_> {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<(), CliError> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    /// The popup takes all input while it is shown, only Esc arrives as
+    /// an action
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>, CliError> {
+        if !self.open {
+            return Ok(None);
+        }
+
+        match key.code {
+            KeyCode::Enter => self.jump_to_selected()?,
+            KeyCode::Up => self.state.select_previous(),
+            KeyCode::Down => self.state.select_next(),
+            KeyCode::Esc => (),
+            _ => {
+                if self.input.input(key) {
+                    self.filter();
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn update(&mut self, ctx: &UpdateContext) -> Result<Option<Action>, CliError> {
+        if !self.open {
+            if ctx.action == Action::ToggleSearch && !ctx.modal_open {
+                self.open(ctx)?;
+            }
+
+            return Ok(None);
+        }
+
+        if ctx.action == Action::Esc {
+            self.close(false)?;
+        }
+
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, _: Rect, ctx: &RenderContext) -> Result<(), CliError> {
+        if !self.open {
+            return Ok(());
+        }
+
+        let rect = frame.area();
+        let poparea = center(
+            rect,
+            constraint!(<=rect.width.saturating_sub(10)),
+            constraint!(==rect.height.saturating_sub(4)),
+        );
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(constraints![==3, *=1])
+            .split(poparea);
+
+        self.input
+            .set_block(popup::block_focused(" Search options ".into(), ctx));
+
+        let title = format!(" {}/{} ", self.results.len(), self.index.entries().len());
+        frame.render_widget(Clear, poparea);
+        frame.render_widget(&self.input, layout[0]);
+        if self.results.is_empty() {
+            let paragraph = Paragraph::new("No option matches").block(popup::block(title, ctx));
+            frame.render_widget(paragraph, layout[1]);
+            return Ok(());
+        }
+
+        let width = self
+            .results
+            .iter()
+            .map(|e| e.identifier.len())
+            .max()
+            .unwrap_or_default();
+        let items = self.results.iter().map(|e| {
+            Line::from(format!(
+                "{:width$}  {} = {}",
+                e.identifier,
+                e.title.unwrap_or(e.id.option.as_str()),
+                e.short_value(MAX_VALUE_LEN),
+            ))
+        });
+        let list = List::new(items)
+            .block(popup::block(title, ctx))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, layout[1], &mut self.state);
+
+        Ok(())
+    }
+}
@@ -111,16 +111,19 @@ impl<'de> Deserialize<'de> for KeyBindings {
     {
         let parsed_map = HashMap::<Mode, HashMap<String, Action>>::deserialize(deserializer)?;
 
-        let keybindings = parsed_map
-            .into_iter()
-            .map(|(mode, inner_map)| {
-                let converted_inner_map = inner_map
-                    .into_iter()
-                    .map(|(key_str, cmd)| (parse_key_sequence(&key_str).unwrap(), cmd))
-                    .collect();
-                (mode, converted_inner_map)
-            })
-            .collect();
+        let mut keybindings = HashMap::new();
+        for (mode, inner_map) in parsed_map {
+            // a typo in the user keymap must not crash the TUI
+            let converted_inner_map = inner_map
+                .into_iter()
+                .map(|(key_str, cmd)| {
+                    parse_key_sequence(&key_str)
+                        .map(|keys| (keys, cmd))
+                        .map_err(serde::de::Error::custom)
+                })
+                .collect::<Result<_, D::Error>>()?;
+            keybindings.insert(mode, converted_inner_map);
+        }
 
         Ok(KeyBindings(keybindings))
     }
@@ -234,6 +237,54 @@ pub fn parse_key_sequence(raw: &str) -> Result<Vec<KeyEvent>, String> {
     sequences.into_iter().map(parse_key_event).collect()
 }
 
+/// Formats a key the way it is written in the keymap, e.g. `<Ctrl-k>`
+pub fn key_event_to_string(key: &KeyEvent) -> String {
+    let mut modifiers = key.modifiers;
+    let code = match key.code {
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        KeyCode::BackTab => {
+            // implied by the key, see parse_key_code_with_modifiers
+            modifiers.remove(KeyModifiers::SHIFT);
+            "backtab".to_string()
+        }
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Delete => "delete".to_string(),
+        KeyCode::Insert => "insert".to_string(),
+        KeyCode::F(n) => format!("f{n}"),
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_ascii_lowercase().to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        other => format!("{other:?}").to_ascii_lowercase(),
+    };
+
+    let mut res = String::new();
+    for (modifier, name) in [
+        (KeyModifiers::CONTROL, "Ctrl-"),
+        (KeyModifiers::ALT, "Alt-"),
+        (KeyModifiers::SHIFT, "Shift-"),
+    ] {
+        if modifiers.contains(modifier) {
+            res.push_str(name);
+        }
+    }
+
+    format!("<{res}{code}>")
+}
+
+/// Formats a key sequence the way it is written in the keymap, e.g. `<g><g>`
+pub fn key_sequence_to_string(keys: &[KeyEvent]) -> String {
+    keys.iter().map(key_event_to_string).collect()
+}
+
 #[derive(Clone, Debug, Default, Deref, DerefMut)]
 pub struct Styles(pub HashMap<Mode, HashMap<String, Style>>);
 
@@ -488,6 +539,31 @@ mod tests {
         assert!(parse_key_event("ctrl-invalid-key").is_err());
     }
 
+    #[test]
+    fn test_key_sequence_to_string() {
+        for raw in [
+            "<q>",
+            "<Ctrl-k>",
+            "<Shift-g>",
+            "<g><g>",
+            "<PageUp>",
+            "<Ctrl-Alt-enter>",
+            "<space>",
+            "<f5>",
+        ] {
+            let keys = parse_key_sequence(raw).unwrap();
+            let formatted = key_sequence_to_string(&keys);
+            assert_eq!(formatted.to_lowercase(), raw.to_lowercase());
+            assert_eq!(parse_key_sequence(&formatted).unwrap(), keys);
+        }
+    }
+
+    #[test]
+    fn test_invalid_user_keymap() {
+        let res: Result<KeyBindings, _> = json5::from_str(r#"{ "Home": { "<nope>": "Quit" } }"#);
+        assert!(res.is_err());
+    }
+
     #[test]
     fn test_case_insensitivity() {
         assert_eq!(
@@ -4,7 +4,7 @@ use cli_log::error;
 use error_stack::Report;
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum CliError {
     #[error("Unknown error")]
     Unknown,
@@ -36,6 +36,70 @@ pub enum CliError {
     OptionTypeMismatch(String, String),
     #[error("{}", .0 )]
     StringParseError(String),
+    #[error("Unable to load quick actions from {}", .0)]
+    QuickActionsLoadError(String),
+    #[error("Unable to run quick action {}", .0)]
+    QuickActionRunError(String),
+    #[error("Found {} conflicting setting(s)", .0)]
+    ConfigConflicts(usize),
+    #[error("Unable to recover the project")]
+    RecoveryError,
+    #[error("No documentation found for topic {}", .0)]
+    DocTopicNotFound(String),
+    #[error("Unable to build or run the VM")]
+    VmError,
+    #[error("Unable to manage the data snapshots")]
+    SnapshotError,
+    #[error("Unable to manage the config backups")]
+    BackupError,
+    #[error("Unable to watch the project files")]
+    FileWatcherError,
+    #[error("The lightning node check failed")]
+    NodeCheckError,
+    #[error("Unable to upgrade the templates")]
+    UpgradeError,
+    #[error("Unable to run the system backup")]
+    SystemBackupError,
+    #[error("Unable to build the installer image")]
+    IsoError,
+    #[error("Unable to benchmark the disk")]
+    DiskBenchmarkError,
+    #[error("Unable to set up the secrets")]
+    SecretsError,
+    #[error("Unable to reset the project")]
+    FactoryResetError,
+    #[error("Unable to list the pending changes")]
+    DiffError,
+    #[error("Unable to read the option values")]
+    GetError,
+    #[error("Unable to export or import the config bundle")]
+    BundleError,
+    #[error("Unable to print the option schema")]
+    SchemaError,
+    #[error("Unable to copy the project to the installed system")]
+    ConfigSyncError,
+    #[error("The review of the configuration was not completed")]
+    ReviewError,
+    #[error("Unable to report the status of the node")]
+    StatusError,
+    #[error("Unable to apply the configuration")]
+    ApplyError,
+    #[error("Unable to show the logs")]
+    LogsError,
+    #[error("Unable to roll back the node")]
+    RollbackError,
+    #[error("Unable to upgrade to an archival node")]
+    ArchivalError,
+    #[error("Unable to import the TLS certificate")]
+    TlsError,
+    #[error("Unable to reset the options")]
+    ResetError,
+    #[error("Unable to set the options")]
+    SetError,
+    #[error("Unable to import the SSH keys")]
+    SshKeyError,
+    #[error("Unable to set up the node")]
+    SetupError,
 }
 
 pub fn init_error_handlers() {
@@ -0,0 +1,45 @@
+use std::path::Path;
+
+use error_stack::{Result, ResultExt};
+use nixblitzlib::disk_state::is_project_file;
+use notify::{recommended_watcher, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{action::Action, errors::CliError};
+
+/// Sends [Action::ConfigChangedOnDisk] whenever one of the project files in
+/// the work dir is modified. Watching stops when the returned watcher is dropped.
+///
+/// Our own writes trigger the action as well, the project tells them apart.
+pub fn watch_project_files(
+    work_dir: &Path,
+    tx: UnboundedSender<Action>,
+) -> Result<RecommendedWatcher, CliError> {
+    let root = work_dir
+        .canonicalize()
+        .change_context(CliError::FileWatcherError)
+        .attach_printable_lazy(|| format!("Unable to resolve {}", work_dir.display()))?;
+
+    let watch_root = root.clone();
+    let mut watcher = recommended_watcher(move |res: notify::Result<Event>| {
+        let Ok(event) = res else {
+            return;
+        };
+
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+
+        if event.paths.iter().any(|p| is_project_file(&watch_root, p)) {
+            let _ = tx.send(Action::ConfigChangedOnDisk);
+        }
+    })
+    .change_context(CliError::FileWatcherError)?;
+
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .change_context(CliError::FileWatcherError)
+        .attach_printable_lazy(|| format!("Unable to watch {}", root.display()))?;
+
+    Ok(watcher)
+}
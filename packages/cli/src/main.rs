@@ -1,9 +1,44 @@
 use clap::Parser;
 use cli::Cli;
 use cli_log::init_cli_log;
-use commands::{init::init_default_project_cmd, tui::start_tui};
+use commands::{
+    apply::apply_cmd,
+    archival::archival_cmd,
+    backup::{backup_cmd, restore_backup_cmd},
+    bundle::{export_cmd, import_cmd},
+    diff::diff_cmd,
+    disk_bench::bench_disk_cmd,
+    doctor::doctor_cmd,
+    factory_reset::factory_reset_cmd,
+    generations::{generations_cmd, rollback_cmd},
+    get::get_cmd,
+    init::{init_default_project_cmd, init_from_git_cmd},
+    iso::build_iso_cmd,
+    logs::logs_cmd,
+    man::man_cmd,
+    node::ping_cmd,
+    recover::recover_cmd,
+    reset::{reset_cmd, unset_cmd},
+    review::review_cmd,
+    schema::schema_cmd,
+    search::search_cmd,
+    secrets::setup_secrets_cmd,
+    set::set_cmd,
+    setup::setup_cmd,
+    snapshot::{rollback_data_cmd, snapshot_cmd},
+    ssh_keys::import_ssh_keys_cmd,
+    status::status_cmd,
+    sync_config::sync_config_cmd,
+    system_backup::system_backup_cmd,
+    tls::import_tls_cmd,
+    tui::start_tui,
+    upgrade_templates::upgrade_templates_cmd,
+    vm::vm_run_cmd,
+    NodeCommands, VmCommands,
+};
 use error_stack::Result;
 use errors::CliError;
+use nixblitzlib::{apps::SupportedApps, nix_format::take_fallbacks};
 
 mod action;
 mod app;
@@ -15,7 +50,10 @@ mod components;
 mod config;
 mod constants;
 mod errors;
+mod file_watcher;
+mod markdown;
 mod pages;
+mod quick_actions;
 mod tui;
 mod utils;
 
@@ -30,14 +68,156 @@ async fn main() -> Result<(), CliError> {
             frame_rate,
             work_dir,
         }) => start_tui(*tick_rate, *frame_rate, work_dir.clone()).await?,
-        Some(commands::Commands::Init { work_dir, force }) => {
-            init_default_project_cmd(work_dir, *force)?
+        Some(commands::Commands::Init {
+            work_dir,
+            force,
+            from_git: None,
+            ..
+        }) => init_default_project_cmd(work_dir, *force)?,
+        Some(commands::Commands::Init {
+            work_dir,
+            from_git: Some(url),
+            branch,
+            platform,
+            ..
+        }) => init_from_git_cmd(work_dir, url, branch.as_deref(), platform.clone())?,
+        Some(commands::Commands::Setup { work_dir }) => setup_cmd(work_dir)?,
+        Some(commands::Commands::Doctor { work_dir, online }) => doctor_cmd(work_dir, *online)?,
+        Some(commands::Commands::Status { work_dir, json }) => status_cmd(work_dir, *json)?,
+        Some(commands::Commands::Logs {
+            app,
+            follow,
+            lines,
+            priority,
+        }) => logs_cmd(app, *follow, *lines, *priority)?,
+        Some(commands::Commands::Apply {
+            work_dir,
+            platform,
+            dry_run,
+            json_progress,
+        }) => apply_cmd(work_dir, platform.clone(), *dry_run, *json_progress)?,
+        Some(commands::Commands::Generations { work_dir }) => generations_cmd(work_dir)?,
+        Some(commands::Commands::Rollback {
+            generation,
+            work_dir,
+        }) => rollback_cmd(work_dir, *generation)?,
+        Some(commands::Commands::Archival {
+            work_dir,
+            maintenance_window,
+            at,
+            progress,
+        }) => archival_cmd(work_dir, *maintenance_window, at.as_deref(), *progress)?,
+        Some(commands::Commands::ImportTls {
+            cert,
+            key,
+            work_dir,
+        }) => import_tls_cmd(work_dir, cert, key)?,
+        Some(commands::Commands::ImportSshKeys {
+            user,
+            from,
+            yes,
+            work_dir,
+        }) => import_ssh_keys_cmd(work_dir, user, *from, *yes)?,
+        Some(commands::Commands::Recover {
+            work_dir,
+            strategy,
+            from,
+        }) => recover_cmd(work_dir, *strategy, from)?,
+        Some(commands::Commands::Man { topic, search }) => man_cmd(topic, search)?,
+        Some(commands::Commands::Snapshot { work_dir, list }) => snapshot_cmd(work_dir, *list)?,
+        Some(commands::Commands::RollbackData {
+            snapshot,
+            allow_lightning,
+            work_dir,
+        }) => rollback_data_cmd(work_dir, snapshot, *allow_lightning)?,
+        Some(commands::Commands::Backup { work_dir, list }) => backup_cmd(work_dir, *list)?,
+        Some(commands::Commands::RestoreBackup { backup, work_dir }) => {
+            restore_backup_cmd(work_dir, backup)?
+        }
+        Some(commands::Commands::Export {
+            output,
+            secrets,
+            work_dir,
+        }) => export_cmd(work_dir, output, *secrets)?,
+        Some(commands::Commands::Import { file, work_dir }) => import_cmd(work_dir, file)?,
+        Some(commands::Commands::SystemBackup { work_dir, verify }) => {
+            system_backup_cmd(work_dir, *verify)?
         }
-        Some(commands::Commands::Doctor {}) => {
-            println!("We haven't quite figured out how to implement this yet. Maybe try asking a magic 8-ball instead?")
+        Some(commands::Commands::UpgradeTemplates { work_dir, dry_run }) => {
+            upgrade_templates_cmd(work_dir, *dry_run)?
         }
+        Some(commands::Commands::BenchDisk { work_dir, target }) => {
+            bench_disk_cmd(work_dir, target)?
+        }
+        Some(commands::Commands::SyncConfig {
+            work_dir,
+            root,
+            attempts,
+        }) => sync_config_cmd(work_dir, root, *attempts)?,
+        Some(commands::Commands::SetupSecrets { work_dir }) => setup_secrets_cmd(work_dir)?,
+        Some(commands::Commands::Get {
+            app,
+            option,
+            all,
+            explain,
+            format,
+            work_dir,
+        }) => get_cmd(
+            work_dir,
+            app,
+            option.as_deref().filter(|_| !all),
+            *explain,
+            *format,
+        )?,
+        Some(commands::Commands::Search { query, work_dir }) => search_cmd(work_dir, query)?,
+        Some(commands::Commands::Set {
+            from_file,
+            work_dir,
+        }) => set_cmd(work_dir, from_file)?,
+        Some(commands::Commands::Unset {
+            app,
+            option,
+            work_dir,
+        }) => unset_cmd(work_dir, app, option)?,
+        Some(commands::Commands::Reset {
+            app,
+            option,
+            all: _,
+            work_dir,
+        }) => reset_cmd(work_dir, app, option.as_deref())?,
+        Some(commands::Commands::Review { work_dir }) => review_cmd(work_dir).await?,
+        Some(commands::Commands::Schema { app }) => schema_cmd(app.as_deref())?,
+        Some(commands::Commands::Diff { work_dir }) => diff_cmd(work_dir)?,
+        Some(commands::Commands::FactoryReset {
+            work_dir,
+            wipe_data,
+        }) => factory_reset_cmd(work_dir, *wipe_data)?,
+        Some(commands::Commands::BuildIso {
+            platform,
+            embed_config,
+            out_link,
+        }) => build_iso_cmd(platform, embed_config.as_deref(), out_link)?,
+        Some(commands::Commands::Vm { command }) => match command {
+            VmCommands::Run {
+                work_dir,
+                memory,
+                cores,
+                forward,
+                platform,
+            } => vm_run_cmd(work_dir, *memory, *cores, forward, platform.clone())?,
+        },
+        Some(commands::Commands::Lnd { command }) => match command {
+            NodeCommands::Ping { work_dir } => ping_cmd(work_dir, SupportedApps::LND)?,
+        },
+        Some(commands::Commands::Cln { command }) => match command {
+            NodeCommands::Ping { work_dir } => ping_cmd(work_dir, SupportedApps::CoreLightning)?,
+        },
         None => println!("Please use --help to find the available commands."),
     }
 
+    for fallback in take_fallbacks() {
+        eprintln!("Warning: {}", fallback);
+    }
+
     Ok(())
 }
@@ -0,0 +1,154 @@
+//! Minimal markdown rendering for the bundled documentation.
+//!
+//! Only supports what the docs use: headings, bullet lists, fenced code
+//! blocks and inline code.
+
+use crossterm::style::Stylize as _;
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+
+use crate::app_contexts::RenderContext;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MdLine {
+    /// A heading with its level, e.g. 2 for `## Title`
+    Heading(usize, String),
+    Bullet(String),
+    Code(String),
+    Text(String),
+    Blank,
+}
+
+/// Splits the markdown source into classified lines
+pub fn parse(md: &str) -> Vec<MdLine> {
+    let mut res = vec![];
+    let mut in_code = false;
+    for line in md.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+
+        if in_code {
+            res.push(MdLine::Code(line.to_string()));
+        } else if line.trim().is_empty() {
+            res.push(MdLine::Blank);
+        } else if let Some(bullet) = line.strip_prefix("- ") {
+            res.push(MdLine::Bullet(bullet.to_string()));
+        } else if line.starts_with('#') {
+            let level = line.chars().take_while(|c| *c == '#').count();
+            res.push(MdLine::Heading(level, line[level..].trim().to_string()));
+        } else {
+            res.push(MdLine::Text(line.to_string()));
+        }
+    }
+
+    res
+}
+
+/// Splits a line at backticks, returns the parts and whether they are inline code
+fn inline_parts(text: &str) -> Vec<(String, bool)> {
+    text.split('`')
+        .enumerate()
+        .filter(|(_, part)| !part.is_empty())
+        .map(|(i, part)| (part.to_string(), i % 2 == 1))
+        .collect()
+}
+
+/// Renders the markdown source into styled lines for the TUI
+pub fn to_lines(md: &str, ctx: &RenderContext) -> Vec<Line<'static>> {
+    let colors = ctx.theme_data.borrow().colors.clone();
+    let code_style = Style::new().fg(colors.tertiary);
+    let inline = |text: &str| -> Vec<Span<'static>> {
+        inline_parts(text)
+            .into_iter()
+            .map(|(part, code)| match code {
+                true => Span::styled(part, code_style),
+                false => Span::raw(part),
+            })
+            .collect()
+    };
+
+    parse(md)
+        .into_iter()
+        .map(|line| match line {
+            MdLine::Heading(level, text) => {
+                let style = Style::new().fg(colors.primary).add_modifier(Modifier::BOLD);
+                match level {
+                    1 => Line::styled(text.to_uppercase(), style),
+                    _ => Line::styled(text, style),
+                }
+            }
+            MdLine::Bullet(text) => {
+                let mut spans = vec![Span::raw("  • ")];
+                spans.extend(inline(&text));
+                Line::from(spans)
+            }
+            MdLine::Code(text) => Line::styled(format!("    {}", text), code_style),
+            MdLine::Text(text) => Line::from(inline(&text)),
+            MdLine::Blank => Line::default(),
+        })
+        .collect()
+}
+
+/// Renders the markdown source with ANSI styles for printing to a terminal
+pub fn to_ansi(md: &str) -> String {
+    let inline = |text: &str| -> String {
+        inline_parts(text)
+            .into_iter()
+            .map(|(part, code)| match code {
+                true => part.cyan().to_string(),
+                false => part,
+            })
+            .collect()
+    };
+
+    parse(md)
+        .into_iter()
+        .map(|line| match line {
+            MdLine::Heading(1, text) => text.to_uppercase().bold().to_string(),
+            MdLine::Heading(_, text) => text.bold().to_string(),
+            MdLine::Bullet(text) => format!("  • {}", inline(&text)),
+            MdLine::Code(text) => format!("    {}", text.cyan()),
+            MdLine::Text(text) => inline(&text),
+            MdLine::Blank => String::new(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let md = "# Title\n\nSome `code` here\n- item\n```\n# not a heading\n```";
+        assert_eq!(
+            parse(md),
+            vec![
+                MdLine::Heading(1, "Title".into()),
+                MdLine::Blank,
+                MdLine::Text("Some `code` here".into()),
+                MdLine::Bullet("item".into()),
+                MdLine::Code("# not a heading".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inline_parts() {
+        assert_eq!(
+            inline_parts("run `nixblitz doctor` now"),
+            vec![
+                ("run ".to_string(), false),
+                ("nixblitz doctor".to_string(), true),
+                (" now".to_string(), false),
+            ]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub mod actions_page;
 pub mod apps_page;
 pub mod help_page;
+pub mod logs_page;
 pub mod settings_page;
@@ -90,6 +90,8 @@ impl<'a> Component for AppsPage<'a> {
         match ctx.action {
             Action::NavUp
             | Action::NavDown
+            | Action::NavFirst
+            | Action::NavLast
             | Action::PageUp
             | Action::PageDown
             | Action::TogglePasswordVisibility => {
@@ -103,7 +105,18 @@ impl<'a> Component for AppsPage<'a> {
                     return self.app_options.update(ctx);
                 }
             }
-            Action::NavLeft | Action::NavRight => todo!(),
+            Action::NavLeft | Action::NavRight => {
+                if ctx.modal_open {
+                    return self.app_options.update(ctx);
+                }
+
+                // h and l move between the app list and its options
+                if ctx.action == Action::NavLeft {
+                    self.on_focus_req(FocusableComponent::AppTabList);
+                } else {
+                    self.on_focus_req(FocusableComponent::AppTabOptions);
+                }
+            }
             Action::Enter => {
                 // When the user hits enter and the App List is selected
                 // then we'll focus on the options part of the page
@@ -122,7 +135,14 @@ impl<'a> Component for AppsPage<'a> {
                     self.on_focus_req(FocusableComponent::AppTabList);
                 }
             }
-            Action::AppTabOptionChangeAccepted | Action::AppTabAppSelected(_) => {
+            Action::AppTabOptionChangeAccepted
+            | Action::AppTabOptionsChanged(_)
+            | Action::AppTabAppSelected(_) => {
+                return self.app_options.update(ctx);
+            }
+            Action::JumpToOption(_) => {
+                self.app_list.update(ctx)?;
+                self.on_focus_req(FocusableComponent::AppTabOptions);
                 return self.app_options.update(ctx);
             }
             Action::FocusRequest(r) => self.on_focus_req(r),
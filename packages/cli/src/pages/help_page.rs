@@ -1,43 +1,124 @@
+use std::collections::BTreeMap;
+
 use error_stack::Result;
-use ratatui::{layout::Rect, Frame};
+use nixblitzlib::docs::{pages, DocPage};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{ListState, Paragraph, Wrap},
+    Frame,
+};
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::{
     action::Action,
+    app::Mode,
     app_contexts::{RenderContext, UpdateContext},
-    components::{theme, Component},
-    config::Config,
+    components::{
+        theme::{self, list},
+        Component,
+    },
+    config::{get_config_dir, key_sequence_to_string, Config},
     errors::CliError,
+    markdown::to_lines,
 };
 
+const TOPICS_TITLE: &str = " Topics ";
+
+/// Number of lines PageUp and PageDown scroll the page content
+const SCROLL_STEP: u16 = 10;
+
+/// Slug of the generated topic listing the key bindings
+const KEY_BINDINGS_SLUG: &str = "key-bindings";
+
+/// Builds the help topic listing the active key bindings, i.e. the defaults
+/// merged with the user keymap
+fn key_bindings_page(config: &Config) -> DocPage {
+    let mut by_action: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    if let Some(keymap) = config.keybindings.get(&Mode::Home) {
+        for (keys, action) in keymap.iter() {
+            by_action
+                .entry(action.to_string())
+                .or_default()
+                .push(key_sequence_to_string(keys));
+        }
+    }
+
+    let mut content = format!(
+        "# Key bindings\n\nAdd a `keybindings` section to `{}` to change them, \
+         see `nixblitz man getting-started`.\n\n",
+        get_config_dir().join("config.json5").display()
+    );
+    for (action, mut keys) in by_action {
+        keys.sort();
+        let keys: Vec<String> = keys.iter().map(|k| format!("`{k}`")).collect();
+        content.push_str(&format!("- {}: {}\n", action, keys.join(" ")));
+    }
+
+    DocPage {
+        slug: KEY_BINDINGS_SLUG.to_string(),
+        title: "Key bindings".to_string(),
+        content,
+    }
+}
+
+/// Browses the bundled documentation.
+///
+/// Up and down select a topic, PageUp and PageDown scroll its content. The
+/// first topic lists the active key bindings.
 #[derive(Default)]
 pub struct HelpPage {
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
+    pages: Vec<DocPage>,
+    state: ListState,
+    scroll: u16,
+
+    /// Whether the help page is the current home page.
+    /// All pages receive every action, so we must ignore them when hidden.
+    active: bool,
 }
 
 impl HelpPage {
     pub fn new() -> Self {
-        Self {
-            command_tx: None,
-            config: Config::default(),
-        }
+        let mut instance = Self {
+            pages: pages(),
+            ..Default::default()
+        };
+        instance.state.select(Some(0));
+        instance
     }
 
     fn nav(&mut self, action: &Action) {
         match action {
-            Action::NavUp | Action::NavDown => {}
-            Action::NavLeft => todo!(),
-            Action::NavRight => todo!(),
-            Action::Enter => self.on_enter(),
-            Action::Esc => self.on_esc(),
+            Action::NavUp | Action::NavDown | Action::NavFirst | Action::NavLast => {
+                self.select(action)
+            }
+            Action::PageUp => self.scroll = self.scroll.saturating_sub(SCROLL_STEP),
+            Action::PageDown => self.scroll = self.scroll.saturating_add(SCROLL_STEP),
             _ => (),
         }
     }
 
-    fn on_enter(&mut self) {}
+    fn select(&mut self, action: &Action) {
+        let old = self.state.selected();
+        match action {
+            Action::NavUp => self.state.select_previous(),
+            Action::NavDown => self.state.select_next(),
+            Action::NavFirst => self.state.select_first(),
+            Action::NavLast => self.state.select(self.pages.len().checked_sub(1)),
+            _ => (),
+        }
+
+        if old != self.state.selected() {
+            self.scroll = 0;
+        }
+    }
 
-    fn on_esc(&mut self) {}
+    fn selected_page(&self) -> Option<&DocPage> {
+        // select_next may move past the end, the list clamps it when drawing
+        let index = self.state.selected()?.min(self.pages.len().checked_sub(1)?);
+        self.pages.get(index)
+    }
 }
 
 impl Component for HelpPage {
@@ -47,6 +128,8 @@ impl Component for HelpPage {
     }
 
     fn register_config_handler(&mut self, config: Config) -> Result<(), CliError> {
+        self.pages.retain(|p| p.slug != KEY_BINDINGS_SLUG);
+        self.pages.insert(0, key_bindings_page(&config));
         self.config = config;
         Ok(())
     }
@@ -61,20 +144,49 @@ impl Component for HelpPage {
 
     fn update(&mut self, ctx: &UpdateContext) -> Result<Option<Action>, CliError> {
         match ctx.action {
+            Action::NavHelpTab if !ctx.modal_open => self.active = true,
+            Action::NavAppsTab
+            | Action::NavSettingsTab
+            | Action::NavActionsTab
+            | Action::NavLogsTab
+                if !ctx.modal_open =>
+            {
+                self.active = false
+            }
             Action::NavUp
             | Action::NavDown
-            | Action::NavLeft
-            | Action::NavRight
-            | Action::Enter
-            | Action::Esc => self.nav(&ctx.action),
+            | Action::NavFirst
+            | Action::NavLast
+            | Action::PageUp
+            | Action::PageDown
+                if self.active && !ctx.modal_open =>
+            {
+                self.nav(&ctx.action)
+            }
             _ => (),
         }
         Ok(None)
     }
 
     fn draw(&mut self, frame: &mut Frame, area: Rect, ctx: &RenderContext) -> Result<(), CliError> {
-        let c = theme::block::default(" Help ", ctx);
-        frame.render_widget(c, area);
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(26), Constraint::Min(0)])
+            .split(area);
+
+        let titles: Vec<&str> = self.pages.iter().map(|p| p.title.as_str()).collect();
+        let topics = list::focused(TOPICS_TITLE, &titles, ctx);
+        frame.render_stateful_widget(topics, layout[0], &mut self.state);
+
+        let (title, lines) = match self.selected_page() {
+            Some(page) => (format!(" {} ", page.title), to_lines(&page.content, ctx)),
+            None => (" Help ".to_string(), vec![]),
+        };
+        let content = Paragraph::new(lines)
+            .block(theme::block::default(&title, ctx))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(content, layout[1]);
 
         Ok(())
     }
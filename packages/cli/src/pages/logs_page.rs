@@ -0,0 +1,478 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use cli_log::error;
+use crossterm::event::{KeyCode, KeyEvent};
+use error_stack::{Result, ResultExt};
+use nixblitzlib::{
+    apps::SupportedApps,
+    journal::{self, JournalStream, LogEntry, LogLevel},
+    registry::{registration, APPS},
+};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{ListState, Paragraph},
+    Frame,
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    action::Action,
+    app_contexts::{RenderContext, UpdateContext},
+    components::{
+        theme::{self, list},
+        Component,
+    },
+    config::Config,
+    errors::CliError,
+    quick_actions::QuickActionOutput,
+};
+
+const SERVICES_TITLE: &str = " Services ";
+const QUICK_ACTIONS_TITLE: &str = "Quick actions";
+const KEYS_HINT: &str = " / search | v level | p pause | w save ";
+
+/// Number of past entries loaded when a service is selected
+const INITIAL_LINES: usize = 500;
+
+/// Number of entries kept per service, older ones are dropped
+const MAX_ENTRIES: usize = 5000;
+
+/// Number of lines PageUp and PageDown scroll the log
+const SCROLL_STEP: usize = 10;
+
+/// What the log shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogSource {
+    /// The output of the quick actions run since the TUI started
+    QuickActions,
+
+    /// The journal of the service behind an app, with its unit
+    Service(SupportedApps, &'static str),
+}
+
+impl LogSource {
+    fn title(&self) -> &'static str {
+        match self {
+            LogSource::QuickActions => QUICK_ACTIONS_TITLE,
+            LogSource::Service(app, _) => registration(*app).title,
+        }
+    }
+}
+
+/// Follows the journal of the service behind an app, or the output of the
+/// quick actions.
+///
+/// Up and down select a service, PageUp and PageDown scroll the log.
+/// The log can be filtered by level and text, paused and saved to a file.
+#[derive(Default)]
+pub struct LogsPage {
+    command_tx: Option<UnboundedSender<Action>>,
+    config: Config,
+
+    /// The quick actions and the apps that run a service
+    sources: Vec<LogSource>,
+    state: ListState,
+
+    /// Follows the selected service while the page is shown
+    stream: Option<JournalStream>,
+    entries: VecDeque<LogEntry>,
+
+    /// Collected while the TUI runs, also when the page isn't shown
+    quick_action_entries: VecDeque<LogEntry>,
+
+    /// The least severe level that is shown
+    level: LogLevel,
+    search: String,
+
+    /// Whether the search term is being typed
+    searching: bool,
+
+    /// A paused log keeps its entries until it is resumed
+    paused: bool,
+
+    /// Number of lines scrolled up from the newest entry
+    scroll: usize,
+
+    /// Feedback for the last save
+    status: Option<String>,
+
+    /// Whether the logs page is the current home page.
+    /// All pages receive every action, so we must ignore them when hidden.
+    active: bool,
+    modal_open: bool,
+}
+
+impl LogsPage {
+    pub fn new() -> Self {
+        let mut instance = Self {
+            sources: std::iter::once(LogSource::QuickActions)
+                .chain(APPS.iter().filter_map(|r| {
+                    journal::unit_name(r.app).map(|unit| LogSource::Service(r.app, unit))
+                }))
+                .collect(),
+            ..Default::default()
+        };
+        instance.state.select(Some(0));
+        instance
+    }
+
+    fn selected_source(&self) -> Option<LogSource> {
+        // select_next may move past the end, the list clamps it when drawing
+        let index = self
+            .state
+            .selected()?
+            .min(self.sources.len().checked_sub(1)?);
+        self.sources.get(index).copied()
+    }
+
+    fn nav(&mut self, action: &Action) -> Result<(), CliError> {
+        match action {
+            Action::NavUp | Action::NavDown | Action::NavFirst | Action::NavLast => {
+                self.select(action)?
+            }
+            Action::PageUp => self.scroll = self.scroll.saturating_add(SCROLL_STEP),
+            Action::PageDown => self.scroll = self.scroll.saturating_sub(SCROLL_STEP),
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn select(&mut self, action: &Action) -> Result<(), CliError> {
+        let old = self.selected_source();
+        match action {
+            Action::NavUp => self.state.select_previous(),
+            Action::NavDown => self.state.select_next(),
+            Action::NavFirst => self.state.select_first(),
+            Action::NavLast => self.state.select(self.sources.len().checked_sub(1)),
+            _ => (),
+        }
+
+        if old != self.selected_source() {
+            self.start()?;
+        }
+
+        Ok(())
+    }
+
+    /// Starts following the selected service, dropping the previous stream
+    fn start(&mut self) -> Result<(), CliError> {
+        self.stop();
+        let Some(LogSource::Service(_, unit)) = self.selected_source() else {
+            return Ok(());
+        };
+
+        match journal::follow(unit, INITIAL_LINES) {
+            Ok(stream) => self.stream = Some(stream),
+            Err(e) => {
+                error!("{:?}", e);
+                self.send(Action::Error(e.current_context().to_string()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.stream = None;
+        self.entries.clear();
+        self.scroll = 0;
+        self.status = None;
+    }
+
+    /// Takes the entries received since the last tick
+    ///
+    /// # Returns
+    /// Whether new entries were added
+    fn poll(&mut self) -> bool {
+        if self.paused {
+            return false;
+        }
+
+        let Some(stream) = &self.stream else {
+            return false;
+        };
+
+        let new = stream.poll();
+        if new.is_empty() {
+            return false;
+        }
+
+        self.entries.extend(new);
+        while self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+
+        true
+    }
+
+    /// Adds a line of a quick action. Switches to the quick actions when
+    /// one is started.
+    fn on_quick_action_output(&mut self, output: &QuickActionOutput) -> Result<(), CliError> {
+        let (level, message) = match output {
+            QuickActionOutput::Started { label, command } => {
+                (LogLevel::Notice, format!("Running {}: {}", label, command))
+            }
+            QuickActionOutput::Stdout(line) => (LogLevel::Info, line.clone()),
+            QuickActionOutput::Stderr(line) => (LogLevel::Warning, line.clone()),
+            QuickActionOutput::Finished {
+                label,
+                success,
+                status,
+            } => (
+                if *success {
+                    LogLevel::Notice
+                } else {
+                    LogLevel::Error
+                },
+                format!("{} finished: {}", label, status),
+            ),
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or_default();
+        self.quick_action_entries.push_back(LogEntry {
+            timestamp,
+            level,
+            message,
+        });
+        while self.quick_action_entries.len() > MAX_ENTRIES {
+            self.quick_action_entries.pop_front();
+        }
+
+        let started = matches!(output, QuickActionOutput::Started { .. });
+        if started && self.selected_source() != Some(LogSource::QuickActions) {
+            self.state.select(Some(0));
+            self.start()?;
+        }
+
+        Ok(())
+    }
+
+    fn filtered(&self) -> Vec<&LogEntry> {
+        let entries = match self.selected_source() {
+            Some(LogSource::QuickActions) => &self.quick_action_entries,
+            _ => &self.entries,
+        };
+        entries
+            .iter()
+            .filter(|e| e.matches(self.level, &self.search))
+            .collect()
+    }
+
+    /// Writes the shown entries to a file in the temp dir
+    fn save(&self) -> Result<PathBuf, CliError> {
+        let unit = match self.selected_source() {
+            Some(LogSource::QuickActions) => "quick-actions",
+            Some(LogSource::Service(_, unit)) => unit,
+            None => "logs",
+        };
+        let path = std::env::temp_dir().join(format!("nixblitz-{}.log", unit));
+        let content: String = self.filtered().iter().map(|e| format!("{}\n", e)).collect();
+
+        fs::write(&path, content)
+            .change_context(CliError::Unknown)
+            .attach_printable_lazy(|| format!("Unable to write the log to {}", path.display()))?;
+
+        Ok(path)
+    }
+
+    fn on_search_key(&mut self, key: KeyEvent) -> Result<(), CliError> {
+        match key.code {
+            KeyCode::Enter => {
+                self.searching = false;
+                self.send(Action::PopModal(true))?;
+            }
+            KeyCode::Esc => {
+                self.searching = false;
+                self.search.clear();
+                self.send(Action::PopModal(false))?;
+            }
+            KeyCode::Backspace => {
+                self.search.pop();
+            }
+            KeyCode::Char(c) => self.search.push(c),
+            _ => (),
+        }
+        self.scroll = 0;
+
+        self.send(Action::Refresh)
+    }
+
+    fn send(&self, action: Action) -> Result<(), CliError> {
+        let tx = self
+            .command_tx
+            .as_ref()
+            .ok_or(CliError::UnableToFindUnboundedSender)?;
+        tx.send(action)
+            .change_context(CliError::UnableToSendViaUnboundedSender)?;
+
+        Ok(())
+    }
+
+    fn title(&self, title: &str) -> String {
+        let mut parts = vec![title.to_string(), format!("level: {}", self.level)];
+        if !self.search.is_empty() || self.searching {
+            parts.push(format!("search: {}", self.search));
+        }
+        if self.paused {
+            parts.push("PAUSED".into());
+        }
+        if let Some(status) = &self.status {
+            parts.push(status.clone());
+        }
+
+        format!(" {} ", parts.join(" | "))
+    }
+
+    fn line<'a>(entry: &LogEntry, ctx: &RenderContext) -> Line<'a> {
+        let colors = &ctx.theme_data.borrow().colors;
+        let style = match entry.level {
+            LogLevel::Error => Style::default().fg(colors.error),
+            LogLevel::Warning => Style::default().fg(colors.tertiary),
+            LogLevel::Notice | LogLevel::Info => Style::default().fg(colors.on_surface),
+            LogLevel::Debug => Style::default().add_modifier(Modifier::DIM),
+        };
+
+        Line::styled(entry.to_string(), style)
+    }
+}
+
+impl Component for LogsPage {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<(), CliError> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<(), CliError> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>, CliError> {
+        if !self.active {
+            return Ok(None);
+        }
+
+        if self.searching {
+            self.on_search_key(key)?;
+            return Ok(None);
+        }
+
+        if self.modal_open {
+            return Ok(None);
+        }
+
+        match key.code {
+            KeyCode::Char('/') => {
+                self.searching = true;
+                self.send(Action::PushModal(true))?;
+            }
+            KeyCode::Char('v') => {
+                self.level = self.level.next();
+                self.scroll = 0;
+            }
+            KeyCode::Char('p') => self.paused = !self.paused,
+            KeyCode::Char('w') => match self.save() {
+                Ok(path) => self.status = Some(format!("saved to {}", path.display())),
+                Err(e) => {
+                    error!("{:?}", e);
+                    self.status = Some("unable to save".into());
+                }
+            },
+            _ => return Ok(None),
+        }
+
+        Ok(Some(Action::Refresh))
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: crossterm::event::MouseEvent,
+    ) -> Result<Option<Action>, CliError> {
+        let _ = mouse;
+        Ok(None)
+    }
+
+    fn update(&mut self, ctx: &UpdateContext) -> Result<Option<Action>, CliError> {
+        self.modal_open = ctx.modal_open;
+        match ctx.action {
+            Action::NavLogsTab if !ctx.modal_open && !self.active => {
+                self.active = true;
+                self.start()?;
+            }
+            Action::NavAppsTab
+            | Action::NavSettingsTab
+            | Action::NavActionsTab
+            | Action::NavHelpTab
+                if !ctx.modal_open =>
+            {
+                // don't keep journalctl running in the background
+                self.active = false;
+                self.stop();
+            }
+            Action::NavUp
+            | Action::NavDown
+            | Action::NavFirst
+            | Action::NavLast
+            | Action::PageUp
+            | Action::PageDown
+                if self.active && !ctx.modal_open =>
+            {
+                self.nav(&ctx.action)?
+            }
+            Action::Tick if self.active && self.poll() => return Ok(Some(Action::Refresh)),
+            Action::QuickActionOutput(ref output) => {
+                self.on_quick_action_output(output)?;
+                if self.active {
+                    return Ok(Some(Action::Refresh));
+                }
+            }
+            _ => (),
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, ctx: &RenderContext) -> Result<(), CliError> {
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(26), Constraint::Min(0)])
+            .split(area);
+
+        let titles: Vec<&str> = self.sources.iter().map(LogSource::title).collect();
+        let services = list::focused(SERVICES_TITLE, &titles, ctx);
+        frame.render_stateful_widget(services, layout[0], &mut self.state);
+
+        let title = match self.selected_source() {
+            Some(source) => self.title(source.title()),
+            None => " Logs ".to_string(),
+        };
+
+        // show the newest entries that fit, minus the scrolled lines
+        let height = usize::from(layout[1].height.saturating_sub(2));
+        let total = self.filtered().len();
+        self.scroll = self.scroll.min(total.saturating_sub(height));
+        let entries = self.filtered();
+        let end = total - self.scroll;
+        let start = end.saturating_sub(height);
+        let lines: Vec<Line> = entries[start..end]
+            .iter()
+            .map(|e| Self::line(e, ctx))
+            .collect();
+
+        let content = Paragraph::new(lines).block(
+            theme::block::default(&title, ctx).title_bottom(Line::from(KEYS_HINT).right_aligned()),
+        );
+        frame.render_widget(content, layout[1]);
+
+        Ok(())
+    }
+}
@@ -25,9 +25,7 @@ impl SettingsPage {
 
     fn nav(&mut self, action: &Action) {
         match action {
-            Action::NavUp | Action::NavDown => {}
-            Action::NavLeft => todo!(),
-            Action::NavRight => todo!(),
+            Action::NavUp | Action::NavDown | Action::NavLeft | Action::NavRight => {}
             Action::Enter => self.on_enter(),
             Action::Esc => self.on_esc(),
             _ => (),
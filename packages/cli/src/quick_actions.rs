@@ -0,0 +1,222 @@
+use std::{
+    fs,
+    path::Path,
+    process::{ExitStatus, Stdio},
+};
+
+use cli_log::error;
+use error_stack::{Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+    sync::mpsc::UnboundedSender,
+    task::JoinHandle,
+};
+
+use crate::{action::Action, errors::CliError};
+
+/// The name of the file inside the config directory holding the user defined quick actions
+pub const QUICK_ACTIONS_FILE_NAME: &str = "quickactions.json";
+
+/// A user defined command that can be run from within the TUI
+///
+/// Quick actions are read from `quickactions.json` in the config directory:
+///
+/// ```json
+/// [
+///   { "label": "LND info", "command": "lncli getinfo" },
+///   { "label": "Disk usage", "command": "df -h" }
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuickAction {
+    /// The label shown in the quick actions menu
+    pub label: String,
+
+    /// The shell command to execute
+    pub command: String,
+}
+
+/// What a running quick action reports to the logs page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuickActionOutput {
+    Started {
+        label: String,
+        command: String,
+    },
+    Stdout(String),
+    Stderr(String),
+
+    /// The command exited or couldn't be started
+    Finished {
+        label: String,
+        success: bool,
+
+        /// The exit status or why the command couldn't be started
+        status: String,
+    },
+}
+
+impl QuickAction {
+    /// Runs the command via `sh -c` in a background task, so a command that
+    /// hangs doesn't block the TUI. Its stdout and stderr are sent line by
+    /// line as [Action::QuickActionOutput] while it runs.
+    pub fn spawn(&self, tx: UnboundedSender<Action>) -> JoinHandle<()> {
+        let action = self.clone();
+        tokio::spawn(async move {
+            let send = |output| {
+                let _ = tx.send(Action::QuickActionOutput(output));
+            };
+            send(QuickActionOutput::Started {
+                label: action.label.clone(),
+                command: action.command.clone(),
+            });
+
+            let (success, status) = match action.run(&tx).await {
+                Ok(status) => (status.success(), status.to_string()),
+                Err(e) => {
+                    error!("{:?}", e);
+                    (false, e.to_string())
+                }
+            };
+            send(QuickActionOutput::Finished {
+                label: action.label,
+                success,
+                status,
+            });
+        })
+    }
+
+    async fn run(&self, tx: &UnboundedSender<Action>) -> Result<ExitStatus, CliError> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .change_context(CliError::QuickActionRunError(self.label.clone()))
+            .attach_printable_lazy(|| format!("Command: {}", self.command))?;
+
+        if let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) {
+            tokio::join!(
+                forward(stdout, tx, QuickActionOutput::Stdout),
+                forward(stderr, tx, QuickActionOutput::Stderr),
+            );
+        }
+
+        child
+            .wait()
+            .await
+            .change_context(CliError::QuickActionRunError(self.label.clone()))
+    }
+}
+
+/// Sends the lines of the output until it is closed
+async fn forward(
+    output: impl AsyncRead + Unpin,
+    tx: &UnboundedSender<Action>,
+    wrap: fn(String) -> QuickActionOutput,
+) {
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let _ = tx.send(Action::QuickActionOutput(wrap(line)));
+    }
+}
+
+/// Loads the quick actions from the given config directory
+///
+/// # Returns
+///
+/// * `Ok(Vec<QuickAction>)` - The configured quick actions, empty if the file does not exist.
+/// * `Err(CliError)` - If the file exists but cannot be read or parsed.
+pub fn load_quick_actions(config_dir: &Path) -> Result<Vec<QuickAction>, CliError> {
+    let path = config_dir.join(QUICK_ACTIONS_FILE_NAME);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let contents = fs::read_to_string(&path)
+        .change_context(CliError::QuickActionsLoadError(path.display().to_string()))?;
+
+    let actions: Vec<QuickAction> = serde_json::from_str(&contents)
+        .change_context(CliError::QuickActionsLoadError(path.display().to_string()))?;
+
+    if let Some(a) = actions.iter().find(|a| a.label.trim().is_empty()) {
+        return Err(
+            Report::new(CliError::QuickActionsLoadError(path.display().to_string()))
+                .attach_printable(format!("Quick action without a label: {}", a.command)),
+        );
+    }
+
+    Ok(actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_load_missing_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let actions = load_quick_actions(temp_dir.path()).unwrap();
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn test_load_quick_actions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(QUICK_ACTIONS_FILE_NAME),
+            r#"[{"label": "Hello", "command": "echo hello"}]"#,
+        )
+        .unwrap();
+
+        let actions = load_quick_actions(temp_dir.path()).unwrap();
+        assert_eq!(
+            actions,
+            vec![QuickAction {
+                label: "Hello".into(),
+                command: "echo hello".into()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spawn() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let action = QuickAction {
+            label: "Hello".into(),
+            command: "echo hello; echo oops >&2; exit 3".into(),
+        };
+        action.spawn(tx).await.unwrap();
+
+        let mut output = vec![];
+        while let Ok(Action::QuickActionOutput(o)) = rx.try_recv() {
+            output.push(o);
+        }
+        assert!(matches!(output[0], QuickActionOutput::Started { .. }));
+        assert!(output.contains(&QuickActionOutput::Stdout("hello".into())));
+        assert!(output.contains(&QuickActionOutput::Stderr("oops".into())));
+        assert!(matches!(
+            output.last(),
+            Some(QuickActionOutput::Finished { success: false, .. })
+        ));
+    }
+
+    #[test]
+    fn test_load_invalid_quick_actions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(QUICK_ACTIONS_FILE_NAME);
+
+        fs::write(&path, "not json").unwrap();
+        assert!(load_quick_actions(temp_dir.path()).is_err());
+
+        fs::write(&path, r#"[{"label": " ", "command": "true"}]"#).unwrap();
+        assert!(load_quick_actions(temp_dir.path()).is_err());
+    }
+}
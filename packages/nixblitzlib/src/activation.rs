@@ -0,0 +1,171 @@
+//! Detects a running nixos-rebuild before the system is changed.
+//!
+//! Two activations at the same time can leave the node with a half switched
+//! configuration. Everything that builds, switches or restarts units of the
+//! configuration checks [ensure_idle] first and refuses to start otherwise.
+
+use std::{fs, path::Path, process::Command};
+
+use error_stack::{Report, Result};
+
+use crate::errors::ProjectError;
+
+/// The transient unit nixos-rebuild runs switch-to-configuration in
+pub const SWITCH_UNIT: &str = "nixos-rebuild-switch-to-configuration.service";
+
+/// Program names that build or activate a system configuration
+const REBUILD_PROGRAMS: [&str; 2] = ["nixos-rebuild", "switch-to-configuration"];
+
+/// A process that builds or activates a configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunningRebuild {
+    pub pid: u32,
+
+    /// The command line, with the arguments separated by spaces
+    pub command: String,
+}
+
+/// Finds the processes building or activating a configuration
+///
+/// # Arguments
+///
+/// * `proc_dir` - Usually `/proc`
+///
+/// # Returns
+/// The matching processes, except the current one
+pub fn running_rebuilds(proc_dir: &Path) -> Vec<RunningRebuild> {
+    let Ok(entries) = fs::read_dir(proc_dir) else {
+        return vec![];
+    };
+
+    let own_pid = std::process::id();
+    let mut res: Vec<RunningRebuild> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let pid: u32 = e.file_name().to_str()?.parse().ok()?;
+            // processes may exit while we are iterating
+            let cmdline = fs::read(e.path().join("cmdline")).ok()?;
+            Some((pid, cmdline))
+        })
+        .filter(|(pid, _)| *pid != own_pid)
+        .filter_map(|(pid, cmdline)| {
+            is_rebuild(&cmdline).then(|| RunningRebuild {
+                pid,
+                command: String::from_utf8_lossy(&cmdline)
+                    .split('\0')
+                    .filter(|a| !a.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            })
+        })
+        .collect();
+
+    res.sort_by_key(|r| r.pid);
+    res
+}
+
+/// Whether the NUL separated command line runs one of the [REBUILD_PROGRAMS].
+///
+/// nixos-rebuild is a script, so the program is usually an argument of the
+/// interpreter and every argument is checked.
+fn is_rebuild(cmdline: &[u8]) -> bool {
+    cmdline
+        .split(|b| *b == 0)
+        .filter_map(|arg| std::str::from_utf8(arg).ok())
+        .filter_map(|arg| arg.rsplit('/').next())
+        .any(|name| REBUILD_PROGRAMS.iter().any(|p| name.contains(p)))
+}
+
+/// Whether systemd is currently running [SWITCH_UNIT]
+///
+/// Systems without systemctl never have the unit running.
+pub fn switch_unit_active() -> bool {
+    Command::new("systemctl")
+        .args(["is-active", SWITCH_UNIT])
+        .output()
+        .map(|o| is_busy_state(&String::from_utf8_lossy(&o.stdout)))
+        .unwrap_or(false)
+}
+
+/// Parses the output of `systemctl is-active`
+fn is_busy_state(state: &str) -> bool {
+    matches!(state.trim(), "active" | "activating" | "reloading")
+}
+
+/// Makes sure no other rebuild is building or activating a configuration
+///
+/// # Errors
+/// [ProjectError::RebuildInProgress] with the running processes attached
+pub fn ensure_idle() -> Result<(), ProjectError> {
+    let rebuilds = running_rebuilds(Path::new("/proc"));
+    if rebuilds.is_empty() && !switch_unit_active() {
+        return Ok(());
+    }
+
+    let mut report = Report::new(ProjectError::RebuildInProgress).attach_printable(
+        "Wait until it has finished and try again, the current state can be seen with \
+         journalctl -f",
+    );
+    if rebuilds.is_empty() {
+        report = report.attach_printable(format!("{} is running", SWITCH_UNIT));
+    }
+    for r in rebuilds {
+        report = report.attach_printable(format!("PID {}: {}", r.pid, r.command));
+    }
+
+    Err(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_is_rebuild() {
+        assert!(is_rebuild(
+            b"bash\0/run/current-system/sw/bin/nixos-rebuild\0switch\0"
+        ));
+        assert!(is_rebuild(
+            b"/nix/store/abc-nixos-system/bin/switch-to-configuration\0switch\0"
+        ));
+        assert!(is_rebuild(
+            b"/nix/store/abc/bin/.nixos-rebuild-wrapped\0boot\0"
+        ));
+        assert!(!is_rebuild(b"/nix/store/abc/bin/nix-daemon\0--daemon\0"));
+        assert!(!is_rebuild(b"vim\0notes.txt\0"));
+        assert!(!is_rebuild(b""));
+    }
+
+    #[test]
+    fn test_running_rebuilds() {
+        let dir = tempdir().unwrap();
+        let add = |pid: &str, cmdline: &[u8]| {
+            fs::create_dir(dir.path().join(pid)).unwrap();
+            fs::write(dir.path().join(pid).join("cmdline"), cmdline).unwrap();
+        };
+        add("42", b"bash\0/bin/nixos-rebuild\0switch\0");
+        add("7", b"sshd\0");
+        add("self", b"nixos-rebuild\0");
+        fs::create_dir(dir.path().join("99")).unwrap();
+
+        assert_eq!(
+            running_rebuilds(dir.path()),
+            vec![RunningRebuild {
+                pid: 42,
+                command: "bash /bin/nixos-rebuild switch".into(),
+            }]
+        );
+        assert!(running_rebuilds(&dir.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_is_busy_state() {
+        assert!(is_busy_state("active\n"));
+        assert!(is_busy_state("activating"));
+        assert!(!is_busy_state("inactive\n"));
+        assert!(!is_busy_state("failed"));
+        assert!(!is_busy_state(""));
+    }
+}
@@ -1,11 +1,32 @@
 use crate::{
-    app_option_data::option_data::{OptionData, OptionDataChangeNotification},
+    app_option_data::{
+        option_data::{OptionData, OptionDataChangeNotification},
+        password_data::PasswordOptionData,
+    },
     errors::ProjectError,
 };
 use error_stack::Result;
-use std::{fmt::Debug, path::Path};
+use std::{any::Any, fmt::Debug, path::Path};
 
-pub trait AppConfig: Debug {
+/// Gives access to the concrete type behind a `dyn AppConfig`, implemented
+/// for every type
+pub trait AsAny: Any {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub trait AppConfig: Debug + AsAny {
     fn app_option_changed(
         &mut self,
         option: &OptionDataChangeNotification,
@@ -14,4 +35,72 @@ pub trait AppConfig: Debug {
     fn get_options(&self) -> Vec<OptionData>;
 
     fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError>;
+
+    /// The passwords the services read from their config, with the user of
+    /// the service reading them. With an encrypting backend the
+    /// [Project](crate::project::Project) moves their values to the
+    /// [secret_store](crate::secret_store).
+    fn secret_options(&mut self) -> Vec<(&mut PasswordOptionData, &'static str)> {
+        vec![]
+    }
 }
+
+/// Implements [AppConfig::get_options] and [AppConfig::app_option_changed]
+/// inside an `impl AppConfig` block from a list of `Variant => field` pairs.
+///
+/// The options are listed in the given order. Every field must implement
+/// [ApplyChange](crate::app_option_data::apply_change::ApplyChange).
+/// Variants of the option enum that are not listed are rejected with
+/// [ProjectError::ChangeOptionValueError].
+///
+/// A pair can name a validator with `Variant => field where check`. It is
+/// called as `self.check(option)` before the change is applied and rejects
+/// the value by returning an error. It has the signature
+/// `fn(&self, &OptionDataChangeNotification) -> Result<(), ProjectError>`.
+///
+/// ```ignore
+/// impl AppConfig for LightningNetworkDaemonService {
+///     app_options!(LndConfigOption {
+///         Enable => enable,
+///         Port => port where check_port,
+///     });
+///
+///     fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> { ... }
+/// }
+/// ```
+macro_rules! app_options {
+    ($option:ident { $($variant:ident => $field:ident $(where $check:ident)?),* $(,)? }) => {
+        fn get_options(&self) -> Vec<$crate::app_option_data::option_data::OptionData> {
+            use $crate::app_option_data::apply_change::ApplyChange;
+
+            vec![$(self.$field.to_option_data()),*]
+        }
+
+        #[allow(unreachable_patterns)]
+        fn app_option_changed(
+            &mut self,
+            option: &$crate::app_option_data::option_data::OptionDataChangeNotification,
+        ) -> error_stack::Result<bool, $crate::errors::ProjectError> {
+            use $crate::app_option_data::{
+                apply_change::ApplyChange, option_data::GetOptionId,
+            };
+
+            let Ok(opt) = <$option as std::str::FromStr>::from_str(&option.id().option) else {
+                return Ok(false);
+            };
+
+            match opt {
+                $($option::$variant => {
+                    $(self.$check(option)?;)?
+                    self.$field.apply_change(option)
+                })*
+                _ => Err(error_stack::Report::new(
+                    $crate::errors::ProjectError::ChangeOptionValueError(opt.to_string()),
+                )
+                .attach_printable(format!("Unknown option: {}", opt))),
+            }
+        }
+    };
+}
+
+pub(crate) use app_options;
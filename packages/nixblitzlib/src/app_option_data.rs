@@ -1,8 +1,14 @@
+pub mod amount_data;
+pub mod apply_change;
 pub mod bool_data;
+pub mod code_data;
+pub mod manual_string_list_data;
 pub mod net_address_data;
+pub mod net_address_list_data;
 pub mod number_data;
 pub mod option_data;
 pub mod password_data;
+pub mod percent_data;
 pub mod port_data;
 pub mod string_list_data;
 pub mod text_edit_data;
@@ -0,0 +1,348 @@
+use core::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ArgumentError;
+
+use super::option_data::{GetOptionId, OptionId, ToNixString};
+
+/// Satoshis in one bitcoin
+pub const SATS_PER_BTC: u64 = 100_000_000;
+
+/// All bitcoin that will ever exist, no amount can be larger
+pub const MAX_SATS: u64 = 21_000_000 * SATS_PER_BTC;
+
+/// The unit an amount is shown and entered in. The value is always stored
+/// in satoshis.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AmountUnit {
+    #[default]
+    Sats,
+    Btc,
+}
+
+impl AmountUnit {
+    pub fn to_string_array() -> [&'static str; 2] {
+        ["sats", "BTC"]
+    }
+}
+
+impl fmt::Display for AmountUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit_str = match self {
+            AmountUnit::Sats => "sats",
+            AmountUnit::Btc => "BTC",
+        };
+        write!(f, "{}", unit_str)
+    }
+}
+
+impl FromStr for AmountUnit {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<AmountUnit, ()> {
+        match s.to_lowercase().as_str() {
+            "sat" | "sats" => Ok(AmountUnit::Sats),
+            "btc" => Ok(AmountUnit::Btc),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Formats an amount in the given unit, e.g. "0.015 BTC" or "150000 sats".
+/// Trailing zeros of BTC amounts are dropped.
+pub fn format_amount(sats: u64, unit: AmountUnit) -> String {
+    match unit {
+        AmountUnit::Sats => format!("{} {}", sats, unit),
+        AmountUnit::Btc => format!("{} {}", sats_to_btc(sats), unit),
+    }
+}
+
+/// Formats satoshis as BTC without trailing zeros, e.g. "0.015"
+pub fn sats_to_btc(sats: u64) -> String {
+    let whole = sats / SATS_PER_BTC;
+    let fraction = sats % SATS_PER_BTC;
+    if fraction == 0 {
+        return whole.to_string();
+    }
+
+    let fraction = format!("{:08}", fraction);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+/// Converts a BTC amount like "0.015" to satoshis. Both `.` and `,` are
+/// accepted as decimal sign.
+///
+/// # Returns
+/// None if the text is not a number, has more than 8 decimals or is larger
+/// than [MAX_SATS]
+pub fn btc_to_sats(btc: &str) -> Option<u64> {
+    let btc = btc.trim().replace(',', ".");
+    let (whole, fraction) = btc.split_once('.').unwrap_or((&btc, ""));
+    if (whole.is_empty() && fraction.is_empty()) || fraction.len() > 8 {
+        return None;
+    }
+
+    let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if !digits(whole) || !digits(fraction) {
+        return None;
+    }
+
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let fraction: u64 = format!("{:0<8}", fraction).parse().ok()?;
+    let sats = whole.checked_mul(SATS_PER_BTC)?.checked_add(fraction)?;
+
+    (sats <= MAX_SATS).then_some(sats)
+}
+
+/// Parses an amount entered by the user, e.g. "0.5 BTC", "50000 sats" or
+/// "50000". Without a unit the given one is used.
+///
+/// # Returns
+/// The amount in satoshis and the unit it was entered in, None if the text
+/// is not a valid amount
+pub fn parse_amount(input: &str, default_unit: AmountUnit) -> Option<(u64, AmountUnit)> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let unit = match unit.trim() {
+        "" => default_unit,
+        unit => AmountUnit::from_str(unit).ok()?,
+    };
+
+    let number = number.trim().replace('_', "");
+    let sats = match unit {
+        AmountUnit::Sats => number.parse::<u64>().ok().filter(|s| *s <= MAX_SATS)?,
+        AmountUnit::Btc => btc_to_sats(&number)?,
+    };
+
+    Some((sats, unit))
+}
+
+/// An amount of bitcoin, e.g. a minimum channel size or a budget.
+///
+/// The value is stored in satoshis, the unit only decides how it is shown
+/// and is remembered when the user enters the amount in the other unit.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmountOptionData {
+    id: OptionId,
+    dirty: bool,
+
+    /// Current value in satoshis
+    value: u64,
+
+    /// Value in satoshis as applied to the system
+    original: u64,
+
+    /// The unit the value is shown in
+    unit: AmountUnit,
+
+    /// Least possible value in satoshis
+    min_sats: u64,
+
+    /// Largest possible value in satoshis
+    max_sats: u64,
+}
+
+impl AmountOptionData {
+    /// # Errors
+    /// [ArgumentError::InvalidArgument] if the range is empty, larger than
+    /// [MAX_SATS] or doesn't contain the value
+    pub fn new(
+        id: OptionId,
+        value: u64,
+        unit: AmountUnit,
+        min_sats: u64,
+        max_sats: u64,
+    ) -> Result<Self, ArgumentError> {
+        if min_sats > max_sats || max_sats > MAX_SATS {
+            return Err(ArgumentError::InvalidArgument(
+                format!("{}..{}", min_sats, max_sats),
+                format!("a range within 0..{}", MAX_SATS),
+            ));
+        }
+        if !(min_sats..=max_sats).contains(&value) {
+            return Err(ArgumentError::InvalidArgument(
+                value.to_string(),
+                format!("a value within {}..{}", min_sats, max_sats),
+            ));
+        }
+
+        Ok(Self {
+            id,
+            dirty: false,
+            value,
+            original: value,
+            unit,
+            min_sats,
+            max_sats,
+        })
+    }
+
+    pub fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// The value in satoshis
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn unit(&self) -> AmountUnit {
+        self.unit
+    }
+
+    pub fn min_sats(&self) -> u64 {
+        self.min_sats
+    }
+
+    pub fn max_sats(&self) -> u64 {
+        self.max_sats
+    }
+
+    /// Whether the given amount in satoshis is allowed
+    pub fn in_range(&self, sats: u64) -> bool {
+        (self.min_sats..=self.max_sats).contains(&sats)
+    }
+
+    /// Sets the value in satoshis. Callers must check it with
+    /// [in_range](Self::in_range) first.
+    pub fn set_value(&mut self, value: u64) {
+        self.value = value;
+        self.dirty = self.value != self.original;
+    }
+
+    pub fn set_unit(&mut self, unit: AmountUnit) {
+        self.unit = unit;
+    }
+
+    /// The value formatted in its unit, see [format_amount]
+    pub fn formatted(&self) -> String {
+        format_amount(self.value, self.unit)
+    }
+}
+
+impl ToNixString for AmountOptionData {
+    /// Nix has no unit for amounts, the satoshis are used
+    fn to_nix_string(&self, quote: bool) -> String {
+        if quote {
+            format!("\"{}\"", self.value)
+        } else {
+            self.value.to_string()
+        }
+    }
+}
+
+impl GetOptionId for AmountOptionData {
+    fn id(&self) -> &OptionId {
+        &self.id
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmountOptionChangeData {
+    pub id: OptionId,
+
+    /// The new value in satoshis
+    pub value: u64,
+
+    /// The unit the value was entered in
+    pub unit: AmountUnit,
+}
+
+impl AmountOptionChangeData {
+    pub fn new(id: OptionId, value: u64, unit: AmountUnit) -> Self {
+        Self { id, value, unit }
+    }
+}
+
+impl GetOptionId for AmountOptionChangeData {
+    fn id(&self) -> &OptionId {
+        &self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::apps::SupportedApps;
+
+    use super::*;
+
+    fn id() -> OptionId {
+        OptionId::new(SupportedApps::LND, "test".into())
+    }
+
+    #[test]
+    fn test_amount_option_data_new() {
+        let data = AmountOptionData::new(id(), 20_000, AmountUnit::Sats, 20_000, MAX_SATS).unwrap();
+        assert_eq!(data.value(), 20_000);
+        assert_eq!(data.formatted(), "20000 sats");
+        assert!(!data.dirty());
+
+        assert!(AmountOptionData::new(id(), 10, AmountUnit::Sats, 20, 10).is_err());
+        assert!(AmountOptionData::new(id(), 10, AmountUnit::Sats, 20, 30).is_err());
+        assert!(AmountOptionData::new(id(), 10, AmountUnit::Sats, 0, MAX_SATS + 1).is_err());
+    }
+
+    #[test]
+    fn test_amount_option_data_set_value() {
+        let mut data = AmountOptionData::new(id(), 0, AmountUnit::Btc, 0, SATS_PER_BTC).unwrap();
+        assert!(data.in_range(SATS_PER_BTC));
+        assert!(!data.in_range(SATS_PER_BTC + 1));
+
+        data.set_value(1_500_000);
+        assert!(data.dirty());
+        assert_eq!(data.formatted(), "0.015 BTC");
+        assert_eq!(data.to_nix_string(false), "1500000");
+
+        data.set_value(0);
+        assert!(!data.dirty());
+    }
+
+    #[test]
+    fn test_conversion() {
+        assert_eq!(sats_to_btc(0), "0");
+        assert_eq!(sats_to_btc(1), "0.00000001");
+        assert_eq!(sats_to_btc(250_000_000), "2.5");
+
+        assert_eq!(btc_to_sats("0.015"), Some(1_500_000));
+        assert_eq!(btc_to_sats("0,015"), Some(1_500_000));
+        assert_eq!(btc_to_sats(".5"), Some(50_000_000));
+        assert_eq!(btc_to_sats("21000000"), Some(MAX_SATS));
+        assert_eq!(btc_to_sats("21000000.00000001"), None);
+        assert_eq!(btc_to_sats("0.000000001"), None);
+        assert_eq!(btc_to_sats("1.2.3"), None);
+        assert_eq!(btc_to_sats("-1"), None);
+        assert_eq!(btc_to_sats("."), None);
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(
+            parse_amount("0.5 BTC", AmountUnit::Sats),
+            Some((50_000_000, AmountUnit::Btc))
+        );
+        assert_eq!(
+            parse_amount("50_000sats", AmountUnit::Btc),
+            Some((50_000, AmountUnit::Sats))
+        );
+        assert_eq!(
+            parse_amount("2", AmountUnit::Btc),
+            Some((200_000_000, AmountUnit::Btc))
+        );
+        assert_eq!(
+            parse_amount("2", AmountUnit::Sats),
+            Some((2, AmountUnit::Sats))
+        );
+        assert_eq!(parse_amount("0.5 sats", AmountUnit::Sats), None);
+        assert_eq!(parse_amount("1 eth", AmountUnit::Sats), None);
+        assert_eq!(parse_amount("", AmountUnit::Sats), None);
+    }
+}
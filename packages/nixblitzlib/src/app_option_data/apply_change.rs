@@ -0,0 +1,325 @@
+use error_stack::{Report, Result};
+
+use crate::errors::ProjectError;
+
+use super::{
+    amount_data::{format_amount, AmountOptionData},
+    bool_data::BoolOptionData,
+    code_data::CodeOptionData,
+    manual_string_list_data::ManualStringListOptionData,
+    net_address_data::NetAddressOptionData,
+    net_address_list_data::NetAddressListOptionData,
+    number_data::NumberOptionData,
+    option_data::{GetOptionId, OptionData, OptionDataChangeNotification, OptionId},
+    percent_data::{PercentOptionData, MAX_PERCENT},
+    port_data::PortOptionData,
+    string_list_data::StringListOptionData,
+    text_edit_data::TextOptionData,
+};
+
+/// Option data that is handled the same way in every app: it is listed as is
+/// and simply takes the value of a matching change notification.
+///
+/// Used by [app_options](crate::app_config::app_options) to implement
+/// [AppConfig](crate::app_config::AppConfig) for the fields of an app.
+pub trait ApplyChange {
+    /// Wraps a copy of the data into the matching [OptionData] variant
+    fn to_option_data(&self) -> OptionData;
+
+    /// Takes the value of the given notification
+    ///
+    /// # Returns
+    /// Whether the value changed
+    ///
+    /// # Errors
+    /// [ProjectError::ChangeOptionValueError] if the notification doesn't
+    /// match the kind of the option or the value is not allowed
+    fn apply_change(&mut self, change: &OptionDataChangeNotification)
+        -> Result<bool, ProjectError>;
+}
+
+fn invalid_change(id: &OptionId, change: &OptionDataChangeNotification) -> Report<ProjectError> {
+    Report::new(ProjectError::ChangeOptionValueError(id.option.clone()))
+        .attach_printable(format!("{:?}", change))
+}
+
+impl ApplyChange for BoolOptionData {
+    fn to_option_data(&self) -> OptionData {
+        OptionData::Bool(Box::new(self.clone()))
+    }
+
+    fn apply_change(
+        &mut self,
+        change: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let OptionDataChangeNotification::Bool(val) = change else {
+            return Err(invalid_change(self.id(), change));
+        };
+
+        let changed = self.value() != val.value;
+        self.set_value(val.value);
+        Ok(changed)
+    }
+}
+
+impl ApplyChange for NetAddressOptionData {
+    fn to_option_data(&self) -> OptionData {
+        OptionData::NetAddress(Box::new(self.clone()))
+    }
+
+    fn apply_change(
+        &mut self,
+        change: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let OptionDataChangeNotification::NetAddress(val) = change else {
+            return Err(invalid_change(self.id(), change));
+        };
+
+        let changed = self.value() != val.value;
+        self.set_value(val.value);
+        Ok(changed)
+    }
+}
+
+impl ApplyChange for PortOptionData {
+    fn to_option_data(&self) -> OptionData {
+        OptionData::Port(Box::new(self.clone()))
+    }
+
+    fn apply_change(
+        &mut self,
+        change: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let OptionDataChangeNotification::Port(val) = change else {
+            return Err(invalid_change(self.id(), change));
+        };
+
+        let changed = *self.value() != val.value;
+        self.set_value(val.value.clone());
+        Ok(changed)
+    }
+}
+
+impl ApplyChange for NumberOptionData {
+    fn to_option_data(&self) -> OptionData {
+        OptionData::NumberEdit(Box::new(self.clone()))
+    }
+
+    fn apply_change(
+        &mut self,
+        change: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let OptionDataChangeNotification::Number(val) = change else {
+            return Err(invalid_change(self.id(), change));
+        };
+
+        let changed = *self.value() != val.value;
+        self.set_value(val.value.clone());
+        Ok(changed)
+    }
+}
+
+impl ApplyChange for PercentOptionData {
+    fn to_option_data(&self) -> OptionData {
+        OptionData::Percent(Box::new(self.clone()))
+    }
+
+    fn apply_change(
+        &mut self,
+        change: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let OptionDataChangeNotification::Percent(val) = change else {
+            return Err(invalid_change(self.id(), change));
+        };
+
+        if val.value > MAX_PERCENT {
+            return Err(invalid_change(self.id(), change));
+        }
+
+        let changed = self.value() != val.value;
+        self.set_value(val.value);
+        Ok(changed)
+    }
+}
+
+impl ApplyChange for AmountOptionData {
+    fn to_option_data(&self) -> OptionData {
+        OptionData::Amount(Box::new(self.clone()))
+    }
+
+    fn apply_change(
+        &mut self,
+        change: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let OptionDataChangeNotification::Amount(val) = change else {
+            return Err(invalid_change(self.id(), change));
+        };
+
+        if !self.in_range(val.value) {
+            return Err(invalid_change(self.id(), change).attach_printable(format!(
+                "The amount must be between {} and {}",
+                format_amount(self.min_sats(), val.unit),
+                format_amount(self.max_sats(), val.unit)
+            )));
+        }
+
+        let changed = self.value() != val.value || self.unit() != val.unit;
+        self.set_value(val.value);
+        self.set_unit(val.unit);
+        Ok(changed)
+    }
+}
+
+impl ApplyChange for TextOptionData {
+    fn to_option_data(&self) -> OptionData {
+        OptionData::TextEdit(Box::new(self.clone()))
+    }
+
+    fn apply_change(
+        &mut self,
+        change: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let OptionDataChangeNotification::TextEdit(val) = change else {
+            return Err(invalid_change(self.id(), change));
+        };
+
+        let changed = self.value() != val.value;
+        self.set_value(val.value.clone());
+        Ok(changed)
+    }
+}
+
+impl ApplyChange for CodeOptionData {
+    fn to_option_data(&self) -> OptionData {
+        OptionData::Code(Box::new(self.clone()))
+    }
+
+    /// Fails with [ProjectError::InvalidCode] if the code doesn't pass the
+    /// checks of its language
+    fn apply_change(
+        &mut self,
+        change: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let OptionDataChangeNotification::Code(val) = change else {
+            return Err(invalid_change(self.id(), change));
+        };
+
+        self.check(&val.value)?;
+        let changed = self.value() != val.value;
+        self.set_value(val.value.clone());
+        Ok(changed)
+    }
+}
+
+impl ApplyChange for StringListOptionData {
+    fn to_option_data(&self) -> OptionData {
+        OptionData::StringList(Box::new(self.clone()))
+    }
+
+    /// Only values of the allowed items are accepted
+    fn apply_change(
+        &mut self,
+        change: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let OptionDataChangeNotification::StringList(val) = change else {
+            return Err(invalid_change(self.id(), change));
+        };
+
+        if !self.options().iter().any(|o| o.value == val.value) {
+            return Err(invalid_change(self.id(), change));
+        }
+
+        let changed = self.value() != val.value;
+        self.set_value(val.value.clone());
+        Ok(changed)
+    }
+}
+
+impl ApplyChange for ManualStringListOptionData {
+    fn to_option_data(&self) -> OptionData {
+        OptionData::ManualStringList(Box::new(self.clone()))
+    }
+
+    fn apply_change(
+        &mut self,
+        change: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let OptionDataChangeNotification::ManualStringList(val) = change else {
+            return Err(invalid_change(self.id(), change));
+        };
+
+        let changed = *self.value() != val.value;
+        self.set_value(val.value.clone());
+        Ok(changed)
+    }
+}
+
+impl ApplyChange for NetAddressListOptionData {
+    fn to_option_data(&self) -> OptionData {
+        OptionData::NetAddressList(Box::new(self.clone()))
+    }
+
+    fn apply_change(
+        &mut self,
+        change: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let OptionDataChangeNotification::NetAddressList(val) = change else {
+            return Err(invalid_change(self.id(), change));
+        };
+
+        let old = self.value().clone();
+        self.set_value(val.value.clone());
+        Ok(*self.value() != old)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app_option_data::{
+            bool_data::BoolOptionChangeData,
+            string_list_data::{StringListOptionChangeData, StringListOptionItem},
+        },
+        apps::SupportedApps,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_apply_change() {
+        let id = OptionId::new(SupportedApps::LND, "enable".into());
+        let mut data = BoolOptionData::new(id.clone(), false);
+        let change =
+            OptionDataChangeNotification::Bool(BoolOptionChangeData::new(id.clone(), true));
+
+        assert!(data.apply_change(&change).unwrap());
+        assert!(!data.apply_change(&change).unwrap());
+        assert!(data.value());
+
+        let wrong_kind = OptionDataChangeNotification::StringList(StringListOptionChangeData::new(
+            id.clone(),
+            "true".into(),
+        ));
+        assert!(data.apply_change(&wrong_kind).is_err());
+    }
+
+    #[test]
+    fn test_string_list_only_takes_allowed_values() {
+        let id = OptionId::new(SupportedApps::BitcoinCore, "network".into());
+        let items = ["mainnet", "regtest"]
+            .iter()
+            .map(|i| StringListOptionItem::new(i.to_string(), i.to_string()))
+            .collect();
+        let mut data = StringListOptionData::new(id.clone(), "mainnet".into(), items);
+        let change = |value: &str| {
+            OptionDataChangeNotification::StringList(StringListOptionChangeData::new(
+                id.clone(),
+                value.into(),
+            ))
+        };
+
+        assert!(data.apply_change(&change("testnet")).is_err());
+        assert!(data.apply_change(&change("regtest")).unwrap());
+        assert_eq!(data.value(), "regtest");
+    }
+}
@@ -0,0 +1,311 @@
+use std::fmt;
+
+use alejandra::format;
+use error_stack::{Report, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ProjectError;
+
+use super::option_data::{GetOptionId, OptionId, ToNixString};
+
+/// The language of a code snippet, used for highlighting and linting
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CodeLanguage {
+    /// No highlighting and no checks
+    #[default]
+    Plain,
+
+    /// The body of a Nix attribute set
+    Nix,
+
+    /// Lines of `key=value` pairs as used by bitcoin.conf, lnd.conf, etc.
+    Ini,
+
+    /// One command line option per line, e.g. `-dbcache=100`
+    CmdLine,
+}
+
+impl fmt::Display for CodeLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodeLanguage::Plain => write!(f, "text"),
+            CodeLanguage::Nix => write!(f, "nix"),
+            CodeLanguage::Ini => write!(f, "ini"),
+            CodeLanguage::CmdLine => write!(f, "options"),
+        }
+    }
+}
+
+/// A problem found in a code snippet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    /// The line the issue was found in, starting at 1. 0 if unknown.
+    pub line: usize,
+
+    /// What is wrong
+    pub message: String,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+impl CodeLanguage {
+    /// Checks the given snippet for obvious mistakes
+    ///
+    /// # Returns
+    /// The issues found, empty if the snippet looks fine
+    pub fn lint(&self, code: &str) -> Vec<LintIssue> {
+        match self {
+            CodeLanguage::Plain => vec![],
+            CodeLanguage::Nix => lint_nix(code),
+            CodeLanguage::Ini => lint_lines(code, &["#", ";"], false),
+            CodeLanguage::CmdLine => lint_lines(code, &["#"], true),
+        }
+    }
+}
+
+/// Parses the snippet as the body of an attribute set
+fn lint_nix(code: &str) -> Vec<LintIssue> {
+    if code.trim().is_empty() {
+        return vec![];
+    }
+
+    let (status, _) = format::in_memory("<snippet>".to_string(), format!("{{\n{}\n}}", code));
+    match status {
+        format::Status::Error(e) => vec![LintIssue {
+            line: 0,
+            message: e,
+        }],
+        _ => vec![],
+    }
+}
+
+/// Checks that every line is empty, a comment, a section header or a
+/// `key` or `key=value` pair
+fn lint_lines(code: &str, comments: &[&str], allow_dashes: bool) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    for (index, line) in code.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || comments.iter().any(|c| line.starts_with(c)) {
+            continue;
+        }
+
+        let issue = |message: &str| LintIssue {
+            line: index + 1,
+            message: message.to_string(),
+        };
+
+        if line.starts_with('[') {
+            if !line.ends_with(']') || allow_dashes {
+                issues.push(issue("invalid section header"));
+            }
+            continue;
+        }
+
+        let key = line.split('=').next().unwrap_or_default().trim();
+        let key = if allow_dashes {
+            key.trim_start_matches('-')
+        } else {
+            key
+        };
+
+        if key.is_empty() {
+            issues.push(issue("missing option name"));
+        } else if !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+        {
+            issues.push(issue(&format!("invalid option name \"{}\"", key)));
+        }
+    }
+
+    issues
+}
+
+/// A multiline snippet of code or config, e.g. extra lines for a config file
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Default, Debug)]
+pub struct CodeOptionData {
+    /// Unique identifier for the option
+    id: OptionId,
+
+    /// Current value of the option
+    value: String,
+
+    /// Maximum number of lines allowed
+    max_lines: u16,
+
+    /// Indicates if the current value has been modified from the original
+    /// since last rebuild from the system
+    dirty: bool,
+
+    /// Original value of the option as applied to the system
+    original: String,
+
+    /// The language of the snippet.
+    /// Options stored as plain text before have none.
+    #[serde(default)]
+    language: CodeLanguage,
+}
+
+impl CodeOptionData {
+    pub fn new(
+        id: OptionId,
+        value: String,
+        max_lines: u16,
+        dirty: bool,
+        original: String,
+        language: CodeLanguage,
+    ) -> Self {
+        let max_lines = if max_lines == 0 { 1 } else { max_lines };
+        Self {
+            id,
+            value,
+            max_lines,
+            dirty,
+            original,
+            language,
+        }
+    }
+
+    pub fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn value(&self) -> &str {
+        self.value.as_str()
+    }
+
+    pub fn set_value(&mut self, value: String) {
+        self.dirty = value != self.original;
+        self.value = value;
+    }
+
+    pub fn max_lines(&self) -> u16 {
+        self.max_lines
+    }
+
+    pub fn language(&self) -> CodeLanguage {
+        self.language
+    }
+
+    /// Checks the given value in the language of this option
+    pub fn lint(&self, value: &str) -> Vec<LintIssue> {
+        self.language.lint(value)
+    }
+
+    /// Like [lint](Self::lint), but fails with [ProjectError::InvalidCode]
+    /// if any issue was found
+    pub fn check(&self, value: &str) -> Result<(), ProjectError> {
+        let issues = self.lint(value);
+        if issues.is_empty() {
+            return Ok(());
+        }
+
+        Err(Report::new(ProjectError::InvalidCode(
+            self.id.to_string(),
+            issues.iter().map(|i| i.to_string()).collect(),
+        )))
+    }
+}
+
+impl ToNixString for CodeOptionData {
+    fn to_nix_string(&self, quote: bool) -> String {
+        if quote {
+            format!("\"{}\"", self.value)
+        } else {
+            self.value.clone()
+        }
+    }
+}
+
+impl GetOptionId for CodeOptionData {
+    fn id(&self) -> &OptionId {
+        &self.id
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeOptionChangeData {
+    pub id: OptionId,
+    pub value: String,
+}
+
+impl CodeOptionChangeData {
+    pub fn new(id: OptionId, value: String) -> Self {
+        Self { id, value }
+    }
+}
+
+impl GetOptionId for CodeOptionChangeData {
+    fn id(&self) -> &OptionId {
+        &self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_ini() {
+        let code = "# comment\n[Application Options]\nalias=blitz\n\nnoseedbackup\n";
+        assert!(CodeLanguage::Ini.lint(code).is_empty());
+
+        let issues = CodeLanguage::Ini.lint("[broken\n=value\nmy key=1");
+        assert_eq!(
+            issues.iter().map(|i| i.line).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_lint_cmd_line() {
+        assert!(CodeLanguage::CmdLine
+            .lint("-dbcache=100\noption1\n--debug=net")
+            .is_empty());
+        assert_eq!(CodeLanguage::CmdLine.lint("-db cache=1").len(), 1);
+    }
+
+    #[test]
+    fn test_lint_nix() {
+        assert!(CodeLanguage::Nix
+            .lint("services.foo.enable = true;")
+            .is_empty());
+        assert_eq!(CodeLanguage::Nix.lint("services.foo.enable = ;").len(), 1);
+        assert!(CodeLanguage::Plain.lint("anything { goes").is_empty());
+    }
+
+    #[test]
+    fn test_loads_text_option_json() {
+        let json = r#"{"id":{"app":"LND","option":"extra_config"},"value":"a=1","max_lines":1,"dirty":false,"original":"a=1"}"#;
+        let data: CodeOptionData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.value(), "a=1");
+        assert_eq!(data.language(), CodeLanguage::Plain);
+    }
+
+    #[test]
+    fn test_check() {
+        let data = CodeOptionData::new(
+            OptionId::default(),
+            "".into(),
+            10,
+            false,
+            "".into(),
+            CodeLanguage::Ini,
+        );
+        assert!(data.check("a=1\nb=2").is_ok());
+
+        let err = data.check("a=1\n=2").unwrap_err();
+        assert!(matches!(
+            err.current_context(),
+            ProjectError::InvalidCode(_, issues) if issues == &vec!["line 2: missing option name".to_string()]
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use super::option_data::{GetOptionId, OptionId, ToNixString};
+
+/// A list of free form strings entered by the user, e.g. a list of URIs.
+/// Unlike [StringListOptionData](super::string_list_data::StringListOptionData)
+/// the values are not picked from a fixed set of options.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManualStringListOptionData {
+    /// The id of the option
+    id: OptionId,
+
+    /// The current entries of the list
+    value: Vec<String>,
+
+    /// The original, unaltered entries of the list
+    original: Vec<String>,
+
+    /// Maximum number of entries, 0 means unlimited
+    max_entries: u16,
+
+    /// Whether the option is currently dirty (not yet saved)
+    dirty: bool,
+}
+
+impl ManualStringListOptionData {
+    pub fn new(id: OptionId, value: Vec<String>, max_entries: u16) -> Self {
+        Self {
+            id,
+            value: value.clone(),
+            original: value,
+            max_entries,
+            dirty: false,
+        }
+    }
+
+    pub fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn value(&self) -> &Vec<String> {
+        &self.value
+    }
+
+    /// Sets the entries of the list. Empty entries are dropped and
+    /// surrounding whitespace is removed.
+    pub fn set_value(&mut self, value: Vec<String>) {
+        let value: Vec<String> = value
+            .iter()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect();
+        self.dirty = value != self.original;
+        self.value = value;
+    }
+
+    pub fn max_entries(&self) -> u16 {
+        self.max_entries
+    }
+}
+
+impl ToNixString for ManualStringListOptionData {
+    /// Renders the entries as the items of a nix list, without the brackets
+    fn to_nix_string(&self, quote: bool) -> String {
+        self.value
+            .iter()
+            .map(|v| {
+                if quote {
+                    format!("\"{}\"", v)
+                } else {
+                    v.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl GetOptionId for ManualStringListOptionData {
+    fn id(&self) -> &OptionId {
+        &self.id
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManualStringListOptionChangeData {
+    pub id: OptionId,
+    pub value: Vec<String>,
+}
+
+impl ManualStringListOptionChangeData {
+    pub fn new(id: OptionId, value: Vec<String>) -> Self {
+        Self { id, value }
+    }
+}
+
+impl GetOptionId for ManualStringListOptionChangeData {
+    fn id(&self) -> &OptionId {
+        &self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_value() {
+        let mut data = ManualStringListOptionData::new(OptionId::default(), vec![], 0);
+        data.set_value(vec![" a@b:9911 ".into(), "".into(), "c@d".into()]);
+        assert_eq!(
+            data.value(),
+            &vec!["a@b:9911".to_string(), "c@d".to_string()]
+        );
+        assert!(data.dirty());
+
+        data.set_value(vec![]);
+        assert!(!data.dirty());
+    }
+
+    #[test]
+    fn test_to_nix_string() {
+        let data =
+            ManualStringListOptionData::new(OptionId::default(), vec!["a".into(), "b".into()], 0);
+        assert_eq!(data.to_nix_string(true), "\"a\" \"b\"");
+        assert_eq!(data.to_nix_string(false), "a b");
+    }
+}
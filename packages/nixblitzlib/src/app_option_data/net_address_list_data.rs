@@ -0,0 +1,258 @@
+use std::{fmt, net::IpAddr, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::errors::ParseError;
+
+use super::option_data::{GetOptionId, OptionId, ToNixString};
+
+/// A single IP address or a network in CIDR notation, e.g. `192.168.0.0/16`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NetAddressRange {
+    address: IpAddr,
+    prefix_len: Option<u8>,
+}
+
+impl NetAddressRange {
+    /// # Errors
+    /// [ParseError::AddrParseError] if the prefix is longer than the address
+    pub fn new(address: IpAddr, prefix_len: Option<u8>) -> Result<Self, ParseError> {
+        let max = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_len.is_some_and(|p| p > max) {
+            return Err(ParseError::AddrParseError(format!(
+                "{}/{}",
+                address,
+                prefix_len.unwrap_or_default()
+            )));
+        }
+
+        Ok(Self {
+            address,
+            prefix_len,
+        })
+    }
+
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    /// The length of the network prefix, `None` for a single address
+    pub fn prefix_len(&self) -> Option<u8> {
+        self.prefix_len
+    }
+}
+
+impl From<IpAddr> for NetAddressRange {
+    fn from(address: IpAddr) -> Self {
+        Self {
+            address,
+            prefix_len: None,
+        }
+    }
+}
+
+impl FromStr for NetAddressRange {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || ParseError::AddrParseError(s.to_string());
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix)) => {
+                // u8 accepts a leading '+', bitcoind does not
+                if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(invalid());
+                }
+                (address, Some(prefix.parse::<u8>().map_err(|_| invalid())?))
+            }
+            None => (s, None),
+        };
+        let address = IpAddr::from_str(address).map_err(|_| invalid())?;
+
+        Self::new(address, prefix_len)
+    }
+}
+
+impl fmt::Display for NetAddressRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.prefix_len {
+            Some(prefix_len) => write!(f, "{}/{}", self.address, prefix_len),
+            None => write!(f, "{}", self.address),
+        }
+    }
+}
+
+impl Serialize for NetAddressRange {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for NetAddressRange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        NetAddressRange::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A list of IP addresses and networks, e.g. the hosts allowed to connect
+/// to a service
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetAddressListOptionData {
+    /// The id of the option
+    id: OptionId,
+
+    /// The current entries of the list
+    value: Vec<NetAddressRange>,
+
+    /// The original, unaltered entries of the list
+    original: Vec<NetAddressRange>,
+
+    /// Whether the option is currently dirty (not yet saved)
+    dirty: bool,
+}
+
+impl NetAddressListOptionData {
+    pub fn new(id: OptionId, value: Vec<NetAddressRange>) -> Self {
+        Self {
+            id,
+            value: value.clone(),
+            original: value,
+            dirty: false,
+        }
+    }
+
+    pub fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn value(&self) -> &Vec<NetAddressRange> {
+        &self.value
+    }
+
+    /// Sets the entries of the list, duplicates are dropped
+    pub fn set_value(&mut self, value: Vec<NetAddressRange>) {
+        let mut unique: Vec<NetAddressRange> = Vec::with_capacity(value.len());
+        for v in value {
+            if !unique.contains(&v) {
+                unique.push(v);
+            }
+        }
+        self.dirty = unique != self.original;
+        self.value = unique;
+    }
+
+    /// Adds the entry if it is not in the list yet
+    ///
+    /// # Returns
+    /// `true` if the entry was added
+    pub fn push(&mut self, entry: NetAddressRange) -> bool {
+        if self.value.contains(&entry) {
+            return false;
+        }
+
+        let mut value = self.value.clone();
+        value.push(entry);
+        self.set_value(value);
+        true
+    }
+}
+
+impl ToNixString for NetAddressListOptionData {
+    /// Renders the entries as the items of a nix list, without the brackets
+    fn to_nix_string(&self, quote: bool) -> String {
+        self.value
+            .iter()
+            .map(|v| {
+                if quote {
+                    format!("\"{}\"", v)
+                } else {
+                    v.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl GetOptionId for NetAddressListOptionData {
+    fn id(&self) -> &OptionId {
+        &self.id
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetAddressListOptionChangeData {
+    pub id: OptionId,
+    pub value: Vec<NetAddressRange>,
+}
+
+impl NetAddressListOptionChangeData {
+    pub fn new(id: OptionId, value: Vec<NetAddressRange>) -> Self {
+        Self { id, value }
+    }
+}
+
+impl GetOptionId for NetAddressListOptionChangeData {
+    fn id(&self) -> &OptionId {
+        &self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_net_address_range_from_str() {
+        let range = NetAddressRange::from_str("192.168.0.0/16").unwrap();
+        assert_eq!(range.address(), IpAddr::from_str("192.168.0.0").unwrap());
+        assert_eq!(range.prefix_len(), Some(16));
+        assert_eq!(range.to_string(), "192.168.0.0/16");
+
+        let range = NetAddressRange::from_str(" fd00::/8 ").unwrap();
+        assert_eq!(range.to_string(), "fd00::/8");
+
+        let range = NetAddressRange::from_str("10.0.0.1").unwrap();
+        assert_eq!(range.prefix_len(), None);
+        assert_eq!(range.to_string(), "10.0.0.1");
+
+        assert!(NetAddressRange::from_str("10.0.0.1/33").is_err());
+        assert!(NetAddressRange::from_str("fd00::/129").is_err());
+        assert!(NetAddressRange::from_str("10.0.0.1/").is_err());
+        assert!(NetAddressRange::from_str("10.0.0.1/+8").is_err());
+        assert!(NetAddressRange::from_str("10.0.0/8").is_err());
+        assert!(NetAddressRange::from_str("localhost").is_err());
+    }
+
+    #[test]
+    fn test_net_address_range_serde() {
+        let range = NetAddressRange::from_str("192.168.0.0/16").unwrap();
+        let json = serde_json::to_string(&range).unwrap();
+        assert_eq!(json, "\"192.168.0.0/16\"");
+        assert_eq!(
+            serde_json::from_str::<NetAddressRange>(&json).unwrap(),
+            range
+        );
+        assert!(serde_json::from_str::<NetAddressRange>("\"10.0.0.1/40\"").is_err());
+    }
+
+    #[test]
+    fn test_set_value() {
+        let a = NetAddressRange::from_str("10.0.0.0/8").unwrap();
+        let b = NetAddressRange::from_str("127.0.0.1").unwrap();
+        let mut data = NetAddressListOptionData::new(OptionId::default(), vec![b]);
+
+        data.set_value(vec![a, b, a]);
+        assert_eq!(data.value(), &vec![a, b]);
+        assert!(data.dirty());
+        assert_eq!(data.to_nix_string(true), "\"10.0.0.0/8\" \"127.0.0.1\"");
+
+        assert!(!data.push(b));
+        data.set_value(vec![b]);
+        assert!(!data.dirty());
+    }
+}
@@ -5,10 +5,15 @@ use serde::{Deserialize, Serialize};
 use crate::apps::SupportedApps;
 
 use super::{
+    amount_data::{AmountOptionChangeData, AmountOptionData},
     bool_data::{BoolOptionChangeData, BoolOptionData},
+    code_data::{CodeOptionChangeData, CodeOptionData},
+    manual_string_list_data::{ManualStringListOptionChangeData, ManualStringListOptionData},
     net_address_data::{NetAddressOptionChangeData, NetAddressOptionData},
+    net_address_list_data::{NetAddressListOptionChangeData, NetAddressListOptionData},
     number_data::{NumberOptionChangeData, NumberOptionData},
     password_data::{PasswordOptionChangeData, PasswordOptionData},
+    percent_data::{PercentOptionChangeData, PercentOptionData},
     port_data::{PortOptionChangeData, PortOptionData},
     string_list_data::{StringListOptionChangeData, StringListOptionData},
     text_edit_data::{TextOptionChangeData, TextOptionData},
@@ -51,6 +56,25 @@ impl OptionId {
     }
 }
 
+/// String lists with more items than this are sent without them
+/// in the [OptionsProfile::Web] profile
+pub const LAZY_ITEMS_THRESHOLD: usize = 50;
+
+/// Determines how much of the options is sent to a frontend
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OptionsProfile {
+    /// Everything is included. The TUI holds the project in memory,
+    /// so there is nothing to save.
+    #[default]
+    Tui,
+
+    /// Large string lists (e.g. the timezones) are sent without their items.
+    /// The items are fetched with
+    /// [Project::get_option_items](crate::project::Project::get_option_items)
+    /// once the user opens the list.
+    Web,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum OptionData {
     Bool(Box<BoolOptionData>),
@@ -59,7 +83,12 @@ pub enum OptionData {
     PasswordEdit(Box<PasswordOptionData>),
     NumberEdit(Box<NumberOptionData>),
     NetAddress(Box<NetAddressOptionData>),
+    NetAddressList(Box<NetAddressListOptionData>),
     Port(Box<PortOptionData>),
+    ManualStringList(Box<ManualStringListOptionData>),
+    Code(Box<CodeOptionData>),
+    Percent(Box<PercentOptionData>),
+    Amount(Box<AmountOptionData>),
 }
 
 impl GetOptionId for OptionData {
@@ -71,7 +100,91 @@ impl GetOptionId for OptionData {
             OptionData::PasswordEdit(data) => data.id(),
             OptionData::NumberEdit(data) => data.id(),
             OptionData::NetAddress(data) => data.id(),
+            OptionData::NetAddressList(data) => data.id(),
             OptionData::Port(data) => data.id(),
+            OptionData::ManualStringList(data) => data.id(),
+            OptionData::Code(data) => data.id(),
+            OptionData::Percent(data) => data.id(),
+            OptionData::Amount(data) => data.id(),
+        }
+    }
+}
+
+impl OptionData {
+    /// The name of the option type, as used in the serialized data
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OptionData::Bool(_) => "Bool",
+            OptionData::StringList(_) => "StringList",
+            OptionData::TextEdit(_) => "TextEdit",
+            OptionData::PasswordEdit(_) => "PasswordEdit",
+            OptionData::NumberEdit(_) => "NumberEdit",
+            OptionData::NetAddress(_) => "NetAddress",
+            OptionData::NetAddressList(_) => "NetAddressList",
+            OptionData::Port(_) => "Port",
+            OptionData::ManualStringList(_) => "ManualStringList",
+            OptionData::Code(_) => "Code",
+            OptionData::Percent(_) => "Percent",
+            OptionData::Amount(_) => "Amount",
+        }
+    }
+
+    /// The change that sets an option to the value of this one, e.g. to
+    /// reset it to its default
+    ///
+    /// # Returns
+    /// None for passwords, only their hash is known
+    pub fn to_change(&self) -> Option<OptionDataChangeNotification> {
+        let id = self.id().clone();
+        let change = match self {
+            OptionData::Bool(o) => {
+                OptionDataChangeNotification::Bool(BoolOptionChangeData::new(id, o.value()))
+            }
+            OptionData::StringList(o) => OptionDataChangeNotification::StringList(
+                StringListOptionChangeData::new(id, o.value().to_string()),
+            ),
+            OptionData::TextEdit(o) => OptionDataChangeNotification::TextEdit(
+                TextOptionChangeData::new(id, o.value().to_string()),
+            ),
+            OptionData::PasswordEdit(_) => return None,
+            OptionData::NumberEdit(o) => OptionDataChangeNotification::Number(
+                NumberOptionChangeData::new(id, o.value().clone()),
+            ),
+            OptionData::NetAddress(o) => OptionDataChangeNotification::NetAddress(
+                NetAddressOptionChangeData::new(id, o.value()),
+            ),
+            OptionData::NetAddressList(o) => OptionDataChangeNotification::NetAddressList(
+                NetAddressListOptionChangeData::new(id, o.value().clone()),
+            ),
+            OptionData::Port(o) => {
+                OptionDataChangeNotification::Port(PortOptionChangeData::new(id, o.value().clone()))
+            }
+            OptionData::ManualStringList(o) => OptionDataChangeNotification::ManualStringList(
+                ManualStringListOptionChangeData::new(id, o.value().clone()),
+            ),
+            OptionData::Code(o) => {
+                OptionDataChangeNotification::Code(CodeOptionChangeData::new(id, o.value().into()))
+            }
+            OptionData::Percent(o) => {
+                OptionDataChangeNotification::Percent(PercentOptionChangeData::new(id, o.value()))
+            }
+            OptionData::Amount(o) => OptionDataChangeNotification::Amount(
+                AmountOptionChangeData::new(id, o.value(), o.unit()),
+            ),
+        };
+
+        Some(change)
+    }
+
+    /// Strips the data not needed by a frontend using the given profile
+    pub fn for_profile(self, profile: OptionsProfile) -> Self {
+        match (profile, self) {
+            (OptionsProfile::Web, OptionData::StringList(data))
+                if data.options().len() > LAZY_ITEMS_THRESHOLD =>
+            {
+                OptionData::StringList(Box::new(data.without_items()))
+            }
+            (_, data) => data,
         }
     }
 }
@@ -84,7 +197,12 @@ pub enum OptionDataChangeNotification {
     PasswordEdit(PasswordOptionChangeData),
     Number(NumberOptionChangeData),
     NetAddress(NetAddressOptionChangeData),
+    NetAddressList(NetAddressListOptionChangeData),
     Port(PortOptionChangeData),
+    ManualStringList(ManualStringListOptionChangeData),
+    Code(CodeOptionChangeData),
+    Percent(PercentOptionChangeData),
+    Amount(AmountOptionChangeData),
 }
 
 impl GetOptionId for OptionDataChangeNotification {
@@ -96,7 +214,12 @@ impl GetOptionId for OptionDataChangeNotification {
             OptionDataChangeNotification::PasswordEdit(data) => data.id(),
             OptionDataChangeNotification::Number(data) => data.id(),
             OptionDataChangeNotification::NetAddress(data) => data.id(),
+            OptionDataChangeNotification::NetAddressList(data) => data.id(),
             OptionDataChangeNotification::Port(data) => data.id(),
+            OptionDataChangeNotification::ManualStringList(data) => data.id(),
+            OptionDataChangeNotification::Code(data) => data.id(),
+            OptionDataChangeNotification::Percent(data) => data.id(),
+            OptionDataChangeNotification::Amount(data) => data.id(),
         }
     }
 }
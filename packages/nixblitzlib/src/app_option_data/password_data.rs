@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::secret_store::SecretRef;
+
 use super::option_data::{GetOptionId, OptionId, ToNixString};
 
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Default, Debug)]
@@ -22,6 +24,12 @@ pub struct PasswordOptionData {
 
     /// Am optional to display in the option menu
     subtitle: String,
+
+    /// Where the value is stored encrypted, the hashed value is empty then.
+    /// Only used for passwords the services need in plain text, see
+    /// [secret_store](crate::secret_store).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    secret: Option<SecretRef>,
 }
 
 impl PasswordOptionData {
@@ -40,6 +48,7 @@ impl PasswordOptionData {
             min_length,
             dirty,
             subtitle,
+            secret: None,
         }
     }
 
@@ -67,8 +76,20 @@ impl PasswordOptionData {
         &self.hashed_value
     }
 
+    /// Sets the value, a stored secret is replaced by it
     pub fn set_hashed_value(&mut self, value: String) {
         self.hashed_value = value;
+        self.secret = None;
+    }
+
+    pub fn secret(&self) -> Option<&SecretRef> {
+        self.secret.as_ref()
+    }
+
+    /// Only keeps the reference to the stored secret and drops the value
+    pub fn set_secret(&mut self, secret: SecretRef) {
+        self.hashed_value = String::new();
+        self.secret = Some(secret);
     }
 }
 
@@ -115,6 +136,7 @@ mod tests {
             password_data::PasswordOptionData,
         },
         nix_base_config::NixBaseConfigOption,
+        secret_store::{SecretBackend, SecretRef},
         utils::unix_hash_password,
     };
 
@@ -156,6 +178,18 @@ mod tests {
 
         password_option.set_subtitle("New Subtitle".to_string());
         assert_eq!(password_option.subtitle(), "New Subtitle");
+
+        let secret = SecretRef {
+            backend: SecretBackend::Agenix,
+            name: "initial_password".into(),
+            owner: "root".into(),
+        };
+        password_option.set_secret(secret.clone());
+        assert_eq!(password_option.secret(), Some(&secret));
+        assert!(password_option.hashed_value().is_empty());
+
+        password_option.set_hashed_value("new_hash".to_string());
+        assert!(password_option.secret().is_none());
     }
 
     #[test]
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+
+use super::option_data::{GetOptionId, OptionId, ToNixString};
+
+/// The largest value of a [PercentOptionData]
+pub const MAX_PERCENT: u8 = 100;
+
+/// A share from 0 to 100 percent, e.g. of the memory or the CPU.
+///
+/// Frontends show it as a slider that moves in steps of [step](Self::step).
+/// Values in between are still allowed, e.g. when typed in.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PercentOptionData {
+    id: OptionId,
+    dirty: bool,
+    value: u8,
+    original: u8,
+
+    /// How far the slider moves per key press
+    step: u8,
+}
+
+impl PercentOptionData {
+    /// Values above 100 are clamped, a step of 0 is treated as 1
+    pub fn new(id: OptionId, value: u8, step: u8) -> Self {
+        let value = value.min(MAX_PERCENT);
+        Self {
+            id,
+            value,
+            dirty: false,
+            original: value,
+            step: step.clamp(1, MAX_PERCENT),
+        }
+    }
+
+    pub fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn value(&self) -> u8 {
+        self.value
+    }
+
+    pub fn step(&self) -> u8 {
+        self.step
+    }
+
+    /// Sets the value, clamped to 100
+    pub fn set_value(&mut self, value: u8) {
+        self.value = value.min(MAX_PERCENT);
+        self.dirty = self.value != self.original;
+    }
+
+    /// The value one step above or below the given one
+    ///
+    /// Values between two steps snap to the next step in the given
+    /// direction, so the slider always lands on the steps.
+    pub fn stepped(&self, value: u8, up: bool) -> u8 {
+        let rest = value % self.step;
+        if up {
+            value.saturating_add(self.step - rest).min(MAX_PERCENT)
+        } else if rest != 0 {
+            value - rest
+        } else {
+            value.saturating_sub(self.step)
+        }
+    }
+}
+
+impl ToNixString for PercentOptionData {
+    fn to_nix_string(&self, quote: bool) -> String {
+        if quote {
+            format!("\"{}\"", self.value)
+        } else {
+            self.value.to_string()
+        }
+    }
+}
+
+impl GetOptionId for PercentOptionData {
+    fn id(&self) -> &OptionId {
+        &self.id
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PercentOptionChangeData {
+    pub id: OptionId,
+    pub value: u8,
+}
+
+impl PercentOptionChangeData {
+    pub fn new(id: OptionId, value: u8) -> Self {
+        Self { id, value }
+    }
+}
+
+impl GetOptionId for PercentOptionChangeData {
+    fn id(&self) -> &OptionId {
+        &self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{app_option_data::option_data::ToOptionId, nix_base_config::NixBaseConfigOption};
+
+    use super::*;
+
+    #[test]
+    fn test_percent_option_data_new() {
+        let id = NixBaseConfigOption::ZramPercent.to_option_id();
+        let data = PercentOptionData::new(id.clone(), 150, 0);
+        assert_eq!(data.id(), &id);
+        assert_eq!(data.value(), 100);
+        assert_eq!(data.step(), 1);
+        assert!(!data.dirty());
+    }
+
+    #[test]
+    fn test_percent_option_data_set_value() {
+        let id = NixBaseConfigOption::ZramPercent.to_option_id();
+        let mut data = PercentOptionData::new(id, 50, 5);
+        data.set_value(120);
+        assert_eq!(data.value(), 100);
+        assert!(data.dirty());
+        data.set_value(50);
+        assert!(!data.dirty());
+        assert_eq!(data.to_nix_string(false), "50");
+    }
+
+    #[test]
+    fn test_percent_option_data_stepped() {
+        let id = NixBaseConfigOption::ZramPercent.to_option_id();
+        let data = PercentOptionData::new(id, 50, 5);
+        assert_eq!(data.stepped(50, true), 55);
+        assert_eq!(data.stepped(50, false), 45);
+        assert_eq!(data.stepped(52, true), 55);
+        assert_eq!(data.stepped(52, false), 50);
+        assert_eq!(data.stepped(98, true), 100);
+        assert_eq!(data.stepped(100, true), 100);
+        assert_eq!(data.stepped(3, false), 0);
+        assert_eq!(data.stepped(0, false), 0);
+    }
+}
@@ -37,6 +37,11 @@ pub struct StringListOptionData {
 
     /// Whether the option is currently dirty (not yet saved)
     dirty: bool,
+
+    /// Whether the allowed values were left out to keep the payload small.
+    /// They must be fetched separately before showing the list.
+    #[serde(default)]
+    items_omitted: bool,
 }
 
 impl StringListOptionData {
@@ -49,6 +54,7 @@ impl StringListOptionData {
             options,
             dirty: false,
             original: value,
+            items_omitted: false,
         }
     }
 
@@ -68,6 +74,25 @@ impl StringListOptionData {
     pub fn options(&self) -> &Vec<StringListOptionItem> {
         &self.options
     }
+
+    pub fn items_omitted(&self) -> bool {
+        self.items_omitted
+    }
+
+    /// Returns a copy without the allowed values, see [items_omitted](Self::items_omitted)
+    pub fn without_items(&self) -> Self {
+        Self {
+            options: vec![],
+            items_omitted: true,
+            ..self.clone()
+        }
+    }
+
+    /// Sets the allowed values after they were fetched separately
+    pub fn set_items(&mut self, options: Vec<StringListOptionItem>) {
+        self.options = options;
+        self.items_omitted = false;
+    }
 }
 
 impl ToNixString for StringListOptionData {
@@ -155,6 +180,23 @@ mod tests {
         assert_eq!(data.to_nix_string(false), "value1");
     }
 
+    #[test]
+    fn test_string_list_option_data_without_items() {
+        let options = vec![
+            StringListOptionItem::new("value1".to_string(), "display1".to_string()),
+            StringListOptionItem::new("value2".to_string(), "display2".to_string()),
+        ];
+        let data = StringListOptionData::new(OptionId::default(), "value1".to_string(), options);
+
+        let mut lazy = data.without_items();
+        assert!(lazy.items_omitted());
+        assert!(lazy.options().is_empty());
+        assert_eq!(lazy.value(), "value1");
+
+        lazy.set_items(data.options().clone());
+        assert_eq!(lazy, data);
+    }
+
     #[test]
     fn test_string_list_option_change_data_new() {
         let id = OptionId::default();
@@ -0,0 +1,250 @@
+//! Durations and sizes of the applied configurations.
+//!
+//! Every `nixblitz apply` appends a record to [HISTORY_FILE]. A rebuild that
+//! suddenly takes much longer than the ones before often points to a failing
+//! disk or an unreachable binary cache, [trend] compares the last apply with
+//! the usual duration. The file belongs to the machine and is kept out of
+//! git.
+
+use std::{
+    fs,
+    io::Write,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use error_stack::{Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    defaults::apply_history::{BASELINE, MAX_RECORDS, MIN_SAMPLES, SLOW_FACTOR},
+    errors::ProjectError,
+    utils::{create_file, exclude_from_git},
+};
+
+/// One JSON record per line, relative to the work dir
+pub const HISTORY_FILE: &str = ".nixblitz/apply-history.jsonl";
+
+/// A finished apply
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplyRecord {
+    /// When the apply finished, seconds since the unix epoch
+    pub finished: u64,
+
+    /// How long building and switching took
+    pub duration_secs: u64,
+
+    /// The closure size of the system in bytes, None if unknown
+    pub closure_size: Option<u64>,
+
+    /// Whether the configuration was switched to
+    pub success: bool,
+
+    /// The system generation the apply created, None if unknown
+    #[serde(default)]
+    pub generation: Option<u64>,
+
+    /// The commit of the project that was checked out, None if unknown
+    #[serde(default)]
+    pub commit: Option<String>,
+}
+
+impl ApplyRecord {
+    /// Creates a record of an apply that just finished, without the
+    /// generation and the commit
+    pub fn finished_now(duration_secs: u64, closure_size: Option<u64>, success: bool) -> Self {
+        let finished = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            finished,
+            duration_secs,
+            closure_size,
+            success,
+            generation: None,
+            commit: None,
+        }
+    }
+}
+
+/// How the last successful apply compares with the ones before
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApplyTrend {
+    pub last: ApplyRecord,
+
+    /// The median duration of up to [BASELINE] previous applies
+    pub usual_secs: u64,
+
+    /// The number of previous applies the median is based on
+    pub samples: usize,
+
+    /// The change of the closure size to the previous apply in bytes
+    pub size_change: Option<i64>,
+}
+
+impl ApplyTrend {
+    /// The duration of the last apply relative to the usual one
+    pub fn ratio(&self) -> f64 {
+        self.last.duration_secs as f64 / self.usual_secs.max(1) as f64
+    }
+
+    /// Whether the last apply took abnormally long
+    pub fn is_slow(&self) -> bool {
+        self.samples >= MIN_SAMPLES && self.ratio() >= SLOW_FACTOR
+    }
+}
+
+/// Loads the records, oldest first. Lines that can't be parsed are skipped.
+pub fn load(work_dir: &Path) -> Vec<ApplyRecord> {
+    fs::read_to_string(work_dir.join(HISTORY_FILE))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect()
+}
+
+/// Appends a record, keeping the last [MAX_RECORDS]
+///
+/// # Errors
+/// [ProjectError::ApplyHistoryError] if the file can't be written
+pub fn record(work_dir: &Path, record: &ApplyRecord) -> Result<(), ProjectError> {
+    let path = work_dir.join(HISTORY_FILE);
+    let mut records = load(work_dir);
+    records.push(record.clone());
+    let skip = records.len().saturating_sub(MAX_RECORDS);
+
+    let mut contents = vec![];
+    for r in records.iter().skip(skip) {
+        let line = serde_json::to_string(r).change_context(ProjectError::ApplyHistoryError)?;
+        writeln!(contents, "{}", line).change_context(ProjectError::ApplyHistoryError)?;
+    }
+
+    create_file(&path, &contents, Some(true))?;
+
+    exclude_from_git(work_dir, HISTORY_FILE)
+}
+
+/// Compares the last successful apply with the previous ones
+///
+/// # Returns
+/// None if there is no successful apply yet
+pub fn trend(records: &[ApplyRecord]) -> Option<ApplyTrend> {
+    let successful: Vec<&ApplyRecord> = records.iter().filter(|r| r.success).collect();
+    let (last, previous) = successful.split_last()?;
+    let baseline = &previous[previous.len().saturating_sub(BASELINE)..];
+
+    let mut durations: Vec<u64> = baseline.iter().map(|r| r.duration_secs).collect();
+    durations.sort_unstable();
+    let usual_secs = durations
+        .get(durations.len() / 2)
+        .copied()
+        .unwrap_or(last.duration_secs);
+    let size_change = match (
+        previous.last().and_then(|r| r.closure_size),
+        last.closure_size,
+    ) {
+        (Some(before), Some(after)) => Some(after as i64 - before as i64),
+        _ => None,
+    };
+
+    Some(ApplyTrend {
+        last: (*last).clone(),
+        usual_secs,
+        samples: baseline.len(),
+        size_change,
+    })
+}
+
+/// Returns the closure size of a store path in bytes, e.g. of
+/// `/run/current-system`
+pub fn closure_size(path: &Path) -> Option<u64> {
+    let output = Command::new("nix")
+        .args(["path-info", "--closure-size"])
+        .arg(path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    parse_path_info(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parses the `<path> <size>` line of `nix path-info --closure-size`
+fn parse_path_info(output: &str) -> Option<u64> {
+    output
+        .lines()
+        .next()?
+        .split_whitespace()
+        .last()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn apply(duration_secs: u64, closure_size: u64) -> ApplyRecord {
+        ApplyRecord {
+            finished: 1730000000,
+            duration_secs,
+            closure_size: Some(closure_size),
+            success: true,
+            generation: None,
+            commit: None,
+        }
+    }
+
+    #[test]
+    fn test_record_and_load() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        assert!(load(dir.path()).is_empty());
+
+        for i in 0..MAX_RECORDS + 5 {
+            record(dir.path(), &apply(i as u64, 1000)).unwrap();
+        }
+        let records = load(dir.path());
+        assert_eq!(records.len(), MAX_RECORDS);
+        assert_eq!(records[0].duration_secs, 5);
+
+        let excluded = fs::read_to_string(dir.path().join(".git/info/exclude")).unwrap();
+        assert_eq!(excluded, format!("{}\n", HISTORY_FILE));
+    }
+
+    #[test]
+    fn test_trend() {
+        assert!(trend(&[]).is_none());
+
+        let mut records = vec![apply(300, 1000), apply(280, 1000), apply(320, 1100)];
+        let first = trend(&records).unwrap();
+        assert_eq!(first.samples, 2);
+        assert!(!first.is_slow());
+
+        records.push(ApplyRecord {
+            success: false,
+            ..apply(5, 0)
+        });
+        records.push(apply(900, 1200));
+        let slow = trend(&records).unwrap();
+        assert_eq!(slow.usual_secs, 300);
+        assert_eq!(slow.samples, 3);
+        assert_eq!(slow.size_change, Some(100));
+        assert!(slow.is_slow());
+    }
+
+    #[test]
+    fn test_parse_path_info() {
+        assert_eq!(
+            parse_path_info("/nix/store/abc-nixos-system-nixblitz-24.11\t 4212871680\n"),
+            Some(4212871680)
+        );
+        assert_eq!(parse_path_info(""), None);
+    }
+}
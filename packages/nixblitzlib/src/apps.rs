@@ -1,7 +1,10 @@
 use core::fmt;
 
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
+use crate::registry;
+
 #[derive(Default, Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
 pub enum SupportedApps {
     #[default]
@@ -11,67 +14,45 @@ pub enum SupportedApps {
     LND,
     BlitzAPI,
     WebUI,
+    Electrs,
+    Mempool,
+    ThunderHub,
+    Tor,
+    Fulcrum,
+    LightningTerminal,
+    ChannelBackup,
+    Tailscale,
+    Monitoring,
+    Grafana,
+    SystemBackup,
+    ElectrumPersonalServer,
+    I2P,
+    BalanceOfSatoshis,
+    ChargeLnd,
+    Phoenixd,
+    Elements,
+    Mining,
 }
 
-impl SupportedApps {
-    const APP_NAMES: [&'static str; 6] = [
-        "Nix OS",
-        "Bitcoin Core",
-        "Core Lightning",
-        "LND",
-        "Blitz Api",
-        "Web UI",
-    ];
+/// The titles of all apps, in the order of their ids
+static APP_NAMES: Lazy<Vec<&'static str>> =
+    Lazy::new(|| registry::APPS.iter().map(|r| r.title).collect());
 
+impl SupportedApps {
     pub fn from(s: &str) -> Option<Self> {
-        if s == Self::APP_NAMES[0] {
-            return Some(SupportedApps::NixOS);
-        } else if s == Self::APP_NAMES[1] {
-            return Some(SupportedApps::BitcoinCore);
-        } else if s == Self::APP_NAMES[2] {
-            return Some(SupportedApps::CoreLightning);
-        } else if s == Self::APP_NAMES[3] {
-            return Some(SupportedApps::LND);
-        } else if s == Self::APP_NAMES[4] {
-            return Some(SupportedApps::BlitzAPI);
-        } else if s == Self::APP_NAMES[5] {
-            return Some(SupportedApps::WebUI);
-        }
-
-        None
+        registry::APPS.iter().find(|r| r.title == s).map(|r| r.app)
     }
 
     pub fn from_id(id: usize) -> Option<Self> {
-        if id == 0 {
-            return Some(SupportedApps::NixOS);
-        } else if id == 1 {
-            return Some(SupportedApps::BitcoinCore);
-        } else if id == 2 {
-            return Some(SupportedApps::CoreLightning);
-        } else if id == 3 {
-            return Some(SupportedApps::LND);
-        } else if id == 4 {
-            return Some(SupportedApps::BlitzAPI);
-        } else if id == 5 {
-            return Some(SupportedApps::WebUI);
-        }
-
-        None
+        registry::APPS.get(id).map(|r| r.app)
     }
 
     pub fn to_string(&self) -> &'static str {
-        match self {
-            SupportedApps::NixOS => Self::APP_NAMES[0],
-            SupportedApps::BitcoinCore => Self::APP_NAMES[1],
-            SupportedApps::CoreLightning => Self::APP_NAMES[2],
-            SupportedApps::LND => Self::APP_NAMES[3],
-            SupportedApps::BlitzAPI => Self::APP_NAMES[4],
-            SupportedApps::WebUI => Self::APP_NAMES[5],
-        }
+        registry::registration(*self).title
     }
 
     pub fn as_string_list() -> &'static [&'static str] {
-        &Self::APP_NAMES
+        &APP_NAMES
     }
 }
 
@@ -94,6 +75,24 @@ mod tests {
             SupportedApps::LND,
             SupportedApps::BlitzAPI,
             SupportedApps::WebUI,
+            SupportedApps::Electrs,
+            SupportedApps::Mempool,
+            SupportedApps::ThunderHub,
+            SupportedApps::Tor,
+            SupportedApps::Fulcrum,
+            SupportedApps::LightningTerminal,
+            SupportedApps::ChannelBackup,
+            SupportedApps::Tailscale,
+            SupportedApps::Monitoring,
+            SupportedApps::Grafana,
+            SupportedApps::SystemBackup,
+            SupportedApps::ElectrumPersonalServer,
+            SupportedApps::I2P,
+            SupportedApps::BalanceOfSatoshis,
+            SupportedApps::ChargeLnd,
+            SupportedApps::Phoenixd,
+            SupportedApps::Elements,
+            SupportedApps::Mining,
         ] {
             let string = app.to_string();
             assert_eq!(SupportedApps::from(string), Some(app));
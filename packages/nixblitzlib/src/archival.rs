@@ -0,0 +1,229 @@
+//! The upgrade of a pruned node to an archival node.
+//!
+//! Bitcoin Core can't simply stop pruning. The deleted blocks have to be
+//! downloaded again and the block index rebuilt with `reindex=1`, until
+//! then it refuses to start. Switching the prune option off is therefore
+//! rejected with [ProjectError::ArchivalUpgradeRequired]. [plan] collects
+//! what the user has to know, [Project::upgrade_to_archival] switches
+//! pruning off and schedules the reindex, right after the next apply or in
+//! the maintenance window of the node. [progress] follows the reindex via
+//! RPC and must run on the node.
+
+use std::path::{Path, PathBuf};
+
+use error_stack::{Report, Result};
+
+use crate::{
+    app_option_data::option_data::{GetOptionId, OptionData, ToOptionId},
+    apps::SupportedApps,
+    bitcoind::{BitcoindConfigOption, PruneOptions},
+    defaults, dependencies, disk_usage,
+    errors::ProjectError,
+    project::Project,
+    status::{self, ChainSync},
+};
+
+/// The file the reindex unit creates once it started the reindex
+const REINDEX_MARKER: &str = "nixblitz-reindexed";
+
+/// What the upgrade to an archival node involves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivalPlan {
+    /// The current prune mode, e.g. "Automatic(2048)"
+    pub prune: String,
+
+    /// The data dir the block chain is stored in
+    pub data_dir: PathBuf,
+
+    /// The space the block chain will take
+    pub required_gb: u64,
+
+    /// The enabled apps that wait for Bitcoin Core during the reindex
+    pub affected: Vec<SupportedApps>,
+
+    /// The schedule of the chain verification, the quiet window of the
+    /// node, if there is one
+    pub maintenance_window: Option<String>,
+}
+
+impl ArchivalPlan {
+    /// Explains the consequences of the upgrade, one sentence per line
+    pub fn consequences(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!(
+                "Bitcoin Core stops pruning ({}) and downloads the deleted blocks again.",
+                self.prune
+            ),
+            format!(
+                "The block chain needs about {} GB in {}, make sure the disk has the space.",
+                self.required_gb,
+                self.data_dir.display()
+            ),
+            "Bitcoin Core rebuilds its block index with a reindex, which takes hours on fast \
+             hardware and days on a Raspberry Pi."
+                .to_string(),
+            "Bitcoin Core stays stopped from the apply until the reindex starts.".to_string(),
+        ];
+        if !self.affected.is_empty() {
+            let names: Vec<String> = self.affected.iter().map(|a| a.to_string()).collect();
+            lines.push(format!(
+                "{} can't follow the chain until the reindex is done.",
+                names.join(", ")
+            ));
+        }
+
+        lines
+    }
+}
+
+/// Where a reindex stands
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReindexProgress {
+    /// Waits for its schedule, a systemd calendar event or
+    /// [REINDEX_NOW](crate::bitcoind::REINDEX_NOW)
+    Scheduled(String),
+
+    /// Bitcoin Core is rebuilding its index or fetching blocks
+    Running(ChainSync),
+
+    /// Bitcoin Core has the whole chain
+    Done(ChainSync),
+}
+
+/// Collects what the upgrade involves
+///
+/// # Errors
+/// [ProjectError::ArchivalError] if Bitcoin Core doesn't prune
+pub fn plan(project: &Project) -> Result<ArchivalPlan, ProjectError> {
+    let (prune, _) = project.prune_state();
+    if prune == PruneOptions::Disable {
+        return Err(Report::new(ProjectError::ArchivalError)
+            .attach_printable("Bitcoin Core doesn't prune the block chain"));
+    }
+
+    let maintenance_window = project
+        .get_options_for(SupportedApps::BitcoinCore)
+        .into_iter()
+        .find_map(|o| match o {
+            OptionData::TextEdit(t)
+                if *t.id() == BitcoindConfigOption::VerifyChainSchedule.to_option_id() =>
+            {
+                Some(t.value().trim().to_string())
+            }
+            _ => None,
+        })
+        .filter(|s| !s.is_empty());
+
+    Ok(ArchivalPlan {
+        prune: prune.to_string(),
+        data_dir: bitcoind_data_dir(project),
+        required_gb: defaults::bitcoind::ARCHIVAL_SIZE_GB,
+        affected: dependencies::dependents(SupportedApps::BitcoinCore)
+            .into_iter()
+            .filter(|a| project.is_app_enabled(*a))
+            .collect(),
+        maintenance_window,
+    })
+}
+
+/// Follows the reindex after the upgrade
+///
+/// # Errors
+/// [ProjectError::ArchivalError] if Bitcoin Core still prunes and
+/// [ProjectError::NodeUnreachable] if it doesn't answer
+pub fn progress(project: &Project) -> Result<ReindexProgress, ProjectError> {
+    let (prune, schedule) = project.prune_state();
+    if prune != PruneOptions::Disable {
+        return Err(Report::new(ProjectError::ArchivalError)
+            .attach_printable("Bitcoin Core still prunes the block chain"));
+    }
+
+    if let Some(schedule) = schedule {
+        if !reindex_started(&bitcoind_data_dir(project)) {
+            return Ok(ReindexProgress::Scheduled(schedule));
+        }
+    }
+
+    let sync = status::chain_sync()?;
+    Ok(classify(sync))
+}
+
+fn classify(sync: ChainSync) -> ReindexProgress {
+    if sync.initial_block_download || sync.pruned || sync.blocks < sync.headers {
+        ReindexProgress::Running(sync)
+    } else {
+        ReindexProgress::Done(sync)
+    }
+}
+
+/// Whether the reindex unit ran. A data dir we aren't allowed to look into
+/// counts as started, the RPC knows better.
+fn reindex_started(data_dir: &Path) -> bool {
+    data_dir.join(REINDEX_MARKER).try_exists().unwrap_or(true)
+}
+
+fn bitcoind_data_dir(project: &Project) -> PathBuf {
+    disk_usage::data_dirs(project)
+        .into_iter()
+        .find(|(app, _)| *app == SupportedApps::BitcoinCore)
+        .map(|(_, dir)| dir)
+        .unwrap_or_else(|| PathBuf::from(defaults::bitcoind::DATA_DIR))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::{
+            option_data::OptionDataChangeNotification, string_list_data::StringListOptionChangeData,
+        },
+        utils::init_default_project,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_plan() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+        init_default_project(work_dir, Some(false)).unwrap();
+
+        let mut project = Project::load(work_dir.to_path_buf()).unwrap();
+        assert!(plan(&project).is_err());
+
+        project.set_selected_app(SupportedApps::BitcoinCore);
+        project
+            .on_option_changed(OptionDataChangeNotification::StringList(
+                StringListOptionChangeData::new(
+                    BitcoindConfigOption::Prune.to_option_id(),
+                    PruneOptions::Manual.to_string(),
+                ),
+            ))
+            .unwrap();
+
+        let plan = plan(&project).unwrap();
+        assert_eq!(plan.prune, "Manual");
+        assert_eq!(plan.required_gb, defaults::bitcoind::ARCHIVAL_SIZE_GB);
+        assert!(plan.consequences()[0].contains("(Manual)"));
+    }
+
+    #[test]
+    fn test_classify() {
+        let sync = |blocks, initial_block_download| ChainSync {
+            blocks,
+            headers: 870000,
+            progress: 0.5,
+            initial_block_download,
+            pruned: false,
+        };
+        assert!(matches!(
+            classify(sync(420000, true)),
+            ReindexProgress::Running(_)
+        ));
+        assert!(matches!(
+            classify(sync(870000, false)),
+            ReindexProgress::Done(_)
+        ));
+    }
+}
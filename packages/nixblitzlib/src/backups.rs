@@ -0,0 +1,414 @@
+//! Backups of the project repository as git bundles.
+//!
+//! A bundle holds the complete history of the project, so a backup can be
+//! restored even if the repository itself was damaged. Old backups are thinned
+//! out with a daily and weekly retention policy. Bundles can be exported
+//! and imported together with their SHA-256 checksum to keep a copy off the
+//! node.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use error_stack::{Report, Result, ResultExt};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    errors::ProjectError,
+    utils::{to_hex, write_file_atomic},
+};
+
+/// Directory inside the work dir the bundles are stored in.
+/// It lives inside .git so the backups are never committed.
+pub const BACKUP_DIR: &str = ".git/nixblitz-backups";
+
+/// All backups created by nixblitz start with this prefix, others are never touched
+pub const BACKUP_PREFIX: &str = "nixblitz-";
+
+/// Imported bundles larger than this are rejected
+pub const MAX_IMPORT_SIZE: usize = 64 * 1024 * 1024;
+
+const BUNDLE_EXTENSION: &str = ".bundle";
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+
+/// A git bundle of the project repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    /// Name of the backup, e.g. "nixblitz-1700000000". Backups created in
+    /// the same second get a counter, e.g. "nixblitz-1700000000-1".
+    pub name: String,
+
+    /// Unix timestamp the backup was created at
+    pub created: u64,
+}
+
+impl Backup {
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        let name = file_name.strip_suffix(BUNDLE_EXTENSION)?;
+        let stamp = name.strip_prefix(BACKUP_PREFIX)?;
+        let created = match stamp.split_once('-') {
+            Some((created, counter)) => {
+                counter.parse::<u32>().ok()?;
+                created
+            }
+            None => stamp,
+        };
+        let created = created.parse().ok()?;
+
+        Some(Self {
+            name: name.to_string(),
+            created,
+        })
+    }
+
+    /// Path of the bundle inside the given work dir
+    pub fn path(&self, work_dir: &Path) -> PathBuf {
+        work_dir
+            .join(BACKUP_DIR)
+            .join(format!("{}{}", self.name, BUNDLE_EXTENSION))
+    }
+}
+
+/// Bundles all committed branches and tags of the project
+pub fn create_backup(work_dir: &Path) -> Result<Backup, ProjectError> {
+    let backup = new_backup(work_dir)?;
+
+    git(
+        work_dir,
+        &[
+            "bundle",
+            "create",
+            &backup.path(work_dir).to_string_lossy(),
+            "--all",
+        ],
+    )?;
+
+    Ok(backup)
+}
+
+/// Lists the backups of the project, oldest first
+pub fn list_backups(work_dir: &Path) -> Result<Vec<Backup>, ProjectError> {
+    let dir = work_dir.join(BACKUP_DIR);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(vec![]),
+    };
+
+    let mut backups: Vec<Backup> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().to_str().and_then(Backup::from_file_name))
+        .collect();
+    // the counter of backups created in the same second has no leading zeros
+    backups.sort_by_key(|b| (b.created, b.name.len(), b.name.clone()));
+
+    Ok(backups)
+}
+
+/// Deletes all backups not covered by the retention policy
+///
+/// # Arguments
+/// * `keep_daily` - number of days to keep the newest backup of
+/// * `keep_weekly` - number of weeks to keep the newest backup of
+///
+/// # Returns
+/// The deleted backups
+pub fn prune_backups(
+    work_dir: &Path,
+    keep_daily: usize,
+    keep_weekly: usize,
+) -> Result<Vec<Backup>, ProjectError> {
+    let backups = list_backups(work_dir)?;
+    let created: Vec<u64> = backups.iter().map(|b| b.created).collect();
+    let retained = retained_backups(&created, keep_daily, keep_weekly);
+
+    let mut deleted = vec![];
+    for backup in backups {
+        if retained.contains(&backup.created) {
+            continue;
+        }
+
+        let path = backup.path(work_dir);
+        fs::remove_file(&path)
+            .change_context(ProjectError::BackupError)
+            .attach_printable_lazy(|| format!("Unable to delete {}", path.display()))?;
+        deleted.push(backup);
+    }
+
+    Ok(deleted)
+}
+
+/// Reads the bundle of the given backup for download
+///
+/// # Returns
+/// The contents of the bundle and their hex encoded SHA-256 checksum
+pub fn export_backup(work_dir: &Path, name: &str) -> Result<(Vec<u8>, String), ProjectError> {
+    let backup = find_backup(work_dir, name)?;
+    let path = backup.path(work_dir);
+    let data = fs::read(&path)
+        .change_context(ProjectError::BackupError)
+        .attach_printable_lazy(|| format!("Unable to read {}", path.display()))?;
+    let checksum = to_hex(&Sha256::digest(&data));
+
+    Ok((data, checksum))
+}
+
+/// Stores an uploaded bundle as a new backup so it can be restored with
+/// [restore_backup].
+///
+/// # Arguments
+/// * `data` - the contents of the bundle
+/// * `checksum` - the hex encoded SHA-256 checksum the uploader computed
+///
+/// # Errors
+/// [ProjectError::BackupError] if the bundle is larger than
+/// [MAX_IMPORT_SIZE], the checksum doesn't match or git doesn't accept
+/// the bundle. Nothing is kept in that case.
+pub fn import_backup(work_dir: &Path, data: &[u8], checksum: &str) -> Result<Backup, ProjectError> {
+    if data.len() > MAX_IMPORT_SIZE {
+        return Err(
+            Report::new(ProjectError::BackupError).attach_printable(format!(
+                "The bundle has {} bytes, at most {} are allowed",
+                data.len(),
+                MAX_IMPORT_SIZE
+            )),
+        );
+    }
+
+    let actual = to_hex(&Sha256::digest(data));
+    if !actual.eq_ignore_ascii_case(checksum.trim()) {
+        return Err(
+            Report::new(ProjectError::BackupError).attach_printable(format!(
+                "Checksum mismatch: expected {}, got {}",
+                checksum, actual
+            )),
+        );
+    }
+
+    let backup = new_backup(work_dir)?;
+    let path = backup.path(work_dir);
+    write_file_atomic(&path, data, None).change_context(ProjectError::BackupError)?;
+
+    if let Err(e) = git(work_dir, &["bundle", "verify", &path.to_string_lossy()]) {
+        let _ = fs::remove_file(&path);
+        return Err(e.attach_printable("The uploaded file is not a valid bundle"));
+    }
+
+    Ok(backup)
+}
+
+/// Checks out the files of the given backup into the working tree.
+///
+/// The branches and HEAD are left alone, the restored files show up as
+/// uncommitted changes and can be reviewed before committing them.
+pub fn restore_backup(work_dir: &Path, name: &str) -> Result<(), ProjectError> {
+    let backup = find_backup(work_dir, name)?;
+
+    git(
+        work_dir,
+        &["fetch", &backup.path(work_dir).to_string_lossy(), "HEAD"],
+    )?;
+    git(work_dir, &["checkout", "FETCH_HEAD", "--", "."])?;
+
+    Ok(())
+}
+
+/// Returns a backup created now whose bundle doesn't exist yet and creates
+/// the [BACKUP_DIR]
+fn new_backup(work_dir: &Path) -> Result<Backup, ProjectError> {
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .change_context(ProjectError::BackupError)?
+        .as_secs();
+
+    let dir = work_dir.join(BACKUP_DIR);
+    fs::create_dir_all(&dir)
+        .change_context(ProjectError::BackupError)
+        .attach_printable_lazy(|| format!("Unable to create {}", dir.display()))?;
+
+    let mut backup = Backup {
+        name: format!("{}{}", BACKUP_PREFIX, created),
+        created,
+    };
+    let mut counter = 0;
+    while backup.path(work_dir).exists() {
+        counter += 1;
+        backup.name = format!("{}{}-{}", BACKUP_PREFIX, created, counter);
+    }
+
+    Ok(backup)
+}
+
+fn find_backup(work_dir: &Path, name: &str) -> Result<Backup, ProjectError> {
+    list_backups(work_dir)?
+        .into_iter()
+        .find(|b| b.name == name)
+        .ok_or_else(|| {
+            Report::new(ProjectError::BackupError)
+                .attach_printable(format!("Backup {} not found", name))
+        })
+}
+
+/// Returns the timestamps of the backups to keep.
+///
+/// For each of the newest `keep_daily` days and `keep_weekly` weeks that
+/// have a backup, the newest backup of that day or week is kept.
+fn retained_backups(created: &[u64], keep_daily: usize, keep_weekly: usize) -> Vec<u64> {
+    let mut sorted = created.to_vec();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut keep = vec![];
+    for (period, count) in [
+        (SECONDS_PER_DAY, keep_daily),
+        (SECONDS_PER_DAY * 7, keep_weekly),
+    ] {
+        let mut periods = vec![];
+        for ts in sorted.iter() {
+            if periods.len() == count {
+                break;
+            }
+
+            let p = ts / period;
+            if !periods.contains(&p) {
+                periods.push(p);
+                if !keep.contains(ts) {
+                    keep.push(*ts);
+                }
+            }
+        }
+    }
+
+    keep
+}
+
+fn git(work_dir: &Path, args: &[&str]) -> Result<(), ProjectError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(work_dir)
+        .args(args)
+        .output()
+        .change_context(ProjectError::BackupError)
+        .attach_printable("Unable to run git")?;
+
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::BackupError).attach_printable(format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_backup_from_file_name() {
+        assert_eq!(
+            Backup::from_file_name("nixblitz-1700000000.bundle"),
+            Some(Backup {
+                name: "nixblitz-1700000000".into(),
+                created: 1700000000
+            })
+        );
+        assert_eq!(
+            Backup::from_file_name("nixblitz-1700000000-2.bundle"),
+            Some(Backup {
+                name: "nixblitz-1700000000-2".into(),
+                created: 1700000000
+            })
+        );
+        assert_eq!(Backup::from_file_name("nixblitz-1700000000"), None);
+        assert_eq!(Backup::from_file_name("nixblitz-1700000000-x.bundle"), None);
+        assert_eq!(Backup::from_file_name("other-1700000000.bundle"), None);
+    }
+
+    #[test]
+    fn test_import_backup_checks_checksum() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+        let data = b"not a bundle";
+
+        let wrong = to_hex(&Sha256::digest(b"something else"));
+        assert!(import_backup(work_dir, data, &wrong).is_err());
+        assert!(list_backups(work_dir).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_import_backup() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+        git(work_dir, &["init", "-q"]).unwrap();
+        fs::write(work_dir.join("flake.nix"), "{}").unwrap();
+        git(work_dir, &["add", "--all"]).unwrap();
+        git(
+            work_dir,
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-q",
+                "-m",
+                "init",
+            ],
+        )
+        .unwrap();
+
+        let created = create_backup(work_dir).unwrap();
+        let (data, checksum) = export_backup(work_dir, &created.name).unwrap();
+
+        // imports in the same second as the backup get their own names
+        let first = import_backup(work_dir, &data, &checksum).unwrap();
+        let second = import_backup(work_dir, &data, &checksum.to_uppercase()).unwrap();
+        assert_ne!(first.name, created.name);
+        assert_ne!(first.name, second.name);
+        assert_eq!(fs::read(second.path(work_dir)).unwrap(), data);
+
+        let names: Vec<String> = list_backups(work_dir)
+            .unwrap()
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(names.len(), 3);
+        assert!(names.contains(&first.name) && names.contains(&second.name));
+
+        fs::write(work_dir.join("flake.nix"), "changed").unwrap();
+        restore_backup(work_dir, &second.name).unwrap();
+        assert_eq!(
+            fs::read_to_string(work_dir.join("flake.nix")).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn test_retained_backups() {
+        let day = SECONDS_PER_DAY;
+        // two backups on each of the last 10 days
+        let created: Vec<u64> = (0..10)
+            .flat_map(|d| [d * day + 100, d * day + 200])
+            .collect();
+
+        let mut keep = retained_backups(&created, 3, 0);
+        keep.sort();
+        assert_eq!(keep, vec![7 * day + 200, 8 * day + 200, 9 * day + 200]);
+
+        // days 0 to 6 are in the first week, the newest backup of the
+        // second week is also the newest daily one
+        let mut keep = retained_backups(&created, 1, 2);
+        keep.sort();
+        assert_eq!(keep, vec![6 * day + 200, 9 * day + 200]);
+
+        assert!(retained_backups(&created, 0, 0).is_empty());
+    }
+}
@@ -1,7 +1,10 @@
 use core::{fmt, str};
-use std::{collections::HashMap, net::IpAddr, path::Path, str::FromStr};
-
-use alejandra::format;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    str::FromStr,
+};
 
 use error_stack::{Report, Result, ResultExt};
 use handlebars::{no_escape, Handlebars};
@@ -9,14 +12,17 @@ use serde::{Deserialize, Serialize};
 use strum::EnumCount;
 
 use crate::{
-    app_config::AppConfig,
+    app_config::{app_options, AppConfig},
     app_option_data::{
+        apply_change::ApplyChange,
         bool_data::BoolOptionData,
+        code_data::{CodeLanguage, CodeOptionData},
+        manual_string_list_data::ManualStringListOptionData,
         net_address_data::NetAddressOptionData,
+        net_address_list_data::{NetAddressListOptionData, NetAddressRange},
         number_data::NumberOptionData,
         option_data::{
-            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToNixString,
-            ToOptionId,
+            OptionData, OptionDataChangeNotification, OptionId, ToNixString, ToOptionId,
         },
         password_data::PasswordOptionData,
         port_data::PortOptionData,
@@ -24,14 +30,27 @@ use crate::{
         text_edit_data::TextOptionData,
     },
     apps::SupportedApps,
+    defaults,
     errors::{ProjectError, TemplatingError},
+    i2p::I2pService,
+    migrations,
+    nix_format::format_nix,
     number_value::NumberValue,
-    utils::{update_file, BASE_TEMPLATE},
+    platform::get_system_platform,
+    tor::{port_or, HiddenService, HiddenServiceProvider},
+    utils::{create_rpcauth_hmac, update_file, update_nix_files, BASE_TEMPLATE},
 };
 
 pub const TEMPLATE_FILE_NAME: &str = "src/apps/bitcoind.nix.templ";
 pub const JSON_FILE_NAME: &str = "src/apps/bitcoind.json";
 
+/// The [reindex_schedule](BitcoinDaemonService::reindex_schedule) to
+/// reindex right after the next apply
+pub const REINDEX_NOW: &str = "now";
+
+/// The file bitcoind includes to be started once with `reindex=1`
+const REINDEX_CONF: &str = "nixblitz-reindex.conf";
+
 #[derive(Debug, Clone, Copy, EnumCount, Hash, PartialEq, Serialize, Deserialize)]
 pub enum BitcoindConfigOption {
     Enable,
@@ -42,6 +61,7 @@ pub enum BitcoindConfigOption {
     ExtraConfig,
     User,
     Network,
+    Implementation,
     RpcUsers,
     RpcAddress,
     RpcPort,
@@ -50,11 +70,22 @@ pub enum BitcoindConfigOption {
     PruneSize,
     ExtraCmdLineOptions,
     DbCache,
+    Par,
+    RpcThreads,
+    MaxUploadTarget,
+    QuietHours,
+    QuietHoursStart,
+    QuietHoursEnd,
+    VerifyChainSchedule,
+    VerifyChainBlocks,
+    ReindexSchedule,
     DataDir,
     TxIndex,
     DisableWallet,
     ZmqPubRawTx,
     ZmqPubRawBlock,
+    I2pSam,
+    OnlyNet,
 }
 
 impl ToOptionId for BitcoindConfigOption {
@@ -76,6 +107,7 @@ impl FromStr for BitcoindConfigOption {
             "extra_config" => Ok(BitcoindConfigOption::ExtraConfig),
             "user" => Ok(BitcoindConfigOption::User),
             "network" => Ok(BitcoindConfigOption::Network),
+            "implementation" => Ok(BitcoindConfigOption::Implementation),
             "rpc_users" => Ok(BitcoindConfigOption::RpcUsers),
             "rpc_address" => Ok(BitcoindConfigOption::RpcAddress),
             "rpc_port" => Ok(BitcoindConfigOption::RpcPort),
@@ -84,11 +116,22 @@ impl FromStr for BitcoindConfigOption {
             "prune_size" => Ok(BitcoindConfigOption::PruneSize),
             "extra_cmd_line_options" => Ok(BitcoindConfigOption::ExtraCmdLineOptions),
             "db_cache" => Ok(BitcoindConfigOption::DbCache),
+            "par" => Ok(BitcoindConfigOption::Par),
+            "rpc_threads" => Ok(BitcoindConfigOption::RpcThreads),
+            "max_upload_target" => Ok(BitcoindConfigOption::MaxUploadTarget),
+            "quiet_hours" => Ok(BitcoindConfigOption::QuietHours),
+            "quiet_hours_start" => Ok(BitcoindConfigOption::QuietHoursStart),
+            "quiet_hours_end" => Ok(BitcoindConfigOption::QuietHoursEnd),
+            "verify_chain_schedule" => Ok(BitcoindConfigOption::VerifyChainSchedule),
+            "verify_chain_blocks" => Ok(BitcoindConfigOption::VerifyChainBlocks),
+            "reindex_schedule" => Ok(BitcoindConfigOption::ReindexSchedule),
             "data_dir" => Ok(BitcoindConfigOption::DataDir),
             "tx_index" => Ok(BitcoindConfigOption::TxIndex),
             "disable_wallet" => Ok(BitcoindConfigOption::DisableWallet),
             "zmq_pub_raw_tx" => Ok(BitcoindConfigOption::ZmqPubRawTx),
             "zmq_pub_raw_block" => Ok(BitcoindConfigOption::ZmqPubRawBlock),
+            "i2p_sam" => Ok(BitcoindConfigOption::I2pSam),
+            "only_net" => Ok(BitcoindConfigOption::OnlyNet),
             _ => Err(()),
         }
     }
@@ -105,7 +148,8 @@ impl fmt::Display for BitcoindConfigOption {
             BitcoindConfigOption::ExtraConfig => "extra_config",
             BitcoindConfigOption::User => "user",
             BitcoindConfigOption::Network => "network",
-            BitcoindConfigOption::RpcUsers => "rpcUsers",
+            BitcoindConfigOption::Implementation => "implementation",
+            BitcoindConfigOption::RpcUsers => "rpc_users",
             BitcoindConfigOption::RpcAddress => "rpc_address",
             BitcoindConfigOption::RpcPort => "rpc_port",
             BitcoindConfigOption::RpcAllowIp => "rpc_allow_ip",
@@ -113,11 +157,22 @@ impl fmt::Display for BitcoindConfigOption {
             BitcoindConfigOption::PruneSize => "prune_size",
             BitcoindConfigOption::ExtraCmdLineOptions => "extra_cmd_line_options",
             BitcoindConfigOption::DbCache => "db_cache",
+            BitcoindConfigOption::Par => "par",
+            BitcoindConfigOption::RpcThreads => "rpc_threads",
+            BitcoindConfigOption::MaxUploadTarget => "max_upload_target",
+            BitcoindConfigOption::QuietHours => "quiet_hours",
+            BitcoindConfigOption::QuietHoursStart => "quiet_hours_start",
+            BitcoindConfigOption::QuietHoursEnd => "quiet_hours_end",
+            BitcoindConfigOption::VerifyChainSchedule => "verify_chain_schedule",
+            BitcoindConfigOption::VerifyChainBlocks => "verify_chain_blocks",
+            BitcoindConfigOption::ReindexSchedule => "reindex_schedule",
             BitcoindConfigOption::DataDir => "data_dir",
             BitcoindConfigOption::TxIndex => "tx_index",
             BitcoindConfigOption::DisableWallet => "disable_wallet",
             BitcoindConfigOption::ZmqPubRawTx => "zmq_pub_raw_tx",
             BitcoindConfigOption::ZmqPubRawBlock => "zmq_pub_raw_block",
+            BitcoindConfigOption::I2pSam => "i2p_sam",
+            BitcoindConfigOption::OnlyNet => "only_net",
         };
         write!(f, "{}", option_str)
     }
@@ -157,6 +212,54 @@ impl fmt::Display for BitcoinNetwork {
     }
 }
 
+/// The node software running the bitcoind service
+///
+/// Knots is a derivative of Bitcoin Core with the same RPC and ZMQ
+/// interfaces, so the apps using bitcoind work with both.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub enum BitcoinImplementation {
+    #[default]
+    /// [default] Bitcoin Core
+    Core,
+
+    /// Bitcoin Knots
+    Knots,
+}
+
+impl BitcoinImplementation {
+    pub fn to_string_array() -> [&'static str; 2] {
+        ["Core", "Knots"]
+    }
+
+    pub fn from_string(s: &str) -> Option<BitcoinImplementation> {
+        match s {
+            "Core" => Some(BitcoinImplementation::Core),
+            "Knots" => Some(BitcoinImplementation::Knots),
+            _ => None,
+        }
+    }
+
+    /// The nixpkgs package of the implementation
+    ///
+    /// # Returns
+    /// None for Core, which keeps the version pinned by nix-bitcoin
+    pub fn package(&self) -> Option<&'static str> {
+        match self {
+            BitcoinImplementation::Core => None,
+            BitcoinImplementation::Knots => Some("bitcoind-knots"),
+        }
+    }
+}
+
+impl fmt::Display for BitcoinImplementation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitcoinImplementation::Core => write!(f, "Core"),
+            BitcoinImplementation::Knots => write!(f, "Knots"),
+        }
+    }
+}
+
 /// Prune options for a blockchain.
 ///
 /// This enum defines the different pruning strategies that can be used.
@@ -309,6 +412,91 @@ impl BitcoinDaemonServiceRPCUser {
     }
 }
 
+/// Separates the name of a RPC user from its password in the list editor
+const RPC_USER_PASSWORD_SEPARATOR: char = ':';
+
+/// Checks whether the name can be used for a `rpcauth` entry and as a Nix
+/// attribute name
+fn is_valid_rpc_user_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// The RPC users are edited as a list of names. A new user, or a user whose
+/// password should change, is entered as `name:password`. Only the HMAC of
+/// the password is kept, so the list never shows the password again.
+impl ApplyChange for Vec<BitcoinDaemonServiceRPCUser> {
+    fn to_option_data(&self) -> OptionData {
+        OptionData::ManualStringList(Box::new(ManualStringListOptionData::new(
+            BitcoindConfigOption::RpcUsers.to_option_id(),
+            self.iter().map(|u| u.name.value().to_string()).collect(),
+            0,
+        )))
+    }
+
+    fn apply_change(
+        &mut self,
+        change: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let option = BitcoindConfigOption::RpcUsers.to_string();
+        let OptionDataChangeNotification::ManualStringList(val) = change else {
+            return Err(Report::new(ProjectError::ChangeOptionValueError(option))
+                .attach_printable(format!("{:?}", change)));
+        };
+
+        let mut users = Vec::new();
+        for entry in val.value.iter().map(|e| e.trim()).filter(|e| !e.is_empty()) {
+            let (name, password) = match entry.split_once(RPC_USER_PASSWORD_SEPARATOR) {
+                Some((name, password)) => (name.trim(), Some(password)),
+                None => (entry, None),
+            };
+
+            if !is_valid_rpc_user_name(name) {
+                return Err(Report::new(ProjectError::ChangeOptionValueError(option))
+                    .attach_printable(format!("Invalid RPC user name: {}", name)));
+            }
+            if users
+                .iter()
+                .any(|u: &BitcoinDaemonServiceRPCUser| u.name.value() == name)
+            {
+                return Err(Report::new(ProjectError::ChangeOptionValueError(option))
+                    .attach_printable(format!("Duplicate RPC user: {}", name)));
+            }
+
+            let password_hmac = match password {
+                Some(password) if !password.is_empty() => create_rpcauth_hmac(password)
+                    .change_context(ProjectError::ChangeOptionValueError(option.clone()))
+                    .attach_printable_lazy(|| {
+                        format!("Unable to create the rpcauth entry of {}", name)
+                    })?,
+                _ => match self.iter().find(|u| u.name.value() == name) {
+                    Some(u) => u.password_hmac.hashed_value().to_string(),
+                    None => {
+                        return Err(Report::new(ProjectError::ChangeOptionValueError(option))
+                            .attach_printable(format!(
+                                "The new RPC user {} needs a password, enter it as {}{}password",
+                                name, name, RPC_USER_PASSWORD_SEPARATOR
+                            )))
+                    }
+                },
+            };
+
+            users.push(BitcoinDaemonServiceRPCUser::new(
+                password_hmac,
+                name.to_string(),
+            ));
+        }
+
+        if *self == users {
+            return Ok(false);
+        }
+
+        *self = users;
+        Ok(true)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct BitcoinDaemonService {
     /// Whether the service is enabled or not
@@ -347,7 +535,7 @@ pub struct BitcoinDaemonService {
     /// rpcthreads=16
     /// logips=1
     /// ''
-    pub extra_config: Box<TextOptionData>,
+    pub extra_config: Box<CodeOptionData>,
 
     /// The user as which to run bitcoind.
     pub user: Box<TextOptionData>,
@@ -355,6 +543,9 @@ pub struct BitcoinDaemonService {
     /// Which chiain to use
     pub network: Box<StringListOptionData>,
 
+    /// Which node software to run, see [BitcoinImplementation]
+    pub implementation: Box<StringListOptionData>,
+
     /// Allowed users for JSON-RPC connections.
     pub rpc_users: Box<Vec<BitcoinDaemonServiceRPCUser>>,
 
@@ -369,7 +560,8 @@ pub struct BitcoinDaemonService {
     ///
     /// Example: "192.168.0.0/16"
     /// Default: None
-    pub rpc_allow_ip: Box<Vec<NetAddressOptionData>>,
+    #[serde(deserialize_with = "deserialize_rpc_allow_ip")]
+    pub rpc_allow_ip: Box<NetAddressListOptionData>,
 
     /// Whether to prune the node
     pub prune: Box<StringListOptionData>,
@@ -381,7 +573,7 @@ pub struct BitcoinDaemonService {
     pub prune_size: Box<NumberOptionData>,
 
     /// Extra command line options to pass to bitcoind. Run bitcoind –help to list all available options.
-    pub extra_cmd_line_options: Box<TextOptionData>,
+    pub extra_cmd_line_options: Box<CodeOptionData>,
 
     /// Override the default database cache size in MiB.
     /// Integer between 4 and 16384 (both inclusive)
@@ -390,6 +582,56 @@ pub struct BitcoinDaemonService {
     /// Default: None
     pub db_cache: Box<NumberOptionData>,
 
+    /// Number of script verification threads.
+    /// 0 lets bitcoind use one thread per core of the machine it runs on.
+    ///
+    /// Default: 0
+    pub par: Box<NumberOptionData>,
+
+    /// Number of threads serving RPC calls
+    ///
+    /// Default: 4
+    pub rpc_threads: Box<NumberOptionData>,
+
+    /// Upper limit of the data uploaded to peers in MiB per day.
+    /// 0 means no limit.
+    ///
+    /// Default: 0
+    pub max_upload_target: Box<NumberOptionData>,
+
+    /// Whether to pause the peer connections during the quiet hours.
+    /// bitcoind can't change the upload target at runtime, so a timer
+    /// switches the networking off and on via RPC instead.
+    pub quiet_hours: Box<BoolOptionData>,
+
+    /// Hour of the day (0-23) the quiet hours start at
+    ///
+    /// Default: 18
+    pub quiet_hours_start: Box<NumberOptionData>,
+
+    /// Hour of the day (0-23) the quiet hours end at
+    ///
+    /// Default: 23
+    pub quiet_hours_end: Box<NumberOptionData>,
+
+    /// When to verify the most recent blocks with `bitcoin-cli verifychain`,
+    /// as systemd calendar event. Empty disables the check.
+    ///
+    /// Example: "Sun *-*-* 04:00:00"
+    pub verify_chain_schedule: Box<TextOptionData>,
+
+    /// Number of blocks the scheduled check verifies
+    ///
+    /// Default: 288
+    pub verify_chain_blocks: Box<NumberOptionData>,
+
+    /// When to reindex the block chain after pruning was switched off, as
+    /// systemd calendar event or [REINDEX_NOW]. Set by the upgrade to an
+    /// archival node, see [archival](crate::archival).
+    ///
+    /// Default: ""
+    pub reindex_schedule: Box<TextOptionData>,
+
     /// The data directory for bitcoind.
     ///
     /// Default: "/var/lib/bitcoind"
@@ -412,10 +654,123 @@ pub struct BitcoinDaemonService {
     /// # Example
     /// "tcp://127.0.0.1:28332"
     pub zmqpubrawblock: Box<NetAddressOptionData>,
+
+    /// SAM bridge of the I2P router to connect to peers over I2P.
+    /// Set when the I2P app is toggled, empty disables I2P.
+    ///
+    /// # Example
+    /// "127.0.0.1:7656"
+    pub i2p_sam: Box<TextOptionData>,
+
+    /// Only connect to peers in these networks, all if empty.
+    /// One of ipv4, ipv6, onion, i2p or cjdns per entry.
+    pub only_net: Box<ManualStringListOptionData>,
+}
+
+/// The networks bitcoind can be restricted to with `onlynet`
+pub const NETWORKS: [&str; 5] = ["ipv4", "ipv6", "onion", "i2p", "cjdns"];
+
+/// Checks that every entry is one of the [NETWORKS]
+///
+/// # Errors
+///
+/// [ProjectError::InvalidValue] listing all unknown networks
+pub fn check_only_net(option: &str, networks: &[String]) -> Result<(), ProjectError> {
+    let issues: Vec<String> = networks
+        .iter()
+        .map(|n| n.trim())
+        .filter(|n| !n.is_empty() && !NETWORKS.contains(n))
+        .map(|n| format!("{} is not one of {}", n, NETWORKS.join(", ")))
+        .collect();
+
+    if issues.is_empty() {
+        return Ok(());
+    }
+
+    Err(Report::new(ProjectError::InvalidValue(
+        option.to_string(),
+        issues,
+    )))
+}
+
+/// Older projects stored the allowed hosts as a list of single addresses
+fn deserialize_rpc_allow_ip<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Box<NetAddressListOptionData>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum AllowIp {
+        List(Box<NetAddressListOptionData>),
+        Legacy(Vec<NetAddressOptionData>),
+    }
+
+    Ok(match AllowIp::deserialize(deserializer)? {
+        AllowIp::List(list) => list,
+        AllowIp::Legacy(addresses) => Box::new(NetAddressListOptionData::new(
+            BitcoindConfigOption::RpcAllowIp.to_option_id(),
+            addresses
+                .iter()
+                .filter_map(|a| a.value())
+                .map(NetAddressRange::from)
+                .collect(),
+        )),
+    })
+}
+
+/// Renders a ZMQ endpoint like "tcp://127.0.0.1:28332" or `null` if no address is set
+fn zmq_endpoint(address: &NetAddressOptionData, port: u16) -> String {
+    match address.value() {
+        Some(ip) => format!("\"tcp://{}\"", SocketAddr::new(ip, port)),
+        None => "null".to_string(),
+    }
+}
+
+/// Renders the `par` setting, or nothing if bitcoind should pick the number of threads itself
+fn par_config(par: &NumberValue) -> String {
+    match par {
+        NumberValue::UInt(Some(p)) if *p > 0 => format!("par={}", p),
+        _ => "".to_string(),
+    }
+}
+
+/// Renders the `i2psam` and `onlynet` settings, or nothing if neither is set
+fn network_config(i2p_sam: &str, only_net: &[String]) -> String {
+    let mut lines = vec![];
+    if !i2p_sam.is_empty() {
+        lines.push(format!("i2psam={}", i2p_sam));
+    }
+    for net in only_net {
+        lines.push(format!("onlynet={}", net));
+    }
+
+    // keep the indentation of the other lines in the template
+    lines.join("\n      ")
+}
+
+/// Renders the `maxuploadtarget` setting, or nothing if the upload is unlimited
+fn max_upload_target_config(target: &NumberValue) -> String {
+    match target {
+        NumberValue::UInt(Some(t)) if *t > 0 => format!("maxuploadtarget={}", t),
+        _ => "".to_string(),
+    }
 }
 
 impl Default for BitcoinDaemonService {
     fn default() -> Self {
+        let platform = get_system_platform();
+        let rpc_threads = defaults::get(
+            &platform,
+            &BitcoindConfigOption::RpcThreads.to_option_id(),
+            defaults::bitcoind::RPC_THREADS,
+        );
+        let verify_chain_blocks = defaults::get(
+            &platform,
+            &BitcoindConfigOption::VerifyChainBlocks.to_option_id(),
+            defaults::bitcoind::VERIFY_CHAIN_BLOCKS,
+        );
         Self {
             enable: Box::new(BoolOptionData::new(
                 BitcoindConfigOption::Enable.to_option_id(),
@@ -423,11 +778,11 @@ impl Default for BitcoinDaemonService {
             )),
             address: Box::new(NetAddressOptionData::new(
                 BitcoindConfigOption::Address.to_option_id(),
-                Some(IpAddr::from_str("127.0.0.1").unwrap()),
+                Some(defaults::LOCALHOST),
             )),
             port: Box::new(PortOptionData::new(
                 BitcoindConfigOption::Port.to_option_id(),
-                NumberValue::U16(Some(8333)),
+                NumberValue::U16(Some(defaults::bitcoind::PORT)),
             )),
             onion_port: Box::new(PortOptionData::new(
                 BitcoindConfigOption::OnionPort.to_option_id(),
@@ -437,16 +792,17 @@ impl Default for BitcoinDaemonService {
                 BitcoindConfigOption::Listen.to_option_id(),
                 false,
             )),
-            extra_config: Box::new(TextOptionData::new(
+            extra_config: Box::new(CodeOptionData::new(
                 BitcoindConfigOption::ExtraConfig.to_option_id(),
                 "".into(),
                 10000,
                 false,
                 "".into(),
+                CodeLanguage::Ini,
             )),
             user: Box::new(TextOptionData::new(
                 BitcoindConfigOption::User.to_option_id(),
-                "admin".into(),
+                defaults::SYSTEM_USER.into(),
                 0,
                 false,
                 "".into(),
@@ -459,16 +815,27 @@ impl Default for BitcoinDaemonService {
                     .map(|n| StringListOptionItem::new(n.to_string(), n.to_string()))
                     .collect(),
             )),
+            implementation: Box::new(StringListOptionData::new(
+                BitcoindConfigOption::Implementation.to_option_id(),
+                BitcoinImplementation::Core.to_string(),
+                BitcoinImplementation::to_string_array()
+                    .iter()
+                    .map(|n| StringListOptionItem::new(n.to_string(), n.to_string()))
+                    .collect(),
+            )),
             rpc_users: Box::new(Vec::new()),
             rpc_address: Box::new(NetAddressOptionData::new(
                 BitcoindConfigOption::RpcAddress.to_option_id(),
-                Some(IpAddr::from_str("127.0.0.1").unwrap()),
+                Some(defaults::LOCALHOST),
             )),
             rpc_port: Box::new(PortOptionData::new(
                 BitcoindConfigOption::RpcPort.to_option_id(),
-                NumberValue::U16(Some(8332)),
+                NumberValue::U16(Some(defaults::bitcoind::RPC_PORT)),
+            )),
+            rpc_allow_ip: Box::new(NetAddressListOptionData::new(
+                BitcoindConfigOption::RpcAllowIp.to_option_id(),
+                vec![],
             )),
-            rpc_allow_ip: Box::new(Vec::new()),
             prune: Box::new(StringListOptionData::new(
                 BitcoindConfigOption::Prune.to_option_id(),
                 PruneOptions::Disable.to_string(),
@@ -480,20 +847,21 @@ impl Default for BitcoinDaemonService {
             prune_size: Box::new(
                 NumberOptionData::new(
                     BitcoindConfigOption::PruneSize.to_option_id(),
-                    NumberValue::UInt(Some(2048)),
+                    NumberValue::UInt(Some(defaults::bitcoind::PRUNE_SIZE)),
                     551,
                     99999,
                     false,
-                    NumberValue::UInt(Some(2048)),
+                    NumberValue::UInt(Some(defaults::bitcoind::PRUNE_SIZE)),
                 )
                 .unwrap(),
             ),
-            extra_cmd_line_options: Box::new(TextOptionData::new(
+            extra_cmd_line_options: Box::new(CodeOptionData::new(
                 BitcoindConfigOption::ExtraCmdLineOptions.to_option_id(),
                 "".to_string(),
                 9999,
                 false,
                 "".to_string(),
+                CodeLanguage::CmdLine,
             )),
             db_cache: Box::new(
                 NumberOptionData::new(
@@ -506,9 +874,93 @@ impl Default for BitcoinDaemonService {
                 )
                 .unwrap(),
             ),
+            par: Box::new(
+                NumberOptionData::new(
+                    BitcoindConfigOption::Par.to_option_id(),
+                    NumberValue::UInt(Some(defaults::bitcoind::PAR)),
+                    0,
+                    defaults::bitcoind::MAX_PAR,
+                    false,
+                    NumberValue::UInt(Some(defaults::bitcoind::PAR)),
+                )
+                .unwrap(),
+            ),
+            rpc_threads: Box::new(
+                NumberOptionData::new(
+                    BitcoindConfigOption::RpcThreads.to_option_id(),
+                    NumberValue::UInt(Some(rpc_threads)),
+                    1,
+                    64,
+                    false,
+                    NumberValue::UInt(Some(rpc_threads)),
+                )
+                .unwrap(),
+            ),
+            max_upload_target: Box::new(
+                NumberOptionData::new(
+                    BitcoindConfigOption::MaxUploadTarget.to_option_id(),
+                    NumberValue::UInt(Some(0)),
+                    0,
+                    defaults::bitcoind::MAX_UPLOAD_TARGET,
+                    false,
+                    NumberValue::UInt(Some(0)),
+                )
+                .unwrap(),
+            ),
+            quiet_hours: Box::new(BoolOptionData::new(
+                BitcoindConfigOption::QuietHours.to_option_id(),
+                false,
+            )),
+            quiet_hours_start: Box::new(
+                NumberOptionData::new(
+                    BitcoindConfigOption::QuietHoursStart.to_option_id(),
+                    NumberValue::UInt(Some(defaults::bitcoind::QUIET_HOURS_START)),
+                    0,
+                    23,
+                    false,
+                    NumberValue::UInt(Some(defaults::bitcoind::QUIET_HOURS_START)),
+                )
+                .unwrap(),
+            ),
+            quiet_hours_end: Box::new(
+                NumberOptionData::new(
+                    BitcoindConfigOption::QuietHoursEnd.to_option_id(),
+                    NumberValue::UInt(Some(defaults::bitcoind::QUIET_HOURS_END)),
+                    0,
+                    23,
+                    false,
+                    NumberValue::UInt(Some(defaults::bitcoind::QUIET_HOURS_END)),
+                )
+                .unwrap(),
+            ),
+            verify_chain_schedule: Box::new(TextOptionData::new(
+                BitcoindConfigOption::VerifyChainSchedule.to_option_id(),
+                "".into(),
+                1,
+                false,
+                "".into(),
+            )),
+            verify_chain_blocks: Box::new(
+                NumberOptionData::new(
+                    BitcoindConfigOption::VerifyChainBlocks.to_option_id(),
+                    NumberValue::UInt(Some(verify_chain_blocks)),
+                    1,
+                    defaults::bitcoind::MAX_VERIFY_CHAIN_BLOCKS,
+                    false,
+                    NumberValue::UInt(Some(verify_chain_blocks)),
+                )
+                .unwrap(),
+            ),
+            reindex_schedule: Box::new(TextOptionData::new(
+                BitcoindConfigOption::ReindexSchedule.to_option_id(),
+                "".into(),
+                1,
+                false,
+                "".into(),
+            )),
             data_dir: Box::new(TextOptionData::new(
                 BitcoindConfigOption::DataDir.to_option_id(),
-                "/var/lib/bitcoind".into(),
+                defaults::bitcoind::DATA_DIR.into(),
                 1,
                 false,
                 "".into(),
@@ -529,10 +981,37 @@ impl Default for BitcoinDaemonService {
                 BitcoindConfigOption::ZmqPubRawBlock.to_option_id(),
                 None,
             )),
+            i2p_sam: Box::new(TextOptionData::new(
+                BitcoindConfigOption::I2pSam.to_option_id(),
+                "".into(),
+                1,
+                false,
+                "".into(),
+            )),
+            only_net: Box::new(ManualStringListOptionData::new(
+                BitcoindConfigOption::OnlyNet.to_option_id(),
+                vec![],
+                NETWORKS.len() as u16,
+            )),
         }
     }
 }
 
+impl HiddenServiceProvider for BitcoinDaemonService {
+    fn hidden_service(&self) -> Option<HiddenService> {
+        if !self.enable.value() {
+            return None;
+        }
+
+        Some(HiddenService::new(
+            SupportedApps::BitcoinCore,
+            "bitcoind",
+            port_or(self.port.value(), defaults::bitcoind::PORT),
+            true,
+        ))
+    }
+}
+
 impl BitcoinDaemonService {
     pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
         let mut handlebars = Handlebars::new();
@@ -574,18 +1053,90 @@ impl BitcoinDaemonService {
                 (self.network.value() == BitcoinNetwork::Regtest.to_string()).to_string(),
             ),
             ("enable", self.enable.value().to_string()),
+            (
+                "package",
+                self.implementation()
+                    .package()
+                    .map(|p| format!("package = pkgs.{};", p))
+                    .unwrap_or_default(),
+            ),
             ("tx_index", self.tx_index.value().to_string()),
+            ("prune", self.prune_mib().to_string()),
+            (
+                "pruned",
+                if self.prune_mode() == PruneOptions::Disable {
+                    String::new()
+                } else {
+                    "true".to_string()
+                },
+            ),
+            (
+                "reindex_include",
+                self.pending_reindex()
+                    .map(|_| format!("includeconf={}", REINDEX_CONF))
+                    .unwrap_or_default(),
+            ),
+            ("reindex_conf", REINDEX_CONF.to_string()),
+            (
+                "reindex_timer",
+                self.pending_reindex()
+                    .map(|s| match s {
+                        REINDEX_NOW => "OnActiveSec = \"1min\";".to_string(),
+                        s => format!("OnCalendar = \"{}\";", s),
+                    })
+                    .unwrap_or_default(),
+            ),
             ("disable_wallet", self.disable_wallet.value().to_string()),
             ("address", self.address.to_nix_string(true)),
             ("listen", self.listen.value().to_string()),
-            ("port", self.port.value().to_string_or("8333")),
+            (
+                "port",
+                port_or(self.port.value(), defaults::bitcoind::PORT).to_string(),
+            ),
             ("rpc_address", self.rpc_address.to_nix_string(true)),
-            ("rpc_port", self.rpc_port.value().to_string_or("8332")),
+            (
+                "rpc_port",
+                port_or(self.rpc_port.value(), defaults::bitcoind::RPC_PORT).to_string(),
+            ),
+            (
+                "rpc_threads",
+                self.rpc_threads
+                    .value()
+                    .to_string_or(&defaults::bitcoind::RPC_THREADS.to_string()),
+            ),
+            ("par", par_config(self.par.value())),
+            (
+                "max_upload_target",
+                max_upload_target_config(self.max_upload_target.value()),
+            ),
+            (
+                "quiet_hours_start",
+                self.quiet_hours()
+                    .map(|(start, _)| format!("{:02}", start))
+                    .unwrap_or_default(),
+            ),
+            (
+                "quiet_hours_end",
+                self.quiet_hours()
+                    .map(|(_, end)| format!("{:02}", end))
+                    .unwrap_or_default(),
+            ),
+            (
+                "verify_chain_schedule",
+                self.verify_chain_schedule.value().trim().to_string(),
+            ),
+            (
+                "verify_chain_blocks",
+                self.verify_chain_blocks
+                    .value()
+                    .to_string_or(&defaults::bitcoind::VERIFY_CHAIN_BLOCKS.to_string()),
+            ),
             (
                 "rpc_allow_ip",
                 self.rpc_allow_ip
+                    .value()
                     .iter()
-                    .map(|s| s.to_nix_string(true))
+                    .map(|s| format!("\"{}\"", s))
                     .collect::<Vec<_>>()
                     .join("\n"),
             ),
@@ -603,8 +1154,21 @@ impl BitcoinDaemonService {
                     .collect::<Vec<_>>()
                     .join("\n"),
             ),
-            ("zmqpubrawblock", self.zmqpubrawblock.to_nix_string(true)),
-            ("zmqpubrawtx", self.zmqpubrawtx.to_nix_string(true)),
+            (
+                "zmqpubrawblock",
+                zmq_endpoint(
+                    &self.zmqpubrawblock,
+                    defaults::bitcoind::ZMQ_PUB_RAW_BLOCK_PORT,
+                ),
+            ),
+            (
+                "zmqpubrawtx",
+                zmq_endpoint(&self.zmqpubrawtx, defaults::bitcoind::ZMQ_PUB_RAW_TX_PORT),
+            ),
+            (
+                "network",
+                network_config(self.i2p_sam.value(), self.only_net.value()),
+            ),
         ]);
 
         let res = handlebars
@@ -612,290 +1176,171 @@ impl BitcoinDaemonService {
             .attach_printable("Failed to render bitcoin daemon template".to_string())
             .change_context(TemplatingError::Render)?;
 
-        let (status, text) = format::in_memory("<convert bitcoind>".to_string(), res);
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
 
-        if let format::Status::Error(e) = status {
-            Err(Report::new(TemplatingError::Format)).attach_printable_lazy(|| {
-                format!("Could not format the template file due to error: {e}")
-            })?
-        } else {
-            rendered_contents.insert(TEMPLATE_FILE_NAME.to_string(), text);
+    /// The prune mode, Disable if the value is unknown
+    pub fn prune_mode(&self) -> PruneOptions {
+        PruneOptions::from_str(self.prune.value()).unwrap_or(PruneOptions::Disable)
+    }
+
+    /// The `prune` value of bitcoind: 0 disables pruning, 1 allows pruning
+    /// via RPC and larger values are the target size in MiB
+    pub fn prune_mib(&self) -> usize {
+        match self.prune_mode() {
+            PruneOptions::Disable => 0,
+            PruneOptions::Manual => 1,
+            PruneOptions::Automatic { prune_at } if prune_at > 0 => prune_at as usize,
+            PruneOptions::Automatic { .. } => match self.prune_size.value() {
+                NumberValue::UInt(Some(size)) => *size,
+                _ => defaults::bitcoind::PRUNE_SIZE,
+            },
         }
+    }
 
-        Ok(rendered_contents)
+    /// The schedule of the reindex, None if no reindex is pending. A node
+    /// that prunes again has nothing to reindex.
+    pub fn pending_reindex(&self) -> Option<&str> {
+        let schedule = self.reindex_schedule.value().trim();
+        (!schedule.is_empty() && self.prune_mode() == PruneOptions::Disable).then_some(schedule)
     }
 
-    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
-        serde_json::to_string(self).change_context(TemplatingError::JsonRenderError)
+    /// The hours the peer connections are paused at and resumed at,
+    /// or None if there are no quiet hours
+    pub fn quiet_hours(&self) -> Option<(usize, usize)> {
+        if !self.quiet_hours.value() {
+            return None;
+        }
+
+        match (self.quiet_hours_start.value(), self.quiet_hours_end.value()) {
+            (NumberValue::UInt(Some(start)), NumberValue::UInt(Some(end))) if start != end => {
+                Some((*start, *end))
+            }
+            _ => None,
+        }
     }
 
-    pub(crate) fn from_json(json_data: &str) -> Result<BitcoinDaemonService, TemplatingError> {
-        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    /// Points bitcoind to the SAM bridge of the I2P router, or stops using
+    /// I2P if the router is disabled
+    ///
+    /// # Returns
+    /// `true` if the SAM address changed
+    /// The selected implementation, Core if the value is unknown
+    pub fn implementation(&self) -> BitcoinImplementation {
+        BitcoinImplementation::from_string(self.implementation.value()).unwrap_or_default()
     }
-}
 
-impl AppConfig for BitcoinDaemonService {
-    fn app_option_changed(
-        &mut self,
-        option: &OptionDataChangeNotification,
-    ) -> Result<bool, ProjectError> {
-        let id = option.id();
-        if let Ok(opt) = BitcoindConfigOption::from_str(&id.option) {
-            let mut res = Ok(false);
-            if opt == BitcoindConfigOption::Enable {
-                if let OptionDataChangeNotification::Bool(val) = option {
-                    res = Ok(self.enable.value() != val.value);
-                    self.enable.set_value(val.value);
-                } else {
-                    Err(Report::new(ProjectError::ChangeOptionValueError(
-                        opt.to_string(),
-                    )))?;
-                }
-            } else if opt == BitcoindConfigOption::Address {
-                if let OptionDataChangeNotification::NetAddress(val) = option {
-                    res = Ok(self.address.value() != val.value);
-                    self.address.set_value(val.value);
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::Port {
-                if let OptionDataChangeNotification::Port(val) = option {
-                    res = Ok(*self.port.value() != val.value);
-                    self.port.set_value(val.value.clone());
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::OnionPort {
-                if let OptionDataChangeNotification::Port(val) = option {
-                    res = Ok(*self.onion_port.value() != val.value);
-                    self.onion_port.set_value(val.value.clone());
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::Listen {
-                if let OptionDataChangeNotification::Bool(val) = option {
-                    res = Ok(self.listen.value() != val.value);
-                    self.listen.set_value(val.value);
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::ExtraConfig {
-                if let OptionDataChangeNotification::TextEdit(val) = option {
-                    res = Ok(self.extra_config.value() != val.value);
-                    self.extra_config.set_value(val.value.clone());
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::User {
-                if let OptionDataChangeNotification::TextEdit(val) = option {
-                    res = Ok(self.user.value() != val.value);
-                    self.user.set_value(val.value.clone());
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::Network {
-                if let OptionDataChangeNotification::StringList(val) = option {
-                    if BitcoinNetwork::from_string(val.value.as_str()).is_none() {
-                        Err(Report::new(ProjectError::ChangeOptionValueError(
-                            BitcoindConfigOption::Network.to_string(),
-                        ))
-                        .attach_printable(format!("{:?}", option)))?
-                    }
-                    res = Ok(self.network.value() != val.value);
-                    self.network.set_value(val.value.clone());
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::RpcAddress {
-                if let OptionDataChangeNotification::NetAddress(val) = option {
-                    res = Ok(self.rpc_address.value() != val.value);
-                    self.rpc_address.set_value(val.value);
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::RpcPort {
-                if let OptionDataChangeNotification::Port(val) = option {
-                    res = Ok(*self.port.value() != val.value);
-                    self.port.set_value(val.value.clone());
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::Prune {
-                if let OptionDataChangeNotification::StringList(val) = option {
-                    if PruneOptions::from_str(val.value.as_str()).is_err() {
-                        Err(
-                            Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                                .attach_printable(format!("{:?}", option)),
-                        )?
-                    }
-                    res = Ok(self.prune.value() != val.value);
-                    self.prune.set_value(val.value.clone());
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::PruneSize {
-                if let OptionDataChangeNotification::Number(val) = option {
-                    res = Ok(*self.prune_size.value() != val.value);
-                    self.prune_size.set_value(val.value.clone());
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::ExtraCmdLineOptions {
-                if let OptionDataChangeNotification::TextEdit(val) = option {
-                    res = Ok(self.extra_cmd_line_options.value() != val.value);
-                    self.extra_cmd_line_options.set_value(val.value.clone());
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::DbCache {
-                if let OptionDataChangeNotification::Number(val) = option {
-                    res = Ok(*self.db_cache.value() != val.value);
-                    self.db_cache.set_value(val.value.clone());
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::DataDir {
-                if let OptionDataChangeNotification::TextEdit(val) = option {
-                    res = Ok(self.data_dir.value() != val.value);
-                    self.data_dir.set_value(val.value.clone());
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::TxIndex {
-                if let OptionDataChangeNotification::Bool(val) = option {
-                    res = Ok(self.tx_index.value() != val.value);
-                    self.tx_index.set_value(val.value);
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::DisableWallet {
-                if let OptionDataChangeNotification::Bool(val) = option {
-                    res = Ok(self.disable_wallet.value() != val.value);
-                    self.disable_wallet.set_value(val.value);
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::ZmqPubRawTx {
-                if let OptionDataChangeNotification::NetAddress(val) = option {
-                    res = Ok(self.zmqpubrawtx.value() != val.value);
-                    self.zmqpubrawtx.set_value(val.value);
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else if opt == BitcoindConfigOption::ZmqPubRawBlock {
-                if let OptionDataChangeNotification::NetAddress(val) = option {
-                    res = Ok(self.zmqpubrawblock.value() != val.value);
-                    self.zmqpubrawblock.set_value(val.value);
-                } else {
-                    Err(
-                        Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                            .attach_printable(format!("{:?}", option)),
-                    )?;
-                }
-            } else {
-                Err(
-                    Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
-                        .attach_printable(format!("Unknown option: {}", opt,)),
-                )?
+    pub fn sync_with_i2p(&mut self, i2p: &I2pService) -> bool {
+        let sam = i2p.sam().unwrap_or_default();
+        if self.i2p_sam.value() == sam {
+            return false;
+        }
+
+        self.i2p_sam.set_value(sam);
+        true
+    }
+
+    /// Whether a RPC user with the given name exists
+    pub fn has_rpc_user(&self, name: &str) -> bool {
+        self.rpc_users.iter().any(|u| u.name.value() == name)
+    }
+
+    /// Adds a RPC user or replaces the password HMAC of an existing one
+    ///
+    /// # Arguments
+    /// * `name` - The name of the RPC user
+    /// * `password_hmac` - The password HMAC in the format `<SALT-HEX>$<HMAC-HEX>`
+    ///
+    /// # Returns
+    /// `true` if the list of RPC users was changed
+    pub fn set_rpc_user(&mut self, name: &str, password_hmac: &str) -> bool {
+        if let Some(user) = self.rpc_users.iter_mut().find(|u| u.name.value() == name) {
+            if user.password_hmac.hashed_value() == password_hmac {
+                return false;
             }
 
-            return res;
+            user.password_hmac
+                .set_hashed_value(password_hmac.to_string());
+            return true;
         }
 
-        Ok(false)
+        self.rpc_users.push(BitcoinDaemonServiceRPCUser::new(
+            password_hmac.to_string(),
+            name.to_string(),
+        ));
+
+        true
     }
 
-    fn get_options(&self) -> Vec<OptionData> {
-        vec![
-            OptionData::Bool(self.enable.clone()),
-            OptionData::NetAddress(self.address.clone()),
-            OptionData::Port(self.port.clone()),
-            OptionData::Port(self.onion_port.clone()),
-            OptionData::Bool(self.listen.clone()),
-            OptionData::TextEdit(self.extra_config.clone()),
-            OptionData::TextEdit(self.user.clone()),
-            OptionData::StringList(Box::new(StringListOptionData::new(
-                BitcoindConfigOption::Network.to_option_id(),
-                self.network.value().to_string(),
-                BitcoinNetwork::to_string_array()
-                    .map(|entry| StringListOptionItem::new(entry.to_string(), entry.to_string()))
-                    .to_vec(),
-            ))),
-            //// TODO: implement me
-            ////OptionData::RpcUsers(self.rpc_users.clone()),
-            OptionData::NetAddress(self.rpc_address.clone()),
-            OptionData::Port(self.rpc_port.clone()),
-            //// TODO: implement me
-            //OptionData::IpList(self.rpc_allow_ip.clone()),
-            OptionData::StringList(Box::new(StringListOptionData::new(
-                BitcoindConfigOption::Prune.to_option_id(),
-                self.prune.value().to_string(),
-                PruneOptions::to_string_array()
-                    .map(|entry| StringListOptionItem::new(entry.to_string(), entry.to_string()))
-                    .to_vec(),
-            ))),
-            OptionData::NumberEdit(self.prune_size.clone()),
-            OptionData::TextEdit(self.extra_cmd_line_options.clone()),
-            OptionData::NumberEdit(self.db_cache.clone()),
-            OptionData::TextEdit(self.data_dir.clone()),
-            OptionData::Bool(self.tx_index.clone()),
-            OptionData::Bool(self.disable_wallet.clone()),
-            OptionData::NetAddress(self.zmqpubrawtx.clone()),
-            OptionData::NetAddress(self.zmqpubrawblock.clone()),
-        ]
+    /// Removes the RPC user with the given name
+    ///
+    /// # Returns
+    /// `true` if the user existed
+    pub fn remove_rpc_user(&mut self, name: &str) -> bool {
+        let len = self.rpc_users.len();
+        self.rpc_users.retain(|u| u.name.value() != name);
+        len != self.rpc_users.len()
     }
 
+    /// Makes sure the given IP is allowed to connect to the RPC server
+    ///
+    /// # Returns
+    /// `true` if the IP was added
+    pub fn ensure_rpc_allow_ip(&mut self, ip: IpAddr) -> bool {
+        self.rpc_allow_ip.push(NetAddressRange::from(ip))
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<BitcoinDaemonService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+impl AppConfig for BitcoinDaemonService {
+    app_options!(BitcoindConfigOption {
+        Enable => enable,
+        Address => address,
+        Port => port,
+        OnionPort => onion_port,
+        Listen => listen,
+        ExtraConfig => extra_config,
+        User => user,
+        Network => network,
+        Implementation => implementation,
+        RpcUsers => rpc_users,
+        RpcAddress => rpc_address,
+        RpcPort => rpc_port,
+        RpcAllowIp => rpc_allow_ip,
+        Prune => prune,
+        PruneSize => prune_size,
+        ExtraCmdLineOptions => extra_cmd_line_options,
+        DbCache => db_cache,
+        Par => par,
+        RpcThreads => rpc_threads,
+        MaxUploadTarget => max_upload_target,
+        QuietHours => quiet_hours,
+        QuietHoursStart => quiet_hours_start,
+        QuietHoursEnd => quiet_hours_end,
+        VerifyChainSchedule => verify_chain_schedule,
+        VerifyChainBlocks => verify_chain_blocks,
+        ReindexSchedule => reindex_schedule,
+        DataDir => data_dir,
+        TxIndex => tx_index,
+        DisableWallet => disable_wallet,
+        ZmqPubRawTx => zmqpubrawtx,
+        ZmqPubRawBlock => zmqpubrawblock,
+        I2pSam => i2p_sam,
+        OnlyNet => only_net,
+    });
+
     fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
         let rendered_json = self
             .to_json_string()
@@ -904,12 +1349,7 @@ impl AppConfig for BitcoinDaemonService {
             "Failed at rendering bitcoind config".to_string(),
         ))?;
 
-        for (key, val) in rendered_nix.iter() {
-            update_file(
-                Path::new(&work_dir.join(key.replace(".templ", ""))),
-                val.as_bytes(),
-            )?;
-        }
+        update_nix_files(work_dir, &rendered_nix)?;
 
         update_file(
             Path::new(&work_dir.join(JSON_FILE_NAME)),
@@ -922,7 +1362,15 @@ impl AppConfig for BitcoinDaemonService {
 
 #[cfg(test)]
 pub mod tests {
-    use crate::utils::init_default_project;
+    use crate::{
+        app_option_data::{
+            manual_string_list_data::ManualStringListOptionChangeData,
+            net_address_list_data::NetAddressListOptionChangeData,
+            option_data::{GetOptionId, OptionDataChangeNotification},
+            port_data::PortOptionChangeData,
+        },
+        utils::init_default_project,
+    };
 
     use super::*;
 
@@ -950,6 +1398,14 @@ pub mod tests {
                 .map(|n| StringListOptionItem::new(n.to_string(), n.to_string()))
                 .collect(),
         ));
+        let implementation = Box::new(StringListOptionData::new(
+            BitcoindConfigOption::Implementation.to_option_id(),
+            BitcoinImplementation::Knots.to_string(),
+            BitcoinImplementation::to_string_array()
+                .iter()
+                .map(|n| StringListOptionItem::new(n.to_string(), n.to_string()))
+                .collect(),
+        ));
         let tx_index = Box::new(BoolOptionData::new(
             BitcoindConfigOption::TxIndex.to_option_id(),
             true,
@@ -962,12 +1418,13 @@ pub mod tests {
             BitcoindConfigOption::Listen.to_option_id(),
             false,
         ));
-        let extra_config = Box::new(TextOptionData::new(
+        let extra_config = Box::new(CodeOptionData::new(
             BitcoindConfigOption::ExtraConfig.to_option_id(),
             "extra_config_value".to_string(),
             10000,
             false,
             "".into(),
+            CodeLanguage::Ini,
         ));
         let user = Box::new(TextOptionData::new(
             BitcoindConfigOption::User.to_option_id(),
@@ -989,16 +1446,13 @@ pub mod tests {
             BitcoindConfigOption::RpcPort.to_option_id(),
             NumberValue::U16(Some(8332)),
         ));
-        let rpc_allow_ip = Box::new(vec![
-            NetAddressOptionData::new(
-                BitcoindConfigOption::RpcAllowIp.to_option_id(),
-                Some(IpAddr::from_str("192.168.1.100").unwrap()),
-            ),
-            NetAddressOptionData::new(
-                BitcoindConfigOption::RpcAllowIp.to_option_id(),
-                Some(IpAddr::from_str("192.168.1.111").unwrap()),
-            ),
-        ]);
+        let rpc_allow_ip = Box::new(NetAddressListOptionData::new(
+            BitcoindConfigOption::RpcAllowIp.to_option_id(),
+            vec![
+                NetAddressRange::from_str("192.168.1.100").unwrap(),
+                NetAddressRange::from_str("192.168.1.111").unwrap(),
+            ],
+        ));
         let prune = Box::new(StringListOptionData::new(
             BitcoindConfigOption::Prune.to_option_id(),
             PruneOptions::Automatic { prune_at: 2500 }.to_string(),
@@ -1018,12 +1472,13 @@ pub mod tests {
             )
             .unwrap(),
         );
-        let extra_cmd_line_options = Box::new(TextOptionData::new(
+        let extra_cmd_line_options = Box::new(CodeOptionData::new(
             BitcoindConfigOption::ExtraCmdLineOptions.to_option_id(),
             "option1\noption2=value".to_string(),
             9999,
             false,
             "".to_string(),
+            CodeLanguage::CmdLine,
         ));
         let db_cache = Box::new(
             NumberOptionData::new(
@@ -1036,6 +1491,83 @@ pub mod tests {
             )
             .unwrap(),
         );
+        let par = Box::new(
+            NumberOptionData::new(
+                BitcoindConfigOption::Par.to_option_id(),
+                NumberValue::UInt(Some(3)),
+                0,
+                defaults::bitcoind::MAX_PAR,
+                false,
+                NumberValue::UInt(Some(3)),
+            )
+            .unwrap(),
+        );
+        let rpc_threads = Box::new(
+            NumberOptionData::new(
+                BitcoindConfigOption::RpcThreads.to_option_id(),
+                NumberValue::UInt(Some(8)),
+                1,
+                64,
+                false,
+                NumberValue::UInt(Some(8)),
+            )
+            .unwrap(),
+        );
+        let max_upload_target = Box::new(
+            NumberOptionData::new(
+                BitcoindConfigOption::MaxUploadTarget.to_option_id(),
+                NumberValue::UInt(Some(5000)),
+                0,
+                defaults::bitcoind::MAX_UPLOAD_TARGET,
+                false,
+                NumberValue::UInt(Some(5000)),
+            )
+            .unwrap(),
+        );
+        let quiet_hours = Box::new(BoolOptionData::new(
+            BitcoindConfigOption::QuietHours.to_option_id(),
+            true,
+        ));
+        let quiet_hours_start = Box::new(
+            NumberOptionData::new(
+                BitcoindConfigOption::QuietHoursStart.to_option_id(),
+                NumberValue::UInt(Some(19)),
+                0,
+                23,
+                false,
+                NumberValue::UInt(Some(19)),
+            )
+            .unwrap(),
+        );
+        let quiet_hours_end = Box::new(
+            NumberOptionData::new(
+                BitcoindConfigOption::QuietHoursEnd.to_option_id(),
+                NumberValue::UInt(Some(7)),
+                0,
+                23,
+                false,
+                NumberValue::UInt(Some(7)),
+            )
+            .unwrap(),
+        );
+        let verify_chain_schedule = Box::new(TextOptionData::new(
+            BitcoindConfigOption::VerifyChainSchedule.to_option_id(),
+            "Sun *-*-* 04:00:00".to_string(),
+            1,
+            false,
+            "".into(),
+        ));
+        let verify_chain_blocks = Box::new(
+            NumberOptionData::new(
+                BitcoindConfigOption::VerifyChainBlocks.to_option_id(),
+                NumberValue::UInt(Some(144)),
+                1,
+                defaults::bitcoind::MAX_VERIFY_CHAIN_BLOCKS,
+                false,
+                NumberValue::UInt(Some(144)),
+            )
+            .unwrap(),
+        );
         let data_dir = Box::new(TextOptionData::new(
             BitcoindConfigOption::DataDir.to_option_id(),
             "/path/to/data/dir".to_string(),
@@ -1061,6 +1593,7 @@ pub mod tests {
             address,
             port,
             network,
+            implementation,
             tx_index,
             onion_port,
             listen,
@@ -1074,10 +1607,37 @@ pub mod tests {
             prune_size,
             extra_cmd_line_options,
             db_cache,
+            par,
+            rpc_threads,
+            max_upload_target,
+            quiet_hours,
+            quiet_hours_start,
+            quiet_hours_end,
+            verify_chain_schedule,
+            verify_chain_blocks,
+            reindex_schedule: Box::new(TextOptionData::new(
+                BitcoindConfigOption::ReindexSchedule.to_option_id(),
+                "".into(),
+                1,
+                false,
+                "".into(),
+            )),
             data_dir,
             disable_wallet,
             zmqpubrawtx,
             zmqpubrawblock,
+            i2p_sam: Box::new(TextOptionData::new(
+                BitcoindConfigOption::I2pSam.to_option_id(),
+                "127.0.0.1:7656".into(),
+                1,
+                false,
+                "".into(),
+            )),
+            only_net: Box::new(ManualStringListOptionData::new(
+                BitcoindConfigOption::OnlyNet.to_option_id(),
+                vec!["onion".into(), "i2p".into()],
+                5,
+            )),
         }
     }
 
@@ -1162,6 +1722,10 @@ pub mod tests {
             default_service.network.value(),
             BitcoinNetwork::Mainnet.to_string()
         );
+        assert_eq!(
+            default_service.implementation(),
+            BitcoinImplementation::Core
+        );
         assert_eq!(
             default_service.prune.value(),
             PruneOptions::Disable.to_string()
@@ -1186,13 +1750,20 @@ pub mod tests {
         assert!(nix_str.contains(&format!("txindex = {};", d.tx_index.value())));
         assert!(nix_str.contains(&format!("disablewallet = {};", d.disable_wallet.value())));
         assert!(nix_str.contains(&format!("listen = {};", d.listen.value())));
-        assert!(nix_str.contains(&format!("address = {};", d.address.to_nix_string(true))));
-        assert!(nix_str.contains(&format!("port = {};", d.port.value().to_string_or("8333"))));
+        assert!(nix_str.contains(&format!(
+            "address = lib.mkDefault {};",
+            d.address.to_nix_string(true)
+        )));
+        assert!(nix_str.contains(&format!(
+            "port = {};",
+            port_or(d.port.value(), defaults::bitcoind::PORT)
+        )));
         assert!(nix_str.contains(&format!(
             r#"
     rpc = {{
-      address = {};
+      address = lib.mkDefault {};
       port = {};
+      threads = 8;
       allowip = [
         "192.168.1.100"
         "192.168.1.111"
@@ -1204,15 +1775,242 @@ pub mod tests {
     }};
 "#,
             d.rpc_address.to_nix_string(true),
-            d.rpc_port.value().to_string_or("8332")
-        )));
-        assert!(nix_str.contains(&format!(
-            "zmqpubrawblock = {};",
-            d.zmqpubrawblock.to_nix_string(true)
-        )));
-        assert!(nix_str.contains(&format!(
-            "zmqpubrawtx = {};",
-            d.zmqpubrawtx.to_nix_string(true)
+            port_or(d.rpc_port.value(), defaults::bitcoind::RPC_PORT)
         )));
+        assert!(nix_str.contains("zmqpubrawblock = \"tcp://247.0.0.1:28332\";"));
+        assert!(nix_str.contains("zmqpubrawtx = \"tcp://227.0.0.1:28333\";"));
+        assert!(nix_str.contains("par=3"));
+        assert!(nix_str.contains("maxuploadtarget=5000"));
+        assert!(nix_str.contains("OnCalendar = \"*-*-* 19:00:00\";"));
+        assert!(nix_str.contains("OnCalendar = \"*-*-* 07:00:00\";"));
+        assert!(nix_str.contains("package = pkgs.bitcoind-knots;"));
+        assert!(nix_str.contains("bitcoin-cli verifychain 3 144"));
+        assert!(nix_str.contains("OnCalendar = \"Sun *-*-* 04:00:00\";"));
+    }
+
+    #[test]
+    fn test_render_implementation() {
+        let mut d = get_test_service();
+        let knots = d.render().unwrap()[TEMPLATE_FILE_NAME].clone();
+
+        d.implementation
+            .set_value(BitcoinImplementation::Core.to_string());
+        let core = d.render().unwrap()[TEMPLATE_FILE_NAME].clone();
+        assert!(!core.contains("package ="));
+
+        // the apps using bitcoind read its RPC and ZMQ settings, which must
+        // not depend on the implementation
+        let strip = |s: &str| {
+            s.lines()
+                .filter(|l| !l.contains("package ="))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        assert_eq!(strip(&knots), strip(&core));
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let mut d = get_test_service();
+        assert_eq!(d.quiet_hours(), Some((19, 7)));
+
+        d.quiet_hours_end.set_value(NumberValue::UInt(Some(19)));
+        assert_eq!(d.quiet_hours(), None);
+
+        d.quiet_hours_end.set_value(NumberValue::UInt(Some(7)));
+        d.quiet_hours.set_value(false);
+        assert_eq!(d.quiet_hours(), None);
+
+        let rendered = d.render().unwrap();
+        assert!(!rendered[TEMPLATE_FILE_NAME].contains("setnetworkactive"));
+    }
+
+    #[test]
+    fn test_verify_chain_disabled() {
+        let mut d = get_test_service();
+        d.verify_chain_schedule.set_value(" ".to_string());
+
+        let rendered = d.render().unwrap();
+        assert!(!rendered[TEMPLATE_FILE_NAME].contains("verifychain"));
+    }
+
+    #[test]
+    fn test_reindex_schedule() {
+        let mut d = get_test_service();
+        d.reindex_schedule.set_value(REINDEX_NOW.to_string());
+        assert_eq!(d.pending_reindex(), None);
+
+        let pruned = d.render().unwrap()[TEMPLATE_FILE_NAME].clone();
+        assert!(pruned.contains("prune = 2500;"));
+        assert!(pruned.contains("\"r ${config.services.bitcoind.dataDir}/nixblitz-reindexed\""));
+        assert!(!pruned.contains("bitcoind-reindex"));
+
+        d.prune.set_value(PruneOptions::Disable.to_string());
+        assert_eq!(d.pending_reindex(), Some(REINDEX_NOW));
+        let now = d.render().unwrap()[TEMPLATE_FILE_NAME].clone();
+        assert!(now.contains("prune = 0;"));
+        assert!(now.contains("includeconf=nixblitz-reindex.conf"));
+        assert!(now.contains("OnActiveSec = \"1min\";"));
+
+        d.reindex_schedule
+            .set_value("Sun *-*-* 03:00:00".to_string());
+        let scheduled = d.render().unwrap()[TEMPLATE_FILE_NAME].clone();
+        assert!(scheduled.contains("OnCalendar = \"Sun *-*-* 03:00:00\";"));
+    }
+
+    #[test]
+    fn test_max_upload_target_config() {
+        assert_eq!(max_upload_target_config(&NumberValue::UInt(Some(0))), "");
+        assert_eq!(
+            max_upload_target_config(&NumberValue::UInt(Some(500))),
+            "maxuploadtarget=500"
+        );
+    }
+
+    #[test]
+    fn test_par_config() {
+        assert_eq!(par_config(&NumberValue::UInt(Some(0))), "");
+        assert_eq!(par_config(&NumberValue::UInt(None)), "");
+        assert_eq!(par_config(&NumberValue::UInt(Some(4))), "par=4");
+    }
+
+    #[test]
+    fn test_set_rpc_user() {
+        let mut d = BitcoinDaemonService::default();
+        assert!(!d.has_rpc_user("blitz_api"));
+
+        assert!(d.set_rpc_user("blitz_api", "salt$hmac"));
+        assert!(d.has_rpc_user("blitz_api"));
+        assert!(!d.set_rpc_user("blitz_api", "salt$hmac"));
+        assert!(d.set_rpc_user("blitz_api", "salt2$hmac2"));
+        assert_eq!(d.rpc_users.len(), 1);
+        assert_eq!(d.rpc_users[0].password_hmac.hashed_value(), "salt2$hmac2");
+
+        assert!(d.remove_rpc_user("blitz_api"));
+        assert!(!d.remove_rpc_user("blitz_api"));
+        assert!(d.rpc_users.is_empty());
+
+        let localhost = IpAddr::from_str("127.0.0.1").unwrap();
+        assert!(d.ensure_rpc_allow_ip(localhost));
+        assert!(!d.ensure_rpc_allow_ip(localhost));
+        assert_eq!(d.rpc_allow_ip.value().len(), 1);
+    }
+
+    #[test]
+    fn test_rpc_allow_ip_change() {
+        let mut d = BitcoinDaemonService::default();
+        let allowed = vec![
+            NetAddressRange::from_str("192.168.0.0/16").unwrap(),
+            NetAddressRange::from_str("fd00::/8").unwrap(),
+        ];
+
+        let changed = d
+            .app_option_changed(&OptionDataChangeNotification::NetAddressList(
+                NetAddressListOptionChangeData::new(
+                    BitcoindConfigOption::RpcAllowIp.to_option_id(),
+                    allowed.clone(),
+                ),
+            ))
+            .unwrap();
+        assert!(changed);
+        assert_eq!(*d.rpc_allow_ip.value(), allowed);
+
+        let nix_str = d.render().unwrap()[TEMPLATE_FILE_NAME].clone();
+        assert!(nix_str.contains("\"192.168.0.0/16\""));
+        assert!(nix_str.contains("\"fd00::/8\""));
+    }
+
+    #[test]
+    fn test_rpc_allow_ip_legacy_json() {
+        let mut json: serde_json::Value =
+            serde_json::from_str(&get_test_service().to_json_string().unwrap()).unwrap();
+        let legacy = serde_json::to_value(vec![NetAddressOptionData::new(
+            BitcoindConfigOption::RpcAllowIp.to_option_id(),
+            Some(IpAddr::from_str("192.168.1.100").unwrap()),
+        )])
+        .unwrap();
+        json["rpc_allow_ip"] = legacy;
+
+        let d = BitcoinDaemonService::from_json(&json.to_string()).unwrap();
+        assert_eq!(
+            *d.rpc_allow_ip.value(),
+            vec![NetAddressRange::from_str("192.168.1.100").unwrap()]
+        );
+        assert_eq!(
+            *d.rpc_allow_ip.id(),
+            BitcoindConfigOption::RpcAllowIp.to_option_id()
+        );
+    }
+
+    #[test]
+    fn test_rpc_users_change() {
+        let change = |users: &[&str]| {
+            OptionDataChangeNotification::ManualStringList(ManualStringListOptionChangeData::new(
+                BitcoindConfigOption::RpcUsers.to_option_id(),
+                users.iter().map(|u| u.to_string()).collect(),
+            ))
+        };
+
+        let mut d = BitcoinDaemonService::default();
+        d.set_rpc_user("blitz_api", "salt$hmac");
+
+        // a new user needs a password
+        assert!(d
+            .app_option_changed(&change(&["blitz_api", "alice"]))
+            .is_err());
+        assert!(d
+            .app_option_changed(&change(&["blitz_api", "al ice:pw"]))
+            .is_err());
+        assert!(d
+            .app_option_changed(&change(&["alice:pw", "alice:pw"]))
+            .is_err());
+        assert_eq!(d.rpc_users.len(), 1);
+
+        assert!(d
+            .app_option_changed(&change(&["blitz_api", "alice:secret"]))
+            .unwrap());
+        assert!(d.has_rpc_user("alice"));
+        assert_eq!(d.rpc_users[0].password_hmac.hashed_value(), "salt$hmac");
+        assert_ne!(d.rpc_users[1].password_hmac.hashed_value(), "secret");
+
+        // the list only shows the names
+        let options = d.get_options();
+        let users = options
+            .iter()
+            .find_map(|o| match o {
+                OptionData::ManualStringList(l)
+                    if *l.id() == BitcoindConfigOption::RpcUsers.to_option_id() =>
+                {
+                    Some(l.value().clone())
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(users, vec!["blitz_api".to_string(), "alice".to_string()]);
+
+        assert!(!d
+            .app_option_changed(&change(&["blitz_api", "alice"]))
+            .unwrap());
+        assert!(d.app_option_changed(&change(&["alice"])).unwrap());
+        assert!(!d.has_rpc_user("blitz_api"));
+    }
+
+    #[test]
+    fn test_rpc_port_change() {
+        let mut d = BitcoinDaemonService::default();
+        let port = d.port.value().clone();
+
+        let changed = d
+            .app_option_changed(&OptionDataChangeNotification::Port(
+                PortOptionChangeData::new(
+                    BitcoindConfigOption::RpcPort.to_option_id(),
+                    NumberValue::U16(Some(18332)),
+                ),
+            ))
+            .unwrap();
+
+        assert!(changed);
+        assert_eq!(*d.rpc_port.value(), NumberValue::U16(Some(18332)));
+        assert_eq!(*d.port.value(), port);
     }
 }
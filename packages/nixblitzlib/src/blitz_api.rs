@@ -1,7 +1,6 @@
 use core::fmt;
 use std::{collections::HashMap, path::Path, str::FromStr};
 
-use alejandra::format;
 use error_stack::{Report, Result, ResultExt};
 use handlebars::{no_escape, Handlebars};
 use serde::{Deserialize, Serialize};
@@ -17,13 +16,21 @@ use crate::{
         text_edit_data::TextOptionData,
     },
     apps::SupportedApps,
+    defaults,
     errors::{ProjectError, TemplatingError},
-    utils::{update_file, BASE_TEMPLATE},
+    migrations,
+    nix_format::format_nix,
+    reverse_proxy::{ProxyRoute, ProxyRouteProvider},
+    tor::{HiddenService, HiddenServiceProvider},
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
 };
 
 pub const TEMPLATE_FILE_NAME: &str = "src/apps/blitz_api.nix.templ";
 pub const JSON_FILE_NAME: &str = "src/apps/blitz_api.json";
 
+/// The name of the bitcoind RPC user that is generated for Blitz API
+pub const BITCOIND_RPC_USER: &str = "blitz_api";
+
 /// To which node to connect to
 enum ConnectionType {
     /// LND via GRPC
@@ -150,6 +157,11 @@ pub struct BlitzApiService {
 
     /// Where to which path the service should be mounted to
     pub nginx_location: Box<TextOptionData>,
+
+    /// The bitcoind RPC user to connect with. Set when the service is
+    /// enabled, nix-bitcoin generates its password on the node.
+    #[serde(default)]
+    pub bitcoind_rpc_user: String,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -337,12 +349,7 @@ impl AppConfig for BlitzApiService {
             "Failed at rendering blitz api config".to_string(),
         ))?;
 
-        for (key, val) in rendered_nix.iter() {
-            update_file(
-                Path::new(&work_dir.join(key.replace(".templ", ""))),
-                val.as_bytes(),
-            )?;
-        }
+        update_nix_files(work_dir, &rendered_nix)?;
 
         update_file(
             Path::new(&work_dir.join(JSON_FILE_NAME)),
@@ -376,24 +383,24 @@ impl Default for BlitzApiService {
             )),
             env_file: Box::new(TextOptionData::new(
                 BlitzApiConfigOption::EnvFile.to_option_id(),
-                "/etc/blitz_api/env".to_string(),
+                defaults::blitz_api::ENV_FILE.to_string(),
                 1,
                 false,
-                "/etc/blitz_api/env".to_string(),
+                defaults::blitz_api::ENV_FILE.to_string(),
             )),
             password_file: Box::new(TextOptionData::new(
                 BlitzApiConfigOption::PasswordFile.to_option_id(),
-                "/etc/blitz_api/password".to_string(),
+                defaults::blitz_api::PASSWORD_FILE.to_string(),
                 1,
                 false,
-                "/etc/blitz_api/password".to_string(),
+                defaults::blitz_api::PASSWORD_FILE.to_string(),
             )),
             root_path: Box::new(TextOptionData::new(
                 BlitzApiConfigOption::RootPath.to_option_id(),
-                "/api".to_string(),
+                defaults::blitz_api::ROOT_PATH.to_string(),
                 1,
                 false,
-                "/api".to_string(),
+                defaults::blitz_api::ROOT_PATH.to_string(),
             )),
             nginx_enable: Box::new(BoolOptionData::new(
                 BlitzApiConfigOption::NginxEnable.to_option_id(),
@@ -405,15 +412,49 @@ impl Default for BlitzApiService {
             )),
             nginx_location: Box::new(TextOptionData::new(
                 BlitzApiConfigOption::NginxLocation.to_option_id(),
-                "/".to_string(),
+                defaults::blitz_api::NGINX_LOCATION.to_string(),
                 1,
                 false,
-                "/".to_string(),
+                defaults::blitz_api::NGINX_LOCATION.to_string(),
             )),
+            bitcoind_rpc_user: String::new(),
         }
     }
 }
 
+impl HiddenServiceProvider for BlitzApiService {
+    fn hidden_service(&self) -> Option<HiddenService> {
+        if !self.enable.value() {
+            return None;
+        }
+
+        Some(HiddenService::new(
+            SupportedApps::BlitzAPI,
+            "blitz-api",
+            defaults::tor::NGINX_PORT,
+            false,
+        ))
+    }
+}
+
+impl ProxyRouteProvider for BlitzApiService {
+    /// The NixOS module declares the location itself
+    fn proxy_route(&self) -> Option<ProxyRoute> {
+        if !self.enable.value() || !self.nginx_enable.value() {
+            return None;
+        }
+
+        Some(ProxyRoute::new(
+            SupportedApps::BlitzAPI,
+            BlitzApiConfigOption::NginxEnable.to_option_id(),
+            self.nginx_location.value(),
+            None,
+            false,
+            false,
+        ))
+    }
+}
+
 impl BlitzApiService {
     pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
         let mut handlebars = Handlebars::new();
@@ -461,29 +502,23 @@ impl BlitzApiService {
                 format!("{}", self.nginx_open_firewall.value()),
             ),
             ("nginx_location", self.nginx_location.value().to_string()),
+            ("bitcoind_rpc_user", self.bitcoind_rpc_user.clone()),
         ]);
 
         let res = handlebars
             .render(TEMPLATE_FILE_NAME, &data)
             .attach_printable("Failed to render Blitz API template".to_string())
             .change_context(TemplatingError::Render)?;
-        let (status, text) = format::in_memory("<blitz_api>".to_string(), res);
-
-        if let format::Status::Error(e) = status {
-            Err(Report::new(TemplatingError::Format))
-                .attach_printable_lazy(|| text)
-                .attach_printable_lazy(|| {
-                    format!("Could not format the template file due to error: {e}")
-                })?
-        } else {
-            rendered_contents.insert(TEMPLATE_FILE_NAME.to_string(), text);
-        }
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
 
         Ok(rendered_contents)
     }
 
     pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
-        serde_json::to_string(self).change_context(TemplatingError::JsonRenderError)
+        migrations::to_json_string(self)
     }
 
     pub(crate) fn from_json(json_data: &str) -> Result<BlitzApiService, TemplatingError> {
@@ -559,6 +594,7 @@ mod tests {
                 false,
                 "/".to_string(),
             )),
+            bitcoind_rpc_user: BITCOIND_RPC_USER.to_string(),
         }
     }
 
@@ -659,6 +695,16 @@ mod tests {
                 s.nginx_location.to_nix_string(true),
             ));
             assert!(data.contains(&text));
+            let text = trim_lines_left(&format!(
+                r#"
+                bitcoind = {{
+                  rpcUser = "{0}";
+                  rpcPasswordFile = "${{config.nix-bitcoin.secretsDir}}/bitcoin-rpcpassword-{0}";
+                }};"#,
+                s.bitcoind_rpc_user,
+            ));
+            assert!(data.contains(&text));
+            assert!(data.contains("blitz_api.passwordHMACFromFile = true;"));
         } else if let Err(e) = result {
             let msg = e.to_string();
             panic!("{msg}");
@@ -1,7 +1,6 @@
 use core::fmt;
 use std::{collections::HashMap, path::Path, str::FromStr};
 
-use alejandra::format;
 use error_stack::{Report, Result, ResultExt};
 use handlebars::{no_escape, Handlebars};
 use serde::{Deserialize, Serialize};
@@ -15,8 +14,13 @@ use crate::{
         },
     },
     apps::SupportedApps,
+    defaults,
     errors::{ProjectError, TemplatingError},
-    utils::{update_file, BASE_TEMPLATE},
+    migrations,
+    nix_format::format_nix,
+    reverse_proxy::{ProxyRoute, ProxyRouteProvider},
+    tor::{HiddenService, HiddenServiceProvider},
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
 };
 
 pub const TEMPLATE_FILE_NAME: &str = "src/apps/blitz_web.nix.templ";
@@ -116,12 +120,7 @@ impl AppConfig for BlitzWebUiService {
             "Failed at rendering blitz webui config".to_string(),
         ))?;
 
-        for (key, val) in rendered_nix.iter() {
-            update_file(
-                Path::new(&work_dir.join(key.replace(".templ", ""))),
-                val.as_bytes(),
-            )?;
-        }
+        update_nix_files(work_dir, &rendered_nix)?;
 
         update_file(
             Path::new(&work_dir.join(JSON_FILE_NAME)),
@@ -147,6 +146,39 @@ impl Default for BlitzWebUiService {
     }
 }
 
+impl HiddenServiceProvider for BlitzWebUiService {
+    fn hidden_service(&self) -> Option<HiddenService> {
+        if !self.enable.value() {
+            return None;
+        }
+
+        Some(HiddenService::new(
+            SupportedApps::WebUI,
+            "blitz-web",
+            defaults::tor::NGINX_PORT,
+            false,
+        ))
+    }
+}
+
+impl ProxyRouteProvider for BlitzWebUiService {
+    /// The NixOS module declares the location itself
+    fn proxy_route(&self) -> Option<ProxyRoute> {
+        if !self.enable.value() || !self.nginx_enable.value() {
+            return None;
+        }
+
+        Some(ProxyRoute::new(
+            SupportedApps::WebUI,
+            BlitzWebUiConfigOption::NginxEnable.to_option_id(),
+            defaults::blitz_webui::NGINX_LOCATION,
+            None,
+            false,
+            false,
+        ))
+    }
+}
+
 impl BlitzWebUiService {
     pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
         let mut handlebars = Handlebars::new();
@@ -190,21 +222,16 @@ impl BlitzWebUiService {
             .render(TEMPLATE_FILE_NAME, &data)
             .attach_printable("Failed to render Blitz Web UI template".to_string())
             .change_context(TemplatingError::Render)?;
-        let (status, text) = format::in_memory("<blitz_webui>".to_string(), res);
-
-        if let format::Status::Error(e) = status {
-            Err(Report::new(TemplatingError::Format)).attach_printable_lazy(|| {
-                format!("Could not format the template file due to error: {e}")
-            })?
-        } else {
-            rendered_contents.insert(TEMPLATE_FILE_NAME.to_string(), text);
-        }
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
 
         Ok(rendered_contents)
     }
 
     pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
-        serde_json::to_string(self).change_context(TemplatingError::JsonRenderError)
+        migrations::to_json_string(self)
     }
 
     pub(crate) fn from_json(json_data: &str) -> Result<BlitzWebUiService, TemplatingError> {
@@ -0,0 +1,314 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::{app_options, AppConfig},
+    app_option_data::{
+        bool_data::BoolOptionData,
+        option_data::{OptionId, ToOptionId},
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    lnd::LightningNetworkDaemonService,
+    migrations,
+    nix_format::format_nix,
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/bos.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/bos.json";
+
+/// Runs the telegram bot of Balance of Satoshis (bos) against LND.
+///
+/// The bot reports forwards, payments and channel changes of the node
+/// to a telegram chat. The credentials of LND are derived from the LND
+/// config, see [BosService::sync_with_lnd].
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BosService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// File holding the API key of the telegram bot, as given by @BotFather.
+    /// Only the path ends up in the Nix store.
+    pub telegram_token_file: Box<TextOptionData>,
+
+    /// The id of the telegram user the bot talks to. bos prints it
+    /// when connecting to the bot via /connect.
+    pub telegram_user_id: Box<TextOptionData>,
+
+    /// Path to the admin macaroon of LND.
+    /// Derived from the LND network directory.
+    #[serde(default)]
+    pub lnd_macaroon_path: String,
+
+    /// The gRPC endpoint of LND, e.g. 127.0.0.1:10009
+    #[serde(default)]
+    pub lnd_socket: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BosConfigOption {
+    Enable,
+    TelegramTokenFile,
+    TelegramUserId,
+}
+
+impl ToOptionId for BosConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::BalanceOfSatoshis, self.to_string())
+    }
+}
+
+impl FromStr for BosConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<BosConfigOption, ()> {
+        match s {
+            "enable" => Ok(BosConfigOption::Enable),
+            "telegram_token_file" => Ok(BosConfigOption::TelegramTokenFile),
+            "telegram_user_id" => Ok(BosConfigOption::TelegramUserId),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for BosConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            BosConfigOption::Enable => "enable",
+            BosConfigOption::TelegramTokenFile => "telegram_token_file",
+            BosConfigOption::TelegramUserId => "telegram_user_id",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for BosService {
+    app_options!(BosConfigOption {
+        Enable => enable,
+        TelegramTokenFile => telegram_token_file,
+        TelegramUserId => telegram_user_id,
+    });
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering bos config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Default for BosService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                BosConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            telegram_token_file: Box::new(TextOptionData::new(
+                BosConfigOption::TelegramTokenFile.to_option_id(),
+                defaults::bos::TELEGRAM_TOKEN_FILE.into(),
+                1,
+                false,
+                defaults::bos::TELEGRAM_TOKEN_FILE.into(),
+            )),
+            telegram_user_id: Box::new(TextOptionData::new(
+                BosConfigOption::TelegramUserId.to_option_id(),
+                "".into(),
+                1,
+                false,
+                "".into(),
+            )),
+            lnd_macaroon_path: format!("{}/admin.macaroon", defaults::lnd::NETWORK_DIR),
+            lnd_socket: format!("{}:{}", defaults::LOCALHOST, defaults::lnd::RPC_PORT),
+        }
+    }
+}
+
+impl BosService {
+    /// Derives the macaroon path and the gRPC endpoint from the LND config.
+    ///
+    /// # Returns
+    /// Whether the credentials changed
+    pub fn sync_with_lnd(&mut self, lnd: &LightningNetworkDaemonService) -> bool {
+        let macaroon_path = format!("{}/admin.macaroon", lnd.network_dir.value());
+        let address = lnd.rpc_address.value().unwrap_or(defaults::LOCALHOST);
+        let port = lnd
+            .rpc_port
+            .value()
+            .to_string_or(&defaults::lnd::RPC_PORT.to_string());
+        let socket = format!("{}:{}", address, port);
+
+        let changed = self.lnd_macaroon_path != macaroon_path || self.lnd_socket != socket;
+        self.lnd_macaroon_path = macaroon_path;
+        self.lnd_socket = socket;
+
+        changed
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            (
+                "telegram_token_file",
+                self.telegram_token_file.value().to_string(),
+            ),
+            (
+                "telegram_user_id",
+                self.telegram_user_id.value().trim().to_string(),
+            ),
+            ("lnd_macaroon_path", self.lnd_macaroon_path.clone()),
+            ("lnd_socket", self.lnd_socket.clone()),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render bos template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<BosService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::{number_value::NumberValue, utils::init_default_project};
+
+    use super::*;
+
+    fn get_test_service() -> BosService {
+        let mut service = BosService::default();
+        service.enable.set_value(true);
+        service.telegram_user_id.set_value("123456789".into());
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = BosService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_sync_with_lnd() {
+        let mut service = BosService::default();
+        let mut lnd = LightningNetworkDaemonService::default();
+        assert!(!service.sync_with_lnd(&lnd));
+
+        lnd.network_dir
+            .set_value("/data/lnd/chain/bitcoin/mainnet".into());
+        lnd.rpc_port.set_value(NumberValue::U16(Some(10010)));
+        assert!(service.sync_with_lnd(&lnd));
+        assert_eq!(
+            service.lnd_macaroon_path,
+            "/data/lnd/chain/bitcoin/mainnet/admin.macaroon"
+        );
+        assert_eq!(service.lnd_socket, "127.0.0.1:10010");
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains("lib.mkIf true"));
+            assert!(data.contains("--connected 123456789"));
+            assert!(data.contains("socket = \"127.0.0.1:10009\";"));
+        }
+
+        assert!(result.is_ok());
+    }
+}
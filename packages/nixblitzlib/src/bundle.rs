@@ -0,0 +1,445 @@
+//! Config bundles to move a project to another machine.
+//!
+//! A bundle is a single JSON file holding the JSON files and extra nix
+//! config of all apps, and optionally the encrypted secrets from the
+//! [SECRETS_DIR]. Without the secrets, the passwords the services need in
+//! plain text are left out as well and must be set again after the import.
+//! The nix files are not part of the bundle, they are rendered again from
+//! the imported settings with the templates of the importing nixblitz.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use error_stack::{Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    activation,
+    app_option_data::option_data::OptionId,
+    apps::SupportedApps,
+    errors::ProjectError,
+    extra_nix,
+    project::Project,
+    registry::{self, registration_by_name},
+    secret_store::SECRETS_DIR,
+    utils::{load_json_file, to_hex, update_file, write_file_atomic, SECRET_FILE_MODE},
+};
+
+/// The format version of the bundles written by this version of nixblitz.
+/// Bump it whenever a change breaks reading older bundles.
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Bundles larger than this are rejected
+pub const MAX_BUNDLE_SIZE: usize = 16 * 1024 * 1024;
+
+/// The settings of a project, see the [module docs](self)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    /// The format version, see [BUNDLE_VERSION]
+    pub version: u32,
+
+    /// The contents of the JSON files, keyed by the name of the app,
+    /// e.g. "bitcoind"
+    pub apps: BTreeMap<String, String>,
+
+    /// The extra nix config of the apps that have one, keyed by the name
+    /// of the app
+    pub extra_nix: BTreeMap<String, String>,
+
+    /// The hex encoded secret files, keyed by their file name.
+    /// Empty if the bundle was exported without secrets.
+    #[serde(default)]
+    pub secrets: BTreeMap<String, String>,
+}
+
+/// Only used to read the version before the rest of the bundle, so a
+/// bundle of another version gets a helpful error
+#[derive(Deserialize)]
+struct BundleHeader {
+    version: u32,
+}
+
+impl ConfigBundle {
+    /// Serializes the bundle for writing it to a file
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProjectError> {
+        serde_json::to_vec_pretty(self).change_context(ProjectError::BundleError)
+    }
+
+    /// Reads and checks a bundle
+    ///
+    /// # Errors
+    /// [ProjectError::BundleError] if the bundle is larger than
+    /// [MAX_BUNDLE_SIZE], was written for another [BUNDLE_VERSION] or holds
+    /// anything this version of nixblitz doesn't understand
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ProjectError> {
+        if data.len() > MAX_BUNDLE_SIZE {
+            return Err(
+                Report::new(ProjectError::BundleError).attach_printable(format!(
+                    "The bundle has {} bytes, at most {} are allowed",
+                    data.len(),
+                    MAX_BUNDLE_SIZE
+                )),
+            );
+        }
+
+        let header: BundleHeader = serde_json::from_slice(data)
+            .change_context(ProjectError::BundleError)
+            .attach_printable("The file is not a nixblitz config bundle")?;
+        if header.version != BUNDLE_VERSION {
+            return Err(
+                Report::new(ProjectError::BundleError).attach_printable(format!(
+                    "The bundle has version {}, this version of nixblitz reads version {}",
+                    header.version, BUNDLE_VERSION
+                )),
+            );
+        }
+
+        let bundle: Self = serde_json::from_slice(data)
+            .change_context(ProjectError::BundleError)
+            .attach_printable("The file is not a nixblitz config bundle")?;
+        bundle.check()?;
+
+        Ok(bundle)
+    }
+
+    /// Makes sure all apps are known and their settings can be loaded
+    fn check(&self) -> Result<(), ProjectError> {
+        for (name, json) in self.apps.iter() {
+            let reg = registration(name)?;
+            (reg.parse_json)(json)
+                .change_context(ProjectError::BundleError)
+                .attach_printable_lazy(|| {
+                    format!("Unable to read the settings of {}", reg.title)
+                })?;
+        }
+
+        for name in self.extra_nix.keys() {
+            registration(name)?;
+        }
+
+        for (name, data) in self.secrets.iter() {
+            check_secret_name(name)?;
+            from_hex(data).ok_or_else(|| {
+                Report::new(ProjectError::BundleError)
+                    .attach_printable(format!("The secret {} is damaged", name))
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Packs the settings of the project in the given work dir
+///
+/// # Arguments
+/// * `secret_options` - the passwords the services need in plain text,
+///   see [AppConfig::secret_options](crate::app_config::AppConfig::secret_options)
+/// * `include_secrets` - whether to include them and the stored secrets
+pub fn export(
+    work_dir: &Path,
+    secret_options: &[OptionId],
+    include_secrets: bool,
+) -> Result<ConfigBundle, ProjectError> {
+    let mut bundle = ConfigBundle {
+        version: BUNDLE_VERSION,
+        ..Default::default()
+    };
+
+    for reg in registry::APPS.iter() {
+        let json = load_json_file(&work_dir.join(reg.json_file_name))?;
+        let json = if include_secrets {
+            json
+        } else {
+            strip_secrets(&json, secret_options)?
+        };
+        bundle.apps.insert(reg.name().to_string(), json);
+
+        if let Some(extra) = extra_nix::option(work_dir, reg.app) {
+            if !extra.value().trim().is_empty() {
+                bundle
+                    .extra_nix
+                    .insert(reg.name().to_string(), extra.value().to_string());
+            }
+        }
+    }
+
+    let dir = work_dir.join(SECRETS_DIR);
+    if include_secrets && dir.exists() {
+        let entries = fs::read_dir(&dir)
+            .change_context(ProjectError::BundleError)
+            .attach_printable_lazy(|| format!("Unable to read {}", dir.display()))?;
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let Some(name) = entry.file_name().to_str().map(|n| n.to_string()) else {
+                continue;
+            };
+            if !path.is_file() {
+                continue;
+            }
+
+            let data = fs::read(&path)
+                .change_context(ProjectError::BundleError)
+                .attach_printable_lazy(|| format!("Unable to read {}", path.display()))?;
+            bundle.secrets.insert(name, to_hex(&data));
+        }
+    }
+
+    Ok(bundle)
+}
+
+/// Writes the settings of the bundle into an existing project and renders
+/// the nix files of all apps again. Apps missing from the bundle, e.g.
+/// because it was exported by an older version, keep their settings.
+///
+/// # Errors
+/// [ProjectError::BundleError] if the work dir holds no project, a rebuild
+/// is running or the files can't be written
+pub fn import(work_dir: &Path, bundle: &ConfigBundle) -> Result<Project, ProjectError> {
+    let base = registry::registration(SupportedApps::NixOS).json_file_name;
+    if !work_dir.join(base).exists() {
+        return Err(
+            Report::new(ProjectError::BundleError).attach_printable(format!(
+                "{} holds no project, create one with nixblitz init first",
+                work_dir.display()
+            )),
+        );
+    }
+
+    activation::ensure_idle()?;
+
+    // decode all secrets first, a damaged one must not leave a half
+    // imported project behind
+    let mut secrets = vec![];
+    for (name, data) in bundle.secrets.iter() {
+        check_secret_name(name)?;
+        let data = from_hex(data).ok_or_else(|| {
+            Report::new(ProjectError::BundleError)
+                .attach_printable(format!("The secret {} is damaged", name))
+        })?;
+        secrets.push((name, data));
+    }
+
+    for (name, json) in bundle.apps.iter() {
+        let reg = registration(name)?;
+        update_file(&work_dir.join(reg.json_file_name), json.as_bytes())?;
+    }
+
+    for reg in registry::APPS.iter() {
+        let body = bundle.extra_nix.get(reg.name()).map_or("", |b| b.as_str());
+        extra_nix::save(work_dir, reg.app, body)?;
+    }
+
+    if !secrets.is_empty() {
+        let dir = work_dir.join(SECRETS_DIR);
+        fs::create_dir_all(&dir)
+            .change_context(ProjectError::BundleError)
+            .attach_printable_lazy(|| format!("Unable to create {}", dir.display()))?;
+        // the secrets don't exist yet on a new machine
+        for (name, data) in secrets {
+            write_file_atomic(&dir.join(name), &data, Some(SECRET_FILE_MODE))?;
+        }
+    }
+
+    let mut project = Project::load(work_dir.to_path_buf())?;
+    project.render_all()?;
+
+    Ok(project)
+}
+
+fn registration(name: &str) -> Result<&'static registry::AppRegistration, ProjectError> {
+    registration_by_name(name).ok_or_else(|| {
+        Report::new(ProjectError::BundleError).attach_printable(format!("Unknown app {}", name))
+    })
+}
+
+/// Secrets are written into the [SECRETS_DIR], so their names must not
+/// reach outside of it
+fn check_secret_name(name: &str) -> Result<(), ProjectError> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        return Ok(());
+    }
+
+    Err(Report::new(ProjectError::BundleError)
+        .attach_printable(format!("Invalid secret file name {}", name)))
+}
+
+/// Empties the given password options in the JSON data of an app and drops
+/// their references to stored secrets
+fn strip_secrets(json: &str, secret_options: &[OptionId]) -> Result<String, ProjectError> {
+    let mut value: Value = serde_json::from_str(json).change_context(ProjectError::BundleError)?;
+    strip_value(&mut value, secret_options);
+
+    serde_json::to_string(&value).change_context(ProjectError::BundleError)
+}
+
+fn strip_value(value: &mut Value, secret_options: &[OptionId]) {
+    match value {
+        Value::Object(map) => {
+            let is_secret = map
+                .get("id")
+                .and_then(|id| serde_json::from_value::<OptionId>(id.clone()).ok())
+                .is_some_and(|id| secret_options.contains(&id));
+            if is_secret && map.contains_key("hashed_value") {
+                map.insert("hashed_value".to_string(), Value::from(""));
+                map.remove("secret");
+                return;
+            }
+
+            map.values_mut()
+                .for_each(|v| strip_value(v, secret_options));
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|v| strip_value(v, secret_options)),
+        _ => (),
+    }
+}
+
+fn from_hex(data: &str) -> Option<Vec<u8>> {
+    if data.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..data.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(data.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::{
+            option_data::{OptionData, OptionDataChangeNotification, ToOptionId},
+            password_data::PasswordOptionChangeData,
+            text_edit_data::TextOptionChangeData,
+        },
+        grafana::GrafanaConfigOption,
+        lnd::LndConfigOption,
+        utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn project(work_dir: &Path) -> Project {
+        init_default_project(work_dir, Some(false)).unwrap();
+        Project::load(work_dir.to_path_buf()).unwrap()
+    }
+
+    #[test]
+    fn test_export_import() {
+        let source = tempdir().unwrap();
+        let mut project = project(source.path());
+        project.set_selected_app(SupportedApps::LND);
+        project
+            .on_option_changed(OptionDataChangeNotification::TextEdit(
+                TextOptionChangeData::new(
+                    LndConfigOption::Alias.to_option_id(),
+                    "exported".to_string(),
+                ),
+            ))
+            .unwrap();
+        project.set_selected_app(SupportedApps::Grafana);
+        project
+            .on_option_changed(OptionDataChangeNotification::PasswordEdit(
+                PasswordOptionChangeData::new(
+                    GrafanaConfigOption::AdminPassword.to_option_id(),
+                    "my_secret_password".to_string(),
+                    Some("my_secret_password".to_string()),
+                ),
+            ))
+            .unwrap();
+        extra_nix::save(
+            source.path(),
+            SupportedApps::LND,
+            "  services.lnd.extraConfig = \"\";",
+        )
+        .unwrap();
+
+        let bundle = project.export_bundle(false).unwrap();
+        assert!(!bundle.apps["grafana"].contains("my_secret_password"));
+        assert!(bundle.extra_nix.contains_key("lnd"));
+        let bundle = ConfigBundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+
+        let target = tempdir().unwrap();
+        init_default_project(target.path(), Some(false)).unwrap();
+        let imported = import(target.path(), &bundle).unwrap();
+        assert!(imported
+            .get_options_for(SupportedApps::LND)
+            .iter()
+            .any(|o| matches!(o, OptionData::TextEdit(t) if t.value() == "exported")));
+        let nix = fs::read_to_string(target.path().join("src/apps/lnd.nix")).unwrap();
+        assert!(nix.contains("alias=exported"));
+        assert_eq!(
+            extra_nix::option(target.path(), SupportedApps::LND)
+                .unwrap()
+                .value(),
+            "  services.lnd.extraConfig = \"\";"
+        );
+
+        let mut with_secrets = project.export_bundle(true).unwrap();
+        assert_ne!(with_secrets.apps["grafana"], bundle.apps["grafana"]);
+        with_secrets
+            .secrets
+            .insert("grafana-admin_password.age".to_string(), "0aff".to_string());
+
+        // a fresh project has no secrets dir yet
+        let target = tempdir().unwrap();
+        init_default_project(target.path(), Some(false)).unwrap();
+        let imported = import(target.path(), &with_secrets).unwrap();
+        assert!(imported
+            .get_options_for(SupportedApps::Grafana)
+            .iter()
+            .any(|o| matches!(
+                o,
+                OptionData::PasswordEdit(p) if p.hashed_value() == "my_secret_password"
+            )));
+        let secret = target
+            .path()
+            .join(SECRETS_DIR)
+            .join("grafana-admin_password.age");
+        assert_eq!(fs::read(&secret).unwrap(), vec![0x0a, 0xff]);
+        assert_eq!(
+            fs::metadata(&secret).unwrap().permissions().mode() & 0o777,
+            SECRET_FILE_MODE
+        );
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let mut bundle = ConfigBundle {
+            version: BUNDLE_VERSION + 1,
+            ..Default::default()
+        };
+        assert!(ConfigBundle::from_bytes(&bundle.to_bytes().unwrap()).is_err());
+        assert!(ConfigBundle::from_bytes(b"not json").is_err());
+
+        bundle.version = BUNDLE_VERSION;
+        bundle.apps.insert("unknown".to_string(), "{}".to_string());
+        assert!(ConfigBundle::from_bytes(&bundle.to_bytes().unwrap()).is_err());
+
+        bundle.apps.clear();
+        bundle
+            .secrets
+            .insert("../escape".to_string(), "00".to_string());
+        assert!(ConfigBundle::from_bytes(&bundle.to_bytes().unwrap()).is_err());
+
+        bundle.secrets.clear();
+        bundle
+            .secrets
+            .insert("grafana-admin_password.age".to_string(), "0aff".to_string());
+        let read = ConfigBundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(read, bundle);
+        assert_eq!(from_hex("0aff"), Some(vec![0x0a, 0xff]));
+        assert_eq!(from_hex("0g"), None);
+    }
+}
@@ -0,0 +1,509 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_option_data::{
+        bool_data::BoolOptionData,
+        option_data::{
+            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToOptionId,
+        },
+        string_list_data::{StringListOptionData, StringListOptionItem},
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    lnd::LightningNetworkDaemonService,
+    migrations,
+    nix_format::format_nix,
+    utils::{escape_nix_indented_string, update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/channel_backup.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/channel_backup.json";
+
+/// Where the static channel backup is copied to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackupTarget {
+    /// A local directory, e.g. a mounted USB drive
+    #[default]
+    Local,
+
+    /// A remote directory reachable via scp
+    Ssh,
+
+    /// A Nextcloud instance, uploaded via WebDAV
+    Nextcloud,
+}
+
+impl BackupTarget {
+    pub fn to_string_array() -> [&'static str; 3] {
+        ["Local", "SSH", "Nextcloud"]
+    }
+}
+
+impl fmt::Display for BackupTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupTarget::Local => write!(f, "Local"),
+            BackupTarget::Ssh => write!(f, "SSH"),
+            BackupTarget::Nextcloud => write!(f, "Nextcloud"),
+        }
+    }
+}
+
+impl FromStr for BackupTarget {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<BackupTarget, ()> {
+        match s {
+            "Local" => Ok(BackupTarget::Local),
+            "SSH" => Ok(BackupTarget::Ssh),
+            "Nextcloud" => Ok(BackupTarget::Nextcloud),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Copies the LND static channel backup (SCB) off the node whenever it changes.
+///
+/// LND rewrites channel.backup every time a channel is opened or closed.
+/// Without an up to date copy the funds in the channels can't be recovered
+/// if the disk of the node dies.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ChannelBackupService {
+    /// Whether the backup is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// Where to copy the backup to, see [BackupTarget]
+    pub target: Box<StringListOptionData>,
+
+    /// The directory to copy the backup to if the target is local
+    pub local_dir: Box<TextOptionData>,
+
+    /// The remote directory in the scp format, e.g. user@host:/backups/lnd
+    pub ssh_target: Box<TextOptionData>,
+
+    /// The private key used to log into the SSH remote
+    pub ssh_key_file: Box<TextOptionData>,
+
+    /// The WebDAV URL of the Nextcloud directory, e.g.
+    /// https://cloud.example.com/remote.php/dav/files/user/lnd
+    pub nextcloud_url: Box<TextOptionData>,
+
+    /// The Nextcloud user
+    pub nextcloud_user: Box<TextOptionData>,
+
+    /// File holding the Nextcloud app password.
+    /// Only the path ends up in the Nix store.
+    pub nextcloud_password_file: Box<TextOptionData>,
+
+    /// Path to the channel.backup file of LND.
+    /// Derived from the LND network directory.
+    #[serde(default)]
+    pub channel_backup_path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ChannelBackupConfigOption {
+    Enable,
+    Target,
+    LocalDir,
+    SshTarget,
+    SshKeyFile,
+    NextcloudUrl,
+    NextcloudUser,
+    NextcloudPasswordFile,
+}
+
+impl ToOptionId for ChannelBackupConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::ChannelBackup, self.to_string())
+    }
+}
+
+impl FromStr for ChannelBackupConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<ChannelBackupConfigOption, ()> {
+        match s {
+            "enable" => Ok(ChannelBackupConfigOption::Enable),
+            "target" => Ok(ChannelBackupConfigOption::Target),
+            "local_dir" => Ok(ChannelBackupConfigOption::LocalDir),
+            "ssh_target" => Ok(ChannelBackupConfigOption::SshTarget),
+            "ssh_key_file" => Ok(ChannelBackupConfigOption::SshKeyFile),
+            "nextcloud_url" => Ok(ChannelBackupConfigOption::NextcloudUrl),
+            "nextcloud_user" => Ok(ChannelBackupConfigOption::NextcloudUser),
+            "nextcloud_password_file" => Ok(ChannelBackupConfigOption::NextcloudPasswordFile),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ChannelBackupConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            ChannelBackupConfigOption::Enable => "enable",
+            ChannelBackupConfigOption::Target => "target",
+            ChannelBackupConfigOption::LocalDir => "local_dir",
+            ChannelBackupConfigOption::SshTarget => "ssh_target",
+            ChannelBackupConfigOption::SshKeyFile => "ssh_key_file",
+            ChannelBackupConfigOption::NextcloudUrl => "nextcloud_url",
+            ChannelBackupConfigOption::NextcloudUser => "nextcloud_user",
+            ChannelBackupConfigOption::NextcloudPasswordFile => "nextcloud_password_file",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for ChannelBackupService {
+    fn get_options(&self) -> Vec<OptionData> {
+        vec![
+            OptionData::Bool(self.enable.clone()),
+            OptionData::StringList(self.target.clone()),
+            OptionData::TextEdit(self.local_dir.clone()),
+            OptionData::TextEdit(self.ssh_target.clone()),
+            OptionData::TextEdit(self.ssh_key_file.clone()),
+            OptionData::TextEdit(self.nextcloud_url.clone()),
+            OptionData::TextEdit(self.nextcloud_user.clone()),
+            OptionData::TextEdit(self.nextcloud_password_file.clone()),
+        ]
+    }
+
+    fn app_option_changed(
+        &mut self,
+        option: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let id = option.id();
+        if let Ok(opt) = ChannelBackupConfigOption::from_str(&id.option) {
+            let mut res = Ok(false);
+            match opt {
+                ChannelBackupConfigOption::Enable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.enable.value() != val.value);
+                        self.enable.set_value(val.value);
+                    }
+                }
+                ChannelBackupConfigOption::Target => {
+                    if let OptionDataChangeNotification::StringList(val) = option {
+                        if BackupTarget::from_str(&val.value).is_err() {
+                            Err(
+                                Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
+                                    .attach_printable(format!("{:?}", option)),
+                            )?
+                        }
+
+                        res = Ok(self.target.value() != val.value);
+                        self.target.set_value(val.value.clone());
+                    }
+                }
+                ChannelBackupConfigOption::LocalDir => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.local_dir.value() != val.value);
+                        self.local_dir.set_value(val.value.clone());
+                    }
+                }
+                ChannelBackupConfigOption::SshTarget => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.ssh_target.value() != val.value);
+                        self.ssh_target.set_value(val.value.clone());
+                    }
+                }
+                ChannelBackupConfigOption::SshKeyFile => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.ssh_key_file.value() != val.value);
+                        self.ssh_key_file.set_value(val.value.clone());
+                    }
+                }
+                ChannelBackupConfigOption::NextcloudUrl => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.nextcloud_url.value() != val.value);
+                        self.nextcloud_url.set_value(val.value.clone());
+                    }
+                }
+                ChannelBackupConfigOption::NextcloudUser => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.nextcloud_user.value() != val.value);
+                        self.nextcloud_user.set_value(val.value.clone());
+                    }
+                }
+                ChannelBackupConfigOption::NextcloudPasswordFile => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.nextcloud_password_file.value() != val.value);
+                        self.nextcloud_password_file.set_value(val.value.clone());
+                    }
+                }
+            }
+
+            return res;
+        }
+
+        Ok(false)
+    }
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering channel backup config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// A single line text option with the same value as default
+fn text_option(option: ChannelBackupConfigOption, value: &str) -> Box<TextOptionData> {
+    Box::new(TextOptionData::new(
+        option.to_option_id(),
+        value.to_string(),
+        1,
+        false,
+        value.to_string(),
+    ))
+}
+
+impl Default for ChannelBackupService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                ChannelBackupConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            target: Box::new(StringListOptionData::new(
+                ChannelBackupConfigOption::Target.to_option_id(),
+                BackupTarget::Local.to_string(),
+                BackupTarget::to_string_array()
+                    .iter()
+                    .map(|t| StringListOptionItem::new(t.to_string(), t.to_string()))
+                    .collect(),
+            )),
+            local_dir: text_option(
+                ChannelBackupConfigOption::LocalDir,
+                defaults::channel_backup::LOCAL_DIR,
+            ),
+            ssh_target: text_option(ChannelBackupConfigOption::SshTarget, ""),
+            ssh_key_file: text_option(
+                ChannelBackupConfigOption::SshKeyFile,
+                defaults::channel_backup::SSH_KEY_FILE,
+            ),
+            nextcloud_url: text_option(ChannelBackupConfigOption::NextcloudUrl, ""),
+            nextcloud_user: text_option(ChannelBackupConfigOption::NextcloudUser, ""),
+            nextcloud_password_file: text_option(
+                ChannelBackupConfigOption::NextcloudPasswordFile,
+                defaults::channel_backup::NEXTCLOUD_PASSWORD_FILE,
+            ),
+            channel_backup_path: format!("{}/channel.backup", defaults::lnd::NETWORK_DIR),
+        }
+    }
+}
+
+impl ChannelBackupService {
+    /// Derives the path of channel.backup from the LND config.
+    ///
+    /// # Returns
+    /// Whether the path changed
+    pub fn sync_with_lnd(&mut self, lnd: &LightningNetworkDaemonService) -> bool {
+        let path = format!("{}/channel.backup", lnd.network_dir.value());
+
+        let changed = self.channel_backup_path != path;
+        self.channel_backup_path = path;
+
+        changed
+    }
+
+    /// The shell command that copies `$backup` to the selected target as `$name`
+    fn upload_command(&self) -> String {
+        let target = BackupTarget::from_str(self.target.value()).unwrap_or_default();
+        let command = match target {
+            BackupTarget::Local => format!(
+                "install -D -m 600 \"$backup\" \"{}/$name\"",
+                self.local_dir.value()
+            ),
+            BackupTarget::Ssh => format!(
+                "scp -i \"{}\" -o StrictHostKeyChecking=accept-new \"$backup\" \"{}/$name\"",
+                self.ssh_key_file.value(),
+                self.ssh_target.value()
+            ),
+            BackupTarget::Nextcloud => format!(
+                "curl --fail --silent --show-error --user \"{}:$(cat \"{}\")\" --upload-file \"$backup\" \"{}/$name\"",
+                self.nextcloud_user.value(),
+                self.nextcloud_password_file.value(),
+                self.nextcloud_url.value().trim_end_matches('/')
+            ),
+        };
+
+        escape_nix_indented_string(&command)
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            ("channel_backup_path", self.channel_backup_path.clone()),
+            ("upload_command", self.upload_command()),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render channel backup template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<ChannelBackupService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::string_list_data::StringListOptionChangeData, utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn get_test_service() -> ChannelBackupService {
+        let mut service = ChannelBackupService::default();
+        service.enable.set_value(true);
+        service.target.set_value(BackupTarget::Ssh.to_string());
+        service
+            .ssh_target
+            .set_value("backup@10.0.0.5:/srv/lnd".to_string());
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = ChannelBackupService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_target() {
+        let mut service = get_test_service();
+        let change = |value: &str| {
+            OptionDataChangeNotification::StringList(StringListOptionChangeData::new(
+                ChannelBackupConfigOption::Target.to_option_id(),
+                value.into(),
+            ))
+        };
+
+        assert!(service.app_option_changed(&change("Dropbox")).is_err());
+        assert!(service.app_option_changed(&change("Nextcloud")).unwrap());
+        service
+            .nextcloud_url
+            .set_value("https://cloud.example.com/lnd/".to_string());
+        assert!(service
+            .upload_command()
+            .contains("--upload-file \"$backup\" \"https://cloud.example.com/lnd/$name\""));
+
+        assert!(service.app_option_changed(&change("Local")).unwrap());
+        assert_eq!(
+            service.upload_command(),
+            format!(
+                "install -D -m 600 \"$backup\" \"{}/$name\"",
+                defaults::channel_backup::LOCAL_DIR
+            )
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains("PathChanged = backup;"));
+            assert!(data.contains("\"backup@10.0.0.5:/srv/lnd/$name\""));
+            assert!(data.contains(&s.channel_backup_path));
+        }
+
+        assert!(result.is_ok());
+    }
+}
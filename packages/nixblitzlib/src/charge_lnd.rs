@@ -0,0 +1,438 @@
+use core::fmt;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    str::FromStr,
+};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::{app_options, AppConfig},
+    app_option_data::{
+        bool_data::BoolOptionData,
+        manual_string_list_data::ManualStringListOptionData,
+        option_data::{OptionDataChangeNotification, OptionId, ToOptionId},
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
+    utils::{escape_nix_indented_string, update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/charge_lnd.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/charge_lnd.json";
+
+/// Adjusts the channel fees of LND with charge-lnd on a schedule.
+///
+/// Each policy is a named section of the charge-lnd config, written as
+/// `name: key=value, key=value`, e.g.
+/// `drain: chan.max_ratio=0.2, strategy=static, fee_ppm=2000`.
+/// charge-lnd applies the first policy that matches a channel.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ChargeLndService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// The fee policies, in the order charge-lnd checks them
+    pub policies: Box<ManualStringListOptionData>,
+
+    /// When to run, as systemd calendar event, e.g. hourly
+    pub interval: Box<TextOptionData>,
+
+    /// Only log the fees charge-lnd would set
+    pub dry_run: Box<BoolOptionData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ChargeLndConfigOption {
+    Enable,
+    Policies,
+    Interval,
+    DryRun,
+}
+
+impl ToOptionId for ChargeLndConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::ChargeLnd, self.to_string())
+    }
+}
+
+impl FromStr for ChargeLndConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<ChargeLndConfigOption, ()> {
+        match s {
+            "enable" => Ok(ChargeLndConfigOption::Enable),
+            "policies" => Ok(ChargeLndConfigOption::Policies),
+            "interval" => Ok(ChargeLndConfigOption::Interval),
+            "dry_run" => Ok(ChargeLndConfigOption::DryRun),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ChargeLndConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            ChargeLndConfigOption::Enable => "enable",
+            ChargeLndConfigOption::Policies => "policies",
+            ChargeLndConfigOption::Interval => "interval",
+            ChargeLndConfigOption::DryRun => "dry_run",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+/// A policy split into its name and settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeePolicy {
+    pub name: String,
+
+    /// The key value pairs, in the given order
+    pub settings: Vec<(String, String)>,
+}
+
+impl FromStr for FeePolicy {
+    type Err = String;
+
+    /// Parses a policy in the form `name: key=value, key=value`
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        let Some((name, settings)) = s.split_once(':') else {
+            return Err(format!("{} lacks a name, e.g. \"default: ...\"", s));
+        };
+
+        let name = name.trim();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_-".contains(c))
+        {
+            return Err(format!("\"{}\" is not a valid policy name", name));
+        }
+
+        let mut res = vec![];
+        for setting in settings.split(',').map(str::trim) {
+            let Some((key, value)) = setting.split_once('=') else {
+                return Err(format!("{}: \"{}\" is not a key=value pair", name, setting));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            if key.is_empty()
+                || value.is_empty()
+                || !key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "._".contains(c))
+            {
+                return Err(format!("{}: \"{}\" is not a key=value pair", name, setting));
+            }
+
+            res.push((key.to_string(), value.to_string()));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            settings: res,
+        })
+    }
+}
+
+impl fmt::Display for FeePolicy {
+    /// Writes the policy as section of the charge-lnd config
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[{}]", self.name)?;
+        for (key, value) in &self.settings {
+            writeln!(f, "{} = {}", key, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the given policies
+///
+/// # Errors
+///
+/// [ProjectError::InvalidValue] listing all invalid or duplicate policies
+pub fn parse_policies(option: &str, policies: &[String]) -> Result<Vec<FeePolicy>, ProjectError> {
+    let mut res = vec![];
+    let mut issues = vec![];
+    let mut names = HashSet::new();
+    for policy in policies.iter().filter(|p| !p.trim().is_empty()) {
+        match FeePolicy::from_str(policy) {
+            Ok(p) if !names.insert(p.name.clone()) => {
+                issues.push(format!("The policy {} is defined twice", p.name))
+            }
+            Ok(p) => res.push(p),
+            Err(e) => issues.push(e),
+        }
+    }
+
+    if issues.is_empty() {
+        return Ok(res);
+    }
+
+    Err(Report::new(ProjectError::InvalidValue(
+        option.to_string(),
+        issues,
+    )))
+}
+
+impl AppConfig for ChargeLndService {
+    app_options!(ChargeLndConfigOption {
+        Enable => enable,
+        Policies => policies where validate_policies,
+        Interval => interval,
+        DryRun => dry_run,
+    });
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering charge-lnd config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Default for ChargeLndService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                ChargeLndConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            policies: Box::new(ManualStringListOptionData::new(
+                ChargeLndConfigOption::Policies.to_option_id(),
+                vec![defaults::charge_lnd::DEFAULT_POLICY.to_string()],
+                0,
+            )),
+            interval: Box::new(TextOptionData::new(
+                ChargeLndConfigOption::Interval.to_option_id(),
+                defaults::charge_lnd::INTERVAL.to_string(),
+                1,
+                false,
+                defaults::charge_lnd::INTERVAL.to_string(),
+            )),
+            dry_run: Box::new(BoolOptionData::new(
+                ChargeLndConfigOption::DryRun.to_option_id(),
+                true,
+            )),
+        }
+    }
+}
+
+impl ChargeLndService {
+    fn validate_policies(&self, option: &OptionDataChangeNotification) -> Result<(), ProjectError> {
+        if let OptionDataChangeNotification::ManualStringList(val) = option {
+            parse_policies(&ChargeLndConfigOption::Policies.to_string(), &val.value)?;
+        }
+
+        Ok(())
+    }
+
+    /// The policies as charge-lnd config, invalid ones are left out
+    fn policies_config(&self) -> String {
+        self.policies
+            .value()
+            .iter()
+            .filter_map(|p| FeePolicy::from_str(p).ok())
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let extra_flags = if self.dry_run.value() {
+            "\"--dry-run\" \"-v\""
+        } else {
+            ""
+        };
+
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            ("interval", self.interval.value().trim().to_string()),
+            ("extra_flags", extra_flags.to_string()),
+            (
+                "policies",
+                escape_nix_indented_string(&self.policies_config()),
+            ),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render charge-lnd template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<ChargeLndService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::manual_string_list_data::ManualStringListOptionChangeData,
+        utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn get_test_service() -> ChargeLndService {
+        let mut service = ChargeLndService::default();
+        service.enable.set_value(true);
+        service.dry_run.set_value(false);
+        service.policies.set_value(vec![
+            "drain: chan.max_ratio=0.2, strategy=static, fee_ppm=2000".into(),
+            "default: strategy=static, base_fee_msat=1000, fee_ppm=100".into(),
+        ]);
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = ChargeLndService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_policies() {
+        let policy = FeePolicy::from_str("drain: chan.max_ratio=0.2, fee_ppm=2000").unwrap();
+        assert_eq!(policy.name, "drain");
+        assert_eq!(
+            policy.to_string(),
+            "[drain]\nchan.max_ratio = 0.2\nfee_ppm = 2000\n"
+        );
+
+        assert!(FeePolicy::from_str("strategy=static").is_err());
+        assert!(FeePolicy::from_str("my policy: strategy=static").is_err());
+        assert!(FeePolicy::from_str("a: strategy").is_err());
+        assert!(FeePolicy::from_str("a: strategy=").is_err());
+
+        let mut service = ChargeLndService::default();
+        let change = |value: Vec<&str>| {
+            OptionDataChangeNotification::ManualStringList(ManualStringListOptionChangeData::new(
+                ChargeLndConfigOption::Policies.to_option_id(),
+                value.into_iter().map(String::from).collect(),
+            ))
+        };
+
+        let res = service.app_option_changed(&change(vec![
+            "a: strategy=static",
+            "a: fee_ppm=1",
+            "broken",
+        ]));
+        match res.unwrap_err().current_context() {
+            ProjectError::InvalidValue(_, issues) => assert_eq!(issues.len(), 2),
+            e => panic!("unexpected error {:?}", e),
+        }
+
+        assert!(service
+            .app_option_changed(&change(vec!["a: strategy=static"]))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains("enable = true;"));
+            assert!(data.contains("interval = \"hourly\";"));
+            assert!(data.contains("extraFlags = [];"));
+            assert!(data.contains("[drain]\n"));
+            assert!(data.contains("fee_ppm = 2000\n"));
+        }
+
+        assert!(result.is_ok());
+    }
+}
@@ -1,27 +1,29 @@
 use core::fmt;
-use std::{collections::HashMap, net::IpAddr, path::Path, str::FromStr};
+use std::{collections::HashMap, path::Path, str::FromStr};
 
-use alejandra::format;
 use error_stack::{Report, Result, ResultExt};
 use handlebars::{no_escape, Handlebars};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    app_config::AppConfig,
+    app_config::{app_options, AppConfig},
     app_option_data::{
         bool_data::BoolOptionData,
+        code_data::{CodeLanguage, CodeOptionData},
         net_address_data::NetAddressOptionData,
-        option_data::{
-            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToNixString,
-            ToOptionId,
-        },
+        number_data::NumberOptionData,
+        option_data::{OptionId, ToNixString, ToOptionId},
         port_data::PortOptionData,
         text_edit_data::TextOptionData,
     },
     apps::SupportedApps,
+    defaults,
     errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
     number_value::NumberValue,
-    utils::{update_file, BASE_TEMPLATE},
+    tor::{port_or, HiddenService, HiddenServiceProvider},
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
 };
 
 pub const TEMPLATE_FILE_NAME: &str = "src/apps/cln.nix.templ";
@@ -71,7 +73,32 @@ pub struct CoreLightningService {
     /// example: "
     ///   alias=mynode
     /// "
-    pub extra_config: Box<TextOptionData>,
+    pub extra_config: Box<CodeOptionData>,
+
+    /// The base fee in millisatoshi charged for forwarding a payment.
+    ///
+    /// default: 1000
+    pub base_fee_msat: Box<NumberOptionData>,
+
+    /// The fee rate in parts per million charged on the forwarded amount.
+    ///
+    /// default: 10
+    pub fee_rate_ppm: Box<NumberOptionData>,
+
+    /// The smallest HTLC in millisatoshi accepted on new channels.
+    ///
+    /// default: 0
+    pub min_htlc_msat: Box<NumberOptionData>,
+
+    /// The largest HTLC in millisatoshi accepted on new channels.
+    ///
+    /// default: None (no limit besides the channel capacity)
+    pub max_htlc_msat: Box<NumberOptionData>,
+
+    /// The CLTV delta in blocks subtracted from forwarded HTLCs.
+    ///
+    /// default: 34
+    pub cltv_delta: Box<NumberOptionData>,
 
     /// The user as which to run clightning.
     ///
@@ -100,6 +127,11 @@ pub enum ClnConfigOption {
     DataDir,
     Wallet,
     ExtraConfig,
+    BaseFeeMsat,
+    FeeRatePpm,
+    MinHtlcMsat,
+    MaxHtlcMsat,
+    CltvDelta,
     User,
     Group,
     GetPublicAddressCmd,
@@ -123,6 +155,11 @@ impl FromStr for ClnConfigOption {
             "data_dir" => Ok(ClnConfigOption::DataDir),
             "wallet" => Ok(ClnConfigOption::Wallet),
             "extra_config" => Ok(ClnConfigOption::ExtraConfig),
+            "base_fee_msat" => Ok(ClnConfigOption::BaseFeeMsat),
+            "fee_rate_ppm" => Ok(ClnConfigOption::FeeRatePpm),
+            "min_htlc_msat" => Ok(ClnConfigOption::MinHtlcMsat),
+            "max_htlc_msat" => Ok(ClnConfigOption::MaxHtlcMsat),
+            "cltv_delta" => Ok(ClnConfigOption::CltvDelta),
             "user" => Ok(ClnConfigOption::User),
             "group" => Ok(ClnConfigOption::Group),
             "get_public_address_cmd" => Ok(ClnConfigOption::GetPublicAddressCmd),
@@ -142,6 +179,11 @@ impl fmt::Display for ClnConfigOption {
             ClnConfigOption::DataDir => "data_dir",
             ClnConfigOption::Wallet => "wallet",
             ClnConfigOption::ExtraConfig => "extra_config",
+            ClnConfigOption::BaseFeeMsat => "base_fee_msat",
+            ClnConfigOption::FeeRatePpm => "fee_rate_ppm",
+            ClnConfigOption::MinHtlcMsat => "min_htlc_msat",
+            ClnConfigOption::MaxHtlcMsat => "max_htlc_msat",
+            ClnConfigOption::CltvDelta => "cltv_delta",
             ClnConfigOption::User => "user",
             ClnConfigOption::Group => "group",
             ClnConfigOption::GetPublicAddressCmd => "get_public_address_cmd",
@@ -151,102 +193,24 @@ impl fmt::Display for ClnConfigOption {
 }
 
 impl AppConfig for CoreLightningService {
-    fn get_options(&self) -> Vec<OptionData> {
-        vec![
-            OptionData::Bool(self.enable.clone()),
-            OptionData::NetAddress(self.address.clone()),
-            OptionData::Port(self.port.clone()),
-            OptionData::TextEdit(self.proxy.clone()),
-            OptionData::Bool(self.always_use_proxy.clone()),
-            OptionData::TextEdit(self.data_dir.clone()),
-            OptionData::TextEdit(self.wallet.clone()),
-            OptionData::TextEdit(self.extra_config.clone()),
-            OptionData::TextEdit(self.user.clone()),
-            OptionData::TextEdit(self.group.clone()),
-            OptionData::TextEdit(self.get_public_address_cmd.clone()),
-        ]
-    }
-
-    fn app_option_changed(
-        &mut self,
-        option: &OptionDataChangeNotification,
-    ) -> Result<bool, ProjectError> {
-        let id = option.id();
-        if let Ok(opt) = ClnConfigOption::from_str(&id.option) {
-            let mut res = Ok(false);
-            match opt {
-                ClnConfigOption::Enable => {
-                    if let OptionDataChangeNotification::Bool(val) = option {
-                        res = Ok(self.enable.value() != val.value);
-                        self.enable.set_value(val.value);
-                    }
-                }
-                ClnConfigOption::Address => {
-                    if let OptionDataChangeNotification::NetAddress(val) = option {
-                        res = Ok(self.address.value() != val.value);
-                        self.address.set_value(val.value);
-                    }
-                }
-                ClnConfigOption::Port => {
-                    if let OptionDataChangeNotification::Port(val) = option {
-                        res = Ok(*self.port.value() != val.value);
-                        self.port.set_value(val.value.clone());
-                    }
-                }
-                ClnConfigOption::Proxy => {
-                    if let OptionDataChangeNotification::TextEdit(val) = option {
-                        res = Ok(self.proxy.value() != val.value);
-                        self.proxy.set_value(val.value.clone());
-                    }
-                }
-                ClnConfigOption::AlwaysUseProxy => {
-                    if let OptionDataChangeNotification::Bool(val) = option {
-                        res = Ok(self.always_use_proxy.value() != val.value);
-                        self.always_use_proxy.set_value(val.value);
-                    }
-                }
-                ClnConfigOption::DataDir => {
-                    if let OptionDataChangeNotification::TextEdit(val) = option {
-                        res = Ok(self.data_dir.value() != val.value);
-                        self.data_dir.set_value(val.value.clone());
-                    }
-                }
-                ClnConfigOption::Wallet => {
-                    if let OptionDataChangeNotification::TextEdit(val) = option {
-                        res = Ok(self.wallet.value() != val.value);
-                        self.wallet.set_value(val.value.clone());
-                    }
-                }
-                ClnConfigOption::ExtraConfig => {
-                    if let OptionDataChangeNotification::TextEdit(val) = option {
-                        res = Ok(self.extra_config.value() != val.value);
-                        self.extra_config.set_value(val.value.clone());
-                    }
-                }
-                ClnConfigOption::User => {
-                    if let OptionDataChangeNotification::TextEdit(val) = option {
-                        res = Ok(self.user.value() != val.value);
-                        self.user.set_value(val.value.clone());
-                    }
-                }
-                ClnConfigOption::Group => {
-                    if let OptionDataChangeNotification::TextEdit(val) = option {
-                        res = Ok(self.group.value() != val.value);
-                        self.group.set_value(val.value.clone());
-                    }
-                }
-                ClnConfigOption::GetPublicAddressCmd => {
-                    if let OptionDataChangeNotification::TextEdit(val) = option {
-                        res = Ok(self.get_public_address_cmd.value() != val.value);
-                        self.get_public_address_cmd.set_value(val.value.clone());
-                    }
-                }
-            }
-            return res;
-        }
-
-        Ok(false)
-    }
+    app_options!(ClnConfigOption {
+        Enable => enable,
+        Address => address,
+        Port => port,
+        Proxy => proxy,
+        AlwaysUseProxy => always_use_proxy,
+        DataDir => data_dir,
+        Wallet => wallet,
+        ExtraConfig => extra_config,
+        BaseFeeMsat => base_fee_msat,
+        FeeRatePpm => fee_rate_ppm,
+        MinHtlcMsat => min_htlc_msat,
+        MaxHtlcMsat => max_htlc_msat,
+        CltvDelta => cltv_delta,
+        User => user,
+        Group => group,
+        GetPublicAddressCmd => get_public_address_cmd,
+    });
 
     fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
         let rendered_json = self
@@ -256,12 +220,7 @@ impl AppConfig for CoreLightningService {
             "Failed at rendering cln config".to_string(),
         ))?;
 
-        for (key, val) in rendered_nix.iter() {
-            update_file(
-                Path::new(&work_dir.join(key.replace(".templ", ""))),
-                val.as_bytes(),
-            )?;
-        }
+        update_nix_files(work_dir, &rendered_nix)?;
 
         update_file(
             Path::new(&work_dir.join(JSON_FILE_NAME)),
@@ -281,11 +240,11 @@ impl Default for CoreLightningService {
             )),
             address: Box::new(NetAddressOptionData::new(
                 ClnConfigOption::Address.to_option_id(),
-                Some(IpAddr::from_str("127.0.0.1").unwrap()),
+                Some(defaults::LOCALHOST),
             )),
             port: Box::new(PortOptionData::new(
                 ClnConfigOption::Port.to_option_id(),
-                NumberValue::U16(Some(9735)),
+                NumberValue::U16(Some(defaults::cln::PORT)),
             )),
             proxy: Box::new(TextOptionData::new(
                 ClnConfigOption::Proxy.to_option_id(),
@@ -300,10 +259,10 @@ impl Default for CoreLightningService {
             )),
             data_dir: Box::new(TextOptionData::new(
                 ClnConfigOption::DataDir.to_option_id(),
-                "/var/lib/clightning".to_string(),
+                defaults::cln::DATA_DIR.to_string(),
                 1,
                 false,
-                "/var/lib/clightning".to_string(),
+                defaults::cln::DATA_DIR.to_string(),
             )),
             wallet: Box::new(TextOptionData::new(
                 ClnConfigOption::Wallet.to_option_id(),
@@ -312,13 +271,69 @@ impl Default for CoreLightningService {
                 false,
                 "sqlite3:///var/lib/clightning/bitcoin/lightningd.sqlite3".to_string(),
             )),
-            extra_config: Box::new(TextOptionData::new(
+            extra_config: Box::new(CodeOptionData::new(
                 ClnConfigOption::ExtraConfig.to_option_id(),
                 "".to_string(),
                 9999,
                 false,
                 "".to_string(),
+                CodeLanguage::Ini,
             )),
+            base_fee_msat: Box::new(
+                NumberOptionData::new(
+                    ClnConfigOption::BaseFeeMsat.to_option_id(),
+                    NumberValue::UInt(Some(defaults::cln::BASE_FEE_MSAT)),
+                    0,
+                    100_000_000,
+                    false,
+                    NumberValue::UInt(Some(defaults::cln::BASE_FEE_MSAT)),
+                )
+                .unwrap(),
+            ),
+            fee_rate_ppm: Box::new(
+                NumberOptionData::new(
+                    ClnConfigOption::FeeRatePpm.to_option_id(),
+                    NumberValue::UInt(Some(defaults::cln::FEE_RATE_PPM)),
+                    0,
+                    1_000_000,
+                    false,
+                    NumberValue::UInt(Some(defaults::cln::FEE_RATE_PPM)),
+                )
+                .unwrap(),
+            ),
+            min_htlc_msat: Box::new(
+                NumberOptionData::new(
+                    ClnConfigOption::MinHtlcMsat.to_option_id(),
+                    NumberValue::UInt(Some(defaults::cln::MIN_HTLC_MSAT)),
+                    0,
+                    100_000_000,
+                    false,
+                    NumberValue::UInt(Some(defaults::cln::MIN_HTLC_MSAT)),
+                )
+                .unwrap(),
+            ),
+            max_htlc_msat: Box::new(
+                NumberOptionData::new(
+                    ClnConfigOption::MaxHtlcMsat.to_option_id(),
+                    NumberValue::UInt(None),
+                    1,
+                    usize::MAX,
+                    false,
+                    NumberValue::UInt(None),
+                )
+                .unwrap(),
+            ),
+            cltv_delta: Box::new(
+                NumberOptionData::new(
+                    ClnConfigOption::CltvDelta.to_option_id(),
+                    NumberValue::UInt(Some(defaults::cln::CLTV_DELTA)),
+                    18,
+                    2016,
+                    false,
+                    NumberValue::UInt(Some(defaults::cln::CLTV_DELTA)),
+                )
+                .unwrap(),
+            ),
             user: Box::new(TextOptionData::new(
                 ClnConfigOption::User.to_option_id(),
                 "admin".to_string(),
@@ -344,6 +359,21 @@ impl Default for CoreLightningService {
     }
 }
 
+impl HiddenServiceProvider for CoreLightningService {
+    fn hidden_service(&self) -> Option<HiddenService> {
+        if !self.enable.value() {
+            return None;
+        }
+
+        Some(HiddenService::new(
+            SupportedApps::CoreLightning,
+            "clightning",
+            port_or(self.port.value(), defaults::cln::PORT),
+            true,
+        ))
+    }
+}
+
 impl CoreLightningService {
     pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
         let mut handlebars = Handlebars::new();
@@ -397,6 +427,7 @@ impl CoreLightningService {
             ("data_dir", format!("\"{}\"", self.data_dir.value())),
             ("wallet", format!("\"{}\"", self.wallet.value())),
             ("extra_config", self.extra_config.value().to_string()),
+            ("fee_policy", self.fee_policy()),
             ("user", format!("\"{}\"", self.user.value())),
             ("group", format!("\"{}\"", self.group.value())),
             (
@@ -410,21 +441,37 @@ impl CoreLightningService {
             .attach_printable("Failed to render Core Lightning template".to_string())
             .change_context(TemplatingError::Render)?;
 
-        let (status, text) = format::in_memory("<cln>".to_string(), res);
-
-        if let format::Status::Error(e) = status {
-            Err(Report::new(TemplatingError::Format)).attach_printable_lazy(|| {
-                format!("Could not format the template file due to error: {e}")
-            })?
-        } else {
-            rendered_contents.insert(TEMPLATE_FILE_NAME.to_string(), text);
-        }
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
 
         Ok(rendered_contents)
     }
 
+    /// Renders the channel fee and HTLC policy as config file lines.
+    /// Unset values are omitted so the daemon falls back to its own defaults.
+    fn fee_policy(&self) -> String {
+        let policy = [
+            ("fee-base", &self.base_fee_msat),
+            ("fee-per-satoshi", &self.fee_rate_ppm),
+            ("htlc-minimum-msat", &self.min_htlc_msat),
+            ("htlc-maximum-msat", &self.max_htlc_msat),
+            ("cltv-delta", &self.cltv_delta),
+        ];
+
+        policy
+            .iter()
+            .filter_map(|(key, option)| {
+                let value = option.value().to_string();
+                (!value.is_empty()).then(|| format!("{key}={value}"))
+            })
+            .collect::<Vec<_>>()
+            .join("\n      ")
+    }
+
     pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
-        serde_json::to_string(self).change_context(TemplatingError::JsonRenderError)
+        migrations::to_json_string(self)
     }
 
     pub(crate) fn from_json(json_data: &str) -> Result<CoreLightningService, TemplatingError> {
@@ -437,7 +484,9 @@ mod tests {
     use std::{fs, net::IpAddr, str::FromStr};
     use tempfile::tempdir;
 
-    use crate::utils::init_default_project;
+    use crate::{
+        app_option_data::option_data::OptionDataChangeNotification, utils::init_default_project,
+    };
 
     use super::*;
 
@@ -480,13 +529,69 @@ mod tests {
                 false,
                 "sqlite3:///var/lib/clightning/bitcoin/lightningd.sqlite3".to_string(),
             )),
-            extra_config: Box::new(TextOptionData::new(
+            extra_config: Box::new(CodeOptionData::new(
                 ClnConfigOption::ExtraConfig.to_option_id(),
                 "var1=this is extra config".to_string(),
                 1,
                 false,
                 "var1=this is extra config".to_string(),
+                CodeLanguage::Ini,
             )),
+            base_fee_msat: Box::new(
+                NumberOptionData::new(
+                    ClnConfigOption::BaseFeeMsat.to_option_id(),
+                    NumberValue::UInt(Some(defaults::cln::BASE_FEE_MSAT)),
+                    0,
+                    100_000_000,
+                    false,
+                    NumberValue::UInt(Some(defaults::cln::BASE_FEE_MSAT)),
+                )
+                .unwrap(),
+            ),
+            fee_rate_ppm: Box::new(
+                NumberOptionData::new(
+                    ClnConfigOption::FeeRatePpm.to_option_id(),
+                    NumberValue::UInt(Some(defaults::cln::FEE_RATE_PPM)),
+                    0,
+                    1_000_000,
+                    false,
+                    NumberValue::UInt(Some(defaults::cln::FEE_RATE_PPM)),
+                )
+                .unwrap(),
+            ),
+            min_htlc_msat: Box::new(
+                NumberOptionData::new(
+                    ClnConfigOption::MinHtlcMsat.to_option_id(),
+                    NumberValue::UInt(Some(defaults::cln::MIN_HTLC_MSAT)),
+                    0,
+                    100_000_000,
+                    false,
+                    NumberValue::UInt(Some(defaults::cln::MIN_HTLC_MSAT)),
+                )
+                .unwrap(),
+            ),
+            max_htlc_msat: Box::new(
+                NumberOptionData::new(
+                    ClnConfigOption::MaxHtlcMsat.to_option_id(),
+                    NumberValue::UInt(None),
+                    1,
+                    usize::MAX,
+                    false,
+                    NumberValue::UInt(None),
+                )
+                .unwrap(),
+            ),
+            cltv_delta: Box::new(
+                NumberOptionData::new(
+                    ClnConfigOption::CltvDelta.to_option_id(),
+                    NumberValue::UInt(Some(defaults::cln::CLTV_DELTA)),
+                    18,
+                    2016,
+                    false,
+                    NumberValue::UInt(Some(defaults::cln::CLTV_DELTA)),
+                )
+                .unwrap(),
+            ),
             user: Box::new(TextOptionData::new(
                 ClnConfigOption::User.to_option_id(),
                 "tester".to_string(),
@@ -594,13 +699,19 @@ mod tests {
             assert!(&data.contains_key(TEMPLATE_FILE_NAME));
             let data = &data[TEMPLATE_FILE_NAME];
             assert!(data.contains(&format!("enable = {};", s.enable.value())));
-            assert!(data.contains(&format!("address = {};", s.address.to_nix_string(true))));
+            assert!(data.contains(&format!(
+                "address = lib.mkDefault {};",
+                s.address.to_nix_string(true)
+            )));
             assert!(data.contains(&format!("port = {};", s.port.value())));
             assert!(data.contains(&format!("dataDir = \"{}\";", s.data_dir.value())));
             s.extra_config
                 .value()
                 .lines()
                 .for_each(|line| assert!(data.contains(line)));
+            assert!(data.contains(&format!("fee-base={}", s.base_fee_msat.value())));
+            assert!(data.contains(&format!("cltv-delta={}", s.cltv_delta.value())));
+            assert!(!data.contains("htlc-maximum-msat"));
             assert!(data.contains(&format!("user = \"{}\";", s.user.value())));
             assert!(data.contains(&format!("group = \"{}\";", s.group.value())));
             assert!(data.contains(&format!(
@@ -0,0 +1,234 @@
+//! Initializes a project from an existing config repository, e.g. to manage
+//! the same node from a second machine or to move a config to new hardware.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::{
+    errors::ProjectError,
+    nix_base_config::{self, NixBaseConfig},
+    platform::{resolve_platform, save_local_platform, SystemPlatform},
+    project::Project,
+    recovery::find_broken_files,
+    template_upgrade::plan_upgrade,
+    utils::load_json_file,
+    validation::{validate, ConfigConflict},
+};
+
+/// What was found in the cloned project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClonedProject {
+    /// The platform stored for the local machine
+    pub platform: SystemPlatform,
+
+    /// The NixOS configuration of the project flake matching the platform
+    pub configuration: String,
+
+    /// Nix files the current templates would change, see
+    /// [template_upgrade](crate::template_upgrade)
+    pub pending_upgrades: Vec<PathBuf>,
+
+    /// Conflicts in the configuration, see [validate]
+    pub conflicts: Vec<ConfigConflict>,
+}
+
+/// Clones the config repository at `url` into the work dir and checks that it
+/// holds a project this version can load.
+///
+/// # Arguments
+///
+/// * `work_dir` - must be empty or not exist yet
+/// * `branch` - the branch to check out, the default branch of the remote if `None`
+/// * `platform` - the platform of the local machine, detected if `None`
+///
+/// # Errors
+///
+/// * [ProjectError::UnsupportedPlatform] if the platform is not supported
+/// * [ProjectError::CloneError] if the work dir is not empty, git failed or the
+///   repository isn't a valid project. The cloned files are removed again.
+pub fn clone_project(
+    work_dir: &Path,
+    url: &str,
+    branch: Option<&str>,
+    platform: Option<SystemPlatform>,
+) -> Result<ClonedProject, ProjectError> {
+    let platform = resolve_platform(platform)?;
+
+    let is_empty = match fs::read_dir(work_dir) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(_) => !work_dir.exists(),
+    };
+    if !is_empty {
+        return Err(Report::new(ProjectError::CloneError)
+            .attach_printable(format!("{} is not empty", work_dir.display())));
+    }
+
+    let mut args = vec!["clone"];
+    if let Some(branch) = branch {
+        args.extend(["--branch", branch]);
+    }
+    let target = work_dir.to_string_lossy().to_string();
+    args.extend(["--", url, target.as_str()]);
+    git(&args)?;
+
+    match check_project(work_dir, platform) {
+        Ok(res) => Ok(res),
+        Err(e) => {
+            remove_clone(work_dir);
+            Err(e)
+        }
+    }
+}
+
+/// Validates the cloned files and stores the local platform
+fn check_project(work_dir: &Path, platform: SystemPlatform) -> Result<ClonedProject, ProjectError> {
+    let broken = find_broken_files(work_dir);
+    if !broken.is_empty() {
+        let mut report = Report::new(ProjectError::CloneError)
+            .attach_printable("The repository is not a project of this nixblitz version");
+        for file in broken {
+            report = report.attach_printable(format!("{}: {}", file.path.display(), file.reason));
+        }
+        return Err(report);
+    }
+
+    let project = Project::load(work_dir.to_path_buf()).change_context(ProjectError::CloneError)?;
+    let conflicts = validate(&project);
+    let pending_upgrades = plan_upgrade(work_dir)
+        .change_context(ProjectError::CloneError)?
+        .into_iter()
+        .map(|u| u.path)
+        .collect();
+
+    let nix_base = NixBaseConfig::from_json(&load_json_file(
+        &work_dir.join(nix_base_config::JSON_FILE_NAME),
+    )?)
+    .change_context(ProjectError::CloneError)?;
+    let configuration = nix_base.configuration(&platform).to_string();
+
+    save_local_platform(work_dir, &platform)?;
+
+    Ok(ClonedProject {
+        platform,
+        configuration,
+        pending_upgrades,
+        conflicts,
+    })
+}
+
+/// Removes the contents of the work dir, it was empty before the clone
+fn remove_clone(work_dir: &Path) {
+    let Ok(entries) = fs::read_dir(work_dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let _ = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+    }
+}
+
+fn git(args: &[&str]) -> Result<(), ProjectError> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .change_context(ProjectError::CloneError)
+        .attach_printable("Unable to run git")?;
+
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::CloneError).attach_printable(format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{platform::local_platform, utils::init_default_project};
+
+    use super::*;
+
+    fn commit_all(dir: &Path) {
+        for args in [
+            vec!["init", "-q"],
+            vec!["add", "--all"],
+            vec![
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-q",
+                "-m",
+                "init",
+            ],
+        ] {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+    }
+
+    #[test]
+    fn test_clone_project() {
+        let remote = tempdir().unwrap();
+        init_default_project(remote.path(), Some(false)).unwrap();
+        commit_all(remote.path());
+
+        let local = tempdir().unwrap();
+        let work_dir = local.path().join("project");
+        let url = remote.path().to_string_lossy().to_string();
+        let res = clone_project(&work_dir, &url, None, Some(SystemPlatform::Aarch64)).unwrap();
+
+        assert_eq!(res.platform, SystemPlatform::Aarch64);
+        assert_eq!(res.configuration, "nixblitzpi");
+        assert!(res.pending_upgrades.is_empty());
+        assert_eq!(local_platform(&work_dir), Some(SystemPlatform::Aarch64));
+
+        // the platform of this machine must not end up in the repository
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(&work_dir)
+            .args(["status", "--porcelain"])
+            .output()
+            .unwrap();
+        assert!(status.stdout.is_empty());
+
+        // the work dir is not empty anymore
+        assert!(clone_project(&work_dir, &url, None, Some(SystemPlatform::X86_64)).is_err());
+    }
+
+    #[test]
+    fn test_clone_invalid_project() {
+        let remote = tempdir().unwrap();
+        fs::write(remote.path().join("README.md"), "no project").unwrap();
+        commit_all(remote.path());
+
+        let local = tempdir().unwrap();
+        let url = remote.path().to_string_lossy().to_string();
+        let res = clone_project(local.path(), &url, None, Some(SystemPlatform::X86_64));
+        assert!(res.is_err());
+        assert_eq!(fs::read_dir(local.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,352 @@
+//! Copies the project to the installed system.
+//!
+//! At the end of an install the project has to land on the target disk, e.g.
+//! in `/mnt/home/admin/nixblitz`. Installer media and USB disks fail now and
+//! then, so the copy is retried with a growing delay. rsync skips the files
+//! that already arrived, which makes every retry resume the last one. A copy
+//! only counts once the checksums of all JSON and nix files match a manifest
+//! of the source, which is stored next to the copied project.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use error_stack::{Report, Result, ResultExt};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    defaults::config_sync::{ATTEMPTS, CONFIG_DIR, INITIAL_DELAY_SECS, MAX_DELAY_SECS},
+    errors::ProjectError,
+    utils::{create_file, to_hex},
+};
+
+/// The checksums of the copied files, relative to the copied project
+pub const MANIFEST_FILE: &str = ".nixblitz/sync-manifest.json";
+
+/// How often and how patiently to copy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncOptions {
+    /// Number of attempts, at least one is made
+    pub attempts: u32,
+
+    /// Delay before the second attempt, doubled for every further one
+    pub initial_delay: Duration,
+
+    /// Longest delay between two attempts
+    pub max_delay: Duration,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            attempts: ATTEMPTS,
+            initial_delay: Duration::from_secs(INITIAL_DELAY_SECS),
+            max_delay: Duration::from_secs(MAX_DELAY_SECS),
+        }
+    }
+}
+
+impl SyncOptions {
+    /// The delay after the given failed attempt, starting at 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+/// What the copy is doing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncProgress {
+    /// An attempt starts
+    Attempt { attempt: u32, attempts: u32 },
+
+    /// A file was copied, relative to the project
+    Copied(String),
+
+    /// The attempt failed, the next one starts after the delay
+    Retry { reason: String, delay: Duration },
+
+    /// The checksums of the given number of files match
+    Verified(usize),
+}
+
+/// The result of a successful copy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Where the project was copied to
+    pub target: PathBuf,
+
+    /// The attempts it took
+    pub attempts: u32,
+
+    /// The number of verified files
+    pub files: usize,
+}
+
+/// Returns the directory the project is copied to on the system mounted at
+/// `root`, e.g. `/mnt/home/admin/nixblitz` for `/mnt`
+pub fn target_dir(root: &Path) -> PathBuf {
+    root.join(CONFIG_DIR.trim_start_matches('/'))
+}
+
+/// Whether the file of the project must be verified after copying
+fn is_verified(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("json") | Some("nix")
+    ) || path.ends_with("flake.lock")
+}
+
+/// Whether an entry of the project is not copied, i.e. nix build results
+fn is_excluded(relative: &Path) -> bool {
+    let first = relative
+        .components()
+        .next()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    first == "result" || first.starts_with("result-") || first == ".git"
+}
+
+fn hash_file(path: &Path) -> Option<String> {
+    fs::read(path)
+        .ok()
+        .map(|data| to_hex(&Sha256::digest(data)))
+}
+
+/// Returns the checksums of the JSON and nix files of the project, keyed by
+/// their path relative to the work dir. The git history and build results
+/// are left out.
+///
+/// # Errors
+/// [ProjectError::ConfigSyncError] if the work dir can't be read
+pub fn manifest(work_dir: &Path) -> Result<BTreeMap<String, String>, ProjectError> {
+    let mut res = BTreeMap::new();
+    let mut dirs = vec![work_dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = fs::read_dir(&dir)
+            .change_context(ProjectError::ConfigSyncError)
+            .attach_printable_lazy(|| format!("Unable to read {}", dir.display()))?;
+        for entry in entries {
+            let entry = entry.change_context(ProjectError::ConfigSyncError)?;
+            let path = entry.path();
+            let Ok(relative) = path.strip_prefix(work_dir) else {
+                continue;
+            };
+            if is_excluded(relative) || relative == Path::new(MANIFEST_FILE) {
+                continue;
+            }
+
+            let file_type = entry
+                .file_type()
+                .change_context(ProjectError::ConfigSyncError)?;
+            if file_type.is_dir() {
+                dirs.push(path);
+            } else if file_type.is_file() && is_verified(&path) {
+                let hash = hash_file(&path).ok_or_else(|| {
+                    Report::new(ProjectError::ConfigSyncError)
+                        .attach_printable(format!("Unable to read {}", path.display()))
+                })?;
+                res.insert(relative.to_string_lossy().to_string(), hash);
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+/// Compares the files in the target with the manifest of the source
+///
+/// # Returns
+/// The files that are missing or differ, empty if all arrived
+pub fn verify(manifest: &BTreeMap<String, String>, target: &Path) -> Vec<String> {
+    manifest
+        .iter()
+        .filter(|(path, hash)| hash_file(&target.join(path)).as_ref() != Some(*hash))
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+/// Runs rsync once, reporting every copied file
+///
+/// # Arguments
+/// * `checksum` - compare the files by checksum instead of size and time,
+///   used on retries to catch files that were written partially
+fn rsync<F>(
+    work_dir: &Path,
+    target: &Path,
+    checksum: bool,
+    on_progress: &mut F,
+) -> std::result::Result<(), String>
+where
+    F: FnMut(SyncProgress),
+{
+    let mut cmd = Command::new("rsync");
+    cmd.args(["-a", "--partial", "--info=name1"])
+        .args(["--exclude=/result", "--exclude=/result-*"]);
+    if checksum {
+        cmd.arg("--checksum");
+    }
+    let mut child = cmd
+        .arg(format!("{}/", work_dir.display()))
+        .arg(format!("{}/", target.display()))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Unable to run rsync, is it installed? {}", e))?;
+
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(|l| l.ok()) {
+            let line = line.trim();
+            if !line.is_empty() && !line.ends_with('/') {
+                on_progress(SyncProgress::Copied(line.to_string()));
+            }
+        }
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("rsync didn't finish: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "rsync failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Copies the project to the target directory and checks that all JSON and
+/// nix files arrived. Failed attempts are retried as configured.
+///
+/// # Errors
+/// [ProjectError::ConfigSyncError] if the project can't be read or the last
+/// attempt failed
+pub fn sync<F>(
+    work_dir: &Path,
+    target: &Path,
+    options: SyncOptions,
+    mut on_progress: F,
+) -> Result<SyncReport, ProjectError>
+where
+    F: FnMut(SyncProgress),
+{
+    let manifest = manifest(work_dir)?;
+    if manifest.is_empty() {
+        return Err(Report::new(ProjectError::ConfigSyncError)
+            .attach_printable(format!("No project found in {}", work_dir.display())));
+    }
+    fs::create_dir_all(target)
+        .change_context(ProjectError::ConfigSyncError)
+        .attach_printable_lazy(|| format!("Unable to create {}", target.display()))?;
+
+    let attempts = options.attempts.max(1);
+    let mut reason = String::new();
+    for attempt in 1..=attempts {
+        on_progress(SyncProgress::Attempt { attempt, attempts });
+        let res = rsync(work_dir, target, attempt > 1, &mut on_progress).and_then(|_| {
+            let missing = verify(&manifest, target);
+            if missing.is_empty() {
+                Ok(())
+            } else {
+                Err(format!("Missing or different: {}", missing.join(", ")))
+            }
+        });
+
+        match res {
+            Ok(()) => {
+                let json = serde_json::to_string_pretty(&manifest)
+                    .change_context(ProjectError::ConfigSyncError)?;
+                create_file(&target.join(MANIFEST_FILE), json.as_bytes(), Some(true))?;
+                on_progress(SyncProgress::Verified(manifest.len()));
+
+                return Ok(SyncReport {
+                    target: target.to_path_buf(),
+                    attempts: attempt,
+                    files: manifest.len(),
+                });
+            }
+            Err(e) => reason = e,
+        }
+
+        if attempt < attempts {
+            let delay = options.delay(attempt);
+            on_progress(SyncProgress::Retry {
+                reason: reason.clone(),
+                delay,
+            });
+            thread::sleep(delay);
+        }
+    }
+
+    Err(Report::new(ProjectError::ConfigSyncError)
+        .attach_printable(reason)
+        .attach_printable(format!("Gave up after {} attempts", attempts)))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn write(dir: &Path, path: &str, contents: &str) {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_delay() {
+        let options = SyncOptions::default();
+        assert_eq!(options.delay(1), Duration::from_secs(INITIAL_DELAY_SECS));
+        assert_eq!(
+            options.delay(2),
+            Duration::from_secs(INITIAL_DELAY_SECS * 2)
+        );
+        assert_eq!(options.delay(40), Duration::from_secs(MAX_DELAY_SECS));
+    }
+
+    #[test]
+    fn test_target_dir() {
+        assert_eq!(
+            target_dir(Path::new("/mnt")),
+            PathBuf::from("/mnt/home/admin/nixblitz")
+        );
+    }
+
+    #[test]
+    fn test_manifest_and_verify() {
+        let source = tempdir().unwrap();
+        write(source.path(), "flake.nix", "{}");
+        write(source.path(), "flake.lock", "{}");
+        write(source.path(), "src/apps/lnd.json", "{\"enable\":true}");
+        write(source.path(), "README.md", "not verified");
+        write(source.path(), ".git/config.json", "excluded");
+        write(source.path(), "result-iso/iso.nix", "excluded");
+
+        let manifest = manifest(source.path()).unwrap();
+        let files: Vec<&str> = manifest.keys().map(|k| k.as_str()).collect();
+        assert_eq!(files, vec!["flake.lock", "flake.nix", "src/apps/lnd.json"]);
+
+        let target = tempdir().unwrap();
+        assert_eq!(verify(&manifest, target.path()).len(), 3);
+
+        write(target.path(), "flake.nix", "{}");
+        write(target.path(), "flake.lock", "{}");
+        write(target.path(), "src/apps/lnd.json", "{\"enable\":fal");
+        assert_eq!(verify(&manifest, target.path()), vec!["src/apps/lnd.json"]);
+
+        write(target.path(), "src/apps/lnd.json", "{\"enable\":true}");
+        assert!(verify(&manifest, target.path()).is_empty());
+    }
+}
@@ -0,0 +1,382 @@
+//! Central registry for the default values used by the app configs.
+//!
+//! All [Default] implementations of the services pull their ports, addresses
+//! and paths from here. Changing a default must only happen in this file.
+//! Defaults that differ between platforms are looked up with [get].
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    app_option_data::option_data::{OptionId, ToOptionId},
+    bitcoind::BitcoindConfigOption,
+    monitoring::MonitoringConfigOption,
+    nix_base_config::NixBaseConfigOption,
+    platform::SystemPlatform,
+};
+
+/// The address services bind to unless configured otherwise
+pub const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// The name of the system user that is created on a fresh install
+pub const SYSTEM_USER: &str = "admin";
+
+/// Defaults of the options that differ on a platform. A Raspberry Pi has
+/// four cores, little memory and slow storage.
+static PLATFORM_OVERRIDES: Lazy<HashMap<OptionId, Vec<(SystemPlatform, usize)>>> =
+    Lazy::new(|| {
+        HashMap::from([
+            (
+                BitcoindConfigOption::RpcThreads.to_option_id(),
+                vec![(SystemPlatform::Aarch64, 2)],
+            ),
+            (
+                BitcoindConfigOption::VerifyChainBlocks.to_option_id(),
+                vec![(SystemPlatform::Aarch64, 144)],
+            ),
+            (
+                MonitoringConfigOption::RetentionDays.to_option_id(),
+                vec![(SystemPlatform::Aarch64, 7)],
+            ),
+            (
+                NixBaseConfigOption::ZramPercent.to_option_id(),
+                vec![(SystemPlatform::Aarch64, 25)],
+            ),
+        ])
+    });
+
+/// Returns the default of an option on a platform
+///
+/// # Arguments
+/// * `id` - the option, which includes the app
+/// * `default` - the default on every platform without an override
+pub fn get(platform: &SystemPlatform, id: &OptionId, default: usize) -> usize {
+    PLATFORM_OVERRIDES
+        .get(id)
+        .and_then(|overrides| overrides.iter().find(|(p, _)| p == platform))
+        .map_or(default, |(_, value)| *value)
+}
+
+pub mod apply_history {
+    /// Older records are dropped
+    pub const MAX_RECORDS: usize = 100;
+
+    /// The number of previous applies the last one is compared with, at
+    /// least MIN_SAMPLES are needed for a trend
+    pub const BASELINE: usize = 10;
+    pub const MIN_SAMPLES: usize = 3;
+
+    /// An apply taking this many times the usual duration is slow
+    pub const SLOW_FACTOR: f64 = 2.0;
+}
+
+pub mod initial_sync {
+    /// The sync must have run this long before its end is estimated
+    pub const MIN_ELAPSED_SECS: u64 = 600;
+
+    /// A smaller database cache of Bitcoin Core slows the first sync down
+    pub const RECOMMENDED_DB_CACHE_MIB: u16 = 2000;
+}
+
+pub mod config_sync {
+    /// Where the project is copied to, relative to the root of the installed
+    /// system
+    pub const CONFIG_DIR: &str = super::system_backup::CONFIG_DIR;
+
+    pub const ATTEMPTS: u32 = 5;
+    pub const INITIAL_DELAY_SECS: u64 = 2;
+    pub const MAX_DELAY_SECS: u64 = 60;
+}
+
+pub mod disk_bench {
+    /// Random 4k IOPS below which the initial block download takes very long
+    pub const MIN_READ_IOPS: u64 = 1000;
+    pub const MIN_WRITE_IOPS: u64 = 500;
+
+    pub const TEST_FILE_SIZE: &str = "256M";
+    pub const RUNTIME_SECS: u32 = 20;
+}
+
+pub mod grafana {
+    pub const PORT: u16 = 3001;
+    pub const NGINX_LOCATION: &str = "/grafana";
+}
+
+pub mod monitoring {
+    pub const PORT: u16 = 9090;
+    pub const RETENTION_DAYS: usize = 15;
+    pub const NODE_EXPORTER_PORT: u16 = 9100;
+    pub const BITCOIND_EXPORTER_PORT: u16 = 9332;
+    pub const LND_EXPORTER_PORT: u16 = 9092;
+}
+
+pub mod nix_base {
+    pub const TIME_ZONE: &str = "America/New_York";
+    pub const LOCALE: &str = "en_US.utf8";
+    pub const SNAPSHOT_KEEP: usize = 5;
+    pub const SNAPSHOT_DATA_DIR: &str = "/var/lib";
+    pub const BACKUP_KEEP_DAILY: usize = 7;
+    pub const BACKUP_KEEP_WEEKLY: usize = 4;
+    pub const SSH_PORT: u16 = 22;
+    pub const SSH_MAX_AUTH_TRIES: usize = 3;
+
+    /// Share of the memory used for zram swap, 0 disables it
+    pub const ZRAM_PERCENT: u8 = 0;
+    pub const ZRAM_PERCENT_STEP: u8 = 5;
+
+    /// How full the disk of a data dir may get before the status alerts
+    pub const DISK_ALERT_PERCENT: u8 = 90;
+    pub const DISK_ALERT_PERCENT_STEP: u8 = 5;
+
+    /// The private IPv4 ranges and IPv6 unique local addresses
+    pub const TRUSTED_NETWORKS: [&str; 4] =
+        ["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16", "fd00::/8"];
+
+    /// The servers of the DNS resolvers, with the names on their certificates
+    pub const QUAD9_SERVERS: [&str; 4] = [
+        "9.9.9.9#dns.quad9.net",
+        "149.112.112.112#dns.quad9.net",
+        "2620:fe::fe#dns.quad9.net",
+        "2620:fe::9#dns.quad9.net",
+    ];
+    pub const CLOUDFLARE_SERVERS: [&str; 4] = [
+        "1.1.1.1#cloudflare-dns.com",
+        "1.0.0.1#cloudflare-dns.com",
+        "2606:4700:4700::1111#cloudflare-dns.com",
+        "2606:4700:4700::1001#cloudflare-dns.com",
+    ];
+}
+
+pub mod nix_bitcoin {
+    /// Where nix-bitcoin stores the generated secrets
+    pub const SECRETS_DIR: &str = "/etc/nix-bitcoin-secrets";
+}
+
+pub mod bitcoind {
+    pub const PORT: u16 = 8333;
+    pub const RPC_PORT: u16 = 8332;
+    pub const ZMQ_PUB_RAW_BLOCK_PORT: u16 = 28332;
+    pub const ZMQ_PUB_RAW_TX_PORT: u16 = 28333;
+    pub const DATA_DIR: &str = "/var/lib/bitcoind";
+
+    /// Prune target in MiB
+    pub const PRUNE_SIZE: usize = 2048;
+
+    /// Script verification threads, 0 means one per core of the target machine
+    pub const PAR: usize = 0;
+
+    /// bitcoind supports at most 15 script verification threads
+    pub const MAX_PAR: usize = 15;
+
+    pub const RPC_THREADS: usize = 4;
+
+    /// Upload target in MiB per day, roughly 1 TB
+    pub const MAX_UPLOAD_TARGET: usize = 1_000_000;
+
+    /// Hours of the day the peer connections are paused between
+    pub const QUIET_HOURS_START: usize = 18;
+    pub const QUIET_HOURS_END: usize = 23;
+
+    /// Blocks verified by the scheduled chain check, roughly two days
+    pub const VERIFY_CHAIN_BLOCKS: usize = 288;
+
+    /// Roughly a year of blocks, more takes hours on a Raspberry Pi
+    pub const MAX_VERIFY_CHAIN_BLOCKS: usize = 52_560;
+
+    /// Space in GB an archival node needs for the block chain, with room
+    /// to grow for a year or two
+    pub const ARCHIVAL_SIZE_GB: u64 = 800;
+}
+
+pub mod lnd {
+    pub const PORT: u16 = 9735;
+    pub const RPC_PORT: u16 = 10009;
+    pub const REST_PORT: u16 = 8080;
+    pub const DATA_DIR: &str = "/var/lib/lnd";
+    pub const NETWORK_DIR: &str = "${cfg.lnd.dataDir}/chain/bitcoin/${cfg.bitcoind.network}";
+    pub const BASE_FEE_MSAT: usize = 1000;
+    pub const FEE_RATE_PPM: usize = 1;
+    pub const MIN_HTLC_MSAT: usize = 1;
+    pub const CLTV_DELTA: usize = 80;
+    pub const WATCHTOWER_PORT: u16 = 9911;
+    pub const NODE_INFO_LOCATION: &str = "/node";
+    pub const NODE_INFO_DIR: &str = "/var/lib/lnd-node-info";
+}
+
+pub mod cln {
+    pub const PORT: u16 = 9735;
+    pub const DATA_DIR: &str = "/var/lib/clightning";
+    pub const BASE_FEE_MSAT: usize = 1000;
+    pub const FEE_RATE_PPM: usize = 10;
+    pub const MIN_HTLC_MSAT: usize = 0;
+    pub const CLTV_DELTA: usize = 34;
+}
+
+pub mod blitz_api {
+    pub const ENV_FILE: &str = "/etc/blitz_api/env";
+    pub const PASSWORD_FILE: &str = "/etc/blitz_api/password";
+    pub const ROOT_PATH: &str = "/api";
+    pub const NGINX_LOCATION: &str = "/";
+}
+
+pub mod blitz_webui {
+    /// The web UI module has no location option, it is always served from the root
+    pub const NGINX_LOCATION: &str = "/";
+}
+
+pub mod electrs {
+    pub const PORT: u16 = 50001;
+    pub const MONITORING_PORT: u16 = 4224;
+    pub const DB_DIR: &str = "/var/lib/electrs";
+    pub const INDEX_BATCH_SIZE: usize = 10;
+}
+
+pub mod eps {
+    pub const PORT: u16 = 50002;
+    pub const DATA_DIR: &str = "/var/lib/electrum-personal-server";
+}
+
+pub mod fulcrum {
+    pub const PORT: u16 = 50001;
+    pub const SSL_PORT: u16 = 50002;
+    pub const DB_DIR: &str = "/var/lib/fulcrum";
+}
+
+pub mod mempool {
+    pub const PORT: u16 = 8999;
+    pub const FRONTEND_PORT: u16 = 60845;
+    pub const DB_NAME: &str = "mempool";
+    pub const DB_USER: &str = "mempool";
+
+    /// Where MariaDB keeps the database of the mempool backend
+    pub const DB_DIR: &str = "/var/lib/mysql";
+    pub const NGINX_LOCATION: &str = "/mempool";
+}
+
+pub mod i2p {
+    pub const SAM_PORT: u16 = 7656;
+
+    /// Bandwidth in KB/s
+    pub const BANDWIDTH: usize = 256;
+    pub const MIN_BANDWIDTH: usize = 32;
+    pub const MAX_BANDWIDTH: usize = 100000;
+}
+
+pub mod lit {
+    pub const PORT: u16 = 8443;
+}
+
+pub mod channel_backup {
+    pub const LOCAL_DIR: &str = "/mnt/backup/lnd";
+    pub const SSH_KEY_FILE: &str = "/var/lib/lnd/channel-backup-ssh-key";
+    pub const NEXTCLOUD_PASSWORD_FILE: &str = "/var/lib/lnd/nextcloud-password";
+}
+
+pub mod charge_lnd {
+    /// systemd calendar event
+    pub const INTERVAL: &str = "hourly";
+
+    /// Keeps the fees of channels no other policy matches
+    pub const DEFAULT_POLICY: &str = "default: strategy=static, base_fee_msat=1000, fee_ppm=100";
+}
+
+pub mod bos {
+    pub const TELEGRAM_TOKEN_FILE: &str = "/var/lib/lnd/bos-telegram-token";
+}
+
+pub mod elements {
+    /// RPC port of the liquidv1 chain
+    pub const RPC_PORT: u16 = 7041;
+    pub const DATA_DIR: &str = "/var/lib/elements";
+}
+
+pub mod mining {
+    /// The Stratum port the miners connect to
+    pub const STRATUM_PORT: u16 = 23334;
+
+    /// The dashboard and the block notifications, only on localhost
+    pub const API_PORT: u16 = 7152;
+}
+
+pub mod phoenixd {
+    pub const HTTP_PORT: u16 = 9740;
+    pub const DATA_DIR: &str = "/var/lib/phoenixd";
+}
+
+pub mod tailscale {
+    pub const PORT: u16 = 41641;
+    pub const AUTH_KEY_FILE: &str = "/var/lib/tailscale/auth-key";
+}
+
+pub mod thunderhub {
+    pub const PORT: u16 = 3000;
+    pub const NGINX_LOCATION: &str = "/thunderhub";
+}
+
+pub mod reverse_proxy {
+    /// Name of the TLS virtual host if no domain is set
+    pub const TLS_HOST: &str = "nixblitz";
+    pub const SELF_SIGNED_DIR: &str = "/var/lib/nixblitz-tls";
+
+    /// Days before a custom certificate expires the status warns about it
+    pub const CERT_WARN_DAYS: u64 = 21;
+}
+
+pub mod system_backup {
+    pub const PASSWORD_FILE: &str = "/var/lib/secrets/system-backup-password";
+    pub const SCHEDULE: &str = "daily";
+
+    /// Where the project files live on the node
+    pub const CONFIG_DIR: &str = "/home/admin/nixblitz";
+
+    pub const KEEP_DAILY: usize = 7;
+    pub const KEEP_WEEKLY: usize = 4;
+    pub const KEEP_MONTHLY: usize = 6;
+}
+
+pub mod tor {
+    pub const STATE_DIR: &str = "/var/lib/tor";
+
+    /// The port of the SOCKS proxy of the Tor client
+    pub const SOCKS_PORT: u16 = 9050;
+
+    /// The port nginx serves the Blitz API and the Web UI on
+    pub const NGINX_PORT: u16 = 80;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let threads = BitcoindConfigOption::RpcThreads.to_option_id();
+        assert_eq!(
+            get(&SystemPlatform::Aarch64, &threads, bitcoind::RPC_THREADS),
+            2
+        );
+        assert_eq!(
+            get(&SystemPlatform::X86_64, &threads, bitcoind::RPC_THREADS),
+            bitcoind::RPC_THREADS
+        );
+        assert_eq!(
+            get(
+                &SystemPlatform::Unsupported("riscv64-linux".into()),
+                &threads,
+                bitcoind::RPC_THREADS
+            ),
+            bitcoind::RPC_THREADS
+        );
+
+        // options without overrides keep their default everywhere
+        let par = BitcoindConfigOption::Par.to_option_id();
+        assert_eq!(
+            get(&SystemPlatform::Aarch64, &par, bitcoind::PAR),
+            bitcoind::PAR
+        );
+    }
+}
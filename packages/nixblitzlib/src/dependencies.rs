@@ -0,0 +1,104 @@
+use core::fmt;
+
+use crate::{app_option_data::option_data::OptionId, apps::SupportedApps, registry};
+
+/// The name of the option every app uses to enable or disable itself
+pub const ENABLE_OPTION: &str = "enable";
+
+/// Returned when an app is about to be disabled while other enabled
+/// apps still depend on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyConflict {
+    /// The app that was about to be disabled
+    pub app: SupportedApps,
+
+    /// The enabled apps requiring [DependencyConflict::app]
+    pub required_by: Vec<SupportedApps>,
+}
+
+impl fmt::Display for DependencyConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let required_by = self
+            .required_by
+            .iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{} is required by {}", self.app, required_by)
+    }
+}
+
+/// Returns the apps that must be enabled for the given app to work
+pub fn requirements(app: SupportedApps) -> &'static [SupportedApps] {
+    registry::registration(app).requires
+}
+
+/// Returns all direct and indirect requirements of the given app.
+///
+/// The list is ordered so that every app comes after its own requirements,
+/// e.g. for the Web UI: `[BitcoinCore, BlitzAPI]`.
+pub fn all_requirements(app: SupportedApps) -> Vec<SupportedApps> {
+    let mut res = vec![];
+    for req in requirements(app) {
+        for r in all_requirements(*req) {
+            if !res.contains(&r) {
+                res.push(r);
+            }
+        }
+
+        if !res.contains(req) {
+            res.push(*req);
+        }
+    }
+
+    res
+}
+
+/// Returns all apps that directly or indirectly require the given app
+pub fn dependents(app: SupportedApps) -> Vec<SupportedApps> {
+    registry::APPS
+        .iter()
+        .map(|r| r.app)
+        .filter(|a| all_requirements(*a).contains(&app))
+        .collect()
+}
+
+/// Returns the id of the option that enables the given app, if the app can be toggled
+pub fn enable_option_id(app: SupportedApps) -> Option<OptionId> {
+    if app == SupportedApps::NixOS {
+        return None;
+    }
+
+    Some(OptionId::new(app, ENABLE_OPTION.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_requirements() {
+        assert!(all_requirements(SupportedApps::BitcoinCore).is_empty());
+        assert!(all_requirements(SupportedApps::Phoenixd).is_empty());
+        assert_eq!(
+            all_requirements(SupportedApps::LND),
+            vec![SupportedApps::BitcoinCore]
+        );
+        assert_eq!(
+            all_requirements(SupportedApps::WebUI),
+            vec![SupportedApps::BitcoinCore, SupportedApps::BlitzAPI]
+        );
+    }
+
+    #[test]
+    fn test_dependents() {
+        let deps = dependents(SupportedApps::BlitzAPI);
+        assert_eq!(deps, vec![SupportedApps::WebUI]);
+
+        let deps = dependents(SupportedApps::BitcoinCore);
+        assert!(deps.contains(&SupportedApps::LND));
+        assert!(deps.contains(&SupportedApps::WebUI));
+        assert!(!deps.contains(&SupportedApps::BitcoinCore));
+        assert!(!deps.contains(&SupportedApps::Phoenixd));
+    }
+}
@@ -0,0 +1,218 @@
+//! A quick benchmark of the disk the node is installed to.
+//!
+//! The initial block download is bound by random I/O. Cheap SD cards and USB
+//! adapters often manage only a few hundred IOPS, which stretches it to weeks.
+//! The benchmark runs `fio` with random 4k reads and writes and the result is
+//! stored in the project, so `nixblitz doctor` can point to it later.
+
+use std::{
+    fs,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use error_stack::{Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    defaults::disk_bench::{MIN_READ_IOPS, MIN_WRITE_IOPS, RUNTIME_SECS, TEST_FILE_SIZE},
+    errors::ProjectError,
+    utils::{create_file, load_json_file},
+};
+
+pub const JSON_FILE_NAME: &str = "src/disk_benchmark.json";
+
+/// Name of the temporary file fio writes to
+const TEST_FILE_NAME: &str = "nixblitz-disk-benchmark";
+
+/// The result of a disk benchmark
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskBenchmark {
+    /// The directory on the benchmarked disk
+    pub path: String,
+
+    /// Random 4k reads per second
+    pub read_iops: u64,
+
+    /// Random 4k writes per second
+    pub write_iops: u64,
+
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+}
+
+impl DiskBenchmark {
+    /// Parses the JSON output of fio
+    ///
+    /// # Errors
+    /// [ProjectError::DiskBenchmarkError] if the output lacks the IOPS of the job
+    pub fn from_fio_output(path: &str, output: &str) -> Result<Self, ProjectError> {
+        let json: Value = serde_json::from_str(output)
+            .change_context(ProjectError::DiskBenchmarkError)
+            .attach_printable("Unable to parse the output of fio")?;
+
+        let iops = |kind: &str| {
+            json["jobs"][0][kind]["iops"]
+                .as_f64()
+                .map(|i| i.round() as u64)
+                .ok_or_else(|| {
+                    Report::new(ProjectError::DiskBenchmarkError)
+                        .attach_printable(format!("fio didn't report the {} IOPS", kind))
+                })
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Ok(Self {
+            path: path.to_string(),
+            read_iops: iops("read")?,
+            write_iops: iops("write")?,
+            timestamp,
+        })
+    }
+
+    /// Describes why the disk is too slow for the initial block download
+    ///
+    /// # Returns
+    /// An empty list if the disk is fast enough
+    pub fn warnings(&self) -> Vec<String> {
+        let mut res = vec![];
+        if self.read_iops < MIN_READ_IOPS {
+            res.push(format!(
+                "The disk at {} manages only {} random reads per second, at least {} are recommended",
+                self.path, self.read_iops, MIN_READ_IOPS
+            ));
+        }
+        if self.write_iops < MIN_WRITE_IOPS {
+            res.push(format!(
+                "The disk at {} manages only {} random writes per second, at least {} are recommended",
+                self.path, self.write_iops, MIN_WRITE_IOPS
+            ));
+        }
+
+        res
+    }
+
+    /// Stores the result in the project, replacing an earlier one
+    pub fn save(&self, work_dir: &Path) -> Result<(), ProjectError> {
+        let json =
+            serde_json::to_string_pretty(self).change_context(ProjectError::DiskBenchmarkError)?;
+        create_file(&work_dir.join(JSON_FILE_NAME), json.as_bytes(), Some(true))
+    }
+
+    /// Loads the result stored in the project
+    ///
+    /// # Returns
+    /// None if the disk wasn't benchmarked yet
+    pub fn load(work_dir: &Path) -> Result<Option<Self>, ProjectError> {
+        let path = work_dir.join(JSON_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let json = load_json_file(&path)?;
+        let res = serde_json::from_str(&json)
+            .change_context(ProjectError::DiskBenchmarkError)
+            .attach_printable_lazy(|| format!("Unable to parse {}", JSON_FILE_NAME))?;
+
+        Ok(Some(res))
+    }
+}
+
+/// Benchmarks the disk the given directory is on
+///
+/// Writes a temporary file of a few hundred MB into the directory and removes
+/// it afterwards. Takes about half a minute.
+///
+/// # Errors
+/// [ProjectError::DiskBenchmarkError] if fio isn't installed or fails
+pub fn run(dir: &Path) -> Result<DiskBenchmark, ProjectError> {
+    let file = dir.join(TEST_FILE_NAME);
+    let output = Command::new("fio")
+        .args([
+            "--name=nixblitz",
+            "--rw=randrw",
+            "--bs=4k",
+            "--direct=1",
+            "--iodepth=32",
+            "--ioengine=libaio",
+            "--time_based",
+            "--output-format=json",
+        ])
+        .arg(format!("--filename={}", file.display()))
+        .arg(format!("--size={}", TEST_FILE_SIZE))
+        .arg(format!("--runtime={}", RUNTIME_SECS))
+        .output();
+    let _ = fs::remove_file(&file);
+
+    let output = output
+        .change_context(ProjectError::DiskBenchmarkError)
+        .attach_printable("Unable to run fio, is it installed?")?;
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::DiskBenchmarkError).attach_printable(format!(
+                "fio failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )),
+        );
+    }
+
+    DiskBenchmark::from_fio_output(
+        &dir.display().to_string(),
+        &String::from_utf8_lossy(&output.stdout),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const FIO_OUTPUT: &str = r#"{
+        "fio version": "fio-3.36",
+        "jobs": [
+            {
+                "jobname": "nixblitz",
+                "read": { "io_bytes": 1048576, "iops": 412.734 },
+                "write": { "io_bytes": 1048576, "iops": 2210.5 }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_from_fio_output() {
+        let bench = DiskBenchmark::from_fio_output("/mnt", FIO_OUTPUT).unwrap();
+        assert_eq!(bench.read_iops, 413);
+        assert_eq!(bench.write_iops, 2211);
+
+        let warnings = bench.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("413 random reads"));
+
+        assert!(DiskBenchmark::from_fio_output("/mnt", r#"{"jobs": []}"#).is_err());
+        assert!(DiskBenchmark::from_fio_output("/mnt", "fio: command not found").is_err());
+    }
+
+    #[test]
+    fn test_save_load() {
+        let dir = tempdir().unwrap();
+        assert_eq!(DiskBenchmark::load(dir.path()).unwrap(), None);
+
+        let bench = DiskBenchmark {
+            path: "/mnt".into(),
+            read_iops: 5000,
+            write_iops: 3000,
+            timestamp: 1,
+        };
+        assert!(bench.warnings().is_empty());
+
+        bench.save(dir.path()).unwrap();
+        assert_eq!(DiskBenchmark::load(dir.path()).unwrap(), Some(bench));
+    }
+}
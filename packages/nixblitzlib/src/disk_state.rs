@@ -0,0 +1,101 @@
+//! Detects changes to the project files that were not made by nixblitz.
+//!
+//! The [Project](crate::project::Project) remembers a fingerprint of every
+//! JSON and nix file of the registered apps after loading and after each of
+//! its own writes. If the files on disk differ from that, someone edited them
+//! by hand and the project must be reloaded before it writes them again.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::registry::APPS;
+
+/// Fingerprints of the project files at a point in time
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DiskState {
+    /// Hash of the contents of each file, keyed by the path relative to
+    /// the work dir. Missing files are not part of the map.
+    fingerprints: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl DiskState {
+    /// Reads the current state of the project files in the given work dir
+    pub fn capture(work_dir: &Path) -> Self {
+        let fingerprints = project_files()
+            .into_iter()
+            .filter_map(|file| {
+                let contents = fs::read(work_dir.join(&file)).ok()?;
+                Some((file, Sha256::digest(contents).to_vec()))
+            })
+            .collect();
+
+        Self { fingerprints }
+    }
+
+    /// Returns the files that were changed, created or deleted since this
+    /// state was captured, relative to the work dir
+    pub fn changed_files(&self, work_dir: &Path) -> Vec<PathBuf> {
+        let current = Self::capture(work_dir);
+
+        project_files()
+            .into_iter()
+            .filter(|f| self.fingerprints.get(f) != current.fingerprints.get(f))
+            .collect()
+    }
+}
+
+/// The JSON files and rendered nix files of all registered apps
+fn project_files() -> Vec<PathBuf> {
+    APPS.iter()
+        .flat_map(|reg| {
+            [
+                PathBuf::from(reg.json_file_name),
+                PathBuf::from(reg.template_file_name.replace(".templ", "")),
+            ]
+        })
+        .collect()
+}
+
+/// Whether the given path is one of the project files nixblitz manages
+pub fn is_project_file(work_dir: &Path, path: &Path) -> bool {
+    project_files().iter().any(|f| work_dir.join(f) == path)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{lnd, utils::init_default_project};
+
+    use super::*;
+
+    #[test]
+    fn test_changed_files() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+        init_default_project(work_dir, Some(false)).unwrap();
+
+        let state = DiskState::capture(work_dir);
+        assert!(state.changed_files(work_dir).is_empty());
+
+        let json = work_dir.join(lnd::JSON_FILE_NAME);
+        fs::write(&json, "{}").unwrap();
+        assert_eq!(
+            state.changed_files(work_dir),
+            vec![PathBuf::from(lnd::JSON_FILE_NAME)]
+        );
+        assert!(is_project_file(work_dir, &json));
+        assert!(!is_project_file(work_dir, &work_dir.join("README.md")));
+
+        fs::remove_file(&json).unwrap();
+        assert_eq!(state.changed_files(work_dir).len(), 1);
+        assert!(DiskState::capture(work_dir)
+            .changed_files(work_dir)
+            .is_empty());
+    }
+}
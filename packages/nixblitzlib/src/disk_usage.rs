@@ -0,0 +1,191 @@
+//! How much space the data of the apps takes.
+//!
+//! The data dirs come from the `data_dir` and `db_dir` options of the
+//! enabled apps, plus the database of the mempool backend. `du` measures
+//! the size of each dir and `df` how full the disk holding it is. A disk
+//! fuller than [disk_alert_percent](crate::nix_base_config::NixBaseConfig::disk_alert_percent)
+//! makes the node unhealthy, see [status](crate::status). Must run on the
+//! node.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use error_stack::{Result, ResultExt};
+use serde::Serialize;
+
+use crate::{
+    app_option_data::option_data::{GetOptionId, OptionData},
+    apps::SupportedApps,
+    defaults,
+    errors::ProjectError,
+    project::Project,
+    validation::{normalize_path, DATA_DIR_OPTIONS},
+};
+
+/// Data dirs of apps that have no option for them
+const FIXED_DATA_DIRS: [(SupportedApps, &str); 1] =
+    [(SupportedApps::Mempool, defaults::mempool::DB_DIR)];
+
+/// The space used by the data dir of an app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DataDirUsage {
+    pub app: SupportedApps,
+    pub dir: PathBuf,
+
+    /// The size of the dir in bytes, None if it doesn't exist yet
+    pub bytes: Option<u64>,
+
+    /// The size of the disk holding the dir in bytes
+    pub disk_size: Option<u64>,
+
+    /// The bytes used on that disk
+    pub disk_used: Option<u64>,
+
+    /// Whether the disk is fuller than the alert threshold
+    pub alert: bool,
+}
+
+impl DataDirUsage {
+    /// How full the disk is, between 0 and 100
+    pub fn disk_percent(&self) -> Option<u8> {
+        match (self.disk_size, self.disk_used) {
+            (Some(size), Some(used)) if size > 0 => Some((used * 100 / size).min(100) as u8),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the data dirs of the enabled apps, with the variables resolved
+pub fn data_dirs(project: &Project) -> Vec<(SupportedApps, PathBuf)> {
+    let vars = project.variables();
+    let apps = project.enabled_apps();
+
+    let mut res: Vec<(SupportedApps, PathBuf)> = apps
+        .iter()
+        .flat_map(|a| project.get_options_for(*a))
+        .filter_map(|o| match o {
+            OptionData::TextEdit(t) if DATA_DIR_OPTIONS.contains(&t.id().option.as_str()) => {
+                let dir = normalize_path(&vars.substitute(t.value()));
+                (!dir.is_empty()).then(|| (t.id().app, PathBuf::from(dir)))
+            }
+            _ => None,
+        })
+        .collect();
+    res.extend(
+        FIXED_DATA_DIRS
+            .iter()
+            .filter(|(a, _)| apps.contains(a))
+            .map(|(a, d)| (*a, PathBuf::from(d))),
+    );
+
+    res
+}
+
+/// Measures the data dirs of the enabled apps
+///
+/// # Errors
+/// [ProjectError::DiskUsageError] if `du` or `df` can't be run
+pub fn collect(project: &Project) -> Result<Vec<DataDirUsage>, ProjectError> {
+    let threshold = project.disk_alert_percent();
+
+    data_dirs(project)
+        .into_iter()
+        .map(|(app, dir)| measure(app, &dir, threshold))
+        .collect()
+}
+
+/// Measures a single data dir. A dir that doesn't exist yet has no size.
+fn measure(app: SupportedApps, dir: &Path, threshold: u8) -> Result<DataDirUsage, ProjectError> {
+    let mut usage = DataDirUsage {
+        app,
+        dir: dir.to_path_buf(),
+        bytes: None,
+        disk_size: None,
+        disk_used: None,
+        alert: false,
+    };
+    if !dir.exists() {
+        return Ok(usage);
+    }
+
+    usage.bytes = run("du", &["-sb"], dir)?.and_then(|o| parse_du(&o));
+    if let Some((size, used)) =
+        run("df", &["-B1", "--output=size,used"], dir)?.and_then(|o| parse_df(&o))
+    {
+        usage.disk_size = Some(size);
+        usage.disk_used = Some(used);
+    }
+    usage.alert = usage.disk_percent().is_some_and(|p| p >= threshold);
+
+    Ok(usage)
+}
+
+/// Runs the program on the dir, None if it failed, e.g. for lack of
+/// permissions
+fn run(program: &str, args: &[&str], dir: &Path) -> Result<Option<String>, ProjectError> {
+    let output = Command::new(program)
+        .args(args)
+        .arg(dir)
+        .output()
+        .change_context(ProjectError::DiskUsageError)
+        .attach_printable_lazy(|| format!("Unable to run {}", program))?;
+
+    Ok(output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string()))
+}
+
+/// Parses the `<bytes>\t<dir>` line of `du -sb`
+fn parse_du(output: &str) -> Option<u64> {
+    output.split_whitespace().next()?.parse().ok()
+}
+
+/// Parses the output of `df -B1 --output=size,used`, the first line is the
+/// header
+fn parse_df(output: &str) -> Option<(u64, u64)> {
+    let mut values = output.lines().nth(1)?.split_whitespace();
+    let size = values.next()?.parse().ok()?;
+    let used = values.next()?.parse().ok()?;
+
+    Some((size, used))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_du() {
+        assert_eq!(
+            parse_du("712345678901\t/var/lib/bitcoind\n"),
+            Some(712345678901)
+        );
+        assert_eq!(parse_du(""), None);
+    }
+
+    #[test]
+    fn test_parse_df() {
+        let output = "     1B-blocks          Used\n 1000204886016  912345678901\n";
+        assert_eq!(parse_df(output), Some((1000204886016, 912345678901)));
+        assert_eq!(parse_df("1B-blocks Used\n"), None);
+    }
+
+    #[test]
+    fn test_disk_percent() {
+        let mut usage = DataDirUsage {
+            app: SupportedApps::BitcoinCore,
+            dir: PathBuf::from("/var/lib/bitcoind"),
+            bytes: Some(700),
+            disk_size: Some(1000),
+            disk_used: Some(912),
+            alert: false,
+        };
+        assert_eq!(usage.disk_percent(), Some(91));
+
+        usage.disk_size = Some(0);
+        assert_eq!(usage.disk_percent(), None);
+    }
+}
@@ -0,0 +1,182 @@
+//! Offline documentation bundled with the binary.
+//!
+//! Nodes often run without easy web access, so the how-to pages are
+//! compiled in and the option reference is generated from [OPTION_TITLES].
+
+use include_dir::{include_dir, Dir};
+
+use crate::{apps::SupportedApps, strings::OPTION_TITLES};
+
+static DOCS_DIR: Dir = include_dir!("./nixblitzlib/src/docs/");
+
+/// The slug of the generated option reference
+pub const OPTION_REFERENCE_SLUG: &str = "options";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocPage {
+    /// Short name used to look up the page, e.g. "getting-started"
+    pub slug: String,
+
+    /// The first heading of the page
+    pub title: String,
+
+    /// The markdown source
+    pub content: String,
+}
+
+/// A line of a page matching a search query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    pub slug: String,
+    pub title: String,
+
+    /// One based line number within the page
+    pub line: usize,
+    pub text: String,
+}
+
+/// Returns all pages, the bundled ones ordered by file name followed by the option reference
+pub fn pages() -> Vec<DocPage> {
+    let mut files: Vec<_> = DOCS_DIR
+        .files()
+        .filter(|f| f.path().extension().is_some_and(|e| e == "md"))
+        .collect();
+    files.sort_by(|a, b| a.path().cmp(b.path()));
+
+    let mut res: Vec<DocPage> = files
+        .into_iter()
+        .filter_map(|f| {
+            let stem = f.path().file_stem()?.to_str()?;
+            let content = f.contents_utf8()?.to_string();
+            Some(DocPage {
+                slug: slug_from_stem(stem),
+                title: title_from_content(&content).unwrap_or(stem).to_string(),
+                content,
+            })
+        })
+        .collect();
+
+    res.push(DocPage {
+        slug: OPTION_REFERENCE_SLUG.to_string(),
+        title: "Option reference".to_string(),
+        content: option_reference(),
+    });
+
+    res
+}
+
+/// Returns the page with the given slug
+pub fn page(slug: &str) -> Option<DocPage> {
+    pages().into_iter().find(|p| p.slug == slug)
+}
+
+/// Case insensitive search through all pages
+pub fn search(query: &str) -> Vec<SearchHit> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return vec![];
+    }
+
+    let mut res = vec![];
+    for page in pages() {
+        for (i, line) in page.content.lines().enumerate() {
+            if line.to_lowercase().contains(&query) {
+                res.push(SearchHit {
+                    slug: page.slug.clone(),
+                    title: page.title.clone(),
+                    line: i + 1,
+                    text: line.trim().to_string(),
+                });
+            }
+        }
+    }
+
+    res
+}
+
+/// Strips the ordering prefix, "01-getting-started" becomes "getting-started"
+fn slug_from_stem(stem: &str) -> String {
+    match stem.split_once('-') {
+        Some((prefix, rest))
+            if !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            rest.to_string()
+        }
+        _ => stem.to_string(),
+    }
+}
+
+fn title_from_content(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .find_map(|l| l.strip_prefix("# "))
+        .map(|t| t.trim())
+}
+
+/// Generates a markdown page listing the options of every app
+fn option_reference() -> String {
+    let mut res = String::from("# Option reference\n");
+    for id in 0..SupportedApps::as_string_list().len() {
+        let Some(app) = SupportedApps::from_id(id) else {
+            continue;
+        };
+
+        let mut options: Vec<_> = OPTION_TITLES
+            .iter()
+            .filter(|(option, _)| option.app == app)
+            .map(|(option, title)| (option.option.as_str(), *title))
+            .collect();
+        if options.is_empty() {
+            continue;
+        }
+        options.sort();
+
+        res.push_str(&format!("\n## {}\n\n", app));
+        for (option, title) in options {
+            res.push_str(&format!("- `{}`: {}\n", option, title));
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages() {
+        let pages = pages();
+        assert_eq!(pages[0].slug, "getting-started");
+        assert_eq!(pages[0].title, "Getting started");
+        assert_eq!(pages.last().unwrap().slug, OPTION_REFERENCE_SLUG);
+        assert!(page("troubleshooting").is_some());
+        assert!(page("does-not-exist").is_none());
+    }
+
+    #[test]
+    fn test_option_reference_covers_all_apps() {
+        let reference = page(OPTION_REFERENCE_SLUG).unwrap().content;
+        for app in SupportedApps::as_string_list() {
+            assert!(reference.contains(&format!("## {}", app)), "{}", app);
+        }
+        assert!(reference.contains("- `enable`: "));
+    }
+
+    #[test]
+    fn test_search() {
+        let hits = search("PRUNING");
+        assert!(hits.iter().any(|h| h.slug == "apps"));
+        assert!(hits
+            .iter()
+            .all(|h| h.text.to_lowercase().contains("pruning")));
+        assert!(search("  ").is_empty());
+    }
+
+    #[test]
+    fn test_slug_from_stem() {
+        assert_eq!(slug_from_stem("01-getting-started"), "getting-started");
+        assert_eq!(slug_from_stem("faq"), "faq");
+        assert_eq!(slug_from_stem("lnd-fees"), "lnd-fees");
+    }
+}
@@ -0,0 +1,310 @@
+//! Builds the configuration without switching to it.
+//!
+//! `nixos-rebuild dry-build` evaluates the flake of the project and prints
+//! what would be built. Clashing options and broken extra nix config fail
+//! the evaluation, so they are caught before the running system is touched.
+//! The error of nix is mapped back to the app and, where possible, the
+//! option it came from: either the error names the nix option, e.g.
+//! "The option `services.lnd.rpcPort' has conflicting definition values",
+//! or it points to a line of a rendered file, see [explain](crate::explain).
+
+use std::{fs, path::Path, process::Command};
+
+use error_stack::{Report, Result, ResultExt};
+use serde::Serialize;
+
+use crate::{
+    app_option_data::option_data::GetOptionId,
+    apps::SupportedApps,
+    errors::ProjectError,
+    explain::{placeholders, template_uses},
+    extra_nix,
+    registry::{self, AppRegistration},
+    utils::BASE_TEMPLATE,
+};
+
+/// The end of the directory flakes are copied to before they are evaluated,
+/// e.g. `/nix/store/<hash>-source/apps/lnd.nix`. Inputs like nixpkgs end
+/// the same way.
+const FLAKE_SOURCE: &str = "-source/";
+
+/// The directory of the flake in the work dir
+const FLAKE_DIR: &str = "src";
+
+/// Why the configuration can't be built
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EvalIssue {
+    /// The innermost error message of nix
+    pub message: String,
+
+    /// The project file the error points to, relative to the work dir
+    pub file: Option<String>,
+
+    /// The line in [file](Self::file), starting at 1
+    pub line: Option<usize>,
+
+    /// The app the file or the option belongs to
+    pub app: Option<SupportedApps>,
+
+    /// The option of the app that is likely to blame
+    pub option: Option<String>,
+
+    /// The full output of nix
+    pub output: String,
+}
+
+/// Evaluates and builds the given configuration of the project flake
+/// without activating it
+///
+/// # Returns
+/// None if the configuration builds
+///
+/// # Errors
+/// [ProjectError::DryBuildError] if nixos-rebuild can't be run
+pub fn dry_build(work_dir: &Path, configuration: &str) -> Result<Option<EvalIssue>, ProjectError> {
+    let flake = format!("{}#{}", work_dir.join(FLAKE_DIR).display(), configuration);
+    let output = Command::new("nixos-rebuild")
+        .args(["dry-build", "--flake", &flake])
+        .output()
+        .change_context(ProjectError::DryBuildError)
+        .attach_printable("Unable to run nixos-rebuild, is it installed?")?;
+    if output.status.success() {
+        return Ok(None);
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.contains("error:") {
+        return Err(
+            Report::new(ProjectError::DryBuildError).attach_printable(format!(
+                "nixos-rebuild dry-build exited with {}: {}",
+                output.status,
+                stderr.trim()
+            )),
+        );
+    }
+
+    Ok(Some(parse_eval_error(work_dir, &stderr)))
+}
+
+/// Finds the innermost error in the output of nix and what it points to
+fn parse_eval_error(work_dir: &Path, output: &str) -> EvalIssue {
+    let lines: Vec<&str> = output.lines().map(|l| l.trim()).collect();
+    let last_error = lines
+        .iter()
+        .rposition(|l| l.starts_with("error:"))
+        .unwrap_or_default();
+    let mut message = lines
+        .get(last_error)
+        .and_then(|l| l.strip_prefix("error:"))
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    // newer versions print the message on the next line
+    if message.is_empty() {
+        message = lines
+            .get(last_error + 1)
+            .map(|l| l.to_string())
+            .unwrap_or_default();
+    }
+
+    // the position of the error follows it, the trace before it leads there
+    let locations: Vec<(String, Option<usize>)> = lines
+        .iter()
+        .filter_map(|l| project_location(work_dir, l))
+        .collect();
+    let after = lines[last_error..]
+        .iter()
+        .find_map(|l| project_location(work_dir, l));
+    let (file, line) = after
+        .or_else(|| locations.last().cloned())
+        .map(|(f, l)| (Some(f), l))
+        .unwrap_or_default();
+
+    let mut issue = EvalIssue {
+        message,
+        file,
+        line,
+        app: None,
+        option: None,
+        output: output.to_string(),
+    };
+
+    if let Some((app, option)) = option_path(&issue.message).and_then(option_by_nix_path) {
+        issue.app = Some(app);
+        issue.option = Some(option);
+    } else if let Some(file) = &issue.file {
+        if let Some(reg) = registration_by_file(file) {
+            issue.app = Some(reg.app);
+            issue.option = if extra_nix::file_name(reg.app).to_string_lossy() == *file {
+                Some(extra_nix::OPTION_NAME.to_string())
+            } else {
+                issue
+                    .line
+                    .and_then(|l| option_by_line(reg, &work_dir.join(file), l))
+            };
+        }
+    }
+
+    issue
+}
+
+/// Extracts the project file and the line from a line like
+/// ``at /nix/store/<hash>-source/apps/lnd.nix:16:5:`` or
+/// ``- In `/nix/store/<hash>-source/apps/lnd.nix': 10009``.
+/// Files that don't exist in the project belong to an input.
+fn project_location(work_dir: &Path, line: &str) -> Option<(String, Option<usize>)> {
+    let (_, rest) = line.split_once(FLAKE_SOURCE)?;
+    let path_end = rest
+        .find(|c: char| c == ':' || c == '\'' || c.is_whitespace())
+        .unwrap_or(rest.len());
+    let (path, rest) = rest.split_at(path_end);
+    let path = format!("{}/{}", FLAKE_DIR, path);
+    if !path.ends_with(".nix") || !work_dir.join(&path).is_file() {
+        return None;
+    }
+
+    let line = rest
+        .strip_prefix(':')
+        .and_then(|r| r.split(':').next())
+        .and_then(|l| l.parse().ok());
+
+    Some((path, line))
+}
+
+/// Extracts the nix option from a message like
+/// "The option `services.lnd.rpcPort' has conflicting definition values:"
+fn option_path(message: &str) -> Option<&str> {
+    let (_, rest) = message.split_once("option `")?;
+    rest.split_once('\'').map(|(path, _)| path)
+}
+
+/// Finds the option rendered into the given nix option, e.g.
+/// `services.lnd.rpcPort`
+fn option_by_nix_path(path: &str) -> Option<(SupportedApps, String)> {
+    registry::APPS.iter().find_map(|reg| {
+        let template = BASE_TEMPLATE
+            .get_file(reg.template_file_name)?
+            .contents_utf8()?;
+        (reg.default_options)().into_iter().find_map(|o| {
+            let option = o.id().option.clone();
+            template_uses(template, &option)
+                .iter()
+                .any(|u| u.nix_path.as_deref() == Some(path))
+                .then_some((reg.app, option))
+        })
+    })
+}
+
+/// Finds the app a rendered or extra nix file belongs to
+fn registration_by_file(file: &str) -> Option<&'static AppRegistration> {
+    registry::APPS.iter().find(|r| {
+        r.template_file_name.strip_suffix(".templ") == Some(file)
+            || extra_nix::file_name(r.app).to_string_lossy() == file
+    })
+}
+
+/// Finds the option whose template line assigns the same attribute as the
+/// given line of the rendered file
+fn option_by_line(reg: &AppRegistration, rendered: &Path, line: usize) -> Option<String> {
+    let contents = fs::read_to_string(rendered).ok()?;
+    let (name, _) = contents
+        .lines()
+        .nth(line.checked_sub(1)?)?
+        .split_once(" = ")?;
+    let name = name.trim();
+    let template = BASE_TEMPLATE
+        .get_file(reg.template_file_name)?
+        .contents_utf8()?;
+
+    template
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| l.split_once(" = ").is_some_and(|(n, _)| n.trim() == name))
+        .find_map(|l| placeholders(l).first().map(|p| p.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::{tempdir, TempDir};
+
+    use super::*;
+
+    /// A project with a rendered lnd file and an extra nix file
+    fn project() -> TempDir {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/apps")).unwrap();
+        fs::write(
+            dir.path().join("src/apps/lnd.nix"),
+            "{\n  services.lnd = {\n    rpcPort = \"x\";\n  };\n}\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("src/apps/lnd.extra.nix"), "{}\n").unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn test_project_location() {
+        let dir = project();
+        assert_eq!(
+            project_location(dir.path(), "at /nix/store/abc-source/apps/lnd.nix:16:5:"),
+            Some(("src/apps/lnd.nix".into(), Some(16)))
+        );
+        assert_eq!(
+            project_location(
+                dir.path(),
+                "- In `/nix/store/abc-source/apps/lnd.extra.nix': 10009"
+            ),
+            Some(("src/apps/lnd.extra.nix".into(), None))
+        );
+        assert_eq!(
+            project_location(
+                dir.path(),
+                "at /nix/store/xyz-source/nixos/modules/services/lnd.nix:3:1:"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_conflicting_definitions() {
+        let dir = project();
+        let output = "error:
+       … while evaluating the attribute 'config.system.build.toplevel'
+         at /nix/store/xyz-source/nixos/modules/system/activation/top-level.nix:71:12:
+
+       error: The option `services.lnd.rpcPort' has conflicting definition values:
+       - In `/nix/store/abc-source/apps/lnd.nix': 10009
+       - In `/nix/store/abc-source/apps/lnd.extra.nix': 10010
+";
+        let issue = parse_eval_error(dir.path(), output);
+        assert!(issue
+            .message
+            .starts_with("The option `services.lnd.rpcPort' has conflicting"));
+        assert_eq!(issue.file.as_deref(), Some("src/apps/lnd.nix"));
+        assert_eq!(issue.app, Some(SupportedApps::LND));
+        assert_eq!(issue.option.as_deref(), Some("rpc_port"));
+    }
+
+    #[test]
+    fn test_error_in_project_file() {
+        let dir = project();
+        let output = "error:
+       … while evaluating the attribute 'services.lnd.rpcPort'
+
+       error: A definition for option `foo' is not of type `16 bit unsigned integer'.
+         at /nix/store/abc-source/apps/lnd.nix:3:5:
+";
+        let issue = parse_eval_error(dir.path(), output);
+        assert_eq!(issue.file.as_deref(), Some("src/apps/lnd.nix"));
+        assert_eq!(issue.line, Some(3));
+        assert_eq!(issue.app, Some(SupportedApps::LND));
+        assert_eq!(issue.option.as_deref(), Some("rpc_port"));
+
+        let output =
+            "error: undefined variable 'foo'\n at /nix/store/abc-source/apps/lnd.extra.nix:9:3:\n";
+        let issue = parse_eval_error(dir.path(), output);
+        assert_eq!(issue.message, "undefined variable 'foo'");
+        assert_eq!(issue.option.as_deref(), Some(extra_nix::OPTION_NAME));
+    }
+}
@@ -0,0 +1,479 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_option_data::{
+        bool_data::BoolOptionData,
+        net_address_data::NetAddressOptionData,
+        number_data::NumberOptionData,
+        option_data::{
+            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToNixString,
+            ToOptionId,
+        },
+        port_data::PortOptionData,
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    tor::port_or,
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/electrs.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/electrs.json";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ElectrsService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// Address to listen for RPC connections
+    pub address: Box<NetAddressOptionData>,
+
+    /// Port to listen for RPC connections
+    ///
+    /// Default: 50001
+    pub port: Box<PortOptionData>,
+
+    /// The directory where electrs stores its index
+    ///
+    /// Default: "/var/lib/electrs"
+    pub db_dir: Box<TextOptionData>,
+
+    /// Address of the Prometheus monitoring endpoint
+    pub monitoring_address: Box<NetAddressOptionData>,
+
+    /// Port of the Prometheus monitoring endpoint
+    ///
+    /// Default: 4224
+    pub monitoring_port: Box<PortOptionData>,
+
+    /// Number of blocks to fetch and index in one batch.
+    /// Higher values speed up the initial sync at the cost of memory.
+    ///
+    /// Default: 10
+    pub index_batch_size: Box<NumberOptionData>,
+
+    /// Number of transactions to lookup before returning an error,
+    /// to prevent "too popular" addresses from causing a DoS attack.
+    ///
+    /// Default: None (no limit)
+    pub index_lookup_limit: Box<NumberOptionData>,
+
+    /// Extra command line arguments passed to electrs
+    pub extra_args: Box<TextOptionData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ElectrsConfigOption {
+    Enable,
+    Address,
+    Port,
+    DbDir,
+    MonitoringAddress,
+    MonitoringPort,
+    IndexBatchSize,
+    IndexLookupLimit,
+    ExtraArgs,
+}
+
+impl ToOptionId for ElectrsConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::Electrs, self.to_string())
+    }
+}
+
+impl FromStr for ElectrsConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<ElectrsConfigOption, ()> {
+        match s {
+            "enable" => Ok(ElectrsConfigOption::Enable),
+            "address" => Ok(ElectrsConfigOption::Address),
+            "port" => Ok(ElectrsConfigOption::Port),
+            "db_dir" => Ok(ElectrsConfigOption::DbDir),
+            "monitoring_address" => Ok(ElectrsConfigOption::MonitoringAddress),
+            "monitoring_port" => Ok(ElectrsConfigOption::MonitoringPort),
+            "index_batch_size" => Ok(ElectrsConfigOption::IndexBatchSize),
+            "index_lookup_limit" => Ok(ElectrsConfigOption::IndexLookupLimit),
+            "extra_args" => Ok(ElectrsConfigOption::ExtraArgs),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ElectrsConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            ElectrsConfigOption::Enable => "enable",
+            ElectrsConfigOption::Address => "address",
+            ElectrsConfigOption::Port => "port",
+            ElectrsConfigOption::DbDir => "db_dir",
+            ElectrsConfigOption::MonitoringAddress => "monitoring_address",
+            ElectrsConfigOption::MonitoringPort => "monitoring_port",
+            ElectrsConfigOption::IndexBatchSize => "index_batch_size",
+            ElectrsConfigOption::IndexLookupLimit => "index_lookup_limit",
+            ElectrsConfigOption::ExtraArgs => "extra_args",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for ElectrsService {
+    fn get_options(&self) -> Vec<OptionData> {
+        vec![
+            OptionData::Bool(self.enable.clone()),
+            OptionData::NetAddress(self.address.clone()),
+            OptionData::Port(self.port.clone()),
+            OptionData::TextEdit(self.db_dir.clone()),
+            OptionData::NetAddress(self.monitoring_address.clone()),
+            OptionData::Port(self.monitoring_port.clone()),
+            OptionData::NumberEdit(self.index_batch_size.clone()),
+            OptionData::NumberEdit(self.index_lookup_limit.clone()),
+            OptionData::TextEdit(self.extra_args.clone()),
+        ]
+    }
+
+    fn app_option_changed(
+        &mut self,
+        option: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let id = option.id();
+        if let Ok(opt) = ElectrsConfigOption::from_str(&id.option) {
+            let mut res = Ok(false);
+            match opt {
+                ElectrsConfigOption::Enable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.enable.value() != val.value);
+                        self.enable.set_value(val.value);
+                    }
+                }
+                ElectrsConfigOption::Address => {
+                    if let OptionDataChangeNotification::NetAddress(val) = option {
+                        res = Ok(self.address.value() != val.value);
+                        self.address.set_value(val.value);
+                    }
+                }
+                ElectrsConfigOption::Port => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.port.value() != val.value);
+                        self.port.set_value(val.value.clone());
+                    }
+                }
+                ElectrsConfigOption::DbDir => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.db_dir.value() != val.value);
+                        self.db_dir.set_value(val.value.clone());
+                    }
+                }
+                ElectrsConfigOption::MonitoringAddress => {
+                    if let OptionDataChangeNotification::NetAddress(val) = option {
+                        res = Ok(self.monitoring_address.value() != val.value);
+                        self.monitoring_address.set_value(val.value);
+                    }
+                }
+                ElectrsConfigOption::MonitoringPort => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.monitoring_port.value() != val.value);
+                        self.monitoring_port.set_value(val.value.clone());
+                    }
+                }
+                ElectrsConfigOption::IndexBatchSize => {
+                    if let OptionDataChangeNotification::Number(val) = option {
+                        res = Ok(*self.index_batch_size.value() != val.value);
+                        self.index_batch_size.set_value(val.value.clone());
+                    }
+                }
+                ElectrsConfigOption::IndexLookupLimit => {
+                    if let OptionDataChangeNotification::Number(val) = option {
+                        res = Ok(*self.index_lookup_limit.value() != val.value);
+                        self.index_lookup_limit.set_value(val.value.clone());
+                    }
+                }
+                ElectrsConfigOption::ExtraArgs => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.extra_args.value() != val.value);
+                        self.extra_args.set_value(val.value.clone());
+                    }
+                }
+            }
+
+            return res;
+        }
+
+        Ok(false)
+    }
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering electrs config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Default for ElectrsService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                ElectrsConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            address: Box::new(NetAddressOptionData::new(
+                ElectrsConfigOption::Address.to_option_id(),
+                Some(defaults::LOCALHOST),
+            )),
+            port: Box::new(PortOptionData::new(
+                ElectrsConfigOption::Port.to_option_id(),
+                NumberValue::U16(Some(defaults::electrs::PORT)),
+            )),
+            db_dir: Box::new(TextOptionData::new(
+                ElectrsConfigOption::DbDir.to_option_id(),
+                defaults::electrs::DB_DIR.to_string(),
+                1,
+                false,
+                defaults::electrs::DB_DIR.to_string(),
+            )),
+            monitoring_address: Box::new(NetAddressOptionData::new(
+                ElectrsConfigOption::MonitoringAddress.to_option_id(),
+                Some(defaults::LOCALHOST),
+            )),
+            monitoring_port: Box::new(PortOptionData::new(
+                ElectrsConfigOption::MonitoringPort.to_option_id(),
+                NumberValue::U16(Some(defaults::electrs::MONITORING_PORT)),
+            )),
+            index_batch_size: Box::new(
+                NumberOptionData::new(
+                    ElectrsConfigOption::IndexBatchSize.to_option_id(),
+                    NumberValue::UInt(Some(defaults::electrs::INDEX_BATCH_SIZE)),
+                    1,
+                    1000,
+                    false,
+                    NumberValue::UInt(Some(defaults::electrs::INDEX_BATCH_SIZE)),
+                )
+                .unwrap(),
+            ),
+            index_lookup_limit: Box::new(
+                NumberOptionData::new(
+                    ElectrsConfigOption::IndexLookupLimit.to_option_id(),
+                    NumberValue::UInt(None),
+                    1,
+                    usize::MAX,
+                    false,
+                    NumberValue::UInt(None),
+                )
+                .unwrap(),
+            ),
+            extra_args: Box::new(TextOptionData::new(
+                ElectrsConfigOption::ExtraArgs.to_option_id(),
+                "".to_string(),
+                1,
+                false,
+                "".to_string(),
+            )),
+        }
+    }
+}
+
+impl ElectrsService {
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            ("address", self.address.to_nix_string(true)),
+            (
+                "port",
+                port_or(self.port.value(), defaults::electrs::PORT).to_string(),
+            ),
+            ("db_dir", self.db_dir.value().to_string()),
+            (
+                "monitoring_port",
+                port_or(
+                    self.monitoring_port.value(),
+                    defaults::electrs::MONITORING_PORT,
+                )
+                .to_string(),
+            ),
+            ("extra_args", self.cmd_line_args()),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render electrs template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    /// Builds the command line arguments that have no dedicated nix-bitcoin option
+    fn cmd_line_args(&self) -> String {
+        let mut args = vec![];
+        if let Some(addr) = self.monitoring_address.value() {
+            args.push(format!(
+                "--monitoring-addr={}:{}",
+                addr,
+                port_or(
+                    self.monitoring_port.value(),
+                    defaults::electrs::MONITORING_PORT
+                )
+            ));
+        }
+
+        let batch_size = self.index_batch_size.value().to_string();
+        if !batch_size.is_empty() {
+            args.push(format!("--index-batch-size={}", batch_size));
+        }
+
+        let lookup_limit = self.index_lookup_limit.value().to_string();
+        if !lookup_limit.is_empty() {
+            args.push(format!("--index-lookup-limit={}", lookup_limit));
+        }
+
+        let extra = self.extra_args.value().trim();
+        if !extra.is_empty() {
+            args.push(extra.to_string());
+        }
+
+        args.join(" ")
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<ElectrsService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::utils::init_default_project;
+
+    use super::*;
+
+    fn get_test_service() -> ElectrsService {
+        let mut service = ElectrsService::default();
+        service.enable.set_value(true);
+        service
+            .index_lookup_limit
+            .set_value(NumberValue::UInt(Some(200)));
+        service.extra_args.set_value("--log-filters=INFO".into());
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = ElectrsService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains(&format!("enable = {};", s.enable.value())));
+            assert!(data.contains(&format!(
+                "address = lib.mkDefault {};",
+                s.address.to_nix_string(true)
+            )));
+            assert!(data.contains(&format!("port = {};", s.port.value())));
+            assert!(data.contains(&format!("dataDir = \"{}\";", s.db_dir.value())));
+            assert!(data.contains(&format!("monitoringPort = {};", s.monitoring_port.value())));
+            assert!(data.contains(
+                "extraArgs = \"--monitoring-addr=127.0.0.1:4224 --index-batch-size=10 \
+                 --index-lookup-limit=200 --log-filters=INFO\";"
+            ));
+        }
+
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,381 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::{app_options, AppConfig},
+    app_option_data::{
+        bool_data::BoolOptionData,
+        option_data::{OptionId, ToOptionId},
+        port_data::PortOptionData,
+        string_list_data::{StringListOptionData, StringListOptionItem},
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    bitcoind::BitcoinDaemonService,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    tor::port_or,
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/elements.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/elements.json";
+
+/// The bitcoind RPC user elementsd validates peg-ins with
+pub const BITCOIND_RPC_USER: &str = "elements";
+
+/// The Liquid networks elementsd can follow
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ElementsChain {
+    #[default]
+    LiquidV1,
+    LiquidTestnet,
+}
+
+impl ElementsChain {
+    pub fn to_string_array() -> [&'static str; 2] {
+        ["liquidv1", "liquidtestnet"]
+    }
+}
+
+impl fmt::Display for ElementsChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chain_str = match self {
+            ElementsChain::LiquidV1 => "liquidv1",
+            ElementsChain::LiquidTestnet => "liquidtestnet",
+        };
+        write!(f, "{}", chain_str)
+    }
+}
+
+impl FromStr for ElementsChain {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<ElementsChain, ()> {
+        match s {
+            "liquidv1" => Ok(ElementsChain::LiquidV1),
+            "liquidtestnet" => Ok(ElementsChain::LiquidTestnet),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Runs elementsd, a node of the Liquid sidechain.
+///
+/// With peg-in validation elementsd checks peg-ins against the local
+/// bitcoind. It connects with a dedicated RPC user that is set when the
+/// app is enabled, nix-bitcoin generates its password on the node.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ElementsService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// The Liquid network, see [ElementsChain]
+    pub chain: Box<StringListOptionData>,
+
+    /// Port of the elementsd RPC interface
+    pub rpc_port: Box<PortOptionData>,
+
+    /// Where elementsd stores the sidechain
+    pub data_dir: Box<TextOptionData>,
+
+    /// Whether peg-ins are validated against bitcoind
+    pub validate_pegin: Box<BoolOptionData>,
+
+    /// The bitcoind RPC user to validate peg-ins with. Set when the service
+    /// is enabled, nix-bitcoin generates its password on the node.
+    pub bitcoind_rpc_user: String,
+
+    /// The RPC port of bitcoind, derived from the bitcoind config
+    pub bitcoind_rpc_port: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ElementsConfigOption {
+    Enable,
+    Chain,
+    RpcPort,
+    DataDir,
+    ValidatePegin,
+}
+
+impl ToOptionId for ElementsConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::Elements, self.to_string())
+    }
+}
+
+impl FromStr for ElementsConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<ElementsConfigOption, ()> {
+        match s {
+            "enable" => Ok(ElementsConfigOption::Enable),
+            "chain" => Ok(ElementsConfigOption::Chain),
+            "rpc_port" => Ok(ElementsConfigOption::RpcPort),
+            "data_dir" => Ok(ElementsConfigOption::DataDir),
+            "validate_pegin" => Ok(ElementsConfigOption::ValidatePegin),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ElementsConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            ElementsConfigOption::Enable => "enable",
+            ElementsConfigOption::Chain => "chain",
+            ElementsConfigOption::RpcPort => "rpc_port",
+            ElementsConfigOption::DataDir => "data_dir",
+            ElementsConfigOption::ValidatePegin => "validate_pegin",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for ElementsService {
+    app_options!(ElementsConfigOption {
+        Enable => enable,
+        Chain => chain,
+        RpcPort => rpc_port,
+        DataDir => data_dir,
+        ValidatePegin => validate_pegin,
+    });
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering elements config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Default for ElementsService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                ElementsConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            chain: Box::new(StringListOptionData::new(
+                ElementsConfigOption::Chain.to_option_id(),
+                ElementsChain::default().to_string(),
+                ElementsChain::to_string_array()
+                    .map(|entry| StringListOptionItem::new(entry.to_string(), entry.to_string()))
+                    .to_vec(),
+            )),
+            rpc_port: Box::new(PortOptionData::new(
+                ElementsConfigOption::RpcPort.to_option_id(),
+                NumberValue::U16(Some(defaults::elements::RPC_PORT)),
+            )),
+            data_dir: Box::new(TextOptionData::new(
+                ElementsConfigOption::DataDir.to_option_id(),
+                defaults::elements::DATA_DIR.into(),
+                1,
+                false,
+                defaults::elements::DATA_DIR.into(),
+            )),
+            validate_pegin: Box::new(BoolOptionData::new(
+                ElementsConfigOption::ValidatePegin.to_option_id(),
+                true,
+            )),
+            bitcoind_rpc_user: String::new(),
+            bitcoind_rpc_port: defaults::bitcoind::RPC_PORT,
+        }
+    }
+}
+
+impl ElementsService {
+    /// Whether elementsd needs the RPC user of bitcoind
+    pub fn needs_bitcoind(&self) -> bool {
+        self.enable.value() && self.validate_pegin.value()
+    }
+
+    /// Takes the RPC port from the bitcoind config. The RPC user is
+    /// created by the [Project](crate::project::Project).
+    ///
+    /// # Returns
+    /// Whether the port changed
+    pub fn sync_with_bitcoind(&mut self, bitcoin: &BitcoinDaemonService) -> bool {
+        let port = port_or(bitcoin.rpc_port.value(), defaults::bitcoind::RPC_PORT);
+        let changed = self.bitcoind_rpc_port != port;
+        self.bitcoind_rpc_port = port;
+
+        changed
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        // without credentials elementsd could not reach bitcoind anyway
+        let validate_pegin = self.validate_pegin.value() && !self.bitcoind_rpc_user.is_empty();
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            ("chain", self.chain.value().to_string()),
+            (
+                "rpc_port",
+                port_or(self.rpc_port.value(), defaults::elements::RPC_PORT).to_string(),
+            ),
+            ("data_dir", self.data_dir.value().to_string()),
+            (
+                "validate_pegin",
+                if validate_pegin { "1" } else { "0" }.into(),
+            ),
+            ("bitcoind_rpc_user", self.bitcoind_rpc_user.clone()),
+            ("bitcoind_rpc_port", self.bitcoind_rpc_port.to_string()),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render elements template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<ElementsService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::utils::init_default_project;
+
+    use super::*;
+
+    fn get_test_service() -> ElementsService {
+        let mut service = ElementsService::default();
+        service.enable.set_value(true);
+        service.bitcoind_rpc_user = BITCOIND_RPC_USER.to_string();
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+        assert!(!json_content.contains("password"));
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = ElementsService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_sync_with_bitcoind() {
+        let mut service = ElementsService::default();
+        let mut bitcoin = BitcoinDaemonService::default();
+        assert!(!service.sync_with_bitcoind(&bitcoin));
+
+        bitcoin.rpc_port.set_value(NumberValue::U16(Some(18332)));
+        assert!(service.sync_with_bitcoind(&bitcoin));
+        assert_eq!(service.bitcoind_rpc_port, 18332);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut s = get_test_service();
+
+        let data = &s.render().unwrap()[TEMPLATE_FILE_NAME];
+        assert!(data.contains("chain=liquidv1"));
+        assert!(data.contains("[liquidv1]"));
+        assert!(data.contains("rpcport=7041"));
+        assert!(data.contains("validatepegin=1"));
+        assert!(data.contains("mainchainrpcuser=elements"));
+        assert!(data.contains("mainchainrpcport=8332"));
+        // the password is read from the secrets dir when elementsd starts
+        assert_eq!(data.matches("mainchainrpcpassword=").count(), 1);
+        assert!(data.contains(
+            "mainchainrpcpassword=$(cat ${config.nix-bitcoin.secretsDir}/bitcoin-rpcpassword-elements)"
+        ));
+        assert!(data.contains("elements.passwordHMACFromFile = true;"));
+
+        s.bitcoind_rpc_user = String::new();
+        let data = &s.render().unwrap()[TEMPLATE_FILE_NAME];
+        assert!(data.contains("validatepegin=0"));
+        assert!(!data.contains("mainchainrpcuser"));
+        assert!(!data.contains("passwordHMACFromFile"));
+    }
+}
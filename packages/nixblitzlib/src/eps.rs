@@ -0,0 +1,409 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::{app_options, AppConfig},
+    app_option_data::{
+        bool_data::BoolOptionData,
+        manual_string_list_data::ManualStringListOptionData,
+        net_address_data::NetAddressOptionData,
+        option_data::{OptionDataChangeNotification, OptionId, ToNixString, ToOptionId},
+        port_data::PortOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/eps.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/eps.json";
+
+/// Prefixes of the extended public keys Electrum wallets export
+const KEY_PREFIXES: [&str; 6] = ["xpub", "ypub", "zpub", "tpub", "upub", "vpub"];
+
+/// Electrum Personal Server, a lightweight Electrum server for a single user.
+///
+/// Unlike Electrs and Fulcrum it doesn't index the whole block chain but only
+/// tracks the wallets of the given master public keys in the bitcoind wallet.
+/// This makes it usable on pruned nodes.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct EpsService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// Address to listen for Electrum connections
+    pub address: Box<NetAddressOptionData>,
+
+    /// Port to listen for Electrum connections, EPS only supports SSL
+    ///
+    /// Default: 50002
+    pub port: Box<PortOptionData>,
+
+    /// The master public keys of the wallets to track, one per entry.
+    /// Multisig wallets are given as the number of required signatures
+    /// followed by the keys.
+    ///
+    /// Example: "2 xpub661MyMwAqRbcF... xpub661MyMwAqRbcG..."
+    pub master_public_keys: Box<ManualStringListOptionData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EpsConfigOption {
+    Enable,
+    Address,
+    Port,
+    MasterPublicKeys,
+}
+
+impl ToOptionId for EpsConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::ElectrumPersonalServer, self.to_string())
+    }
+}
+
+impl FromStr for EpsConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<EpsConfigOption, ()> {
+        match s {
+            "enable" => Ok(EpsConfigOption::Enable),
+            "address" => Ok(EpsConfigOption::Address),
+            "port" => Ok(EpsConfigOption::Port),
+            "master_public_keys" => Ok(EpsConfigOption::MasterPublicKeys),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for EpsConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            EpsConfigOption::Enable => "enable",
+            EpsConfigOption::Address => "address",
+            EpsConfigOption::Port => "port",
+            EpsConfigOption::MasterPublicKeys => "master_public_keys",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for EpsService {
+    app_options!(EpsConfigOption {
+        Enable => enable where validate_enable,
+        Address => address,
+        Port => port,
+        MasterPublicKeys => master_public_keys where validate_keys,
+    });
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering electrum personal server config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Checks that every entry is a master public key or a multisig wallet,
+/// i.e. the number of required signatures followed by its keys
+///
+/// # Arguments
+///
+/// * `required` - Whether at least one wallet is needed, EPS refuses to
+///   start without one
+///
+/// # Errors
+///
+/// [ProjectError::InvalidValue] listing all invalid entries
+fn check_keys(option: &str, entries: &[String], required: bool) -> Result<(), ProjectError> {
+    let entries: Vec<&str> = entries
+        .iter()
+        .map(|e| e.trim())
+        .filter(|e| !e.is_empty())
+        .collect();
+
+    let mut issues: Vec<String> = entries
+        .iter()
+        .filter(|e| !is_wallet(e))
+        .map(|e| format!("{} is not a master public key", e))
+        .collect();
+    if required && entries.is_empty() {
+        issues.push("At least one master public key is required".to_string());
+    }
+
+    if issues.is_empty() {
+        return Ok(());
+    }
+
+    Err(Report::new(ProjectError::InvalidValue(
+        option.to_string(),
+        issues,
+    )))
+}
+
+fn is_wallet(entry: &str) -> bool {
+    let is_key = |k: &str| KEY_PREFIXES.iter().any(|p| k.starts_with(p)) && k.len() > 100;
+
+    let parts: Vec<&str> = entry.split_whitespace().collect();
+    match parts.as_slice() {
+        [key] => is_key(key),
+        [required, keys @ ..] => match required.parse::<usize>() {
+            Ok(n) => (1..=keys.len()).contains(&n) && keys.iter().all(|k| is_key(k)),
+            Err(_) => false,
+        },
+        [] => false,
+    }
+}
+
+impl Default for EpsService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                EpsConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            address: Box::new(NetAddressOptionData::new(
+                EpsConfigOption::Address.to_option_id(),
+                Some(defaults::LOCALHOST),
+            )),
+            port: Box::new(PortOptionData::new(
+                EpsConfigOption::Port.to_option_id(),
+                NumberValue::U16(Some(defaults::eps::PORT)),
+            )),
+            master_public_keys: Box::new(ManualStringListOptionData::new(
+                EpsConfigOption::MasterPublicKeys.to_option_id(),
+                vec![],
+                0,
+            )),
+        }
+    }
+}
+
+impl EpsService {
+    /// EPS can only be enabled with at least one wallet
+    fn validate_enable(&self, option: &OptionDataChangeNotification) -> Result<(), ProjectError> {
+        match option {
+            OptionDataChangeNotification::Bool(val) if val.value => check_keys(
+                &EpsConfigOption::Enable.to_string(),
+                self.master_public_keys.value(),
+                true,
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_keys(&self, option: &OptionDataChangeNotification) -> Result<(), ProjectError> {
+        match option {
+            OptionDataChangeNotification::ManualStringList(val) => check_keys(
+                &EpsConfigOption::MasterPublicKeys.to_string(),
+                &val.value,
+                self.enable.value(),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let master_public_keys = self
+            .master_public_keys
+            .value()
+            .iter()
+            .map(|k| k.trim())
+            .filter(|k| !k.is_empty())
+            .enumerate()
+            .map(|(i, k)| format!("wallet{} = {}", i + 1, k))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            ("address", self.address.to_nix_string(false)),
+            (
+                "port",
+                self.port
+                    .value()
+                    .to_string_or(&defaults::eps::PORT.to_string()),
+            ),
+            ("data_dir", defaults::eps::DATA_DIR.to_string()),
+            ("master_public_keys", master_public_keys),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render electrum personal server template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<EpsService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::{
+            bool_data::BoolOptionChangeData,
+            manual_string_list_data::ManualStringListOptionChangeData,
+        },
+        utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn xpub(c: char) -> String {
+        format!("xpub{}", c.to_string().repeat(107))
+    }
+
+    fn get_test_service() -> EpsService {
+        let mut service = EpsService::default();
+        service.enable.set_value(true);
+        service
+            .master_public_keys
+            .set_value(vec![xpub('a'), format!("2 {} {}", xpub('b'), xpub('c'))]);
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = EpsService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_master_public_keys() {
+        let mut service = EpsService::default();
+        let change = |value: Vec<String>| {
+            OptionDataChangeNotification::ManualStringList(ManualStringListOptionChangeData::new(
+                EpsConfigOption::MasterPublicKeys.to_option_id(),
+                value,
+            ))
+        };
+        let enable = OptionDataChangeNotification::Bool(BoolOptionChangeData::new(
+            EpsConfigOption::Enable.to_option_id(),
+            true,
+        ));
+
+        // EPS doesn't start without a wallet
+        assert!(service.app_option_changed(&enable).is_err());
+
+        assert!(service
+            .app_option_changed(&change(vec!["xpub123".into()]))
+            .is_err());
+        assert!(service
+            .app_option_changed(&change(vec![format!("3 {} {}", xpub('a'), xpub('b'))]))
+            .is_err());
+        assert!(service
+            .app_option_changed(&change(vec![format!("1 {} {}", xpub('a'), xpub('b'))]))
+            .unwrap());
+        assert!(service.app_option_changed(&enable).unwrap());
+
+        // the last wallet can't be removed while EPS is enabled
+        assert!(service.app_option_changed(&change(vec![])).is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains(&format!("wallet1 = {}", xpub('a'))));
+            assert!(data.contains(&format!("wallet2 = 2 {} {}", xpub('b'), xpub('c'))));
+            assert!(data.contains("port = 50002"));
+        }
+
+        assert!(result.is_ok());
+    }
+}
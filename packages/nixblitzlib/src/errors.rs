@@ -1,5 +1,8 @@
+use error_stack::Report;
 use thiserror::Error;
 
+use crate::{dependencies::DependencyConflict, validation::ConfigConflict};
+
 #[derive(Debug, Error)]
 pub enum ArgumentError {
     #[error("Wrong argument provided: {}, expected: {} ", .0, .1)]
@@ -18,6 +21,8 @@ pub enum PasswordError {
     MissingConfirm,
     #[error("Passwords do not match")]
     Mismatch,
+    #[error("Unable to generate random data")]
+    RandomGenerationError,
 }
 
 #[derive(Debug, Error)]
@@ -62,6 +67,94 @@ pub enum ProjectError {
     FileReadError(String),
     #[error("Invalid data type. Got {:?} Expected {:?}", .0, .1)]
     InvalidDataType(String, String),
+    #[error("{}", .0)]
+    DependencyConflict(DependencyConflict),
+    #[error("{}", .0)]
+    ConfigConflict(ConfigConflict),
+    #[error("Unable to manage the filesystem snapshots")]
+    SnapshotError,
+    #[error("Unable to manage the config backups")]
+    BackupError,
+    #[error("The project files were changed on disk: {}", .0.join(", "))]
+    ChangedOnDisk(Vec<String>),
+    #[error("Unable to reach {}", .0)]
+    NodeUnreachable(String),
+    #[error("Invalid code for {}: {}", .0, .1.join("; "))]
+    InvalidCode(String, Vec<String>),
+    #[error("The platform {} is not supported", .0)]
+    UnsupportedPlatform(String),
+    #[error("Unable to upgrade the templates")]
+    UpgradeError,
+    #[error("Unable to run the system backup")]
+    SystemBackupError,
+    #[error("Unable to read the logs of {}", .0)]
+    JournalError(String),
+    #[error("Unable to benchmark the disk")]
+    DiskBenchmarkError,
+    #[error("Unable to set up the secrets")]
+    SecretsError,
+    #[error("Missing secrets: {}", .0.join(", "))]
+    MissingSecrets(Vec<String>),
+    #[error("Another nixos-rebuild is already running")]
+    RebuildInProgress,
+    #[error("Unable to store the secret")]
+    SecretStoreError,
+    #[error("Unable to reset the project")]
+    FactoryResetError,
+    #[error("Unable to initialize the project from the git repository")]
+    CloneError,
+    #[error("Unable to compare the project with the applied configuration")]
+    PendingChangesError,
+    #[error("Unable to read the results of the scheduled maintenance")]
+    MaintenanceError,
+    #[error("Unable to format the option values")]
+    DumpError,
+    #[error("Unable to export or import the config bundle")]
+    BundleError,
+    #[error("Unable to migrate the project files")]
+    MigrationError,
+    #[error("Unable to copy the project to the installed system")]
+    ConfigSyncError,
+    #[error("The project needs a newer nixblitz, config version {} > {}", .0, .1)]
+    ConfigTooNew(u32, u32),
+    #[error("Unable to read the status of the services")]
+    StatusError,
+    #[error("Unable to record the apply")]
+    ApplyHistoryError,
+    #[error("Unable to roll back the system")]
+    RollbackError,
+    #[error("Unable to build the configuration")]
+    DryBuildError,
+    #[error("Unable to measure the disk usage of the apps")]
+    DiskUsageError,
+    #[error("Switching pruning off needs a reindex of the block chain, use the upgrade to an archival node")]
+    ArchivalUpgradeRequired,
+    #[error("Unable to upgrade to an archival node")]
+    ArchivalError,
+    #[error("Unable to check the TLS certificate")]
+    CertificateError,
+    #[error("Unable to reset the option")]
+    ResetError,
+    #[error("Unable to set the options")]
+    BatchError,
+    #[error("Unable to import the SSH keys")]
+    SshKeyError,
+    #[error("Invalid value for {}: {}", .0, .1.join("; "))]
+    InvalidValue(String, Vec<String>),
+}
+
+/// Joins the error and its printable attachments into a single line
+pub fn describe(report: &Report<ProjectError>) -> String {
+    let details: Vec<String> = report
+        .frames()
+        .filter_map(|f| {
+            f.downcast_ref::<String>()
+                .cloned()
+                .or_else(|| f.downcast_ref::<&str>().map(|s| s.to_string()))
+        })
+        .collect();
+
+    format!("{}: {}", report.current_context(), details.join(". "))
 }
 
 #[derive(Debug, Error)]
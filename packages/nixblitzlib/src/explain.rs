@@ -0,0 +1,221 @@
+//! Explains where the value of an option comes from and where it goes.
+//!
+//! The type, allowed values and defaults come from the same [OptionData]
+//! frontends get, see [option_schema]. Where the value ends up is found by
+//! looking for the option in the nix template of the app. The attribute path
+//! is derived from the nesting of the template, which covers the usual
+//! `services.x = { y = {{ y }}; }` layout. Options rendered under another
+//! name, e.g. through a helper, have no uses.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    app_option_data::option_data::{GetOptionId, OptionData},
+    dependencies::{all_requirements, enable_option_id},
+    option_values::json_value,
+    registry::{registration, AppRegistration},
+    schema::option_schema,
+    strings::OPTION_TITLES,
+    utils::BASE_TEMPLATE,
+};
+
+/// A line of a template the option is rendered in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TemplateUse {
+    /// Starting at 1
+    pub line: usize,
+
+    /// The line as written in the template
+    pub text: String,
+
+    /// The nix attribute the line belongs to, e.g. `services.lnd.port`.
+    /// None at the top level of the template.
+    pub nix_path: Option<String>,
+}
+
+/// Everything known about an option
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Explanation {
+    /// The [name](AppRegistration::name) of the app
+    pub app: &'static str,
+    pub option: String,
+    pub title: Option<&'static str>,
+
+    /// The type of the option, e.g. "Port"
+    pub kind: &'static str,
+    pub value: Value,
+    pub default: Value,
+
+    /// The JSON Schema of the value, i.e. the allowed values and the range
+    pub schema: Value,
+
+    /// The apps that must be enabled for the option to have an effect
+    pub requires: Vec<&'static str>,
+
+    /// The template of the app, relative to the work dir
+    pub template: &'static str,
+
+    /// Where the option is rendered in the template
+    pub uses: Vec<TemplateUse>,
+}
+
+/// Explains an option of an app, using its current data
+///
+/// # Returns
+/// None if the app has no such option
+pub fn explain(reg: &AppRegistration, options: &[OptionData], option: &str) -> Option<Explanation> {
+    let current = options.iter().find(|o| o.id().option == option)?;
+    let default = (reg.default_options)()
+        .into_iter()
+        .find(|o| o.id().option == option)
+        .map(|o| json_value(&o))
+        .unwrap_or(Value::Null);
+
+    let mut schema = option_schema(current);
+    if let Some(schema) = schema.as_object_mut() {
+        schema.remove("title");
+        schema.remove("default");
+    }
+
+    let mut requires: Vec<&'static str> = all_requirements(reg.app)
+        .into_iter()
+        .map(|a| registration(a).name())
+        .collect();
+    if enable_option_id(reg.app).is_some_and(|id| id != *current.id()) {
+        requires.push(reg.name());
+    }
+
+    let template = BASE_TEMPLATE
+        .get_file(reg.template_file_name)
+        .and_then(|f| f.contents_utf8())
+        .unwrap_or_default();
+
+    Some(Explanation {
+        app: reg.name(),
+        option: option.to_string(),
+        title: OPTION_TITLES.get(current.id()).copied(),
+        kind: current.kind(),
+        value: json_value(current),
+        default,
+        schema,
+        requires,
+        template: reg.template_file_name,
+        uses: template_uses(template, option),
+    })
+}
+
+/// The names of the handlebars expressions in a line, e.g. `port` for
+/// `{{ port }}` and `{{#if port}}`
+pub(crate) fn placeholders(line: &str) -> Vec<&str> {
+    line.split("{{")
+        .skip(1)
+        .filter_map(|s| s.split_once("}}").map(|(expr, _)| expr))
+        .filter_map(|expr| {
+            let expr = expr.trim_matches(|c: char| c == '~' || c.is_whitespace());
+            let expr = expr
+                .strip_prefix('#')
+                .or_else(|| expr.strip_prefix('/'))
+                .map(|e| e.split_whitespace().last().unwrap_or_default())
+                .unwrap_or(expr);
+            (!expr.is_empty()).then_some(expr)
+        })
+        .collect()
+}
+
+/// Finds the lines of the template the option is rendered in
+pub(crate) fn template_uses(template: &str, option: &str) -> Vec<TemplateUse> {
+    // the attribute names of the open braces, None for other braces
+    let mut stack: Vec<Option<String>> = vec![];
+    let mut res = vec![];
+
+    for (i, line) in template.lines().enumerate() {
+        let trimmed = line.trim();
+        let assigned = trimmed
+            .split_once(" = ")
+            .map(|(name, _)| name.trim())
+            .filter(|name| !name.contains(char::is_whitespace) && !name.contains("{{"));
+
+        if placeholders(trimmed).contains(&option) {
+            let parents: Vec<&str> = stack.iter().flatten().map(|s| s.as_str()).collect();
+            let nix_path = match assigned {
+                Some(name) => Some(parents.iter().copied().chain([name]).collect()),
+                None if !parents.is_empty() => Some(parents.clone()),
+                None => None,
+            }
+            .map(|p: Vec<&str>| p.join("."));
+
+            res.push(TemplateUse {
+                line: i + 1,
+                text: trimmed.to_string(),
+                nix_path,
+            });
+        }
+
+        if trimmed.starts_with('}') || trimmed.starts_with("'';") {
+            stack.pop();
+        }
+        if trimmed.ends_with('{') || trimmed.ends_with("''") {
+            stack.push(assigned.map(String::from));
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app_config::AppConfig, apps::SupportedApps, lnd::LightningNetworkDaemonService, registry,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(placeholders("port = {{ port }};"), vec!["port"]);
+        assert_eq!(
+            placeholders("{{#if wtclient_towers}}"),
+            vec!["wtclient_towers"]
+        );
+        assert_eq!(placeholders("{{/if}}"), vec!["if"]);
+        assert_eq!(placeholders("x = [ {{ a }} {{~ b ~}} ];"), vec!["a", "b"]);
+        assert!(placeholders("no handlebars").is_empty());
+    }
+
+    #[test]
+    fn test_template_uses() {
+        let template = "{\n  config,\n}: {\n  services.lnd = {\n    port = {{ port }};\n    \
+                        extraConfig = ''\n      {{ alias }}\n    '';\n  };\n  x = {{ port }};\n}\n";
+
+        let uses = template_uses(template, "port");
+        assert_eq!(uses.len(), 2);
+        assert_eq!(uses[0].line, 5);
+        assert_eq!(uses[0].nix_path.as_deref(), Some("services.lnd.port"));
+        assert_eq!(uses[1].nix_path.as_deref(), Some("x"));
+
+        let uses = template_uses(template, "alias");
+        assert_eq!(
+            uses[0].nix_path.as_deref(),
+            Some("services.lnd.extraConfig")
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        let reg = registry::registration(SupportedApps::LND);
+        let options = LightningNetworkDaemonService::default().get_options();
+
+        let explanation = explain(reg, &options, "rpc_port").unwrap();
+        assert_eq!(explanation.kind, "Port");
+        assert_eq!(explanation.value, explanation.default);
+        assert_eq!(explanation.requires, vec!["bitcoind", "lnd"]);
+        assert_eq!(explanation.schema["maximum"], 65535);
+        assert!(explanation
+            .uses
+            .iter()
+            .any(|u| u.nix_path.as_deref() == Some("services.lnd.rpcPort")));
+
+        assert!(explain(reg, &options, "nope").is_none());
+    }
+}
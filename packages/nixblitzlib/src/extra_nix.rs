@@ -0,0 +1,186 @@
+//! Hand written nix config that extends an app.
+//!
+//! The module of every app imports a `<name>.extra.nix` next to it if the
+//! file exists. nixblitz creates it with an empty module and afterwards only
+//! writes it when the extra nix option of the app changes. Saving the app or
+//! upgrading the templates never touches it, so edits made by hand survive.
+//!
+//! The option holds the body of the module. If the file was rewritten in a
+//! shape nixblitz doesn't recognize, the option is left out and the file
+//! must be edited directly.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{Result, ResultExt};
+
+use crate::{
+    app_option_data::{
+        code_data::{CodeLanguage, CodeOptionData},
+        option_data::OptionId,
+    },
+    apps::SupportedApps,
+    errors::ProjectError,
+    registry,
+    utils::create_file,
+};
+
+/// Name of the option every app gets, see [option_id]
+pub const OPTION_NAME: &str = "extra_nix";
+
+/// Maximum number of lines of the option
+const MAX_LINES: u16 = 100;
+
+/// The line the body of the module starts after
+const BODY_START: &str = "}: {";
+
+/// The id of the extra nix option of an app
+pub fn option_id(app: SupportedApps) -> OptionId {
+    OptionId::new(app, OPTION_NAME.to_string())
+}
+
+/// Path of the extra nix file of an app relative to the work dir,
+/// e.g. src/apps/lnd.extra.nix
+pub fn file_name(app: SupportedApps) -> PathBuf {
+    PathBuf::from(
+        registry::registration(app)
+            .template_file_name
+            .replace(".nix.templ", ".extra.nix"),
+    )
+}
+
+/// Wraps the body into a module with the usual arguments
+fn render(app: SupportedApps, body: &str) -> String {
+    let mut res = format!(
+        "# Extra config of {}. nixblitz doesn't overwrite this file when saving\n\
+         # the app, edit it here or via its Extra Nix option.\n\
+         {{\n  config,\n  lib,\n  pkgs,\n  ...\n{}\n",
+        app, BODY_START
+    );
+    for line in body.trim_end().lines() {
+        if line.trim().is_empty() {
+            res.push('\n');
+        } else {
+            res.push_str(&format!("  {}\n", line));
+        }
+    }
+    res.push_str("}\n");
+
+    res
+}
+
+/// Extracts the body of a module written by [render]
+///
+/// # Returns
+/// None if the file doesn't have the expected shape
+fn parse(contents: &str) -> Option<String> {
+    let lines: Vec<&str> = contents.trim_end().lines().collect();
+    let start = lines.iter().position(|l| l.trim_end() == BODY_START)?;
+    if lines.last()?.trim_end() != "}" || start + 1 > lines.len() - 1 {
+        return None;
+    }
+
+    let body: Vec<&str> = lines[start + 1..lines.len() - 1]
+        .iter()
+        .map(|l| l.strip_prefix("  ").unwrap_or(l))
+        .collect();
+
+    Some(body.join("\n"))
+}
+
+/// Builds the extra nix option of an app from its file
+///
+/// # Returns
+/// None if the file was rewritten by hand in a shape we don't recognize
+pub fn option(work_dir: &Path, app: SupportedApps) -> Option<CodeOptionData> {
+    let path = work_dir.join(file_name(app));
+    if path.exists() {
+        option_from_contents(app, Some(&fs::read_to_string(&path).ok()?))
+    } else {
+        option_from_contents(app, None)
+    }
+}
+
+/// Builds the extra nix option of an app from the contents of its file,
+/// `None` if the file doesn't exist
+pub fn option_from_contents(app: SupportedApps, contents: Option<&str>) -> Option<CodeOptionData> {
+    let body = match contents {
+        Some(contents) => parse(contents)?,
+        None => String::new(),
+    };
+
+    Some(CodeOptionData::new(
+        option_id(app),
+        body.clone(),
+        MAX_LINES,
+        false,
+        body,
+        CodeLanguage::Nix,
+    ))
+}
+
+/// Writes the extra nix file of an app
+///
+/// The body must have been checked with [CodeLanguage::Nix] before.
+pub fn save(work_dir: &Path, app: SupportedApps, body: &str) -> Result<(), ProjectError> {
+    let path = work_dir.join(file_name(app));
+    create_file(&path, render(app, body).as_bytes(), Some(true))
+        .attach_printable_lazy(|| format!("Unable to write {}", path.display()))
+}
+
+/// Creates an empty extra nix file for the app unless one exists
+pub(crate) fn create_default(work_dir: &Path, app: SupportedApps) -> Result<(), ProjectError> {
+    if work_dir.join(file_name(app)).exists() {
+        return Ok(());
+    }
+
+    save(work_dir, app, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(
+            file_name(SupportedApps::LND),
+            PathBuf::from("src/apps/lnd.extra.nix")
+        );
+        assert_eq!(
+            file_name(SupportedApps::NixOS),
+            PathBuf::from("src/configuration.common.extra.nix")
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempdir().unwrap();
+        let work_dir = dir.path();
+        let app = SupportedApps::LND;
+
+        assert_eq!(option(work_dir, app).unwrap().value(), "");
+        create_default(work_dir, app).unwrap();
+        assert_eq!(option(work_dir, app).unwrap().value(), "");
+
+        let body = "systemd.services.lnd.serviceConfig = {\n  Nice = 5;\n};\n\nusers.users.lnd.extraGroups = [\"video\"];";
+        save(work_dir, app, body).unwrap();
+        assert_eq!(option(work_dir, app).unwrap().value(), body);
+
+        let contents = fs::read_to_string(work_dir.join(file_name(app))).unwrap();
+        assert!(contents.contains("\n    Nice = 5;\n"));
+        assert!(CodeLanguage::Nix.lint(body).is_empty());
+
+        // a file written in another shape is left to the user
+        fs::write(work_dir.join(file_name(app)), "{...}: {}\n").unwrap();
+        assert!(option(work_dir, app).is_none());
+        create_default(work_dir, app).unwrap();
+        assert!(option(work_dir, app).is_none());
+    }
+}
@@ -0,0 +1,252 @@
+//! Resets a project to the state of a fresh install, e.g. before the
+//! hardware is repurposed or sold.
+//!
+//! The optional apps are disabled and the options of all apps return to their
+//! defaults. The Nix OS settings are kept, so the node stays reachable with the
+//! current login. The data of the apps is only wiped on request.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::{
+    activation,
+    app_option_data::option_data::{GetOptionId, OptionData},
+    apps::SupportedApps,
+    dependencies,
+    errors::ProjectError,
+    extra_nix,
+    project::Project,
+    registry, reverse_proxy, secret_store,
+    validation::{normalize_path, DATA_DIR_OPTIONS},
+};
+
+/// The commit message of the reset project files
+const COMMIT_MESSAGE: &str = "Factory reset";
+
+/// Directories holding more than the data of a single app
+const PROTECTED_DIRS: [&str; 6] = [
+    "/var/lib",
+    "/var/log",
+    "/var/cache",
+    "/nix/store",
+    "/nix/var",
+    "/etc/nixos",
+];
+
+/// What [reset] changes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResetPlan {
+    /// The optional apps that are enabled now
+    pub enabled_apps: Vec<SupportedApps>,
+
+    /// The data directories of all apps, with the variables resolved
+    pub data_dirs: Vec<PathBuf>,
+}
+
+/// Collects what a reset of the project in the work dir changes
+pub fn plan(work_dir: &Path) -> Result<ResetPlan, ProjectError> {
+    let project = Project::load(work_dir.to_path_buf())?;
+    let vars = project.variables();
+    let project_dir = work_dir
+        .canonicalize()
+        .unwrap_or_else(|_| work_dir.to_path_buf());
+
+    let enabled_apps = project
+        .enabled_apps()
+        .into_iter()
+        .filter(|a| dependencies::enable_option_id(*a).is_some())
+        .collect();
+
+    let mut data_dirs: Vec<PathBuf> = registry::APPS
+        .iter()
+        .filter(|r| r.app != SupportedApps::NixOS)
+        .flat_map(|r| project.get_options_for(r.app))
+        .filter_map(|o| match o {
+            OptionData::TextEdit(t) if DATA_DIR_OPTIONS.contains(&t.id().option.as_str()) => {
+                Some(normalize_path(&vars.substitute(t.value())))
+            }
+            _ => None,
+        })
+        .filter(|d| is_wipeable(d))
+        .map(PathBuf::from)
+        // keep the project, even if it lives in a data dir
+        .filter(|d| !project_dir.starts_with(d))
+        .collect();
+    data_dirs.sort();
+    data_dirs.dedup();
+
+    Ok(ResetPlan {
+        enabled_apps,
+        data_dirs,
+    })
+}
+
+/// Only absolute directories below the top level are wiped, a typo like "/"
+/// or "/var/lib" must never remove the system.
+fn is_wipeable(dir: &str) -> bool {
+    dir.starts_with('/')
+        && !dir.split('/').any(|c| c == "..")
+        && dir.split('/').filter(|c| !c.is_empty()).count() >= 2
+        && !PROTECTED_DIRS.contains(&dir)
+}
+
+/// Resets the project files to the defaults and commits them if the work dir
+/// is a git repository.
+///
+/// # Arguments
+///
+/// * `wipe_data` - Stops the services of the enabled apps and removes the
+///   [data dirs](ResetPlan::data_dirs). Only works on the node as root.
+///
+/// # Errors
+///
+/// * [ProjectError::RebuildInProgress] if a nixos-rebuild is running
+/// * [ProjectError::FactoryResetError] if a step failed. The files reset
+///   before stay reset, running the reset again finishes it.
+pub fn reset(work_dir: &Path, plan: &ResetPlan, wipe_data: bool) -> Result<(), ProjectError> {
+    activation::ensure_idle()?;
+
+    for reg in registry::APPS
+        .iter()
+        .filter(|r| r.app != SupportedApps::NixOS)
+    {
+        registry::create_default_files(reg.app, work_dir, Some(true))?;
+        extra_nix::save(work_dir, reg.app, "")?;
+    }
+
+    reverse_proxy::create_default_file(work_dir, Some(true))?;
+    secret_store::create_default_file(work_dir, Some(true))?;
+    let secrets = work_dir.join(secret_store::SECRETS_DIR);
+    if secrets.exists() {
+        fs::remove_dir_all(&secrets)
+            .change_context(ProjectError::FactoryResetError)
+            .attach_printable_lazy(|| format!("Unable to remove {}", secrets.display()))?;
+    }
+
+    if wipe_data {
+        stop_units(&plan.enabled_apps)?;
+        for dir in plan.data_dirs.iter().filter(|d| d.exists()) {
+            fs::remove_dir_all(dir)
+                .change_context(ProjectError::FactoryResetError)
+                .attach_printable_lazy(|| format!("Unable to remove {}", dir.display()))?;
+        }
+    }
+
+    if work_dir.join(".git").exists() {
+        git(work_dir, &["add", "--all"])?;
+        git(work_dir, &["commit", "--allow-empty", "-m", COMMIT_MESSAGE])?;
+    }
+
+    Ok(())
+}
+
+/// Stops the systemd units of the given apps, so their data can be removed
+fn stop_units(apps: &[SupportedApps]) -> Result<(), ProjectError> {
+    let units: Vec<&str> = apps
+        .iter()
+        .flat_map(|a| registry::registration(*a).units.iter().copied())
+        .collect();
+    if units.is_empty() {
+        return Ok(());
+    }
+
+    let output = Command::new("systemctl")
+        .arg("stop")
+        .args(&units)
+        .output()
+        .change_context(ProjectError::FactoryResetError)
+        .attach_printable("Unable to run systemctl")?;
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::FactoryResetError).attach_printable(format!(
+                "Unable to stop {}: {}",
+                units.join(", "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        );
+    }
+
+    Ok(())
+}
+
+fn git(work_dir: &Path, args: &[&str]) -> Result<(), ProjectError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(work_dir)
+        .args(args)
+        .output()
+        .change_context(ProjectError::FactoryResetError)
+        .attach_printable("Unable to run git")?;
+
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::FactoryResetError).attach_printable(format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::{
+            bool_data::BoolOptionChangeData,
+            option_data::{OptionDataChangeNotification, ToOptionId},
+        },
+        lnd::LndConfigOption,
+        utils::init_default_project,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_is_wipeable() {
+        assert!(is_wipeable("/var/lib/bitcoind"));
+        assert!(is_wipeable("/mnt/hdd/lnd"));
+        assert!(!is_wipeable("/"));
+        assert!(!is_wipeable("/var"));
+        assert!(!is_wipeable("/var/lib"));
+        assert!(!is_wipeable("/var/lib/../.."));
+        assert!(!is_wipeable("var/lib/lnd"));
+        assert!(!is_wipeable(""));
+    }
+
+    #[test]
+    fn test_plan_and_reset() {
+        let dir = tempdir().unwrap();
+        let work_dir = dir.path();
+        init_default_project(work_dir, Some(false)).unwrap();
+
+        let mut project = Project::load(work_dir.to_path_buf()).unwrap();
+        project.set_selected_app(SupportedApps::LND);
+        project
+            .on_option_changed(OptionDataChangeNotification::Bool(
+                BoolOptionChangeData::new(LndConfigOption::Enable.to_option_id(), true),
+            ))
+            .unwrap();
+
+        let plan = plan(work_dir).unwrap();
+        assert!(plan.enabled_apps.contains(&SupportedApps::LND));
+        assert!(plan.enabled_apps.contains(&SupportedApps::BitcoinCore));
+        assert!(!plan.enabled_apps.contains(&SupportedApps::NixOS));
+        assert!(plan.data_dirs.contains(&PathBuf::from("/var/lib/lnd")));
+
+        // never wipe the data of the test host
+        reset(work_dir, &plan, false).unwrap();
+        let project = Project::load(work_dir.to_path_buf()).unwrap();
+        assert!(!project.is_app_enabled(SupportedApps::LND));
+        assert!(!project.is_app_enabled(SupportedApps::BitcoinCore));
+    }
+}
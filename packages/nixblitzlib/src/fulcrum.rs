@@ -0,0 +1,421 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_option_data::{
+        bool_data::BoolOptionData,
+        net_address_data::NetAddressOptionData,
+        option_data::{
+            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToNixString,
+            ToOptionId,
+        },
+        port_data::PortOptionData,
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    utils::{escape_nix_indented_string, update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/fulcrum.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/fulcrum.json";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FulcrumService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// Address to listen for TCP and SSL connections
+    pub address: Box<NetAddressOptionData>,
+
+    /// Port to listen for TCP connections
+    ///
+    /// Default: 50001
+    pub port: Box<PortOptionData>,
+
+    /// Port to listen for SSL connections.
+    /// SSL is only enabled if the certificate and the key are set.
+    ///
+    /// Default: 50002
+    pub ssl_port: Box<PortOptionData>,
+
+    /// Path to the PEM encoded SSL certificate
+    pub cert_path: Box<TextOptionData>,
+
+    /// Path to the PEM encoded private key of the SSL certificate
+    pub key_path: Box<TextOptionData>,
+
+    /// The directory where Fulcrum stores its database
+    ///
+    /// Default: "/var/lib/fulcrum"
+    pub db_dir: Box<TextOptionData>,
+
+    /// Text shown to Electrum clients when they connect
+    pub banner: Box<TextOptionData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FulcrumConfigOption {
+    Enable,
+    Address,
+    Port,
+    SslPort,
+    CertPath,
+    KeyPath,
+    DbDir,
+    Banner,
+}
+
+impl ToOptionId for FulcrumConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::Fulcrum, self.to_string())
+    }
+}
+
+impl FromStr for FulcrumConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<FulcrumConfigOption, ()> {
+        match s {
+            "enable" => Ok(FulcrumConfigOption::Enable),
+            "address" => Ok(FulcrumConfigOption::Address),
+            "port" => Ok(FulcrumConfigOption::Port),
+            "ssl_port" => Ok(FulcrumConfigOption::SslPort),
+            "cert_path" => Ok(FulcrumConfigOption::CertPath),
+            "key_path" => Ok(FulcrumConfigOption::KeyPath),
+            "db_dir" => Ok(FulcrumConfigOption::DbDir),
+            "banner" => Ok(FulcrumConfigOption::Banner),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for FulcrumConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            FulcrumConfigOption::Enable => "enable",
+            FulcrumConfigOption::Address => "address",
+            FulcrumConfigOption::Port => "port",
+            FulcrumConfigOption::SslPort => "ssl_port",
+            FulcrumConfigOption::CertPath => "cert_path",
+            FulcrumConfigOption::KeyPath => "key_path",
+            FulcrumConfigOption::DbDir => "db_dir",
+            FulcrumConfigOption::Banner => "banner",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for FulcrumService {
+    fn get_options(&self) -> Vec<OptionData> {
+        vec![
+            OptionData::Bool(self.enable.clone()),
+            OptionData::NetAddress(self.address.clone()),
+            OptionData::Port(self.port.clone()),
+            OptionData::Port(self.ssl_port.clone()),
+            OptionData::TextEdit(self.cert_path.clone()),
+            OptionData::TextEdit(self.key_path.clone()),
+            OptionData::TextEdit(self.db_dir.clone()),
+            OptionData::TextEdit(self.banner.clone()),
+        ]
+    }
+
+    fn app_option_changed(
+        &mut self,
+        option: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let id = option.id();
+        if let Ok(opt) = FulcrumConfigOption::from_str(&id.option) {
+            let mut res = Ok(false);
+            match opt {
+                FulcrumConfigOption::Enable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.enable.value() != val.value);
+                        self.enable.set_value(val.value);
+                    }
+                }
+                FulcrumConfigOption::Address => {
+                    if let OptionDataChangeNotification::NetAddress(val) = option {
+                        res = Ok(self.address.value() != val.value);
+                        self.address.set_value(val.value);
+                    }
+                }
+                FulcrumConfigOption::Port => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.port.value() != val.value);
+                        self.port.set_value(val.value.clone());
+                    }
+                }
+                FulcrumConfigOption::SslPort => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.ssl_port.value() != val.value);
+                        self.ssl_port.set_value(val.value.clone());
+                    }
+                }
+                FulcrumConfigOption::CertPath => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.cert_path.value() != val.value);
+                        self.cert_path.set_value(val.value.clone());
+                    }
+                }
+                FulcrumConfigOption::KeyPath => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.key_path.value() != val.value);
+                        self.key_path.set_value(val.value.clone());
+                    }
+                }
+                FulcrumConfigOption::DbDir => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.db_dir.value() != val.value);
+                        self.db_dir.set_value(val.value.clone());
+                    }
+                }
+                FulcrumConfigOption::Banner => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.banner.value() != val.value);
+                        self.banner.set_value(val.value.clone());
+                    }
+                }
+            }
+
+            return res;
+        }
+
+        Ok(false)
+    }
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering fulcrum config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Default for FulcrumService {
+    fn default() -> Self {
+        let text_option = |opt: FulcrumConfigOption, value: &str, max_lines: u16| {
+            Box::new(TextOptionData::new(
+                opt.to_option_id(),
+                value.to_string(),
+                max_lines,
+                false,
+                value.to_string(),
+            ))
+        };
+
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                FulcrumConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            address: Box::new(NetAddressOptionData::new(
+                FulcrumConfigOption::Address.to_option_id(),
+                Some(defaults::LOCALHOST),
+            )),
+            port: Box::new(PortOptionData::new(
+                FulcrumConfigOption::Port.to_option_id(),
+                NumberValue::U16(Some(defaults::fulcrum::PORT)),
+            )),
+            ssl_port: Box::new(PortOptionData::new(
+                FulcrumConfigOption::SslPort.to_option_id(),
+                NumberValue::U16(Some(defaults::fulcrum::SSL_PORT)),
+            )),
+            cert_path: text_option(FulcrumConfigOption::CertPath, "", 1),
+            key_path: text_option(FulcrumConfigOption::KeyPath, "", 1),
+            db_dir: text_option(FulcrumConfigOption::DbDir, defaults::fulcrum::DB_DIR, 1),
+            banner: text_option(FulcrumConfigOption::Banner, "", 10),
+        }
+    }
+}
+
+impl FulcrumService {
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let banner = self.banner.value().trim();
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            ("address", self.address.to_nix_string(true)),
+            (
+                "port",
+                self.port
+                    .value()
+                    .to_string_or(&defaults::fulcrum::PORT.to_string()),
+            ),
+            ("db_dir", self.db_dir.value().to_string()),
+            ("ssl_config", self.ssl_config()),
+            ("banner", escape_nix_indented_string(banner)),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render fulcrum template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    /// Builds the Fulcrum config lines of the SSL listener.
+    /// Empty unless the port, the certificate and the key are set.
+    fn ssl_config(&self) -> String {
+        let port = self.ssl_port.value().to_string();
+        let cert = self.cert_path.value().trim();
+        let key = self.key_path.value().trim();
+        if port.is_empty() || cert.is_empty() || key.is_empty() {
+            return String::new();
+        }
+
+        let address = self.address.value().unwrap_or(defaults::LOCALHOST);
+        [
+            format!("ssl = {}:{}", address, port),
+            format!("cert = {}", cert),
+            format!("key = {}", key),
+        ]
+        .join("\n      ")
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<FulcrumService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::utils::init_default_project;
+
+    use super::*;
+
+    fn get_test_service() -> FulcrumService {
+        let mut service = FulcrumService::default();
+        service.enable.set_value(true);
+        service.cert_path.set_value("/etc/fulcrum/cert.pem".into());
+        service.key_path.set_value("/etc/fulcrum/key.pem".into());
+        service.banner.set_value("Welcome to my node!".into());
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = FulcrumService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_ssl_requires_cert_and_key() {
+        let mut service = get_test_service();
+        assert!(service.ssl_config().starts_with("ssl = 127.0.0.1:50002"));
+
+        service.key_path.set_value("".into());
+        assert!(service.ssl_config().is_empty());
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains(&format!("enable = {};", s.enable.value())));
+            assert!(data.contains(&format!(
+                "address = lib.mkDefault {};",
+                s.address.to_nix_string(true)
+            )));
+            assert!(data.contains(&format!("port = {};", s.port.value())));
+            assert!(data.contains(&format!("dataDir = \"{}\";", s.db_dir.value())));
+            assert!(data.contains("cert = /etc/fulcrum/cert.pem"));
+            assert!(data.contains("Welcome to my node!"));
+        }
+
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,251 @@
+//! Lists the system generations and rolls the node back to one of them.
+//!
+//! Every switch creates a new generation of the system profile. `nixblitz
+//! apply` records which generation it created from which commit of the
+//! project, see [apply_history]. A rollback switches to an older generation
+//! and checks out the project files of the matching commit, so the project
+//! describes what the node runs again. The files show up as uncommitted
+//! changes, like a restored backup. Generations that weren't created by
+//! `nixblitz apply` can be switched to, but leave the project alone.
+
+use std::{fs, path::Path, process::Command};
+
+use error_stack::{Report, Result, ResultExt};
+use serde::Serialize;
+
+use crate::{activation::ensure_idle, apply_history, errors::ProjectError};
+
+/// The profile of the system, its links are named `system-<N>-link`
+pub const SYSTEM_PROFILE: &str = "/nix/var/nix/profiles/system";
+
+/// A generation of the system profile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Generation {
+    pub number: u64,
+
+    /// When the generation was created, as printed by nix-env
+    pub created: String,
+
+    /// Whether the node runs this generation
+    pub current: bool,
+
+    /// The commit of the project it was applied from, None if it wasn't
+    /// created by `nixblitz apply`
+    pub commit: Option<String>,
+}
+
+/// Lists the generations of the system, oldest first
+///
+/// # Errors
+/// [ProjectError::RollbackError] if nix-env can't list them
+pub fn list_generations(work_dir: &Path) -> Result<Vec<Generation>, ProjectError> {
+    let output = Command::new("nix-env")
+        .args(["--list-generations", "--profile", SYSTEM_PROFILE])
+        .output()
+        .change_context(ProjectError::RollbackError)
+        .attach_printable("Unable to run nix-env, is this a NixOS system?")?;
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::RollbackError).attach_printable(format!(
+                "nix-env --list-generations failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        );
+    }
+
+    let records = apply_history::load(work_dir);
+    let mut generations = parse_generations(&String::from_utf8_lossy(&output.stdout));
+    for g in generations.iter_mut() {
+        // the newest apply wins if a generation number was reused
+        g.commit = records
+            .iter()
+            .rev()
+            .find(|r| r.success && r.generation == Some(g.number))
+            .and_then(|r| r.commit.clone());
+    }
+
+    Ok(generations)
+}
+
+/// Parses the `<N>   <date> <time>   (current)` lines of nix-env
+fn parse_generations(output: &str) -> Vec<Generation> {
+    output
+        .lines()
+        .filter_map(|l| {
+            let mut parts = l.split_whitespace();
+            let number = parts.next()?.parse().ok()?;
+            let rest: Vec<&str> = parts.collect();
+            let current = rest.last() == Some(&"(current)");
+            let created = rest
+                .iter()
+                .filter(|p| **p != "(current)")
+                .copied()
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            Some(Generation {
+                number,
+                created,
+                current,
+                commit: None,
+            })
+        })
+        .collect()
+}
+
+/// Returns the generation the system profile points to, e.g. 42 for
+/// `system-42-link`
+pub fn current_generation() -> Option<u64> {
+    let link = fs::read_link(SYSTEM_PROFILE).ok()?;
+    parse_profile_link(&link.to_string_lossy())
+}
+
+/// Returns the commit the project has checked out, None if it has none
+pub fn project_commit(work_dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(work_dir)
+        .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn parse_profile_link(link: &str) -> Option<u64> {
+    link.rsplit('/')
+        .next()?
+        .strip_prefix("system-")?
+        .strip_suffix("-link")?
+        .parse()
+        .ok()
+}
+
+/// Switches the node to an older generation and checks out the project
+/// files it was applied from
+///
+/// # Arguments
+///
+/// * `generation` - The generation to switch to, the one before the current
+///   if None
+///
+/// # Returns
+/// The generation the node runs now
+///
+/// # Errors
+/// [ProjectError::RebuildInProgress] if another rebuild is running,
+/// [ProjectError::RollbackError] if the generation doesn't exist or the
+/// switch failed
+pub fn rollback(work_dir: &Path, generation: Option<u64>) -> Result<Generation, ProjectError> {
+    let generations = list_generations(work_dir)?;
+    let target = match generation {
+        Some(n) => generations.iter().find(|g| g.number == n),
+        None => generations.iter().rev().skip_while(|g| !g.current).nth(1),
+    }
+    .cloned()
+    .ok_or_else(|| {
+        Report::new(ProjectError::RollbackError).attach_printable(match generation {
+            Some(n) => format!("Generation {} doesn't exist, see nixblitz generations", n),
+            None => "There is no generation before the current one".to_string(),
+        })
+    })?;
+    if target.current {
+        return Err(
+            Report::new(ProjectError::RollbackError).attach_printable(format!(
+                "The node already runs generation {}",
+                target.number
+            )),
+        );
+    }
+
+    ensure_idle()?;
+    run(
+        "nix-env",
+        &[
+            "--switch-generation",
+            &target.number.to_string(),
+            "--profile",
+            SYSTEM_PROFILE,
+        ],
+    )?;
+    run(
+        &format!("{}/bin/switch-to-configuration", SYSTEM_PROFILE),
+        &["switch"],
+    )?;
+
+    if let Some(commit) = &target.commit {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(work_dir)
+            .args(["checkout", commit, "--", "."])
+            .output()
+            .change_context(ProjectError::RollbackError)
+            .attach_printable("Unable to run git")?;
+        if !output.status.success() {
+            return Err(
+                Report::new(ProjectError::RollbackError).attach_printable(format!(
+                    "The node was rolled back, but the project files of {} can't be checked \
+                     out: {}",
+                    commit,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )),
+            );
+        }
+    }
+
+    Ok(Generation {
+        current: true,
+        ..target
+    })
+}
+
+fn run(program: &str, args: &[&str]) -> Result<(), ProjectError> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .change_context(ProjectError::RollbackError)
+        .attach_printable_lazy(|| format!("Unable to run {}", program))?;
+    if !status.success() {
+        return Err(
+            Report::new(ProjectError::RollbackError).attach_printable(format!(
+                "{} {} exited with {}",
+                program,
+                args.join(" "),
+                status
+            )),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_generations() {
+        let output = "  41   2024-11-01 10:12:03   \n  42   2024-11-03 04:12:09   (current)\n";
+        let generations = parse_generations(output);
+        assert_eq!(generations.len(), 2);
+        assert_eq!(generations[0].number, 41);
+        assert_eq!(generations[0].created, "2024-11-01 10:12:03");
+        assert!(!generations[0].current);
+        assert!(generations[1].current);
+
+        assert!(parse_generations("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_profile_link() {
+        assert_eq!(parse_profile_link("system-42-link"), Some(42));
+        assert_eq!(
+            parse_profile_link("/nix/var/nix/profiles/system-7-link"),
+            Some(7)
+        );
+        assert_eq!(parse_profile_link("/nix/store/abc-nixos-system"), None);
+    }
+}
@@ -0,0 +1,487 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_option_data::{
+        bool_data::BoolOptionData,
+        option_data::{
+            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToOptionId,
+        },
+        password_data::PasswordOptionData,
+        port_data::PortOptionData,
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    monitoring::MonitoringService,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    reverse_proxy::{ProxyRoute, ProxyRouteProvider},
+    utils::{
+        check_password_validity_confirm, escape_nix_indented_string, update_file, update_nix_files,
+        BASE_TEMPLATE,
+    },
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/grafana.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/grafana.json";
+
+/// Grafana with the Prometheus of the [MonitoringService] as data source.
+///
+/// Dashboards for the host, the bitcoind sync and the Lightning channels
+/// are provisioned from src/apps/grafana/dashboards.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GrafanaService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// Port the web interface listens on
+    ///
+    /// Default: 3001
+    pub port: Box<PortOptionData>,
+
+    /// The password of the admin user.
+    /// Grafana only applies it when it creates its database.
+    pub admin_password: Box<PasswordOptionData>,
+
+    /// Whether to serve Grafana via nginx
+    pub nginx_enable: Box<BoolOptionData>,
+
+    /// The location Grafana is served from
+    ///
+    /// Default: "/grafana"
+    pub nginx_location: Box<TextOptionData>,
+
+    /// Whether to serve the location via HTTPS, see the TLS options of the
+    /// base config
+    pub nginx_https: Box<BoolOptionData>,
+
+    /// The URL of the Prometheus data source.
+    /// Derived from the monitoring config.
+    #[serde(default)]
+    pub prometheus_url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GrafanaConfigOption {
+    Enable,
+    Port,
+    AdminPassword,
+    NginxEnable,
+    NginxLocation,
+    NginxHttps,
+}
+
+impl ToOptionId for GrafanaConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::Grafana, self.to_string())
+    }
+}
+
+impl FromStr for GrafanaConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<GrafanaConfigOption, ()> {
+        match s {
+            "enable" => Ok(GrafanaConfigOption::Enable),
+            "port" => Ok(GrafanaConfigOption::Port),
+            "admin_password" => Ok(GrafanaConfigOption::AdminPassword),
+            "nginx_enable" => Ok(GrafanaConfigOption::NginxEnable),
+            "nginx_location" => Ok(GrafanaConfigOption::NginxLocation),
+            "nginx_https" => Ok(GrafanaConfigOption::NginxHttps),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for GrafanaConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            GrafanaConfigOption::Enable => "enable",
+            GrafanaConfigOption::Port => "port",
+            GrafanaConfigOption::AdminPassword => "admin_password",
+            GrafanaConfigOption::NginxEnable => "nginx_enable",
+            GrafanaConfigOption::NginxLocation => "nginx_location",
+            GrafanaConfigOption::NginxHttps => "nginx_https",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for GrafanaService {
+    fn get_options(&self) -> Vec<OptionData> {
+        vec![
+            OptionData::Bool(self.enable.clone()),
+            OptionData::Port(self.port.clone()),
+            OptionData::PasswordEdit(self.admin_password.clone()),
+            OptionData::Bool(self.nginx_enable.clone()),
+            OptionData::TextEdit(self.nginx_location.clone()),
+            OptionData::Bool(self.nginx_https.clone()),
+        ]
+    }
+
+    fn app_option_changed(
+        &mut self,
+        option: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let id = option.id();
+        if let Ok(opt) = GrafanaConfigOption::from_str(&id.option) {
+            let mut res = Ok(false);
+            match opt {
+                GrafanaConfigOption::Enable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.enable.value() != val.value);
+                        self.enable.set_value(val.value);
+                    }
+                }
+                GrafanaConfigOption::Port => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.port.value() != val.value);
+                        self.port.set_value(val.value.clone());
+                    }
+                }
+                GrafanaConfigOption::AdminPassword => {
+                    if let OptionDataChangeNotification::PasswordEdit(val) = option {
+                        // Invalid passwords are ignored, see NixBaseConfig
+                        if check_password_validity_confirm(&val.value, &val.confirm).is_err() {
+                            return Ok(false);
+                        }
+
+                        res = Ok(*self.admin_password.hashed_value() != val.value);
+                        self.admin_password.set_hashed_value(val.value.clone());
+                    }
+                }
+                GrafanaConfigOption::NginxEnable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.nginx_enable.value() != val.value);
+                        self.nginx_enable.set_value(val.value);
+                    }
+                }
+                GrafanaConfigOption::NginxLocation => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.nginx_location.value() != val.value);
+                        self.nginx_location.set_value(val.value.clone());
+                    }
+                }
+                GrafanaConfigOption::NginxHttps => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.nginx_https.value() != val.value);
+                        self.nginx_https.set_value(val.value);
+                    }
+                }
+            }
+
+            return res;
+        }
+
+        Ok(false)
+    }
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering grafana config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+
+    fn secret_options(&mut self) -> Vec<(&mut PasswordOptionData, &'static str)> {
+        vec![(&mut self.admin_password, "grafana")]
+    }
+}
+
+impl Default for GrafanaService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                GrafanaConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            port: Box::new(PortOptionData::new(
+                GrafanaConfigOption::Port.to_option_id(),
+                NumberValue::U16(Some(defaults::grafana::PORT)),
+            )),
+            admin_password: Box::new(PasswordOptionData::new(
+                GrafanaConfigOption::AdminPassword.to_option_id(),
+                "".to_string(),
+                true,
+                10,
+                false,
+                "".to_string(),
+            )),
+            nginx_enable: Box::new(BoolOptionData::new(
+                GrafanaConfigOption::NginxEnable.to_option_id(),
+                false,
+            )),
+            nginx_location: Box::new(TextOptionData::new(
+                GrafanaConfigOption::NginxLocation.to_option_id(),
+                defaults::grafana::NGINX_LOCATION.to_string(),
+                1,
+                false,
+                defaults::grafana::NGINX_LOCATION.to_string(),
+            )),
+            nginx_https: Box::new(BoolOptionData::new(
+                GrafanaConfigOption::NginxHttps.to_option_id(),
+                false,
+            )),
+            prometheus_url: format!(
+                "http://{}:{}",
+                defaults::LOCALHOST,
+                defaults::monitoring::PORT
+            ),
+        }
+    }
+}
+
+impl ProxyRouteProvider for GrafanaService {
+    fn proxy_route(&self) -> Option<ProxyRoute> {
+        if !self.enable.value() || !self.nginx_enable.value() {
+            return None;
+        }
+
+        let port = self
+            .port
+            .value()
+            .to_string_or(&defaults::grafana::PORT.to_string());
+
+        // Grafana live pushes dashboard updates via websockets
+        Some(ProxyRoute::new(
+            SupportedApps::Grafana,
+            GrafanaConfigOption::NginxEnable.to_option_id(),
+            self.nginx_location.value(),
+            Some(format!("http://127.0.0.1:{}", port)),
+            true,
+            self.nginx_https.value(),
+        ))
+    }
+}
+
+impl GrafanaService {
+    /// Derives the Prometheus data source from the monitoring config.
+    ///
+    /// # Returns
+    /// Whether the URL changed
+    pub fn sync_with_monitoring(&mut self, monitoring: &MonitoringService) -> bool {
+        let port = monitoring
+            .port
+            .value()
+            .to_string_or(&defaults::monitoring::PORT.to_string());
+        let url = format!("http://{}:{}", defaults::LOCALHOST, port);
+
+        let changed = self.prometheus_url != url;
+        self.prometheus_url = url;
+
+        changed
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let port = self
+            .port
+            .value()
+            .to_string_or(&defaults::grafana::PORT.to_string());
+        // Behind nginx Grafana only listens locally and has to know the sub
+        // path it is served from
+        let (http_addr, sub_path) = if self.nginx_enable.value() {
+            (
+                defaults::LOCALHOST.to_string(),
+                self.nginx_location
+                    .value()
+                    .trim_end_matches('/')
+                    .to_string(),
+            )
+        } else {
+            ("0.0.0.0".to_string(), "".to_string())
+        };
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            ("http_addr", http_addr),
+            ("port", port),
+            ("sub_path", sub_path),
+            (
+                "admin_password",
+                escape_nix_indented_string(self.admin_password.hashed_value()),
+            ),
+            (
+                "admin_password_file",
+                self.admin_password
+                    .secret()
+                    .and_then(|s| s.nix_path())
+                    .map(|p| format!("\"$__file{{${{{}}}}}\"", p))
+                    .unwrap_or_default(),
+            ),
+            ("prometheus_url", self.prometheus_url.clone()),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render grafana template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<GrafanaService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::password_data::PasswordOptionChangeData, utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn get_test_service() -> GrafanaService {
+        let mut service = GrafanaService::default();
+        service.enable.set_value(true);
+        service.nginx_enable.set_value(true);
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+
+        assert!(work_dir
+            .join("src/apps/grafana/dashboards/host.json")
+            .exists());
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = GrafanaService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_admin_password() {
+        let mut service = get_test_service();
+        let change = |value: &str, confirm: &str| {
+            OptionDataChangeNotification::PasswordEdit(PasswordOptionChangeData::new(
+                GrafanaConfigOption::AdminPassword.to_option_id(),
+                value.into(),
+                Some(confirm.into()),
+            ))
+        };
+
+        assert!(!service
+            .app_option_changed(&change("my_secret_password", "other_password"))
+            .unwrap());
+        assert!(service
+            .app_option_changed(&change("my_secret_password", "my_secret_password"))
+            .unwrap());
+        assert_eq!(service.admin_password.hashed_value(), "my_secret_password");
+    }
+
+    #[test]
+    fn test_sync_with_monitoring() {
+        let mut service = get_test_service();
+        let mut monitoring = MonitoringService::default();
+        assert!(!service.sync_with_monitoring(&monitoring));
+
+        monitoring.port.set_value(NumberValue::U16(Some(9091)));
+        assert!(service.sync_with_monitoring(&monitoring));
+        assert_eq!(service.prometheus_url, "http://127.0.0.1:9091");
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains("http_addr = \"127.0.0.1\";"));
+            assert!(data.contains("serve_from_sub_path = true;"));
+            assert!(data.contains("/grafana/\";"));
+            assert!(data.contains(&format!("url = \"{}\";", s.prometheus_url)));
+            assert!(!data.contains("admin_password"));
+        }
+
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,308 @@
+use core::fmt;
+use std::{collections::HashMap, net::SocketAddr, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::{app_options, AppConfig},
+    app_option_data::{
+        bool_data::BoolOptionData,
+        net_address_data::NetAddressOptionData,
+        number_data::NumberOptionData,
+        option_data::{OptionId, ToOptionId},
+        port_data::PortOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    tor::port_or,
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/i2p.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/i2p.json";
+
+/// Runs the i2pd router so bitcoind can connect to peers over I2P.
+///
+/// bitcoind talks to the router via its SAM bridge. Enabling the app
+/// hands the SAM address to bitcoind, see
+/// [BitcoinDaemonService::sync_with_i2p](crate::bitcoind::BitcoinDaemonService::sync_with_i2p).
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct I2pService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// Address the SAM bridge listens on
+    pub sam_address: Box<NetAddressOptionData>,
+
+    /// Port the SAM bridge listens on
+    pub sam_port: Box<PortOptionData>,
+
+    /// Bandwidth the router may use in KB/s, shared with the I2P network
+    pub bandwidth: Box<NumberOptionData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum I2pConfigOption {
+    Enable,
+    SamAddress,
+    SamPort,
+    Bandwidth,
+}
+
+impl ToOptionId for I2pConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::I2P, self.to_string())
+    }
+}
+
+impl FromStr for I2pConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<I2pConfigOption, ()> {
+        match s {
+            "enable" => Ok(I2pConfigOption::Enable),
+            "sam_address" => Ok(I2pConfigOption::SamAddress),
+            "sam_port" => Ok(I2pConfigOption::SamPort),
+            "bandwidth" => Ok(I2pConfigOption::Bandwidth),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for I2pConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            I2pConfigOption::Enable => "enable",
+            I2pConfigOption::SamAddress => "sam_address",
+            I2pConfigOption::SamPort => "sam_port",
+            I2pConfigOption::Bandwidth => "bandwidth",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for I2pService {
+    app_options!(I2pConfigOption {
+        Enable => enable,
+        SamAddress => sam_address,
+        SamPort => sam_port,
+        Bandwidth => bandwidth,
+    });
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering i2p config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Default for I2pService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                I2pConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            sam_address: Box::new(NetAddressOptionData::new(
+                I2pConfigOption::SamAddress.to_option_id(),
+                Some(defaults::LOCALHOST),
+            )),
+            sam_port: Box::new(PortOptionData::new(
+                I2pConfigOption::SamPort.to_option_id(),
+                NumberValue::U16(Some(defaults::i2p::SAM_PORT)),
+            )),
+            bandwidth: Box::new(
+                NumberOptionData::new(
+                    I2pConfigOption::Bandwidth.to_option_id(),
+                    NumberValue::UInt(Some(defaults::i2p::BANDWIDTH)),
+                    defaults::i2p::MIN_BANDWIDTH,
+                    defaults::i2p::MAX_BANDWIDTH,
+                    false,
+                    NumberValue::UInt(Some(defaults::i2p::BANDWIDTH)),
+                )
+                .unwrap(),
+            ),
+        }
+    }
+}
+
+impl I2pService {
+    /// The address of the SAM bridge, e.g. 127.0.0.1:7656
+    ///
+    /// # Returns
+    /// None if the router is disabled
+    pub fn sam(&self) -> Option<String> {
+        if !self.enable.value() {
+            return None;
+        }
+
+        let ip = self.sam_address.value().unwrap_or(defaults::LOCALHOST);
+        let port = port_or(self.sam_port.value(), defaults::i2p::SAM_PORT);
+        Some(SocketAddr::new(ip, port).to_string())
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            (
+                "sam_address",
+                format!(
+                    "\"{}\"",
+                    self.sam_address.value().unwrap_or(defaults::LOCALHOST)
+                ),
+            ),
+            (
+                "sam_port",
+                port_or(self.sam_port.value(), defaults::i2p::SAM_PORT).to_string(),
+            ),
+            (
+                "bandwidth",
+                self.bandwidth
+                    .value()
+                    .to_string_or(&defaults::i2p::BANDWIDTH.to_string()),
+            ),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render i2p template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<I2pService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::utils::init_default_project;
+
+    use super::*;
+
+    fn get_test_service() -> I2pService {
+        let mut service = I2pService::default();
+        service.enable.set_value(true);
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = I2pService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_sam() {
+        let mut service = I2pService::default();
+        assert_eq!(service.sam(), None);
+
+        service.enable.set_value(true);
+        assert_eq!(service.sam(), Some("127.0.0.1:7656".to_string()));
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains("enable = true;"));
+            assert!(data.contains("port = 7656;"));
+            assert!(data.contains("bandwidth = 256;"));
+        }
+
+        assert!(result.is_ok());
+    }
+}
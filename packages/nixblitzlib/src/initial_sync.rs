@@ -0,0 +1,204 @@
+//! The first sync of the block chain after a fresh install.
+//!
+//! Until Bitcoin Core has downloaded and verified the whole chain most apps
+//! can't do anything useful. Instead of a generic status the node then
+//! reports [NodePhase::SyncingInitialBlockchain] with the progress, an
+//! estimate of when it is done and tips for the wait.
+//!
+//! The estimate is based on the progress since the sync was first seen,
+//! stored in [SAMPLE_FILE]. It is a rough guess, later blocks hold more
+//! transactions and take longer to verify. The file belongs to the machine
+//! and is kept out of git.
+
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use error_stack::{Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    defaults::initial_sync::{MIN_ELAPSED_SECS, RECOMMENDED_DB_CACHE_MIB},
+    errors::ProjectError,
+    status::ChainSync,
+    utils::{create_file, exclude_from_git},
+};
+
+/// Relative to the work dir
+pub const SAMPLE_FILE: &str = ".nixblitz/initial-sync.json";
+
+/// What the node is busy with, for a dashboard to pick a view
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum NodePhase {
+    /// Bitcoin Core is downloading and verifying the block chain for the
+    /// first time
+    SyncingInitialBlockchain {
+        sync: ChainSync,
+
+        /// Seconds until the sync is done, None until enough progress was
+        /// seen to tell
+        eta_secs: Option<u64>,
+
+        /// Hints for the user while waiting
+        tips: Vec<&'static str>,
+    },
+
+    /// Nothing special, the node runs its apps. Also if Bitcoin Core is
+    /// disabled or doesn't answer.
+    Idle,
+}
+
+/// The progress when the sync was first seen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SyncSample {
+    /// Seconds since the unix epoch
+    time: u64,
+
+    /// Between 0 and 1
+    progress: f64,
+}
+
+/// Derives the phase of the node from the sync state of Bitcoin Core
+///
+/// # Arguments
+/// * `sync` - None if Bitcoin Core is disabled or doesn't answer
+/// * `db_cache_mib` - the database cache of Bitcoin Core, None for its default
+///
+/// # Errors
+/// [ProjectError::StatusError] if the [SAMPLE_FILE] can't be written
+pub fn phase(
+    work_dir: &Path,
+    sync: Option<&ChainSync>,
+    db_cache_mib: Option<u16>,
+) -> Result<NodePhase, ProjectError> {
+    let path = work_dir.join(SAMPLE_FILE);
+    let Some(sync) = sync.filter(|s| s.initial_block_download) else {
+        if sync.is_some() && path.exists() {
+            fs::remove_file(&path).change_context(ProjectError::StatusError)?;
+        }
+        return Ok(NodePhase::Idle);
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let current = SyncSample {
+        time: now,
+        progress: sync.progress,
+    };
+    let first = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<SyncSample>(&s).ok())
+        // a reindex starts over
+        .filter(|s| s.progress <= sync.progress);
+    let eta_secs = match &first {
+        Some(first) => estimate(first, &current),
+        None => {
+            let json = serde_json::to_string(&current).change_context(ProjectError::StatusError)?;
+            create_file(&path, json.as_bytes(), Some(true))
+                .change_context(ProjectError::StatusError)?;
+            exclude_from_git(work_dir, SAMPLE_FILE).change_context(ProjectError::StatusError)?;
+            None
+        }
+    };
+
+    Ok(NodePhase::SyncingInitialBlockchain {
+        sync: sync.clone(),
+        eta_secs,
+        tips: tips(sync, db_cache_mib),
+    })
+}
+
+/// Extrapolates the progress since the first sample
+fn estimate(first: &SyncSample, current: &SyncSample) -> Option<u64> {
+    let elapsed = current.time.checked_sub(first.time)?;
+    let done = current.progress - first.progress;
+    if elapsed < MIN_ELAPSED_SECS || done <= 0.0 {
+        return None;
+    }
+
+    let remaining = (1.0 - current.progress).max(0.0);
+    Some((remaining / done * elapsed as f64).round() as u64)
+}
+
+fn tips(sync: &ChainSync, db_cache_mib: Option<u16>) -> Vec<&'static str> {
+    let mut tips = vec![
+        "The first sync downloads and verifies every block since 2009. Depending on the \
+         disk and the CPU it takes from hours to several days.",
+        "Lightning nodes and indexers like Electrs or Fulcrum catch up once Bitcoin Core is \
+         synced, until then they can't be used.",
+        "Keep the node powered and online. After a restart the sync continues where it \
+         stopped.",
+    ];
+    if db_cache_mib.unwrap_or_default() < RECOMMENDED_DB_CACHE_MIB {
+        tips.push(
+            "A larger DB cache of Bitcoin Core, e.g. 2000 MiB, speeds the sync up if the \
+             node has the memory to spare. Lower it again once the sync is done.",
+        );
+    }
+    if sync.pruned {
+        tips.push("The node prunes, verified blocks are deleted to save space.");
+    }
+
+    tips
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn sync(progress: f64, initial_block_download: bool) -> ChainSync {
+        ChainSync {
+            blocks: 500_000,
+            headers: 870_000,
+            progress,
+            initial_block_download,
+            pruned: false,
+        }
+    }
+
+    #[test]
+    fn test_estimate() {
+        let first = SyncSample {
+            time: 1000,
+            progress: 0.2,
+        };
+        let current = |time, progress| SyncSample { time, progress };
+        // 10% per hour, 70% left
+        assert_eq!(estimate(&first, &current(4600, 0.3)), Some(25200));
+        assert_eq!(estimate(&first, &current(1060, 0.3)), None);
+        assert_eq!(estimate(&first, &current(4600, 0.2)), None);
+    }
+
+    #[test]
+    fn test_phase() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        assert_eq!(phase(work_dir, None, None).unwrap(), NodePhase::Idle);
+
+        let res = phase(work_dir, Some(&sync(0.2, true)), Some(4000)).unwrap();
+        let NodePhase::SyncingInitialBlockchain { eta_secs, tips, .. } = res else {
+            panic!("expected the initial sync");
+        };
+        assert_eq!(eta_secs, None);
+        assert_eq!(tips.len(), 3);
+        assert!(work_dir.join(SAMPLE_FILE).exists());
+
+        let res = phase(work_dir, Some(&sync(0.3, true)), None).unwrap();
+        assert!(matches!(
+            res,
+            NodePhase::SyncingInitialBlockchain { ref tips, .. } if tips.len() == 4
+        ));
+
+        let res = phase(work_dir, Some(&sync(1.0, false)), None).unwrap();
+        assert_eq!(res, NodePhase::Idle);
+        assert!(!work_dir.join(SAMPLE_FILE).exists());
+    }
+}
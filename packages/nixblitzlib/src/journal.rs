@@ -0,0 +1,364 @@
+//! Streams the journald logs of the services behind the apps.
+//!
+//! The entries are read from `journalctl -o json`, which must run on the node.
+//! Following a unit reads its output on a separate thread, the receiver is
+//! drained without blocking, e.g. once per tick of a UI. [show] leaves the
+//! output to journalctl instead, for the terminal.
+
+use std::{
+    fmt,
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    str::FromStr,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use error_stack::{Report, Result, ResultExt};
+use serde_json::Value;
+
+use crate::{apps::SupportedApps, errors::ProjectError, registry};
+
+/// The severity of a log entry, derived from the syslog priority
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    /// Priorities 0 to 3, from emergency to error
+    Error,
+    Warning,
+    Notice,
+    #[default]
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub fn from_priority(priority: u8) -> Self {
+        match priority {
+            0..=3 => LogLevel::Error,
+            4 => LogLevel::Warning,
+            5 => LogLevel::Notice,
+            6 => LogLevel::Info,
+            _ => LogLevel::Debug,
+        }
+    }
+
+    /// The name journalctl uses for the least severe priority of the level
+    pub fn journalctl_priority(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "err",
+            LogLevel::Warning => "warning",
+            LogLevel::Notice => "notice",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+
+    /// The next less severe level, wraps around to [LogLevel::Error]
+    pub fn next(&self) -> Self {
+        match self {
+            LogLevel::Error => LogLevel::Warning,
+            LogLevel::Warning => LogLevel::Notice,
+            LogLevel::Notice => LogLevel::Info,
+            LogLevel::Info => LogLevel::Debug,
+            LogLevel::Debug => LogLevel::Error,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self {
+            LogLevel::Error => "error",
+            LogLevel::Warning => "warning",
+            LogLevel::Notice => "notice",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        };
+        write!(f, "{}", level)
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(LogLevel::Error),
+            "warning" => Ok(LogLevel::Warning),
+            "notice" => Ok(LogLevel::Notice),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            _ => Err(format!(
+                "expected one of error, warning, notice, info, debug, got \"{}\"",
+                s
+            )),
+        }
+    }
+}
+
+/// A single line of the journal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// Microseconds since the unix epoch
+    pub timestamp: u64,
+
+    pub level: LogLevel,
+
+    /// The message, without the timestamp and unit journalctl usually adds
+    pub message: String,
+}
+
+impl LogEntry {
+    /// Parses a line of `journalctl -o json`
+    ///
+    /// # Returns
+    /// None if the line isn't a journal entry with a message
+    pub fn from_json(line: &str) -> Option<Self> {
+        let json: Value = serde_json::from_str(line).ok()?;
+
+        // journald stores messages that aren't valid UTF-8 as byte arrays
+        let message = match &json["MESSAGE"] {
+            Value::String(s) => s.clone(),
+            Value::Array(bytes) => {
+                let bytes: Vec<u8> = bytes
+                    .iter()
+                    .filter_map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                    .collect();
+                String::from_utf8_lossy(&bytes).to_string()
+            }
+            _ => return None,
+        };
+
+        // all fields are strings in the JSON output
+        let field = |name: &str| json[name].as_str().and_then(|v| v.parse().ok());
+
+        Some(Self {
+            timestamp: field("__REALTIME_TIMESTAMP").unwrap_or_default(),
+            level: field("PRIORITY")
+                .map(LogLevel::from_priority)
+                .unwrap_or_default(),
+            message,
+        })
+    }
+
+    /// Whether the entry is at least as severe as the given level and
+    /// contains the search term, ignoring the case
+    pub fn matches(&self, level: LogLevel, search: &str) -> bool {
+        self.level <= level
+            && (search.is_empty() || self.message.to_lowercase().contains(&search.to_lowercase()))
+    }
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the time of day in UTC is enough to follow a log
+        let secs = self.timestamp / 1_000_000;
+        write!(
+            f,
+            "{:02}:{:02}:{:02} [{}] {}",
+            secs / 3600 % 24,
+            secs / 60 % 60,
+            secs % 60,
+            self.level,
+            self.message
+        )
+    }
+}
+
+/// The main systemd unit of the service behind an app, see
+/// [AppRegistration::units](crate::registry::AppRegistration::units)
+///
+/// # Returns
+/// None for apps without a long running service
+pub fn unit_name(app: SupportedApps) -> Option<&'static str> {
+    registry::registration(app).units.first().copied()
+}
+
+/// Follows the journal of a unit until it is dropped
+pub struct JournalStream {
+    child: Child,
+    rx: Receiver<LogEntry>,
+}
+
+impl JournalStream {
+    /// Returns the entries received since the last call without blocking
+    pub fn poll(&self) -> Vec<LogEntry> {
+        self.rx.try_iter().collect()
+    }
+}
+
+impl Drop for JournalStream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Starts following the journal of a unit
+///
+/// # Arguments
+///
+/// * `unit` - The systemd unit, see [unit_name]
+/// * `lines` - The number of past entries to start with
+///
+/// # Errors
+/// [ProjectError::JournalError] if journalctl can't be started
+pub fn follow(unit: &str, lines: usize) -> Result<JournalStream, ProjectError> {
+    let mut child = Command::new("journalctl")
+        .args(["-u", unit, "-o", "json", "-f", "--no-pager", "-n"])
+        .arg(lines.to_string())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .change_context(ProjectError::JournalError(unit.to_string()))
+        .attach_printable("Unable to run journalctl")?;
+
+    let Some(stdout) = child.stdout.take() else {
+        let _ = child.kill();
+        return Err(Report::new(ProjectError::JournalError(unit.to_string()))
+            .attach_printable("Unable to read the output of journalctl"));
+    };
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+
+            // the receiver is gone once the stream is dropped
+            if let Some(entry) = LogEntry::from_json(&line) {
+                if tx.send(entry).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(JournalStream { child, rx })
+}
+
+/// The arguments of journalctl to show the given units
+fn show_args(units: &[&str], lines: usize, follow: bool, level: LogLevel) -> Vec<String> {
+    let mut args: Vec<String> = units.iter().map(|u| format!("--unit={}", u)).collect();
+    args.push(format!("--lines={}", lines));
+    args.push(format!("--priority={}", level.journalctl_priority()));
+    if follow {
+        args.push("--follow".into());
+    }
+
+    args
+}
+
+/// Prints the journal of the units of an app to the terminal, interleaved
+/// by time. Blocks until journalctl exits, when following until it is
+/// interrupted.
+///
+/// # Arguments
+///
+/// * `lines` - The number of past entries to show
+/// * `level` - Entries less severe are left out
+///
+/// # Errors
+/// [ProjectError::JournalError] if the app has no units or journalctl fails
+pub fn show(
+    app: SupportedApps,
+    lines: usize,
+    follow: bool,
+    level: LogLevel,
+) -> Result<(), ProjectError> {
+    let reg = registry::registration(app);
+    if reg.units.is_empty() {
+        return Err(
+            Report::new(ProjectError::JournalError(reg.title.to_string()))
+                .attach_printable(format!("{} doesn't run a service of its own", reg.title)),
+        );
+    }
+
+    let status = Command::new("journalctl")
+        .args(show_args(reg.units, lines, follow, level))
+        .status()
+        .change_context(ProjectError::JournalError(reg.units.join(", ")))
+        .attach_printable("Unable to run journalctl")?;
+    // interrupting a follow ends journalctl with a signal, which is no error
+    if !status.success() && status.code().is_some() {
+        return Err(
+            Report::new(ProjectError::JournalError(reg.units.join(", ")))
+                .attach_printable(format!("journalctl exited with {}", status)),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let line = r#"{"__REALTIME_TIMESTAMP":"1729000000000000","PRIORITY":"4","_SYSTEMD_UNIT":"lnd.service","MESSAGE":"Unable to connect to peer"}"#;
+        assert_eq!(
+            LogEntry::from_json(line),
+            Some(LogEntry {
+                timestamp: 1729000000000000,
+                level: LogLevel::Warning,
+                message: "Unable to connect to peer".into(),
+            })
+        );
+
+        let line = r#"{"PRIORITY":"6","MESSAGE":[104,105]}"#;
+        assert_eq!(LogEntry::from_json(line).unwrap().message, "hi");
+
+        assert!(LogEntry::from_json(r#"{"PRIORITY":"6"}"#).is_none());
+        assert!(LogEntry::from_json("-- No entries --").is_none());
+    }
+
+    #[test]
+    fn test_matches() {
+        let entry = LogEntry {
+            timestamp: 0,
+            level: LogLevel::Notice,
+            message: "Opened channel".into(),
+        };
+
+        assert!(entry.matches(LogLevel::Debug, ""));
+        assert!(entry.matches(LogLevel::Notice, "CHANNEL"));
+        assert!(!entry.matches(LogLevel::Warning, ""));
+        assert!(!entry.matches(LogLevel::Info, "peer"));
+        assert_eq!(entry.to_string(), "00:00:00 [notice] Opened channel");
+    }
+
+    #[test]
+    fn test_level() {
+        assert_eq!(LogLevel::from_priority(0), LogLevel::Error);
+        assert_eq!(LogLevel::from_priority(7), LogLevel::Debug);
+        assert_eq!(LogLevel::Debug.next(), LogLevel::Error);
+        assert!(LogLevel::Error < LogLevel::Info);
+        assert_eq!(LogLevel::from_str("warning"), Ok(LogLevel::Warning));
+        assert!(LogLevel::from_str("warn").is_err());
+    }
+
+    #[test]
+    fn test_show_args() {
+        assert_eq!(
+            show_args(
+                &["charge-lnd.service", "charge-lnd.timer"],
+                50,
+                true,
+                LogLevel::Error
+            ),
+            vec![
+                "--unit=charge-lnd.service",
+                "--unit=charge-lnd.timer",
+                "--lines=50",
+                "--priority=err",
+                "--follow",
+            ]
+        );
+        assert_eq!(
+            show_args(&["lnd.service"], 10, false, LogLevel::Debug).last(),
+            Some(&"--priority=debug".to_string())
+        );
+    }
+}
@@ -1,16 +1,77 @@
+pub mod activation;
 pub mod app_config;
 pub mod app_option_data;
+pub mod apply_history;
 pub mod apps;
+pub mod archival;
+pub mod backups;
 pub mod bitcoind;
 pub mod blitz_api;
 pub mod blitz_webui;
+pub mod bos;
+pub mod bundle;
+pub mod channel_backup;
+pub mod charge_lnd;
 pub mod cln;
+pub mod clone;
+pub mod config_sync;
+pub mod defaults;
+pub mod dependencies;
+pub mod disk_bench;
+pub mod disk_state;
+pub mod disk_usage;
+pub mod docs;
+pub mod dry_build;
+pub mod electrs;
+pub mod elements;
+pub mod eps;
 pub mod errors;
+pub mod explain;
+pub mod extra_nix;
+pub mod factory_reset;
+pub mod fulcrum;
+pub mod generations;
+pub mod grafana;
+pub mod i2p;
+pub mod initial_sync;
+pub mod journal;
+pub mod lit;
 pub mod lnd;
 pub mod locales;
+pub mod maintenance;
+pub mod mempool;
+pub mod migrations;
+pub mod mining;
+pub mod monitoring;
 pub mod nix_base_config;
+pub mod nix_format;
+pub mod node_check;
 pub mod number_value;
+pub mod option_batch;
+pub mod option_values;
+pub mod pending_changes;
+pub mod phoenixd;
+pub mod platform;
 pub mod project;
+pub mod rebuild_progress;
+pub mod recovery;
+pub mod registry;
+pub mod reverse_proxy;
+pub mod schema;
+pub mod search;
+pub mod secret_store;
+pub mod secrets;
+pub mod snapshots;
+pub mod ssh_keys;
+pub mod status;
 pub mod strings;
+pub mod system_backup;
+pub mod tailscale;
+pub mod template_upgrade;
+pub mod thunderhub;
 pub mod timezones;
+pub mod tls_cert;
+pub mod tor;
 pub mod utils;
+pub mod validation;
+pub mod variables;
@@ -0,0 +1,449 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_option_data::{
+        bool_data::BoolOptionData,
+        option_data::{
+            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToOptionId,
+        },
+        password_data::PasswordOptionData,
+        port_data::PortOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    lnd::LightningNetworkDaemonService,
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    utils::{
+        check_password_validity_confirm, escape_nix_indented_string, update_file, update_nix_files,
+        BASE_TEMPLATE,
+    },
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/lit.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/lit.json";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct LightningTerminalService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// Port the web interface listens on (HTTPS)
+    ///
+    /// Default: 8443
+    pub port: Box<PortOptionData>,
+
+    /// The password to log into the web interface.
+    /// litd only accepts the plain password, so it is not hashed.
+    pub ui_password: Box<PasswordOptionData>,
+
+    /// Whether to run Loop inside litd
+    ///
+    /// Default: true
+    pub loop_enable: Box<BoolOptionData>,
+
+    /// Whether to run Pool inside litd
+    ///
+    /// Default: false
+    pub pool_enable: Box<BoolOptionData>,
+
+    /// Whether to run Faraday inside litd
+    ///
+    /// Default: true
+    pub faraday_enable: Box<BoolOptionData>,
+
+    /// Path to the LND admin macaroon.
+    /// Derived from the LND network directory.
+    #[serde(default)]
+    pub lnd_macaroon_path: String,
+
+    /// The LND gRPC endpoint litd connects to.
+    /// Derived from the LND RPC address and port.
+    #[serde(default)]
+    pub lnd_server_url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LightningTerminalConfigOption {
+    Enable,
+    Port,
+    UiPassword,
+    LoopEnable,
+    PoolEnable,
+    FaradayEnable,
+}
+
+impl ToOptionId for LightningTerminalConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::LightningTerminal, self.to_string())
+    }
+}
+
+impl FromStr for LightningTerminalConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<LightningTerminalConfigOption, ()> {
+        match s {
+            "enable" => Ok(LightningTerminalConfigOption::Enable),
+            "port" => Ok(LightningTerminalConfigOption::Port),
+            "ui_password" => Ok(LightningTerminalConfigOption::UiPassword),
+            "loop_enable" => Ok(LightningTerminalConfigOption::LoopEnable),
+            "pool_enable" => Ok(LightningTerminalConfigOption::PoolEnable),
+            "faraday_enable" => Ok(LightningTerminalConfigOption::FaradayEnable),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for LightningTerminalConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            LightningTerminalConfigOption::Enable => "enable",
+            LightningTerminalConfigOption::Port => "port",
+            LightningTerminalConfigOption::UiPassword => "ui_password",
+            LightningTerminalConfigOption::LoopEnable => "loop_enable",
+            LightningTerminalConfigOption::PoolEnable => "pool_enable",
+            LightningTerminalConfigOption::FaradayEnable => "faraday_enable",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for LightningTerminalService {
+    fn get_options(&self) -> Vec<OptionData> {
+        vec![
+            OptionData::Bool(self.enable.clone()),
+            OptionData::Port(self.port.clone()),
+            OptionData::PasswordEdit(self.ui_password.clone()),
+            OptionData::Bool(self.loop_enable.clone()),
+            OptionData::Bool(self.pool_enable.clone()),
+            OptionData::Bool(self.faraday_enable.clone()),
+        ]
+    }
+
+    fn app_option_changed(
+        &mut self,
+        option: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let id = option.id();
+        if let Ok(opt) = LightningTerminalConfigOption::from_str(&id.option) {
+            let mut res = Ok(false);
+            match opt {
+                LightningTerminalConfigOption::Enable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.enable.value() != val.value);
+                        self.enable.set_value(val.value);
+                    }
+                }
+                LightningTerminalConfigOption::Port => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.port.value() != val.value);
+                        self.port.set_value(val.value.clone());
+                    }
+                }
+                LightningTerminalConfigOption::UiPassword => {
+                    if let OptionDataChangeNotification::PasswordEdit(val) = option {
+                        // Invalid passwords are ignored, see NixBaseConfig
+                        if check_password_validity_confirm(&val.value, &val.confirm).is_err() {
+                            return Ok(false);
+                        }
+
+                        res = Ok(*self.ui_password.hashed_value() != val.value);
+                        self.ui_password.set_hashed_value(val.value.clone());
+                    }
+                }
+                LightningTerminalConfigOption::LoopEnable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.loop_enable.value() != val.value);
+                        self.loop_enable.set_value(val.value);
+                    }
+                }
+                LightningTerminalConfigOption::PoolEnable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.pool_enable.value() != val.value);
+                        self.pool_enable.set_value(val.value);
+                    }
+                }
+                LightningTerminalConfigOption::FaradayEnable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.faraday_enable.value() != val.value);
+                        self.faraday_enable.set_value(val.value);
+                    }
+                }
+            }
+
+            return res;
+        }
+
+        Ok(false)
+    }
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering lit config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+
+    fn secret_options(&mut self) -> Vec<(&mut PasswordOptionData, &'static str)> {
+        vec![(&mut self.ui_password, "lnd")]
+    }
+}
+
+impl Default for LightningTerminalService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                LightningTerminalConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            port: Box::new(PortOptionData::new(
+                LightningTerminalConfigOption::Port.to_option_id(),
+                NumberValue::U16(Some(defaults::lit::PORT)),
+            )),
+            ui_password: Box::new(PasswordOptionData::new(
+                LightningTerminalConfigOption::UiPassword.to_option_id(),
+                "".to_string(),
+                true,
+                10,
+                false,
+                "".to_string(),
+            )),
+            loop_enable: Box::new(BoolOptionData::new(
+                LightningTerminalConfigOption::LoopEnable.to_option_id(),
+                true,
+            )),
+            pool_enable: Box::new(BoolOptionData::new(
+                LightningTerminalConfigOption::PoolEnable.to_option_id(),
+                false,
+            )),
+            faraday_enable: Box::new(BoolOptionData::new(
+                LightningTerminalConfigOption::FaradayEnable.to_option_id(),
+                true,
+            )),
+            lnd_macaroon_path: format!("{}/admin.macaroon", defaults::lnd::NETWORK_DIR),
+            lnd_server_url: format!("{}:{}", defaults::LOCALHOST, defaults::lnd::RPC_PORT),
+        }
+    }
+}
+
+/// Returns the litd mode of a sub-server
+fn sub_server_mode(enable: bool) -> String {
+    if enable {
+        "integrated".to_string()
+    } else {
+        "disable".to_string()
+    }
+}
+
+impl LightningTerminalService {
+    /// Derives the LND connection settings from the LND config.
+    ///
+    /// # Returns
+    /// Whether any of the settings changed
+    pub fn sync_with_lnd(&mut self, lnd: &LightningNetworkDaemonService) -> bool {
+        let macaroon_path = format!("{}/admin.macaroon", lnd.network_dir.value());
+        let address = lnd.rpc_address.value().unwrap_or(defaults::LOCALHOST);
+        let port = lnd
+            .rpc_port
+            .value()
+            .to_string_or(&defaults::lnd::RPC_PORT.to_string());
+        let server_url = format!("{}:{}", address, port);
+
+        let changed = self.lnd_macaroon_path != macaroon_path || self.lnd_server_url != server_url;
+        self.lnd_macaroon_path = macaroon_path;
+        self.lnd_server_url = server_url;
+
+        changed
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            (
+                "port",
+                self.port
+                    .value()
+                    .to_string_or(&defaults::lit::PORT.to_string()),
+            ),
+            (
+                "ui_password",
+                escape_nix_indented_string(self.ui_password.hashed_value()),
+            ),
+            (
+                "ui_password_file",
+                self.ui_password
+                    .secret()
+                    .and_then(|s| s.nix_path())
+                    .unwrap_or_default(),
+            ),
+            ("loop_mode", sub_server_mode(self.loop_enable.value())),
+            ("pool_mode", sub_server_mode(self.pool_enable.value())),
+            ("faraday_mode", sub_server_mode(self.faraday_enable.value())),
+            ("lnd_macaroon_path", self.lnd_macaroon_path.clone()),
+            ("lnd_server_url", self.lnd_server_url.clone()),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render lit template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<LightningTerminalService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::password_data::PasswordOptionChangeData, utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn get_test_service() -> LightningTerminalService {
+        let mut service = LightningTerminalService::default();
+        service.enable.set_value(true);
+        service.pool_enable.set_value(true);
+        service.faraday_enable.set_value(false);
+        service.port.set_value(NumberValue::U16(Some(8444)));
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = LightningTerminalService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_ui_password() {
+        let mut service = get_test_service();
+        let change = |value: &str, confirm: &str| {
+            OptionDataChangeNotification::PasswordEdit(PasswordOptionChangeData::new(
+                LightningTerminalConfigOption::UiPassword.to_option_id(),
+                value.into(),
+                Some(confirm.into()),
+            ))
+        };
+
+        assert!(!service
+            .app_option_changed(&change("my_secret_password", "other_password"))
+            .unwrap());
+        assert!(service
+            .app_option_changed(&change("my_secret_password", "my_secret_password"))
+            .unwrap());
+        assert_eq!(service.ui_password.hashed_value(), "my_secret_password");
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains("httpslisten=0.0.0.0:8444"));
+            assert!(data.contains("loop-mode=integrated"));
+            assert!(data.contains("pool-mode=integrated"));
+            assert!(data.contains("faraday-mode=disable"));
+            assert!(data.contains(&format!("remote.lnd.macaroonpath={}", s.lnd_macaroon_path)));
+            assert!(data.contains(&format!("remote.lnd.rpcserver={}", s.lnd_server_url)));
+        }
+
+        assert!(result.is_ok());
+    }
+}
@@ -1,27 +1,31 @@
 use core::fmt;
-use std::{collections::HashMap, net::IpAddr, path::Path, str::FromStr};
+use std::{collections::HashMap, path::Path, str::FromStr};
 
-use alejandra::format;
 use error_stack::{Report, Result, ResultExt};
 use handlebars::{no_escape, Handlebars};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    app_config::AppConfig,
+    app_config::{app_options, AppConfig},
     app_option_data::{
         bool_data::BoolOptionData,
+        code_data::{CodeLanguage, CodeOptionData},
+        manual_string_list_data::ManualStringListOptionData,
         net_address_data::NetAddressOptionData,
-        option_data::{
-            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToNixString,
-            ToOptionId,
-        },
+        number_data::NumberOptionData,
+        option_data::{OptionId, ToNixString, ToOptionId},
         port_data::PortOptionData,
         text_edit_data::TextOptionData,
     },
     apps::SupportedApps,
+    defaults,
     errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
     number_value::NumberValue,
-    utils::{update_file, BASE_TEMPLATE},
+    reverse_proxy::{ProxyRoute, ProxyRouteProvider},
+    tor::{port_or, HiddenService, HiddenServiceProvider},
+    utils::{escape_nix_indented_string, update_file, update_nix_files, BASE_TEMPLATE},
 };
 
 pub const TEMPLATE_FILE_NAME: &str = "src/apps/lnd.nix.templ";
@@ -70,7 +74,62 @@ pub struct LightningNetworkDaemonService {
     /// Extra lines appended to {file}`lnd.conf`.
     /// See here for all available options:
     /// https://github.com/lightningnetwork/lnd/blob/master/sample-lnd.conf
-    pub extra_config: Box<TextOptionData>,
+    pub extra_config: Box<CodeOptionData>,
+
+    /// The base fee in millisatoshi charged for forwarding a payment.
+    ///
+    /// default: 1000
+    pub base_fee_msat: Box<NumberOptionData>,
+
+    /// The fee rate in parts per million charged on the forwarded amount.
+    ///
+    /// default: 1
+    pub fee_rate_ppm: Box<NumberOptionData>,
+
+    /// The smallest HTLC in millisatoshi accepted on incoming channels.
+    ///
+    /// default: 1
+    pub min_htlc_msat: Box<NumberOptionData>,
+
+    /// The CLTV delta in blocks subtracted from forwarded HTLCs.
+    ///
+    /// default: 80
+    pub cltv_delta: Box<NumberOptionData>,
+
+    /// Whether to run a watchtower server that watches the channels
+    /// of other nodes for breaches
+    pub watchtower_enable: Box<BoolOptionData>,
+
+    /// Address the watchtower server listens on
+    pub watchtower_address: Box<NetAddressOptionData>,
+
+    /// Port the watchtower server listens on
+    ///
+    /// default: 9911
+    pub watchtower_port: Box<PortOptionData>,
+
+    /// Whether to back up the channel states to remote watchtowers
+    pub wtclient_enable: Box<BoolOptionData>,
+
+    /// The remote watchtowers to use, in the format pubkey@host:port
+    pub wtclient_towers: Box<ManualStringListOptionData>,
+
+    /// When to restart LND to compact its channel database, as systemd
+    /// calendar event. Empty disables the compaction.
+    pub compact_db_schedule: Box<TextOptionData>,
+
+    /// The name the node announces to the network, at most 32 bytes.
+    /// Empty leaves the choice to LND.
+    pub alias: Box<TextOptionData>,
+
+    /// Whether to serve a static page with the alias, pubkey, URI and a QR
+    /// code of the node via nginx
+    pub node_info_enable: Box<BoolOptionData>,
+
+    /// The nginx location of the node info page
+    ///
+    /// default: /node
+    pub node_info_location: Box<TextOptionData>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -88,6 +147,19 @@ pub enum LndConfigOption {
     CertExtraIps,
     CertExtraDomains,
     ExtraConfig,
+    BaseFeeMsat,
+    FeeRatePpm,
+    MinHtlcMsat,
+    CltvDelta,
+    WatchtowerEnable,
+    WatchtowerAddress,
+    WatchtowerPort,
+    WtClientEnable,
+    WtClientTowers,
+    CompactDbSchedule,
+    Alias,
+    NodeInfoEnable,
+    NodeInfoLocation,
 }
 
 impl ToOptionId for LndConfigOption {
@@ -113,6 +185,19 @@ impl FromStr for LndConfigOption {
             "cert_extra_ips" => Ok(LndConfigOption::CertExtraIps),
             "cert_extra_domains" => Ok(LndConfigOption::CertExtraDomains),
             "extra_config" => Ok(LndConfigOption::ExtraConfig),
+            "base_fee_msat" => Ok(LndConfigOption::BaseFeeMsat),
+            "fee_rate_ppm" => Ok(LndConfigOption::FeeRatePpm),
+            "min_htlc_msat" => Ok(LndConfigOption::MinHtlcMsat),
+            "cltv_delta" => Ok(LndConfigOption::CltvDelta),
+            "watchtower_enable" => Ok(LndConfigOption::WatchtowerEnable),
+            "watchtower_address" => Ok(LndConfigOption::WatchtowerAddress),
+            "watchtower_port" => Ok(LndConfigOption::WatchtowerPort),
+            "wtclient_enable" => Ok(LndConfigOption::WtClientEnable),
+            "wtclient_towers" => Ok(LndConfigOption::WtClientTowers),
+            "compact_db_schedule" => Ok(LndConfigOption::CompactDbSchedule),
+            "alias" => Ok(LndConfigOption::Alias),
+            "node_info_enable" => Ok(LndConfigOption::NodeInfoEnable),
+            "node_info_location" => Ok(LndConfigOption::NodeInfoLocation),
             _ => Err(()),
         }
     }
@@ -134,125 +219,51 @@ impl fmt::Display for LndConfigOption {
             LndConfigOption::CertExtraIps => "cert_extra_ips",
             LndConfigOption::CertExtraDomains => "cert_extra_domains",
             LndConfigOption::ExtraConfig => "extra_config",
+            LndConfigOption::BaseFeeMsat => "base_fee_msat",
+            LndConfigOption::FeeRatePpm => "fee_rate_ppm",
+            LndConfigOption::MinHtlcMsat => "min_htlc_msat",
+            LndConfigOption::CltvDelta => "cltv_delta",
+            LndConfigOption::WatchtowerEnable => "watchtower_enable",
+            LndConfigOption::WatchtowerAddress => "watchtower_address",
+            LndConfigOption::WatchtowerPort => "watchtower_port",
+            LndConfigOption::WtClientEnable => "wtclient_enable",
+            LndConfigOption::WtClientTowers => "wtclient_towers",
+            LndConfigOption::CompactDbSchedule => "compact_db_schedule",
+            LndConfigOption::Alias => "alias",
+            LndConfigOption::NodeInfoEnable => "node_info_enable",
+            LndConfigOption::NodeInfoLocation => "node_info_location",
         };
         write!(f, "{}", option_str)
     }
 }
 
 impl AppConfig for LightningNetworkDaemonService {
-    fn get_options(&self) -> Vec<OptionData> {
-        vec![
-            OptionData::Bool(self.enable.clone()),
-            OptionData::NetAddress(self.address.clone()),
-            OptionData::Port(self.port.clone()),
-            OptionData::TextEdit(self.user.clone()),
-            OptionData::NetAddress(self.rpc_address.clone()),
-            OptionData::Port(self.rpc_port.clone()),
-            OptionData::NetAddress(self.rest_address.clone()),
-            OptionData::Port(self.rest_port.clone()),
-            OptionData::TextEdit(self.data_dir.clone()),
-            OptionData::TextEdit(self.network_dir.clone()),
-            //OptionData::IpList(self.cert_extra_ips.clone()),
-            //OptionData::TextList(self.cert_extra_domains.clone()),
-            OptionData::TextEdit(self.extra_config.clone()),
-        ]
-    }
-
-    fn app_option_changed(
-        &mut self,
-        option: &OptionDataChangeNotification,
-    ) -> Result<bool, ProjectError> {
-        let id = option.id();
-        if let Ok(opt) = LndConfigOption::from_str(&id.option) {
-            let mut res = Ok(false);
-            match opt {
-                LndConfigOption::Enable => {
-                    if let OptionDataChangeNotification::Bool(val) = option {
-                        res = Ok(self.enable.value() != val.value);
-                        self.enable.set_value(val.value);
-                    }
-                }
-                LndConfigOption::Address => {
-                    if let OptionDataChangeNotification::NetAddress(val) = option {
-                        res = Ok(self.address.value() != val.value);
-                        self.address.set_value(val.value);
-                    }
-                }
-                LndConfigOption::Port => {
-                    if let OptionDataChangeNotification::Port(val) = option {
-                        res = Ok(*self.port.value() != val.value);
-                        self.port.set_value(val.value.clone());
-                    }
-                }
-                LndConfigOption::User => {
-                    if let OptionDataChangeNotification::TextEdit(val) = option {
-                        res = Ok(self.user.value() != val.value);
-                        self.user.set_value(val.value.clone());
-                    }
-                }
-                LndConfigOption::RpcAddress => {
-                    if let OptionDataChangeNotification::NetAddress(val) = option {
-                        res = Ok(self.rpc_address.value() != val.value);
-                        self.rpc_address.set_value(val.value);
-                    }
-                }
-                LndConfigOption::RpcPort => {
-                    if let OptionDataChangeNotification::Port(val) = option {
-                        res = Ok(*self.rpc_port.value() != val.value);
-                        self.rpc_port.set_value(val.value.clone());
-                    }
-                }
-                LndConfigOption::RestAddress => {
-                    if let OptionDataChangeNotification::NetAddress(val) = option {
-                        res = Ok(self.rest_address.value() != val.value);
-                        self.rest_address.set_value(val.value);
-                    }
-                }
-                LndConfigOption::RestPort => {
-                    if let OptionDataChangeNotification::Port(val) = option {
-                        res = Ok(*self.rest_port.value() != val.value);
-                        self.rest_port.set_value(val.value.clone());
-                    }
-                }
-                LndConfigOption::DataDir => {
-                    if let OptionDataChangeNotification::TextEdit(val) = option {
-                        res = Ok(self.data_dir.value() != val.value);
-                        self.data_dir.set_value(val.value.clone());
-                    }
-                }
-                LndConfigOption::NetworkDir => {
-                    if let OptionDataChangeNotification::TextEdit(val) = option {
-                        res = Ok(self.network_dir.value() != val.value);
-                        self.network_dir.set_value(val.value.clone());
-                    }
-                }
-                LndConfigOption::CertExtraIps => {
-                    todo!("implement me");
-                    //if let OptionDataChangeNotification::IpList(val) = option {
-                    //    res = Ok(self.cert_extra_ips != val.value);
-                    //    self.cert_extra_ips = val.value.clone();
-                    //}
-                }
-                LndConfigOption::CertExtraDomains => {
-                    todo!("implement me");
-                    //if let OptionDataChangeNotification::TextList(val) = option {
-                    //    res = Ok(self.cert_extra_domains != val.value);
-                    //    self.cert_extra_domains = val.value.clone();
-                    //}
-                }
-                LndConfigOption::ExtraConfig => {
-                    if let OptionDataChangeNotification::TextEdit(val) = option {
-                        res = Ok(self.extra_config.value() != val.value);
-                        self.extra_config.set_value(val.value.clone());
-                    }
-                }
-            }
-
-            return res;
-        }
-
-        Ok(false)
-    }
+    app_options!(LndConfigOption {
+        Enable => enable,
+        Address => address,
+        Port => port,
+        User => user,
+        RpcAddress => rpc_address,
+        RpcPort => rpc_port,
+        RestAddress => rest_address,
+        RestPort => rest_port,
+        DataDir => data_dir,
+        NetworkDir => network_dir,
+        ExtraConfig => extra_config,
+        BaseFeeMsat => base_fee_msat,
+        FeeRatePpm => fee_rate_ppm,
+        MinHtlcMsat => min_htlc_msat,
+        CltvDelta => cltv_delta,
+        WatchtowerEnable => watchtower_enable,
+        WatchtowerAddress => watchtower_address,
+        WatchtowerPort => watchtower_port,
+        WtClientEnable => wtclient_enable,
+        WtClientTowers => wtclient_towers,
+        CompactDbSchedule => compact_db_schedule,
+        Alias => alias,
+        NodeInfoEnable => node_info_enable,
+        NodeInfoLocation => node_info_location,
+    });
 
     fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
         let rendered_json = self
@@ -262,12 +273,7 @@ impl AppConfig for LightningNetworkDaemonService {
             "Failed at rendering lnd config".to_string(),
         ))?;
 
-        for (key, val) in rendered_nix.iter() {
-            update_file(
-                Path::new(&work_dir.join(key.replace(".templ", ""))),
-                val.as_bytes(),
-            )?;
-        }
+        update_nix_files(work_dir, &rendered_nix)?;
 
         update_file(
             Path::new(&work_dir.join(JSON_FILE_NAME)),
@@ -287,59 +293,183 @@ impl Default for LightningNetworkDaemonService {
             )),
             address: Box::new(NetAddressOptionData::new(
                 LndConfigOption::Address.to_option_id(),
-                Some(IpAddr::from_str("127.0.0.1").unwrap()),
+                Some(defaults::LOCALHOST),
             )),
             port: Box::new(PortOptionData::new(
                 LndConfigOption::Port.to_option_id(),
-                NumberValue::U16(Some(9735)),
+                NumberValue::U16(Some(defaults::lnd::PORT)),
             )),
             user: Box::new(TextOptionData::new(
                 LndConfigOption::User.to_option_id(),
-                "admin".to_string(),
+                defaults::SYSTEM_USER.to_string(),
                 1,
                 false,
-                "admin".to_string(),
+                defaults::SYSTEM_USER.to_string(),
             )),
             rpc_address: Box::new(NetAddressOptionData::new(
                 LndConfigOption::RpcAddress.to_option_id(),
-                Some(IpAddr::from_str("127.0.0.1").unwrap()),
+                Some(defaults::LOCALHOST),
             )),
             rpc_port: Box::new(PortOptionData::new(
                 LndConfigOption::RpcPort.to_option_id(),
-                NumberValue::U16(Some(10009)),
+                NumberValue::U16(Some(defaults::lnd::RPC_PORT)),
             )),
             rest_address: Box::new(NetAddressOptionData::new(
                 LndConfigOption::RestAddress.to_option_id(),
-                Some(IpAddr::from_str("127.0.0.1").unwrap()),
+                Some(defaults::LOCALHOST),
             )),
             rest_port: Box::new(PortOptionData::new(
                 LndConfigOption::RestPort.to_option_id(),
-                NumberValue::U16(Some(8080)),
+                NumberValue::U16(Some(defaults::lnd::REST_PORT)),
             )),
             data_dir: Box::new(TextOptionData::new(
                 LndConfigOption::DataDir.to_option_id(),
-                "/var/lib/lnd".to_string(),
+                defaults::lnd::DATA_DIR.to_string(),
                 1,
                 false,
-                "/var/lib/lnd".to_string(),
+                defaults::lnd::DATA_DIR.to_string(),
             )),
             network_dir: Box::new(TextOptionData::new(
                 LndConfigOption::NetworkDir.to_option_id(),
-                "${cfg.lnd.dataDir}/chain/bitcoin/${cfg.bitcoind.network}".to_string(),
+                defaults::lnd::NETWORK_DIR.to_string(),
                 1,
                 false,
-                "${cfg.lnd.dataDir}/chain/bitcoin/${cfg.bitcoind.network}".to_string(),
+                defaults::lnd::NETWORK_DIR.to_string(),
             )),
             cert_extra_ips: Box::new(Vec::new()),
             cert_extra_domains: Box::new(Vec::new()),
-            extra_config: Box::new(TextOptionData::new(
+            extra_config: Box::new(CodeOptionData::new(
                 LndConfigOption::ExtraConfig.to_option_id(),
                 "".to_string(),
                 1,
                 false,
                 "".to_string(),
+                CodeLanguage::Ini,
+            )),
+            base_fee_msat: Box::new(
+                NumberOptionData::new(
+                    LndConfigOption::BaseFeeMsat.to_option_id(),
+                    NumberValue::UInt(Some(defaults::lnd::BASE_FEE_MSAT)),
+                    0,
+                    100_000_000,
+                    false,
+                    NumberValue::UInt(Some(defaults::lnd::BASE_FEE_MSAT)),
+                )
+                .unwrap(),
+            ),
+            fee_rate_ppm: Box::new(
+                NumberOptionData::new(
+                    LndConfigOption::FeeRatePpm.to_option_id(),
+                    NumberValue::UInt(Some(defaults::lnd::FEE_RATE_PPM)),
+                    0,
+                    1_000_000,
+                    false,
+                    NumberValue::UInt(Some(defaults::lnd::FEE_RATE_PPM)),
+                )
+                .unwrap(),
+            ),
+            min_htlc_msat: Box::new(
+                NumberOptionData::new(
+                    LndConfigOption::MinHtlcMsat.to_option_id(),
+                    NumberValue::UInt(Some(defaults::lnd::MIN_HTLC_MSAT)),
+                    0,
+                    100_000_000,
+                    false,
+                    NumberValue::UInt(Some(defaults::lnd::MIN_HTLC_MSAT)),
+                )
+                .unwrap(),
+            ),
+            cltv_delta: Box::new(
+                NumberOptionData::new(
+                    LndConfigOption::CltvDelta.to_option_id(),
+                    NumberValue::UInt(Some(defaults::lnd::CLTV_DELTA)),
+                    18,
+                    2016,
+                    false,
+                    NumberValue::UInt(Some(defaults::lnd::CLTV_DELTA)),
+                )
+                .unwrap(),
+            ),
+            watchtower_enable: Box::new(BoolOptionData::new(
+                LndConfigOption::WatchtowerEnable.to_option_id(),
+                false,
+            )),
+            watchtower_address: Box::new(NetAddressOptionData::new(
+                LndConfigOption::WatchtowerAddress.to_option_id(),
+                Some(defaults::LOCALHOST),
+            )),
+            watchtower_port: Box::new(PortOptionData::new(
+                LndConfigOption::WatchtowerPort.to_option_id(),
+                NumberValue::U16(Some(defaults::lnd::WATCHTOWER_PORT)),
+            )),
+            wtclient_enable: Box::new(BoolOptionData::new(
+                LndConfigOption::WtClientEnable.to_option_id(),
+                false,
+            )),
+            wtclient_towers: Box::new(ManualStringListOptionData::new(
+                LndConfigOption::WtClientTowers.to_option_id(),
+                vec![],
+                0,
+            )),
+            compact_db_schedule: Box::new(TextOptionData::new(
+                LndConfigOption::CompactDbSchedule.to_option_id(),
+                "".to_string(),
+                1,
+                false,
+                "".to_string(),
+            )),
+            alias: Box::new(TextOptionData::new(
+                LndConfigOption::Alias.to_option_id(),
+                "".to_string(),
+                1,
+                false,
+                "".to_string(),
             )),
+            node_info_enable: Box::new(BoolOptionData::new(
+                LndConfigOption::NodeInfoEnable.to_option_id(),
+                false,
+            )),
+            node_info_location: Box::new(TextOptionData::new(
+                LndConfigOption::NodeInfoLocation.to_option_id(),
+                defaults::lnd::NODE_INFO_LOCATION.to_string(),
+                1,
+                false,
+                defaults::lnd::NODE_INFO_LOCATION.to_string(),
+            )),
+        }
+    }
+}
+
+impl HiddenServiceProvider for LightningNetworkDaemonService {
+    fn hidden_service(&self) -> Option<HiddenService> {
+        if !self.enable.value() {
+            return None;
         }
+
+        Some(HiddenService::new(
+            SupportedApps::LND,
+            "lnd",
+            port_or(self.port.value(), defaults::lnd::PORT),
+            true,
+        ))
+    }
+}
+
+impl ProxyRouteProvider for LightningNetworkDaemonService {
+    /// The page is a static file, the template declares the location itself
+    fn proxy_route(&self) -> Option<ProxyRoute> {
+        if !self.enable.value() || !self.node_info_enable.value() {
+            return None;
+        }
+
+        Some(ProxyRoute::new(
+            SupportedApps::LND,
+            LndConfigOption::NodeInfoLocation.to_option_id(),
+            self.node_info_location.value(),
+            None,
+            false,
+            false,
+        ))
     }
 }
 
@@ -407,27 +537,141 @@ impl LightningNetworkDaemonService {
                     .join("\n"),
             ),
             ("extra_config", self.extra_config.value().to_string()),
+            ("fee_policy", self.fee_policy()),
+            ("watchtower", self.watchtower_config()),
+            ("wtclient_towers", self.wtclient_towers()),
+            ("compact_db", self.compact_db_config()),
+            (
+                "compact_db_schedule",
+                self.compact_db_schedule.value().trim().to_string(),
+            ),
+            ("alias", self.alias_config()),
+            ("node_info", self.node_info_locations()),
+            ("node_info_title", self.node_info_title()),
+            ("node_info_dir", defaults::lnd::NODE_INFO_DIR.to_string()),
         ]);
 
         let res = handlebars
             .render(TEMPLATE_FILE_NAME, &data)
             .attach_printable("Failed to render LND template".to_string())
             .change_context(TemplatingError::Render)?;
-        let (status, text) = format::in_memory("<lnd>".to_string(), res);
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    /// Renders the channel fee and HTLC policy as config file lines.
+    /// Unset values are omitted so the daemon falls back to its own defaults.
+    fn fee_policy(&self) -> String {
+        let policy = [
+            ("bitcoin.basefee", &self.base_fee_msat),
+            ("bitcoin.feerate", &self.fee_rate_ppm),
+            ("bitcoin.minhtlc", &self.min_htlc_msat),
+            ("bitcoin.timelockdelta", &self.cltv_delta),
+        ];
+
+        policy
+            .iter()
+            .filter_map(|(key, option)| {
+                let value = option.value().to_string();
+                (!value.is_empty()).then(|| format!("{key}={value}"))
+            })
+            .collect::<Vec<_>>()
+            .join("\n      ")
+    }
+
+    /// Renders the watchtower server and client sections as config file lines
+    fn watchtower_config(&self) -> String {
+        let mut lines = vec![];
+        if self.watchtower_enable.value() {
+            lines.push("watchtower.active=1".to_string());
+            lines.push(format!(
+                "watchtower.listen={}:{}",
+                self.watchtower_address.to_nix_string(false),
+                port_or(self.watchtower_port.value(), defaults::lnd::WATCHTOWER_PORT)
+            ));
+        }
+        if self.wtclient_enable.value() {
+            lines.push("wtclient.active=1".to_string());
+        }
+
+        lines.join("\n      ")
+    }
+
+    /// LND only compacts its database on startup. With a schedule it does
+    /// so on every start and a timer restarts it, otherwise it is left alone.
+    fn compact_db_config(&self) -> String {
+        if self.compact_db_schedule.value().trim().is_empty() {
+            return String::new();
+        }
+
+        [
+            "db.bolt.auto-compact=true",
+            "db.bolt.auto-compact-min-age=0",
+        ]
+        .join("\n      ")
+    }
+
+    /// Renders the alias as config file line, empty if it isn't set
+    fn alias_config(&self) -> String {
+        let alias = self.alias.value().trim();
+        if alias.is_empty() {
+            return String::new();
+        }
+
+        format!("alias={}", escape_nix_indented_string(alias))
+    }
+
+    /// Renders the nginx locations of the node info page, empty if the page
+    /// is disabled. The path without trailing slash redirects to the page.
+    fn node_info_locations(&self) -> String {
+        let Some(route) = self.proxy_route() else {
+            return String::new();
+        };
+
+        if route.path == "/" {
+            return format!("\"/\".alias = \"{}/\";", defaults::lnd::NODE_INFO_DIR);
+        }
+
+        [
+            format!("\"= {0}\".return = \"301 {0}/\";", route.path),
+            format!(
+                "\"{}/\".alias = \"{}/\";",
+                route.path,
+                defaults::lnd::NODE_INFO_DIR
+            ),
+        ]
+        .join("\n      ")
+    }
 
-        if let format::Status::Error(e) = status {
-            Err(Report::new(TemplatingError::Format)).attach_printable_lazy(|| {
-                format!("Could not format the template file due to error: {e}")
-            })?
+    /// The heading of the node info page. The pubkey and URI are only known
+    /// once LND is running and are filled in on the node.
+    fn node_info_title(&self) -> String {
+        let alias = self.alias.value().trim();
+        let title = if alias.is_empty() {
+            "Lightning Node"
         } else {
-            rendered_contents.insert(TEMPLATE_FILE_NAME.to_string(), text);
+            alias
+        };
+
+        escape_nix_indented_string(&html_escape(title))
+    }
+
+    /// The remote towers can't be set in the config file, they are added
+    /// with lncli once LND is up. Returns an empty string if there are none.
+    fn wtclient_towers(&self) -> String {
+        if !self.wtclient_enable.value() {
+            return String::new();
         }
 
-        Ok(rendered_contents)
+        self.wtclient_towers.to_nix_string(true)
     }
 
     pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
-        serde_json::to_string(self).change_context(TemplatingError::JsonRenderError)
+        migrations::to_json_string(self)
     }
 
     pub(crate) fn from_json(
@@ -437,12 +681,22 @@ impl LightningNetworkDaemonService {
     }
 }
 
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
 #[cfg(test)]
 mod tests {
     use std::{fs, net::IpAddr, str::FromStr};
     use tempfile::tempdir;
 
-    use crate::utils::init_default_project;
+    use crate::{
+        app_option_data::option_data::OptionDataChangeNotification, utils::init_default_project,
+    };
 
     use super::*;
 
@@ -523,12 +777,106 @@ mod tests {
                     "cde.fg".to_string(),
                 ),
             ]),
-            extra_config: Box::new(TextOptionData::new(
+            extra_config: Box::new(CodeOptionData::new(
                 LndConfigOption::ExtraConfig.to_option_id(),
                 "var1=this is extra config".to_string(),
                 1,
                 false,
                 "var1=this is extra config".to_string(),
+                CodeLanguage::Ini,
+            )),
+            base_fee_msat: Box::new(
+                NumberOptionData::new(
+                    LndConfigOption::BaseFeeMsat.to_option_id(),
+                    NumberValue::UInt(Some(defaults::lnd::BASE_FEE_MSAT)),
+                    0,
+                    100_000_000,
+                    false,
+                    NumberValue::UInt(Some(defaults::lnd::BASE_FEE_MSAT)),
+                )
+                .unwrap(),
+            ),
+            fee_rate_ppm: Box::new(
+                NumberOptionData::new(
+                    LndConfigOption::FeeRatePpm.to_option_id(),
+                    NumberValue::UInt(Some(defaults::lnd::FEE_RATE_PPM)),
+                    0,
+                    1_000_000,
+                    false,
+                    NumberValue::UInt(Some(defaults::lnd::FEE_RATE_PPM)),
+                )
+                .unwrap(),
+            ),
+            min_htlc_msat: Box::new(
+                NumberOptionData::new(
+                    LndConfigOption::MinHtlcMsat.to_option_id(),
+                    NumberValue::UInt(Some(defaults::lnd::MIN_HTLC_MSAT)),
+                    0,
+                    100_000_000,
+                    false,
+                    NumberValue::UInt(Some(defaults::lnd::MIN_HTLC_MSAT)),
+                )
+                .unwrap(),
+            ),
+            cltv_delta: Box::new(
+                NumberOptionData::new(
+                    LndConfigOption::CltvDelta.to_option_id(),
+                    NumberValue::UInt(Some(defaults::lnd::CLTV_DELTA)),
+                    18,
+                    2016,
+                    false,
+                    NumberValue::UInt(Some(defaults::lnd::CLTV_DELTA)),
+                )
+                .unwrap(),
+            ),
+            watchtower_enable: Box::new(BoolOptionData::new(
+                LndConfigOption::WatchtowerEnable.to_option_id(),
+                true,
+            )),
+            watchtower_address: Box::new(NetAddressOptionData::new(
+                LndConfigOption::WatchtowerAddress.to_option_id(),
+                Some(IpAddr::from_str("0.0.0.0").unwrap()),
+            )),
+            watchtower_port: Box::new(PortOptionData::new(
+                LndConfigOption::WatchtowerPort.to_option_id(),
+                NumberValue::U16(Some(9912)),
+            )),
+            wtclient_enable: Box::new(BoolOptionData::new(
+                LndConfigOption::WtClientEnable.to_option_id(),
+                true,
+            )),
+            wtclient_towers: Box::new(ManualStringListOptionData::new(
+                LndConfigOption::WtClientTowers.to_option_id(),
+                vec![
+                    "02abc@tower.example.com:9911".to_string(),
+                    "03def@10.0.0.2:9911".to_string(),
+                ],
+                0,
+            )),
+            compact_db_schedule: Box::new(TextOptionData::new(
+                LndConfigOption::CompactDbSchedule.to_option_id(),
+                "Sun *-*-* 04:30:00".to_string(),
+                1,
+                false,
+                "".to_string(),
+            )),
+            alias: Box::new(TextOptionData::new(
+                LndConfigOption::Alias.to_option_id(),
+                "Tom's <node>".to_string(),
+                1,
+                false,
+                "".to_string(),
+            )),
+            node_info_enable: Box::new(BoolOptionData::new(
+                LndConfigOption::NodeInfoEnable.to_option_id(),
+                true,
+            )),
+            node_info_location: Box::new(TextOptionData::new(
+                LndConfigOption::NodeInfoLocation.to_option_id(),
+                "/info/".to_string(),
+                1,
+                false,
+                defaults::lnd::NODE_INFO_LOCATION.to_string(),
             )),
         }
     }
@@ -616,15 +964,18 @@ mod tests {
             assert!(&data.contains_key(TEMPLATE_FILE_NAME));
             let data = &data[TEMPLATE_FILE_NAME];
             assert!(data.contains(&format!("enable = {};", s.enable.value())));
-            assert!(data.contains(&format!("address = {};", s.address.to_nix_string(true))));
+            assert!(data.contains(&format!(
+                "address = lib.mkDefault {};",
+                s.address.to_nix_string(true)
+            )));
             assert!(data.contains(&format!("port = {};", s.port.value())));
             assert!(data.contains(&format!(
-                "rpcAddress = {};",
+                "rpcAddress = lib.mkDefault {};",
                 s.rpc_address.to_nix_string(true)
             )));
             assert!(data.contains(&format!("rpcPort = {};", s.rpc_port.value())));
             assert!(data.contains(&format!(
-                "restAddress = {};",
+                "restAddress = lib.mkDefault {};",
                 s.rest_address.to_nix_string(true)
             )));
             assert!(data.contains(&format!("restPort = {};", s.rest_port.value())));
@@ -637,8 +988,66 @@ mod tests {
                 .iter()
                 .for_each(|domain| assert!(data.contains(&format!("\"{}\"", domain.value()))));
             assert!(data.contains(&s.extra_config.value().to_string()));
+            assert!(data.contains(&format!("bitcoin.basefee={}", s.base_fee_msat.value())));
+            assert!(data.contains(&format!("bitcoin.timelockdelta={}", s.cltv_delta.value())));
+            assert!(data.contains("watchtower.active=1"));
+            assert!(data.contains("watchtower.listen=0.0.0.0:9912"));
+            assert!(data.contains("wtclient.active=1"));
+            assert!(data.contains("\"02abc@tower.example.com:9911\" \"03def@10.0.0.2:9911\""));
         }
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_watchtower_config() {
+        let mut s = get_test_service();
+        s.watchtower_enable.set_value(false);
+        assert_eq!(s.watchtower_config(), "wtclient.active=1");
+
+        s.wtclient_enable.set_value(false);
+        assert_eq!(s.watchtower_config(), "");
+        assert_eq!(s.wtclient_towers(), "");
+
+        let data = s.render().unwrap();
+        assert!(!data[TEMPLATE_FILE_NAME].contains("lnd-wtclient-towers"));
+    }
+
+    #[test]
+    fn test_compact_db_config() {
+        let mut s = get_test_service();
+        let data = s.render().unwrap();
+        assert!(data[TEMPLATE_FILE_NAME].contains("db.bolt.auto-compact=true"));
+        assert!(data[TEMPLATE_FILE_NAME].contains("OnCalendar = \"Sun *-*-* 04:30:00\";"));
+
+        s.compact_db_schedule.set_value("".to_string());
+        assert_eq!(s.compact_db_config(), "");
+        let data = s.render().unwrap();
+        assert!(!data[TEMPLATE_FILE_NAME].contains("lnd-compact-db"));
+    }
+
+    #[test]
+    fn test_node_info() {
+        let mut s = get_test_service();
+        let data = s.render().unwrap();
+        let data = &data[TEMPLATE_FILE_NAME];
+        assert!(data.contains("alias=Tom's <node>"));
+        assert!(data.contains("<h1>Tom&#39;s &lt;node&gt;</h1>"));
+        assert!(data.contains("\"= /info\".return = \"301 /info/\";"));
+        assert!(data.contains("\"/info/\".alias = \"/var/lib/lnd-node-info/\";"));
+        assert_eq!(s.proxy_route().unwrap().path, "/info");
+
+        s.node_info_location.set_value("/".to_string());
+        assert_eq!(
+            s.node_info_locations(),
+            "\"/\".alias = \"/var/lib/lnd-node-info/\";"
+        );
+
+        s.alias.set_value("".to_string());
+        s.node_info_enable.set_value(false);
+        assert_eq!(s.alias_config(), "");
+        assert_eq!(s.proxy_route(), None);
+        let data = s.render().unwrap();
+        assert!(!data[TEMPLATE_FILE_NAME].contains("lnd-node-info"));
+    }
 }
@@ -0,0 +1,214 @@
+//! Results of the scheduled integrity checks.
+//!
+//! Bitcoin Core can verify its most recent blocks and LND can compact its
+//! channel database on a schedule, ideally in a quiet window of the node.
+//! Both run as oneshot units, the checks here read their last result so
+//! `nixblitz doctor --online` can report a failed run.
+
+use core::fmt;
+use std::process::Command;
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::{
+    app_option_data::option_data::{GetOptionId, OptionData, OptionId, ToOptionId},
+    apps::SupportedApps,
+    bitcoind::BitcoindConfigOption,
+    errors::ProjectError,
+    lnd::LndConfigOption,
+    project::Project,
+};
+
+/// A scheduled integrity check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceTask {
+    pub app: SupportedApps,
+
+    /// The systemd unit the timer starts
+    pub unit: &'static str,
+
+    /// What the task does, e.g. "chain verification"
+    pub description: &'static str,
+}
+
+/// The tasks together with the option holding their schedule
+fn tasks() -> [(MaintenanceTask, OptionId); 2] {
+    [
+        (
+            MaintenanceTask {
+                app: SupportedApps::BitcoinCore,
+                unit: "bitcoind-verifychain.service",
+                description: "chain verification",
+            },
+            BitcoindConfigOption::VerifyChainSchedule.to_option_id(),
+        ),
+        (
+            MaintenanceTask {
+                app: SupportedApps::LND,
+                unit: "lnd-compact-db.service",
+                description: "database compaction",
+            },
+            LndConfigOption::CompactDbSchedule.to_option_id(),
+        ),
+    ]
+}
+
+/// Returns the tasks of the enabled apps that have a schedule
+pub fn scheduled_tasks(project: &Project) -> Vec<MaintenanceTask> {
+    tasks()
+        .into_iter()
+        .filter(|(task, _)| project.is_app_enabled(task.app))
+        .filter(|(task, id)| {
+            project.get_options_for(task.app).iter().any(|o| match o {
+                OptionData::TextEdit(t) => t.id() == id && !t.value().trim().is_empty(),
+                _ => false,
+            })
+        })
+        .map(|(task, _)| task)
+        .collect()
+}
+
+/// The last run of a task that didn't succeed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedTask {
+    pub task: MaintenanceTask,
+
+    /// The result systemd recorded, e.g. exit-code
+    pub result: String,
+
+    /// When the run ended, empty if unknown
+    pub finished: String,
+}
+
+impl fmt::Display for FailedTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The scheduled {} of {} failed ({})",
+            self.task.description, self.task.app, self.result
+        )?;
+        if !self.finished.is_empty() {
+            write!(f, " at {}", self.finished)?;
+        }
+        write!(f, ", see journalctl -u {}", self.task.unit)
+    }
+}
+
+/// Checks the last run of every scheduled task
+///
+/// # Errors
+/// [ProjectError::MaintenanceError] if systemctl can't be run, e.g. when not
+/// running on the node
+pub fn failed_tasks(project: &Project) -> Result<Vec<FailedTask>, ProjectError> {
+    let mut res = vec![];
+    for task in scheduled_tasks(project) {
+        let output = Command::new("systemctl")
+            .args(["show", "--property=Result,ExecMainExitTimestamp", task.unit])
+            .output()
+            .change_context(ProjectError::MaintenanceError)
+            .attach_printable("Unable to run systemctl")?;
+
+        if !output.status.success() {
+            return Err(
+                Report::new(ProjectError::MaintenanceError).attach_printable(format!(
+                    "Unable to read the state of {}: {}",
+                    task.unit,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )),
+            );
+        }
+
+        if let Some(failed) = parse_show(task, &String::from_utf8_lossy(&output.stdout)) {
+            res.push(failed);
+        }
+    }
+
+    Ok(res)
+}
+
+/// Parses the output of `systemctl show`, a unit that never ran reports success
+fn parse_show(task: MaintenanceTask, output: &str) -> Option<FailedTask> {
+    let property = |name: &str| {
+        output
+            .lines()
+            .find_map(|l| l.strip_prefix(name)?.strip_prefix('='))
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let result = property("Result");
+    if result.is_empty() || result == "success" {
+        return None;
+    }
+
+    Some(FailedTask {
+        task,
+        result,
+        finished: property("ExecMainExitTimestamp"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::{
+            bool_data::BoolOptionChangeData, option_data::OptionDataChangeNotification,
+            text_edit_data::TextOptionChangeData,
+        },
+        utils::init_default_project,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_scheduled_tasks() {
+        let dir = tempdir().unwrap();
+        init_default_project(dir.path(), Some(false)).unwrap();
+        let mut project = Project::load(dir.path().to_path_buf()).unwrap();
+        assert!(scheduled_tasks(&project).is_empty());
+
+        project.set_selected_app(SupportedApps::LND);
+        project
+            .on_option_changed(OptionDataChangeNotification::TextEdit(
+                TextOptionChangeData::new(
+                    LndConfigOption::CompactDbSchedule.to_option_id(),
+                    "weekly".to_string(),
+                ),
+            ))
+            .unwrap();
+        // LND is still disabled
+        assert!(scheduled_tasks(&project).is_empty());
+
+        project
+            .on_option_changed(OptionDataChangeNotification::Bool(
+                BoolOptionChangeData::new(LndConfigOption::Enable.to_option_id(), true),
+            ))
+            .unwrap();
+        let tasks = scheduled_tasks(&project);
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].unit, "lnd-compact-db.service");
+    }
+
+    #[test]
+    fn test_parse_show() {
+        let task = tasks()[0].0;
+        assert_eq!(
+            parse_show(task, "Result=success\nExecMainExitTimestamp=\n"),
+            None
+        );
+
+        let failed = parse_show(
+            task,
+            "Result=exit-code\nExecMainExitTimestamp=Sun 2024-11-03 04:12:09 CET\n",
+        )
+        .unwrap();
+        assert_eq!(failed.result, "exit-code");
+        assert_eq!(
+            failed.to_string(),
+            "The scheduled chain verification of Bitcoin Core failed (exit-code) at \
+             Sun 2024-11-03 04:12:09 CET, see journalctl -u bitcoind-verifychain.service"
+        );
+    }
+}
@@ -0,0 +1,552 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_option_data::{
+        bool_data::BoolOptionData,
+        net_address_data::NetAddressOptionData,
+        option_data::{
+            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToNixString,
+            ToOptionId,
+        },
+        port_data::PortOptionData,
+        string_list_data::{StringListOptionData, StringListOptionItem},
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    reverse_proxy::{ProxyRoute, ProxyRouteProvider},
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/mempool.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/mempool.json";
+
+/// The Electrum server the mempool backend queries for address lookups
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ElectrumServer {
+    Electrs,
+    Fulcrum,
+}
+
+impl ElectrumServer {
+    pub fn to_string_array() -> [&'static str; 2] {
+        ["electrs", "fulcrum"]
+    }
+}
+
+impl fmt::Display for ElectrumServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let server_str = match self {
+            ElectrumServer::Electrs => "electrs",
+            ElectrumServer::Fulcrum => "fulcrum",
+        };
+        write!(f, "{}", server_str)
+    }
+}
+
+impl FromStr for ElectrumServer {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<ElectrumServer, ()> {
+        match s {
+            "electrs" => Ok(ElectrumServer::Electrs),
+            "fulcrum" => Ok(ElectrumServer::Fulcrum),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct MempoolService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// Address the backend API listens on
+    pub address: Box<NetAddressOptionData>,
+
+    /// Port the backend API listens on
+    ///
+    /// Default: 8999
+    pub port: Box<PortOptionData>,
+
+    /// Address the frontend listens on
+    pub frontend_address: Box<NetAddressOptionData>,
+
+    /// Port the frontend listens on
+    ///
+    /// Default: 60845
+    pub frontend_port: Box<PortOptionData>,
+
+    /// The Electrum server to use as the address index backend
+    pub electrum_server: Box<StringListOptionData>,
+
+    /// Name of the MySQL database
+    ///
+    /// Default: "mempool"
+    pub db_name: Box<TextOptionData>,
+
+    /// Name of the MySQL user
+    ///
+    /// Default: "mempool"
+    pub db_user: Box<TextOptionData>,
+
+    /// Whether to expose the frontend via nginx
+    pub nginx_enable: Box<BoolOptionData>,
+
+    /// The nginx location the frontend is served from
+    ///
+    /// Default: "/mempool"
+    pub nginx_location: Box<TextOptionData>,
+
+    /// Whether to serve the location via HTTPS, see the TLS options of the
+    /// base config
+    pub nginx_https: Box<BoolOptionData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MempoolConfigOption {
+    Enable,
+    Address,
+    Port,
+    FrontendAddress,
+    FrontendPort,
+    ElectrumServer,
+    DbName,
+    DbUser,
+    NginxEnable,
+    NginxLocation,
+    NginxHttps,
+}
+
+impl ToOptionId for MempoolConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::Mempool, self.to_string())
+    }
+}
+
+impl FromStr for MempoolConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<MempoolConfigOption, ()> {
+        match s {
+            "enable" => Ok(MempoolConfigOption::Enable),
+            "address" => Ok(MempoolConfigOption::Address),
+            "port" => Ok(MempoolConfigOption::Port),
+            "frontend_address" => Ok(MempoolConfigOption::FrontendAddress),
+            "frontend_port" => Ok(MempoolConfigOption::FrontendPort),
+            "electrum_server" => Ok(MempoolConfigOption::ElectrumServer),
+            "db_name" => Ok(MempoolConfigOption::DbName),
+            "db_user" => Ok(MempoolConfigOption::DbUser),
+            "nginx_enable" => Ok(MempoolConfigOption::NginxEnable),
+            "nginx_location" => Ok(MempoolConfigOption::NginxLocation),
+            "nginx_https" => Ok(MempoolConfigOption::NginxHttps),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for MempoolConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            MempoolConfigOption::Enable => "enable",
+            MempoolConfigOption::Address => "address",
+            MempoolConfigOption::Port => "port",
+            MempoolConfigOption::FrontendAddress => "frontend_address",
+            MempoolConfigOption::FrontendPort => "frontend_port",
+            MempoolConfigOption::ElectrumServer => "electrum_server",
+            MempoolConfigOption::DbName => "db_name",
+            MempoolConfigOption::DbUser => "db_user",
+            MempoolConfigOption::NginxEnable => "nginx_enable",
+            MempoolConfigOption::NginxLocation => "nginx_location",
+            MempoolConfigOption::NginxHttps => "nginx_https",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for MempoolService {
+    fn get_options(&self) -> Vec<OptionData> {
+        vec![
+            OptionData::Bool(self.enable.clone()),
+            OptionData::NetAddress(self.address.clone()),
+            OptionData::Port(self.port.clone()),
+            OptionData::NetAddress(self.frontend_address.clone()),
+            OptionData::Port(self.frontend_port.clone()),
+            OptionData::StringList(self.electrum_server.clone()),
+            OptionData::TextEdit(self.db_name.clone()),
+            OptionData::TextEdit(self.db_user.clone()),
+            OptionData::Bool(self.nginx_enable.clone()),
+            OptionData::TextEdit(self.nginx_location.clone()),
+            OptionData::Bool(self.nginx_https.clone()),
+        ]
+    }
+
+    fn app_option_changed(
+        &mut self,
+        option: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let id = option.id();
+        if let Ok(opt) = MempoolConfigOption::from_str(&id.option) {
+            let mut res = Ok(false);
+            match opt {
+                MempoolConfigOption::Enable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.enable.value() != val.value);
+                        self.enable.set_value(val.value);
+                    }
+                }
+                MempoolConfigOption::Address => {
+                    if let OptionDataChangeNotification::NetAddress(val) = option {
+                        res = Ok(self.address.value() != val.value);
+                        self.address.set_value(val.value);
+                    }
+                }
+                MempoolConfigOption::Port => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.port.value() != val.value);
+                        self.port.set_value(val.value.clone());
+                    }
+                }
+                MempoolConfigOption::FrontendAddress => {
+                    if let OptionDataChangeNotification::NetAddress(val) = option {
+                        res = Ok(self.frontend_address.value() != val.value);
+                        self.frontend_address.set_value(val.value);
+                    }
+                }
+                MempoolConfigOption::FrontendPort => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.frontend_port.value() != val.value);
+                        self.frontend_port.set_value(val.value.clone());
+                    }
+                }
+                MempoolConfigOption::ElectrumServer => {
+                    if let OptionDataChangeNotification::StringList(val) = option {
+                        if ElectrumServer::from_str(&val.value).is_err() {
+                            return Err(Report::new(ProjectError::ChangeOptionValueError(
+                                opt.to_string(),
+                            ))
+                            .attach_printable(format!("Unknown electrum server {}", val.value)));
+                        }
+                        res = Ok(self.electrum_server.value() != val.value);
+                        self.electrum_server.set_value(val.value.clone());
+                    }
+                }
+                MempoolConfigOption::DbName => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.db_name.value() != val.value);
+                        self.db_name.set_value(val.value.clone());
+                    }
+                }
+                MempoolConfigOption::DbUser => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.db_user.value() != val.value);
+                        self.db_user.set_value(val.value.clone());
+                    }
+                }
+                MempoolConfigOption::NginxEnable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.nginx_enable.value() != val.value);
+                        self.nginx_enable.set_value(val.value);
+                    }
+                }
+                MempoolConfigOption::NginxLocation => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.nginx_location.value() != val.value);
+                        self.nginx_location.set_value(val.value.clone());
+                    }
+                }
+                MempoolConfigOption::NginxHttps => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.nginx_https.value() != val.value);
+                        self.nginx_https.set_value(val.value);
+                    }
+                }
+            }
+
+            return res;
+        }
+
+        Ok(false)
+    }
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering mempool config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Default for MempoolService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                MempoolConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            address: Box::new(NetAddressOptionData::new(
+                MempoolConfigOption::Address.to_option_id(),
+                Some(defaults::LOCALHOST),
+            )),
+            port: Box::new(PortOptionData::new(
+                MempoolConfigOption::Port.to_option_id(),
+                NumberValue::U16(Some(defaults::mempool::PORT)),
+            )),
+            frontend_address: Box::new(NetAddressOptionData::new(
+                MempoolConfigOption::FrontendAddress.to_option_id(),
+                Some(defaults::LOCALHOST),
+            )),
+            frontend_port: Box::new(PortOptionData::new(
+                MempoolConfigOption::FrontendPort.to_option_id(),
+                NumberValue::U16(Some(defaults::mempool::FRONTEND_PORT)),
+            )),
+            electrum_server: Box::new(StringListOptionData::new(
+                MempoolConfigOption::ElectrumServer.to_option_id(),
+                ElectrumServer::Electrs.to_string(),
+                ElectrumServer::to_string_array()
+                    .map(|entry| StringListOptionItem::new(entry.to_string(), entry.to_string()))
+                    .to_vec(),
+            )),
+            db_name: Box::new(TextOptionData::new(
+                MempoolConfigOption::DbName.to_option_id(),
+                defaults::mempool::DB_NAME.to_string(),
+                1,
+                false,
+                defaults::mempool::DB_NAME.to_string(),
+            )),
+            db_user: Box::new(TextOptionData::new(
+                MempoolConfigOption::DbUser.to_option_id(),
+                defaults::mempool::DB_USER.to_string(),
+                1,
+                false,
+                defaults::mempool::DB_USER.to_string(),
+            )),
+            nginx_enable: Box::new(BoolOptionData::new(
+                MempoolConfigOption::NginxEnable.to_option_id(),
+                false,
+            )),
+            nginx_location: Box::new(TextOptionData::new(
+                MempoolConfigOption::NginxLocation.to_option_id(),
+                defaults::mempool::NGINX_LOCATION.to_string(),
+                1,
+                false,
+                defaults::mempool::NGINX_LOCATION.to_string(),
+            )),
+            nginx_https: Box::new(BoolOptionData::new(
+                MempoolConfigOption::NginxHttps.to_option_id(),
+                false,
+            )),
+        }
+    }
+}
+
+impl ProxyRouteProvider for MempoolService {
+    fn proxy_route(&self) -> Option<ProxyRoute> {
+        if !self.enable.value() || !self.nginx_enable.value() {
+            return None;
+        }
+
+        let address = self.frontend_address.value().unwrap_or(defaults::LOCALHOST);
+        let port = self
+            .frontend_port
+            .value()
+            .to_string_or(&defaults::mempool::FRONTEND_PORT.to_string());
+
+        Some(ProxyRoute::new(
+            SupportedApps::Mempool,
+            MempoolConfigOption::NginxEnable.to_option_id(),
+            self.nginx_location.value(),
+            Some(format!("http://{}:{}/", address, port)),
+            true,
+            self.nginx_https.value(),
+        ))
+    }
+}
+
+impl MempoolService {
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let port = self
+            .port
+            .value()
+            .to_string_or(&defaults::mempool::PORT.to_string());
+        let frontend_port = self
+            .frontend_port
+            .value()
+            .to_string_or(&defaults::mempool::FRONTEND_PORT.to_string());
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            ("address", self.address.to_nix_string(true)),
+            ("port", port),
+            (
+                "frontend_address",
+                self.frontend_address.to_nix_string(true),
+            ),
+            ("frontend_port", frontend_port),
+            ("electrum_server", self.electrum_server.value().to_string()),
+            ("db_name", self.db_name.value().to_string()),
+            ("db_user", self.db_user.value().to_string()),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render mempool template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<MempoolService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::string_list_data::StringListOptionChangeData, utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn get_test_service() -> MempoolService {
+        let mut service = MempoolService::default();
+        service.enable.set_value(true);
+        service.nginx_enable.set_value(true);
+        service.nginx_location.set_value("/explorer".into());
+        service
+            .electrum_server
+            .set_value(ElectrumServer::Fulcrum.to_string());
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = MempoolService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_rejects_unknown_electrum_server() {
+        let mut service = MempoolService::default();
+        let res = service.app_option_changed(&OptionDataChangeNotification::StringList(
+            StringListOptionChangeData::new(
+                MempoolConfigOption::ElectrumServer.to_option_id(),
+                "esplora".into(),
+            ),
+        ));
+        assert!(res.is_err());
+        assert_eq!(service.electrum_server.value(), "electrs");
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains(&format!("enable = {};", s.enable.value())));
+            assert!(data.contains(&format!(
+                "address = lib.mkDefault {};",
+                s.address.to_nix_string(true)
+            )));
+            assert!(data.contains(&format!("port = {};", s.port.value())));
+            assert!(data.contains("electrumServer = \"fulcrum\";"));
+            assert!(data.contains(&format!("port = {};", s.frontend_port.value())));
+            assert!(data.contains("DATABASE = \"mempool\";"));
+        }
+
+        assert!(result.is_ok());
+    }
+}
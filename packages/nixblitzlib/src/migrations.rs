@@ -0,0 +1,395 @@
+//! Upgrades the JSON files of projects written by older nixblitz releases.
+//!
+//! Every app file carries a [VERSION_KEY] field, files written before it was
+//! introduced are version 0. [Project::load](crate::project::Project::load)
+//! runs the [MIGRATIONS] above the lowest version found, e.g. to rename an
+//! option or move a file, and stamps the files with [CONFIG_VERSION].
+//! Options missing in a file get their default value, so adding an option
+//! needs no migration. The files of apps missing in a project, e.g. of apps
+//! added by a newer release, are created with their defaults. Projects of a
+//! newer release are refused instead of being loaded half.
+
+use std::{fs, path::Path};
+
+use error_stack::{Report, Result, ResultExt};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    apps::SupportedApps,
+    errors::{ProjectError, TemplatingError},
+    registry::{self, AppRegistration},
+    utils::{create_file, load_json_file, update_file},
+};
+
+/// The version of the app files written by this release
+pub const CONFIG_VERSION: u32 = 1;
+
+/// The field holding the version in every app file
+pub const VERSION_KEY: &str = "config_version";
+
+/// The original files are copied to `<BACKUP_DIR>/v<version>` before they
+/// are migrated
+pub const BACKUP_DIR: &str = ".nixblitz/migrations";
+
+/// A step that upgrades the project files to a new version
+pub struct Migration {
+    /// The version the files have afterwards
+    pub version: u32,
+
+    /// What changes, e.g. "Renames lnd rpc_listen to rpc_address"
+    pub description: &'static str,
+
+    /// Moves or rewrites files in the work dir. Runs before the JSON data of
+    /// the apps is changed.
+    pub files: fn(&Path) -> Result<(), ProjectError>,
+
+    /// Changes the JSON data of an app, e.g. renames its options
+    pub json: fn(SupportedApps, &mut Map<String, Value>),
+}
+
+/// All migrations, ordered by version. Version 1 only introduced
+/// [VERSION_KEY].
+pub static MIGRATIONS: &[Migration] = &[];
+
+#[derive(Serialize)]
+struct Versioned<'a, T: Serialize> {
+    config_version: u32,
+
+    #[serde(flatten)]
+    config: &'a T,
+}
+
+/// Serializes the config of an app with the current [CONFIG_VERSION]
+pub(crate) fn to_json_string<T: Serialize>(config: &T) -> Result<String, TemplatingError> {
+    serde_json::to_string(&Versioned {
+        config_version: CONFIG_VERSION,
+        config,
+    })
+    .change_context(TemplatingError::JsonRenderError)
+}
+
+/// Returns the version of the JSON data of an app, 0 if it has none
+pub fn version_of(json: &Value) -> u32 {
+    json.get(VERSION_KEY)
+        .and_then(Value::as_u64)
+        .and_then(|v| u32::try_from(v).ok())
+        .unwrap_or(0)
+}
+
+/// Renames an option of an app and updates its id
+pub fn rename_option(json: &mut Map<String, Value>, from: &str, to: &str) {
+    let Some(mut option) = json.remove(from) else {
+        return;
+    };
+    if let Some(id) = option.get_mut("id").and_then(Value::as_object_mut) {
+        id.insert("option".into(), Value::from(to));
+    }
+    json.insert(to.into(), option);
+}
+
+/// Moves a file inside the work dir, if it exists
+pub fn move_file(work_dir: &Path, from: &str, to: &str) -> Result<(), ProjectError> {
+    let from = work_dir.join(from);
+    if !from.exists() {
+        return Ok(());
+    }
+    let to = work_dir.join(to);
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).change_context(ProjectError::MigrationError)?;
+    }
+
+    fs::rename(&from, &to)
+        .change_context(ProjectError::MigrationError)
+        .attach_printable_lazy(|| format!("Unable to move {} to {}", from.display(), to.display()))
+}
+
+/// Adds the keys of `default` missing in `json`, recursing into objects
+fn fill_defaults(json: &mut Map<String, Value>, default: &Map<String, Value>) {
+    for (key, default) in default {
+        match (json.get_mut(key), default) {
+            (None, _) => {
+                json.insert(key.clone(), default.clone());
+            }
+            (Some(Value::Object(value)), Value::Object(default)) => fill_defaults(value, default),
+            _ => {}
+        }
+    }
+}
+
+/// Upgrades the JSON data of an app to [CONFIG_VERSION]
+///
+/// # Returns
+/// Whether the data changed
+fn migrate_value(
+    reg: &AppRegistration,
+    json: &mut Value,
+    migrations: &[Migration],
+) -> Result<bool, ProjectError> {
+    let version = version_of(json);
+    if version > CONFIG_VERSION {
+        return Err(Report::new(ProjectError::ConfigTooNew(
+            version,
+            CONFIG_VERSION,
+        )))
+        .attach_printable(format!("Found in {}", reg.json_file_name));
+    }
+
+    let default: Value = (reg.default_json)()
+        .and_then(|d| serde_json::from_str(&d).change_context(TemplatingError::JsonLoadError))
+        .change_context(ProjectError::MigrationError)?;
+    let (Some(map), Some(default)) = (json.as_object_mut(), default.as_object()) else {
+        return Err(Report::new(ProjectError::MigrationError))
+            .attach_printable(format!("{} is not a JSON object", reg.json_file_name));
+    };
+
+    let original = map.clone();
+    for migration in migrations.iter().filter(|m| m.version > version) {
+        (migration.json)(reg.app, map);
+    }
+    fill_defaults(map, default);
+    map.insert(VERSION_KEY.into(), Value::from(CONFIG_VERSION));
+
+    Ok(*map != original)
+}
+
+/// Upgrades the JSON data of an app in memory, e.g. of a file read from git.
+/// Files are not moved.
+///
+/// # Errors
+/// [ProjectError::ConfigTooNew] if the data is of a newer release,
+/// [ProjectError::MigrationError] if it isn't a JSON object
+pub fn migrate_json(app: SupportedApps, json: &str) -> Result<String, ProjectError> {
+    let mut value: Value =
+        serde_json::from_str(json).change_context(ProjectError::MigrationError)?;
+    migrate_value(registry::registration(app), &mut value, MIGRATIONS)?;
+
+    serde_json::to_string(&value).change_context(ProjectError::MigrationError)
+}
+
+/// Upgrades the app files in the work dir to [CONFIG_VERSION] and creates
+/// the missing ones with their defaults. Files that are no valid JSON are
+/// left to the [recovery](crate::recovery).
+///
+/// # Returns
+/// The JSON files that were changed or created
+///
+/// # Errors
+/// [ProjectError::ConfigTooNew] if any file is of a newer release,
+/// [ProjectError::MigrationError] if a file can't be migrated
+pub fn migrate(work_dir: &Path) -> Result<Vec<&'static str>, ProjectError> {
+    migrate_with(work_dir, MIGRATIONS)
+}
+
+fn migrate_with(
+    work_dir: &Path,
+    migrations: &[Migration],
+) -> Result<Vec<&'static str>, ProjectError> {
+    let mut files = vec![];
+    let mut missing = vec![];
+    for reg in registry::APPS.iter() {
+        let path = work_dir.join(reg.json_file_name);
+        if !path.exists() {
+            missing.push(reg);
+            continue;
+        }
+        let Ok(json) = load_json_file(&path) else {
+            continue;
+        };
+        let Ok(value) = serde_json::from_str::<Value>(&json) else {
+            continue;
+        };
+        files.push((reg, json, value));
+    }
+
+    // no app file at all, this isn't a project
+    let Some(version) = files.iter().map(|(_, _, v)| version_of(v)).min() else {
+        return Ok(vec![]);
+    };
+    if let Some((reg, _, value)) = files
+        .iter()
+        .find(|(_, _, v)| version_of(v) > CONFIG_VERSION)
+    {
+        return Err(Report::new(ProjectError::ConfigTooNew(
+            version_of(value),
+            CONFIG_VERSION,
+        )))
+        .attach_printable(format!("Found in {}", reg.json_file_name));
+    }
+    for migration in migrations.iter().filter(|m| m.version > version) {
+        (migration.files)(work_dir)
+            .attach_printable_lazy(|| format!("Migration: {}", migration.description))?;
+    }
+
+    let mut res = vec![];
+    for reg in missing {
+        create_missing_files(work_dir, reg)?;
+        res.push(reg.json_file_name);
+    }
+
+    for (reg, json, mut value) in files {
+        let old_version = version_of(&value);
+        if !migrate_value(reg, &mut value, migrations)? {
+            continue;
+        }
+
+        let backup = Path::new(BACKUP_DIR)
+            .join(format!("v{}", old_version))
+            .join(reg.json_file_name);
+        create_file(&work_dir.join(backup), json.as_bytes(), Some(true))?;
+
+        let migrated =
+            serde_json::to_string(&value).change_context(ProjectError::MigrationError)?;
+        update_file(&work_dir.join(reg.json_file_name), migrated.as_bytes())?;
+        res.push(reg.json_file_name);
+    }
+
+    Ok(res)
+}
+
+/// Creates the default files of an app. If only its JSON file is missing,
+/// e.g. because it was deleted by hand, the nix files are kept.
+fn create_missing_files(work_dir: &Path, reg: &AppRegistration) -> Result<(), ProjectError> {
+    let nix_file = work_dir.join(reg.template_file_name.replace(".templ", ""));
+    if !nix_file.exists() {
+        return registry::create_default_files(reg.app, work_dir, Some(false));
+    }
+
+    let json = (reg.default_json)().change_context(ProjectError::MigrationError)?;
+    create_file(
+        &work_dir.join(reg.json_file_name),
+        json.as_bytes(),
+        Some(false),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{
+        lnd::LightningNetworkDaemonService, project::Project, utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn lnd_json() -> Value {
+        let json = LightningNetworkDaemonService::default()
+            .to_json_string()
+            .unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_to_json_string() {
+        let json = lnd_json();
+        assert_eq!(version_of(&json), CONFIG_VERSION);
+        assert!(json.get("enable").is_some());
+
+        // the version is ignored when loading
+        LightningNetworkDaemonService::from_json(&json.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_json() {
+        let mut json = lnd_json();
+        let map = json.as_object_mut().unwrap();
+        map.remove(VERSION_KEY);
+        map.remove("alias");
+        map["port"].as_object_mut().unwrap().remove("dirty");
+
+        let migrated = migrate_json(SupportedApps::LND, &json.to_string()).unwrap();
+        let migrated: Value = serde_json::from_str(&migrated).unwrap();
+        assert_eq!(migrated, lnd_json());
+
+        json[VERSION_KEY] = Value::from(CONFIG_VERSION + 1);
+        let err = migrate_json(SupportedApps::LND, &json.to_string()).unwrap_err();
+        assert!(matches!(
+            err.current_context(),
+            ProjectError::ConfigTooNew(_, CONFIG_VERSION)
+        ));
+    }
+
+    #[test]
+    fn test_rename_option() {
+        let mut json = lnd_json();
+        let map = json.as_object_mut().unwrap();
+        let port = map.remove("port").unwrap();
+        map.insert("listen_port".into(), port);
+        map["listen_port"]["id"]["option"] = Value::from("listen_port");
+
+        rename_option(map, "listen_port", "port");
+        assert_eq!(json, lnd_json());
+    }
+
+    #[test]
+    fn test_migrate() {
+        let dir = tempdir().unwrap();
+        let work_dir = dir.path().to_path_buf();
+        init_default_project(&work_dir, Some(true)).unwrap();
+        assert!(migrate(&work_dir).unwrap().is_empty());
+
+        let lnd_file = work_dir.join(crate::lnd::JSON_FILE_NAME);
+        let mut json = lnd_json();
+        let map = json.as_object_mut().unwrap();
+        map.remove(VERSION_KEY);
+        map.insert("listen_port".into(), map["port"].clone());
+        map.remove("port");
+        fs::write(&lnd_file, json.to_string()).unwrap();
+        fs::write(work_dir.join("old.nix"), "{}").unwrap();
+
+        let migrations = [Migration {
+            version: 1,
+            description: "Renames the lnd listen_port option",
+            files: |work_dir| move_file(work_dir, "old.nix", "src/new.nix"),
+            json: |app, json| {
+                if app == SupportedApps::LND {
+                    rename_option(json, "listen_port", "port");
+                }
+            },
+        }];
+        let res = migrate_with(&work_dir, &migrations).unwrap();
+        assert_eq!(res, vec![crate::lnd::JSON_FILE_NAME]);
+
+        let migrated: Value =
+            serde_json::from_str(&fs::read_to_string(&lnd_file).unwrap()).unwrap();
+        assert_eq!(migrated, lnd_json());
+        assert!(work_dir.join("src/new.nix").exists());
+        assert!(work_dir
+            .join(BACKUP_DIR)
+            .join("v0")
+            .join(crate::lnd::JSON_FILE_NAME)
+            .exists());
+
+        Project::load(work_dir).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_creates_missing_files() {
+        let dir = tempdir().unwrap();
+        let work_dir = dir.path().to_path_buf();
+        init_default_project(&work_dir, Some(true)).unwrap();
+
+        let lnd_file = work_dir.join(crate::lnd::JSON_FILE_NAME);
+        fs::remove_file(&lnd_file).unwrap();
+        let mining = registry::registration(SupportedApps::Mining);
+        fs::remove_file(work_dir.join(mining.json_file_name)).unwrap();
+        fs::remove_file(work_dir.join(mining.template_file_name.replace(".templ", ""))).unwrap();
+
+        let res = migrate(&work_dir).unwrap();
+        assert!(res.contains(&crate::lnd::JSON_FILE_NAME));
+        assert!(res.contains(&mining.json_file_name));
+        assert!(lnd_file.exists());
+        assert!(work_dir
+            .join(mining.template_file_name.replace(".templ", ""))
+            .exists());
+        assert!(migrate(&work_dir).unwrap().is_empty());
+
+        Project::load(work_dir.clone()).unwrap();
+
+        // an empty dir is no project, nothing is created
+        let empty = tempdir().unwrap();
+        assert!(migrate(empty.path()).unwrap().is_empty());
+        assert!(!empty.path().join(crate::lnd::JSON_FILE_NAME).exists());
+    }
+}
@@ -0,0 +1,519 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    app_config::{app_options, AppConfig},
+    app_option_data::{
+        bool_data::BoolOptionData,
+        option_data::{OptionDataChangeNotification, OptionId, ToOptionId},
+        port_data::PortOptionData,
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    bitcoind::BitcoinDaemonService,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    tor::port_or,
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/mining.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/mining.json";
+
+/// The bitcoind RPC user the gateway fetches the block templates with
+pub const BITCOIND_RPC_USER: &str = "datum";
+
+/// Runs the DATUM gateway for solo mining against the local bitcoind.
+///
+/// The gateway builds the block templates itself and hands out work to the
+/// miners via Stratum. A found block pays the whole reward to the payout
+/// address. It connects to bitcoind with a dedicated RPC user that is set
+/// when the app is enabled, nix-bitcoin generates its password on the node.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct MiningService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// The port the miners connect to
+    pub stratum_port: Box<PortOptionData>,
+
+    /// The address the block reward is paid to
+    pub payout_address: Box<TextOptionData>,
+
+    /// The bitcoind RPC user to fetch the block templates with. Set when the
+    /// service is enabled, nix-bitcoin generates its password on the node.
+    pub bitcoind_rpc_user: String,
+
+    /// The RPC port of bitcoind, derived from the bitcoind config
+    pub bitcoind_rpc_port: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MiningConfigOption {
+    Enable,
+    StratumPort,
+    PayoutAddress,
+}
+
+impl ToOptionId for MiningConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::Mining, self.to_string())
+    }
+}
+
+impl FromStr for MiningConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<MiningConfigOption, ()> {
+        match s {
+            "enable" => Ok(MiningConfigOption::Enable),
+            "stratum_port" => Ok(MiningConfigOption::StratumPort),
+            "payout_address" => Ok(MiningConfigOption::PayoutAddress),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for MiningConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            MiningConfigOption::Enable => "enable",
+            MiningConfigOption::StratumPort => "stratum_port",
+            MiningConfigOption::PayoutAddress => "payout_address",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+/// Characters of the data part of bech32 addresses, in the order of their values
+const BECH32_CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Characters of base58 addresses, in the order of their values
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Checks that the given string is a valid address of mainnet, testnet or regtest
+///
+/// Both the segwit addresses (bech32 and bech32m) and the legacy base58
+/// addresses are accepted, including their checksums.
+///
+/// # Returns
+/// A description of the problem if the address is invalid
+pub fn check_payout_address(address: &str) -> std::result::Result<(), String> {
+    let lower = address.to_lowercase();
+    let is_segwit = ["bc1", "tb1", "bcrt1"].iter().any(|p| lower.starts_with(p));
+    let valid = if is_segwit {
+        is_valid_segwit_address(address)
+    } else {
+        is_valid_base58_address(address)
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("\"{}\" is not a valid bitcoin address", address))
+    }
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GEN: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+    let mut chk: u32 = 1;
+    for v in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ u32::from(*v);
+        for (i, g) in GEN.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+
+    chk
+}
+
+/// See BIP 173 and BIP 350
+fn is_valid_segwit_address(address: &str) -> bool {
+    // mixed case is not allowed
+    if address.len() > 90
+        || (address.to_lowercase() != address && address.to_uppercase() != address)
+    {
+        return false;
+    }
+
+    let address = address.to_lowercase();
+    let Some((hrp, data)) = address.rsplit_once('1') else {
+        return false;
+    };
+    if !["bc", "tb", "bcrt"].contains(&hrp) {
+        return false;
+    }
+
+    let Some(data): Option<Vec<u8>> = data
+        .chars()
+        .map(|c| BECH32_CHARSET.find(c).map(|v| v as u8))
+        .collect()
+    else {
+        return false;
+    };
+
+    // the witness version and the checksum
+    if data.len() < 7 {
+        return false;
+    }
+
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    values.extend(&data);
+
+    let version = data[0];
+    let expected = match version {
+        0 => 1,
+        1..=16 => 0x2bc830a3,
+        _ => return false,
+    };
+    if bech32_polymod(&values) != expected {
+        return false;
+    }
+
+    // the witness program, converted from 5 to 8 bit groups
+    let bits = (data.len() - 7) * 5;
+    let program_len = bits / 8;
+    if bits % 8 >= 5 || !(2..=40).contains(&program_len) {
+        return false;
+    }
+
+    version != 0 || program_len == 20 || program_len == 32
+}
+
+/// Legacy P2PKH and P2SH addresses
+fn is_valid_base58_address(address: &str) -> bool {
+    let mut bytes: Vec<u8> = vec![];
+    for c in address.chars() {
+        let Some(mut carry) = BASE58_ALPHABET.find(c).map(|v| v as u32) else {
+            return false;
+        };
+        for b in bytes.iter_mut().rev() {
+            carry += u32::from(*b) * 58;
+            *b = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    // every leading 1 is a leading zero byte
+    let zeros = address.chars().take_while(|c| *c == '1').count();
+    let mut decoded = vec![0u8; zeros];
+    decoded.extend(bytes);
+
+    if decoded.len() != 25 || ![0x00, 0x05, 0x6f, 0xc4].contains(&decoded[0]) {
+        return false;
+    }
+
+    let checksum = Sha256::digest(Sha256::digest(&decoded[..21]));
+    checksum[..4] == decoded[21..]
+}
+
+impl AppConfig for MiningService {
+    app_options!(MiningConfigOption {
+        Enable => enable,
+        StratumPort => stratum_port,
+        PayoutAddress => payout_address where validate_payout_address,
+    });
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering mining config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Default for MiningService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                MiningConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            stratum_port: Box::new(PortOptionData::new(
+                MiningConfigOption::StratumPort.to_option_id(),
+                NumberValue::U16(Some(defaults::mining::STRATUM_PORT)),
+            )),
+            payout_address: Box::new(TextOptionData::new(
+                MiningConfigOption::PayoutAddress.to_option_id(),
+                String::new(),
+                1,
+                false,
+                String::new(),
+            )),
+            bitcoind_rpc_user: String::new(),
+            bitcoind_rpc_port: defaults::bitcoind::RPC_PORT,
+        }
+    }
+}
+
+impl MiningService {
+    /// An empty address is allowed, the gateway stays off without one
+    fn validate_payout_address(
+        &self,
+        option: &OptionDataChangeNotification,
+    ) -> Result<(), ProjectError> {
+        let OptionDataChangeNotification::TextEdit(val) = option else {
+            return Ok(());
+        };
+
+        let address = val.value.trim();
+        if address.is_empty() {
+            return Ok(());
+        }
+
+        check_payout_address(address).map_err(|e| {
+            Report::new(ProjectError::InvalidValue(
+                MiningConfigOption::PayoutAddress.to_string(),
+                vec![e],
+            ))
+        })
+    }
+
+    /// Takes the RPC port from the bitcoind config. The RPC user is
+    /// created by the [Project](crate::project::Project).
+    ///
+    /// # Returns
+    /// Whether the port changed
+    pub fn sync_with_bitcoind(&mut self, bitcoin: &BitcoinDaemonService) -> bool {
+        let port = port_or(bitcoin.rpc_port.value(), defaults::bitcoind::RPC_PORT);
+        let changed = self.bitcoind_rpc_port != port;
+        self.bitcoind_rpc_port = port;
+
+        changed
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        // without an address or credentials the gateway can't do anything
+        let enable = self.enable.value()
+            && !self.payout_address.value().trim().is_empty()
+            && !self.bitcoind_rpc_user.is_empty();
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", enable)),
+            (
+                "stratum_port",
+                port_or(self.stratum_port.value(), defaults::mining::STRATUM_PORT).to_string(),
+            ),
+            ("api_port", defaults::mining::API_PORT.to_string()),
+            (
+                "payout_address",
+                self.payout_address.value().trim().to_string(),
+            ),
+            ("bitcoind_rpc_user", self.bitcoind_rpc_user.clone()),
+            ("bitcoind_rpc_port", self.bitcoind_rpc_port.to_string()),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render mining template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<MiningService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::text_edit_data::TextOptionChangeData, utils::init_default_project,
+    };
+
+    use super::*;
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    fn get_test_service() -> MiningService {
+        let mut service = MiningService::default();
+        service.enable.set_value(true);
+        service.payout_address.set_value(ADDRESS.to_string());
+        service.bitcoind_rpc_user = BITCOIND_RPC_USER.to_string();
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+        assert!(!json_content.contains("password"));
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = MiningService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_check_payout_address() {
+        // BIP 173, BIP 350 and well known legacy addresses
+        for address in [
+            ADDRESS,
+            "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+            "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+        ] {
+            assert!(check_payout_address(address).is_ok(), "{}", address);
+        }
+
+        for address in [
+            // wrong checksum
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5",
+            // mixed case
+            "bc1qw508d6qejxtdg4y5r3zarvary0C5xw7kv8f3t4",
+            // v0 with a bech32m checksum
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb",
+            "lnbc1",
+            "",
+        ] {
+            assert!(check_payout_address(address).is_err(), "{}", address);
+        }
+    }
+
+    #[test]
+    fn test_payout_address_option() {
+        let mut s = MiningService::default();
+        let id = MiningConfigOption::PayoutAddress.to_option_id();
+
+        let res = s.app_option_changed(&OptionDataChangeNotification::TextEdit(
+            TextOptionChangeData::new(id.clone(), "not an address".into()),
+        ));
+        assert!(res.is_err());
+        assert!(s.payout_address.value().is_empty());
+
+        let res = s.app_option_changed(&OptionDataChangeNotification::TextEdit(
+            TextOptionChangeData::new(id, format!(" {} ", ADDRESS)),
+        ));
+        assert!(res.unwrap());
+        let data = &s.render().unwrap()[TEMPLATE_FILE_NAME];
+        assert!(data.contains(&format!("pool_address = \"{}\"", ADDRESS)));
+    }
+
+    #[test]
+    fn test_sync_with_bitcoind() {
+        let mut service = MiningService::default();
+        let mut bitcoin = BitcoinDaemonService::default();
+        assert!(!service.sync_with_bitcoind(&bitcoin));
+
+        bitcoin.rpc_port.set_value(NumberValue::U16(Some(18332)));
+        assert!(service.sync_with_bitcoind(&bitcoin));
+        assert_eq!(service.bitcoind_rpc_port, 18332);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut s = get_test_service();
+
+        let data = &s.render().unwrap()[TEMPLATE_FILE_NAME];
+        assert!(data.contains("lib.mkIf true"));
+        assert!(data.contains("listen_port = 23334"));
+        assert!(data.contains(&format!("pool_address = \"{}\"", ADDRESS)));
+        assert!(data.contains("rpcurl = \"http://127.0.0.1:8332\""));
+        assert!(data.contains("rpcuser = \"datum\""));
+        // the password is read from the secrets dir when the gateway starts
+        assert!(!data.contains("rpcpassword = "));
+        assert!(data.contains("secretsDir}/bitcoin-rpcpassword-datum"));
+        assert!(data.contains("datum.passwordHMACFromFile = true;"));
+
+        s.payout_address.set_value(String::new());
+        let data = &s.render().unwrap()[TEMPLATE_FILE_NAME];
+        assert!(data.contains("lib.mkIf false"));
+    }
+}
@@ -0,0 +1,514 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_option_data::{
+        bool_data::BoolOptionData,
+        number_data::NumberOptionData,
+        option_data::{
+            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToOptionId,
+        },
+        port_data::PortOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    platform::get_system_platform,
+    tor::port_or,
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/monitoring.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/monitoring.json";
+
+/// An endpoint Prometheus scrapes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScrapeTarget {
+    /// Name of the scrape job, e.g. "node"
+    pub job: &'static str,
+
+    /// The port the exporter listens on
+    pub port: u16,
+}
+
+impl ScrapeTarget {
+    /// The URL of the metrics page of the exporter on the node
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}/metrics", self.port)
+    }
+}
+
+/// Collects metrics of the host, bitcoind and LND with Prometheus.
+///
+/// The node exporter always runs with Prometheus, the bitcoind and LND
+/// exporters only if the respective app is enabled as well.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct MonitoringService {
+    /// Whether Prometheus is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// The port Prometheus serves its web interface and API on
+    pub port: Box<PortOptionData>,
+
+    /// Number of days to keep the collected metrics
+    pub retention_days: Box<NumberOptionData>,
+
+    /// The port of the node exporter (host metrics)
+    pub node_exporter_port: Box<PortOptionData>,
+
+    /// Whether to export the metrics of bitcoind
+    pub bitcoind_exporter: Box<BoolOptionData>,
+
+    /// The port of the bitcoind exporter
+    pub bitcoind_exporter_port: Box<PortOptionData>,
+
+    /// Whether to export the metrics of LND
+    pub lnd_exporter: Box<BoolOptionData>,
+
+    /// The port of the LND exporter
+    pub lnd_exporter_port: Box<PortOptionData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MonitoringConfigOption {
+    Enable,
+    Port,
+    RetentionDays,
+    NodeExporterPort,
+    BitcoindExporter,
+    BitcoindExporterPort,
+    LndExporter,
+    LndExporterPort,
+}
+
+impl ToOptionId for MonitoringConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::Monitoring, self.to_string())
+    }
+}
+
+impl FromStr for MonitoringConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<MonitoringConfigOption, ()> {
+        match s {
+            "enable" => Ok(MonitoringConfigOption::Enable),
+            "port" => Ok(MonitoringConfigOption::Port),
+            "retention_days" => Ok(MonitoringConfigOption::RetentionDays),
+            "node_exporter_port" => Ok(MonitoringConfigOption::NodeExporterPort),
+            "bitcoind_exporter" => Ok(MonitoringConfigOption::BitcoindExporter),
+            "bitcoind_exporter_port" => Ok(MonitoringConfigOption::BitcoindExporterPort),
+            "lnd_exporter" => Ok(MonitoringConfigOption::LndExporter),
+            "lnd_exporter_port" => Ok(MonitoringConfigOption::LndExporterPort),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for MonitoringConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            MonitoringConfigOption::Enable => "enable",
+            MonitoringConfigOption::Port => "port",
+            MonitoringConfigOption::RetentionDays => "retention_days",
+            MonitoringConfigOption::NodeExporterPort => "node_exporter_port",
+            MonitoringConfigOption::BitcoindExporter => "bitcoind_exporter",
+            MonitoringConfigOption::BitcoindExporterPort => "bitcoind_exporter_port",
+            MonitoringConfigOption::LndExporter => "lnd_exporter",
+            MonitoringConfigOption::LndExporterPort => "lnd_exporter_port",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for MonitoringService {
+    fn get_options(&self) -> Vec<OptionData> {
+        vec![
+            OptionData::Bool(self.enable.clone()),
+            OptionData::Port(self.port.clone()),
+            OptionData::NumberEdit(self.retention_days.clone()),
+            OptionData::Port(self.node_exporter_port.clone()),
+            OptionData::Bool(self.bitcoind_exporter.clone()),
+            OptionData::Port(self.bitcoind_exporter_port.clone()),
+            OptionData::Bool(self.lnd_exporter.clone()),
+            OptionData::Port(self.lnd_exporter_port.clone()),
+        ]
+    }
+
+    fn app_option_changed(
+        &mut self,
+        option: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let id = option.id();
+        if let Ok(opt) = MonitoringConfigOption::from_str(&id.option) {
+            let mut res = Ok(false);
+            match opt {
+                MonitoringConfigOption::Enable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.enable.value() != val.value);
+                        self.enable.set_value(val.value);
+                    }
+                }
+                MonitoringConfigOption::Port => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.port.value() != val.value);
+                        self.port.set_value(val.value.clone());
+                    }
+                }
+                MonitoringConfigOption::RetentionDays => {
+                    if let OptionDataChangeNotification::Number(val) = option {
+                        res = Ok(*self.retention_days.value() != val.value);
+                        self.retention_days.set_value(val.value.clone());
+                    }
+                }
+                MonitoringConfigOption::NodeExporterPort => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.node_exporter_port.value() != val.value);
+                        self.node_exporter_port.set_value(val.value.clone());
+                    }
+                }
+                MonitoringConfigOption::BitcoindExporter => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.bitcoind_exporter.value() != val.value);
+                        self.bitcoind_exporter.set_value(val.value);
+                    }
+                }
+                MonitoringConfigOption::BitcoindExporterPort => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.bitcoind_exporter_port.value() != val.value);
+                        self.bitcoind_exporter_port.set_value(val.value.clone());
+                    }
+                }
+                MonitoringConfigOption::LndExporter => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.lnd_exporter.value() != val.value);
+                        self.lnd_exporter.set_value(val.value);
+                    }
+                }
+                MonitoringConfigOption::LndExporterPort => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.lnd_exporter_port.value() != val.value);
+                        self.lnd_exporter_port.set_value(val.value.clone());
+                    }
+                }
+            }
+
+            return res;
+        }
+
+        Ok(false)
+    }
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering monitoring config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+fn port_option(option: MonitoringConfigOption, port: u16) -> Box<PortOptionData> {
+    Box::new(PortOptionData::new(
+        option.to_option_id(),
+        NumberValue::U16(Some(port)),
+    ))
+}
+
+impl Default for MonitoringService {
+    fn default() -> Self {
+        let retention_days = defaults::get(
+            &get_system_platform(),
+            &MonitoringConfigOption::RetentionDays.to_option_id(),
+            defaults::monitoring::RETENTION_DAYS,
+        );
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                MonitoringConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            port: port_option(MonitoringConfigOption::Port, defaults::monitoring::PORT),
+            retention_days: Box::new(
+                NumberOptionData::new(
+                    MonitoringConfigOption::RetentionDays.to_option_id(),
+                    NumberValue::UInt(Some(retention_days)),
+                    1,
+                    3650,
+                    false,
+                    NumberValue::UInt(Some(retention_days)),
+                )
+                .unwrap(),
+            ),
+            node_exporter_port: port_option(
+                MonitoringConfigOption::NodeExporterPort,
+                defaults::monitoring::NODE_EXPORTER_PORT,
+            ),
+            bitcoind_exporter: Box::new(BoolOptionData::new(
+                MonitoringConfigOption::BitcoindExporter.to_option_id(),
+                true,
+            )),
+            bitcoind_exporter_port: port_option(
+                MonitoringConfigOption::BitcoindExporterPort,
+                defaults::monitoring::BITCOIND_EXPORTER_PORT,
+            ),
+            lnd_exporter: Box::new(BoolOptionData::new(
+                MonitoringConfigOption::LndExporter.to_option_id(),
+                true,
+            )),
+            lnd_exporter_port: port_option(
+                MonitoringConfigOption::LndExporterPort,
+                defaults::monitoring::LND_EXPORTER_PORT,
+            ),
+        }
+    }
+}
+
+impl MonitoringService {
+    /// The endpoints Prometheus scrapes, for linking them from a dashboard.
+    ///
+    /// Prometheus itself is listed as job "prometheus". Empty if
+    /// monitoring is disabled.
+    ///
+    /// # Arguments
+    /// * `bitcoind_enabled` - whether Bitcoin Core is enabled
+    /// * `lnd_enabled` - whether LND is enabled
+    pub fn scrape_targets(&self, bitcoind_enabled: bool, lnd_enabled: bool) -> Vec<ScrapeTarget> {
+        if !self.enable.value() {
+            return vec![];
+        }
+
+        let mut res = vec![];
+        let mut push = |job, port: &PortOptionData| {
+            if let NumberValue::U16(Some(port)) = port.value() {
+                res.push(ScrapeTarget { job, port: *port });
+            }
+        };
+
+        push("prometheus", &self.port);
+        push("node", &self.node_exporter_port);
+        if bitcoind_enabled && self.bitcoind_exporter.value() {
+            push("bitcoind", &self.bitcoind_exporter_port);
+        }
+        if lnd_enabled && self.lnd_exporter.value() {
+            push("lnd", &self.lnd_exporter_port);
+        }
+
+        res
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            (
+                "port",
+                port_or(self.port.value(), defaults::monitoring::PORT).to_string(),
+            ),
+            (
+                "retention_days",
+                self.retention_days
+                    .value()
+                    .to_string_or(&defaults::monitoring::RETENTION_DAYS.to_string()),
+            ),
+            (
+                "node_exporter_port",
+                port_or(
+                    self.node_exporter_port.value(),
+                    defaults::monitoring::NODE_EXPORTER_PORT,
+                )
+                .to_string(),
+            ),
+            (
+                "bitcoind_exporter",
+                format!("{}", self.bitcoind_exporter.value()),
+            ),
+            (
+                "bitcoind_exporter_port",
+                port_or(
+                    self.bitcoind_exporter_port.value(),
+                    defaults::monitoring::BITCOIND_EXPORTER_PORT,
+                )
+                .to_string(),
+            ),
+            ("lnd_exporter", format!("{}", self.lnd_exporter.value())),
+            (
+                "lnd_exporter_port",
+                port_or(
+                    self.lnd_exporter_port.value(),
+                    defaults::monitoring::LND_EXPORTER_PORT,
+                )
+                .to_string(),
+            ),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render monitoring template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<MonitoringService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::utils::init_default_project;
+
+    use super::*;
+
+    fn get_test_service() -> MonitoringService {
+        let mut service = MonitoringService::default();
+        service.enable.set_value(true);
+        service
+            .retention_days
+            .set_value(NumberValue::UInt(Some(30)));
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = MonitoringService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_scrape_targets() {
+        let mut service = get_test_service();
+        let jobs = |s: &MonitoringService, bitcoind, lnd| {
+            s.scrape_targets(bitcoind, lnd)
+                .into_iter()
+                .map(|t| t.job)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(jobs(&service, false, false), vec!["prometheus", "node"]);
+        assert_eq!(
+            jobs(&service, true, true),
+            vec!["prometheus", "node", "bitcoind", "lnd"]
+        );
+
+        service.lnd_exporter.set_value(false);
+        assert_eq!(
+            jobs(&service, true, true),
+            vec!["prometheus", "node", "bitcoind"]
+        );
+        assert_eq!(
+            service.scrape_targets(true, true)[1].url(),
+            format!(
+                "http://127.0.0.1:{}/metrics",
+                defaults::monitoring::NODE_EXPORTER_PORT
+            )
+        );
+
+        service.enable.set_value(false);
+        assert!(service.scrape_targets(true, true).is_empty());
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains("retentionTime = \"30d\";"));
+            assert!(data.contains(&format!(
+                "port = {};",
+                defaults::monitoring::NODE_EXPORTER_PORT
+            )));
+        }
+
+        assert!(result.is_ok());
+    }
+}
@@ -1,27 +1,46 @@
-use alejandra::format;
 use error_stack::{Report, Result, ResultExt};
 use handlebars::{no_escape, Handlebars};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Display, path::Path, str::FromStr};
+use std::{collections::HashMap, fmt::Display, net::IpAddr, path::Path, str::FromStr};
 use strum::EnumCount;
 
 use crate::{
     app_config::AppConfig,
     app_option_data::{
+        apply_change::ApplyChange,
         bool_data::BoolOptionData,
+        manual_string_list_data::ManualStringListOptionData,
+        number_data::NumberOptionData,
         option_data::{
-            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToOptionId,
+            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToNixString,
+            ToOptionId,
         },
         password_data::PasswordOptionData,
+        percent_data::PercentOptionData,
+        port_data::PortOptionData,
         string_list_data::{StringListOptionData, StringListOptionItem},
         text_edit_data::TextOptionData,
     },
     apps::SupportedApps,
+    defaults,
     errors::{ProjectError, TemplatingError},
     locales::LOCALES,
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    platform::{get_system_platform, SystemPlatform},
+    reverse_proxy::{TlsMode, TlsSettings},
+    secret_store::{SecretBackend, SecretRef, SecretSettings},
     strings::INITIAL_PASSWORD,
+    tailscale::check_routes,
     timezones::TIMEZONES,
-    utils::{check_password_validity_confirm, unix_hash_password, update_file, BASE_TEMPLATE},
+    tls_cert,
+    tor::port_or,
+    utils::{
+        check_password_validity_confirm, unix_hash_password, update_file, update_nix_files,
+        BASE_TEMPLATE,
+    },
+    variables::Variables,
 };
 
 pub const TEMPLATE_FILE_NAME: &str = "src/configuration.common.nix.templ";
@@ -63,12 +82,20 @@ pub struct NixBaseConfig {
     /// Example: "nixblitz"
     pub username: String,
 
-    /// Whether to allow SSH password authentication.
+    /// Where SSH password logins are allowed from, see [SshPasswordPolicy].
+    /// Key logins are always allowed.
     ///
     /// [nixos.org:services.openssh.settings.PasswordAuthentication](https://search.nixos.org/options?show=services.openssh.settings.PasswordAuthentication)
     ///
-    /// Default: false
-    pub ssh_password_auth: bool,
+    /// Default: "never"
+    pub ssh_password_auth: Box<StringListOptionData>,
+
+    /// The LAN and VPN subnets in CIDR notation. Connections from there are
+    /// trusted, e.g. for SSH password logins. All other addresses are
+    /// treated as WAN.
+    ///
+    /// Default: the private IPv4 ranges and the IPv6 unique local addresses
+    pub trusted_networks: Box<ManualStringListOptionData>,
 
     /// The initial password that will be used.
     /// Use the [`crate::utils::unix_hash_password`] utility fn to generate the hash.
@@ -125,14 +152,155 @@ pub struct NixBaseConfig {
     ///
     /// [nisos.org:networking.hostName](https://search.nixos.org/options?show=networking.hostName)
     pub hostname_pi: String,
+
+    /// Number of data snapshots to keep, 0 disables them.
+    /// Only used if the data dir is on ZFS or btrfs.
+    ///
+    /// Default: 5
+    pub snapshot_keep: Box<NumberOptionData>,
+
+    /// The directory holding the service data that is snapshotted
+    ///
+    /// Default: "/var/lib"
+    pub snapshot_data_dir: Box<TextOptionData>,
+
+    /// Number of days to keep the newest config backup of
+    ///
+    /// Default: 7
+    pub backup_keep_daily: Box<NumberOptionData>,
+
+    /// Number of weeks to keep the newest config backup of
+    ///
+    /// Default: 4
+    pub backup_keep_weekly: Box<NumberOptionData>,
+
+    /// How apps with HTTPS enabled get their certificate, see [TlsMode]
+    ///
+    /// Default: "off"
+    pub tls_mode: Box<StringListOptionData>,
+
+    /// The public domain of the node. Required for ACME.
+    ///
+    /// Example: "node.example.com"
+    pub domain: Box<TextOptionData>,
+
+    /// The contact address for Let's Encrypt. Required for ACME.
+    ///
+    /// [nixos.org:security.acme.defaults.email](https://search.nixos.org/options?show=security.acme.defaults.email)
+    pub acme_email: Box<TextOptionData>,
+
+    /// The PEM certificate chain on the node. Required for the custom TLS mode.
+    ///
+    /// [nixos.org:services.nginx.virtualHosts.<name>.sslCertificate](https://search.nixos.org/options?show=services.nginx.virtualHosts.%3Cname%3E.sslCertificate)
+    ///
+    /// Example: "/etc/ssl/node/fullchain.pem"
+    pub tls_certificate: Box<TextOptionData>,
+
+    /// The PEM private key of the certificate on the node. Required for the
+    /// custom TLS mode.
+    ///
+    /// [nixos.org:services.nginx.virtualHosts.<name>.sslCertificateKey](https://search.nixos.org/options?show=services.nginx.virtualHosts.%3Cname%3E.sslCertificateKey)
+    ///
+    /// Example: "/etc/ssl/node/key.pem"
+    pub tls_key: Box<TextOptionData>,
+
+    /// Project wide variables as `NAME=value` entries, see [Variables]
+    ///
+    /// Example: "DATA_ROOT=/mnt/hdd"
+    pub variables: Box<ManualStringListOptionData>,
+
+    /// Whether to ban IPs with too many failed login attempts
+    ///
+    /// [nixos.org:services.fail2ban.enable](https://search.nixos.org/options?show=services.fail2ban.enable)
+    ///
+    /// Default: true
+    pub fail2ban_enable: Box<BoolOptionData>,
+
+    /// The port the SSH daemon listens on. It is opened in the firewall.
+    ///
+    /// [nixos.org:services.openssh.ports](https://search.nixos.org/options?show=services.openssh.ports)
+    ///
+    /// Default: 22
+    pub ssh_port: Box<PortOptionData>,
+
+    /// Number of authentication attempts per SSH connection
+    ///
+    /// Default: 3
+    pub ssh_max_auth_tries: Box<NumberOptionData>,
+
+    /// Whether to refuse SSH logins as root, even with a key
+    ///
+    /// [nixos.org:services.openssh.settings.PermitRootLogin](https://search.nixos.org/options?show=services.openssh.settings.PermitRootLogin)
+    ///
+    /// Default: true
+    pub ssh_disable_root_login: Box<BoolOptionData>,
+
+    /// Whether SSH password logins also ask for a one time code of
+    /// google-authenticator. Each user must run `google-authenticator`
+    /// once before enabling it, key logins are not affected.
+    ///
+    /// Default: false
+    pub ssh_two_factor: Box<BoolOptionData>,
+
+    /// Share of the memory used for a compressed swap device in RAM,
+    /// 0 disables it. Helps nodes with little memory, e.g. a Pi.
+    ///
+    /// [nixos.org:zramSwap.memoryPercent](https://search.nixos.org/options?show=zramSwap.memoryPercent)
+    ///
+    /// Default: 0
+    pub zram_percent: Box<PercentOptionData>,
+
+    /// Where the passwords the services need in plain text are stored,
+    /// see [SecretBackend]
+    ///
+    /// Default: "plaintext"
+    pub secret_backend: Box<StringListOptionData>,
+
+    /// The age public keys secrets are encrypted for, see
+    /// [SecretSettings::recipients]
+    ///
+    /// Example: "age1qyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqs3290gq"
+    pub secret_recipients: Box<ManualStringListOptionData>,
+
+    /// The DNS servers the node resolves names with, see [DnsResolver]
+    ///
+    /// [nixos.org:networking.nameservers](https://search.nixos.org/options?show=networking.nameservers)
+    ///
+    /// Default: "system"
+    pub dns_resolver: Box<StringListOptionData>,
+
+    /// The servers of the custom resolver, an IP address optionally
+    /// followed by `#` and the name on its TLS certificate
+    ///
+    /// Example: "9.9.9.9#dns.quad9.net"
+    pub dns_servers: Box<ManualStringListOptionData>,
+
+    /// Whether DNS queries are encrypted with TLS. Servers handed out by
+    /// DHCP are only used with TLS if they support it.
+    ///
+    /// [nixos.org:services.resolved.dnsovertls](https://search.nixos.org/options?show=services.resolved.dnsovertls)
+    ///
+    /// Default: false
+    pub dns_over_tls: Box<BoolOptionData>,
+
+    /// How full the disk holding the data dir of an app may get before
+    /// `nixblitz status` reports it as unhealthy
+    ///
+    /// Default: 90
+    pub disk_alert_percent: Box<PercentOptionData>,
 }
 
 impl Default for NixBaseConfig {
     fn default() -> Self {
         let allow_unfree = false;
-        let time_zone = "America/New_York".to_string();
-        let default_locale = "en_US.utf8".to_string();
-        let username = "admin".to_string();
+        let time_zone = defaults::nix_base::TIME_ZONE.to_string();
+        let default_locale = defaults::nix_base::LOCALE.to_string();
+        let username = defaults::SYSTEM_USER.to_string();
+        let zram_percent = defaults::get(
+            &get_system_platform(),
+            &NixBaseConfigOption::ZramPercent.to_option_id(),
+            defaults::nix_base::ZRAM_PERCENT.into(),
+        ) as u8;
         Self {
             allow_unfree: Box::new(BoolOptionData::new(
                 NixBaseConfigOption::AllowUnfree.to_option_id(),
@@ -155,7 +323,21 @@ impl Default for NixBaseConfig {
                     .collect(),
             )),
             username: username.clone(),
-            ssh_password_auth: false,
+            ssh_password_auth: Box::new(StringListOptionData::new(
+                NixBaseConfigOption::SshPasswordAuth.to_option_id(),
+                SshPasswordPolicy::Never.to_string(),
+                SshPasswordPolicy::to_string_array()
+                    .map(|entry| StringListOptionItem::new(entry.to_string(), entry.to_string()))
+                    .to_vec(),
+            )),
+            trusted_networks: Box::new(ManualStringListOptionData::new(
+                NixBaseConfigOption::TrustedNetworks.to_option_id(),
+                defaults::nix_base::TRUSTED_NETWORKS
+                    .iter()
+                    .map(|n| n.to_string())
+                    .collect(),
+                0,
+            )),
             hashed_password: Box::new(PasswordOptionData::new(
                 NixBaseConfigOption::InitialPassword.to_option_id(),
                 INITIAL_PASSWORD.to_string(),
@@ -178,6 +360,151 @@ impl Default for NixBaseConfig {
             ports: vec![22],
             hostname_vm: "nixblitzvm".to_string(),
             hostname_pi: "nixblitzpi".to_string(),
+            snapshot_keep: Box::new(
+                NumberOptionData::new(
+                    NixBaseConfigOption::SnapshotKeep.to_option_id(),
+                    NumberValue::UInt(Some(defaults::nix_base::SNAPSHOT_KEEP)),
+                    0,
+                    100,
+                    false,
+                    NumberValue::UInt(Some(defaults::nix_base::SNAPSHOT_KEEP)),
+                )
+                .unwrap(),
+            ),
+            snapshot_data_dir: Box::new(TextOptionData::new(
+                NixBaseConfigOption::SnapshotDataDir.to_option_id(),
+                defaults::nix_base::SNAPSHOT_DATA_DIR.to_string(),
+                1,
+                false,
+                defaults::nix_base::SNAPSHOT_DATA_DIR.to_string(),
+            )),
+            backup_keep_daily: Box::new(
+                NumberOptionData::new(
+                    NixBaseConfigOption::BackupKeepDaily.to_option_id(),
+                    NumberValue::UInt(Some(defaults::nix_base::BACKUP_KEEP_DAILY)),
+                    0,
+                    100,
+                    false,
+                    NumberValue::UInt(Some(defaults::nix_base::BACKUP_KEEP_DAILY)),
+                )
+                .unwrap(),
+            ),
+            backup_keep_weekly: Box::new(
+                NumberOptionData::new(
+                    NixBaseConfigOption::BackupKeepWeekly.to_option_id(),
+                    NumberValue::UInt(Some(defaults::nix_base::BACKUP_KEEP_WEEKLY)),
+                    0,
+                    100,
+                    false,
+                    NumberValue::UInt(Some(defaults::nix_base::BACKUP_KEEP_WEEKLY)),
+                )
+                .unwrap(),
+            ),
+            tls_mode: Box::new(StringListOptionData::new(
+                NixBaseConfigOption::TlsMode.to_option_id(),
+                TlsMode::Off.to_string(),
+                TlsMode::to_string_array()
+                    .map(|entry| StringListOptionItem::new(entry.to_string(), entry.to_string()))
+                    .to_vec(),
+            )),
+            domain: Box::new(TextOptionData::new(
+                NixBaseConfigOption::Domain.to_option_id(),
+                "".to_string(),
+                1,
+                false,
+                "".to_string(),
+            )),
+            acme_email: Box::new(TextOptionData::new(
+                NixBaseConfigOption::AcmeEmail.to_option_id(),
+                "".to_string(),
+                1,
+                false,
+                "".to_string(),
+            )),
+            tls_certificate: Box::new(TextOptionData::new(
+                NixBaseConfigOption::TlsCertificate.to_option_id(),
+                "".to_string(),
+                1,
+                false,
+                "".to_string(),
+            )),
+            tls_key: Box::new(TextOptionData::new(
+                NixBaseConfigOption::TlsKey.to_option_id(),
+                "".to_string(),
+                1,
+                false,
+                "".to_string(),
+            )),
+            variables: Box::new(ManualStringListOptionData::new(
+                NixBaseConfigOption::Variables.to_option_id(),
+                vec![],
+                0,
+            )),
+            fail2ban_enable: Box::new(BoolOptionData::new(
+                NixBaseConfigOption::Fail2banEnable.to_option_id(),
+                true,
+            )),
+            ssh_port: Box::new(PortOptionData::new(
+                NixBaseConfigOption::SshPort.to_option_id(),
+                NumberValue::U16(Some(defaults::nix_base::SSH_PORT)),
+            )),
+            ssh_max_auth_tries: Box::new(
+                NumberOptionData::new(
+                    NixBaseConfigOption::SshMaxAuthTries.to_option_id(),
+                    NumberValue::UInt(Some(defaults::nix_base::SSH_MAX_AUTH_TRIES)),
+                    1,
+                    10,
+                    false,
+                    NumberValue::UInt(Some(defaults::nix_base::SSH_MAX_AUTH_TRIES)),
+                )
+                .unwrap(),
+            ),
+            ssh_disable_root_login: Box::new(BoolOptionData::new(
+                NixBaseConfigOption::SshDisableRootLogin.to_option_id(),
+                true,
+            )),
+            ssh_two_factor: Box::new(BoolOptionData::new(
+                NixBaseConfigOption::SshTwoFactor.to_option_id(),
+                false,
+            )),
+            zram_percent: Box::new(PercentOptionData::new(
+                NixBaseConfigOption::ZramPercent.to_option_id(),
+                zram_percent,
+                defaults::nix_base::ZRAM_PERCENT_STEP,
+            )),
+            secret_backend: Box::new(StringListOptionData::new(
+                NixBaseConfigOption::SecretBackend.to_option_id(),
+                SecretBackend::Plaintext.to_string(),
+                SecretBackend::to_string_array()
+                    .map(|entry| StringListOptionItem::new(entry.to_string(), entry.to_string()))
+                    .to_vec(),
+            )),
+            secret_recipients: Box::new(ManualStringListOptionData::new(
+                NixBaseConfigOption::SecretRecipients.to_option_id(),
+                vec![],
+                0,
+            )),
+            dns_resolver: Box::new(StringListOptionData::new(
+                NixBaseConfigOption::DnsResolver.to_option_id(),
+                DnsResolver::System.to_string(),
+                DnsResolver::to_string_array()
+                    .map(|entry| StringListOptionItem::new(entry.to_string(), entry.to_string()))
+                    .to_vec(),
+            )),
+            dns_servers: Box::new(ManualStringListOptionData::new(
+                NixBaseConfigOption::DnsServers.to_option_id(),
+                vec![],
+                0,
+            )),
+            dns_over_tls: Box::new(BoolOptionData::new(
+                NixBaseConfigOption::DnsOverTls.to_option_id(),
+                false,
+            )),
+            disk_alert_percent: Box::new(PercentOptionData::new(
+                NixBaseConfigOption::DiskAlertPercent.to_option_id(),
+                defaults::nix_base::DISK_ALERT_PERCENT,
+                defaults::nix_base::DISK_ALERT_PERCENT_STEP,
+            )),
         }
     }
 }
@@ -194,6 +521,30 @@ pub enum NixBaseConfigOption {
     DefaultLocale,
     Username,
     InitialPassword,
+    SnapshotKeep,
+    SnapshotDataDir,
+    BackupKeepDaily,
+    BackupKeepWeekly,
+    TlsMode,
+    Domain,
+    AcmeEmail,
+    TlsCertificate,
+    TlsKey,
+    Variables,
+    Fail2banEnable,
+    SshPort,
+    SshMaxAuthTries,
+    SshDisableRootLogin,
+    SshTwoFactor,
+    SshPasswordAuth,
+    TrustedNetworks,
+    ZramPercent,
+    SecretBackend,
+    SecretRecipients,
+    DnsResolver,
+    DnsServers,
+    DnsOverTls,
+    DiskAlertPercent,
 }
 
 impl ToOptionId for NixBaseConfigOption {
@@ -212,6 +563,30 @@ impl FromStr for NixBaseConfigOption {
             "default_locale" => Ok(NixBaseConfigOption::DefaultLocale),
             "username" => Ok(NixBaseConfigOption::Username),
             "initial_password" => Ok(NixBaseConfigOption::InitialPassword),
+            "snapshot_keep" => Ok(NixBaseConfigOption::SnapshotKeep),
+            "snapshot_data_dir" => Ok(NixBaseConfigOption::SnapshotDataDir),
+            "backup_keep_daily" => Ok(NixBaseConfigOption::BackupKeepDaily),
+            "backup_keep_weekly" => Ok(NixBaseConfigOption::BackupKeepWeekly),
+            "tls_mode" => Ok(NixBaseConfigOption::TlsMode),
+            "domain" => Ok(NixBaseConfigOption::Domain),
+            "acme_email" => Ok(NixBaseConfigOption::AcmeEmail),
+            "tls_certificate" => Ok(NixBaseConfigOption::TlsCertificate),
+            "tls_key" => Ok(NixBaseConfigOption::TlsKey),
+            "variables" => Ok(NixBaseConfigOption::Variables),
+            "fail2ban_enable" => Ok(NixBaseConfigOption::Fail2banEnable),
+            "ssh_port" => Ok(NixBaseConfigOption::SshPort),
+            "ssh_max_auth_tries" => Ok(NixBaseConfigOption::SshMaxAuthTries),
+            "ssh_disable_root_login" => Ok(NixBaseConfigOption::SshDisableRootLogin),
+            "ssh_two_factor" => Ok(NixBaseConfigOption::SshTwoFactor),
+            "ssh_password_auth" => Ok(NixBaseConfigOption::SshPasswordAuth),
+            "trusted_networks" => Ok(NixBaseConfigOption::TrustedNetworks),
+            "zram_percent" => Ok(NixBaseConfigOption::ZramPercent),
+            "secret_backend" => Ok(NixBaseConfigOption::SecretBackend),
+            "secret_recipients" => Ok(NixBaseConfigOption::SecretRecipients),
+            "dns_resolver" => Ok(NixBaseConfigOption::DnsResolver),
+            "dns_servers" => Ok(NixBaseConfigOption::DnsServers),
+            "dns_over_tls" => Ok(NixBaseConfigOption::DnsOverTls),
+            "disk_alert_percent" => Ok(NixBaseConfigOption::DiskAlertPercent),
             _ => Err(()),
         }
     }
@@ -225,11 +600,208 @@ impl Display for NixBaseConfigOption {
             NixBaseConfigOption::DefaultLocale => "default_locale",
             NixBaseConfigOption::Username => "username",
             NixBaseConfigOption::InitialPassword => "initial_password",
+            NixBaseConfigOption::SnapshotKeep => "snapshot_keep",
+            NixBaseConfigOption::SnapshotDataDir => "snapshot_data_dir",
+            NixBaseConfigOption::BackupKeepDaily => "backup_keep_daily",
+            NixBaseConfigOption::BackupKeepWeekly => "backup_keep_weekly",
+            NixBaseConfigOption::TlsMode => "tls_mode",
+            NixBaseConfigOption::Domain => "domain",
+            NixBaseConfigOption::AcmeEmail => "acme_email",
+            NixBaseConfigOption::TlsCertificate => "tls_certificate",
+            NixBaseConfigOption::TlsKey => "tls_key",
+            NixBaseConfigOption::Variables => "variables",
+            NixBaseConfigOption::Fail2banEnable => "fail2ban_enable",
+            NixBaseConfigOption::SshPort => "ssh_port",
+            NixBaseConfigOption::SshMaxAuthTries => "ssh_max_auth_tries",
+            NixBaseConfigOption::SshDisableRootLogin => "ssh_disable_root_login",
+            NixBaseConfigOption::SshTwoFactor => "ssh_two_factor",
+            NixBaseConfigOption::SshPasswordAuth => "ssh_password_auth",
+            NixBaseConfigOption::TrustedNetworks => "trusted_networks",
+            NixBaseConfigOption::ZramPercent => "zram_percent",
+            NixBaseConfigOption::SecretBackend => "secret_backend",
+            NixBaseConfigOption::SecretRecipients => "secret_recipients",
+            NixBaseConfigOption::DnsResolver => "dns_resolver",
+            NixBaseConfigOption::DnsServers => "dns_servers",
+            NixBaseConfigOption::DnsOverTls => "dns_over_tls",
+            NixBaseConfigOption::DiskAlertPercent => "disk_alert_percent",
         };
         write!(f, "{}", s)
     }
 }
 
+/// Where SSH password logins are allowed from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SshPasswordPolicy {
+    /// Only key logins are allowed
+    #[default]
+    Never,
+
+    /// Password logins are allowed from the
+    /// [trusted networks](NixBaseConfig::trusted_networks) only
+    TrustedNetworks,
+
+    /// Password logins are allowed from everywhere
+    Always,
+}
+
+impl SshPasswordPolicy {
+    pub fn to_string_array() -> [&'static str; 3] {
+        ["never", "trusted_networks", "always"]
+    }
+}
+
+impl Display for SshPasswordPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            SshPasswordPolicy::Never => "never",
+            SshPasswordPolicy::TrustedNetworks => "trusted_networks",
+            SshPasswordPolicy::Always => "always",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for SshPasswordPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<SshPasswordPolicy, ()> {
+        match s {
+            "never" => Ok(SshPasswordPolicy::Never),
+            "trusted_networks" => Ok(SshPasswordPolicy::TrustedNetworks),
+            "always" => Ok(SshPasswordPolicy::Always),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Which DNS servers the node uses
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DnsResolver {
+    /// The servers handed out by DHCP
+    #[default]
+    System,
+
+    /// Quad9, which blocks known malicious domains
+    Quad9,
+
+    /// Cloudflare
+    Cloudflare,
+
+    /// The servers in [NixBaseConfig::dns_servers]
+    Custom,
+}
+
+impl DnsResolver {
+    pub fn to_string_array() -> [&'static str; 4] {
+        ["system", "quad9", "cloudflare", "custom"]
+    }
+}
+
+impl Display for DnsResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            DnsResolver::System => "system",
+            DnsResolver::Quad9 => "quad9",
+            DnsResolver::Cloudflare => "cloudflare",
+            DnsResolver::Custom => "custom",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for DnsResolver {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<DnsResolver, ()> {
+        match s {
+            "system" => Ok(DnsResolver::System),
+            "quad9" => Ok(DnsResolver::Quad9),
+            "cloudflare" => Ok(DnsResolver::Cloudflare),
+            "custom" => Ok(DnsResolver::Custom),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The DNS settings of the node, rendered into the systemd-resolved config
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DnsSettings {
+    pub resolver: DnsResolver,
+
+    /// The servers of [DnsResolver::Custom], empty entries removed
+    pub servers: Vec<String>,
+    pub over_tls: bool,
+}
+
+impl DnsSettings {
+    /// Returns the servers to use, empty for [DnsResolver::System]
+    pub fn nameservers(&self) -> Vec<String> {
+        let servers: &[&str] = match self.resolver {
+            DnsResolver::System => &[],
+            DnsResolver::Quad9 => &defaults::nix_base::QUAD9_SERVERS,
+            DnsResolver::Cloudflare => &defaults::nix_base::CLOUDFLARE_SERVERS,
+            DnsResolver::Custom => return self.servers.clone(),
+        };
+
+        servers.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Returns the value of `services.resolved.dnsovertls`, `None` if the
+    /// settings of the system are kept
+    pub fn dns_over_tls(&self) -> Option<&'static str> {
+        match (self.resolver, self.over_tls) {
+            (DnsResolver::System, false) => None,
+            // DHCP servers often don't support TLS, fall back to plain DNS
+            (DnsResolver::System, true) => Some("opportunistic"),
+            (_, true) => Some("true"),
+            (_, false) => Some("false"),
+        }
+    }
+
+    /// Makes sure the custom resolver has valid servers
+    ///
+    /// # Errors
+    ///
+    /// [ProjectError::InvalidValue] if an entry isn't an IP address with an
+    /// optional server name, or the custom resolver has no servers
+    pub fn check(&self) -> Result<(), ProjectError> {
+        let mut issues: Vec<String> = self
+            .servers
+            .iter()
+            .filter(|s| !is_dns_server(s))
+            .map(|s| format!("{} is not an IP address with an optional #name", s))
+            .collect();
+        if self.resolver == DnsResolver::Custom && self.servers.is_empty() {
+            issues.push("The custom resolver needs at least one server".into());
+        }
+
+        if issues.is_empty() {
+            return Ok(());
+        }
+
+        Err(Report::new(ProjectError::InvalidValue(
+            NixBaseConfigOption::DnsServers.to_string(),
+            issues,
+        )))
+    }
+}
+
+/// Whether the entry is an IP address, optionally followed by `#` and the
+/// name systemd-resolved checks the TLS certificate of the server against
+fn is_dns_server(entry: &str) -> bool {
+    let (address, name) = match entry.split_once('#') {
+        Some((address, name)) => (address, Some(name)),
+        None => (entry, None),
+    };
+    let valid_name = name.map_or(true, |n| {
+        !n.is_empty()
+            && n.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+    });
+
+    IpAddr::from_str(address).is_ok() && valid_name
+}
+
 const _FILES: [&str; 3] = [
     "src/configuration.common.nix.templ",
     "src/vm/configuration.nix.templ",
@@ -262,13 +834,36 @@ impl NixBaseConfig {
         time_zone: Box<StringListOptionData>,
         default_locale: Box<StringListOptionData>,
         username: String,
-        ssh_password_auth: bool,
+        ssh_password_auth: Box<StringListOptionData>,
+        trusted_networks: Box<ManualStringListOptionData>,
         hashed_password: Box<PasswordOptionData>,
         openssh_auth_keys: Vec<String>,
         system_packages: Vec<String>,
         ports: Vec<usize>,
         hostname_vm: String,
         hostname_pi: String,
+        snapshot_keep: Box<NumberOptionData>,
+        snapshot_data_dir: Box<TextOptionData>,
+        backup_keep_daily: Box<NumberOptionData>,
+        backup_keep_weekly: Box<NumberOptionData>,
+        tls_mode: Box<StringListOptionData>,
+        domain: Box<TextOptionData>,
+        acme_email: Box<TextOptionData>,
+        tls_certificate: Box<TextOptionData>,
+        tls_key: Box<TextOptionData>,
+        variables: Box<ManualStringListOptionData>,
+        fail2ban_enable: Box<BoolOptionData>,
+        ssh_port: Box<PortOptionData>,
+        ssh_max_auth_tries: Box<NumberOptionData>,
+        ssh_disable_root_login: Box<BoolOptionData>,
+        ssh_two_factor: Box<BoolOptionData>,
+        zram_percent: Box<PercentOptionData>,
+        secret_backend: Box<StringListOptionData>,
+        secret_recipients: Box<ManualStringListOptionData>,
+        dns_resolver: Box<StringListOptionData>,
+        dns_servers: Box<ManualStringListOptionData>,
+        dns_over_tls: Box<BoolOptionData>,
+        disk_alert_percent: Box<PercentOptionData>,
     ) -> Self {
         Self {
             allow_unfree,
@@ -276,12 +871,151 @@ impl NixBaseConfig {
             default_locale,
             username: username.clone(),
             ssh_password_auth,
+            trusted_networks,
             hashed_password,
             openssh_auth_keys,
             system_packages,
             ports,
             hostname_vm,
             hostname_pi,
+            snapshot_keep,
+            snapshot_data_dir,
+            backup_keep_daily,
+            backup_keep_weekly,
+            tls_mode,
+            domain,
+            acme_email,
+            tls_certificate,
+            tls_key,
+            variables,
+            fail2ban_enable,
+            ssh_port,
+            ssh_max_auth_tries,
+            ssh_disable_root_login,
+            ssh_two_factor,
+            zram_percent,
+            secret_backend,
+            secret_recipients,
+            dns_resolver,
+            dns_servers,
+            dns_over_tls,
+            disk_alert_percent,
+        }
+    }
+
+    /// Returns the project variables. Invalid entries are rejected when the
+    /// option is changed, if the file was edited by hand no variables are used.
+    pub fn variables(&self) -> Variables {
+        Variables::parse(
+            &NixBaseConfigOption::Variables.to_string(),
+            self.variables.value(),
+        )
+        .unwrap_or_default()
+    }
+
+    /// Returns the SSH password policy, [SshPasswordPolicy::Never] if unknown
+    pub fn ssh_password_policy(&self) -> SshPasswordPolicy {
+        SshPasswordPolicy::from_str(self.ssh_password_auth.value()).unwrap_or_default()
+    }
+
+    /// Renders the sshd settings of the [SshPasswordPolicy]
+    ///
+    /// # Returns
+    /// The global `PasswordAuthentication` and `KbdInteractiveAuthentication`
+    /// values and the `Match` block allowing passwords from the trusted
+    /// networks, which is empty unless the policy needs it
+    fn ssh_password_config(&self) -> (bool, bool, String) {
+        let two_factor = self.ssh_two_factor.value();
+        let networks: Vec<&str> = self
+            .trusted_networks
+            .value()
+            .iter()
+            .map(|n| n.trim())
+            .filter(|n| !n.is_empty())
+            .collect();
+
+        match self.ssh_password_policy() {
+            SshPasswordPolicy::Never => (false, false, "".into()),
+            SshPasswordPolicy::Always => (true, two_factor, "".into()),
+            SshPasswordPolicy::TrustedNetworks if networks.is_empty() => (false, false, "".into()),
+            SshPasswordPolicy::TrustedNetworks => {
+                let mut lines = vec![
+                    format!("Match Address {}", networks.join(",")),
+                    "  PasswordAuthentication yes".to_string(),
+                ];
+                if two_factor {
+                    lines.push("  KbdInteractiveAuthentication yes".into());
+                }
+                // keep the indentation of the first line in the template
+                (false, false, lines.join("\n        "))
+            }
+        }
+    }
+
+    /// Returns the TLS settings the reverse proxy is rendered with
+    pub fn tls_settings(&self) -> TlsSettings {
+        TlsSettings {
+            mode: TlsMode::from_str(self.tls_mode.value()).unwrap_or_default(),
+            domain: self.domain.value().trim().to_string(),
+            acme_email: self.acme_email.value().trim().to_string(),
+            certificate: self.tls_certificate.value().trim().to_string(),
+            key: self.tls_key.value().trim().to_string(),
+        }
+    }
+
+    /// The certificate and the key imported into the secret store, see
+    /// [tls_cert]. Paths set by hand aren't secrets of the project.
+    pub fn tls_secrets(&self) -> Vec<SecretRef> {
+        let backend = self.secret_settings().backend;
+
+        [
+            (tls_cert::CERTIFICATE_SECRET, &self.tls_certificate),
+            (tls_cert::KEY_SECRET, &self.tls_key),
+        ]
+        .into_iter()
+        .filter(|(name, path)| backend.decrypted_path(name).as_deref() == Some(path.value().trim()))
+        .map(|(name, _)| SecretRef {
+            backend,
+            name: name.to_string(),
+            owner: tls_cert::OWNER.to_string(),
+        })
+        .collect()
+    }
+
+    /// Returns the settings the secrets of the apps are stored with
+    pub fn secret_settings(&self) -> SecretSettings {
+        SecretSettings {
+            backend: SecretBackend::from_str(self.secret_backend.value()).unwrap_or_default(),
+            recipients: self
+                .secret_recipients
+                .value()
+                .iter()
+                .map(|r| r.trim().to_string())
+                .filter(|r| !r.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Returns the NixOS configuration of the project flake for the platform
+    pub fn configuration(&self, platform: &SystemPlatform) -> &str {
+        match platform {
+            SystemPlatform::Aarch64 => &self.hostname_pi,
+            _ => &self.hostname_vm,
+        }
+    }
+
+    /// Returns the DNS settings of the node
+    pub fn dns_settings(&self) -> DnsSettings {
+        DnsSettings {
+            resolver: DnsResolver::from_str(self.dns_resolver.value()).unwrap_or_default(),
+            servers: self
+                .dns_servers
+                .value()
+                .iter()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            over_tls: self.dns_over_tls.value(),
         }
     }
 
@@ -328,12 +1062,30 @@ impl NixBaseConfig {
             // TODO: de-hardcode this
             let mut data = HashMap::new();
             if file_name == "src/configuration.common.nix.templ" {
+                // users need the tool to set up their one time codes
+                let mut system_packages = self.system_packages.clone();
+                if self.ssh_two_factor.value() {
+                    system_packages.push("google-authenticator".into());
+                }
+                let (password_auth, kbd_interactive_auth, ssh_match) = self.ssh_password_config();
+                let dns = self.dns_settings();
+                let permit_root_login = if self.ssh_disable_root_login.value() {
+                    "no"
+                } else {
+                    "prohibit-password"
+                };
+
                 data = HashMap::from([
                     ("allow_unfree", format!("{}", self.allow_unfree.value())),
                     ("time_zone", self.time_zone.value().into()),
                     ("default_locale", self.default_locale.value().into()),
                     ("username", self.username.clone()),
-                    ("ssh_password_auth", format!("{}", self.ssh_password_auth)),
+                    ("ssh_password_auth", format!("{}", password_auth)),
+                    (
+                        "ssh_kbd_interactive_auth",
+                        format!("{}", kbd_interactive_auth),
+                    ),
+                    ("ssh_match", ssh_match),
                     (
                         "initial_password",
                         self.hashed_password.hashed_value().clone(),
@@ -346,7 +1098,7 @@ impl NixBaseConfig {
                             .collect::<Vec<_>>()
                             .join("\n"),
                     ),
-                    ("system_packages", self.system_packages.join(" ")),
+                    ("system_packages", system_packages.join(" ")),
                     (
                         "ports",
                         self.ports
@@ -355,6 +1107,36 @@ impl NixBaseConfig {
                             .collect::<Vec<String>>()
                             .join(" "),
                     ),
+                    (
+                        "fail2ban_enable",
+                        format!("{}", self.fail2ban_enable.value()),
+                    ),
+                    (
+                        "ssh_port",
+                        port_or(self.ssh_port.value(), defaults::nix_base::SSH_PORT).to_string(),
+                    ),
+                    (
+                        "ssh_max_auth_tries",
+                        self.ssh_max_auth_tries
+                            .value()
+                            .to_string_or(&defaults::nix_base::SSH_MAX_AUTH_TRIES.to_string()),
+                    ),
+                    ("permit_root_login", permit_root_login.into()),
+                    ("ssh_two_factor", format!("{}", self.ssh_two_factor.value())),
+                    ("zram_enable", format!("{}", self.zram_percent.value() > 0)),
+                    ("zram_percent", self.zram_percent.to_nix_string(false)),
+                    (
+                        "dns_over_tls",
+                        dns.dns_over_tls().unwrap_or_default().into(),
+                    ),
+                    (
+                        "dns_servers",
+                        dns.nameservers()
+                            .iter()
+                            .map(|s| format!("\"{}\"", s))
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    ),
                 ]);
             } else if file_name == "src/vm/configuration.nix.templ" {
                 data = HashMap::from([("hostname", self.hostname_vm.clone())]);
@@ -373,22 +1155,14 @@ impl NixBaseConfig {
                 .render(file_name, &data)
                 .attach_printable(format!("Failed to render template {template}"))
                 .change_context(TemplatingError::Render)?;
-            let (status, text) = format::in_memory("<convert nix base>".to_string(), res);
-
-            if let format::Status::Error(e) = status {
-                Err(Report::new(TemplatingError::Format)).attach_printable_lazy(|| {
-                    format!("Could not format the template file due to error: {e}")
-                })?
-            } else {
-                rendered_contents.insert(file_name.to_string(), text);
-            }
+            rendered_contents.insert(file_name.to_string(), format_nix(file_name, res));
         }
 
         Ok(rendered_contents)
     }
 
     pub fn to_json_string(&self) -> Result<String, TemplatingError> {
-        serde_json::to_string(self).change_context(TemplatingError::JsonRenderError)
+        migrations::to_json_string(self)
     }
 
     pub fn from_json(json_data: &str) -> Result<NixBaseConfig, TemplatingError> {
@@ -466,6 +1240,286 @@ impl AppConfig for NixBaseConfig {
                         NixBaseConfigOption::InitialPassword.to_string(),
                     )))?;
                 }
+            } else if opt == NixBaseConfigOption::SnapshotKeep {
+                if let OptionDataChangeNotification::Number(val) = option {
+                    res = Ok(*self.snapshot_keep.value() != val.value);
+                    self.snapshot_keep.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::SnapshotKeep.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::BackupKeepDaily {
+                if let OptionDataChangeNotification::Number(val) = option {
+                    res = Ok(*self.backup_keep_daily.value() != val.value);
+                    self.backup_keep_daily.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::BackupKeepDaily.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::BackupKeepWeekly {
+                if let OptionDataChangeNotification::Number(val) = option {
+                    res = Ok(*self.backup_keep_weekly.value() != val.value);
+                    self.backup_keep_weekly.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::BackupKeepWeekly.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::TlsMode {
+                if let OptionDataChangeNotification::StringList(val) = option {
+                    let Ok(mode) = TlsMode::from_str(&val.value) else {
+                        return Err(Report::new(ProjectError::ChangeOptionValueError(
+                            opt.to_string(),
+                        ))
+                        .attach_printable(format!("Unknown TLS mode {}", val.value)));
+                    };
+                    TlsSettings {
+                        mode,
+                        ..self.tls_settings()
+                    }
+                    .check()?;
+
+                    res = Ok(self.tls_mode.value() != val.value);
+                    self.tls_mode.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::TlsMode.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::Domain {
+                if let OptionDataChangeNotification::TextEdit(val) = option {
+                    TlsSettings {
+                        domain: val.value.trim().to_string(),
+                        ..self.tls_settings()
+                    }
+                    .check()?;
+
+                    res = Ok(self.domain.value() != val.value);
+                    self.domain.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::Domain.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::AcmeEmail {
+                if let OptionDataChangeNotification::TextEdit(val) = option {
+                    TlsSettings {
+                        acme_email: val.value.trim().to_string(),
+                        ..self.tls_settings()
+                    }
+                    .check()?;
+
+                    res = Ok(self.acme_email.value() != val.value);
+                    self.acme_email.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::AcmeEmail.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::TlsCertificate {
+                if let OptionDataChangeNotification::TextEdit(val) = option {
+                    TlsSettings {
+                        certificate: val.value.trim().to_string(),
+                        ..self.tls_settings()
+                    }
+                    .check()?;
+
+                    res = Ok(self.tls_certificate.value() != val.value);
+                    self.tls_certificate.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::TlsCertificate.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::TlsKey {
+                if let OptionDataChangeNotification::TextEdit(val) = option {
+                    TlsSettings {
+                        key: val.value.trim().to_string(),
+                        ..self.tls_settings()
+                    }
+                    .check()?;
+
+                    res = Ok(self.tls_key.value() != val.value);
+                    self.tls_key.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::TlsKey.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::Variables {
+                if let OptionDataChangeNotification::ManualStringList(val) = option {
+                    Variables::parse(&opt.to_string(), &val.value)?;
+
+                    res = Ok(*self.variables.value() != val.value);
+                    self.variables.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::Variables.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::Fail2banEnable
+                || opt == NixBaseConfigOption::SshDisableRootLogin
+                || opt == NixBaseConfigOption::SshTwoFactor
+            {
+                if let OptionDataChangeNotification::Bool(val) = option {
+                    let field = match opt {
+                        NixBaseConfigOption::Fail2banEnable => &mut self.fail2ban_enable,
+                        NixBaseConfigOption::SshDisableRootLogin => {
+                            &mut self.ssh_disable_root_login
+                        }
+                        _ => &mut self.ssh_two_factor,
+                    };
+                    res = Ok(field.value() != val.value);
+                    field.set_value(val.value);
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        opt.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::SshPasswordAuth {
+                if let OptionDataChangeNotification::StringList(val) = option {
+                    if SshPasswordPolicy::from_str(&val.value).is_err() {
+                        return Err(Report::new(ProjectError::ChangeOptionValueError(
+                            opt.to_string(),
+                        ))
+                        .attach_printable(format!("Unknown SSH password policy {}", val.value)));
+                    }
+
+                    res = Ok(self.ssh_password_auth.value() != val.value);
+                    self.ssh_password_auth.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::SshPasswordAuth.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::TrustedNetworks {
+                if let OptionDataChangeNotification::ManualStringList(val) = option {
+                    check_routes(&opt.to_string(), &val.value)?;
+
+                    res = Ok(*self.trusted_networks.value() != val.value);
+                    self.trusted_networks.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::TrustedNetworks.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::SshPort {
+                if let OptionDataChangeNotification::Port(val) = option {
+                    res = Ok(*self.ssh_port.value() != val.value);
+                    self.ssh_port.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::SshPort.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::SshMaxAuthTries {
+                if let OptionDataChangeNotification::Number(val) = option {
+                    res = Ok(*self.ssh_max_auth_tries.value() != val.value);
+                    self.ssh_max_auth_tries.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::SshMaxAuthTries.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::ZramPercent {
+                res = self.zram_percent.apply_change(option);
+            } else if opt == NixBaseConfigOption::SecretBackend {
+                if let OptionDataChangeNotification::StringList(val) = option {
+                    let Ok(backend) = SecretBackend::from_str(&val.value) else {
+                        return Err(Report::new(ProjectError::ChangeOptionValueError(
+                            opt.to_string(),
+                        ))
+                        .attach_printable(format!("Unknown secret backend {}", val.value)));
+                    };
+                    SecretSettings {
+                        backend,
+                        ..self.secret_settings()
+                    }
+                    .check()?;
+
+                    res = Ok(self.secret_backend.value() != val.value);
+                    self.secret_backend.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::SecretBackend.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::SecretRecipients {
+                if let OptionDataChangeNotification::ManualStringList(val) = option {
+                    SecretSettings {
+                        recipients: val
+                            .value
+                            .iter()
+                            .map(|r| r.trim().to_string())
+                            .filter(|r| !r.is_empty())
+                            .collect(),
+                        ..self.secret_settings()
+                    }
+                    .check()?;
+
+                    res = Ok(*self.secret_recipients.value() != val.value);
+                    self.secret_recipients.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::SecretRecipients.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::DnsResolver {
+                if let OptionDataChangeNotification::StringList(val) = option {
+                    let Ok(resolver) = DnsResolver::from_str(&val.value) else {
+                        return Err(Report::new(ProjectError::ChangeOptionValueError(
+                            opt.to_string(),
+                        ))
+                        .attach_printable(format!("Unknown DNS resolver {}", val.value)));
+                    };
+                    DnsSettings {
+                        resolver,
+                        ..self.dns_settings()
+                    }
+                    .check()?;
+
+                    res = Ok(self.dns_resolver.value() != val.value);
+                    self.dns_resolver.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::DnsResolver.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::DnsServers {
+                if let OptionDataChangeNotification::ManualStringList(val) = option {
+                    DnsSettings {
+                        servers: val
+                            .value
+                            .iter()
+                            .map(|s| s.trim().to_string())
+                            .filter(|s| !s.is_empty())
+                            .collect(),
+                        ..self.dns_settings()
+                    }
+                    .check()?;
+
+                    res = Ok(*self.dns_servers.value() != val.value);
+                    self.dns_servers.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::DnsServers.to_string(),
+                    )))?;
+                }
+            } else if opt == NixBaseConfigOption::DnsOverTls {
+                res = self.dns_over_tls.apply_change(option);
+            } else if opt == NixBaseConfigOption::DiskAlertPercent {
+                res = self.disk_alert_percent.apply_change(option);
+            } else if opt == NixBaseConfigOption::SnapshotDataDir {
+                if let OptionDataChangeNotification::TextEdit(val) = option {
+                    res = Ok(self.snapshot_data_dir.value() != val.value);
+                    self.snapshot_data_dir.set_value(val.value.clone());
+                } else {
+                    Err(Report::new(ProjectError::ChangeOptionValueError(
+                        NixBaseConfigOption::SnapshotDataDir.to_string(),
+                    )))?;
+                }
             } else {
                 Err(
                     Report::new(ProjectError::ChangeOptionValueError(opt.to_string()))
@@ -492,6 +1546,30 @@ impl AppConfig for NixBaseConfig {
                 self.username.clone(),
             ))),
             OptionData::PasswordEdit(self.hashed_password.clone()),
+            OptionData::NumberEdit(self.snapshot_keep.clone()),
+            OptionData::TextEdit(self.snapshot_data_dir.clone()),
+            OptionData::NumberEdit(self.backup_keep_daily.clone()),
+            OptionData::NumberEdit(self.backup_keep_weekly.clone()),
+            OptionData::StringList(self.tls_mode.clone()),
+            OptionData::TextEdit(self.domain.clone()),
+            OptionData::TextEdit(self.acme_email.clone()),
+            OptionData::TextEdit(self.tls_certificate.clone()),
+            OptionData::TextEdit(self.tls_key.clone()),
+            OptionData::ManualStringList(self.variables.clone()),
+            OptionData::Bool(self.fail2ban_enable.clone()),
+            OptionData::Port(self.ssh_port.clone()),
+            OptionData::NumberEdit(self.ssh_max_auth_tries.clone()),
+            OptionData::Bool(self.ssh_disable_root_login.clone()),
+            OptionData::Bool(self.ssh_two_factor.clone()),
+            OptionData::StringList(self.ssh_password_auth.clone()),
+            OptionData::ManualStringList(self.trusted_networks.clone()),
+            OptionData::Percent(self.zram_percent.clone()),
+            OptionData::StringList(self.secret_backend.clone()),
+            OptionData::ManualStringList(self.secret_recipients.clone()),
+            OptionData::StringList(self.dns_resolver.clone()),
+            OptionData::ManualStringList(self.dns_servers.clone()),
+            OptionData::Bool(self.dns_over_tls.clone()),
+            OptionData::Percent(self.disk_alert_percent.clone()),
         ]
     }
 
@@ -505,19 +1583,13 @@ impl AppConfig for NixBaseConfig {
                 "Failed at rendering the nix base config".to_string(),
             ))?;
 
-        for (key, val) in rendered_nix.iter() {
-            update_file(
-                Path::new(&work_dir.join(key.replace(".templ", ""))),
-                val.as_bytes(),
-            )?;
-        }
-
+        // the variables are read from the JSON file, write it first
         update_file(
             Path::new(&work_dir.join(JSON_FILE_NAME)),
             rendered_json.as_bytes(),
         )?;
 
-        Ok(())
+        update_nix_files(work_dir, &rendered_nix)
     }
 }
 
@@ -598,7 +1670,8 @@ mod tests {
         assert_eq!(config.time_zone.value(), "America/New_York");
         assert_eq!(config.default_locale.value(), "en_US.utf8");
         assert_eq!(config.username, "admin");
-        assert!(!config.ssh_password_auth);
+        assert_eq!(config.ssh_password_policy(), SshPasswordPolicy::Never);
+        assert_eq!(config.trusted_networks.value().len(), 4);
         assert_eq!(config.openssh_auth_keys.len(), 0);
         assert_eq!(config.system_packages.len(), 8);
     }
@@ -630,7 +1703,16 @@ mod tests {
                     .collect(),
             )),
             "myUserName".to_string(),
-            true,
+            Box::new(StringListOptionData::new(
+                NixBaseConfigOption::SshPasswordAuth.to_option_id(),
+                SshPasswordPolicy::TrustedNetworks.to_string(),
+                vec![],
+            )),
+            Box::new(ManualStringListOptionData::new(
+                NixBaseConfigOption::TrustedNetworks.to_option_id(),
+                vec!["192.168.1.0/24".into(), "100.64.0.0/10".into()],
+                0,
+            )),
             Box::new(PasswordOptionData::new(
                 NixBaseConfigOption::InitialPassword.to_option_id(),
                 pw.to_string(),
@@ -644,6 +1726,49 @@ mod tests {
             vec![22, 1337],
             "nixblitzvm".to_string(),
             "nixblitzpi".to_string(),
+            NixBaseConfig::default().snapshot_keep,
+            NixBaseConfig::default().snapshot_data_dir,
+            NixBaseConfig::default().backup_keep_daily,
+            NixBaseConfig::default().backup_keep_weekly,
+            NixBaseConfig::default().tls_mode,
+            NixBaseConfig::default().domain,
+            NixBaseConfig::default().acme_email,
+            NixBaseConfig::default().tls_certificate,
+            NixBaseConfig::default().tls_key,
+            NixBaseConfig::default().variables,
+            NixBaseConfig::default().fail2ban_enable,
+            Box::new(PortOptionData::new(
+                NixBaseConfigOption::SshPort.to_option_id(),
+                NumberValue::U16(Some(2222)),
+            )),
+            NixBaseConfig::default().ssh_max_auth_tries,
+            NixBaseConfig::default().ssh_disable_root_login,
+            Box::new(BoolOptionData::new(
+                NixBaseConfigOption::SshTwoFactor.to_option_id(),
+                true,
+            )),
+            Box::new(PercentOptionData::new(
+                NixBaseConfigOption::ZramPercent.to_option_id(),
+                50,
+                5,
+            )),
+            NixBaseConfig::default().secret_backend,
+            NixBaseConfig::default().secret_recipients,
+            Box::new(StringListOptionData::new(
+                NixBaseConfigOption::DnsResolver.to_option_id(),
+                DnsResolver::Custom.to_string(),
+                vec![],
+            )),
+            Box::new(ManualStringListOptionData::new(
+                NixBaseConfigOption::DnsServers.to_option_id(),
+                vec!["9.9.9.9#dns.quad9.net".into(), "2620:fe::fe".into()],
+                0,
+            )),
+            Box::new(BoolOptionData::new(
+                NixBaseConfigOption::DnsOverTls.to_option_id(),
+                true,
+            )),
+            NixBaseConfig::default().disk_alert_percent,
         );
 
         let result = config.render(NixBaseConfigsTemplates::Common);
@@ -669,10 +1794,10 @@ mod tests {
             "i18n.defaultLocale = \"{}\";",
             config.default_locale.value()
         )));
-        assert!(res_base.contains(&format!(
-            "PasswordAuthentication = {};",
-            config.ssh_password_auth
-        )));
+        assert!(res_base.contains("PasswordAuthentication = false;"));
+        assert!(res_base.contains("KbdInteractiveAuthentication = false;"));
+        assert!(res_base.contains("Match Address 192.168.1.0/24,100.64.0.0/10"));
+        assert!(res_base.contains("  KbdInteractiveAuthentication yes\n"));
         assert!(res_base.contains(&format!(
             "hashedPassword = \"{}\";",
             &config.hashed_password.hashed_value()
@@ -683,6 +1808,15 @@ mod tests {
         for port in config.ports {
             assert!(res_base.contains(&format!("{}", port)));
         }
+        assert!(res_base.contains("ports = [2222];"));
+        assert!(res_base.contains("MaxAuthTries = 3;"));
+        assert!(res_base.contains("PermitRootLogin = \"no\";"));
+        assert!(res_base.contains("fail2ban.enable = true;"));
+        assert!(res_base.contains("googleAuthenticator.enable = true;"));
+        assert!(res_base.contains("google-authenticator"));
+        assert!(res_base.contains("memoryPercent = 50;"));
+        assert!(res_base.contains("dnsovertls = \"true\";"));
+        assert!(res_base.contains("nameservers = [\"9.9.9.9#dns.quad9.net\" \"2620:fe::fe\"];"));
 
         #[allow(clippy::unnecessary_to_owned)]
         let res_vm = texts.get(&templates.get(1).unwrap().to_string());
@@ -703,6 +1837,178 @@ mod tests {
         )));
     }
 
+    #[test]
+    fn test_acme_needs_domain_and_email() {
+        use crate::app_option_data::{
+            string_list_data::StringListOptionChangeData, text_edit_data::TextOptionChangeData,
+        };
+
+        let mut config = NixBaseConfig::default();
+        let acme = OptionDataChangeNotification::StringList(StringListOptionChangeData::new(
+            NixBaseConfigOption::TlsMode.to_option_id(),
+            TlsMode::Acme.to_string(),
+        ));
+        assert!(config.app_option_changed(&acme).is_err());
+        assert_eq!(config.tls_settings().mode, TlsMode::Off);
+
+        for (opt, value) in [
+            (NixBaseConfigOption::Domain, "node.example.com"),
+            (NixBaseConfigOption::AcmeEmail, "admin@example.com"),
+        ] {
+            config
+                .app_option_changed(&OptionDataChangeNotification::TextEdit(
+                    TextOptionChangeData::new(opt.to_option_id(), value.into()),
+                ))
+                .unwrap();
+        }
+        assert!(config.app_option_changed(&acme).unwrap());
+        assert_eq!(config.tls_settings().domain, "node.example.com");
+
+        // the domain can't be removed while ACME is in use
+        let res = config.app_option_changed(&OptionDataChangeNotification::TextEdit(
+            TextOptionChangeData::new(NixBaseConfigOption::Domain.to_option_id(), "".into()),
+        ));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_secret_backend_needs_recipients() {
+        use crate::app_option_data::{
+            manual_string_list_data::ManualStringListOptionChangeData,
+            string_list_data::StringListOptionChangeData,
+        };
+
+        let mut config = NixBaseConfig::default();
+        let sops = OptionDataChangeNotification::StringList(StringListOptionChangeData::new(
+            NixBaseConfigOption::SecretBackend.to_option_id(),
+            SecretBackend::Sops.to_string(),
+        ));
+        assert!(config.app_option_changed(&sops).is_err());
+        assert_eq!(config.secret_settings().backend, SecretBackend::Plaintext);
+
+        let recipients = |keys: Vec<&str>| {
+            OptionDataChangeNotification::ManualStringList(ManualStringListOptionChangeData::new(
+                NixBaseConfigOption::SecretRecipients.to_option_id(),
+                keys.into_iter().map(String::from).collect(),
+            ))
+        };
+        config
+            .app_option_changed(&recipients(vec!["age1abc", " "]))
+            .unwrap();
+        assert!(config.app_option_changed(&sops).unwrap());
+        assert_eq!(config.secret_settings().recipients, vec!["age1abc"]);
+
+        // the last recipient can't be removed while secrets are encrypted
+        assert!(config.app_option_changed(&recipients(vec![])).is_err());
+        assert!(config
+            .app_option_changed(&recipients(vec!["ssh-ed25519 AAAA"]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_ssh_password_policy() {
+        use crate::app_option_data::{
+            manual_string_list_data::ManualStringListOptionChangeData,
+            string_list_data::StringListOptionChangeData,
+        };
+
+        let mut config = NixBaseConfig::default();
+        assert_eq!(config.ssh_password_config(), (false, false, "".into()));
+
+        let policy = |p: &str| {
+            OptionDataChangeNotification::StringList(StringListOptionChangeData::new(
+                NixBaseConfigOption::SshPasswordAuth.to_option_id(),
+                p.into(),
+            ))
+        };
+        assert!(config.app_option_changed(&policy("lan")).is_err());
+        assert!(config.app_option_changed(&policy("always")).unwrap());
+        assert_eq!(config.ssh_password_config(), (true, false, "".into()));
+
+        config
+            .app_option_changed(&policy("trusted_networks"))
+            .unwrap();
+        let networks = |n: Vec<&str>| {
+            OptionDataChangeNotification::ManualStringList(ManualStringListOptionChangeData::new(
+                NixBaseConfigOption::TrustedNetworks.to_option_id(),
+                n.into_iter().map(String::from).collect(),
+            ))
+        };
+        assert!(config.app_option_changed(&networks(vec!["eth0"])).is_err());
+        config
+            .app_option_changed(&networks(vec!["10.0.0.0/8", "fd00::/8"]))
+            .unwrap();
+        assert_eq!(
+            config.ssh_password_config(),
+            (
+                false,
+                false,
+                "Match Address 10.0.0.0/8,fd00::/8\n          PasswordAuthentication yes".into()
+            )
+        );
+
+        // without trusted networks, no one may use a password
+        config.app_option_changed(&networks(vec![])).unwrap();
+        assert_eq!(config.ssh_password_config(), (false, false, "".into()));
+    }
+
+    #[test]
+    fn test_dns_settings() {
+        use crate::app_option_data::{
+            bool_data::BoolOptionChangeData,
+            manual_string_list_data::ManualStringListOptionChangeData,
+            string_list_data::StringListOptionChangeData,
+        };
+
+        let mut config = NixBaseConfig::default();
+        assert_eq!(config.dns_settings().dns_over_tls(), None);
+        assert!(config.dns_settings().nameservers().is_empty());
+
+        let resolver = |r: &str| {
+            OptionDataChangeNotification::StringList(StringListOptionChangeData::new(
+                NixBaseConfigOption::DnsResolver.to_option_id(),
+                r.into(),
+            ))
+        };
+        let servers = |s: Vec<&str>| {
+            OptionDataChangeNotification::ManualStringList(ManualStringListOptionChangeData::new(
+                NixBaseConfigOption::DnsServers.to_option_id(),
+                s.into_iter().map(String::from).collect(),
+            ))
+        };
+
+        assert!(config.app_option_changed(&resolver("google")).is_err());
+        assert!(config.app_option_changed(&resolver("quad9")).unwrap());
+        assert_eq!(config.dns_settings().dns_over_tls(), Some("false"));
+        assert_eq!(config.dns_settings().nameservers().len(), 4);
+
+        // the custom resolver needs valid servers
+        assert!(config.app_option_changed(&resolver("custom")).is_err());
+        assert!(config
+            .app_option_changed(&servers(vec!["dns.example.com"]))
+            .is_err());
+        assert!(config
+            .app_option_changed(&servers(vec!["1.1.1.1#"]))
+            .is_err());
+        config
+            .app_option_changed(&servers(vec!["10.0.0.1", " ", "fd00::1#dns.lan"]))
+            .unwrap();
+        assert!(config.app_option_changed(&resolver("custom")).unwrap());
+        assert_eq!(
+            config.dns_settings().nameservers(),
+            vec!["10.0.0.1", "fd00::1#dns.lan"]
+        );
+        assert!(config.app_option_changed(&servers(vec![])).is_err());
+
+        config.app_option_changed(&resolver("system")).unwrap();
+        config
+            .app_option_changed(&OptionDataChangeNotification::Bool(
+                BoolOptionChangeData::new(NixBaseConfigOption::DnsOverTls.to_option_id(), true),
+            ))
+            .unwrap();
+        assert_eq!(config.dns_settings().dns_over_tls(), Some("opportunistic"));
+    }
+
     #[test]
     fn test_nix_base_config_option_from_str_and_to_string() {
         let options = [
@@ -711,6 +2017,30 @@ mod tests {
             NixBaseConfigOption::DefaultLocale,
             NixBaseConfigOption::Username,
             NixBaseConfigOption::InitialPassword,
+            NixBaseConfigOption::SnapshotKeep,
+            NixBaseConfigOption::SnapshotDataDir,
+            NixBaseConfigOption::BackupKeepDaily,
+            NixBaseConfigOption::BackupKeepWeekly,
+            NixBaseConfigOption::TlsMode,
+            NixBaseConfigOption::Domain,
+            NixBaseConfigOption::AcmeEmail,
+            NixBaseConfigOption::TlsCertificate,
+            NixBaseConfigOption::TlsKey,
+            NixBaseConfigOption::Variables,
+            NixBaseConfigOption::Fail2banEnable,
+            NixBaseConfigOption::SshPort,
+            NixBaseConfigOption::SshMaxAuthTries,
+            NixBaseConfigOption::SshDisableRootLogin,
+            NixBaseConfigOption::SshTwoFactor,
+            NixBaseConfigOption::SshPasswordAuth,
+            NixBaseConfigOption::TrustedNetworks,
+            NixBaseConfigOption::ZramPercent,
+            NixBaseConfigOption::SecretBackend,
+            NixBaseConfigOption::SecretRecipients,
+            NixBaseConfigOption::DnsResolver,
+            NixBaseConfigOption::DnsServers,
+            NixBaseConfigOption::DnsOverTls,
+            NixBaseConfigOption::DiskAlertPercent,
         ];
 
         for &option in &options {
@@ -0,0 +1,118 @@
+//! Formats the rendered nix files with alejandra.
+//!
+//! Formatting is cosmetic. If alejandra fails on a file, e.g. on an edge
+//! case in its parser, the file is written as rendered instead of failing
+//! the save, so a formatter bug can't block applying an urgent fix. Every
+//! fallback is recorded for the frontend to warn about, see
+//! [take_fallbacks], and `nixblitz doctor` notes the files that can't be
+//! formatted, see [unformatted_files].
+
+use std::{fmt, fs, path::Path, sync::Mutex};
+
+use alejandra::format;
+
+use crate::registry;
+
+/// The fallbacks since the frontend last asked, see [take_fallbacks]
+static FALLBACKS: Mutex<Vec<FormatFallback>> = Mutex::new(Vec::new());
+
+/// A file that was written without formatting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatFallback {
+    /// The file relative to the work dir, e.g. src/apps/lnd.nix
+    pub file: String,
+
+    /// Why alejandra failed
+    pub error: String,
+}
+
+impl fmt::Display for FormatFallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} couldn't be formatted and was written as rendered: {}",
+            self.file, self.error
+        )
+    }
+}
+
+/// Formats a rendered nix file, returns it unchanged if alejandra fails
+///
+/// # Arguments
+/// * `file` - the template the nix was rendered from, used for the warning
+/// * `nix` - the rendered nix
+pub fn format_nix(file: &str, nix: String) -> String {
+    let (status, text) = format::in_memory(file.to_string(), nix.clone());
+    match status {
+        format::Status::Error(error) => {
+            let fallback = FormatFallback {
+                file: file.replace(".templ", ""),
+                error,
+            };
+            if let Ok(mut fallbacks) = FALLBACKS.lock() {
+                fallbacks.push(fallback);
+            }
+            nix
+        }
+        _ => text,
+    }
+}
+
+/// Returns the files written without formatting since the last call
+pub fn take_fallbacks() -> Vec<FormatFallback> {
+    FALLBACKS
+        .lock()
+        .map(|mut f| std::mem::take(&mut *f))
+        .unwrap_or_default()
+}
+
+/// Tries to format the rendered files of all apps in the work dir
+///
+/// # Returns
+/// The files alejandra fails on. Missing files are skipped.
+pub fn unformatted_files(work_dir: &Path) -> Vec<FormatFallback> {
+    registry::APPS
+        .iter()
+        .filter_map(|reg| {
+            let file = reg.template_file_name.replace(".templ", "");
+            let contents = fs::read_to_string(work_dir.join(&file)).ok()?;
+            match format::in_memory(file.clone(), contents).0 {
+                format::Status::Error(error) => Some(FormatFallback { file, error }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_format_nix() {
+        take_fallbacks();
+        assert!(format_nix("src/apps/x.nix.templ", "{ a=1; }".into()).contains("a = 1;"));
+
+        let broken = "{ a = 1; ".to_string();
+        assert_eq!(format_nix("src/apps/x.nix.templ", broken.clone()), broken);
+
+        let fallbacks = take_fallbacks();
+        assert_eq!(fallbacks.len(), 1);
+        assert_eq!(fallbacks[0].file, "src/apps/x.nix");
+        assert!(take_fallbacks().is_empty());
+    }
+
+    #[test]
+    fn test_unformatted_files() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/apps")).unwrap();
+        fs::write(dir.path().join("src/apps/lnd.nix"), "{ a = 1; }\n").unwrap();
+        fs::write(dir.path().join("src/apps/cln.nix"), "{ a = ").unwrap();
+
+        let files = unformatted_files(dir.path());
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].file, "src/apps/cln.nix");
+    }
+}
@@ -0,0 +1,224 @@
+//! Connection checks for the lightning backends.
+//!
+//! The checks ask the node for its info via the CLI tools nix-bitcoin
+//! installs, i.e. `lncli` and `lightning-cli`. This covers the RPC endpoint
+//! and the credentials (macaroon or rune) in one go. They must run on the node.
+
+use std::{fmt, process::Command};
+
+use error_stack::{Report, Result, ResultExt};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    app_option_data::option_data::{GetOptionId, OptionData, ToNixString, ToOptionId},
+    apps::SupportedApps,
+    cln::ClnConfigOption,
+    errors::ProjectError,
+    lnd::LndConfigOption,
+    project::Project,
+    registry::registration,
+};
+
+/// What a node reports about itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NodeInfo {
+    /// The public key of the node
+    pub pubkey: String,
+
+    /// The alias of the node, might be empty
+    pub alias: String,
+
+    /// The block height the node is synced to
+    pub block_height: u64,
+
+    /// Whether the node caught up with the chain
+    pub synced_to_chain: bool,
+}
+
+impl fmt::Display for NodeInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "pubkey:       {}", self.pubkey)?;
+        writeln!(f, "alias:        {}", self.alias)?;
+        writeln!(f, "block height: {}", self.block_height)?;
+        write!(
+            f,
+            "synced:       {}",
+            if self.synced_to_chain { "yes" } else { "no" }
+        )
+    }
+}
+
+/// Asks the given lightning node for its info
+///
+/// # Errors
+/// [ProjectError::NodeUnreachable] if the app isn't a lightning node, isn't
+/// enabled or doesn't answer. The report explains the most likely cause.
+pub fn ping(project: &Project, app: SupportedApps) -> Result<NodeInfo, ProjectError> {
+    let title = registration(app).title;
+    let unreachable = || Report::new(ProjectError::NodeUnreachable(title.to_string()));
+
+    if !project.is_app_enabled(app) {
+        return Err(unreachable().attach_printable(format!("{} is not enabled", title)));
+    }
+
+    let options = project.get_options_for(app);
+    let (program, args, parse): (&str, Vec<String>, fn(&str) -> Option<NodeInfo>) = match app {
+        SupportedApps::LND => ("lncli", lnd_args(&options), parse_lnd_getinfo),
+        SupportedApps::CoreLightning => ("lightning-cli", cln_args(&options), parse_cln_getinfo),
+        _ => {
+            return Err(unreachable().attach_printable(format!("{} is not a lightning node", title)))
+        }
+    };
+
+    let output = Command::new(program)
+        .args(&args)
+        .output()
+        .change_context(ProjectError::NodeUnreachable(title.to_string()))
+        .attach_printable_lazy(|| format!("Unable to run {}, is it installed?", program))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(unreachable()
+            .attach_printable(format!(
+                "{} {} failed: {}",
+                program,
+                args.join(" "),
+                stderr.trim()
+            ))
+            .attach_printable(failure_hint(&stderr)));
+    }
+
+    parse(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| unreachable().attach_printable("Unable to parse the node info"))
+}
+
+/// Arguments for `lncli` to reach the configured gRPC endpoint
+fn lnd_args(options: &[OptionData]) -> Vec<String> {
+    let mut address = "localhost".to_string();
+    let mut port = String::new();
+    for option in options {
+        match option {
+            OptionData::NetAddress(a) if *a.id() == LndConfigOption::RpcAddress.to_option_id() => {
+                if a.value().is_some() {
+                    address = a.to_nix_string(false);
+                }
+            }
+            OptionData::Port(p) if *p.id() == LndConfigOption::RpcPort.to_option_id() => {
+                port = p.value().to_string();
+            }
+            _ => (),
+        }
+    }
+
+    let mut args = vec![];
+    if !port.is_empty() {
+        args.push(format!("--rpcserver={}:{}", address, port));
+    }
+    args.push("getinfo".to_string());
+
+    args
+}
+
+/// Arguments for `lightning-cli` to reach the node via its RPC socket
+fn cln_args(options: &[OptionData]) -> Vec<String> {
+    let mut args = vec![];
+    for option in options {
+        if let OptionData::TextEdit(t) = option {
+            if *t.id() == ClnConfigOption::DataDir.to_option_id() && !t.value().is_empty() {
+                args.push(format!("--lightning-dir={}", t.value()));
+            }
+        }
+    }
+    args.push("getinfo".to_string());
+
+    args
+}
+
+fn parse_lnd_getinfo(json: &str) -> Option<NodeInfo> {
+    let v: Value = serde_json::from_str(json).ok()?;
+
+    Some(NodeInfo {
+        pubkey: v["identity_pubkey"].as_str()?.to_string(),
+        alias: v["alias"].as_str().unwrap_or_default().to_string(),
+        block_height: v["block_height"].as_u64()?,
+        synced_to_chain: v["synced_to_chain"].as_bool().unwrap_or(false),
+    })
+}
+
+fn parse_cln_getinfo(json: &str) -> Option<NodeInfo> {
+    let v: Value = serde_json::from_str(json).ok()?;
+
+    Some(NodeInfo {
+        pubkey: v["id"].as_str()?.to_string(),
+        alias: v["alias"].as_str().unwrap_or_default().to_string(),
+        block_height: v["blockheight"].as_u64()?,
+        // CLN only reports the sync state as warnings
+        synced_to_chain: v.get("warning_bitcoind_sync").is_none()
+            && v.get("warning_lightningd_sync").is_none(),
+    })
+}
+
+/// Translates the error output of the CLI tools into a likely cause
+fn failure_hint(stderr: &str) -> &'static str {
+    let stderr = stderr.to_lowercase();
+    if stderr.contains("macaroon") || stderr.contains("rune") {
+        "The credentials were rejected, run the check as a user that may read the admin macaroon or rune"
+    } else if stderr.contains("permission denied") {
+        "Permission denied, run the check as the service user or add your user to its group"
+    } else if stderr.contains("wallet locked") || stderr.contains("unlock") {
+        "The wallet is locked, unlock it first"
+    } else if stderr.contains("connection refused")
+        || stderr.contains("no such file")
+        || stderr.contains("unavailable")
+    {
+        "Nothing is listening on the RPC endpoint, is the service running?"
+    } else {
+        "Check the service logs, e.g. journalctl -u lnd or journalctl -u clightning"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lnd_getinfo() {
+        let json = r#"{
+            "identity_pubkey": "02abc",
+            "alias": "blitz",
+            "block_height": 860000,
+            "synced_to_chain": true
+        }"#;
+        assert_eq!(
+            parse_lnd_getinfo(json),
+            Some(NodeInfo {
+                pubkey: "02abc".into(),
+                alias: "blitz".into(),
+                block_height: 860000,
+                synced_to_chain: true,
+            })
+        );
+        assert_eq!(parse_lnd_getinfo("{}"), None);
+    }
+
+    #[test]
+    fn test_parse_cln_getinfo() {
+        let json = r#"{
+            "id": "03def",
+            "alias": "blitz",
+            "blockheight": 859000,
+            "warning_bitcoind_sync": "Bitcoind is not up-to-date with network."
+        }"#;
+        let info = parse_cln_getinfo(json).unwrap();
+        assert_eq!(info.pubkey, "03def");
+        assert_eq!(info.block_height, 859000);
+        assert!(!info.synced_to_chain);
+    }
+
+    #[test]
+    fn test_failure_hint() {
+        assert!(failure_hint("[lncli] could not load macaroon").contains("credentials"));
+        assert!(failure_hint("connect: connection refused").contains("listening"));
+    }
+}
@@ -0,0 +1,221 @@
+//! Sets many options at once, e.g. to provision nodes from a script.
+//!
+//! The changes map the apps to their options and the new values, in the
+//! format `nixblitz get --format json` and `--format toml` print:
+//!
+//! ```toml
+//! [lnd]
+//! enable = true
+//! port = 9736
+//! ```
+//!
+//! [Project::set_options](crate::project::Project::set_options) first applies
+//! the changes to a copy of the project in a scratch directory. Only if all
+//! of them are accepted they are applied to the project itself, so a bad
+//! value never leaves it half changed.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{Report, Result, ResultExt};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    app_option_data::option_data::{
+        GetOptionId, OptionData, OptionDataChangeNotification, OptionId,
+    },
+    errors::ProjectError,
+    option_values::change_from_json,
+    registry::registration_by_name,
+};
+
+/// The formats the changes can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangesFormat {
+    Json,
+    Toml,
+}
+
+impl ChangesFormat {
+    /// Picks the format by the extension of the file
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Some(Self::Json),
+            Some("toml") => Some(Self::Toml),
+            _ => None,
+        }
+    }
+}
+
+/// The new value of an option
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionValue {
+    pub id: OptionId,
+
+    /// The value in the format of
+    /// [json_value](crate::option_values::json_value)
+    pub value: Value,
+}
+
+/// Whether a change was accepted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangeResult {
+    pub id: OptionId,
+
+    /// Why the change was rejected, None if it was accepted
+    pub error: Option<String>,
+}
+
+/// The outcome of [Project::set_options](crate::project::Project::set_options)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BatchReport {
+    /// One result per change, in the order they were applied
+    pub results: Vec<ChangeResult>,
+
+    /// Whether the project was changed, only if all changes were accepted
+    pub applied: bool,
+}
+
+/// Reads the changes
+///
+/// Apps are given by their name or title, like in `nixblitz get`. Within an
+/// app its enable option is applied first, so the other options are checked
+/// against the enabled app.
+///
+/// # Errors
+/// [ProjectError::BatchError] if the text can't be parsed or names an
+/// unknown app
+pub fn parse(text: &str, format: ChangesFormat) -> Result<Vec<OptionValue>, ProjectError> {
+    let changes: Value = match format {
+        ChangesFormat::Json => serde_json::from_str(text).change_context(ProjectError::BatchError),
+        ChangesFormat::Toml => toml::from_str(text).change_context(ProjectError::BatchError),
+    }?;
+    let Value::Object(apps) = changes else {
+        return Err(Report::new(ProjectError::BatchError)
+            .attach_printable("Expected a table of apps and their options"));
+    };
+
+    let mut res = vec![];
+    for (name, options) in apps {
+        let reg = registration_by_name(&name).ok_or_else(|| {
+            Report::new(ProjectError::BatchError).attach_printable(format!("Unknown app {}", name))
+        })?;
+        let Value::Object(options) = options else {
+            return Err(Report::new(ProjectError::BatchError)
+                .attach_printable(format!("Expected a table of the options of {}", name)));
+        };
+
+        let mut options: Vec<(String, Value)> = options.into_iter().collect();
+        options.sort_by_key(|(option, _)| option != "enable");
+        res.extend(options.into_iter().map(|(option, value)| OptionValue {
+            id: OptionId::new(reg.app, option),
+            value,
+        }));
+    }
+
+    Ok(res)
+}
+
+/// Converts the value into a change of the option
+///
+/// # Arguments
+/// * `options` - the options of the app as they are now
+///
+/// # Returns
+/// The change, or why the value doesn't fit the option
+pub(crate) fn to_change(
+    options: &[OptionData],
+    change: &OptionValue,
+) -> std::result::Result<OptionDataChangeNotification, String> {
+    let option = options
+        .iter()
+        .find(|o| *o.id() == change.id)
+        .ok_or_else(|| format!("{} has no option {}", change.id.app, change.id.option))?;
+
+    change_from_json(option, &change.value)
+}
+
+/// A copy of the project the changes are tried on, removed when dropped
+pub(crate) struct Scratch {
+    pub dir: PathBuf,
+}
+
+impl Scratch {
+    /// Copies the project files without the git history and the build
+    /// results
+    ///
+    /// # Errors
+    /// [ProjectError::BatchError] if the project can't be copied
+    pub fn copy(work_dir: &Path) -> Result<Self, ProjectError> {
+        let dir = std::env::temp_dir().join(format!("nixblitz-set-{}", std::process::id()));
+        // a scratch dir left behind by a crashed run
+        let _ = fs::remove_dir_all(&dir);
+
+        let scratch = Self { dir };
+        copy_dir(work_dir, &scratch.dir)
+            .change_context(ProjectError::BatchError)
+            .attach_printable_lazy(|| {
+                format!(
+                    "Unable to copy {} to {}",
+                    work_dir.display(),
+                    scratch.dir.display()
+                )
+            })?;
+
+        Ok(scratch)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name == ".git" || name == "result" || name.starts_with("result-") {
+            continue;
+        }
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &to.join(&name))?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), to.join(&name))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::apps::SupportedApps;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let toml = "[lnd]\nport = 9736\nenable = true\n\n[bitcoind]\nprune = \"Manual\"\n";
+        let changes = parse(toml, ChangesFormat::Toml).unwrap();
+        let ids: Vec<String> = changes.iter().map(|c| c.id.to_string()).collect();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].id.app, SupportedApps::BitcoinCore);
+        assert_eq!(ids[1..], ["LND: enable", "LND: port"]);
+        assert_eq!(changes[2].value, Value::from(9736));
+
+        let json = r#"{"lnd": {"enable": true}}"#;
+        assert_eq!(parse(json, ChangesFormat::Json).unwrap().len(), 1);
+
+        assert!(parse(r#"{"foo": {"enable": true}}"#, ChangesFormat::Json).is_err());
+        assert!(parse(r#"{"lnd": true}"#, ChangesFormat::Json).is_err());
+        assert!(parse("[1, 2]", ChangesFormat::Json).is_err());
+    }
+}
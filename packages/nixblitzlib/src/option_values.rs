@@ -0,0 +1,352 @@
+//! Plain values of the options, for output meant to be read by people or
+//! scripts rather than edited, e.g. by `nixblitz get` and `nixblitz diff`.
+//! Passwords only ever show whether they are set. [change_from_json] reads
+//! the values back, e.g. for `nixblitz set --from-file`.
+
+use std::net::IpAddr;
+
+use error_stack::{Result, ResultExt};
+use serde_json::{Map, Value};
+
+use crate::{
+    app_option_data::{
+        amount_data::AmountOptionChangeData,
+        bool_data::BoolOptionChangeData,
+        code_data::CodeOptionChangeData,
+        manual_string_list_data::ManualStringListOptionChangeData,
+        net_address_data::NetAddressOptionChangeData,
+        net_address_list_data::{NetAddressListOptionChangeData, NetAddressRange},
+        number_data::NumberOptionChangeData,
+        option_data::{GetOptionId, OptionData, OptionDataChangeNotification},
+        password_data::PasswordOptionChangeData,
+        percent_data::PercentOptionChangeData,
+        port_data::PortOptionChangeData,
+        string_list_data::StringListOptionChangeData,
+        text_edit_data::TextOptionChangeData,
+    },
+    errors::ProjectError,
+    number_value::NumberValue,
+};
+
+/// Shown instead of the value of a password
+pub const HIDDEN_VALUE: &str = "********";
+
+/// Shown for a password that isn't set
+const NOT_SET_VALUE: &str = "not set";
+
+/// The formats the options of an app can be dumped in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// One `name=value` line per option, line breaks in values are escaped
+    #[default]
+    Plain,
+    Json,
+
+    /// Like JSON, but options without a value are left out
+    Toml,
+}
+
+/// Formats the value of an option for the user. Passwords are masked.
+pub fn display_value(option: &OptionData) -> String {
+    match option {
+        OptionData::Bool(o) => o.value().to_string(),
+        OptionData::StringList(o) => o.value().to_string(),
+        OptionData::TextEdit(o) => o.value().to_string(),
+        OptionData::PasswordEdit(o) => {
+            if o.hashed_value().is_empty() && o.secret().is_none() {
+                NOT_SET_VALUE.to_string()
+            } else {
+                HIDDEN_VALUE.to_string()
+            }
+        }
+        OptionData::NumberEdit(o) => o.value().to_string(),
+        OptionData::NetAddress(o) => o.value().map(|a| a.to_string()).unwrap_or_default(),
+        OptionData::NetAddressList(o) => o
+            .value()
+            .iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        OptionData::Port(o) => o.value().to_string(),
+        OptionData::ManualStringList(o) => o.value().join(", "),
+        OptionData::Code(o) => o.value().to_string(),
+        OptionData::Percent(o) => format!("{}%", o.value()),
+        OptionData::Amount(o) => o.formatted(),
+    }
+}
+
+/// Returns the value of an option with its type, `null` if it isn't set.
+/// Passwords are masked like in [display_value], amounts are in satoshis.
+pub fn json_value(option: &OptionData) -> Value {
+    match option {
+        OptionData::Bool(o) => Value::from(o.value()),
+        OptionData::NumberEdit(o) => number_value(o.value()),
+        OptionData::Port(o) => number_value(o.value()),
+        OptionData::NetAddress(o) => o
+            .value()
+            .map(|a| Value::from(a.to_string()))
+            .unwrap_or(Value::Null),
+        OptionData::NetAddressList(o) => o.value().iter().map(|a| a.to_string()).collect(),
+        OptionData::ManualStringList(o) => Value::from(o.value().clone()),
+        OptionData::Percent(o) => Value::from(o.value()),
+        OptionData::Amount(o) => Value::from(o.value()),
+        OptionData::StringList(_)
+        | OptionData::TextEdit(_)
+        | OptionData::PasswordEdit(_)
+        | OptionData::Code(_) => Value::from(display_value(option)),
+    }
+}
+
+fn number_value(value: &NumberValue) -> Value {
+    match value {
+        NumberValue::U16(v) => v.map(Value::from),
+        NumberValue::UInt(v) => v.map(Value::from),
+        NumberValue::Int(v) => v.map(Value::from),
+        NumberValue::Float(v) => v.map(Value::from),
+    }
+    .unwrap_or(Value::Null)
+}
+
+/// Reads a value in the format of [json_value] into a change of the option.
+/// Passwords are given in plain text, amounts in satoshis.
+///
+/// # Returns
+/// The change, or what the value should look like
+pub fn change_from_json(
+    option: &OptionData,
+    value: &Value,
+) -> std::result::Result<OptionDataChangeNotification, String> {
+    let id = option.id().clone();
+    let string = || {
+        value
+            .as_str()
+            .map(str::to_string)
+            .ok_or("expected a string")
+    };
+    let strings = || {
+        value
+            .as_array()
+            .and_then(|a| {
+                a.iter()
+                    .map(|v| v.as_str().map(str::to_string))
+                    .collect::<Option<Vec<String>>>()
+            })
+            .ok_or("expected a list of strings")
+    };
+
+    let change = match option {
+        OptionData::Bool(_) => OptionDataChangeNotification::Bool(BoolOptionChangeData::new(
+            id,
+            value.as_bool().ok_or("expected true or false")?,
+        )),
+        OptionData::StringList(_) => {
+            OptionDataChangeNotification::StringList(StringListOptionChangeData::new(id, string()?))
+        }
+        OptionData::TextEdit(_) => {
+            OptionDataChangeNotification::TextEdit(TextOptionChangeData::new(id, string()?))
+        }
+        OptionData::PasswordEdit(_) => {
+            let password = string()?;
+            OptionDataChangeNotification::PasswordEdit(PasswordOptionChangeData::new(
+                id,
+                password.clone(),
+                Some(password),
+            ))
+        }
+        OptionData::NumberEdit(o) => OptionDataChangeNotification::Number(
+            NumberOptionChangeData::new(id, number_from_json(o.value(), value)?),
+        ),
+        OptionData::NetAddress(_) => {
+            let address = match value {
+                Value::Null => None,
+                _ => Some(
+                    string()?
+                        .parse::<IpAddr>()
+                        .map_err(|_| "expected an IP address".to_string())?,
+                ),
+            };
+            OptionDataChangeNotification::NetAddress(NetAddressOptionChangeData::new(id, address))
+        }
+        OptionData::NetAddressList(_) => {
+            let ranges = strings()?
+                .iter()
+                .map(|s| s.parse::<NetAddressRange>())
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+            OptionDataChangeNotification::NetAddressList(NetAddressListOptionChangeData::new(
+                id, ranges,
+            ))
+        }
+        OptionData::Port(o) => OptionDataChangeNotification::Port(PortOptionChangeData::new(
+            id,
+            number_from_json(o.value(), value)?,
+        )),
+        OptionData::ManualStringList(_) => OptionDataChangeNotification::ManualStringList(
+            ManualStringListOptionChangeData::new(id, strings()?),
+        ),
+        OptionData::Code(_) => {
+            OptionDataChangeNotification::Code(CodeOptionChangeData::new(id, string()?))
+        }
+        OptionData::Percent(_) => {
+            let percent = value
+                .as_u64()
+                .and_then(|v| u8::try_from(v).ok())
+                .ok_or("expected a number from 0 to 100")?;
+            OptionDataChangeNotification::Percent(PercentOptionChangeData::new(id, percent))
+        }
+        OptionData::Amount(o) => OptionDataChangeNotification::Amount(AmountOptionChangeData::new(
+            id,
+            value.as_u64().ok_or("expected an amount in satoshis")?,
+            o.unit(),
+        )),
+    };
+
+    Ok(change)
+}
+
+/// Reads a number of the same kind as the current value, `null` unsets it
+fn number_from_json(
+    current: &NumberValue,
+    value: &Value,
+) -> std::result::Result<NumberValue, String> {
+    let number = match (current, value) {
+        (NumberValue::U16(_), Value::Null) => Some(NumberValue::U16(None)),
+        (NumberValue::UInt(_), Value::Null) => Some(NumberValue::UInt(None)),
+        (NumberValue::Int(_), Value::Null) => Some(NumberValue::Int(None)),
+        (NumberValue::Float(_), Value::Null) => Some(NumberValue::Float(None)),
+        (NumberValue::U16(_), v) => v
+            .as_u64()
+            .and_then(|v| u16::try_from(v).ok())
+            .map(|v| NumberValue::U16(Some(v))),
+        (NumberValue::UInt(_), v) => v
+            .as_u64()
+            .and_then(|v| usize::try_from(v).ok())
+            .map(|v| NumberValue::UInt(Some(v))),
+        (NumberValue::Int(_), v) => v
+            .as_i64()
+            .and_then(|v| isize::try_from(v).ok())
+            .map(|v| NumberValue::Int(Some(v))),
+        (NumberValue::Float(_), v) => v.as_f64().map(|v| NumberValue::Float(Some(v))),
+    };
+
+    number.ok_or_else(|| match current {
+        NumberValue::U16(_) => "expected a number from 0 to 65535".to_string(),
+        NumberValue::UInt(_) => "expected a positive whole number".to_string(),
+        NumberValue::Int(_) => "expected a whole number".to_string(),
+        NumberValue::Float(_) => "expected a number".to_string(),
+    })
+}
+
+/// Formats the options of an app, keyed by their names
+///
+/// # Errors
+/// [ProjectError::DumpError] if the values can't be serialized
+pub fn dump(options: &[OptionData], format: DumpFormat) -> Result<String, ProjectError> {
+    if format == DumpFormat::Plain {
+        let lines: Vec<String> = options
+            .iter()
+            .map(|o| {
+                format!(
+                    "{}={}",
+                    o.id().option,
+                    display_value(o).replace('\n', "\\n")
+                )
+            })
+            .collect();
+        return Ok(lines.join("\n"));
+    }
+
+    let values: Map<String, Value> = options
+        .iter()
+        .map(|o| (o.id().option.clone(), json_value(o)))
+        .filter(|(_, v)| format != DumpFormat::Toml || !v.is_null())
+        .collect();
+
+    match format {
+        DumpFormat::Toml => toml::to_string(&values).change_context(ProjectError::DumpError),
+        _ => serde_json::to_string_pretty(&values).change_context(ProjectError::DumpError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app_config::AppConfig,
+        app_option_data::option_data::ToOptionId,
+        lnd::{LightningNetworkDaemonService, LndConfigOption},
+    };
+
+    use super::*;
+
+    fn options() -> Vec<OptionData> {
+        LightningNetworkDaemonService::default().get_options()
+    }
+
+    fn find(options: &[OptionData], option: LndConfigOption) -> OptionData {
+        let id = option.to_option_id();
+        options.iter().find(|o| *o.id() == id).unwrap().clone()
+    }
+
+    #[test]
+    fn test_json_value() {
+        let options = options();
+        assert_eq!(
+            json_value(&find(&options, LndConfigOption::Enable)),
+            Value::from(false)
+        );
+        assert_eq!(
+            json_value(&find(&options, LndConfigOption::Port)),
+            Value::from(9735)
+        );
+        assert_eq!(
+            json_value(&find(&options, LndConfigOption::Address)),
+            Value::from("127.0.0.1")
+        );
+        assert_eq!(
+            json_value(&find(&options, LndConfigOption::WtClientTowers)),
+            Value::Array(vec![])
+        );
+    }
+
+    #[test]
+    fn test_change_from_json() {
+        let options = options();
+        let enable = find(&options, LndConfigOption::Enable);
+        assert!(matches!(
+            change_from_json(&enable, &Value::from(true)),
+            Ok(OptionDataChangeNotification::Bool(_))
+        ));
+        assert!(change_from_json(&enable, &Value::from("yes")).is_err());
+
+        let port = find(&options, LndConfigOption::Port);
+        assert!(change_from_json(&port, &Value::from(9736)).is_ok());
+        assert_eq!(
+            change_from_json(&port, &Value::from(70000)).unwrap_err(),
+            "expected a number from 0 to 65535"
+        );
+
+        let address = find(&options, LndConfigOption::Address);
+        assert!(change_from_json(&address, &Value::from("10.0.0.1")).is_ok());
+        assert!(change_from_json(&address, &Value::from("localhost")).is_err());
+
+        let towers = find(&options, LndConfigOption::WtClientTowers);
+        assert!(change_from_json(&towers, &Value::from(vec!["a@b:9911"])).is_ok());
+        assert!(change_from_json(&towers, &Value::from("a@b:9911")).is_err());
+    }
+
+    #[test]
+    fn test_dump() {
+        let options = options();
+
+        let plain = dump(&options, DumpFormat::Plain).unwrap();
+        assert!(plain.lines().any(|l| l == "enable=false"));
+        assert_eq!(plain.lines().count(), options.len());
+
+        let json: Value = serde_json::from_str(&dump(&options, DumpFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["port"], Value::from(9735));
+
+        let toml: toml::Table = dump(&options, DumpFormat::Toml).unwrap().parse().unwrap();
+        assert_eq!(toml["port"].as_integer(), Some(9735));
+        assert_eq!(toml["enable"].as_bool(), Some(false));
+    }
+}
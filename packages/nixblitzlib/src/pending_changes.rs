@@ -0,0 +1,286 @@
+//! Lists the options that changed since the configuration was last applied.
+//!
+//! The node is switched to the configuration in the project repository, so
+//! its last commit (HEAD) counts as the applied state. Every option whose
+//! value differs from the one in that commit is a pending change. Commit the
+//! project after switching to mark the changes as applied.
+//!
+//! Before an install there is nothing applied yet, the options are compared
+//! with their defaults instead, see [non_default_options].
+
+use std::{path::Path, process::Command};
+
+use error_stack::{Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_option_data::option_data::{GetOptionId, OptionData, OptionId},
+    apps::SupportedApps,
+    errors::ProjectError,
+    extra_nix, migrations,
+    option_values::display_value,
+    registry,
+};
+
+/// An option whose value differs from the applied one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptionChange {
+    pub id: OptionId,
+
+    /// The applied value, `None` if the option didn't exist back then
+    pub old: Option<String>,
+
+    /// The value in the project
+    pub new: String,
+}
+
+/// The pending changes of an app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppChanges {
+    pub app: SupportedApps,
+    pub changes: Vec<OptionChange>,
+}
+
+/// Compares the values, a password counts as changed if its hash or the
+/// stored secret changed
+fn has_changed(old: &OptionData, new: &OptionData) -> bool {
+    match (old, new) {
+        (OptionData::PasswordEdit(old), OptionData::PasswordEdit(new)) => {
+            old.hashed_value() != new.hashed_value() || old.secret() != new.secret()
+        }
+        // the unit only changes how the amount is shown
+        (OptionData::Amount(old), OptionData::Amount(new)) => old.value() != new.value(),
+        (old, new) => display_value(old) != display_value(new),
+    }
+}
+
+/// Compares the options of the apps with the last commit of the project
+///
+/// # Arguments
+///
+/// * `current` - returns the options of an app as they are now
+///
+/// # Errors
+///
+/// * [ProjectError::PendingChangesError] if the work dir is not a git
+///   repository with at least one commit or the committed files of an app
+///   can't be read
+pub fn pending_changes<F>(work_dir: &Path, current: F) -> Result<Vec<AppChanges>, ProjectError>
+where
+    F: Fn(SupportedApps) -> Vec<OptionData>,
+{
+    git(work_dir, &["rev-parse", "--verify", "--quiet", "HEAD"])
+        .attach_printable("The project has no commit to compare with")?;
+
+    let mut res = vec![];
+    for reg in registry::APPS.iter() {
+        let mut applied = match committed_file(work_dir, reg.json_file_name) {
+            Some(json) => migrations::migrate_json(reg.app, &json)
+                .and_then(|json| {
+                    (reg.options)(&json).change_context(ProjectError::PendingChangesError)
+                })
+                .change_context(ProjectError::PendingChangesError)
+                .attach_printable_lazy(|| {
+                    format!("Unable to parse {} at HEAD", reg.json_file_name)
+                })?,
+            None => vec![],
+        };
+        let extra_file = extra_nix::file_name(reg.app);
+        let extra = committed_file(work_dir, &extra_file.to_string_lossy());
+        if let Some(option) = extra_nix::option_from_contents(reg.app, extra.as_deref()) {
+            applied.push(OptionData::Code(Box::new(option)));
+        }
+
+        let changes = compare(&applied, &current(reg.app));
+        if !changes.is_empty() {
+            res.push(AppChanges {
+                app: reg.app,
+                changes,
+            });
+        }
+    }
+
+    Ok(res)
+}
+
+/// Compares the options of the apps with their defaults, e.g. for a final
+/// review before the configuration is installed. The old value of a change
+/// is the default.
+///
+/// # Arguments
+///
+/// * `current` - returns the options of an app as they are now
+pub fn non_default_options<F>(current: F) -> Vec<AppChanges>
+where
+    F: Fn(SupportedApps) -> Vec<OptionData>,
+{
+    registry::APPS
+        .iter()
+        .filter_map(|reg| {
+            let mut defaults = (reg.default_options)();
+            if let Some(option) = extra_nix::option_from_contents(reg.app, None) {
+                defaults.push(OptionData::Code(Box::new(option)));
+            }
+
+            let changes = compare(&defaults, &current(reg.app));
+            (!changes.is_empty()).then_some(AppChanges {
+                app: reg.app,
+                changes,
+            })
+        })
+        .collect()
+}
+
+/// Lists the options in `current` that differ from those in `base`
+fn compare(base: &[OptionData], current: &[OptionData]) -> Vec<OptionChange> {
+    current
+        .iter()
+        .filter_map(|new| {
+            let old = base.iter().find(|o| o.id() == new.id());
+            match old {
+                Some(old) if !has_changed(old, new) => None,
+                _ => Some(OptionChange {
+                    id: new.id().clone(),
+                    old: old.map(display_value),
+                    new: display_value(new),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Returns the contents of a project file at HEAD, `None` if it wasn't committed
+fn committed_file(work_dir: &Path, path: &str) -> Option<String> {
+    git(work_dir, &["show", &format!("HEAD:./{}", path)]).ok()
+}
+
+fn git(work_dir: &Path, args: &[&str]) -> Result<String, ProjectError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(work_dir)
+        .args(args)
+        .output()
+        .change_context(ProjectError::PendingChangesError)
+        .attach_printable("Unable to run git")?;
+
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::PendingChangesError).attach_printable(format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::{
+            bool_data::BoolOptionChangeData,
+            option_data::{OptionDataChangeNotification, ToOptionId},
+        },
+        lnd::LndConfigOption,
+        project::Project,
+        utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_pending_changes() {
+        let dir = tempdir().unwrap();
+        init_default_project(dir.path(), Some(false)).unwrap();
+        let mut project = Project::load(dir.path().to_path_buf()).unwrap();
+
+        // not a git repository yet
+        assert!(project.pending_changes().is_err());
+
+        run_git(dir.path(), &["init", "-q"]);
+        run_git(dir.path(), &["add", "--all"]);
+        run_git(dir.path(), &["commit", "-q", "-m", "init"]);
+        assert!(project.pending_changes().unwrap().is_empty());
+
+        project.set_selected_app(SupportedApps::LND);
+        project
+            .on_option_changed(OptionDataChangeNotification::Bool(
+                BoolOptionChangeData::new(LndConfigOption::Enable.to_option_id(), true),
+            ))
+            .unwrap();
+        extra_nix::save(
+            dir.path(),
+            SupportedApps::LND,
+            "services.lnd.enable = true;",
+        )
+        .unwrap();
+
+        let changes = project.pending_changes().unwrap();
+        let lnd = changes
+            .iter()
+            .find(|c| c.app == SupportedApps::LND)
+            .unwrap();
+        let enable = lnd
+            .changes
+            .iter()
+            .find(|c| c.id == LndConfigOption::Enable.to_option_id())
+            .unwrap();
+        assert_eq!(enable.old.as_deref(), Some("false"));
+        assert_eq!(enable.new, "true");
+        let extra = lnd
+            .changes
+            .iter()
+            .find(|c| c.id == extra_nix::option_id(SupportedApps::LND))
+            .unwrap();
+        assert_eq!(extra.old.as_deref(), Some(""));
+        assert_eq!(extra.new, "services.lnd.enable = true;");
+
+        // enabling LND also enables Bitcoin Core
+        assert!(changes.iter().any(|c| c.app == SupportedApps::BitcoinCore));
+
+        run_git(dir.path(), &["add", "--all"]);
+        run_git(dir.path(), &["commit", "-q", "-m", "apply"]);
+        assert!(project.pending_changes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_non_default_options() {
+        let dir = tempdir().unwrap();
+        init_default_project(dir.path(), Some(false)).unwrap();
+        let mut project = Project::load(dir.path().to_path_buf()).unwrap();
+        let lnd_changes = |project: &Project| {
+            project
+                .non_default_options()
+                .into_iter()
+                .find(|c| c.app == SupportedApps::LND)
+        };
+        assert!(lnd_changes(&project).is_none());
+
+        project.set_selected_app(SupportedApps::LND);
+        project
+            .on_option_changed(OptionDataChangeNotification::Bool(
+                BoolOptionChangeData::new(LndConfigOption::Enable.to_option_id(), true),
+            ))
+            .unwrap();
+
+        let lnd = lnd_changes(&project).unwrap();
+        assert_eq!(lnd.changes.len(), 1);
+        assert_eq!(lnd.changes[0].old.as_deref(), Some("false"));
+        assert_eq!(lnd.changes[0].new, "true");
+    }
+}
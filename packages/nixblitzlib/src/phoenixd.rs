@@ -0,0 +1,258 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::{app_options, AppConfig},
+    app_option_data::{
+        bool_data::BoolOptionData,
+        option_data::{OptionId, ToOptionId},
+        port_data::PortOptionData,
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    tor::port_or,
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/phoenixd.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/phoenixd.json";
+
+/// Runs phoenixd, a lightweight lightning node by ACINQ.
+///
+/// phoenixd manages a single channel with the ACINQ node and uses its
+/// Electrum servers for the chain data, so unlike LND and Core Lightning
+/// it doesn't need a local bitcoind. Its HTTP API only listens on localhost,
+/// the password is generated into phoenix.conf in the data directory.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PhoenixdService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// Port of the HTTP API
+    pub http_port: Box<PortOptionData>,
+
+    /// Where phoenixd stores the seed, the channel state and its config
+    pub data_dir: Box<TextOptionData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PhoenixdConfigOption {
+    Enable,
+    HttpPort,
+    DataDir,
+}
+
+impl ToOptionId for PhoenixdConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::Phoenixd, self.to_string())
+    }
+}
+
+impl FromStr for PhoenixdConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<PhoenixdConfigOption, ()> {
+        match s {
+            "enable" => Ok(PhoenixdConfigOption::Enable),
+            "http_port" => Ok(PhoenixdConfigOption::HttpPort),
+            "data_dir" => Ok(PhoenixdConfigOption::DataDir),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for PhoenixdConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            PhoenixdConfigOption::Enable => "enable",
+            PhoenixdConfigOption::HttpPort => "http_port",
+            PhoenixdConfigOption::DataDir => "data_dir",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for PhoenixdService {
+    app_options!(PhoenixdConfigOption {
+        Enable => enable,
+        HttpPort => http_port,
+        DataDir => data_dir,
+    });
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering phoenixd config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Default for PhoenixdService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                PhoenixdConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            http_port: Box::new(PortOptionData::new(
+                PhoenixdConfigOption::HttpPort.to_option_id(),
+                NumberValue::U16(Some(defaults::phoenixd::HTTP_PORT)),
+            )),
+            data_dir: Box::new(TextOptionData::new(
+                PhoenixdConfigOption::DataDir.to_option_id(),
+                defaults::phoenixd::DATA_DIR.into(),
+                1,
+                false,
+                defaults::phoenixd::DATA_DIR.into(),
+            )),
+        }
+    }
+}
+
+impl PhoenixdService {
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            (
+                "http_port",
+                port_or(self.http_port.value(), defaults::phoenixd::HTTP_PORT).to_string(),
+            ),
+            ("data_dir", self.data_dir.value().to_string()),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render phoenixd template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<PhoenixdService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::utils::init_default_project;
+
+    use super::*;
+
+    fn get_test_service() -> PhoenixdService {
+        let mut service = PhoenixdService::default();
+        service.enable.set_value(true);
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = PhoenixdService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains("lib.mkIf true"));
+            assert!(data.contains("--http-bind-port=9740"));
+            assert!(data.contains("--datadir=/var/lib/phoenixd"));
+        }
+
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,182 @@
+//! Detection of the platform nixblitz runs on.
+//!
+//! The project flake has a configuration for each supported platform, see
+//! `nixosConfigurations` in the template. Anything else must be stopped early,
+//! otherwise nix fails with a confusing error about an unknown system.
+
+use core::fmt;
+use std::{env::consts, fs, path::Path, str::FromStr};
+
+use error_stack::{Report, Result};
+
+use crate::{
+    errors::ProjectError,
+    utils::{create_file, exclude_from_git},
+};
+
+/// The platforms the project flake has a configuration for
+pub const SUPPORTED_PLATFORMS: [SystemPlatform; 2] =
+    [SystemPlatform::X86_64, SystemPlatform::Aarch64];
+
+/// File inside the work dir holding the platform of the local machine.
+/// A project shared between machines via git keeps it out of the repository.
+pub const LOCAL_PLATFORM_FILE: &str = ".nixblitz/platform";
+
+/// A platform as nix names it, e.g. "x86_64-linux"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SystemPlatform {
+    /// x86_64 Linux, used by the VM configuration
+    X86_64,
+
+    /// aarch64 Linux, used by the Raspberry Pi configuration
+    Aarch64,
+
+    /// Any other platform, holds the nix system name
+    Unsupported(String),
+}
+
+impl SystemPlatform {
+    /// Builds the platform from a CPU architecture and an OS name as
+    /// found in [std::env::consts]
+    fn from_parts(arch: &str, os: &str) -> Self {
+        match (arch, os) {
+            ("x86_64", "linux") => SystemPlatform::X86_64,
+            ("aarch64", "linux") => SystemPlatform::Aarch64,
+            _ => SystemPlatform::Unsupported(format!("{}-{}", arch, os)),
+        }
+    }
+}
+
+impl fmt::Display for SystemPlatform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SystemPlatform::X86_64 => write!(f, "x86_64-linux"),
+            SystemPlatform::Aarch64 => write!(f, "aarch64-linux"),
+            SystemPlatform::Unsupported(system) => write!(f, "{}", system),
+        }
+    }
+}
+
+impl FromStr for SystemPlatform {
+    type Err = String;
+
+    /// Parses a supported nix system name, e.g. `aarch64-linux`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        SUPPORTED_PLATFORMS
+            .iter()
+            .find(|p| p.to_string() == s)
+            .cloned()
+            .ok_or_else(|| format!("expected one of {}, got \"{}\"", supported_list(), s))
+    }
+}
+
+fn supported_list() -> String {
+    SUPPORTED_PLATFORMS
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the platform of the machine nixblitz runs on
+pub fn get_system_platform() -> SystemPlatform {
+    SystemPlatform::from_parts(consts::ARCH, consts::OS)
+}
+
+/// Returns the platform to build for. A platform forced by the user wins
+/// over the detected one.
+///
+/// # Errors
+/// [ProjectError::UnsupportedPlatform] if the detected platform is unsupported
+/// and none was forced
+pub fn resolve_platform(forced: Option<SystemPlatform>) -> Result<SystemPlatform, ProjectError> {
+    match forced.unwrap_or_else(get_system_platform) {
+        SystemPlatform::Unsupported(system) => {
+            Err(Report::new(ProjectError::UnsupportedPlatform(system))
+                .attach_printable(format!("Supported platforms: {}", supported_list()))
+                .attach_printable("Use --platform to choose one manually"))
+        }
+        platform => Ok(platform),
+    }
+}
+
+/// Returns the platform stored for the local machine, if any
+pub fn local_platform(work_dir: &Path) -> Option<SystemPlatform> {
+    let system = fs::read_to_string(work_dir.join(LOCAL_PLATFORM_FILE)).ok()?;
+    SystemPlatform::from_str(system.trim()).ok()
+}
+
+/// Stores the platform of the local machine in [LOCAL_PLATFORM_FILE] and
+/// excludes the file from git if the work dir is a git repository
+pub fn save_local_platform(work_dir: &Path, platform: &SystemPlatform) -> Result<(), ProjectError> {
+    create_file(
+        &work_dir.join(LOCAL_PLATFORM_FILE),
+        format!("{}\n", platform).as_bytes(),
+        Some(true),
+    )?;
+
+    exclude_from_git(work_dir, LOCAL_PLATFORM_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::GIT_EXCLUDE_FILE;
+
+    use super::*;
+
+    #[test]
+    fn test_from_parts() {
+        assert_eq!(
+            SystemPlatform::from_parts("x86_64", "linux"),
+            SystemPlatform::X86_64
+        );
+        assert_eq!(
+            SystemPlatform::from_parts("aarch64", "linux"),
+            SystemPlatform::Aarch64
+        );
+        assert_eq!(
+            SystemPlatform::from_parts("aarch64", "macos"),
+            SystemPlatform::Unsupported("aarch64-macos".into())
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            SystemPlatform::from_str("aarch64-linux"),
+            Ok(SystemPlatform::Aarch64)
+        );
+        assert!(SystemPlatform::from_str("riscv64-linux").is_err());
+    }
+
+    #[test]
+    fn test_local_platform() {
+        let dir = tempfile::tempdir().unwrap();
+        let work_dir = dir.path();
+        assert_eq!(local_platform(work_dir), None);
+
+        fs::create_dir_all(work_dir.join(".git/info")).unwrap();
+        fs::write(work_dir.join(GIT_EXCLUDE_FILE), "# comment").unwrap();
+        save_local_platform(work_dir, &SystemPlatform::Aarch64).unwrap();
+        save_local_platform(work_dir, &SystemPlatform::Aarch64).unwrap();
+        assert_eq!(local_platform(work_dir), Some(SystemPlatform::Aarch64));
+
+        let excluded = fs::read_to_string(work_dir.join(GIT_EXCLUDE_FILE)).unwrap();
+        assert_eq!(excluded, format!("# comment\n{}\n", LOCAL_PLATFORM_FILE));
+    }
+
+    #[test]
+    fn test_resolve_platform() {
+        assert_eq!(
+            resolve_platform(Some(SystemPlatform::Aarch64)).unwrap(),
+            SystemPlatform::Aarch64
+        );
+
+        let err = resolve_platform(Some(SystemPlatform::Unsupported("riscv64-linux".into())))
+            .unwrap_err();
+        assert!(matches!(
+            err.current_context(),
+            ProjectError::UnsupportedPlatform(s) if s == "riscv64-linux"
+        ));
+    }
+}
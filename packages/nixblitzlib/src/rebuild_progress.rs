@@ -0,0 +1,283 @@
+//! Turns the output of nixos-rebuild into progress events.
+//!
+//! nix announces how many derivations it will build and how many paths it
+//! will fetch, then prints a line per build and download. [ProgressParser]
+//! follows these lines and counts them, so a frontend can show a progress
+//! bar instead of the raw log. `nixblitz apply --json-progress` prints the
+//! events as newline delimited JSON. Lines that carry no progress are
+//! skipped.
+
+use serde::Serialize;
+
+/// The steps of a rebuild
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebuildPhase {
+    /// The configuration is evaluated
+    Evaluating,
+
+    /// Derivations are built or fetched
+    Building,
+
+    /// The new system is switched to
+    Activating,
+}
+
+/// Something that happened during a rebuild
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// A new step started
+    Phase { phase: RebuildPhase },
+
+    /// nix announced what it will build and fetch
+    Plan {
+        derivations: u64,
+        downloads: u64,
+
+        /// The size of the downloads as printed by nix, e.g. "56.70 MiB"
+        download_size: Option<String>,
+    },
+
+    /// A derivation is being built
+    Build {
+        /// The name of the derivation, without the store hash
+        name: String,
+        done: u64,
+        total: u64,
+    },
+
+    /// A path is being fetched from a binary cache
+    Download { name: String, done: u64, total: u64 },
+
+    /// Something went wrong, the rebuild usually fails afterwards
+    Error { message: String },
+
+    /// A warning of nix or nixos-rebuild
+    Warning { message: String },
+
+    /// The rebuild finished
+    Finished { success: bool, duration_secs: u64 },
+}
+
+/// Follows the output of a single rebuild, see [parse_line](Self::parse_line)
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    phase: Option<RebuildPhase>,
+    derivations: u64,
+    downloads: u64,
+    built: u64,
+    fetched: u64,
+
+    /// Whether the lines are the list of the announced paths
+    in_list: bool,
+}
+
+impl ProgressParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a line of nixos-rebuild, stdout or stderr
+    ///
+    /// # Returns
+    /// The events the line causes, none if it is only a log line
+    pub fn parse_line(&mut self, line: &str) -> Vec<ProgressEvent> {
+        let line = line.trim();
+        let mut events = vec![];
+
+        // the paths listed after an announcement are indented store paths
+        if self.in_list && line.starts_with("/nix/store/") {
+            return events;
+        }
+        self.in_list = false;
+
+        if let Some((derivations, downloads, size)) = self.parse_plan(line) {
+            self.enter(RebuildPhase::Building, &mut events);
+            self.derivations += derivations;
+            self.downloads += downloads;
+            self.in_list = true;
+            events.push(ProgressEvent::Plan {
+                derivations: self.derivations,
+                downloads: self.downloads,
+                download_size: size,
+            });
+        } else if let Some(path) = quoted_after(line, "building '") {
+            self.enter(RebuildPhase::Building, &mut events);
+            self.built += 1;
+            events.push(ProgressEvent::Build {
+                name: store_name(path),
+                done: self.built,
+                total: self.derivations.max(self.built),
+            });
+        } else if let Some(path) = quoted_after(line, "copying path '") {
+            self.enter(RebuildPhase::Building, &mut events);
+            self.fetched += 1;
+            events.push(ProgressEvent::Download {
+                name: store_name(path),
+                done: self.fetched,
+                total: self.downloads.max(self.fetched),
+            });
+        } else if line.starts_with("building the system configuration") {
+            self.enter(RebuildPhase::Evaluating, &mut events);
+        } else if line.starts_with("activating the configuration")
+            || line.starts_with("switching to system configuration")
+            || line.starts_with("updating GRUB")
+        {
+            self.enter(RebuildPhase::Activating, &mut events);
+        } else if let Some(message) = line.strip_prefix("error:") {
+            events.push(ProgressEvent::Error {
+                message: message.trim().to_string(),
+            });
+        } else if let Some(message) = line.strip_prefix("warning:") {
+            events.push(ProgressEvent::Warning {
+                message: message.trim().to_string(),
+            });
+        }
+
+        events
+    }
+
+    /// Emits a phase event if the phase changed
+    fn enter(&mut self, phase: RebuildPhase, events: &mut Vec<ProgressEvent>) {
+        if self.phase != Some(phase) {
+            self.phase = Some(phase);
+            events.push(ProgressEvent::Phase { phase });
+        }
+    }
+
+    /// Parses the announcements of nix:
+    ///
+    /// * "these 12 derivations will be built:" or "this derivation will be built:"
+    /// * "these 34 paths will be fetched (56.70 MiB download, 210.3 MiB unpacked):"
+    fn parse_plan(&self, line: &str) -> Option<(u64, u64, Option<String>)> {
+        let count = |s: &str| -> Option<u64> {
+            match s.strip_prefix("these ") {
+                Some(rest) => rest.split_whitespace().next()?.parse().ok(),
+                None => s.starts_with("this ").then_some(1),
+            }
+        };
+
+        if line.contains("will be built") {
+            return Some((count(line)?, 0, None));
+        }
+        if line.contains("will be fetched") {
+            let size = line
+                .split_once('(')
+                .and_then(|(_, rest)| rest.split_once(" download"))
+                .map(|(size, _)| size.to_string());
+            return Some((0, count(line)?, size));
+        }
+
+        None
+    }
+}
+
+/// Returns the text between the prefix and the next `'`
+fn quoted_after<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    line.strip_prefix(prefix)?
+        .split_once('\'')
+        .map(|(quoted, _)| quoted)
+}
+
+/// Strips the store dir and the hash from a store path, e.g. `lnd-0.18.3`
+/// for `/nix/store/<hash>-lnd-0.18.3.drv`
+fn store_name(path: &str) -> String {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    let name = file.split_once('-').map(|(_, n)| n).unwrap_or(file);
+
+    name.strip_suffix(".drv").unwrap_or(name).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rebuild() {
+        let output = "building the system configuration...
+these 2 derivations will be built:
+  /nix/store/aaa-lnd-config.drv
+  /nix/store/bbb-nixos-system-nixblitz-24.11.drv
+these 3 paths will be fetched (56.70 MiB download, 210.30 MiB unpacked):
+  /nix/store/ccc-lnd-0.18.3
+copying path '/nix/store/ccc-lnd-0.18.3' from 'https://cache.nixos.org'...
+building '/nix/store/aaa-lnd-config.drv'...
+warning: the group 'lnd' specified in 'users.users.lnd.group' does not exist
+activating the configuration...
+setting up /etc...
+";
+        let mut parser = ProgressParser::new();
+        let events: Vec<ProgressEvent> =
+            output.lines().flat_map(|l| parser.parse_line(l)).collect();
+
+        assert_eq!(
+            events,
+            vec![
+                ProgressEvent::Phase {
+                    phase: RebuildPhase::Evaluating
+                },
+                ProgressEvent::Phase {
+                    phase: RebuildPhase::Building
+                },
+                ProgressEvent::Plan {
+                    derivations: 2,
+                    downloads: 0,
+                    download_size: None
+                },
+                ProgressEvent::Plan {
+                    derivations: 2,
+                    downloads: 3,
+                    download_size: Some("56.70 MiB".into())
+                },
+                ProgressEvent::Download {
+                    name: "lnd-0.18.3".into(),
+                    done: 1,
+                    total: 3
+                },
+                ProgressEvent::Build {
+                    name: "lnd-config".into(),
+                    done: 1,
+                    total: 2
+                },
+                ProgressEvent::Warning {
+                    message: "the group 'lnd' specified in 'users.users.lnd.group' does not exist"
+                        .into()
+                },
+                ProgressEvent::Phase {
+                    phase: RebuildPhase::Activating
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_derivation_and_error() {
+        let mut parser = ProgressParser::new();
+        assert_eq!(
+            parser.parse_line("this derivation will be built:")[1],
+            ProgressEvent::Plan {
+                derivations: 1,
+                downloads: 0,
+                download_size: None
+            }
+        );
+        assert_eq!(
+            parser.parse_line("error: builder for '/nix/store/x.drv' failed with exit code 1"),
+            vec![ProgressEvent::Error {
+                message: "builder for '/nix/store/x.drv' failed with exit code 1".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let event = ProgressEvent::Phase {
+            phase: RebuildPhase::Building,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"phase","phase":"building"}"#
+        );
+    }
+}
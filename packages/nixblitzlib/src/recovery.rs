@@ -0,0 +1,246 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::{
+    apps::SupportedApps,
+    errors::{ProjectError, TemplatingError},
+    migrations, registry,
+    utils::load_json_file,
+};
+
+/// A project file that is missing or can't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenFile {
+    /// The app the file belongs to
+    pub app: SupportedApps,
+
+    /// Path of the JSON file relative to the work dir
+    pub path: PathBuf,
+
+    /// Why the file was considered broken
+    pub reason: String,
+}
+
+/// Returns the path of the JSON file of the given app, relative to the work dir
+pub fn json_file_name(app: SupportedApps) -> &'static str {
+    registry::registration(app).json_file_name
+}
+
+/// Tries to parse the JSON data of the given app. Files of older releases
+/// are migrated first, they are not broken.
+fn parse_app_json(app: SupportedApps, json: &str) -> Result<(), TemplatingError> {
+    let json =
+        migrations::migrate_json(app, json).change_context(TemplatingError::JsonLoadError)?;
+    (registry::registration(app).parse_json)(&json)
+}
+
+/// Returns every app of the project in the order of [SupportedApps::from_id]
+fn all_apps() -> Vec<SupportedApps> {
+    registry::APPS.iter().map(|r| r.app).collect()
+}
+
+/// Checks the JSON files of all apps and returns the ones that are missing or invalid
+pub fn find_broken_files(work_dir: &Path) -> Vec<BrokenFile> {
+    let mut res = vec![];
+    for app in all_apps() {
+        let path = PathBuf::from(json_file_name(app));
+        let reason = match load_json_file(&work_dir.join(&path)) {
+            Ok(json) => match parse_app_json(app, &json) {
+                Ok(()) => continue,
+                Err(e) => e.current_context().to_string(),
+            },
+            Err(e) => e.current_context().to_string(),
+        };
+
+        res.push(BrokenFile { app, path, reason });
+    }
+
+    res
+}
+
+/// Replaces the broken files with the app defaults.
+///
+/// Every broken file that still exists is moved out of the way first by
+/// appending `.broken-<unix timestamp>` to its name.
+///
+/// # Returns
+///
+/// The paths of the moved files.
+pub fn reset_to_defaults(
+    work_dir: &Path,
+    broken: &[BrokenFile],
+) -> Result<Vec<PathBuf>, ProjectError> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let mut backups = vec![];
+    for file in broken {
+        let path = work_dir.join(&file.path);
+        if path.exists() {
+            let mut backup = path.clone().into_os_string();
+            backup.push(format!(".broken-{}", timestamp));
+            let backup = PathBuf::from(backup);
+            fs::rename(&path, &backup)
+                .change_context(ProjectError::FileOpenError(path.display().to_string()))
+                .attach_printable_lazy(|| format!("Unable to move to {}", backup.display()))?;
+            backups.push(backup);
+        }
+
+        registry::create_default_files(file.app, work_dir, Some(true))?;
+    }
+
+    Ok(backups)
+}
+
+/// Restores the broken files and their rendered nix files from the last git commit
+pub fn restore_from_git(work_dir: &Path, broken: &[BrokenFile]) -> Result<(), ProjectError> {
+    let mut paths = vec![];
+    for file in broken {
+        paths.push(file.path.clone());
+        let nix = file.path.with_extension("nix");
+        let tracked = Command::new("git")
+            .arg("-C")
+            .arg(work_dir)
+            .args(["ls-files", "--error-unmatch"])
+            .arg(&nix)
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false);
+        if tracked {
+            paths.push(nix);
+        }
+    }
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(work_dir)
+        .args(["checkout", "HEAD", "--"])
+        .args(&paths)
+        .output()
+        .change_context(ProjectError::ProjectLoadError)
+        .attach_printable("Unable to run git")?;
+
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::ProjectLoadError).attach_printable(format!(
+                "git checkout failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )),
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the directory holding the project files inside the given backup path.
+///
+/// The path can either point to a single backup or to a directory holding
+/// multiple backups, in which case the most recently modified one is used.
+pub fn find_backup(path: &Path) -> Result<PathBuf, ProjectError> {
+    if path.join(json_file_name(SupportedApps::NixOS)).exists() {
+        return Ok(path.to_path_buf());
+    }
+
+    let entries = fs::read_dir(path)
+        .change_context(ProjectError::FileOpenError(path.display().to_string()))?;
+
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.join(json_file_name(SupportedApps::NixOS)).exists())
+        .max_by_key(|p| {
+            fs::metadata(p)
+                .and_then(|m| m.modified())
+                .unwrap_or(UNIX_EPOCH)
+        })
+        .ok_or_else(|| {
+            Report::new(ProjectError::NoProjectFound)
+                .attach_printable(format!("No backup found in {}", path.display()))
+        })
+}
+
+/// Copies the broken files and their rendered nix files from the given backup
+pub fn restore_from_backup(
+    work_dir: &Path,
+    backup_dir: &Path,
+    broken: &[BrokenFile],
+) -> Result<(), ProjectError> {
+    for file in broken {
+        let nix = file.path.with_extension("nix");
+        for rel in [&file.path, &nix] {
+            let src = backup_dir.join(rel);
+            if !src.exists() {
+                if rel == &file.path {
+                    return Err(Report::new(ProjectError::FileNotFound(
+                        src.display().to_string(),
+                    )));
+                }
+
+                continue;
+            }
+
+            fs::copy(&src, work_dir.join(rel))
+                .change_context(ProjectError::FileOpenError(src.display().to_string()))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{bitcoind, lnd, project::Project, utils::init_default_project};
+
+    use super::*;
+
+    #[test]
+    fn test_reset_broken_file() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+        init_default_project(work_dir, Some(false)).unwrap();
+        assert!(find_broken_files(work_dir).is_empty());
+
+        fs::write(work_dir.join(lnd::JSON_FILE_NAME), "{ not json").unwrap();
+        let broken = find_broken_files(work_dir);
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].app, SupportedApps::LND);
+
+        let backups = reset_to_defaults(work_dir, &broken).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read_to_string(&backups[0]).unwrap(), "{ not json");
+
+        assert!(find_broken_files(work_dir).is_empty());
+        assert!(Project::load(work_dir.to_path_buf()).is_ok());
+    }
+
+    #[test]
+    fn test_restore_from_backup() {
+        let backup_root = tempdir().unwrap();
+        let backup = backup_root.path().join("2024-01-01");
+        init_default_project(&backup, Some(false)).unwrap();
+
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+        init_default_project(work_dir, Some(false)).unwrap();
+        fs::remove_file(work_dir.join(bitcoind::JSON_FILE_NAME)).unwrap();
+
+        let broken = find_broken_files(work_dir);
+        assert_eq!(broken[0].app, SupportedApps::BitcoinCore);
+
+        let found = find_backup(backup_root.path()).unwrap();
+        assert_eq!(found, backup);
+
+        restore_from_backup(work_dir, &found, &broken).unwrap();
+        assert!(find_broken_files(work_dir).is_empty());
+    }
+}
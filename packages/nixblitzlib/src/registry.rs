@@ -0,0 +1,460 @@
+//! Metadata of every app nixblitz knows about.
+//!
+//! Each app registers its title, project files, requirements and how to
+//! create its default files here. Lookups of this kind should go through
+//! the registry instead of matching on [SupportedApps]. Adding an app only
+//! needs a new [SupportedApps] variant and an entry in [APPS], the
+//! [Project](crate::project::Project) loads every registered app.
+
+use std::{cell::RefCell, collections::HashMap, path::Path, rc::Rc};
+
+use error_stack::{Result, ResultExt};
+
+use crate::{
+    app_config::AppConfig,
+    app_option_data::option_data::OptionData,
+    apps::SupportedApps,
+    bitcoind, blitz_api, blitz_webui, bos, channel_backup, charge_lnd, cln, electrs, elements, eps,
+    errors::{ProjectError, TemplatingError},
+    fulcrum, grafana, i2p, lit, lnd, mempool, mining, monitoring,
+    nix_base_config::{self, NixBaseConfig, NixBaseConfigsTemplates},
+    phoenixd, system_backup, tailscale, thunderhub, tor,
+    utils::write_app_files,
+};
+
+/// Creates the default nix and JSON files of an app in the given work dir
+pub type CreateFilesFn = fn(&Path, Option<bool>) -> Result<(), ProjectError>;
+
+/// Checks whether the JSON data of an app can be parsed
+pub type ParseJsonFn = fn(&str) -> Result<(), TemplatingError>;
+
+/// Renders the nix files of an app from its JSON data, keyed by template path
+pub type RenderFn = fn(&str) -> Result<HashMap<String, String>, TemplatingError>;
+
+/// Returns the options of an app from its JSON data
+pub type OptionsFn = fn(&str) -> Result<Vec<OptionData>, TemplatingError>;
+
+/// Returns the options of an app with their default values
+pub type DefaultOptionsFn = fn() -> Vec<OptionData>;
+
+/// Returns the JSON data of the app in a new project
+pub type DefaultJsonFn = fn() -> Result<String, TemplatingError>;
+
+/// Reads the config of an app from its JSON data
+pub type LoadFn = fn(&str) -> Result<Rc<RefCell<dyn AppConfig>>, TemplatingError>;
+
+/// Everything nixblitz needs to know about an app besides its options
+#[derive(Debug)]
+pub struct AppRegistration {
+    /// The app this entry describes
+    pub app: SupportedApps,
+
+    /// Name of the app as shown to the user
+    pub title: &'static str,
+
+    /// Path of the JSON file relative to the work dir
+    pub json_file_name: &'static str,
+
+    /// Path of the main nix template relative to the work dir
+    pub template_file_name: &'static str,
+
+    /// Apps that must be enabled for this app to work
+    pub requires: &'static [SupportedApps],
+
+    /// The systemd units the app runs, its main service first.
+    /// Empty for apps without units of their own.
+    pub units: &'static [&'static str],
+
+    /// Creates the default files of the app
+    pub create_files: CreateFilesFn,
+
+    /// Checks the JSON data of the app
+    pub parse_json: ParseJsonFn,
+
+    /// Renders the nix files of the app
+    pub render: RenderFn,
+
+    /// Reads the options of the app
+    pub options: OptionsFn,
+
+    /// The options of the app in a new project
+    pub default_options: DefaultOptionsFn,
+
+    /// The JSON data of the app in a new project
+    pub default_json: DefaultJsonFn,
+
+    /// Reads the config of the app
+    pub load: LoadFn,
+}
+
+impl AppRegistration {
+    /// Short name of the app for the command line, the name of its JSON
+    /// file without the extension, e.g. `bitcoind`
+    pub fn name(&self) -> &'static str {
+        let file = self.json_file_name.rsplit('/').next().unwrap_or_default();
+        file.strip_suffix(".json").unwrap_or(file)
+    }
+}
+
+/// Registers a service module that follows the usual layout, i.e. it has a
+/// `JSON_FILE_NAME` and `TEMPLATE_FILE_NAME` and its service implements
+/// `Default`, `render`, `to_json_string`, `from_json` and [AppConfig].
+macro_rules! service {
+    (
+        $app:ident,
+        $title:literal,
+        $module:ident::$service:ident,
+        [$($req:ident),*],
+        [$($unit:literal),*]
+    ) => {
+        AppRegistration {
+            app: SupportedApps::$app,
+            title: $title,
+            json_file_name: $module::JSON_FILE_NAME,
+            template_file_name: $module::TEMPLATE_FILE_NAME,
+            requires: &[$(SupportedApps::$req),*],
+            units: &[$($unit),*],
+            create_files: |work_dir, force| {
+                let cfg = $module::$service::default();
+                let rendered_json = cfg
+                    .to_json_string()
+                    .change_context(ProjectError::GenFilesError)?;
+                let rendered_nix = cfg.render().change_context(ProjectError::CreateBaseFiles(
+                    format!("Failed at rendering {} config", $title),
+                ))?;
+
+                write_app_files(
+                    work_dir,
+                    force,
+                    &rendered_nix,
+                    $module::JSON_FILE_NAME,
+                    &rendered_json,
+                )
+            },
+            parse_json: |json| $module::$service::from_json(json).map(|_| ()),
+            render: |json| $module::$service::from_json(json)?.render(),
+            options: |json| Ok($module::$service::from_json(json)?.get_options()),
+            default_options: || $module::$service::default().get_options(),
+            default_json: || $module::$service::default().to_json_string(),
+            load: |json| {
+                let config: Rc<RefCell<dyn AppConfig>> =
+                    Rc::new(RefCell::new($module::$service::from_json(json)?));
+                Ok(config)
+            },
+        }
+    };
+}
+
+/// All apps, in the order of their ids (see [SupportedApps::from_id])
+pub static APPS: [AppRegistration; 24] = [
+    AppRegistration {
+        app: SupportedApps::NixOS,
+        title: "Nix OS",
+        json_file_name: nix_base_config::JSON_FILE_NAME,
+        template_file_name: nix_base_config::TEMPLATE_FILE_NAME,
+        requires: &[],
+        units: &[],
+        create_files: create_nix_base_files,
+        parse_json: |json| NixBaseConfig::from_json(json).map(|_| ()),
+        render: |json| NixBaseConfig::from_json(json)?.render(NixBaseConfigsTemplates::Common),
+        options: |json| Ok(NixBaseConfig::from_json(json)?.get_options()),
+        default_options: || NixBaseConfig::default().get_options(),
+        default_json: || NixBaseConfig::default().to_json_string(),
+        load: |json| {
+            let config: Rc<RefCell<dyn AppConfig>> =
+                Rc::new(RefCell::new(NixBaseConfig::from_json(json)?));
+            Ok(config)
+        },
+    },
+    service!(
+        BitcoinCore,
+        "Bitcoin Core",
+        bitcoind::BitcoinDaemonService,
+        [],
+        ["bitcoind.service"]
+    ),
+    service!(
+        CoreLightning,
+        "Core Lightning",
+        cln::CoreLightningService,
+        [BitcoinCore],
+        ["clightning.service"]
+    ),
+    service!(
+        LND,
+        "LND",
+        lnd::LightningNetworkDaemonService,
+        [BitcoinCore],
+        ["lnd.service"]
+    ),
+    service!(
+        BlitzAPI,
+        "Blitz Api",
+        blitz_api::BlitzApiService,
+        [BitcoinCore],
+        ["blitz-api.service"]
+    ),
+    service!(
+        WebUI,
+        "Web UI",
+        blitz_webui::BlitzWebUiService,
+        [BlitzAPI],
+        ["nginx.service"]
+    ),
+    service!(
+        Electrs,
+        "Electrs",
+        electrs::ElectrsService,
+        [BitcoinCore],
+        ["electrs.service"]
+    ),
+    service!(
+        Mempool,
+        "Mempool",
+        mempool::MempoolService,
+        [BitcoinCore],
+        ["mempool.service"]
+    ),
+    service!(
+        ThunderHub,
+        "ThunderHub",
+        thunderhub::ThunderHubService,
+        [LND],
+        ["thunderhub.service"]
+    ),
+    service!(Tor, "Tor", tor::TorService, [], ["tor.service"]),
+    service!(
+        Fulcrum,
+        "Fulcrum",
+        fulcrum::FulcrumService,
+        [BitcoinCore],
+        ["fulcrum.service"]
+    ),
+    service!(
+        LightningTerminal,
+        "Lightning Terminal",
+        lit::LightningTerminalService,
+        [LND],
+        ["litd.service"]
+    ),
+    service!(
+        ChannelBackup,
+        "Channel Backup",
+        channel_backup::ChannelBackupService,
+        [LND],
+        ["channel-backup.service"]
+    ),
+    service!(
+        Tailscale,
+        "Tailscale",
+        tailscale::TailscaleService,
+        [],
+        ["tailscaled.service"]
+    ),
+    service!(
+        Monitoring,
+        "Monitoring",
+        monitoring::MonitoringService,
+        [],
+        ["prometheus.service", "prometheus-node-exporter.service"]
+    ),
+    service!(
+        Grafana,
+        "Grafana",
+        grafana::GrafanaService,
+        [Monitoring],
+        ["grafana.service"]
+    ),
+    // the unit depends on the backup tool, see BackupTool::unit_name
+    service!(
+        SystemBackup,
+        "System Backup",
+        system_backup::SystemBackupService,
+        [],
+        []
+    ),
+    service!(
+        ElectrumPersonalServer,
+        "Electrum Personal Server",
+        eps::EpsService,
+        [BitcoinCore],
+        ["electrum-personal-server.service"]
+    ),
+    service!(I2P, "I2P", i2p::I2pService, [], ["i2pd.service"]),
+    service!(
+        BalanceOfSatoshis,
+        "Balance of Satoshis",
+        bos::BosService,
+        [LND],
+        ["bos-telegram.service"]
+    ),
+    service!(
+        ChargeLnd,
+        "charge-lnd",
+        charge_lnd::ChargeLndService,
+        [LND],
+        ["charge-lnd.service", "charge-lnd.timer"]
+    ),
+    // talks to the Electrum servers of ACINQ instead of a local bitcoind
+    service!(
+        Phoenixd,
+        "phoenixd",
+        phoenixd::PhoenixdService,
+        [],
+        ["phoenixd.service"]
+    ),
+    service!(
+        Elements,
+        "Elements",
+        elements::ElementsService,
+        [BitcoinCore],
+        ["elementsd.service"]
+    ),
+    service!(
+        Mining,
+        "Solo Mining",
+        mining::MiningService,
+        [BitcoinCore],
+        ["datum-gateway.service"]
+    ),
+];
+
+/// Returns the registration of the given app
+pub fn registration(app: SupportedApps) -> &'static AppRegistration {
+    APPS.iter()
+        .find(|r| r.app == app)
+        .expect("every app must be registered")
+}
+
+/// Returns the registration of the app running the given systemd unit
+///
+/// # Arguments
+/// * `unit` - the unit, the `.service` suffix can be omitted
+pub fn registration_by_unit(unit: &str) -> Option<&'static AppRegistration> {
+    let unit = if unit.contains('.') {
+        unit.to_string()
+    } else {
+        format!("{}.service", unit)
+    };
+
+    APPS.iter().find(|r| r.units.contains(&unit.as_str()))
+}
+
+/// Returns the registration of the app with the given
+/// [name](AppRegistration::name) or title, ignoring the case
+pub fn registration_by_name(name: &str) -> Option<&'static AppRegistration> {
+    APPS.iter()
+        .find(|r| r.name().eq_ignore_ascii_case(name) || r.title.eq_ignore_ascii_case(name))
+}
+
+/// Returns the registration of the app the given template belongs to
+///
+/// # Arguments
+/// * `work_dir` - the project directory
+/// * `path` - absolute path of the template inside the work dir
+pub fn registration_by_template(work_dir: &Path, path: &Path) -> Option<&'static AppRegistration> {
+    APPS.iter()
+        .find(|r| work_dir.join(r.template_file_name) == path)
+}
+
+/// The base config only renders the common config on init, the
+/// platform configs are static files
+fn create_nix_base_files(work_dir: &Path, force: Option<bool>) -> Result<(), ProjectError> {
+    let cfg = NixBaseConfig::default();
+    let rendered_json = cfg
+        .to_json_string()
+        .change_context(ProjectError::GenFilesError)?;
+    let rendered_nix = cfg.render(NixBaseConfigsTemplates::Common).change_context(
+        ProjectError::CreateBaseFiles("Failed at rendering base config".to_string()),
+    )?;
+
+    write_app_files(
+        work_dir,
+        force,
+        &rendered_nix,
+        nix_base_config::JSON_FILE_NAME,
+        &rendered_json,
+    )
+}
+
+/// Creates the default files of the given app
+pub fn create_default_files(
+    app: SupportedApps,
+    work_dir: &Path,
+    force: Option<bool>,
+) -> Result<(), ProjectError> {
+    (registration(app).create_files)(work_dir, force)
+        .attach_printable_lazy(|| format!("Unable to create the files of {}", app))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_ids_match_registry_order() {
+        for (id, reg) in APPS.iter().enumerate() {
+            assert_eq!(SupportedApps::from_id(id), Some(reg.app));
+            assert_eq!(registration(reg.app).title, reg.title);
+            assert_eq!(APPS.iter().filter(|r| r.app == reg.app).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_units() {
+        for reg in APPS.iter() {
+            for unit in reg.units {
+                assert!(unit.ends_with(".service") || unit.ends_with(".timer"));
+                assert_eq!(registration_by_unit(unit).map(|r| r.app), Some(reg.app));
+            }
+        }
+
+        assert_eq!(
+            registration_by_unit("lnd").map(|r| r.app),
+            Some(SupportedApps::LND)
+        );
+        assert_eq!(
+            registration_by_unit("charge-lnd.timer").map(|r| r.app),
+            Some(SupportedApps::ChargeLnd)
+        );
+        assert!(registration_by_unit("sshd").is_none());
+    }
+
+    #[test]
+    fn test_names() {
+        for reg in APPS.iter() {
+            assert_eq!(
+                registration_by_name(reg.name()).map(|r| r.app),
+                Some(reg.app)
+            );
+        }
+
+        assert_eq!(registration(SupportedApps::BitcoinCore).name(), "bitcoind");
+        assert_eq!(
+            registration_by_name("bitcoin core").map(|r| r.app),
+            Some(SupportedApps::BitcoinCore)
+        );
+        assert!(registration_by_name("bitcoin").is_none());
+    }
+
+    #[test]
+    fn test_create_default_files() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        for reg in APPS.iter() {
+            create_default_files(reg.app, work_dir, None).unwrap();
+            let json = fs::read_to_string(work_dir.join(reg.json_file_name)).unwrap();
+            (reg.parse_json)(&json).unwrap();
+            assert_eq!(
+                registration_by_template(work_dir, &work_dir.join(reg.template_file_name))
+                    .map(|r| r.app),
+                Some(reg.app)
+            );
+        }
+    }
+}
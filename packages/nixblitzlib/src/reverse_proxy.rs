@@ -0,0 +1,538 @@
+//! The nginx locations of all apps.
+//!
+//! Apps don't declare nginx locations in their own templates. Each app
+//! provides a [ProxyRoute] and this module renders all of them into a single
+//! virtual host. Two apps claiming the same path are rejected when the option
+//! is changed, see [Project::on_option_changed](crate::project::Project::on_option_changed).
+//!
+//! Routes with HTTPS enabled are served from a second virtual host with TLS,
+//! see [TlsSettings].
+
+use core::fmt;
+use std::{collections::HashMap, fs, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_option_data::option_data::OptionId,
+    apps::SupportedApps,
+    blitz_api::BlitzApiService,
+    blitz_webui::BlitzWebUiService,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    mempool::MempoolService,
+    nix_base_config::{NixBaseConfig, NixBaseConfigOption},
+    nix_format::format_nix,
+    thunderhub::ThunderHubService,
+    utils::{create_file, BASE_TEMPLATE},
+    validation::{normalize_path, ConfigConflict, ConflictKind},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/reverse_proxy.nix.templ";
+
+/// A path on the nginx virtual host an app is served from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyRoute {
+    /// The app the route belongs to
+    pub app: SupportedApps,
+
+    /// The option the path is configured with, used to report conflicts
+    pub option: OptionId,
+
+    /// The location, e.g. "/mempool"
+    pub path: String,
+
+    /// The URL requests are forwarded to. None if the NixOS module of the
+    /// app declares the location itself, the route then only claims the path.
+    pub upstream: Option<String>,
+
+    /// Whether websocket connections must be passed through
+    pub websocket: bool,
+
+    /// Whether the route is served via HTTPS. Only routes with an upstream
+    /// can be moved to the TLS virtual host.
+    pub https: bool,
+}
+
+impl ProxyRoute {
+    pub fn new(
+        app: SupportedApps,
+        option: OptionId,
+        path: &str,
+        upstream: Option<String>,
+        websocket: bool,
+        https: bool,
+    ) -> Self {
+        Self {
+            app,
+            option,
+            path: normalize_path(path),
+            upstream,
+            websocket,
+            https,
+        }
+    }
+
+    fn to_nix_location(&self) -> Option<String> {
+        self.upstream.as_ref().map(|upstream| {
+            format!(
+                "\"{}\" = {{ proxyPass = \"{}\"; proxyWebsockets = {}; }};",
+                self.path, upstream, self.websocket
+            )
+        })
+    }
+}
+
+/// How the routes with HTTPS enabled get their certificate
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TlsMode {
+    /// HTTPS is disabled, all routes are served via plain HTTP
+    #[default]
+    Off,
+
+    /// Let's Encrypt issues the certificate, needs a public domain
+    Acme,
+
+    /// A self-signed certificate is created on the node. Meant for LAN or
+    /// Tor only nodes that can't be reached by Let's Encrypt.
+    SelfSigned,
+
+    /// The user supplies the certificate and its key, e.g. from an internal
+    /// CA. See [tls_cert](crate::tls_cert) for checking them.
+    Custom,
+}
+
+impl TlsMode {
+    pub fn to_string_array() -> [&'static str; 4] {
+        ["off", "acme", "self_signed", "custom"]
+    }
+}
+
+impl fmt::Display for TlsMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode_str = match self {
+            TlsMode::Off => "off",
+            TlsMode::Acme => "acme",
+            TlsMode::SelfSigned => "self_signed",
+            TlsMode::Custom => "custom",
+        };
+        write!(f, "{}", mode_str)
+    }
+}
+
+impl FromStr for TlsMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<TlsMode, ()> {
+        match s {
+            "off" => Ok(TlsMode::Off),
+            "acme" => Ok(TlsMode::Acme),
+            "self_signed" => Ok(TlsMode::SelfSigned),
+            "custom" => Ok(TlsMode::Custom),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The TLS settings of the node, configured in the [NixBaseConfig]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TlsSettings {
+    pub mode: TlsMode,
+
+    /// The public domain of the node, optional for self-signed certificates
+    pub domain: String,
+
+    /// The contact address Let's Encrypt sends expiry warnings to
+    pub acme_email: String,
+
+    /// The path of the custom certificate on the node
+    pub certificate: String,
+
+    /// The path of the key of the custom certificate on the node
+    pub key: String,
+}
+
+impl TlsSettings {
+    /// Name of the virtual host serving the HTTPS routes
+    fn host(&self) -> &str {
+        if self.domain.is_empty() {
+            defaults::reverse_proxy::TLS_HOST
+        } else {
+            &self.domain
+        }
+    }
+
+    /// Makes sure the settings are complete for the selected mode
+    ///
+    /// # Errors
+    ///
+    /// [ProjectError::InvalidValue] if ACME is enabled without a domain or
+    /// email, or a custom certificate without absolute paths
+    pub fn check(&self) -> Result<(), ProjectError> {
+        let mut issues = vec![];
+        match self.mode {
+            TlsMode::Acme => {
+                if self.domain.trim().is_empty() {
+                    issues.push("ACME needs a domain".to_string());
+                }
+                if self.acme_email.trim().is_empty() {
+                    issues.push("ACME needs an email address".to_string());
+                }
+            }
+            TlsMode::Custom => {
+                for (path, what) in [(&self.certificate, "certificate"), (&self.key, "key")] {
+                    if !path.trim().starts_with('/') {
+                        issues.push(format!(
+                            "A custom certificate needs the absolute path of the {} on the node",
+                            what
+                        ));
+                    }
+                }
+            }
+            TlsMode::Off | TlsMode::SelfSigned => (),
+        }
+
+        if issues.is_empty() {
+            return Ok(());
+        }
+
+        Err(Report::new(ProjectError::InvalidValue(
+            NixBaseConfigOption::TlsMode.to_string(),
+            issues,
+        )))
+    }
+}
+
+/// Implemented by apps that can be served via nginx
+pub trait ProxyRouteProvider {
+    /// Returns the route of the app, None if the app is disabled or not
+    /// served via nginx
+    fn proxy_route(&self) -> Option<ProxyRoute>;
+}
+
+/// Collects the routes of the given apps
+pub fn collect_routes(providers: &[&dyn ProxyRouteProvider]) -> Vec<ProxyRoute> {
+    providers.iter().filter_map(|p| p.proxy_route()).collect()
+}
+
+/// Finds the paths claimed by more than one route
+///
+/// # Returns
+///
+/// One conflict per path, empty if all paths are unique.
+pub fn check_routes(routes: &[ProxyRoute]) -> Vec<ConfigConflict> {
+    let mut paths: Vec<(&str, Vec<OptionId>)> = vec![];
+    for route in routes {
+        match paths.iter_mut().find(|(p, _)| *p == route.path) {
+            Some((_, options)) => options.push(route.option.clone()),
+            None => paths.push((&route.path, vec![route.option.clone()])),
+        }
+    }
+
+    paths
+        .into_iter()
+        .filter(|(_, options)| options.len() > 1)
+        .map(|(path, options)| ConfigConflict {
+            kind: ConflictKind::NginxLocation(path.to_string()),
+            options,
+        })
+        .collect()
+}
+
+/// Renders the nginx locations of all routes with an upstream
+pub fn render(
+    routes: &[ProxyRoute],
+    tls: &TlsSettings,
+) -> Result<HashMap<String, String>, TemplatingError> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(no_escape);
+
+    let file = BASE_TEMPLATE
+        .get_file(TEMPLATE_FILE_NAME)
+        .and_then(|f| f.contents_utf8())
+        .ok_or_else(|| {
+            Report::new(TemplatingError::FileNotFound(
+                TEMPLATE_FILE_NAME.to_string(),
+            ))
+            .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template"))
+        })?;
+
+    handlebars
+        .register_template_string(TEMPLATE_FILE_NAME, file)
+        .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+        .change_context(TemplatingError::Register)?;
+
+    let tls_enabled = tls.mode != TlsMode::Off;
+    let (tls_routes, plain_routes): (Vec<&ProxyRoute>, Vec<&ProxyRoute>) =
+        routes.iter().partition(|r| tls_enabled && r.https);
+    let locations: Vec<String> = plain_routes
+        .iter()
+        .filter_map(|r| r.to_nix_location())
+        .collect();
+    let tls_locations: Vec<String> = tls_routes
+        .iter()
+        .filter_map(|r| r.to_nix_location())
+        .collect();
+    let flag = |enabled: bool| if enabled { "true" } else { "" }.to_string();
+
+    let tls_dir = defaults::reverse_proxy::SELF_SIGNED_DIR;
+    let data: HashMap<&str, String> = HashMap::from([
+        (
+            "enable",
+            (!locations.is_empty() || !tls_locations.is_empty()).to_string(),
+        ),
+        ("locations", locations.join("\n")),
+        ("tls", flag(!tls_locations.is_empty())),
+        ("tls_host", tls.host().to_string()),
+        ("tls_locations", tls_locations.join("\n")),
+        (
+            "acme",
+            flag(!tls_locations.is_empty() && tls.mode == TlsMode::Acme),
+        ),
+        ("acme_email", tls.acme_email.clone()),
+        (
+            "self_signed",
+            flag(!tls_locations.is_empty() && tls.mode == TlsMode::SelfSigned),
+        ),
+        ("self_signed_dir", tls_dir.to_string()),
+        (
+            "custom",
+            flag(!tls_locations.is_empty() && tls.mode == TlsMode::Custom),
+        ),
+        ("certificate", tls.certificate.clone()),
+        ("key", tls.key.clone()),
+    ]);
+
+    let res = handlebars
+        .render(TEMPLATE_FILE_NAME, &data)
+        .attach_printable("Failed to render reverse proxy template".to_string())
+        .change_context(TemplatingError::Render)?;
+
+    Ok(HashMap::from([(
+        TEMPLATE_FILE_NAME.to_string(),
+        format_nix(TEMPLATE_FILE_NAME, res),
+    )]))
+}
+
+/// Writes the nginx config of the given routes to the work dir
+///
+/// # Returns
+///
+/// Whether the file changed
+pub fn save(
+    work_dir: &Path,
+    routes: &[ProxyRoute],
+    tls: &TlsSettings,
+) -> Result<bool, ProjectError> {
+    let rendered = render(routes, tls).change_context(ProjectError::CreateBaseFiles(
+        "Failed at rendering reverse proxy config".to_string(),
+    ))?;
+
+    let mut changed = false;
+    for (key, val) in rendered.iter() {
+        let path = work_dir.join(key.replace(".templ", ""));
+        if fs::read_to_string(&path).is_ok_and(|current| current == *val) {
+            continue;
+        }
+
+        create_file(&path, val.as_bytes(), Some(true))?;
+        changed = true;
+    }
+
+    Ok(changed)
+}
+
+/// Creates the nginx config of a new project from the default app configs
+pub fn create_default_file(work_dir: &Path, force: Option<bool>) -> Result<(), ProjectError> {
+    let mempool = MempoolService::default();
+    let thunderhub = ThunderHubService::default();
+    let blitz_api = BlitzApiService::default();
+    let blitz_webui = BlitzWebUiService::default();
+    let routes = collect_routes(&[&mempool, &thunderhub, &blitz_api, &blitz_webui]);
+    let tls = NixBaseConfig::default().tls_settings();
+
+    let rendered = render(&routes, &tls).change_context(ProjectError::CreateBaseFiles(
+        "Failed at rendering reverse proxy config".to_string(),
+    ))?;
+    for (key, val) in rendered.iter() {
+        create_file(
+            &work_dir.join(key.replace(".templ", "")),
+            val.as_bytes(),
+            force,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn route(app: SupportedApps, path: &str, upstream: Option<&str>) -> ProxyRoute {
+        ProxyRoute::new(
+            app,
+            OptionId::new(app, "nginx_location".into()),
+            path,
+            upstream.map(String::from),
+            false,
+            false,
+        )
+    }
+
+    #[test]
+    fn test_check_routes() {
+        let routes = vec![
+            route(SupportedApps::BlitzAPI, "/", None),
+            route(
+                SupportedApps::Mempool,
+                "/mempool/",
+                Some("http://127.0.0.1:1/"),
+            ),
+            route(SupportedApps::WebUI, "/", None),
+        ];
+
+        assert_eq!(
+            check_routes(&routes),
+            vec![ConfigConflict {
+                kind: ConflictKind::NginxLocation("/".into()),
+                options: vec![routes[0].option.clone(), routes[2].option.clone()],
+            }]
+        );
+        assert!(check_routes(&routes[..2]).is_empty());
+    }
+
+    #[test]
+    fn test_render() {
+        let mut routes = vec![
+            route(SupportedApps::BlitzAPI, "/", None),
+            route(
+                SupportedApps::Mempool,
+                "/mempool",
+                Some("http://127.0.0.1:60845/"),
+            ),
+        ];
+        routes[1].websocket = true;
+
+        let rendered = render(&routes, &TlsSettings::default()).unwrap();
+        let nix = &rendered[TEMPLATE_FILE_NAME];
+        assert!(nix.contains("services.nginx = lib.mkIf true"));
+        assert!(nix.contains("\"/mempool\" = {"));
+        assert!(nix.contains("proxyPass = \"http://127.0.0.1:60845/\";"));
+        assert!(nix.contains("proxyWebsockets = true;"));
+        assert!(!nix.contains("\"/\" = {"));
+        assert!(!nix.contains("forceSSL"));
+
+        let rendered = render(&routes[..1], &TlsSettings::default()).unwrap();
+        assert!(rendered[TEMPLATE_FILE_NAME].contains("services.nginx = lib.mkIf false"));
+    }
+
+    #[test]
+    fn test_render_tls() {
+        let mut mempool = route(
+            SupportedApps::Mempool,
+            "/mempool",
+            Some("http://127.0.0.1:60845/"),
+        );
+        mempool.https = true;
+        let thunderhub = route(
+            SupportedApps::ThunderHub,
+            "/thunderhub",
+            Some("http://127.0.0.1:3000"),
+        );
+        let routes = vec![mempool, thunderhub];
+
+        let acme = TlsSettings {
+            mode: TlsMode::Acme,
+            domain: "node.example.com".into(),
+            acme_email: "admin@example.com".into(),
+            ..Default::default()
+        };
+        let rendered = render(&routes, &acme).unwrap();
+        let nix = &rendered[TEMPLATE_FILE_NAME];
+        assert!(nix.contains("virtualHosts.\"node.example.com\""));
+        assert!(nix.contains("enableACME = true;"));
+        assert!(nix.contains("defaults.email = \"admin@example.com\";"));
+        // only the mempool is moved to the TLS host
+        let (plain, tls) = nix.split_once("virtualHosts.\"node.example.com\"").unwrap();
+        assert!(plain.contains("\"/thunderhub\""));
+        assert!(tls.contains("\"/mempool\""));
+        assert!(!tls.contains("\"/thunderhub\""));
+
+        let self_signed = TlsSettings {
+            mode: TlsMode::SelfSigned,
+            ..Default::default()
+        };
+        let rendered = render(&routes, &self_signed).unwrap();
+        let nix = &rendered[TEMPLATE_FILE_NAME];
+        assert!(nix.contains(&format!(
+            "virtualHosts.\"{}\"",
+            defaults::reverse_proxy::TLS_HOST
+        )));
+        assert!(nix.contains("sslCertificateKey"));
+        assert!(!nix.contains("security.acme"));
+
+        let custom = TlsSettings {
+            mode: TlsMode::Custom,
+            certificate: "/etc/ssl/node/fullchain.pem".into(),
+            key: "/etc/ssl/node/key.pem".into(),
+            ..Default::default()
+        };
+        let rendered = render(&routes, &custom).unwrap();
+        let nix = &rendered[TEMPLATE_FILE_NAME];
+        assert!(nix.contains("sslCertificate = \"/etc/ssl/node/fullchain.pem\";"));
+        assert!(nix.contains("sslCertificateKey = \"/etc/ssl/node/key.pem\";"));
+        assert!(!nix.contains("nixblitz-self-signed-cert"));
+    }
+
+    #[test]
+    fn test_tls_settings_check() {
+        let mut tls = TlsSettings {
+            mode: TlsMode::Acme,
+            ..Default::default()
+        };
+        let err = tls.check().unwrap_err();
+        match err.current_context() {
+            ProjectError::InvalidValue(_, issues) => assert_eq!(issues.len(), 2),
+            e => panic!("unexpected error {:?}", e),
+        }
+
+        tls.domain = "node.example.com".into();
+        tls.acme_email = "admin@example.com".into();
+        assert!(tls.check().is_ok());
+        assert!(TlsSettings::default().check().is_ok());
+
+        let mut tls = TlsSettings {
+            mode: TlsMode::Custom,
+            certificate: "/etc/ssl/node/fullchain.pem".into(),
+            key: "key.pem".into(),
+            ..Default::default()
+        };
+        match tls.check().unwrap_err().current_context() {
+            ProjectError::InvalidValue(_, issues) => assert_eq!(issues.len(), 1),
+            e => panic!("unexpected error {:?}", e),
+        }
+        tls.key = "/etc/ssl/node/key.pem".into();
+        assert!(tls.check().is_ok());
+    }
+
+    #[test]
+    fn test_save() {
+        let temp_dir = tempdir().unwrap();
+        let routes = vec![route(
+            SupportedApps::ThunderHub,
+            "/thunderhub",
+            Some("http://127.0.0.1:3000"),
+        )];
+
+        let tls = TlsSettings::default();
+
+        assert!(save(temp_dir.path(), &routes, &tls).unwrap());
+        assert!(!save(temp_dir.path(), &routes, &tls).unwrap());
+        assert!(save(temp_dir.path(), &[], &tls).unwrap());
+    }
+}
@@ -0,0 +1,172 @@
+//! JSON Schemas of the app options.
+//!
+//! The schemas describe the values as printed by `nixblitz get --format json`,
+//! see [json_value], so tools and other frontends can validate them without
+//! knowing the option types of nixblitz.
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    app_option_data::{
+        amount_data::SATS_PER_BTC,
+        option_data::{GetOptionId, OptionData},
+    },
+    number_value::NumberValue,
+    option_values::json_value,
+    registry::{AppRegistration, APPS},
+    strings::OPTION_TITLES,
+};
+
+/// The JSON Schema dialect of all schemas
+pub const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Returns the schema of a single option, titled and with its current value
+/// as default
+pub fn option_schema(option: &OptionData) -> Value {
+    let mut schema = match option {
+        OptionData::Bool(_) => json!({ "type": "boolean" }),
+        OptionData::NumberEdit(o) => {
+            let kind = match o.value() {
+                NumberValue::Float(_) => "number",
+                _ => "integer",
+            };
+            json!({
+                "type": [kind, "null"],
+                "minimum": o.range_min(),
+                "maximum": o.range_max(),
+            })
+        }
+        OptionData::Port(_) => json!({
+            "type": ["integer", "null"],
+            "minimum": 0,
+            "maximum": u16::MAX,
+        }),
+        OptionData::NetAddress(_) => json!({
+            "anyOf": [
+                { "type": "string", "format": "ipv4" },
+                { "type": "string", "format": "ipv6" },
+                { "type": "null" },
+            ],
+        }),
+        OptionData::NetAddressList(_) => json!({
+            "type": "array",
+            "items": {
+                "type": "string",
+                "description": "An IP address, optionally with a prefix length like 10.0.0.0/8",
+            },
+        }),
+        OptionData::StringList(o) if !o.options().is_empty() => json!({
+            "type": "string",
+            "enum": o.options().iter().map(|i| i.value.clone()).collect::<Vec<_>>(),
+        }),
+        OptionData::StringList(_) | OptionData::TextEdit(_) => json!({ "type": "string" }),
+        OptionData::PasswordEdit(o) => json!({
+            "type": "string",
+            "writeOnly": true,
+            "minLength": o.min_length(),
+        }),
+        OptionData::ManualStringList(o) => {
+            let mut schema = json!({
+                "type": "array",
+                "items": { "type": "string", "minLength": 1 },
+            });
+            if o.max_entries() > 0 {
+                schema["maxItems"] = Value::from(o.max_entries());
+            }
+            schema
+        }
+        OptionData::Code(o) => json!({
+            "type": "string",
+            "description": format!("Code in {}", o.language()),
+        }),
+        OptionData::Percent(_) => json!({
+            "type": "integer",
+            "minimum": 0,
+            "maximum": 100,
+        }),
+        OptionData::Amount(o) => json!({
+            "type": "integer",
+            "minimum": o.min_sats(),
+            "maximum": o.max_sats(),
+            "description": format!("Satoshis, {} per BTC", SATS_PER_BTC),
+        }),
+    };
+
+    if let Some(title) = OPTION_TITLES.get(option.id()) {
+        schema["title"] = Value::from(*title);
+    }
+    // Passwords are masked, a default would only be the placeholder
+    if !matches!(option, OptionData::PasswordEdit(_)) {
+        schema["default"] = json_value(option);
+    }
+
+    schema
+}
+
+/// Returns the schema of all options of an app, with their defaults as of a
+/// new project
+pub fn app_schema(reg: &AppRegistration) -> Value {
+    let properties: Map<String, Value> = (reg.default_options)()
+        .iter()
+        .map(|o| (o.id().option.clone(), option_schema(o)))
+        .collect();
+
+    json!({
+        "$schema": SCHEMA_DIALECT,
+        "$id": format!("nixblitz:{}", reg.name()),
+        "title": reg.title,
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    })
+}
+
+/// Returns the schemas of all apps, keyed by the app [name](AppRegistration::name)
+pub fn all_schemas() -> Value {
+    let apps: Map<String, Value> = APPS
+        .iter()
+        .map(|r| (r.name().to_string(), app_schema(r)))
+        .collect();
+
+    json!({
+        "$schema": SCHEMA_DIALECT,
+        "title": "nixblitz",
+        "type": "object",
+        "properties": apps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{apps::SupportedApps, registry::registration};
+
+    use super::*;
+
+    #[test]
+    fn test_app_schema() {
+        let schema = app_schema(registration(SupportedApps::LND));
+        assert_eq!(schema["$schema"], SCHEMA_DIALECT);
+        assert_eq!(schema["title"], "LND");
+
+        let enable = &schema["properties"]["enable"];
+        assert_eq!(enable["type"], "boolean");
+        assert_eq!(enable["default"], false);
+
+        let port = &schema["properties"]["port"];
+        assert_eq!(port["maximum"], 65535);
+        assert_eq!(port["default"], 9735);
+    }
+
+    #[test]
+    fn test_all_schemas() {
+        let schema = all_schemas();
+        let apps = schema["properties"].as_object().unwrap();
+        assert_eq!(apps.len(), APPS.len());
+
+        for app in apps.values() {
+            for option in app["properties"].as_object().unwrap().values() {
+                assert!(option.get("type").is_some() || option.get("anyOf").is_some());
+            }
+        }
+    }
+}
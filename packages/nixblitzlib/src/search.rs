@@ -0,0 +1,206 @@
+//! Finds options across all apps by a fuzzy query.
+//!
+//! The query is matched against the `app.option` identifier, the title from
+//! [OPTION_TITLES] and the current value of each option. The characters of
+//! the query must appear in that order, e.g. `btcrpcp` finds
+//! `bitcoind.rpc_port`. Whole words and consecutive characters rank higher.
+
+use crate::{
+    app_option_data::option_data::{GetOptionId, OptionData, OptionId},
+    option_values::display_value,
+    project::Project,
+    registry::{self, registration},
+    strings::OPTION_TITLES,
+};
+
+/// Bonus for a character that directly follows the previous match
+const CONSECUTIVE_BONUS: u32 = 4;
+
+/// Bonus for a character at the start of a word
+const WORD_START_BONUS: u32 = 3;
+
+/// Bonus if the query appears as is, scaled by its length
+const SUBSTRING_BONUS: u32 = 2;
+
+/// An option as it is searched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchEntry {
+    pub id: OptionId,
+
+    /// The option as `app.option`, e.g. `bitcoind.rpc_port`
+    pub identifier: String,
+
+    pub title: Option<&'static str>,
+
+    /// The current value as shown to the user, passwords are masked
+    pub value: String,
+}
+
+impl SearchEntry {
+    pub fn new(option: &OptionData) -> Self {
+        let id = option.id().clone();
+        Self {
+            identifier: format!("{}.{}", registration(id.app).name(), id.option),
+            title: OPTION_TITLES.get(&id).copied(),
+            value: display_value(option),
+            id,
+        }
+    }
+
+    /// The first line of the value, cut to `max_len` characters to fit a
+    /// list of options
+    pub fn short_value(&self, max_len: usize) -> String {
+        let line = self.value.lines().next().unwrap_or_default();
+        let cut = self.value.lines().count() > 1 || line.chars().count() > max_len;
+        if !cut {
+            return line.to_string();
+        }
+
+        let line: String = line.chars().take(max_len.saturating_sub(1)).collect();
+        format!("{}…", line)
+    }
+
+    /// How well the entry matches the query, None if it doesn't. Matches
+    /// of the value weigh half as much as those of the name.
+    fn score(&self, query: &str) -> Option<u32> {
+        let name = [Some(self.identifier.as_str()), self.title]
+            .into_iter()
+            .flatten()
+            .filter_map(|text| fuzzy_score(query, text))
+            .max();
+        let value = fuzzy_score(query, &self.value).map(|s| s / 2);
+
+        name.max(value)
+    }
+}
+
+/// The options of all apps, in the order of the registry
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    entries: Vec<SearchEntry>,
+}
+
+impl SearchIndex {
+    pub fn new(project: &Project) -> Self {
+        Self::from_options(
+            registry::APPS
+                .iter()
+                .flat_map(|r| project.get_options_for(r.app)),
+        )
+    }
+
+    pub fn from_options(options: impl IntoIterator<Item = OptionData>) -> Self {
+        Self {
+            entries: options.into_iter().map(|o| SearchEntry::new(&o)).collect(),
+        }
+    }
+
+    pub fn entries(&self) -> &[SearchEntry] {
+        &self.entries
+    }
+
+    /// Returns the entries matching the query, the best match first.
+    /// An empty query matches all entries.
+    pub fn search(&self, query: &str) -> Vec<&SearchEntry> {
+        let query: String = query.split_whitespace().collect::<String>().to_lowercase();
+        if query.is_empty() {
+            return self.entries.iter().collect();
+        }
+
+        let mut matches: Vec<(u32, &SearchEntry)> = self
+            .entries
+            .iter()
+            .filter_map(|e| e.score(&query).map(|s| (s, e)))
+            .collect();
+        // stable, equal scores keep the order of the registry
+        matches.sort_by(|a, b| b.0.cmp(&a.0));
+
+        matches.into_iter().map(|(_, e)| e).collect()
+    }
+}
+
+/// Scores how well the text matches the query, None if the characters of
+/// the query don't appear in the text in that order
+///
+/// # Arguments
+/// * `query` - lower case, without whitespace
+fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last: Option<usize> = None;
+    for q in query.chars() {
+        let found = pos + text[pos..].iter().position(|c| *c == q)?;
+        score += 1;
+        if last.is_some_and(|l| l + 1 == found) {
+            score += CONSECUTIVE_BONUS;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        last = Some(found);
+        pos = found + 1;
+    }
+
+    let text: String = text.into_iter().collect();
+    if text.contains(query) {
+        score += SUBSTRING_BONUS * query.chars().count() as u32;
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::option_data::ToOptionId, bitcoind::BitcoindConfigOption,
+        lnd::LndConfigOption, utils::init_default_project,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("rpcport", "bitcoind.rpc_port").is_some());
+        assert!(fuzzy_score("portrpc", "bitcoind.rpc_port").is_none());
+        assert!(fuzzy_score("port", "Port").unwrap() > fuzzy_score("port", "P o r t").unwrap());
+        assert!(fuzzy_score("", "anything").is_some());
+    }
+
+    #[test]
+    fn test_short_value() {
+        let mut entry = SearchEntry {
+            id: LndConfigOption::Port.to_option_id(),
+            identifier: "lnd.port".into(),
+            title: None,
+            value: "9735".into(),
+        };
+        assert_eq!(entry.short_value(10), "9735");
+        assert_eq!(entry.short_value(3), "97…");
+        entry.value = "a = 1;\nb = 2;".into();
+        assert_eq!(entry.short_value(10), "a = 1;…");
+    }
+
+    #[test]
+    fn test_search() {
+        let temp_dir = tempdir().unwrap();
+        init_default_project(temp_dir.path(), Some(false)).unwrap();
+        let project = Project::load(temp_dir.path().to_path_buf()).unwrap();
+        let index = SearchIndex::new(&project);
+
+        assert_eq!(index.search("").len(), index.entries().len());
+        assert!(index.search("zzzzqqqq").is_empty());
+
+        let res = index.search("bitcoind.rpc_port");
+        assert_eq!(res[0].id, BitcoindConfigOption::RpcPort.to_option_id());
+        assert_eq!(res[0].identifier, "bitcoind.rpc_port");
+
+        // matches the value 9735
+        let res = index.search("9735");
+        assert!(res
+            .iter()
+            .any(|e| e.id == LndConfigOption::Port.to_option_id()));
+    }
+}
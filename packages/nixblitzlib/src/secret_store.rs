@@ -0,0 +1,468 @@
+//! Encrypted storage for the secrets the services need in plain text.
+//!
+//! By default passwords like the admin password of Grafana are kept in the
+//! JSON files of the project and end up in the nix store. With sops-nix or
+//! agenix as [SecretBackend] they are encrypted with age for the
+//! [recipients](SecretSettings::recipients) into [SECRETS_DIR] instead. The
+//! option then only keeps a [SecretRef] and the service reads the secret from
+//! the file it is decrypted to on activation.
+//!
+//! All stored secrets are declared in a single nix file, see [render].
+
+use core::fmt;
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{ProjectError, TemplatingError},
+    nix_base_config::NixBaseConfigOption,
+    nix_format::format_nix,
+    utils::{create_file, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/secrets.nix.templ";
+
+/// The encrypted secrets, relative to the work dir
+pub const SECRETS_DIR: &str = "src/secrets";
+
+/// Where the secrets of the apps are stored
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SecretBackend {
+    /// In the JSON and nix files of the project, readable from the nix store
+    #[default]
+    Plaintext,
+
+    /// Encrypted with sops, decrypted by sops-nix to `/run/secrets`
+    Sops,
+
+    /// Encrypted with age, decrypted by agenix to `/run/agenix`
+    Agenix,
+}
+
+impl SecretBackend {
+    pub fn to_string_array() -> [&'static str; 3] {
+        ["plaintext", "sops", "agenix"]
+    }
+
+    /// The path on the node a secret of the backend is decrypted to
+    ///
+    /// # Returns
+    /// None for [SecretBackend::Plaintext]
+    pub fn decrypted_path(&self, name: &str) -> Option<String> {
+        match self {
+            SecretBackend::Plaintext => None,
+            SecretBackend::Sops => Some(format!("/run/secrets/{}", name)),
+            SecretBackend::Agenix => Some(format!("/run/agenix/{}", name)),
+        }
+    }
+
+    /// The NixOS option the secrets of the backend are declared in
+    fn nix_option(&self) -> Option<&'static str> {
+        match self {
+            SecretBackend::Plaintext => None,
+            SecretBackend::Sops => Some("sops.secrets"),
+            SecretBackend::Agenix => Some("age.secrets"),
+        }
+    }
+}
+
+impl fmt::Display for SecretBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let backend_str = match self {
+            SecretBackend::Plaintext => "plaintext",
+            SecretBackend::Sops => "sops",
+            SecretBackend::Agenix => "agenix",
+        };
+        write!(f, "{}", backend_str)
+    }
+}
+
+impl FromStr for SecretBackend {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<SecretBackend, ()> {
+        match s {
+            "plaintext" => Ok(SecretBackend::Plaintext),
+            "sops" => Ok(SecretBackend::Sops),
+            "agenix" => Ok(SecretBackend::Agenix),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The secret settings of the node, configured in the
+/// [NixBaseConfig](crate::nix_base_config::NixBaseConfig)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SecretSettings {
+    pub backend: SecretBackend,
+
+    /// The age public keys the secrets are encrypted for. The node must hold
+    /// the private key of one of them, usually derived from its SSH host key.
+    pub recipients: Vec<String>,
+}
+
+impl SecretSettings {
+    /// Makes sure secrets can be encrypted with the settings
+    ///
+    /// # Errors
+    ///
+    /// [ProjectError::InvalidValue] if an encrypting backend has no valid recipients
+    pub fn check(&self) -> Result<(), ProjectError> {
+        if self.backend == SecretBackend::Plaintext {
+            return Ok(());
+        }
+
+        let mut issues: Vec<String> = self
+            .recipients
+            .iter()
+            .filter(|r| !r.starts_with("age1"))
+            .map(|r| format!("\"{}\" is not an age public key", r))
+            .collect();
+        if self.recipients.is_empty() {
+            issues.push(format!(
+                "{} needs at least one recipient, e.g. the output of \
+                 `ssh-to-age < /etc/ssh/ssh_host_ed25519_key.pub` on the node",
+                self.backend
+            ));
+        }
+
+        if issues.is_empty() {
+            return Ok(());
+        }
+
+        Err(Report::new(ProjectError::InvalidValue(
+            NixBaseConfigOption::SecretRecipients.to_string(),
+            issues,
+        )))
+    }
+}
+
+/// A secret stored encrypted in the [SECRETS_DIR]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretRef {
+    pub backend: SecretBackend,
+
+    /// Unique name of the secret, e.g. "grafana-admin_password"
+    pub name: String,
+
+    /// The user the decrypted file belongs to
+    pub owner: String,
+}
+
+impl SecretRef {
+    /// Path of the encrypted file relative to the work dir
+    pub fn file(&self) -> String {
+        let ext = match self.backend {
+            SecretBackend::Agenix => "age",
+            _ => "bin",
+        };
+        format!("{}/{}.{}", SECRETS_DIR, self.name, ext)
+    }
+
+    /// Nix expression of the path the secret is decrypted to
+    ///
+    /// # Returns
+    /// None for [SecretBackend::Plaintext]
+    pub fn nix_path(&self) -> Option<String> {
+        self.backend
+            .nix_option()
+            .map(|o| format!("config.{}.\"{}\".path", o, self.name))
+    }
+
+    /// Declares the secret for sops-nix or agenix, relative to the rendered
+    /// [TEMPLATE_FILE_NAME]
+    fn to_nix_declaration(&self) -> Option<String> {
+        let option = self.backend.nix_option()?;
+        let file = format!("../{}", self.file().trim_start_matches("src/"));
+        let source = match self.backend {
+            SecretBackend::Sops => format!("sopsFile = {};\n  format = \"binary\";", file),
+            _ => format!("file = {};", file),
+        };
+
+        Some(format!(
+            "{}.\"{}\" = {{\n  {}\n  owner = \"{}\";\n}};",
+            option, self.name, source, self.owner
+        ))
+    }
+}
+
+/// Encrypts a secret for the recipients of the settings and writes it to
+/// the [SECRETS_DIR]. Runs `sops` or `age`, both must be installed.
+///
+/// # Arguments
+///
+/// * `name` - Unique name of the secret, used as file name
+/// * `owner` - The user the service reading the secret runs as
+///
+/// # Errors
+///
+/// * [ProjectError::InvalidValue] if the settings are incomplete, see [SecretSettings::check]
+/// * [ProjectError::SecretStoreError] if the secret can't be encrypted
+pub fn store(
+    work_dir: &Path,
+    settings: &SecretSettings,
+    name: &str,
+    owner: &str,
+    value: &str,
+) -> Result<SecretRef, ProjectError> {
+    settings.check()?;
+    if settings.backend == SecretBackend::Plaintext {
+        return Err(Report::new(ProjectError::SecretStoreError)
+            .attach_printable("Plain text secrets are stored with their option"));
+    }
+
+    let secret = SecretRef {
+        backend: settings.backend,
+        name: name.to_string(),
+        owner: owner.to_string(),
+    };
+    let path = work_dir.join(secret.file());
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .change_context(ProjectError::SecretStoreError)
+            .attach_printable_lazy(|| format!("Unable to create {}", parent.display()))?;
+    }
+
+    let mut cmd = match settings.backend {
+        SecretBackend::Sops => {
+            let mut cmd = Command::new("sops");
+            cmd.args([
+                "--encrypt",
+                "--input-type",
+                "binary",
+                "--output-type",
+                "binary",
+            ])
+            .args(["--age", &settings.recipients.join(",")])
+            .arg("--output")
+            .arg(&path)
+            .arg("/dev/stdin");
+            cmd
+        }
+        _ => {
+            let mut cmd = Command::new("age");
+            cmd.arg("--encrypt");
+            for r in &settings.recipients {
+                cmd.args(["--recipient", r]);
+            }
+            cmd.arg("--output").arg(&path);
+            cmd
+        }
+    };
+    let program = cmd.get_program().to_string_lossy().to_string();
+
+    // the plain text only ever goes through the pipe
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .change_context(ProjectError::SecretStoreError)
+        .attach_printable_lazy(|| format!("Unable to run {}, is it installed?", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(value.as_bytes())
+            .change_context(ProjectError::SecretStoreError)
+            .attach_printable_lazy(|| format!("Unable to pass the secret to {}", program))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .change_context(ProjectError::SecretStoreError)?;
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::SecretStoreError).attach_printable(format!(
+                "{} failed: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        );
+    }
+
+    Ok(secret)
+}
+
+/// Renders the declarations of all given secrets
+pub fn render(secrets: &[SecretRef]) -> Result<HashMap<String, String>, TemplatingError> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(no_escape);
+
+    let file = BASE_TEMPLATE
+        .get_file(TEMPLATE_FILE_NAME)
+        .and_then(|f| f.contents_utf8())
+        .ok_or_else(|| {
+            Report::new(TemplatingError::FileNotFound(
+                TEMPLATE_FILE_NAME.to_string(),
+            ))
+            .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template"))
+        })?;
+
+    handlebars
+        .register_template_string(TEMPLATE_FILE_NAME, file)
+        .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+        .change_context(TemplatingError::Register)?;
+
+    let mut declarations: Vec<String> = secrets
+        .iter()
+        .filter_map(|s| s.to_nix_declaration())
+        .collect();
+    declarations.sort();
+    let data: HashMap<&str, String> = HashMap::from([("secrets", declarations.join("\n"))]);
+
+    let res = handlebars
+        .render(TEMPLATE_FILE_NAME, &data)
+        .attach_printable("Failed to render secrets template".to_string())
+        .change_context(TemplatingError::Render)?;
+
+    Ok(HashMap::from([(
+        TEMPLATE_FILE_NAME.to_string(),
+        format_nix(TEMPLATE_FILE_NAME, res),
+    )]))
+}
+
+/// Writes the declarations of the given secrets to the work dir
+///
+/// # Returns
+///
+/// Whether the file changed
+pub fn save(work_dir: &Path, secrets: &[SecretRef]) -> Result<bool, ProjectError> {
+    let rendered = render(secrets).change_context(ProjectError::CreateBaseFiles(
+        "Failed at rendering secrets config".to_string(),
+    ))?;
+
+    let mut changed = false;
+    for (key, val) in rendered.iter() {
+        let path = work_dir.join(key.replace(".templ", ""));
+        if fs::read_to_string(&path).is_ok_and(|current| current == *val) {
+            continue;
+        }
+
+        create_file(&path, val.as_bytes(), Some(true))?;
+        changed = true;
+    }
+
+    Ok(changed)
+}
+
+/// Creates the secrets config of a new project, which has no secrets yet
+pub fn create_default_file(work_dir: &Path, force: Option<bool>) -> Result<(), ProjectError> {
+    let rendered = render(&[]).change_context(ProjectError::CreateBaseFiles(
+        "Failed at rendering secrets config".to_string(),
+    ))?;
+    for (key, val) in rendered.iter() {
+        create_file(
+            &work_dir.join(key.replace(".templ", "")),
+            val.as_bytes(),
+            force,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn secret(backend: SecretBackend) -> SecretRef {
+        SecretRef {
+            backend,
+            name: "grafana-admin_password".into(),
+            owner: "grafana".into(),
+        }
+    }
+
+    #[test]
+    fn test_secret_ref() {
+        let sops = secret(SecretBackend::Sops);
+        assert_eq!(sops.file(), "src/secrets/grafana-admin_password.bin");
+        assert_eq!(
+            sops.nix_path().unwrap(),
+            "config.sops.secrets.\"grafana-admin_password\".path"
+        );
+
+        let age = secret(SecretBackend::Agenix);
+        assert_eq!(age.file(), "src/secrets/grafana-admin_password.age");
+        assert_eq!(
+            age.nix_path().unwrap(),
+            "config.age.secrets.\"grafana-admin_password\".path"
+        );
+
+        assert!(secret(SecretBackend::Plaintext).nix_path().is_none());
+    }
+
+    #[test]
+    fn test_check() {
+        let mut settings = SecretSettings::default();
+        assert!(settings.check().is_ok());
+
+        settings.backend = SecretBackend::Agenix;
+        assert!(settings.check().is_err());
+
+        settings.recipients = vec!["ssh-ed25519 AAAA".into()];
+        assert!(settings.check().is_err());
+
+        settings.recipients =
+            vec!["age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p".into()];
+        assert!(settings.check().is_ok());
+    }
+
+    #[test]
+    fn test_store_needs_recipients() {
+        let temp_dir = tempdir().unwrap();
+        let settings = SecretSettings {
+            backend: SecretBackend::Sops,
+            recipients: vec![],
+        };
+
+        let res = store(temp_dir.path(), &settings, "test", "nobody", "secret");
+        assert!(res.is_err());
+        assert!(!temp_dir.path().join(SECRETS_DIR).exists());
+    }
+
+    #[test]
+    fn test_render() {
+        let rendered = render(&[
+            secret(SecretBackend::Sops),
+            SecretRef {
+                backend: SecretBackend::Agenix,
+                name: "lit-ui_password".into(),
+                owner: "lnd".into(),
+            },
+        ])
+        .unwrap();
+        let data = &rendered[TEMPLATE_FILE_NAME];
+        assert!(data.contains("sops.secrets.\"grafana-admin_password\""));
+        assert!(data.contains("sopsFile = ../secrets/grafana-admin_password.bin;"));
+        assert!(data.contains("format = \"binary\";"));
+        assert!(data.contains("age.secrets.\"lit-ui_password\""));
+        assert!(data.contains("file = ../secrets/lit-ui_password.age;"));
+        assert!(data.contains("owner = \"lnd\";"));
+
+        let empty = &render(&[]).unwrap()[TEMPLATE_FILE_NAME];
+        assert!(!empty.contains("secrets."));
+    }
+
+    #[test]
+    fn test_save() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let secrets = [secret(SecretBackend::Sops)];
+        assert!(save(work_dir, &secrets).unwrap());
+        assert!(!save(work_dir, &secrets).unwrap());
+        assert!(save(work_dir, &[]).unwrap());
+    }
+}
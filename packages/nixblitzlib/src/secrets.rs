@@ -0,0 +1,173 @@
+//! Checks the secrets nix-bitcoin generates for the enabled apps.
+//!
+//! The nix files enable `nix-bitcoin.generateSecrets`, so the node creates the
+//! missing passwords, keys and certificates in the `setup-secrets` unit when a
+//! configuration is activated. If that fails, the services only report that they
+//! can't read a file. The checks here name the missing secrets instead. They
+//! must run on the node as root, the secrets dir is only readable by root.
+
+use core::fmt;
+use std::{io::ErrorKind, path::Path, process::Command};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::{activation, apps::SupportedApps, errors::ProjectError};
+
+/// The systemd unit of nix-bitcoin that generates the secrets
+pub const SETUP_UNIT: &str = "setup-secrets.service";
+
+/// The secret files nix-bitcoin creates for an app
+pub fn expected_secrets(app: SupportedApps) -> &'static [&'static str] {
+    match app {
+        SupportedApps::BitcoinCore => &[
+            "bitcoin-rpcpassword-privileged",
+            "bitcoin-rpcpassword-public",
+            "bitcoin-HMAC-privileged",
+            "bitcoin-HMAC-public",
+        ],
+        SupportedApps::LND => &["lnd-wallet-password", "lnd-key", "lnd-cert"],
+        SupportedApps::BlitzAPI => &["bitcoin-rpcpassword-blitz_api", "bitcoin-HMAC-blitz_api"],
+        SupportedApps::Mempool => &["mempool-db-password"],
+        SupportedApps::Mining => &["bitcoin-rpcpassword-datum", "bitcoin-HMAC-datum"],
+        _ => &[],
+    }
+}
+
+/// A secret an enabled app needs but which doesn't exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingSecret {
+    pub app: SupportedApps,
+
+    /// The name of the file in the secrets dir
+    pub file: String,
+}
+
+impl fmt::Display for MissingSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is missing the secret {}", self.app, self.file)
+    }
+}
+
+/// Looks for the secrets of the given apps in the secrets dir
+///
+/// # Errors
+/// [ProjectError::SecretsError] if the dir can't be read, e.g. when not
+/// running as root
+pub fn missing_secrets(
+    enabled: &[SupportedApps],
+    dir: &Path,
+) -> Result<Vec<MissingSecret>, ProjectError> {
+    let mut res = vec![];
+    for app in enabled {
+        for file in expected_secrets(*app) {
+            // exists() is false for files we lack the permissions for
+            let exists = dir.join(file).try_exists().or_else(|e| match e.kind() {
+                ErrorKind::NotFound => Ok(false),
+                _ => Err(Report::new(e)
+                    .change_context(ProjectError::SecretsError)
+                    .attach_printable(format!("Unable to read {}, are you root?", dir.display()))),
+            })?;
+
+            if !exists {
+                res.push(MissingSecret {
+                    app: *app,
+                    file: file.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+/// Runs the secrets generation of nix-bitcoin and checks its result
+///
+/// Existing secrets are kept, only the missing ones are generated.
+///
+/// # Errors
+/// * [ProjectError::RebuildInProgress] if a nixos-rebuild is running
+/// * [ProjectError::SecretsError] if the unit fails, with the end of its log attached
+/// * [ProjectError::MissingSecrets] if secrets are still missing afterwards
+pub fn generate(enabled: &[SupportedApps], dir: &Path) -> Result<(), ProjectError> {
+    // the setup unit is also restarted during the activation
+    activation::ensure_idle()?;
+
+    let status = Command::new("systemctl")
+        .args(["restart", SETUP_UNIT])
+        .status()
+        .change_context(ProjectError::SecretsError)
+        .attach_printable("Unable to run systemctl")?;
+
+    if !status.success() {
+        return Err(Report::new(ProjectError::SecretsError)
+            .attach_printable(format!("{} failed with {}", SETUP_UNIT, status))
+            .attach_printable(unit_log()));
+    }
+
+    let missing = missing_secrets(enabled, dir)?;
+    if !missing.is_empty() {
+        return Err(Report::new(ProjectError::MissingSecrets(
+            missing.iter().map(|m| m.file.clone()).collect(),
+        ))
+        .attach_printable(format!(
+            "{} succeeded, but didn't create them. Is the app enabled in the activated configuration?",
+            SETUP_UNIT
+        )));
+    }
+
+    Ok(())
+}
+
+/// The last lines the setup unit logged, to explain why it failed
+fn unit_log() -> String {
+    Command::new("journalctl")
+        .args(["-u", SETUP_UNIT, "-n", "20", "--no-pager", "-o", "cat"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_else(|_| format!("See journalctl -u {}", SETUP_UNIT))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_missing_secrets() {
+        let dir = tempdir().unwrap();
+        for file in expected_secrets(SupportedApps::BitcoinCore) {
+            fs::write(dir.path().join(file), "secret").unwrap();
+        }
+        fs::write(dir.path().join("lnd-cert"), "cert").unwrap();
+
+        let enabled = [
+            SupportedApps::BitcoinCore,
+            SupportedApps::LND,
+            SupportedApps::Electrs,
+        ];
+        let missing = missing_secrets(&enabled, dir.path()).unwrap();
+        assert_eq!(
+            missing,
+            vec![
+                MissingSecret {
+                    app: SupportedApps::LND,
+                    file: "lnd-wallet-password".into(),
+                },
+                MissingSecret {
+                    app: SupportedApps::LND,
+                    file: "lnd-key".into(),
+                },
+            ]
+        );
+        assert_eq!(
+            missing[0].to_string(),
+            "LND is missing the secret lnd-wallet-password"
+        );
+
+        let missing = missing_secrets(&[SupportedApps::Mempool], &dir.path().join("none"));
+        assert_eq!(missing.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,428 @@
+//! Filesystem snapshots of the node data and the project files.
+//!
+//! Snapshots are only available if the directory lives on a ZFS dataset or a
+//! btrfs subvolume. Other filesystems are reported as unsupported, callers
+//! should then carry on without a snapshot.
+//!
+//! Rolling back the data of a lightning node is dangerous: once the node
+//! broadcasts an old channel state, the peer can claim all funds of the
+//! channel with a penalty transaction.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::{
+    app_option_data::option_data::{GetOptionId, OptionData},
+    apps::SupportedApps,
+    errors::ProjectError,
+    project::Project,
+    registry,
+    validation::{normalize_path, DATA_DIR_OPTIONS},
+};
+
+/// All snapshots created by nixblitz start with this prefix, others are never touched
+pub const SNAPSHOT_PREFIX: &str = "nixblitz-";
+
+/// Directory below the btrfs mount point the snapshots are stored in
+const BTRFS_SNAPSHOT_DIR: &str = ".snapshots";
+
+/// Apps whose data must not be rolled back, see the module docs
+pub const LIGHTNING_APPS: [SupportedApps; 2] = [SupportedApps::LND, SupportedApps::CoreLightning];
+
+/// How a directory can be snapshotted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotBackend {
+    /// A ZFS dataset, e.g. "rpool/data"
+    Zfs { dataset: String },
+
+    /// The mount point of a btrfs subvolume
+    Btrfs { mount_point: PathBuf },
+}
+
+impl SnapshotBackend {
+    /// Detects the snapshot backend of the filesystem the given path lives on.
+    ///
+    /// # Returns
+    /// None if the filesystem doesn't support snapshots
+    pub fn detect(path: &Path) -> Result<Option<Self>, ProjectError> {
+        let output = Command::new("findmnt")
+            .args([
+                "--noheadings",
+                "--output",
+                "FSTYPE,SOURCE,TARGET",
+                "--target",
+            ])
+            .arg(path)
+            .output()
+            .change_context(ProjectError::SnapshotError)
+            .attach_printable("Unable to run findmnt")?;
+        if !output.status.success() {
+            return Err(
+                Report::new(ProjectError::SnapshotError).attach_printable(format!(
+                    "Unable to find the mount point of {}",
+                    path.display()
+                )),
+            );
+        }
+
+        Ok(parse_findmnt(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Creates a new read only snapshot
+    ///
+    /// # Returns
+    /// The name of the snapshot
+    pub fn create(&self) -> Result<String, ProjectError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .change_context(ProjectError::SnapshotError)?
+            .as_secs();
+        let name = format!("{}{}", SNAPSHOT_PREFIX, timestamp);
+
+        match self {
+            SnapshotBackend::Zfs { dataset } => {
+                run("zfs", &["snapshot", &format!("{}@{}", dataset, name)])?
+            }
+            SnapshotBackend::Btrfs { mount_point } => {
+                let dir = mount_point.join(BTRFS_SNAPSHOT_DIR);
+                fs::create_dir_all(&dir)
+                    .change_context(ProjectError::SnapshotError)
+                    .attach_printable_lazy(|| format!("Unable to create {}", dir.display()))?;
+                run(
+                    "btrfs",
+                    &[
+                        "subvolume",
+                        "snapshot",
+                        "-r",
+                        &mount_point.to_string_lossy(),
+                        &dir.join(&name).to_string_lossy(),
+                    ],
+                )?
+            }
+        };
+
+        Ok(name)
+    }
+
+    /// Lists the snapshots created by nixblitz, oldest first
+    pub fn list(&self) -> Result<Vec<String>, ProjectError> {
+        let mut names: Vec<String> = match self {
+            SnapshotBackend::Zfs { dataset } => {
+                let output = run(
+                    "zfs",
+                    &["list", "-H", "-t", "snapshot", "-o", "name", dataset],
+                )?;
+                zfs_snapshot_names(&output)
+            }
+            SnapshotBackend::Btrfs { mount_point } => {
+                let dir = mount_point.join(BTRFS_SNAPSHOT_DIR);
+                match fs::read_dir(&dir) {
+                    Ok(entries) => entries
+                        .filter_map(|e| e.ok())
+                        .filter_map(|e| e.file_name().to_str().map(|n| n.to_string()))
+                        .collect(),
+                    Err(_) => vec![],
+                }
+            }
+        };
+
+        names.retain(|n| n.starts_with(SNAPSHOT_PREFIX));
+        // the timestamps have the same number of digits, so this sorts by age
+        names.sort();
+
+        Ok(names)
+    }
+
+    /// Reverts the data to the given snapshot.
+    ///
+    /// Services using the data must be stopped before, see [stop_units]. On
+    /// ZFS the newer snapshots of nixblitz are destroyed, the rollback is
+    /// refused if newer snapshots were created by someone else. btrfs can't
+    /// roll back a mounted subvolume, so the snapshot is only located and the
+    /// path is returned in the error.
+    pub fn rollback(&self, name: &str) -> Result<(), ProjectError> {
+        if !self.list()?.iter().any(|n| n == name) {
+            return Err(Report::new(ProjectError::SnapshotError)
+                .attach_printable(format!("Snapshot {} not found", name)));
+        }
+
+        match self {
+            SnapshotBackend::Zfs { dataset } => {
+                let output = run(
+                    "zfs",
+                    &[
+                        "list",
+                        "-H",
+                        "-t",
+                        "snapshot",
+                        "-o",
+                        "name",
+                        "-s",
+                        "createtxg",
+                        dataset,
+                    ],
+                )?;
+                let foreign = newer_foreign_snapshots(&zfs_snapshot_names(&output), name);
+                if !foreign.is_empty() {
+                    return Err(Report::new(ProjectError::SnapshotError)
+                        .attach_printable(format!(
+                            "Rolling back to {} destroys the newer snapshots {}",
+                            name,
+                            foreign.join(", ")
+                        ))
+                        .attach_printable("They weren't created by nixblitz, roll back manually"));
+                }
+
+                run("zfs", &["rollback", "-r", &format!("{}@{}", dataset, name)])?;
+                Ok(())
+            }
+            SnapshotBackend::Btrfs { mount_point } => {
+                let path = mount_point.join(BTRFS_SNAPSHOT_DIR).join(name);
+                Err(Report::new(ProjectError::SnapshotError)
+                    .attach_printable("A mounted btrfs subvolume can't be rolled back")
+                    .attach_printable(format!("Restore from {} manually", path.display())))
+            }
+        }
+    }
+
+    /// Deletes the oldest snapshots, so that at most `keep` are left
+    ///
+    /// # Returns
+    /// The names of the deleted snapshots
+    pub fn prune(&self, keep: usize) -> Result<Vec<String>, ProjectError> {
+        let expired = expired_snapshots(self.list()?, keep);
+        for name in expired.iter() {
+            match self {
+                SnapshotBackend::Zfs { dataset } => {
+                    run("zfs", &["destroy", &format!("{}@{}", dataset, name)])?
+                }
+                SnapshotBackend::Btrfs { mount_point } => run(
+                    "btrfs",
+                    &[
+                        "subvolume",
+                        "delete",
+                        &mount_point
+                            .join(BTRFS_SNAPSHOT_DIR)
+                            .join(name)
+                            .to_string_lossy(),
+                    ],
+                )?,
+            };
+        }
+
+        Ok(expired)
+    }
+}
+
+/// Returns the data dirs of the enabled lightning nodes inside the given
+/// directory, a rollback of the directory would revert their channel state
+pub fn lightning_data_in(project: &Project, dir: &Path) -> Vec<PathBuf> {
+    let vars = project.variables();
+    let dir = PathBuf::from(normalize_path(&dir.to_string_lossy()));
+
+    LIGHTNING_APPS
+        .iter()
+        .filter(|a| project.is_app_enabled(**a))
+        .flat_map(|a| project.get_options_for(*a))
+        .filter_map(|o| match o {
+            OptionData::TextEdit(t) if DATA_DIR_OPTIONS.contains(&t.id().option.as_str()) => {
+                Some(PathBuf::from(normalize_path(&vars.substitute(t.value()))))
+            }
+            _ => None,
+        })
+        .filter(|d| d.starts_with(&dir))
+        .collect()
+}
+
+/// Returns the systemd units of the enabled apps
+pub fn units_of_enabled_apps(project: &Project) -> Vec<&'static str> {
+    project
+        .enabled_apps()
+        .iter()
+        .flat_map(|a| registry::registration(*a).units.iter().copied())
+        .collect()
+}
+
+/// Stops the given systemd units, e.g. before a rollback
+pub fn stop_units(units: &[&str]) -> Result<(), ProjectError> {
+    systemctl("stop", units)
+}
+
+/// Starts the given systemd units again after a rollback
+pub fn start_units(units: &[&str]) -> Result<(), ProjectError> {
+    systemctl("start", units)
+}
+
+fn systemctl(action: &str, units: &[&str]) -> Result<(), ProjectError> {
+    if units.is_empty() {
+        return Ok(());
+    }
+
+    let mut args = vec![action];
+    args.extend_from_slice(units);
+    run("systemctl", &args)?;
+
+    Ok(())
+}
+
+/// Parses the output of `zfs list -H -o name`, keeps the order
+fn zfs_snapshot_names(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|l| l.trim().split_once('@'))
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// Returns the snapshots after the given one that weren't created by
+/// nixblitz. `names` must be sorted by the creation time.
+fn newer_foreign_snapshots(names: &[String], name: &str) -> Vec<String> {
+    names
+        .iter()
+        .skip_while(|n| *n != name)
+        .skip(1)
+        .filter(|n| !n.starts_with(SNAPSHOT_PREFIX))
+        .cloned()
+        .collect()
+}
+
+/// Parses the output of `findmnt --output FSTYPE,SOURCE,TARGET`
+fn parse_findmnt(output: &str) -> Option<SnapshotBackend> {
+    let mut parts = output.lines().next()?.split_whitespace();
+    let (fs_type, source, target) = (parts.next()?, parts.next()?, parts.next()?);
+
+    match fs_type {
+        "zfs" => Some(SnapshotBackend::Zfs {
+            dataset: source.to_string(),
+        }),
+        "btrfs" => Some(SnapshotBackend::Btrfs {
+            mount_point: PathBuf::from(target),
+        }),
+        _ => None,
+    }
+}
+
+/// Returns the oldest snapshots exceeding the number to keep
+fn expired_snapshots(mut names: Vec<String>, keep: usize) -> Vec<String> {
+    names.sort();
+    let count = names.len().saturating_sub(keep);
+    names.truncate(count);
+    names
+}
+
+/// Runs the given command and returns its output
+fn run(program: &str, args: &[&str]) -> Result<String, ProjectError> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .change_context(ProjectError::SnapshotError)
+        .attach_printable_lazy(|| format!("Unable to run {}", program))?;
+
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::SnapshotError).attach_printable(format!(
+                "{} {} failed: {}",
+                program,
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{
+        app_option_data::{
+            bool_data::BoolOptionChangeData,
+            option_data::{OptionDataChangeNotification, ToOptionId},
+        },
+        lnd::LndConfigOption,
+        utils::init_default_project,
+    };
+
+    #[test]
+    fn test_parse_findmnt() {
+        assert_eq!(
+            parse_findmnt("zfs    rpool/data /var/lib\n"),
+            Some(SnapshotBackend::Zfs {
+                dataset: "rpool/data".into()
+            })
+        );
+        assert_eq!(
+            parse_findmnt("btrfs  /dev/sda2[/@data] /mnt/hdd\n"),
+            Some(SnapshotBackend::Btrfs {
+                mount_point: "/mnt/hdd".into()
+            })
+        );
+        assert_eq!(parse_findmnt("ext4 /dev/sda1 /\n"), None);
+        assert_eq!(parse_findmnt(""), None);
+    }
+
+    #[test]
+    fn test_expired_snapshots() {
+        let names = vec![
+            "nixblitz-1700000300".to_string(),
+            "nixblitz-1700000100".to_string(),
+            "nixblitz-1700000200".to_string(),
+        ];
+        assert_eq!(
+            expired_snapshots(names.clone(), 2),
+            vec!["nixblitz-1700000100".to_string()]
+        );
+        assert_eq!(expired_snapshots(names.clone(), 3), Vec::<String>::new());
+        assert_eq!(expired_snapshots(names, 0).len(), 3);
+    }
+
+    #[test]
+    fn test_newer_foreign_snapshots() {
+        let output = "rpool/data@nixblitz-1700000100\n\
+                      rpool/data@manual\n\
+                      rpool/data@nixblitz-1700000200\n\
+                      rpool/data@autosnap_daily\n\
+                      rpool/data@nixblitz-1700000300\n";
+        let names = zfs_snapshot_names(output);
+        assert_eq!(names.len(), 5);
+        assert_eq!(
+            newer_foreign_snapshots(&names, "nixblitz-1700000100"),
+            vec!["manual".to_string(), "autosnap_daily".to_string()]
+        );
+        assert_eq!(
+            newer_foreign_snapshots(&names, "nixblitz-1700000200"),
+            vec!["autosnap_daily".to_string()]
+        );
+        assert!(newer_foreign_snapshots(&names, "nixblitz-1700000300").is_empty());
+    }
+
+    #[test]
+    fn test_lightning_data_in() {
+        let dir = tempdir().unwrap();
+        init_default_project(dir.path(), Some(false)).unwrap();
+        let mut project = Project::load(dir.path().to_path_buf()).unwrap();
+        assert!(lightning_data_in(&project, Path::new("/var/lib")).is_empty());
+
+        project.set_selected_app(SupportedApps::LND);
+        project
+            .on_option_changed(OptionDataChangeNotification::Bool(
+                BoolOptionChangeData::new(LndConfigOption::Enable.to_option_id(), true),
+            ))
+            .unwrap();
+        assert_eq!(
+            lightning_data_in(&project, Path::new("/var/lib/")),
+            vec![PathBuf::from("/var/lib/lnd")]
+        );
+        assert!(lightning_data_in(&project, Path::new("/mnt/hdd")).is_empty());
+        assert!(units_of_enabled_apps(&project).contains(&"lnd.service"));
+    }
+}
@@ -0,0 +1,234 @@
+//! Imports the public SSH keys of a GitHub or GitLab account.
+//!
+//! Both publish the keys of a user at `https://<host>/<user>.keys`. They are
+//! fetched with curl and shown with their fingerprints from ssh-keygen, so
+//! the user can compare them with `ssh-keygen -lf ~/.ssh/id_ed25519.pub`
+//! before they are added to the authorized keys of the node, see
+//! [Project::add_ssh_auth_keys](crate::project::Project::add_ssh_auth_keys).
+
+use std::{
+    fmt,
+    io::Write,
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::errors::ProjectError;
+
+/// The key types sshd accepts
+const KEY_TYPES: [&str; 6] = [
+    "ssh-ed25519",
+    "ssh-rsa",
+    "ecdsa-sha2-nistp256",
+    "ecdsa-sha2-nistp384",
+    "ecdsa-sha2-nistp521",
+    "sk-ssh-ed25519@openssh.com",
+];
+
+/// Where the keys are published
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    GitHub,
+    GitLab,
+}
+
+impl KeySource {
+    /// The URL the keys of the user are published at
+    pub fn url(&self, user: &str) -> String {
+        match self {
+            KeySource::GitHub => format!("https://github.com/{}.keys", user),
+            KeySource::GitLab => format!("https://gitlab.com/{}.keys", user),
+        }
+    }
+}
+
+impl FromStr for KeySource {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        match s.to_lowercase().as_str() {
+            "github" => Ok(KeySource::GitHub),
+            "gitlab" => Ok(KeySource::GitLab),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySource::GitHub => write!(f, "GitHub"),
+            KeySource::GitLab => write!(f, "GitLab"),
+        }
+    }
+}
+
+/// A public key with the fingerprint ssh-keygen prints for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshKey {
+    /// The key as in authorized_keys, e.g. "ssh-ed25519 AAAA... comment"
+    pub key: String,
+
+    /// e.g. "256 SHA256:... (ED25519)"
+    pub fingerprint: String,
+}
+
+/// Fetches the public keys of the user
+///
+/// # Errors
+/// [ProjectError::SshKeyError] if the user name is invalid, the keys can't
+/// be fetched or the user has none
+pub fn fetch(source: KeySource, user: &str) -> Result<Vec<SshKey>, ProjectError> {
+    check_user(user)?;
+
+    let url = source.url(user);
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        .args(["--max-time", "30"])
+        .arg(&url)
+        .output()
+        .change_context(ProjectError::SshKeyError)
+        .attach_printable("Unable to run curl, is it installed?")?;
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::SshKeyError).attach_printable(format!(
+                "Unable to fetch {}: {}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        );
+    }
+
+    let keys = parse_keys(&String::from_utf8_lossy(&output.stdout));
+    if keys.is_empty() {
+        return Err(Report::new(ProjectError::SshKeyError)
+            .attach_printable(format!("{} has no public SSH keys on {}", user, source)));
+    }
+
+    keys.into_iter()
+        .map(|key| {
+            let fingerprint = fingerprint(&key)?;
+            Ok(SshKey { key, fingerprint })
+        })
+        .collect()
+}
+
+/// Whether both keys are the same, the comments don't matter
+pub fn same_key(a: &str, b: &str) -> bool {
+    let blob = |k: &str| k.split_whitespace().take(2).collect::<Vec<_>>();
+    blob(a) == blob(b)
+}
+
+/// User names on GitHub and GitLab only use these characters, anything
+/// else must not end up in the URL
+fn check_user(user: &str) -> Result<(), ProjectError> {
+    let valid = !user.is_empty()
+        && !user.starts_with(['.', '-'])
+        && user
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        return Ok(());
+    }
+
+    Err(Report::new(ProjectError::SshKeyError)
+        .attach_printable(format!("{} is not a valid user name", user)))
+}
+
+/// Returns the lines holding a public key of a type sshd accepts
+fn parse_keys(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| {
+            let mut parts = l.split_whitespace();
+            matches!(
+                (parts.next(), parts.next()),
+                (Some(kind), Some(_)) if KEY_TYPES.contains(&kind)
+            )
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Asks ssh-keygen for the fingerprint of the key
+fn fingerprint(key: &str) -> Result<String, ProjectError> {
+    let mut child = Command::new("ssh-keygen")
+        .args(["-l", "-f", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .change_context(ProjectError::SshKeyError)
+        .attach_printable("Unable to run ssh-keygen, is OpenSSH installed?")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(key.as_bytes())
+            .change_context(ProjectError::SshKeyError)?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .change_context(ProjectError::SshKeyError)?;
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::SshKeyError).attach_printable(format!(
+                "ssh-keygen can't read the key {}: {}",
+                key,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        );
+    }
+
+    // "256 SHA256:abc no comment (ED25519)", the comment is shown with the key
+    let line = String::from_utf8_lossy(&output.stdout);
+    let mut parts = line.split_whitespace();
+    let fingerprint = match (parts.next(), parts.next(), parts.last()) {
+        (Some(bits), Some(hash), Some(kind)) => format!("{} {} {}", bits, hash, kind),
+        _ => line.trim().to_string(),
+    };
+
+    Ok(fingerprint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        let text = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIA me@laptop\n\
+                    \n\
+                    <html>Not Found</html>\n\
+                    ssh-rsa AAAAB3NzaC1yc2EAAAADAQAB\n\
+                    ssh-dss AAAAB3NzaC1kc3MAAACBA\n";
+        assert_eq!(
+            parse_keys(text),
+            vec![
+                "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIA me@laptop",
+                "ssh-rsa AAAAB3NzaC1yc2EAAAADAQAB"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_user() {
+        assert!(check_user("fusion44").is_ok());
+        assert!(check_user("first.last_1").is_ok());
+        assert!(check_user("").is_err());
+        assert!(check_user("../etc").is_err());
+        assert!(check_user("a/b").is_err());
+        assert!(check_user("a?b=c").is_err());
+    }
+
+    #[test]
+    fn test_same_key() {
+        assert!(same_key("ssh-ed25519 AAAA me@laptop", "ssh-ed25519 AAAA"));
+        assert!(!same_key("ssh-ed25519 AAAA", "ssh-ed25519 AAAB"));
+        assert_eq!(
+            KeySource::GitLab.url("fusion44"),
+            "https://gitlab.com/fusion44.keys"
+        );
+    }
+}
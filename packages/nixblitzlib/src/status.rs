@@ -0,0 +1,439 @@
+//! The health of the services of the node.
+//!
+//! systemd is asked for the state of the units of every enabled app,
+//! Bitcoin Core for its sync progress and the lightning nodes whether they
+//! answer, see [ping]. The data dirs of the apps are measured, see
+//! [disk_usage](crate::disk_usage). All of it must run on the node. The
+//! duration of the last apply is compared with the ones before, see
+//! [apply_history](crate::apply_history). A custom TLS certificate is
+//! checked for its expiry, see [tls_cert](crate::tls_cert). During the first
+//! sync of the block chain the node reports it as its phase, see
+//! [initial_sync](crate::initial_sync).
+
+use std::{fmt, process::Command};
+
+use error_stack::{Report, Result, ResultExt};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    apply_history::ApplyTrend,
+    apps::SupportedApps,
+    disk_usage::{self, DataDirUsage},
+    errors::{describe, ProjectError},
+    initial_sync::NodePhase,
+    node_check::{ping, NodeInfo},
+    project::Project,
+    registry::registration,
+    tls_cert::{self, CertExpiry},
+};
+
+/// The properties read from `systemctl show`
+const UNIT_PROPERTIES: &str = "--property=Id,Type,ActiveState,SubState,Result,ActiveEnterTimestamp";
+
+/// What a unit is doing, derived from its ActiveState
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitHealth {
+    /// The unit is active, for timers this means they are scheduled
+    Running,
+
+    /// The unit is starting, reloading or stopping
+    Changing,
+
+    /// A oneshot unit whose last run succeeded
+    Idle,
+
+    /// The unit doesn't run
+    Stopped,
+
+    /// The unit failed
+    Failed,
+}
+
+impl UnitHealth {
+    /// Whether the unit is fine, i.e. running or waiting for its next run
+    pub fn is_ok(&self) -> bool {
+        matches!(self, UnitHealth::Running | UnitHealth::Idle)
+    }
+}
+
+impl fmt::Display for UnitHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            UnitHealth::Running => "running",
+            UnitHealth::Changing => "changing",
+            UnitHealth::Idle => "idle",
+            UnitHealth::Stopped => "stopped",
+            UnitHealth::Failed => "failed",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// The state of a systemd unit of an app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnitStatus {
+    pub app: SupportedApps,
+
+    /// The name of the unit, e.g. lnd.service
+    pub unit: String,
+    pub health: UnitHealth,
+
+    /// The state as reported by systemd, e.g. "active (running)"
+    pub state: String,
+
+    /// When the unit was last activated, empty if never
+    pub since: String,
+}
+
+/// How far Bitcoin Core is synced
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChainSync {
+    /// The height of the last validated block
+    pub blocks: u64,
+
+    /// The height of the best known header
+    pub headers: u64,
+
+    /// The estimated progress between 0 and 1
+    pub progress: f64,
+
+    /// Whether the node is still in the initial block download
+    pub initial_block_download: bool,
+
+    /// Whether the node prunes the block chain
+    pub pruned: bool,
+}
+
+impl fmt::Display for ChainSync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.2}% (block {} of {})",
+            self.progress * 100.0,
+            self.blocks,
+            self.headers
+        )
+    }
+}
+
+/// The answer of an app to a status query
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Probe<T> {
+    pub app: SupportedApps,
+
+    /// What the app reported, None if it didn't answer
+    pub info: Option<T>,
+
+    /// Why the app didn't answer
+    pub error: Option<String>,
+}
+
+impl<T> Probe<T> {
+    fn new(app: SupportedApps, res: Result<T, ProjectError>) -> Self {
+        match res {
+            Ok(info) => Self {
+                app,
+                info: Some(info),
+                error: None,
+            },
+            Err(e) => Self {
+                app,
+                info: None,
+                error: Some(describe(&e)),
+            },
+        }
+    }
+}
+
+/// Everything `nixblitz status` reports
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeStatus {
+    /// The units of all enabled apps
+    pub units: Vec<UnitStatus>,
+
+    /// The sync progress, None if Bitcoin Core is disabled
+    pub chain: Option<Probe<ChainSync>>,
+
+    /// The enabled lightning nodes
+    pub lightning: Vec<Probe<NodeInfo>>,
+
+    /// The space used by the data dirs of the enabled apps
+    pub disk: Vec<DataDirUsage>,
+
+    /// The duration of the last apply, None if nothing was applied yet
+    pub apply: Option<ApplyTrend>,
+
+    /// The expiry of the custom TLS certificate, None unless one is used
+    pub certificate: Option<Probe<CertExpiry>>,
+
+    /// Whether the node is still in its first sync of the block chain
+    pub phase: NodePhase,
+}
+
+impl NodeStatus {
+    /// Whether all units are fine, all queried apps answered, no disk is
+    /// fuller than the alert threshold and the TLS certificate is valid. A
+    /// slow apply or a certificate expiring soon are only warnings.
+    pub fn is_healthy(&self) -> bool {
+        self.units.iter().all(|u| u.health.is_ok())
+            && self.chain.iter().all(|c| c.error.is_none())
+            && self.lightning.iter().all(|l| l.error.is_none())
+            && self.disk.iter().all(|d| !d.alert)
+            && self
+                .certificate
+                .iter()
+                .all(|c| c.error.is_none() && !c.info.as_ref().is_some_and(|i| i.expired))
+    }
+}
+
+/// Collects the status of all enabled apps
+///
+/// # Errors
+/// [ProjectError::StatusError] if systemd can't be asked, e.g. when not
+/// running on the node, [ProjectError::DiskUsageError] if the data dirs
+/// can't be measured. Apps that don't answer are reported in the status.
+pub fn collect(project: &Project) -> Result<NodeStatus, ProjectError> {
+    let apps = project.enabled_apps();
+    let units = unit_states(&apps)?;
+
+    let chain = apps
+        .contains(&SupportedApps::BitcoinCore)
+        .then(|| Probe::new(SupportedApps::BitcoinCore, chain_sync()));
+    let lightning = [SupportedApps::LND, SupportedApps::CoreLightning]
+        .into_iter()
+        .filter(|a| apps.contains(a))
+        .map(|a| Probe::new(a, ping(project, a)))
+        .collect();
+
+    let phase = project.node_phase(chain.as_ref().and_then(|c| c.info.as_ref()))?;
+
+    Ok(NodeStatus {
+        units,
+        chain,
+        lightning,
+        disk: disk_usage::collect(project)?,
+        apply: project.apply_trend(),
+        certificate: tls_cert::status(&project.tls_settings())
+            .map(|res| Probe::new(SupportedApps::NixOS, res)),
+        phase,
+    })
+}
+
+/// Asks systemd for the state of the units of the given apps
+///
+/// # Errors
+/// [ProjectError::StatusError] if systemctl can't be run
+pub fn unit_states(apps: &[SupportedApps]) -> Result<Vec<UnitStatus>, ProjectError> {
+    let units: Vec<(SupportedApps, &str)> = apps
+        .iter()
+        .flat_map(|a| registration(*a).units.iter().map(|u| (*a, *u)))
+        .collect();
+    if units.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let output = Command::new("systemctl")
+        .args(["show", UNIT_PROPERTIES])
+        .args(units.iter().map(|(_, u)| *u))
+        .output()
+        .change_context(ProjectError::StatusError)
+        .attach_printable("Unable to run systemctl")?;
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::StatusError).attach_printable(format!(
+                "systemctl show failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        );
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(units
+        .into_iter()
+        .map(|(app, unit)| parse_show(app, unit, &stdout))
+        .collect())
+}
+
+/// Finds the block of the unit in the output of `systemctl show` with
+/// multiple units. Units systemd doesn't know are reported as stopped.
+fn parse_show(app: SupportedApps, unit: &str, output: &str) -> UnitStatus {
+    let block = output
+        .split("\n\n")
+        .find(|b| b.lines().any(|l| l.strip_prefix("Id=") == Some(unit)))
+        .unwrap_or_default();
+    let property = |name: &str| {
+        block
+            .lines()
+            .find_map(|l| l.strip_prefix(name)?.strip_prefix('='))
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let active = property("ActiveState");
+    let sub = property("SubState");
+    let health = match active.as_str() {
+        "active" => UnitHealth::Running,
+        "activating" | "deactivating" | "reloading" => UnitHealth::Changing,
+        "failed" => UnitHealth::Failed,
+        _ if property("Type") == "oneshot" && property("Result") == "success" => UnitHealth::Idle,
+        _ => UnitHealth::Stopped,
+    };
+    let state = match (active.is_empty(), sub.is_empty()) {
+        (true, _) => "unknown".to_string(),
+        (false, true) => active,
+        (false, false) => format!("{} ({})", active, sub),
+    };
+
+    UnitStatus {
+        app,
+        unit: unit.to_string(),
+        health,
+        state,
+        since: property("ActiveEnterTimestamp"),
+    }
+}
+
+/// Asks Bitcoin Core for its sync progress via `bitcoin-cli`
+///
+/// # Errors
+/// [ProjectError::NodeUnreachable] if Bitcoin Core doesn't answer
+pub fn chain_sync() -> Result<ChainSync, ProjectError> {
+    let title = registration(SupportedApps::BitcoinCore).title;
+    let unreachable = || Report::new(ProjectError::NodeUnreachable(title.to_string()));
+
+    let output = Command::new("bitcoin-cli")
+        .arg("getblockchaininfo")
+        .output()
+        .change_context(ProjectError::NodeUnreachable(title.to_string()))
+        .attach_printable("Unable to run bitcoin-cli, is it installed?")?;
+    if !output.status.success() {
+        return Err(unreachable().attach_printable(format!(
+            "bitcoin-cli getblockchaininfo failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    parse_blockchain_info(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| unreachable().attach_printable("Unable to parse the blockchain info"))
+}
+
+fn parse_blockchain_info(json: &str) -> Option<ChainSync> {
+    let v: Value = serde_json::from_str(json).ok()?;
+
+    Some(ChainSync {
+        blocks: v["blocks"].as_u64()?,
+        headers: v["headers"].as_u64()?,
+        progress: v["verificationprogress"].as_f64()?,
+        initial_block_download: v["initialblockdownload"].as_bool().unwrap_or(false),
+        pruned: v["pruned"].as_bool().unwrap_or(false),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHOW: &str = "Id=bitcoind.service
+Type=notify
+ActiveState=active
+SubState=running
+Result=success
+ActiveEnterTimestamp=Sun 2024-11-03 04:12:09 CET
+
+Id=charge-lnd.service
+Type=oneshot
+ActiveState=inactive
+SubState=dead
+Result=success
+ActiveEnterTimestamp=
+
+Id=lnd.service
+Type=simple
+ActiveState=failed
+SubState=failed
+Result=exit-code
+ActiveEnterTimestamp=Sun 2024-11-03 04:13:00 CET
+";
+
+    #[test]
+    fn test_parse_show() {
+        let bitcoind = parse_show(SupportedApps::BitcoinCore, "bitcoind.service", SHOW);
+        assert_eq!(bitcoind.health, UnitHealth::Running);
+        assert_eq!(bitcoind.state, "active (running)");
+        assert_eq!(bitcoind.since, "Sun 2024-11-03 04:12:09 CET");
+
+        let charge = parse_show(SupportedApps::ChargeLnd, "charge-lnd.service", SHOW);
+        assert_eq!(charge.health, UnitHealth::Idle);
+        assert!(charge.since.is_empty());
+
+        let lnd = parse_show(SupportedApps::LND, "lnd.service", SHOW);
+        assert_eq!(lnd.health, UnitHealth::Failed);
+        assert!(!lnd.health.is_ok());
+
+        let unknown = parse_show(SupportedApps::Tor, "tor.service", SHOW);
+        assert_eq!(unknown.health, UnitHealth::Stopped);
+        assert_eq!(unknown.state, "unknown");
+    }
+
+    #[test]
+    fn test_parse_blockchain_info() {
+        let json = r#"{
+            "chain": "main",
+            "blocks": 860000,
+            "headers": 870000,
+            "verificationprogress": 0.9512,
+            "initialblockdownload": true
+        }"#;
+        let sync = parse_blockchain_info(json).unwrap();
+        assert_eq!(sync.blocks, 860000);
+        assert!(sync.initial_block_download);
+        assert!(!sync.pruned);
+        assert_eq!(sync.to_string(), "95.12% (block 860000 of 870000)");
+
+        assert!(parse_blockchain_info("{}").is_none());
+    }
+
+    #[test]
+    fn test_is_healthy() {
+        let mut status = NodeStatus {
+            units: vec![parse_show(
+                SupportedApps::BitcoinCore,
+                "bitcoind.service",
+                SHOW,
+            )],
+            chain: None,
+            lightning: vec![],
+            disk: vec![],
+            apply: None,
+            certificate: None,
+            phase: NodePhase::Idle,
+        };
+        assert!(status.is_healthy());
+
+        let mut expiry = CertExpiry {
+            not_after: "Jan  1 00:00:00 2027 GMT".into(),
+            expired: false,
+            expires_soon: true,
+        };
+        status.certificate = Some(Probe {
+            app: SupportedApps::NixOS,
+            info: Some(expiry.clone()),
+            error: None,
+        });
+        assert!(status.is_healthy());
+
+        expiry.expired = true;
+        status.certificate.as_mut().unwrap().info = Some(expiry);
+        assert!(!status.is_healthy());
+        status.certificate = None;
+
+        status.lightning.push(Probe {
+            app: SupportedApps::LND,
+            info: None,
+            error: Some("LND is not reachable".into()),
+        });
+        assert!(!status.is_healthy());
+    }
+}
@@ -8,9 +8,29 @@ use crate::{
     bitcoind::BitcoindConfigOption,
     blitz_api::BlitzApiConfigOption,
     blitz_webui::BlitzWebUiConfigOption,
+    bos::BosConfigOption,
+    channel_backup::ChannelBackupConfigOption,
+    charge_lnd::ChargeLndConfigOption,
     cln::ClnConfigOption,
+    electrs::ElectrsConfigOption,
+    elements::ElementsConfigOption,
+    eps::EpsConfigOption,
+    extra_nix,
+    fulcrum::FulcrumConfigOption,
+    grafana::GrafanaConfigOption,
+    i2p::I2pConfigOption,
+    lit::LightningTerminalConfigOption,
     lnd::LndConfigOption,
+    mempool::MempoolConfigOption,
+    mining::MiningConfigOption,
+    monitoring::MonitoringConfigOption,
     nix_base_config::NixBaseConfigOption,
+    phoenixd::PhoenixdConfigOption,
+    registry,
+    system_backup::SystemBackupConfigOption,
+    tailscale::TailscaleConfigOption,
+    thunderhub::ThunderHubConfigOption,
+    tor::TorConfigOption,
 };
 
 // default password: "nixblitz"
@@ -54,6 +74,83 @@ pub static OPTION_TITLES: Lazy<HashMap<OptionId, &str>> = Lazy::new(|| {
         NixBaseConfigOption::InitialPassword.to_option_id(),
         "Initial Password",
     );
+    map.insert(
+        NixBaseConfigOption::SnapshotKeep.to_option_id(),
+        "Snapshots To Keep",
+    );
+    map.insert(
+        NixBaseConfigOption::SnapshotDataDir.to_option_id(),
+        "Snapshot Data Dir",
+    );
+    map.insert(
+        NixBaseConfigOption::BackupKeepDaily.to_option_id(),
+        "Daily Backups To Keep",
+    );
+    map.insert(
+        NixBaseConfigOption::BackupKeepWeekly.to_option_id(),
+        "Weekly Backups To Keep",
+    );
+    map.insert(NixBaseConfigOption::TlsMode.to_option_id(), "TLS Mode");
+    map.insert(NixBaseConfigOption::Domain.to_option_id(), "Domain");
+    map.insert(NixBaseConfigOption::AcmeEmail.to_option_id(), "ACME Email");
+    map.insert(
+        NixBaseConfigOption::TlsCertificate.to_option_id(),
+        "TLS certificate",
+    );
+    map.insert(NixBaseConfigOption::TlsKey.to_option_id(), "TLS key");
+    map.insert(
+        NixBaseConfigOption::Fail2banEnable.to_option_id(),
+        "Enable fail2ban",
+    );
+    map.insert(NixBaseConfigOption::SshPort.to_option_id(), "SSH Port");
+    map.insert(
+        NixBaseConfigOption::SshMaxAuthTries.to_option_id(),
+        "SSH Max Auth Tries",
+    );
+    map.insert(
+        NixBaseConfigOption::SshDisableRootLogin.to_option_id(),
+        "Disable SSH Root Login",
+    );
+    map.insert(
+        NixBaseConfigOption::SshTwoFactor.to_option_id(),
+        "SSH Two Factor Auth",
+    );
+    map.insert(
+        NixBaseConfigOption::SshPasswordAuth.to_option_id(),
+        "SSH Password Logins",
+    );
+    map.insert(
+        NixBaseConfigOption::TrustedNetworks.to_option_id(),
+        "Trusted Networks",
+    );
+    map.insert(
+        NixBaseConfigOption::ZramPercent.to_option_id(),
+        "zram Swap (% of RAM)",
+    );
+    map.insert(
+        NixBaseConfigOption::SecretBackend.to_option_id(),
+        "Secret Storage",
+    );
+    map.insert(
+        NixBaseConfigOption::SecretRecipients.to_option_id(),
+        "Secret Recipients (age keys)",
+    );
+    map.insert(
+        NixBaseConfigOption::DnsResolver.to_option_id(),
+        "DNS Resolver",
+    );
+    map.insert(
+        NixBaseConfigOption::DnsServers.to_option_id(),
+        "DNS Servers",
+    );
+    map.insert(
+        NixBaseConfigOption::DnsOverTls.to_option_id(),
+        "DNS over TLS",
+    );
+    map.insert(
+        NixBaseConfigOption::DiskAlertPercent.to_option_id(),
+        "Disk Usage Alert (%)",
+    );
 
     // BITCOIN CORE
     map.insert(BitcoindConfigOption::Enable.to_option_id(), "Enable");
@@ -79,6 +176,10 @@ pub static OPTION_TITLES: Lazy<HashMap<OptionId, &str>> = Lazy::new(|| {
         BitcoindConfigOption::Network.to_option_id(),
         "bitcoin network",
     );
+    map.insert(
+        BitcoindConfigOption::Implementation.to_option_id(),
+        "Node implementation",
+    );
     map.insert(BitcoindConfigOption::RpcUsers.to_option_id(), "RPC users");
     map.insert(
         BitcoindConfigOption::RpcAddress.to_option_id(),
@@ -87,7 +188,7 @@ pub static OPTION_TITLES: Lazy<HashMap<OptionId, &str>> = Lazy::new(|| {
     map.insert(BitcoindConfigOption::RpcPort.to_option_id(), "RPC port");
     map.insert(
         BitcoindConfigOption::RpcAllowIp.to_option_id(),
-        "Ips allowed to access RPC",
+        "RPC allowed hosts",
     );
     map.insert(
         BitcoindConfigOption::Prune.to_option_id(),
@@ -105,6 +206,42 @@ pub static OPTION_TITLES: Lazy<HashMap<OptionId, &str>> = Lazy::new(|| {
         BitcoindConfigOption::DbCache.to_option_id(),
         "Database cache size",
     );
+    map.insert(
+        BitcoindConfigOption::Par.to_option_id(),
+        "Script verification threads",
+    );
+    map.insert(
+        BitcoindConfigOption::RpcThreads.to_option_id(),
+        "RPC threads",
+    );
+    map.insert(
+        BitcoindConfigOption::MaxUploadTarget.to_option_id(),
+        "Max upload per day (MiB)",
+    );
+    map.insert(
+        BitcoindConfigOption::QuietHours.to_option_id(),
+        "Pause peers during quiet hours",
+    );
+    map.insert(
+        BitcoindConfigOption::QuietHoursStart.to_option_id(),
+        "Quiet hours start (hour)",
+    );
+    map.insert(
+        BitcoindConfigOption::QuietHoursEnd.to_option_id(),
+        "Quiet hours end (hour)",
+    );
+    map.insert(
+        BitcoindConfigOption::VerifyChainSchedule.to_option_id(),
+        "Verify chain schedule",
+    );
+    map.insert(
+        BitcoindConfigOption::VerifyChainBlocks.to_option_id(),
+        "Verify chain blocks",
+    );
+    map.insert(
+        BitcoindConfigOption::ReindexSchedule.to_option_id(),
+        "Reindex schedule",
+    );
     map.insert(
         BitcoindConfigOption::DataDir.to_option_id(),
         "The data directory",
@@ -125,6 +262,11 @@ pub static OPTION_TITLES: Lazy<HashMap<OptionId, &str>> = Lazy::new(|| {
         BitcoindConfigOption::ZmqPubRawBlock.to_option_id(),
         "ZMQ address for zmqpubrawblock",
     );
+    map.insert(BitcoindConfigOption::I2pSam.to_option_id(), "I2P SAM proxy");
+    map.insert(
+        BitcoindConfigOption::OnlyNet.to_option_id(),
+        "Only connect via these networks",
+    );
 
     // CORE LIGHTNING
     map.insert(
@@ -147,6 +289,20 @@ pub static OPTION_TITLES: Lazy<HashMap<OptionId, &str>> = Lazy::new(|| {
         ClnConfigOption::ExtraConfig.to_option_id(),
         "Extra Configuration",
     );
+    map.insert(
+        ClnConfigOption::BaseFeeMsat.to_option_id(),
+        "Base Fee (msat)",
+    );
+    map.insert(ClnConfigOption::FeeRatePpm.to_option_id(), "Fee Rate (ppm)");
+    map.insert(
+        ClnConfigOption::MinHtlcMsat.to_option_id(),
+        "Min HTLC (msat)",
+    );
+    map.insert(
+        ClnConfigOption::MaxHtlcMsat.to_option_id(),
+        "Max HTLC (msat)",
+    );
+    map.insert(ClnConfigOption::CltvDelta.to_option_id(), "CLTV Delta");
     map.insert(ClnConfigOption::User.to_option_id(), "Service User");
     map.insert(ClnConfigOption::Group.to_option_id(), "Service Group");
     map.insert(
@@ -183,6 +339,49 @@ pub static OPTION_TITLES: Lazy<HashMap<OptionId, &str>> = Lazy::new(|| {
         LndConfigOption::ExtraConfig.to_option_id(),
         "Extra Configuration",
     );
+    map.insert(
+        LndConfigOption::BaseFeeMsat.to_option_id(),
+        "Base Fee (msat)",
+    );
+    map.insert(LndConfigOption::FeeRatePpm.to_option_id(), "Fee Rate (ppm)");
+    map.insert(
+        LndConfigOption::MinHtlcMsat.to_option_id(),
+        "Min HTLC (msat)",
+    );
+    map.insert(LndConfigOption::CltvDelta.to_option_id(), "CLTV Delta");
+    map.insert(
+        LndConfigOption::WatchtowerEnable.to_option_id(),
+        "Run Watchtower",
+    );
+    map.insert(
+        LndConfigOption::WatchtowerAddress.to_option_id(),
+        "Watchtower Address",
+    );
+    map.insert(
+        LndConfigOption::WatchtowerPort.to_option_id(),
+        "Watchtower Port",
+    );
+    map.insert(
+        LndConfigOption::WtClientEnable.to_option_id(),
+        "Use Watchtowers",
+    );
+    map.insert(
+        LndConfigOption::WtClientTowers.to_option_id(),
+        "Watchtower URIs",
+    );
+    map.insert(
+        LndConfigOption::CompactDbSchedule.to_option_id(),
+        "Compact database schedule",
+    );
+    map.insert(LndConfigOption::Alias.to_option_id(), "Node Alias");
+    map.insert(
+        LndConfigOption::NodeInfoEnable.to_option_id(),
+        "Node Info Page",
+    );
+    map.insert(
+        LndConfigOption::NodeInfoLocation.to_option_id(),
+        "Node Info Location",
+    );
 
     // BLITZ API
     map.insert(
@@ -232,5 +431,394 @@ pub static OPTION_TITLES: Lazy<HashMap<OptionId, &str>> = Lazy::new(|| {
         "Expose via nginx",
     );
 
+    // ELECTRS
+    map.insert(ElectrsConfigOption::Enable.to_option_id(), "Enable Electrs");
+    map.insert(ElectrsConfigOption::Address.to_option_id(), "Address");
+    map.insert(ElectrsConfigOption::Port.to_option_id(), "Port");
+    map.insert(
+        ElectrsConfigOption::DbDir.to_option_id(),
+        "Database directory",
+    );
+    map.insert(
+        ElectrsConfigOption::MonitoringAddress.to_option_id(),
+        "Monitoring address",
+    );
+    map.insert(
+        ElectrsConfigOption::MonitoringPort.to_option_id(),
+        "Monitoring port",
+    );
+    map.insert(
+        ElectrsConfigOption::IndexBatchSize.to_option_id(),
+        "Index batch size",
+    );
+    map.insert(
+        ElectrsConfigOption::IndexLookupLimit.to_option_id(),
+        "Index lookup limit",
+    );
+    map.insert(ElectrsConfigOption::ExtraArgs.to_option_id(), "Extra args");
+
+    // MEMPOOL
+    map.insert(MempoolConfigOption::Enable.to_option_id(), "Enable Mempool");
+    map.insert(
+        MempoolConfigOption::Address.to_option_id(),
+        "Backend address",
+    );
+    map.insert(MempoolConfigOption::Port.to_option_id(), "Backend port");
+    map.insert(
+        MempoolConfigOption::FrontendAddress.to_option_id(),
+        "Frontend address",
+    );
+    map.insert(
+        MempoolConfigOption::FrontendPort.to_option_id(),
+        "Frontend port",
+    );
+    map.insert(
+        MempoolConfigOption::ElectrumServer.to_option_id(),
+        "Electrum server",
+    );
+    map.insert(MempoolConfigOption::DbName.to_option_id(), "Database name");
+    map.insert(MempoolConfigOption::DbUser.to_option_id(), "Database user");
+    map.insert(
+        MempoolConfigOption::NginxEnable.to_option_id(),
+        "Expose via nginx",
+    );
+    map.insert(
+        MempoolConfigOption::NginxLocation.to_option_id(),
+        "Nginx location",
+    );
+    map.insert(
+        MempoolConfigOption::NginxHttps.to_option_id(),
+        "Serve via HTTPS",
+    );
+
+    // THUNDERHUB
+    map.insert(ThunderHubConfigOption::Enable.to_option_id(), "Enable");
+    map.insert(ThunderHubConfigOption::Port.to_option_id(), "Port");
+    map.insert(
+        ThunderHubConfigOption::Password.to_option_id(),
+        "Account password",
+    );
+    map.insert(
+        ThunderHubConfigOption::NginxEnable.to_option_id(),
+        "Expose via nginx",
+    );
+    map.insert(
+        ThunderHubConfigOption::NginxLocation.to_option_id(),
+        "Nginx location",
+    );
+    map.insert(
+        ThunderHubConfigOption::NginxHttps.to_option_id(),
+        "Serve via HTTPS",
+    );
+
+    // TOR
+    map.insert(TorConfigOption::Enable.to_option_id(), "Enable");
+    map.insert(
+        TorConfigOption::ClientEnable.to_option_id(),
+        "Tor SOCKS proxy",
+    );
+    map.insert(
+        TorConfigOption::OnionBitcoind.to_option_id(),
+        "Bitcoin Core onion service",
+    );
+    map.insert(
+        TorConfigOption::OnionLnd.to_option_id(),
+        "LND onion service",
+    );
+    map.insert(
+        TorConfigOption::OnionCln.to_option_id(),
+        "Core Lightning onion service",
+    );
+    map.insert(
+        TorConfigOption::OnionBlitzApi.to_option_id(),
+        "Blitz API onion service",
+    );
+    map.insert(
+        TorConfigOption::OnionWebUi.to_option_id(),
+        "Web UI onion service",
+    );
+    map.insert(
+        TorConfigOption::NixOverTor.to_option_id(),
+        "Fetch nix packages via Tor",
+    );
+    map.insert(
+        TorConfigOption::NixFallback.to_option_id(),
+        "Build packages if Tor fetches fail",
+    );
+    map.insert(
+        TorConfigOption::NetnsIsolation.to_option_id(),
+        "Isolate services in network namespaces",
+    );
+
+    // FULCRUM
+    map.insert(FulcrumConfigOption::Enable.to_option_id(), "Enable Fulcrum");
+    map.insert(FulcrumConfigOption::Address.to_option_id(), "Address");
+    map.insert(FulcrumConfigOption::Port.to_option_id(), "TCP port");
+    map.insert(FulcrumConfigOption::SslPort.to_option_id(), "SSL port");
+    map.insert(
+        FulcrumConfigOption::CertPath.to_option_id(),
+        "SSL certificate path",
+    );
+    map.insert(FulcrumConfigOption::KeyPath.to_option_id(), "SSL key path");
+    map.insert(
+        FulcrumConfigOption::DbDir.to_option_id(),
+        "Database directory",
+    );
+    map.insert(FulcrumConfigOption::Banner.to_option_id(), "Banner text");
+
+    // LIGHTNING TERMINAL
+    map.insert(
+        LightningTerminalConfigOption::Enable.to_option_id(),
+        "Enable Lightning Terminal",
+    );
+    map.insert(LightningTerminalConfigOption::Port.to_option_id(), "Port");
+    map.insert(
+        LightningTerminalConfigOption::UiPassword.to_option_id(),
+        "UI Password",
+    );
+    map.insert(
+        LightningTerminalConfigOption::LoopEnable.to_option_id(),
+        "Enable Loop",
+    );
+    map.insert(
+        LightningTerminalConfigOption::PoolEnable.to_option_id(),
+        "Enable Pool",
+    );
+    map.insert(
+        LightningTerminalConfigOption::FaradayEnable.to_option_id(),
+        "Enable Faraday",
+    );
+
+    // CHANNEL BACKUP
+    map.insert(
+        ChannelBackupConfigOption::Enable.to_option_id(),
+        "Enable Channel Backup",
+    );
+    map.insert(
+        ChannelBackupConfigOption::Target.to_option_id(),
+        "Backup Target",
+    );
+    map.insert(
+        ChannelBackupConfigOption::LocalDir.to_option_id(),
+        "Local Directory",
+    );
+    map.insert(
+        ChannelBackupConfigOption::SshTarget.to_option_id(),
+        "SSH Target",
+    );
+    map.insert(
+        ChannelBackupConfigOption::SshKeyFile.to_option_id(),
+        "SSH Key File",
+    );
+    map.insert(
+        ChannelBackupConfigOption::NextcloudUrl.to_option_id(),
+        "Nextcloud WebDAV URL",
+    );
+    map.insert(
+        ChannelBackupConfigOption::NextcloudUser.to_option_id(),
+        "Nextcloud User",
+    );
+    map.insert(
+        ChannelBackupConfigOption::NextcloudPasswordFile.to_option_id(),
+        "Nextcloud Password File",
+    );
+
+    // TAILSCALE
+    map.insert(
+        TailscaleConfigOption::Enable.to_option_id(),
+        "Enable Tailscale",
+    );
+    map.insert(
+        TailscaleConfigOption::AuthKeyFile.to_option_id(),
+        "Auth Key File",
+    );
+    map.insert(TailscaleConfigOption::Port.to_option_id(), "Port");
+    map.insert(
+        TailscaleConfigOption::AdvertiseRoutes.to_option_id(),
+        "Advertise Routes",
+    );
+    map.insert(
+        TailscaleConfigOption::AdvertiseExitNode.to_option_id(),
+        "Advertise as Exit Node",
+    );
+    map.insert(
+        TailscaleConfigOption::Ssh.to_option_id(),
+        "Enable Tailscale SSH",
+    );
+
+    // MONITORING
+    map.insert(
+        MonitoringConfigOption::Enable.to_option_id(),
+        "Enable Monitoring",
+    );
+    map.insert(
+        MonitoringConfigOption::Port.to_option_id(),
+        "Prometheus Port",
+    );
+    map.insert(
+        MonitoringConfigOption::RetentionDays.to_option_id(),
+        "Retention (days)",
+    );
+    map.insert(
+        MonitoringConfigOption::NodeExporterPort.to_option_id(),
+        "Node Exporter Port",
+    );
+    map.insert(
+        MonitoringConfigOption::BitcoindExporter.to_option_id(),
+        "Export Bitcoin Core Metrics",
+    );
+    map.insert(
+        MonitoringConfigOption::BitcoindExporterPort.to_option_id(),
+        "Bitcoin Core Exporter Port",
+    );
+    map.insert(
+        MonitoringConfigOption::LndExporter.to_option_id(),
+        "Export LND Metrics",
+    );
+    map.insert(
+        MonitoringConfigOption::LndExporterPort.to_option_id(),
+        "LND Exporter Port",
+    );
+
+    // GRAFANA
+    map.insert(GrafanaConfigOption::Enable.to_option_id(), "Enable Grafana");
+    map.insert(GrafanaConfigOption::Port.to_option_id(), "Port");
+    map.insert(
+        GrafanaConfigOption::AdminPassword.to_option_id(),
+        "Admin Password",
+    );
+    map.insert(
+        GrafanaConfigOption::NginxEnable.to_option_id(),
+        "Serve via nginx",
+    );
+    map.insert(
+        GrafanaConfigOption::NginxLocation.to_option_id(),
+        "Nginx Location",
+    );
+    map.insert(
+        GrafanaConfigOption::NginxHttps.to_option_id(),
+        "Serve via HTTPS",
+    );
+
+    // ELECTRUM PERSONAL SERVER
+    map.insert(EpsConfigOption::Enable.to_option_id(), "Enable EPS");
+    map.insert(EpsConfigOption::Address.to_option_id(), "Address");
+    map.insert(EpsConfigOption::Port.to_option_id(), "SSL port");
+    map.insert(
+        EpsConfigOption::MasterPublicKeys.to_option_id(),
+        "Master Public Keys",
+    );
+
+    // I2P
+    map.insert(I2pConfigOption::Enable.to_option_id(), "Enable I2P");
+    map.insert(I2pConfigOption::SamAddress.to_option_id(), "SAM Address");
+    map.insert(I2pConfigOption::SamPort.to_option_id(), "SAM Port");
+    map.insert(
+        I2pConfigOption::Bandwidth.to_option_id(),
+        "Bandwidth (KB/s)",
+    );
+
+    // BALANCE OF SATOSHIS
+    map.insert(BosConfigOption::Enable.to_option_id(), "Enable bos");
+    map.insert(
+        BosConfigOption::TelegramTokenFile.to_option_id(),
+        "Telegram Bot Token File",
+    );
+    map.insert(
+        BosConfigOption::TelegramUserId.to_option_id(),
+        "Telegram User Id",
+    );
+
+    // CHARGE-LND
+    map.insert(
+        ChargeLndConfigOption::Enable.to_option_id(),
+        "Enable charge-lnd",
+    );
+    map.insert(
+        ChargeLndConfigOption::Policies.to_option_id(),
+        "Fee Policies",
+    );
+    map.insert(ChargeLndConfigOption::Interval.to_option_id(), "Interval");
+    map.insert(ChargeLndConfigOption::DryRun.to_option_id(), "Dry Run");
+
+    // PHOENIXD
+    map.insert(
+        PhoenixdConfigOption::Enable.to_option_id(),
+        "Enable phoenixd",
+    );
+    map.insert(PhoenixdConfigOption::HttpPort.to_option_id(), "HTTP Port");
+    map.insert(PhoenixdConfigOption::DataDir.to_option_id(), "Data Dir");
+
+    // ELEMENTS
+    map.insert(
+        ElementsConfigOption::Enable.to_option_id(),
+        "Enable Elements",
+    );
+    map.insert(ElementsConfigOption::Chain.to_option_id(), "Chain");
+    map.insert(ElementsConfigOption::RpcPort.to_option_id(), "RPC Port");
+    map.insert(ElementsConfigOption::DataDir.to_option_id(), "Data Dir");
+    map.insert(
+        ElementsConfigOption::ValidatePegin.to_option_id(),
+        "Validate Peg-ins",
+    );
+
+    // MINING
+    map.insert(
+        MiningConfigOption::Enable.to_option_id(),
+        "Enable Solo Mining",
+    );
+    map.insert(
+        MiningConfigOption::StratumPort.to_option_id(),
+        "Stratum Port",
+    );
+    map.insert(
+        MiningConfigOption::PayoutAddress.to_option_id(),
+        "Payout Address",
+    );
+
+    // SYSTEM BACKUP
+    map.insert(
+        SystemBackupConfigOption::Enable.to_option_id(),
+        "Enable System Backup",
+    );
+    map.insert(SystemBackupConfigOption::Tool.to_option_id(), "Tool");
+    map.insert(
+        SystemBackupConfigOption::Repository.to_option_id(),
+        "Repository",
+    );
+    map.insert(
+        SystemBackupConfigOption::PasswordFile.to_option_id(),
+        "Password File",
+    );
+    map.insert(
+        SystemBackupConfigOption::Schedule.to_option_id(),
+        "Schedule",
+    );
+    map.insert(
+        SystemBackupConfigOption::ConfigDir.to_option_id(),
+        "Project Directory",
+    );
+    map.insert(
+        SystemBackupConfigOption::ExtraPaths.to_option_id(),
+        "Extra Paths",
+    );
+    map.insert(
+        SystemBackupConfigOption::KeepDaily.to_option_id(),
+        "Daily Backups To Keep",
+    );
+    map.insert(
+        SystemBackupConfigOption::KeepWeekly.to_option_id(),
+        "Weekly Backups To Keep",
+    );
+    map.insert(
+        SystemBackupConfigOption::KeepMonthly.to_option_id(),
+        "Monthly Backups To Keep",
+    );
+
+    // EXTRA NIX, every app has one
+    for reg in registry::APPS.iter() {
+        map.insert(extra_nix::option_id(reg.app), "Extra Nix");
+    }
+
     map
 });
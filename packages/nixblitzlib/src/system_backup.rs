@@ -0,0 +1,673 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, process::Command, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::{app_options, AppConfig},
+    app_option_data::{
+        bool_data::BoolOptionData,
+        manual_string_list_data::ManualStringListOptionData,
+        number_data::NumberOptionData,
+        option_data::{OptionDataChangeNotification, OptionId, ToNixString, ToOptionId},
+        string_list_data::{StringListOptionData, StringListOptionItem},
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    bitcoind::BitcoinDaemonService,
+    defaults,
+    electrs::ElectrsService,
+    errors::{ProjectError, TemplatingError},
+    fulcrum::FulcrumService,
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/system_backup.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/system_backup.json";
+
+/// Name of the backup job in the NixOS restic and borgbackup modules
+const JOB_NAME: &str = "nixblitz";
+
+/// The program creating the backups
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BackupTool {
+    #[default]
+    Restic,
+    Borg,
+}
+
+impl BackupTool {
+    pub fn to_string_array() -> [&'static str; 2] {
+        ["restic", "borg"]
+    }
+
+    /// The systemd unit NixOS creates for the backup job
+    pub fn unit_name(&self) -> String {
+        match self {
+            BackupTool::Restic => format!("restic-backups-{}.service", JOB_NAME),
+            BackupTool::Borg => format!("borgbackup-job-{}.service", JOB_NAME),
+        }
+    }
+
+    /// The wrapper NixOS installs to run the tool with the settings of the job
+    pub fn wrapper(&self) -> String {
+        match self {
+            BackupTool::Restic => format!("restic-{}", JOB_NAME),
+            BackupTool::Borg => format!("borg-job-{}", JOB_NAME),
+        }
+    }
+}
+
+impl fmt::Display for BackupTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tool_str = match self {
+            BackupTool::Restic => "restic",
+            BackupTool::Borg => "borg",
+        };
+        write!(f, "{}", tool_str)
+    }
+}
+
+impl FromStr for BackupTool {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<BackupTool, ()> {
+        match s {
+            "restic" => Ok(BackupTool::Restic),
+            "borg" => Ok(BackupTool::Borg),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Scheduled, encrypted backups of the node with restic or borg.
+///
+/// Backs up the project files, the secrets and the app data. The block
+/// chain and the indexes built from it are left out, they can be synced
+/// again.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SystemBackupService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// The program creating the backups, see [BackupTool]
+    pub tool: Box<StringListOptionData>,
+
+    /// Where the backups are stored, in the URL format of the tool
+    ///
+    /// Example: "sftp:backup@nas:/srv/restic" or "ssh://backup@nas/./borg"
+    pub repository: Box<TextOptionData>,
+
+    /// File holding the password the backups are encrypted with.
+    /// Only the path ends up in the Nix store.
+    pub password_file: Box<TextOptionData>,
+
+    /// When to run the backup, as systemd calendar event
+    ///
+    /// Example: "daily" or "*-*-* 03:00:00"
+    pub schedule: Box<TextOptionData>,
+
+    /// The directory holding the project files on the node
+    pub config_dir: Box<TextOptionData>,
+
+    /// Additional paths to back up
+    pub extra_paths: Box<ManualStringListOptionData>,
+
+    /// Number of daily backups to keep
+    pub keep_daily: Box<NumberOptionData>,
+
+    /// Number of weekly backups to keep
+    pub keep_weekly: Box<NumberOptionData>,
+
+    /// Number of monthly backups to keep
+    pub keep_monthly: Box<NumberOptionData>,
+
+    /// Directories with chain data that are not backed up.
+    /// Kept in sync by the project, see [SystemBackupService::sync_with_chain_data].
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SystemBackupConfigOption {
+    Enable,
+    Tool,
+    Repository,
+    PasswordFile,
+    Schedule,
+    ConfigDir,
+    ExtraPaths,
+    KeepDaily,
+    KeepWeekly,
+    KeepMonthly,
+}
+
+impl ToOptionId for SystemBackupConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::SystemBackup, self.to_string())
+    }
+}
+
+impl FromStr for SystemBackupConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<SystemBackupConfigOption, ()> {
+        match s {
+            "enable" => Ok(SystemBackupConfigOption::Enable),
+            "tool" => Ok(SystemBackupConfigOption::Tool),
+            "repository" => Ok(SystemBackupConfigOption::Repository),
+            "password_file" => Ok(SystemBackupConfigOption::PasswordFile),
+            "schedule" => Ok(SystemBackupConfigOption::Schedule),
+            "config_dir" => Ok(SystemBackupConfigOption::ConfigDir),
+            "extra_paths" => Ok(SystemBackupConfigOption::ExtraPaths),
+            "keep_daily" => Ok(SystemBackupConfigOption::KeepDaily),
+            "keep_weekly" => Ok(SystemBackupConfigOption::KeepWeekly),
+            "keep_monthly" => Ok(SystemBackupConfigOption::KeepMonthly),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for SystemBackupConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            SystemBackupConfigOption::Enable => "enable",
+            SystemBackupConfigOption::Tool => "tool",
+            SystemBackupConfigOption::Repository => "repository",
+            SystemBackupConfigOption::PasswordFile => "password_file",
+            SystemBackupConfigOption::Schedule => "schedule",
+            SystemBackupConfigOption::ConfigDir => "config_dir",
+            SystemBackupConfigOption::ExtraPaths => "extra_paths",
+            SystemBackupConfigOption::KeepDaily => "keep_daily",
+            SystemBackupConfigOption::KeepWeekly => "keep_weekly",
+            SystemBackupConfigOption::KeepMonthly => "keep_monthly",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for SystemBackupService {
+    app_options!(SystemBackupConfigOption {
+        Enable => enable where validate_enable,
+        Tool => tool,
+        Repository => repository where validate_repository,
+        PasswordFile => password_file,
+        Schedule => schedule,
+        ConfigDir => config_dir,
+        ExtraPaths => extra_paths,
+        KeepDaily => keep_daily,
+        KeepWeekly => keep_weekly,
+        KeepMonthly => keep_monthly,
+    });
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering system backup config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// A backup without a repository fails on every run
+///
+/// # Errors
+///
+/// [ProjectError::InvalidValue] if the repository is empty
+fn check_repository(option: &str, repository: &str) -> Result<(), ProjectError> {
+    if !repository.trim().is_empty() {
+        return Ok(());
+    }
+
+    Err(Report::new(ProjectError::InvalidValue(
+        option.to_string(),
+        vec!["A repository is required to enable the backup".to_string()],
+    )))
+}
+
+/// Returns the directories holding the block chain and the indexes built from it
+fn chain_data_dirs(bitcoind_dir: &str, electrs_db_dir: &str, fulcrum_db_dir: &str) -> Vec<String> {
+    let bitcoind_dir = bitcoind_dir.trim_end_matches('/');
+    vec![
+        format!("{}/blocks", bitcoind_dir),
+        format!("{}/chainstate", bitcoind_dir),
+        format!("{}/indexes", bitcoind_dir),
+        electrs_db_dir.to_string(),
+        fulcrum_db_dir.to_string(),
+    ]
+}
+
+impl Default for SystemBackupService {
+    fn default() -> Self {
+        let keep = |opt: SystemBackupConfigOption, value: usize| {
+            Box::new(
+                NumberOptionData::new(
+                    opt.to_option_id(),
+                    NumberValue::UInt(Some(value)),
+                    0,
+                    1000,
+                    false,
+                    NumberValue::UInt(Some(value)),
+                )
+                .unwrap(),
+            )
+        };
+        let text = |opt: SystemBackupConfigOption, value: &str| {
+            Box::new(TextOptionData::new(
+                opt.to_option_id(),
+                value.to_string(),
+                1,
+                false,
+                value.to_string(),
+            ))
+        };
+
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                SystemBackupConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            tool: Box::new(StringListOptionData::new(
+                SystemBackupConfigOption::Tool.to_option_id(),
+                BackupTool::Restic.to_string(),
+                BackupTool::to_string_array()
+                    .map(|entry| StringListOptionItem::new(entry.to_string(), entry.to_string()))
+                    .to_vec(),
+            )),
+            repository: text(SystemBackupConfigOption::Repository, ""),
+            password_file: text(
+                SystemBackupConfigOption::PasswordFile,
+                defaults::system_backup::PASSWORD_FILE,
+            ),
+            schedule: text(
+                SystemBackupConfigOption::Schedule,
+                defaults::system_backup::SCHEDULE,
+            ),
+            config_dir: text(
+                SystemBackupConfigOption::ConfigDir,
+                defaults::system_backup::CONFIG_DIR,
+            ),
+            extra_paths: Box::new(ManualStringListOptionData::new(
+                SystemBackupConfigOption::ExtraPaths.to_option_id(),
+                vec![],
+                0,
+            )),
+            keep_daily: keep(
+                SystemBackupConfigOption::KeepDaily,
+                defaults::system_backup::KEEP_DAILY,
+            ),
+            keep_weekly: keep(
+                SystemBackupConfigOption::KeepWeekly,
+                defaults::system_backup::KEEP_WEEKLY,
+            ),
+            keep_monthly: keep(
+                SystemBackupConfigOption::KeepMonthly,
+                defaults::system_backup::KEEP_MONTHLY,
+            ),
+            exclude: chain_data_dirs(
+                defaults::bitcoind::DATA_DIR,
+                defaults::electrs::DB_DIR,
+                defaults::fulcrum::DB_DIR,
+            ),
+        }
+    }
+}
+
+impl SystemBackupService {
+    pub fn tool(&self) -> BackupTool {
+        BackupTool::from_str(self.tool.value()).unwrap_or_default()
+    }
+
+    /// Excludes the chain data of the given apps from the backup.
+    ///
+    /// # Returns
+    /// Whether the excluded directories changed
+    pub fn sync_with_chain_data(
+        &mut self,
+        bitcoind: &BitcoinDaemonService,
+        electrs: &ElectrsService,
+        fulcrum: &FulcrumService,
+    ) -> bool {
+        let exclude = chain_data_dirs(
+            bitcoind.data_dir.value(),
+            electrs.db_dir.value(),
+            fulcrum.db_dir.value(),
+        );
+
+        let changed = self.exclude != exclude;
+        self.exclude = exclude;
+
+        changed
+    }
+
+    /// The paths that are backed up
+    fn paths(&self) -> Vec<String> {
+        let mut paths = vec![
+            self.config_dir.value().to_string(),
+            "${config.nix-bitcoin.secretsDir}".to_string(),
+            defaults::nix_base::SNAPSHOT_DATA_DIR.to_string(),
+        ];
+        paths.extend(
+            self.extra_paths
+                .value()
+                .iter()
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty()),
+        );
+
+        paths
+    }
+
+    /// Starts the backup job on the node and waits until it is done
+    ///
+    /// # Errors
+    ///
+    /// [ProjectError::SystemBackupError] if the backup is disabled or fails
+    pub fn run_now(&self) -> Result<(), ProjectError> {
+        self.check_enabled()?;
+        run("systemctl", &["start", &self.tool().unit_name()])
+    }
+
+    /// Checks the integrity of the backup repository
+    ///
+    /// # Errors
+    ///
+    /// [ProjectError::SystemBackupError] if the backup is disabled or the check fails
+    pub fn verify(&self) -> Result<(), ProjectError> {
+        self.check_enabled()?;
+        run(&self.tool().wrapper(), &["check"])
+    }
+
+    fn check_enabled(&self) -> Result<(), ProjectError> {
+        if self.enable.value() {
+            return Ok(());
+        }
+
+        Err(Report::new(ProjectError::SystemBackupError)
+            .attach_printable("The system backup is disabled"))
+    }
+
+    /// The backup can only be enabled with a repository
+    fn validate_enable(&self, option: &OptionDataChangeNotification) -> Result<(), ProjectError> {
+        match option {
+            OptionDataChangeNotification::Bool(val) if val.value => check_repository(
+                &SystemBackupConfigOption::Enable.to_string(),
+                self.repository.value(),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// The repository can't be removed while the backup is enabled
+    fn validate_repository(
+        &self,
+        option: &OptionDataChangeNotification,
+    ) -> Result<(), ProjectError> {
+        match option {
+            OptionDataChangeNotification::TextEdit(val) if self.enable.value() => check_repository(
+                &SystemBackupConfigOption::Repository.to_string(),
+                &val.value,
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let quote = |list: &[String]| {
+            list.iter()
+                .map(|p| format!("\"{}\"", p))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let keep_daily = self
+            .keep_daily
+            .value()
+            .to_string_or(&defaults::system_backup::KEEP_DAILY.to_string());
+        let keep_weekly = self
+            .keep_weekly
+            .value()
+            .to_string_or(&defaults::system_backup::KEEP_WEEKLY.to_string());
+        let keep_monthly = self
+            .keep_monthly
+            .value()
+            .to_string_or(&defaults::system_backup::KEEP_MONTHLY.to_string());
+        let restic_prune = format!(
+            "\"--keep-daily {}\" \"--keep-weekly {}\" \"--keep-monthly {}\"",
+            keep_daily, keep_weekly, keep_monthly
+        );
+
+        let enable = self.enable.value();
+        let data: HashMap<&str, String> = HashMap::from([
+            (
+                "restic",
+                format!("{}", enable && self.tool() == BackupTool::Restic),
+            ),
+            (
+                "borg",
+                format!("{}", enable && self.tool() == BackupTool::Borg),
+            ),
+            ("job_name", JOB_NAME.to_string()),
+            ("repository", self.repository.value().trim().to_string()),
+            ("password_file", self.password_file.value().to_string()),
+            ("schedule", self.schedule.value().to_string()),
+            ("paths", quote(&self.paths())),
+            ("exclude", quote(&self.exclude)),
+            ("restic_prune", restic_prune),
+            ("keep_daily", keep_daily),
+            ("keep_weekly", keep_weekly),
+            ("keep_monthly", keep_monthly),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render system backup template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub fn from_json(json_data: &str) -> Result<SystemBackupService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+/// Runs the given command, its output is shown to the user
+fn run(program: &str, args: &[&str]) -> Result<(), ProjectError> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .change_context(ProjectError::SystemBackupError)
+        .attach_printable_lazy(|| format!("Unable to run {}", program))?;
+
+    if !status.success() {
+        return Err(
+            Report::new(ProjectError::SystemBackupError).attach_printable(format!(
+                "{} {} failed with {}",
+                program,
+                args.join(" "),
+                status
+            )),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::{bool_data::BoolOptionChangeData, text_edit_data::TextOptionChangeData},
+        utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn get_test_service() -> SystemBackupService {
+        let mut service = SystemBackupService::default();
+        service.enable.set_value(true);
+        service
+            .repository
+            .set_value("sftp:backup@nas:/srv/restic".into());
+        service.extra_paths.set_value(vec!["/home/admin".into()]);
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = SystemBackupService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_enable_needs_repository() {
+        let mut service = SystemBackupService::default();
+        let enable = OptionDataChangeNotification::Bool(BoolOptionChangeData::new(
+            SystemBackupConfigOption::Enable.to_option_id(),
+            true,
+        ));
+        assert!(service.app_option_changed(&enable).is_err());
+        assert!(service.run_now().is_err());
+
+        service
+            .app_option_changed(&OptionDataChangeNotification::TextEdit(
+                TextOptionChangeData::new(
+                    SystemBackupConfigOption::Repository.to_option_id(),
+                    "/mnt/backup".into(),
+                ),
+            ))
+            .unwrap();
+        assert!(service.app_option_changed(&enable).unwrap());
+
+        // the repository can't be removed while the backup is enabled
+        let res = service.app_option_changed(&OptionDataChangeNotification::TextEdit(
+            TextOptionChangeData::new(
+                SystemBackupConfigOption::Repository.to_option_id(),
+                " ".into(),
+            ),
+        ));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_sync_with_chain_data() {
+        let mut service = SystemBackupService::default();
+        let mut bitcoind = BitcoinDaemonService::default();
+        let electrs = ElectrsService::default();
+        let fulcrum = FulcrumService::default();
+        assert!(!service.sync_with_chain_data(&bitcoind, &electrs, &fulcrum));
+
+        bitcoind.data_dir.set_value("/mnt/hdd/bitcoin/".into());
+        assert!(service.sync_with_chain_data(&bitcoind, &electrs, &fulcrum));
+        assert!(service
+            .exclude
+            .contains(&"/mnt/hdd/bitcoin/blocks".to_string()));
+        assert!(service.exclude.contains(&defaults::electrs::DB_DIR.into()));
+    }
+
+    #[test]
+    fn test_render() {
+        let mut s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains("services.restic.backups.nixblitz = lib.mkIf true"));
+            assert!(data.contains("services.borgbackup.jobs.nixblitz = lib.mkIf false"));
+            assert!(data.contains("repository = \"sftp:backup@nas:/srv/restic\";"));
+            assert!(data.contains("\"/home/admin\""));
+            assert!(data.contains("\"/var/lib/bitcoind/blocks\""));
+            assert!(data.contains("\"--keep-daily 7\""));
+        }
+        assert!(result.is_ok());
+
+        s.tool.set_value(BackupTool::Borg.to_string());
+        let data = &s.render().unwrap()[TEMPLATE_FILE_NAME];
+        assert!(data.contains("services.borgbackup.jobs.nixblitz = lib.mkIf true"));
+        assert!(data.contains("daily = 7;"));
+    }
+}
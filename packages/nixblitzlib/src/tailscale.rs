@@ -0,0 +1,460 @@
+use core::fmt;
+use std::{collections::HashMap, net::IpAddr, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_option_data::{
+        bool_data::BoolOptionData,
+        manual_string_list_data::ManualStringListOptionData,
+        option_data::{
+            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToOptionId,
+        },
+        port_data::PortOptionData,
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    tor::port_or,
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/tailscale.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/tailscale.json";
+
+/// Joins the node to a Tailscale network (tailnet).
+///
+/// Makes the node reachable from the other devices of the tailnet without
+/// opening ports on the router. It can also route the local network or all
+/// traffic of the tailnet (exit node).
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TailscaleService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// File holding the auth key used to join the tailnet on first start.
+    /// Only the path ends up in the Nix store.
+    pub auth_key_file: Box<TextOptionData>,
+
+    /// The UDP port tailscaled listens on
+    pub port: Box<PortOptionData>,
+
+    /// Local subnets to route into the tailnet, e.g. 192.168.1.0/24
+    pub advertise_routes: Box<ManualStringListOptionData>,
+
+    /// Offer the node as exit node for all traffic of the tailnet
+    pub advertise_exit_node: Box<BoolOptionData>,
+
+    /// Allow logging in via Tailscale SSH
+    pub ssh: Box<BoolOptionData>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TailscaleConfigOption {
+    Enable,
+    AuthKeyFile,
+    Port,
+    AdvertiseRoutes,
+    AdvertiseExitNode,
+    Ssh,
+}
+
+impl ToOptionId for TailscaleConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::Tailscale, self.to_string())
+    }
+}
+
+impl FromStr for TailscaleConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<TailscaleConfigOption, ()> {
+        match s {
+            "enable" => Ok(TailscaleConfigOption::Enable),
+            "auth_key_file" => Ok(TailscaleConfigOption::AuthKeyFile),
+            "port" => Ok(TailscaleConfigOption::Port),
+            "advertise_routes" => Ok(TailscaleConfigOption::AdvertiseRoutes),
+            "advertise_exit_node" => Ok(TailscaleConfigOption::AdvertiseExitNode),
+            "ssh" => Ok(TailscaleConfigOption::Ssh),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for TailscaleConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            TailscaleConfigOption::Enable => "enable",
+            TailscaleConfigOption::AuthKeyFile => "auth_key_file",
+            TailscaleConfigOption::Port => "port",
+            TailscaleConfigOption::AdvertiseRoutes => "advertise_routes",
+            TailscaleConfigOption::AdvertiseExitNode => "advertise_exit_node",
+            TailscaleConfigOption::Ssh => "ssh",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for TailscaleService {
+    fn get_options(&self) -> Vec<OptionData> {
+        vec![
+            OptionData::Bool(self.enable.clone()),
+            OptionData::TextEdit(self.auth_key_file.clone()),
+            OptionData::Port(self.port.clone()),
+            OptionData::ManualStringList(self.advertise_routes.clone()),
+            OptionData::Bool(self.advertise_exit_node.clone()),
+            OptionData::Bool(self.ssh.clone()),
+        ]
+    }
+
+    fn app_option_changed(
+        &mut self,
+        option: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let id = option.id();
+        if let Ok(opt) = TailscaleConfigOption::from_str(&id.option) {
+            let mut res = Ok(false);
+            match opt {
+                TailscaleConfigOption::Enable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.enable.value() != val.value);
+                        self.enable.set_value(val.value);
+                    }
+                }
+                TailscaleConfigOption::AuthKeyFile => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.auth_key_file.value() != val.value);
+                        self.auth_key_file.set_value(val.value.clone());
+                    }
+                }
+                TailscaleConfigOption::Port => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.port.value() != val.value);
+                        self.port.set_value(val.value.clone());
+                    }
+                }
+                TailscaleConfigOption::AdvertiseRoutes => {
+                    if let OptionDataChangeNotification::ManualStringList(val) = option {
+                        check_routes(&opt.to_string(), &val.value)?;
+                        res = Ok(*self.advertise_routes.value() != val.value);
+                        self.advertise_routes.set_value(val.value.clone());
+                    }
+                }
+                TailscaleConfigOption::AdvertiseExitNode => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.advertise_exit_node.value() != val.value);
+                        self.advertise_exit_node.set_value(val.value);
+                    }
+                }
+                TailscaleConfigOption::Ssh => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.ssh.value() != val.value);
+                        self.ssh.set_value(val.value);
+                    }
+                }
+            }
+
+            return res;
+        }
+
+        Ok(false)
+    }
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering tailscale config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Checks that every route is a subnet in CIDR notation, e.g. 192.168.1.0/24
+///
+/// # Errors
+///
+/// [ProjectError::InvalidValue] listing all invalid routes
+pub(crate) fn check_routes(option: &str, routes: &[String]) -> Result<(), ProjectError> {
+    let issues: Vec<String> = routes
+        .iter()
+        .map(|r| r.trim())
+        .filter(|r| !r.is_empty() && !is_cidr(r))
+        .map(|r| format!("{} is not a subnet in CIDR notation", r))
+        .collect();
+
+    if issues.is_empty() {
+        return Ok(());
+    }
+
+    Err(Report::new(ProjectError::InvalidValue(
+        option.to_string(),
+        issues,
+    )))
+}
+
+fn is_cidr(route: &str) -> bool {
+    let Some((addr, prefix)) = route.split_once('/') else {
+        return false;
+    };
+    let (Ok(addr), Ok(prefix)) = (IpAddr::from_str(addr), prefix.parse::<u8>()) else {
+        return false;
+    };
+
+    match addr {
+        IpAddr::V4(_) => prefix <= 32,
+        IpAddr::V6(_) => prefix <= 128,
+    }
+}
+
+impl Default for TailscaleService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                TailscaleConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            auth_key_file: Box::new(TextOptionData::new(
+                TailscaleConfigOption::AuthKeyFile.to_option_id(),
+                defaults::tailscale::AUTH_KEY_FILE.to_string(),
+                1,
+                false,
+                defaults::tailscale::AUTH_KEY_FILE.to_string(),
+            )),
+            port: Box::new(PortOptionData::new(
+                TailscaleConfigOption::Port.to_option_id(),
+                NumberValue::U16(Some(defaults::tailscale::PORT)),
+            )),
+            advertise_routes: Box::new(ManualStringListOptionData::new(
+                TailscaleConfigOption::AdvertiseRoutes.to_option_id(),
+                vec![],
+                0,
+            )),
+            advertise_exit_node: Box::new(BoolOptionData::new(
+                TailscaleConfigOption::AdvertiseExitNode.to_option_id(),
+                false,
+            )),
+            ssh: Box::new(BoolOptionData::new(
+                TailscaleConfigOption::Ssh.to_option_id(),
+                false,
+            )),
+        }
+    }
+}
+
+impl TailscaleService {
+    /// The flags passed to `tailscale up`
+    fn up_flags(&self) -> Vec<String> {
+        let mut flags = vec![];
+        if !self.advertise_routes.value().is_empty() {
+            flags.push(format!(
+                "--advertise-routes={}",
+                self.advertise_routes.value().join(",")
+            ));
+        }
+        if self.advertise_exit_node.value() {
+            flags.push("--advertise-exit-node".to_string());
+        }
+        if self.ssh.value() {
+            flags.push("--ssh".to_string());
+        }
+
+        flags
+    }
+
+    /// Routing subnets or all traffic needs IP forwarding on the node
+    fn routing_features(&self) -> &'static str {
+        if !self.advertise_routes.value().is_empty() || self.advertise_exit_node.value() {
+            "server"
+        } else {
+            "none"
+        }
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let up_flags = self
+            .up_flags()
+            .iter()
+            .map(|f| format!("\"{}\"", f))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            ("auth_key_file", self.auth_key_file.value().to_string()),
+            (
+                "port",
+                port_or(self.port.value(), defaults::tailscale::PORT).to_string(),
+            ),
+            ("routing_features", self.routing_features().to_string()),
+            ("up_flags", up_flags),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render tailscale template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<TailscaleService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::manual_string_list_data::ManualStringListOptionChangeData,
+        utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn get_test_service() -> TailscaleService {
+        let mut service = TailscaleService::default();
+        service.enable.set_value(true);
+        service
+            .advertise_routes
+            .set_value(vec!["192.168.1.0/24".into(), "fd00::/64".into()]);
+        service.advertise_exit_node.set_value(true);
+        service.ssh.set_value(true);
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = TailscaleService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_advertise_routes() {
+        let mut service = TailscaleService::default();
+        let change = |value: Vec<&str>| {
+            OptionDataChangeNotification::ManualStringList(ManualStringListOptionChangeData::new(
+                TailscaleConfigOption::AdvertiseRoutes.to_option_id(),
+                value.into_iter().map(String::from).collect(),
+            ))
+        };
+
+        assert!(service
+            .app_option_changed(&change(vec!["192.168.1.0"]))
+            .is_err());
+        assert!(service
+            .app_option_changed(&change(vec!["10.0.0.0/33"]))
+            .is_err());
+        assert!(service.advertise_routes.value().is_empty());
+        assert_eq!(service.routing_features(), "none");
+
+        assert!(service
+            .app_option_changed(&change(vec!["10.0.0.0/8"]))
+            .unwrap());
+        assert_eq!(service.routing_features(), "server");
+        assert_eq!(service.up_flags(), vec!["--advertise-routes=10.0.0.0/8"]);
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains("useRoutingFeatures = \"server\";"));
+            assert!(data.contains("\"--advertise-routes=192.168.1.0/24,fd00::/64\""));
+            assert!(data.contains("\"--advertise-exit-node\""));
+            assert!(data.contains("\"--ssh\""));
+            assert!(data.contains(defaults::tailscale::AUTH_KEY_FILE));
+        }
+
+        assert!(result.is_ok());
+    }
+}
@@ -9,6 +9,18 @@
       inputs.nixpkgs.follows = "nixpkgs";
     };
     nixos-hardware.url = "github:nixos/nixos-hardware";
+    electrum-personal-server = {
+      url = "github:chris-belcher/electrum-personal-server";
+      flake = false;
+    };
+    sops-nix = {
+      url = "github:Mic92/sops-nix";
+      inputs.nixpkgs.follows = "nixpkgs";
+    };
+    agenix = {
+      url = "github:ryantm/agenix";
+      inputs.nixpkgs.follows = "nixpkgs";
+    };
   };
 
   outputs = {
@@ -19,15 +31,35 @@
     blitz-web,
     home-mgr,
     nixos-hardware,
+    electrum-personal-server,
+    sops-nix,
+    agenix,
     ...
-  }: {
+  }: let
+    # packages nixpkgs doesn't have
+    overlay = {
+      nixpkgs.overlays = [
+        (final: prev: {
+          electrum-personal-server = final.python3Packages.buildPythonApplication {
+            pname = "electrum-personal-server";
+            version = "0.2.4";
+            src = electrum-personal-server;
+            doCheck = false;
+          };
+        })
+      ];
+    };
+  in {
     nixosConfigurations.nixblitzvm = nixpkgs.lib.nixosSystem {
       system = "x86_64-linux";
       modules = [
+        overlay
         home-mgr.nixosModule
         nix-bitcoin.nixosModules.default
         blitz-api.nixosModules.default
         blitz-web.nixosModules.default
+        sops-nix.nixosModules.sops
+        agenix.nixosModules.default
         ./vm/configuration.nix
       ];
     };
@@ -36,18 +68,31 @@
       system = "aarch64-linux";
 
       modules = [
+        overlay
         home-mgr.nixosModule
         nixos-hardware.nixosModules.raspberry-pi-5
         "${nixpkgs}/nixos/modules/installer/sd-card/sd-image-aarch64.nix"
         nix-bitcoin.nixosModules.default
         blitz-api.nixosModules.default
         blitz-web.nixosModules.default
+        sops-nix.nixosModules.sops
+        agenix.nixosModules.default
         ./pi/configuration.nix
       ];
     };
 
+    nixosConfigurations.nixblitziso = nixpkgs.lib.nixosSystem {
+      system = "x86_64-linux";
+      specialArgs = {inherit self;};
+      modules = [
+        "${nixpkgs}/nixos/modules/installer/cd-dvd/installation-cd-minimal.nix"
+        ./iso/configuration.nix
+      ];
+    };
+
     images = {
       pi = self.nixosConfigurations.nixblitzpi.config.system.build.sdImage;
+      iso = self.nixosConfigurations.nixblitziso.config.system.build.isoImage;
     };
   };
 }
@@ -0,0 +1,19 @@
+# Installer image with the project preloaded, see `nixblitz build-iso`
+{
+  pkgs,
+  self,
+  ...
+}: {
+  # the project the image was built from
+  environment.etc."nixblitz/src".source = self;
+
+  # fio for `nixblitz bench-disk`
+  environment.systemPackages = with pkgs; [fio git];
+
+  nix.settings.experimental-features = ["nix-command" "flakes"];
+
+  services.getty.helpLine = ''
+    The nixblitz project is in /etc/nixblitz, copy it to a writable
+    location before installing, e.g. cp -rL /etc/nixblitz ~/nixblitz
+  '';
+}
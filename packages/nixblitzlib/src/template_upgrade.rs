@@ -0,0 +1,354 @@
+//! Upgrades the rendered nix files after the templates changed.
+//!
+//! Every nix file nixblitz writes is also remembered in [RENDERED_DIR]. When
+//! a new release ships changed templates, the remembered file is the common
+//! base of a three-way merge between the file on disk, which may contain
+//! manual edits, and the output of the new templates.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::{
+    errors::ProjectError,
+    registry,
+    utils::{create_file, load_json_file, update_file},
+    variables::Variables,
+};
+
+/// Directory inside the work dir holding the last rendered version of each
+/// nix file
+pub const RENDERED_DIR: &str = ".nixblitz/rendered";
+
+/// Scratch directory for the inputs of `git merge-file`
+const MERGE_DIR: &str = ".nixblitz/merge";
+
+const MARKER_CURRENT: &str = "<<<<<<< ";
+const MARKER_SEPARATOR: &str = "=======";
+const MARKER_NEW: &str = ">>>>>>> ";
+
+/// A part of a merged file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeSegment {
+    /// Lines both sides agree on
+    Text(String),
+
+    /// Lines changed on disk and in the new templates
+    Conflict { current: String, new: String },
+}
+
+/// How to resolve a [MergeSegment::Conflict]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Keep the lines on disk
+    Current,
+
+    /// Use the lines of the new templates
+    New,
+
+    /// Keep the lines on disk followed by the new ones
+    Both,
+}
+
+/// A nix file whose contents on disk differ from the new template output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileUpgrade {
+    /// Path of the file relative to the work dir
+    pub path: PathBuf,
+
+    /// The output of the new templates
+    pub new: String,
+
+    /// The merged contents
+    pub segments: Vec<MergeSegment>,
+}
+
+impl FileUpgrade {
+    /// Number of conflicts that must be resolved
+    pub fn conflicts(&self) -> usize {
+        self.segments
+            .iter()
+            .filter(|s| matches!(s, MergeSegment::Conflict { .. }))
+            .count()
+    }
+
+    /// Joins the segments, asking `resolve` how to handle each conflict
+    pub fn resolve(&self, mut resolve: impl FnMut(&str, &str) -> Resolution) -> String {
+        let mut res = String::new();
+        for segment in self.segments.iter() {
+            match segment {
+                MergeSegment::Text(text) => res.push_str(text),
+                MergeSegment::Conflict { current, new } => match resolve(current, new) {
+                    Resolution::Current => res.push_str(current),
+                    Resolution::New => res.push_str(new),
+                    Resolution::Both => {
+                        res.push_str(current);
+                        res.push_str(new);
+                    }
+                },
+            }
+        }
+
+        res
+    }
+}
+
+/// Remembers the rendered contents of a nix file as the base of the next upgrade
+///
+/// # Arguments
+/// * `file` - path of the nix file relative to the work dir
+pub(crate) fn remember_rendered(
+    work_dir: &Path,
+    file: &str,
+    contents: &str,
+) -> Result<(), ProjectError> {
+    create_file(
+        &work_dir.join(RENDERED_DIR).join(file),
+        contents.as_bytes(),
+        Some(true),
+    )
+}
+
+/// Renders all apps with the current templates and merges the output into
+/// the nix files on disk.
+///
+/// Files without a remembered base are merged against an empty file, every
+/// difference is a conflict then.
+///
+/// # Returns
+///
+/// The files that need to be written, empty if all files are up to date.
+pub fn plan_upgrade(work_dir: &Path) -> Result<Vec<FileUpgrade>, ProjectError> {
+    let vars = Variables::load(work_dir);
+    let mut res = vec![];
+    for reg in registry::APPS.iter() {
+        let json = load_json_file(&work_dir.join(reg.json_file_name))?;
+        let rendered: HashMap<String, String> = (reg.render)(&json)
+            .change_context(ProjectError::UpgradeError)
+            .attach_printable_lazy(|| format!("Unable to render {}", reg.title))?;
+
+        for (key, val) in rendered {
+            let path = PathBuf::from(key.replace(".templ", ""));
+            let new = vars.substitute(&val);
+            let current = fs::read_to_string(work_dir.join(&path)).unwrap_or_default();
+            if current == new {
+                continue;
+            }
+
+            let base =
+                fs::read_to_string(work_dir.join(RENDERED_DIR).join(&path)).unwrap_or_default();
+            let segments = if current == base {
+                vec![MergeSegment::Text(new.clone())]
+            } else {
+                parse_merged(&merge_file(work_dir, &current, &base, &new)?)
+            };
+
+            res.push(FileUpgrade {
+                path,
+                new,
+                segments,
+            });
+        }
+    }
+
+    Ok(res)
+}
+
+/// Writes the merged files and commits them together with the new bases
+///
+/// # Arguments
+/// * `files` - the merged contents, keyed by the upgrade they belong to
+pub fn apply_upgrade(
+    work_dir: &Path,
+    files: &[(&FileUpgrade, String)],
+    message: &str,
+) -> Result<(), ProjectError> {
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    let mut paths = vec![RENDERED_DIR.to_string()];
+    for (upgrade, contents) in files {
+        update_file(&work_dir.join(&upgrade.path), contents.as_bytes())?;
+        remember_rendered(work_dir, &upgrade.path.to_string_lossy(), &upgrade.new)?;
+        paths.push(upgrade.path.to_string_lossy().to_string());
+    }
+
+    let mut add = vec!["add", "--"];
+    add.extend(paths.iter().map(|p| p.as_str()));
+    git(work_dir, &add)?;
+    git(work_dir, &["commit", "-m", message])?;
+
+    Ok(())
+}
+
+/// Three-way merges the given contents with `git merge-file`
+fn merge_file(
+    work_dir: &Path,
+    current: &str,
+    base: &str,
+    new: &str,
+) -> Result<String, ProjectError> {
+    let dir = work_dir.join(MERGE_DIR);
+    fs::create_dir_all(&dir)
+        .change_context(ProjectError::UpgradeError)
+        .attach_printable_lazy(|| format!("Unable to create {}", dir.display()))?;
+
+    let files = [("current", current), ("base", base), ("new", new)];
+    for (name, contents) in files {
+        fs::write(dir.join(name), contents)
+            .change_context(ProjectError::UpgradeError)
+            .attach_printable_lazy(|| format!("Unable to write {}", name))?;
+    }
+
+    let output = Command::new("git")
+        .arg("merge-file")
+        .args(["-p", "-L", "current", "-L", "base", "-L", "new"])
+        .args(files.map(|(name, _)| dir.join(name)))
+        .output();
+    let _ = fs::remove_dir_all(&dir);
+
+    let output = output
+        .change_context(ProjectError::UpgradeError)
+        .attach_printable("Unable to run git merge-file")?;
+
+    // the exit code is the number of conflicts, or negative on errors
+    match output.status.code() {
+        Some(code) if (0..128).contains(&code) => {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        }
+        _ => Err(
+            Report::new(ProjectError::UpgradeError).attach_printable(format!(
+                "git merge-file failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        ),
+    }
+}
+
+/// Splits the output of `git merge-file` at its conflict markers
+fn parse_merged(merged: &str) -> Vec<MergeSegment> {
+    let mut segments = vec![];
+    let mut text = String::new();
+    let mut current = String::new();
+    let mut new = String::new();
+    // None outside of a conflict, otherwise whether we are in the new part
+    let mut in_conflict: Option<bool> = None;
+
+    for line in merged.split_inclusive('\n') {
+        match in_conflict {
+            None if line.starts_with(MARKER_CURRENT) => {
+                if !text.is_empty() {
+                    segments.push(MergeSegment::Text(std::mem::take(&mut text)));
+                }
+                in_conflict = Some(false);
+            }
+            None => text.push_str(line),
+            Some(false) if line.trim_end_matches('\n') == MARKER_SEPARATOR => {
+                in_conflict = Some(true);
+            }
+            Some(false) => current.push_str(line),
+            Some(true) if line.starts_with(MARKER_NEW) => {
+                segments.push(MergeSegment::Conflict {
+                    current: std::mem::take(&mut current),
+                    new: std::mem::take(&mut new),
+                });
+                in_conflict = None;
+            }
+            Some(true) => new.push_str(line),
+        }
+    }
+
+    if !text.is_empty() {
+        segments.push(MergeSegment::Text(text));
+    }
+
+    segments
+}
+
+fn git(work_dir: &Path, args: &[&str]) -> Result<(), ProjectError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(work_dir)
+        .args(args)
+        .output()
+        .change_context(ProjectError::UpgradeError)
+        .attach_printable("Unable to run git")?;
+
+    if !output.status.success() {
+        return Err(
+            Report::new(ProjectError::UpgradeError).attach_printable(format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{electrs, utils::init_default_project};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_merged() {
+        let merged = "a\n<<<<<<< current\nb\n=======\nc\n>>>>>>> new\nd\n";
+        let segments = parse_merged(merged);
+        assert_eq!(
+            segments,
+            vec![
+                MergeSegment::Text("a\n".into()),
+                MergeSegment::Conflict {
+                    current: "b\n".into(),
+                    new: "c\n".into()
+                },
+                MergeSegment::Text("d\n".into()),
+            ]
+        );
+
+        let upgrade = FileUpgrade {
+            path: PathBuf::new(),
+            new: String::new(),
+            segments,
+        };
+        assert_eq!(upgrade.conflicts(), 1);
+        assert_eq!(upgrade.resolve(|_, _| Resolution::New), "a\nc\nd\n");
+        assert_eq!(upgrade.resolve(|_, _| Resolution::Both), "a\nb\nc\nd\n");
+    }
+
+    #[test]
+    fn test_plan_upgrade() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+        init_default_project(work_dir, Some(false)).unwrap();
+        assert!(plan_upgrade(work_dir).unwrap().is_empty());
+
+        // an older template rendered an extra line, the user added a comment
+        let file = electrs::TEMPLATE_FILE_NAME.replace(".templ", "");
+        let new = fs::read_to_string(work_dir.join(&file)).unwrap();
+        let old = new.replacen('\n', "\n  # old template line\n", 1);
+        remember_rendered(work_dir, &file, &old).unwrap();
+        let current = format!("{}# my comment\n", old);
+        fs::write(work_dir.join(&file), &current).unwrap();
+
+        let upgrades = plan_upgrade(work_dir).unwrap();
+        assert_eq!(upgrades.len(), 1);
+        assert_eq!(upgrades[0].path, PathBuf::from(&file));
+        assert_eq!(upgrades[0].conflicts(), 0);
+        assert_eq!(
+            upgrades[0].resolve(|_, _| Resolution::Current),
+            format!("{}# my comment\n", new)
+        );
+    }
+}
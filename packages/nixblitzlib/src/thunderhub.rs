@@ -0,0 +1,509 @@
+use core::fmt;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_option_data::{
+        bool_data::BoolOptionData,
+        option_data::{
+            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToOptionId,
+        },
+        password_data::PasswordOptionData,
+        port_data::PortOptionData,
+        text_edit_data::TextOptionData,
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    lnd::LightningNetworkDaemonService,
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    reverse_proxy::{ProxyRoute, ProxyRouteProvider},
+    utils::{
+        bcrypt_hash_password, check_password_validity_confirm, update_file, update_nix_files,
+        BASE_TEMPLATE,
+    },
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/thunderhub.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/thunderhub.json";
+
+/// ThunderHub skips hashing passwords carrying this prefix and
+/// compares the remainder as a bcrypt hash.
+const HASHED_PASSWORD_PREFIX: &str = "thunderhub-";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ThunderHubService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// Port the web interface listens on
+    ///
+    /// Default: 3000
+    pub port: Box<PortOptionData>,
+
+    /// The master password to log into the LND account.
+    /// Stored as a prefixed bcrypt hash.
+    pub password: Box<PasswordOptionData>,
+
+    /// Whether to serve ThunderHub via nginx
+    pub nginx_enable: Box<BoolOptionData>,
+
+    /// The location ThunderHub is served from
+    ///
+    /// Default: "/thunderhub"
+    pub nginx_location: Box<TextOptionData>,
+
+    /// Whether to serve the location via HTTPS, see the TLS options of the
+    /// base config
+    pub nginx_https: Box<BoolOptionData>,
+
+    /// Path to the LND admin macaroon.
+    /// Derived from the LND network directory.
+    #[serde(default)]
+    pub lnd_macaroon_path: String,
+
+    /// The LND gRPC endpoint ThunderHub connects to.
+    /// Derived from the LND RPC address and port.
+    #[serde(default)]
+    pub lnd_server_url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ThunderHubConfigOption {
+    Enable,
+    Port,
+    Password,
+    NginxEnable,
+    NginxLocation,
+    NginxHttps,
+}
+
+impl ToOptionId for ThunderHubConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::ThunderHub, self.to_string())
+    }
+}
+
+impl FromStr for ThunderHubConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<ThunderHubConfigOption, ()> {
+        match s {
+            "enable" => Ok(ThunderHubConfigOption::Enable),
+            "port" => Ok(ThunderHubConfigOption::Port),
+            "password" => Ok(ThunderHubConfigOption::Password),
+            "nginx_enable" => Ok(ThunderHubConfigOption::NginxEnable),
+            "nginx_location" => Ok(ThunderHubConfigOption::NginxLocation),
+            "nginx_https" => Ok(ThunderHubConfigOption::NginxHttps),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ThunderHubConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            ThunderHubConfigOption::Enable => "enable",
+            ThunderHubConfigOption::Port => "port",
+            ThunderHubConfigOption::Password => "password",
+            ThunderHubConfigOption::NginxEnable => "nginx_enable",
+            ThunderHubConfigOption::NginxLocation => "nginx_location",
+            ThunderHubConfigOption::NginxHttps => "nginx_https",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for ThunderHubService {
+    fn get_options(&self) -> Vec<OptionData> {
+        vec![
+            OptionData::Bool(self.enable.clone()),
+            OptionData::Port(self.port.clone()),
+            OptionData::PasswordEdit(self.password.clone()),
+            OptionData::Bool(self.nginx_enable.clone()),
+            OptionData::TextEdit(self.nginx_location.clone()),
+            OptionData::Bool(self.nginx_https.clone()),
+        ]
+    }
+
+    fn app_option_changed(
+        &mut self,
+        option: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let id = option.id();
+        if let Ok(opt) = ThunderHubConfigOption::from_str(&id.option) {
+            let mut res = Ok(false);
+            match opt {
+                ThunderHubConfigOption::Enable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.enable.value() != val.value);
+                        self.enable.set_value(val.value);
+                    }
+                }
+                ThunderHubConfigOption::Port => {
+                    if let OptionDataChangeNotification::Port(val) = option {
+                        res = Ok(*self.port.value() != val.value);
+                        self.port.set_value(val.value.clone());
+                    }
+                }
+                ThunderHubConfigOption::Password => {
+                    if let OptionDataChangeNotification::PasswordEdit(val) = option {
+                        // Invalid passwords are ignored, see NixBaseConfig
+                        if check_password_validity_confirm(&val.value, &val.confirm).is_err() {
+                            return Ok(false);
+                        }
+
+                        let hashed = bcrypt_hash_password(&val.value).change_context(
+                            ProjectError::ChangeOptionValueError(opt.to_string()),
+                        )?;
+                        self.password
+                            .set_hashed_value(format!("{HASHED_PASSWORD_PREFIX}{hashed}"));
+                        res = Ok(true);
+                    }
+                }
+                ThunderHubConfigOption::NginxEnable => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.nginx_enable.value() != val.value);
+                        self.nginx_enable.set_value(val.value);
+                    }
+                }
+                ThunderHubConfigOption::NginxLocation => {
+                    if let OptionDataChangeNotification::TextEdit(val) = option {
+                        res = Ok(self.nginx_location.value() != val.value);
+                        self.nginx_location.set_value(val.value.clone());
+                    }
+                }
+                ThunderHubConfigOption::NginxHttps => {
+                    if let OptionDataChangeNotification::Bool(val) = option {
+                        res = Ok(self.nginx_https.value() != val.value);
+                        self.nginx_https.set_value(val.value);
+                    }
+                }
+            }
+
+            return res;
+        }
+
+        Ok(false)
+    }
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering thunderhub config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+
+    fn secret_options(&mut self) -> Vec<(&mut PasswordOptionData, &'static str)> {
+        vec![(&mut self.password, "lnd")]
+    }
+}
+
+impl Default for ThunderHubService {
+    fn default() -> Self {
+        Self {
+            enable: Box::new(BoolOptionData::new(
+                ThunderHubConfigOption::Enable.to_option_id(),
+                false,
+            )),
+            port: Box::new(PortOptionData::new(
+                ThunderHubConfigOption::Port.to_option_id(),
+                NumberValue::U16(Some(defaults::thunderhub::PORT)),
+            )),
+            password: Box::new(PasswordOptionData::new(
+                ThunderHubConfigOption::Password.to_option_id(),
+                "".to_string(),
+                true,
+                10,
+                false,
+                "".to_string(),
+            )),
+            nginx_enable: Box::new(BoolOptionData::new(
+                ThunderHubConfigOption::NginxEnable.to_option_id(),
+                false,
+            )),
+            nginx_location: Box::new(TextOptionData::new(
+                ThunderHubConfigOption::NginxLocation.to_option_id(),
+                defaults::thunderhub::NGINX_LOCATION.to_string(),
+                1,
+                false,
+                defaults::thunderhub::NGINX_LOCATION.to_string(),
+            )),
+            nginx_https: Box::new(BoolOptionData::new(
+                ThunderHubConfigOption::NginxHttps.to_option_id(),
+                false,
+            )),
+            lnd_macaroon_path: format!("{}/admin.macaroon", defaults::lnd::NETWORK_DIR),
+            lnd_server_url: format!("{}:{}", defaults::LOCALHOST, defaults::lnd::RPC_PORT),
+        }
+    }
+}
+
+impl ProxyRouteProvider for ThunderHubService {
+    fn proxy_route(&self) -> Option<ProxyRoute> {
+        if !self.enable.value() || !self.nginx_enable.value() {
+            return None;
+        }
+
+        let port = self
+            .port
+            .value()
+            .to_string_or(&defaults::thunderhub::PORT.to_string());
+
+        Some(ProxyRoute::new(
+            SupportedApps::ThunderHub,
+            ThunderHubConfigOption::NginxEnable.to_option_id(),
+            self.nginx_location.value(),
+            Some(format!("http://127.0.0.1:{}", port)),
+            false,
+            self.nginx_https.value(),
+        ))
+    }
+}
+
+impl ThunderHubService {
+    /// Derives the LND connection settings from the LND config.
+    ///
+    /// # Returns
+    /// Whether any of the settings changed
+    pub fn sync_with_lnd(&mut self, lnd: &LightningNetworkDaemonService) -> bool {
+        let macaroon_path = format!("{}/admin.macaroon", lnd.network_dir.value());
+        let address = lnd.rpc_address.value().unwrap_or(defaults::LOCALHOST);
+        let port = lnd
+            .rpc_port
+            .value()
+            .to_string_or(&defaults::lnd::RPC_PORT.to_string());
+        let server_url = format!("{}:{}", address, port);
+
+        let changed = self.lnd_macaroon_path != macaroon_path || self.lnd_server_url != server_url;
+        self.lnd_macaroon_path = macaroon_path;
+        self.lnd_server_url = server_url;
+
+        changed
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let port = self
+            .port
+            .value()
+            .to_string_or(&defaults::thunderhub::PORT.to_string());
+        // ThunderHub needs to know the sub path it is served from behind nginx
+        let base_path = if self.nginx_enable.value() {
+            self.nginx_location
+                .value()
+                .trim_end_matches('/')
+                .to_string()
+        } else {
+            "".to_string()
+        };
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            ("port", port),
+            ("password", self.password.hashed_value().clone()),
+            (
+                "password_file",
+                self.password
+                    .secret()
+                    .and_then(|s| s.nix_path())
+                    .unwrap_or_default(),
+            ),
+            ("base_path", base_path),
+            ("lnd_macaroon_path", self.lnd_macaroon_path.clone()),
+            ("lnd_server_url", self.lnd_server_url.clone()),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render thunderhub template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<ThunderHubService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, net::IpAddr, str::FromStr};
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::password_data::PasswordOptionChangeData,
+        secret_store::{SecretBackend, SecretRef},
+        utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn get_test_service() -> ThunderHubService {
+        let mut service = ThunderHubService::default();
+        service.enable.set_value(true);
+        service.nginx_enable.set_value(true);
+        service.port.set_value(NumberValue::U16(Some(3010)));
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = ThunderHubService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_password_is_hashed() {
+        let mut service = get_test_service();
+        let res = service
+            .app_option_changed(&OptionDataChangeNotification::PasswordEdit(
+                PasswordOptionChangeData::new(
+                    ThunderHubConfigOption::Password.to_option_id(),
+                    "my_secret_password".into(),
+                    Some("my_secret_password".into()),
+                ),
+            ))
+            .unwrap();
+        assert!(res);
+
+        let hashed = service.password.hashed_value();
+        let hash = hashed.strip_prefix(HASHED_PASSWORD_PREFIX).unwrap();
+        assert!(bcrypt::verify("my_secret_password", hash).unwrap());
+    }
+
+    #[test]
+    fn test_sync_with_lnd() {
+        let mut service = get_test_service();
+        let mut lnd = LightningNetworkDaemonService::default();
+        assert!(!service.sync_with_lnd(&lnd));
+
+        lnd.network_dir.set_value("/mnt/hdd/lnd/mainnet".into());
+        lnd.rpc_address
+            .set_value(Some(IpAddr::from_str("10.0.0.2").unwrap()));
+        assert!(service.sync_with_lnd(&lnd));
+        assert_eq!(
+            service.lnd_macaroon_path,
+            "/mnt/hdd/lnd/mainnet/admin.macaroon"
+        );
+        assert_eq!(
+            service.lnd_server_url,
+            format!("10.0.0.2:{}", defaults::lnd::RPC_PORT)
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains("PORT = \"3010\";"));
+            assert!(data.contains(&format!("macaroonPath: \"{}\"", s.lnd_macaroon_path)));
+            assert!(data.contains(&format!("serverUrl: \"{}\"", s.lnd_server_url)));
+            assert!(data.contains("BASE_PATH = \"/thunderhub\";"));
+            assert!(data.contains("masterPassword: \""));
+        }
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_render_stored_password() {
+        let mut s = get_test_service();
+        s.password.set_secret(SecretRef {
+            backend: SecretBackend::Sops,
+            name: "thunderhub-password".into(),
+            owner: "lnd".into(),
+        });
+
+        let data = &s.render().unwrap()[TEMPLATE_FILE_NAME];
+        assert_eq!(data.matches("masterPassword").count(), 1);
+        assert!(data.contains("echo \"masterPassword: \\\"$(cat ${"));
+        assert!(data.contains("config.sops.secrets.\"thunderhub-password\".path"));
+    }
+}
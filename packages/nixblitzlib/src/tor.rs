@@ -0,0 +1,589 @@
+use core::fmt;
+use std::{collections::HashMap, fs, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+use handlebars::{no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_option_data::{
+        bool_data::BoolOptionData,
+        option_data::{
+            GetOptionId, OptionData, OptionDataChangeNotification, OptionId, ToOptionId,
+        },
+    },
+    apps::SupportedApps,
+    defaults,
+    errors::{ProjectError, TemplatingError},
+    migrations,
+    nix_format::format_nix,
+    number_value::NumberValue,
+    utils::{update_file, update_nix_files, BASE_TEMPLATE},
+};
+
+pub const TEMPLATE_FILE_NAME: &str = "src/apps/tor.nix.templ";
+pub const JSON_FILE_NAME: &str = "src/apps/tor.json";
+
+/// An onion service an app wants Tor to provide
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HiddenService {
+    /// The app the service belongs to
+    pub app: SupportedApps,
+
+    /// Name of the service, also the name of its directory in the Tor state dir
+    pub name: String,
+
+    /// The port the service is reachable on via Tor
+    pub port: u16,
+
+    /// The local port Tor forwards the connections to
+    pub target_port: u16,
+
+    /// Whether nix-bitcoin manages the service. This makes sure the node
+    /// announces its onion address, e.g. to the lightning network.
+    pub nix_bitcoin: bool,
+}
+
+impl HiddenService {
+    /// Creates a service reachable via Tor on the same port it listens on locally
+    pub fn new(app: SupportedApps, name: &str, port: u16, nix_bitcoin: bool) -> Self {
+        Self {
+            app,
+            name: name.to_string(),
+            port,
+            target_port: port,
+            nix_bitcoin,
+        }
+    }
+}
+
+/// Returns the port of the given value, the default if it is unset
+pub(crate) fn port_or(value: &NumberValue, default: u16) -> u16 {
+    match value {
+        NumberValue::U16(Some(port)) => *port,
+        _ => default,
+    }
+}
+
+/// Implemented by apps that can be reached via an onion service
+pub trait HiddenServiceProvider {
+    /// Returns the onion service of the app, None if the app is disabled
+    fn hidden_service(&self) -> Option<HiddenService>;
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TorService {
+    /// Whether the service is enabled or not
+    pub enable: Box<BoolOptionData>,
+
+    /// Whether to run the Tor SOCKS proxy for outgoing connections
+    pub client_enable: Box<BoolOptionData>,
+
+    /// Whether to expose Bitcoin Core as an onion service
+    pub onion_bitcoind: Box<BoolOptionData>,
+
+    /// Whether to expose LND as an onion service
+    pub onion_lnd: Box<BoolOptionData>,
+
+    /// Whether to expose Core Lightning as an onion service
+    pub onion_cln: Box<BoolOptionData>,
+
+    /// Whether to expose the Blitz API as an onion service
+    pub onion_blitz_api: Box<BoolOptionData>,
+
+    /// Whether to expose the Web UI as an onion service
+    pub onion_web_ui: Box<BoolOptionData>,
+
+    /// Whether nix fetches from binary caches via the Tor SOCKS proxy only
+    pub nix_over_tor: Box<BoolOptionData>,
+
+    /// Whether nix builds packages itself when the binary caches can't be
+    /// reached via Tor. Only used if [TorService::nix_over_tor] is set.
+    pub nix_fallback: Box<BoolOptionData>,
+
+    /// Whether nix-bitcoin runs its services in separate network namespaces,
+    /// see [TorService::netns_isolation]
+    pub netns_isolation: Box<BoolOptionData>,
+
+    /// The onion services registered by the enabled apps.
+    /// Kept in sync by the project, see [TorService::sync_hidden_services].
+    #[serde(default)]
+    pub hidden_services: Vec<HiddenService>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TorConfigOption {
+    Enable,
+    ClientEnable,
+    OnionBitcoind,
+    OnionLnd,
+    OnionCln,
+    OnionBlitzApi,
+    OnionWebUi,
+    NixOverTor,
+    NixFallback,
+    NetnsIsolation,
+}
+
+impl ToOptionId for TorConfigOption {
+    fn to_option_id(&self) -> OptionId {
+        OptionId::new(SupportedApps::Tor, self.to_string())
+    }
+}
+
+impl FromStr for TorConfigOption {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<TorConfigOption, ()> {
+        match s {
+            "enable" => Ok(TorConfigOption::Enable),
+            "client_enable" => Ok(TorConfigOption::ClientEnable),
+            "onion_bitcoind" => Ok(TorConfigOption::OnionBitcoind),
+            "onion_lnd" => Ok(TorConfigOption::OnionLnd),
+            "onion_cln" => Ok(TorConfigOption::OnionCln),
+            "onion_blitz_api" => Ok(TorConfigOption::OnionBlitzApi),
+            "onion_web_ui" => Ok(TorConfigOption::OnionWebUi),
+            "nix_over_tor" => Ok(TorConfigOption::NixOverTor),
+            "nix_fallback" => Ok(TorConfigOption::NixFallback),
+            "netns_isolation" => Ok(TorConfigOption::NetnsIsolation),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for TorConfigOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let option_str = match self {
+            TorConfigOption::Enable => "enable",
+            TorConfigOption::ClientEnable => "client_enable",
+            TorConfigOption::OnionBitcoind => "onion_bitcoind",
+            TorConfigOption::OnionLnd => "onion_lnd",
+            TorConfigOption::OnionCln => "onion_cln",
+            TorConfigOption::OnionBlitzApi => "onion_blitz_api",
+            TorConfigOption::OnionWebUi => "onion_web_ui",
+            TorConfigOption::NixOverTor => "nix_over_tor",
+            TorConfigOption::NixFallback => "nix_fallback",
+            TorConfigOption::NetnsIsolation => "netns_isolation",
+        };
+        write!(f, "{}", option_str)
+    }
+}
+
+impl AppConfig for TorService {
+    fn get_options(&self) -> Vec<OptionData> {
+        vec![
+            OptionData::Bool(self.enable.clone()),
+            OptionData::Bool(self.client_enable.clone()),
+            OptionData::Bool(self.onion_bitcoind.clone()),
+            OptionData::Bool(self.onion_lnd.clone()),
+            OptionData::Bool(self.onion_cln.clone()),
+            OptionData::Bool(self.onion_blitz_api.clone()),
+            OptionData::Bool(self.onion_web_ui.clone()),
+            OptionData::Bool(self.nix_over_tor.clone()),
+            OptionData::Bool(self.nix_fallback.clone()),
+            OptionData::Bool(self.netns_isolation.clone()),
+        ]
+    }
+
+    fn app_option_changed(
+        &mut self,
+        option: &OptionDataChangeNotification,
+    ) -> Result<bool, ProjectError> {
+        let id = option.id();
+        if let Ok(opt) = TorConfigOption::from_str(&id.option) {
+            let OptionDataChangeNotification::Bool(val) = option else {
+                return Ok(false);
+            };
+
+            let target = match opt {
+                TorConfigOption::Enable => &mut self.enable,
+                TorConfigOption::ClientEnable => &mut self.client_enable,
+                TorConfigOption::OnionBitcoind => &mut self.onion_bitcoind,
+                TorConfigOption::OnionLnd => &mut self.onion_lnd,
+                TorConfigOption::OnionCln => &mut self.onion_cln,
+                TorConfigOption::OnionBlitzApi => &mut self.onion_blitz_api,
+                TorConfigOption::OnionWebUi => &mut self.onion_web_ui,
+                TorConfigOption::NixOverTor => &mut self.nix_over_tor,
+                TorConfigOption::NixFallback => &mut self.nix_fallback,
+                TorConfigOption::NetnsIsolation => &mut self.netns_isolation,
+            };
+            let res = target.value() != val.value;
+            target.set_value(val.value);
+
+            return Ok(res);
+        }
+
+        Ok(false)
+    }
+
+    fn save(&mut self, work_dir: &Path) -> Result<(), ProjectError> {
+        let rendered_json = self
+            .to_json_string()
+            .change_context(ProjectError::GenFilesError)?;
+        let rendered_nix = self.render().change_context(ProjectError::CreateBaseFiles(
+            "Failed at rendering tor config".to_string(),
+        ))?;
+
+        update_nix_files(work_dir, &rendered_nix)?;
+
+        update_file(
+            Path::new(&work_dir.join(JSON_FILE_NAME)),
+            rendered_json.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Default for TorService {
+    fn default() -> Self {
+        let bool_option = |opt: TorConfigOption, value: bool| {
+            Box::new(BoolOptionData::new(opt.to_option_id(), value))
+        };
+
+        Self {
+            enable: bool_option(TorConfigOption::Enable, false),
+            client_enable: bool_option(TorConfigOption::ClientEnable, true),
+            onion_bitcoind: bool_option(TorConfigOption::OnionBitcoind, false),
+            onion_lnd: bool_option(TorConfigOption::OnionLnd, true),
+            onion_cln: bool_option(TorConfigOption::OnionCln, true),
+            onion_blitz_api: bool_option(TorConfigOption::OnionBlitzApi, false),
+            onion_web_ui: bool_option(TorConfigOption::OnionWebUi, false),
+            nix_over_tor: bool_option(TorConfigOption::NixOverTor, false),
+            nix_fallback: bool_option(TorConfigOption::NixFallback, true),
+            netns_isolation: bool_option(TorConfigOption::NetnsIsolation, false),
+            hidden_services: vec![],
+        }
+    }
+}
+
+impl TorService {
+    /// Whether the user wants the given app to be reachable via Tor
+    pub fn is_exposed(&self, app: SupportedApps) -> bool {
+        match app {
+            SupportedApps::BitcoinCore => self.onion_bitcoind.value(),
+            SupportedApps::LND => self.onion_lnd.value(),
+            SupportedApps::CoreLightning => self.onion_cln.value(),
+            SupportedApps::BlitzAPI => self.onion_blitz_api.value(),
+            SupportedApps::WebUI => self.onion_web_ui.value(),
+            _ => false,
+        }
+    }
+
+    /// The proxy nix has to use to fetch from the binary caches, None if nix
+    /// fetches via clearnet. Requires Tor and its SOCKS proxy to be enabled.
+    pub fn nix_proxy(&self) -> Option<String> {
+        if !self.enable.value() || !self.client_enable.value() || !self.nix_over_tor.value() {
+            return None;
+        }
+
+        // socks5h resolves the host names via Tor as well
+        Some(format!(
+            "socks5h://{}:{}",
+            defaults::LOCALHOST,
+            defaults::tor::SOCKS_PORT
+        ))
+    }
+
+    /// Whether nix-bitcoin isolates its services in network namespaces.
+    /// Every service gets its own address and may only reach the services
+    /// it depends on. The services then ignore the addresses set in their
+    /// apps. Requires Tor to be enabled.
+    pub fn netns_isolation(&self) -> bool {
+        self.enable.value() && self.netns_isolation.value()
+    }
+
+    /// The environment a nix command run on the node needs to honor
+    /// [TorService::nix_proxy], empty if nix fetches via clearnet
+    pub fn nix_proxy_env(&self) -> Vec<(&'static str, String)> {
+        match self.nix_proxy() {
+            Some(proxy) => vec![("ALL_PROXY", proxy)],
+            None => vec![],
+        }
+    }
+
+    /// Replaces the registered onion services with the ones of the given apps.
+    ///
+    /// # Returns
+    /// Whether the registered services changed
+    pub fn sync_hidden_services(&mut self, providers: &[&dyn HiddenServiceProvider]) -> bool {
+        let services: Vec<HiddenService> = providers
+            .iter()
+            .filter_map(|p| p.hidden_service())
+            .collect();
+
+        let changed = self.hidden_services != services;
+        self.hidden_services = services;
+
+        changed
+    }
+
+    /// The registered onion services the user chose to expose
+    pub fn exposed_services(&self) -> Vec<&HiddenService> {
+        if !self.enable.value() {
+            return vec![];
+        }
+
+        self.hidden_services
+            .iter()
+            .filter(|s| self.is_exposed(s.app))
+            .collect()
+    }
+
+    /// Reads the onion addresses Tor generated for the exposed services.
+    ///
+    /// Tor creates the addresses when the service starts for the first time,
+    /// so services that never ran on the node are missing from the result.
+    ///
+    /// # Parameters
+    ///
+    /// - `state_dir`: The Tor state dir, usually [defaults::tor::STATE_DIR]
+    pub fn onion_hostnames(&self, state_dir: &Path) -> Vec<(SupportedApps, String)> {
+        self.exposed_services()
+            .into_iter()
+            .filter_map(|s| {
+                let path = state_dir.join("onion").join(&s.name).join("hostname");
+                let hostname = fs::read_to_string(path).ok()?;
+                Some((s.app, hostname.trim().to_string()))
+            })
+            .collect()
+    }
+
+    pub fn render(&self) -> Result<HashMap<String, String>, TemplatingError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(no_escape);
+
+        let mut rendered_contents = HashMap::new();
+        let file = BASE_TEMPLATE.get_file(TEMPLATE_FILE_NAME);
+        let file = match file {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!("File {TEMPLATE_FILE_NAME} not found in template")))
+            }
+        };
+
+        let file = match file.contents_utf8() {
+            Some(f) => f,
+            None => {
+                return Err(Report::new(TemplatingError::FileNotFound(
+                    TEMPLATE_FILE_NAME.to_string(),
+                ))
+                .attach_printable(format!(
+                    "Unable to read file contents of {TEMPLATE_FILE_NAME}"
+                )))
+            }
+        };
+
+        handlebars
+            .register_template_string(TEMPLATE_FILE_NAME, file)
+            .attach_printable_lazy(|| format!("{handlebars:?} could not register the template"))
+            .change_context(TemplatingError::Register)?;
+
+        let (managed, relay): (Vec<&HiddenService>, Vec<&HiddenService>) = self
+            .exposed_services()
+            .into_iter()
+            .partition(|s| s.nix_bitcoin);
+        let nix_bitcoin_services = managed
+            .iter()
+            .map(|s| format!("{}.public = true;", s.name))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let onion_services = relay
+            .iter()
+            .map(|s| {
+                format!(
+                    "\"{}\".map = [{{ port = {}; target = {{ addr = \"{}\"; port = {}; }}; }}];",
+                    s.name,
+                    s.port,
+                    defaults::LOCALHOST,
+                    s.target_port
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let data: HashMap<&str, String> = HashMap::from([
+            ("enable", format!("{}", self.enable.value())),
+            ("client_enable", format!("{}", self.client_enable.value())),
+            ("nix_bitcoin_services", nix_bitcoin_services),
+            ("onion_services", onion_services),
+            ("nix_over_tor", format!("{}", self.nix_proxy().is_some())),
+            ("nix_proxy", self.nix_proxy().unwrap_or_default()),
+            ("nix_fallback", format!("{}", self.nix_fallback.value())),
+            ("netns_isolation", format!("{}", self.netns_isolation())),
+        ]);
+
+        let res = handlebars
+            .render(TEMPLATE_FILE_NAME, &data)
+            .attach_printable("Failed to render tor template".to_string())
+            .change_context(TemplatingError::Render)?;
+
+        rendered_contents.insert(
+            TEMPLATE_FILE_NAME.to_string(),
+            format_nix(TEMPLATE_FILE_NAME, res),
+        );
+
+        Ok(rendered_contents)
+    }
+
+    pub(crate) fn to_json_string(&self) -> Result<String, TemplatingError> {
+        migrations::to_json_string(self)
+    }
+
+    pub(crate) fn from_json(json_data: &str) -> Result<TorService, TemplatingError> {
+        serde_json::from_str(json_data).change_context(TemplatingError::JsonLoadError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::bool_data::BoolOptionChangeData, blitz_api::BlitzApiService,
+        lnd::LightningNetworkDaemonService, utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn get_test_service() -> TorService {
+        let mut service = TorService::default();
+        service.enable.set_value(true);
+        service.onion_blitz_api.set_value(true);
+
+        let mut lnd = LightningNetworkDaemonService::default();
+        lnd.enable.set_value(true);
+        let mut blitz_api = BlitzApiService::default();
+        blitz_api.enable.set_value(true);
+        service.sync_hidden_services(&[&lnd, &blitz_api]);
+
+        service
+    }
+
+    #[test]
+    fn test_save_function() {
+        let temp_dir = tempdir().unwrap();
+        let work_dir = temp_dir.path();
+
+        let _ = init_default_project(work_dir, Some(false));
+        let mut service = get_test_service();
+
+        let result = service.save(work_dir);
+        assert!(result.is_ok());
+
+        let json_content = fs::read_to_string(work_dir.join(JSON_FILE_NAME)).unwrap();
+        assert_eq!(json_content, service.to_json_string().unwrap());
+
+        let nix_file_path = work_dir.join(TEMPLATE_FILE_NAME.replace(".templ", ""));
+        let rendered_nix = service.render().unwrap();
+        let expected_nix_content = rendered_nix.get(TEMPLATE_FILE_NAME).unwrap();
+        let nix_content = fs::read_to_string(nix_file_path).unwrap();
+        assert_eq!(nix_content, *expected_nix_content);
+    }
+
+    #[test]
+    fn test_from_json_string() {
+        let source = get_test_service();
+        let data = source.to_json_string().unwrap();
+
+        let target = TorService::from_json(&data).unwrap();
+        assert!(source == target);
+    }
+
+    #[test]
+    fn test_sync_hidden_services() {
+        let mut service = get_test_service();
+        assert_eq!(service.hidden_services.len(), 2);
+        assert_eq!(service.exposed_services().len(), 2);
+
+        let lnd = LightningNetworkDaemonService::default();
+        assert!(service.sync_hidden_services(&[&lnd]));
+        assert!(service.hidden_services.is_empty());
+        assert!(!service.sync_hidden_services(&[&lnd]));
+    }
+
+    #[test]
+    fn test_toggle_onion_service() {
+        let mut service = get_test_service();
+        let res = service.app_option_changed(&OptionDataChangeNotification::Bool(
+            BoolOptionChangeData::new(TorConfigOption::OnionLnd.to_option_id(), false),
+        ));
+        assert!(res.unwrap());
+        assert!(!service.is_exposed(SupportedApps::LND));
+        assert_eq!(service.exposed_services().len(), 1);
+
+        service.enable.set_value(false);
+        assert!(service.exposed_services().is_empty());
+    }
+
+    #[test]
+    fn test_nix_over_tor() {
+        let mut service = get_test_service();
+        assert_eq!(service.nix_proxy(), None);
+        assert!(service.nix_proxy_env().is_empty());
+
+        service.nix_over_tor.set_value(true);
+        assert_eq!(
+            service.nix_proxy(),
+            Some("socks5h://127.0.0.1:9050".to_string())
+        );
+        let data = &service.render().unwrap()[TEMPLATE_FILE_NAME];
+        assert!(data.contains("ALL_PROXY = \"socks5h://127.0.0.1:9050\";"));
+        assert!(data.contains("fallback = lib.mkIf true true;"));
+
+        // no SOCKS proxy to route nix through
+        service.client_enable.set_value(false);
+        assert_eq!(service.nix_proxy(), None);
+    }
+
+    #[test]
+    fn test_netns_isolation() {
+        let mut service = get_test_service();
+        let render = |s: &TorService| s.render().unwrap()[TEMPLATE_FILE_NAME].clone();
+        assert!(render(&service).contains("netns-isolation.enable = false;"));
+
+        service.netns_isolation.set_value(true);
+        assert!(service.netns_isolation());
+        assert!(render(&service).contains("netns-isolation.enable = true;"));
+
+        service.enable.set_value(false);
+        assert!(!service.netns_isolation());
+    }
+
+    #[test]
+    fn test_onion_hostnames() {
+        let temp_dir = tempdir().unwrap();
+        let state_dir = temp_dir.path();
+        let service = get_test_service();
+
+        let lnd_dir = state_dir.join("onion").join("lnd");
+        fs::create_dir_all(&lnd_dir).unwrap();
+        fs::write(lnd_dir.join("hostname"), "abcdef.onion\n").unwrap();
+
+        assert_eq!(
+            service.onion_hostnames(state_dir),
+            vec![(SupportedApps::LND, "abcdef.onion".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let s = get_test_service();
+
+        let result = s.render();
+        if let Ok(data) = &result {
+            assert!(&data.contains_key(TEMPLATE_FILE_NAME));
+            let data = &data[TEMPLATE_FILE_NAME];
+            assert!(data.contains("enable = true;"));
+            assert!(data.contains("lnd.public = true;"));
+            assert!(data.contains("\"blitz-api\".map"));
+            assert!(data.contains("port = 80;"));
+        }
+
+        assert!(result.is_ok());
+    }
+}
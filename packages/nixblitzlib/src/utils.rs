@@ -1,7 +1,10 @@
 use std::{
+    collections::HashMap,
+    ffi::OsString,
     fmt::Display,
     fs::{self, File},
     io::{Read, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
     path::{Path, PathBuf},
 };
 
@@ -9,14 +12,12 @@ use error_stack::{Report, Result, ResultExt};
 use include_dir::{include_dir, Dir};
 
 use crate::{
-    bitcoind::BitcoinDaemonService,
-    blitz_api::BlitzApiService,
-    blitz_webui::BlitzWebUiService,
-    cln::CoreLightningService,
     errors::{PasswordError, ProjectError},
-    lnd::LightningNetworkDaemonService,
-    nix_base_config::{NixBaseConfig, NixBaseConfigsTemplates},
+    extra_nix, registry, reverse_proxy, secret_store, template_upgrade,
+    variables::Variables,
 };
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
 use sha_crypt::{sha512_simple, Sha512Params};
 
 pub struct AutoLineString(String);
@@ -90,6 +91,19 @@ pub fn unix_hash_password(pw: &str) -> Result<String, PasswordError> {
     Ok(hashed_pw)
 }
 
+/// Hashes a password using bcrypt with the default cost.
+///
+/// Used for apps that verify passwords with bcrypt instead of crypt(3),
+/// e.g. ThunderHub.
+///
+/// # Errors
+/// * `PasswordError::HashingError` - If bcrypt fails to hash the password.
+pub fn bcrypt_hash_password(pw: &str) -> Result<String, PasswordError> {
+    bcrypt::hash(pw, bcrypt::DEFAULT_COST)
+        .change_context(PasswordError::HashingError)
+        .attach_printable("Unable to hash the password with bcrypt")
+}
+
 /// Checks the validity of a password by ensuring it matches the confirmation and is longer than 10 characters.
 ///
 /// # Arguments
@@ -130,6 +144,49 @@ pub fn check_password_validity_confirm(
     Ok(())
 }
 
+/// Generates a random, hex encoded password.
+///
+/// # Arguments
+/// * `num_bytes` - The number of random bytes to use. The resulting string
+///   will be twice as long.
+///
+/// # Errors
+/// * `PasswordError::RandomGenerationError` - If the OS random number
+///   generator is not available.
+pub fn generate_random_password(num_bytes: usize) -> Result<String, PasswordError> {
+    let mut buf = vec![0u8; num_bytes];
+    getrandom::getrandom(&mut buf).change_context(PasswordError::RandomGenerationError)?;
+
+    Ok(to_hex(&buf))
+}
+
+/// Creates the password HMAC for a bitcoind `rpcauth` entry.
+///
+/// Mirrors bitcoin's `share/rpcauth/rpcauth.py`: a random hex salt is used as
+/// the key for a HMAC-SHA-256 over the password.
+///
+/// # Returns
+/// * `Ok(String)` - The HMAC in the format `<SALT-HEX>$<HMAC-HEX>`.
+///
+/// # Errors
+/// * `PasswordError::RandomGenerationError` - If the salt cannot be generated.
+pub fn create_rpcauth_hmac(password: &str) -> Result<String, PasswordError> {
+    let salt = generate_random_password(16)?;
+    let hmac = hmac_sha256(salt.as_bytes(), password.as_bytes());
+
+    Ok(format!("{}${}", salt, to_hex(&hmac)))
+}
+
+fn hmac_sha256(key: &[u8], msg: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(msg);
+    mac.finalize().into_bytes().into()
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 fn safety_checks(work_dir: &Path) -> Result<(), ProjectError> {
     if !work_dir.exists() {
         return Ok(());
@@ -198,197 +255,38 @@ fn render_template_files(
     force: Option<bool>,
 ) -> Result<(), ProjectError> {
     for path in templ_files {
-        let filename = path
-            .file_stem()
-            .and_then(|stem| stem.to_str())
-            .unwrap_or("");
-        if filename.is_empty() {
-            return Err(Report::new(ProjectError::GenFilesError))
-                .attach_lazy(|| format!("Unable to get filename from path: {}", filename));
-        } else if filename == "cln.nix" {
-            _create_cln_files(work_dir, force)?;
-        } else if filename == "lnd.nix" {
-            _create_lnd_files(work_dir, force)?;
-        } else if filename == "bitcoind.nix" {
-            _create_bitcoin_files(work_dir, force)?;
-        } else if filename == "configuration.common.nix" {
-            _create_nix_base_config(work_dir, force)?;
-        } else if filename == "blitz_api.nix" {
-            _create_blitz_api_files(work_dir, force)?;
-        } else if filename == "blitz_web.nix" {
-            _create_blitz_webui_files(work_dir, force)?;
+        if let Some(reg) = registry::registration_by_template(work_dir, &path) {
+            (reg.create_files)(work_dir, force)?;
+            extra_nix::create_default(work_dir, reg.app)?;
+        } else if path == work_dir.join(reverse_proxy::TEMPLATE_FILE_NAME) {
+            reverse_proxy::create_default_file(work_dir, force)?;
+        } else if path == work_dir.join(secret_store::TEMPLATE_FILE_NAME) {
+            secret_store::create_default_file(work_dir, force)?;
         }
     }
 
     Ok(())
 }
 
-fn _create_bitcoin_files(work_dir: &Path, force: Option<bool>) -> Result<(), ProjectError> {
-    let bitcoin_cfg = BitcoinDaemonService::default();
-    let rendered_json = bitcoin_cfg
-        .to_json_string()
-        .change_context(ProjectError::GenFilesError)?;
-    let rendered_nix = bitcoin_cfg
-        .render()
-        .change_context(ProjectError::CreateBaseFiles(
-            "Failed at rendering bitcoind config".to_string(),
-        ))?;
-
-    for (key, val) in rendered_nix.iter() {
-        create_file(
-            Path::new(&work_dir.join(key.replace(".templ", ""))),
-            val.as_bytes(),
-            force,
-        )?;
-    }
-
-    create_file(
-        Path::new(&work_dir.join("src/apps/bitcoind.json")),
-        rendered_json.as_bytes(),
-        force,
-    )?;
-
-    Ok(())
-}
-
-fn _create_blitz_webui_files(work_dir: &Path, force: Option<bool>) -> Result<(), ProjectError> {
-    let blitz_webui_cfg = BlitzWebUiService::default();
-    let rendered_json = blitz_webui_cfg
-        .to_json_string()
-        .change_context(ProjectError::GenFilesError)?;
-    let rendered_nix = blitz_webui_cfg
-        .render()
-        .change_context(ProjectError::CreateBaseFiles(
-            "Failed at rendering blitz web ui config".to_string(),
-        ))?;
-
-    for (key, val) in rendered_nix.iter() {
-        create_file(
-            Path::new(&work_dir.join(key.replace(".templ", ""))),
-            val.as_bytes(),
-            force,
-        )?;
-    }
-
-    create_file(
-        Path::new(&work_dir.join("src/apps/blitz_web.json")),
-        rendered_json.as_bytes(),
-        force,
-    )?;
-
-    Ok(())
-}
-
-fn _create_blitz_api_files(work_dir: &Path, force: Option<bool>) -> Result<(), ProjectError> {
-    let blitz_api_cfg = BlitzApiService::default();
-    let rendered_json = blitz_api_cfg
-        .to_json_string()
-        .change_context(ProjectError::GenFilesError)?;
-    let rendered_nix = blitz_api_cfg
-        .render()
-        .change_context(ProjectError::CreateBaseFiles(
-            "Failed at rendering blitz api config".to_string(),
-        ))?;
-
-    for (key, val) in rendered_nix.iter() {
-        create_file(
-            Path::new(&work_dir.join(key.replace(".templ", ""))),
-            val.as_bytes(),
-            force,
-        )?;
-    }
-
-    create_file(
-        Path::new(&work_dir.join("src/apps/blitz_api.json")),
-        rendered_json.as_bytes(),
-        force,
-    )?;
-
-    Ok(())
-}
-
-fn _create_cln_files(work_dir: &Path, force: Option<bool>) -> Result<(), ProjectError> {
-    let cln_cfg = CoreLightningService::default();
-    let rendered_json = cln_cfg
-        .to_json_string()
-        .change_context(ProjectError::GenFilesError)?;
-    let rendered_nix = cln_cfg
-        .render()
-        .change_context(ProjectError::CreateBaseFiles(
-            "Failed at rendering cln config".to_string(),
-        ))?;
-
-    for (key, val) in rendered_nix.iter() {
-        create_file(
-            Path::new(&work_dir.join(key.replace(".templ", ""))),
-            val.as_bytes(),
-            force,
-        )?;
-    }
-
-    create_file(
-        Path::new(&work_dir.join("src/apps/cln.json")),
-        rendered_json.as_bytes(),
-        force,
-    )?;
-
-    Ok(())
-}
-
-fn _create_lnd_files(work_dir: &Path, force: Option<bool>) -> Result<(), ProjectError> {
-    let lnd_cfg = LightningNetworkDaemonService::default();
-    let rendered_json = lnd_cfg
-        .to_json_string()
-        .change_context(ProjectError::GenFilesError)?;
-    let rendered_nix = lnd_cfg
-        .render()
-        .change_context(ProjectError::CreateBaseFiles(
-            "Failed at rendering lnd config".to_string(),
-        ))?;
-
-    for (key, val) in rendered_nix.iter() {
-        create_file(
-            Path::new(&work_dir.join(key.replace(".templ", ""))),
-            val.as_bytes(),
-            force,
-        )?;
-    }
-
-    create_file(
-        Path::new(&work_dir.join("src/apps/lnd.json")),
-        rendered_json.as_bytes(),
-        force,
-    )?;
-
-    Ok(())
-}
-
-fn _create_nix_base_config(work_dir: &Path, force: Option<bool>) -> Result<(), ProjectError> {
-    let nix_base_config = NixBaseConfig::default();
-    let rendered_json = nix_base_config
-        .to_json_string()
-        .change_context(ProjectError::GenFilesError)?;
-    let rendered_nix = nix_base_config
-        .render(NixBaseConfigsTemplates::Common)
-        .change_context(ProjectError::CreateBaseFiles(
-            "Failed at rendering base config".to_string(),
-        ))?;
-
+/// Writes the rendered nix files and the JSON file of an app to the work dir
+pub(crate) fn write_app_files(
+    work_dir: &Path,
+    force: Option<bool>,
+    rendered_nix: &HashMap<String, String>,
+    json_file_name: &str,
+    rendered_json: &str,
+) -> Result<(), ProjectError> {
     for (key, val) in rendered_nix.iter() {
-        create_file(
-            Path::new(&work_dir.join(key.replace(".templ", ""))),
-            val.as_bytes(),
-            force,
-        )?;
+        let file = key.replace(".templ", "");
+        create_file(Path::new(&work_dir.join(&file)), val.as_bytes(), force)?;
+        template_upgrade::remember_rendered(work_dir, &file, val)?;
     }
 
     create_file(
-        Path::new(&work_dir.join("src/nix_base_config.json")),
+        Path::new(&work_dir.join(json_file_name)),
         rendered_json.as_bytes(),
         force,
-    )?;
-
-    Ok(())
+    )
 }
 
 pub fn create_file(path: &Path, contents: &[u8], force: Option<bool>) -> Result<(), ProjectError> {
@@ -422,23 +320,42 @@ pub fn create_file(path: &Path, contents: &[u8], force: Option<bool>) -> Result<
         }
     }
 
-    let mut file = File::create(path)
-        .change_context(ProjectError::GenFilesError)
-        .attach_printable_lazy(|| {
-            format!(
-                "Unable to create file {}",
-                path.to_str().unwrap_or("Unable to unwrap path")
-            )
-        })?;
+    write_file_atomic(path, contents, None)
+}
 
-    file.write_all(contents)
-        .change_context(ProjectError::GenFilesError)
-        .attach_printable_lazy(|| {
-            format!(
-                "Unable to write file contents to {}",
-                path.to_str().unwrap_or_default()
-            )
-        })?;
+/// The git exclude file of the work dir, unlike `.gitignore` it is not shared
+pub(crate) const GIT_EXCLUDE_FILE: &str = ".git/info/exclude";
+
+/// Adds an entry to the [GIT_EXCLUDE_FILE] of the work dir, e.g. for files
+/// that belong to the local machine only. Does nothing if the work dir is
+/// not a git repository or the entry is already excluded.
+pub(crate) fn exclude_from_git(work_dir: &Path, entry: &str) -> Result<(), ProjectError> {
+    if !work_dir.join(".git").is_dir() {
+        return Ok(());
+    }
+
+    let exclude = work_dir.join(GIT_EXCLUDE_FILE);
+    let excluded = fs::read_to_string(&exclude).unwrap_or_default();
+    if excluded.lines().any(|l| l.trim() == entry) {
+        return Ok(());
+    }
+
+    let path = exclude.display().to_string();
+    if let Some(parent) = exclude.parent() {
+        fs::create_dir_all(parent)
+            .change_context(ProjectError::CreatePathError(parent.display().to_string()))?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&exclude)
+        .change_context(ProjectError::FileOpenError(path.clone()))?;
+    let separator = if excluded.is_empty() || excluded.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    write!(file, "{}{}\n", separator, entry).change_context(ProjectError::FileOpenError(path))?;
 
     Ok(())
 }
@@ -469,20 +386,147 @@ pub fn update_file(path: &Path, contents: &[u8]) -> Result<(), ProjectError> {
         )));
     }
 
-    let mut file = File::create(path)
+    write_file_atomic(path, contents, None)
+}
+
+/// Writes the rendered nix files of an app to the work dir, replacing the
+/// project [Variables] they use.
+pub(crate) fn update_nix_files(
+    work_dir: &Path,
+    rendered_nix: &HashMap<String, String>,
+) -> Result<(), ProjectError> {
+    let vars = Variables::load(work_dir);
+    for (key, val) in rendered_nix.iter() {
+        let file = key.replace(".templ", "");
+        let contents = vars.substitute(val);
+        update_file(Path::new(&work_dir.join(&file)), contents.as_bytes())?;
+        template_upgrade::remember_rendered(work_dir, &file, &contents)?;
+    }
+
+    Ok(())
+}
+
+/// The mode of secret files written by [write_file_atomic]
+pub(crate) const SECRET_FILE_MODE: u32 = 0o600;
+
+/// The mode new files are created with before the umask is applied
+const DEFAULT_FILE_MODE: u32 = 0o666;
+
+/// Atomically replaces the contents of a file.
+///
+/// The contents are first written to a temporary file next to the target,
+/// flushed to disk and then renamed over the target. A crash or power loss
+/// will leave either the old or the new file on disk, never a truncated one.
+/// The temporary file is created with the final mode, so secrets never
+/// become readable by others in between.
+///
+/// # Arguments
+///
+/// * `path` - A reference to the path of the file to write.
+/// * `contents` - The contents to write to the file.
+/// * `mode` - The mode of the file, e.g. [SECRET_FILE_MODE]. If `None`, an
+///   existing file keeps its mode and a new one gets the umask default.
+///
+/// # Errors
+///
+/// This function will return an error if:
+///
+/// * The path has no parent directory or file name.
+/// * The temporary file cannot be created, written or synced.
+/// * The temporary file cannot be renamed to the target path.
+pub(crate) fn write_file_atomic(
+    path: &Path,
+    contents: &[u8],
+    mode: Option<u32>,
+) -> Result<(), ProjectError> {
+    let parent = path.parent().ok_or_else(|| {
+        Report::new(ProjectError::GenFilesError).attach_printable(format!(
+            "Unable to determine parent directory of {}",
+            path.to_str().unwrap_or("Unable to unwrap path")
+        ))
+    })?;
+    let file_name = path.file_name().ok_or_else(|| {
+        Report::new(ProjectError::GenFilesError).attach_printable(format!(
+            "Unable to determine file name of {}",
+            path.to_str().unwrap_or("Unable to unwrap path")
+        ))
+    })?;
+
+    let mut tmp_name = OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = parent.join(tmp_name);
+    let mode = mode.or_else(|| {
+        fs::metadata(path)
+            .ok()
+            .map(|m| m.permissions().mode() & 0o7777)
+    });
+
+    let res = write_and_sync(&tmp_path, contents, mode).and_then(|_| {
+        fs::rename(&tmp_path, path)
+            .change_context(ProjectError::GenFilesError)
+            .attach_printable_lazy(|| {
+                format!(
+                    "Unable to move temporary file into place at {}",
+                    path.to_str().unwrap_or_default()
+                )
+            })
+    });
+
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+        return res;
+    }
+
+    // Persist the rename itself. Not every platform allows opening a
+    // directory for syncing, so failures here are not fatal.
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+
+    Ok(())
+}
+
+fn write_and_sync(path: &Path, contents: &[u8], mode: Option<u32>) -> Result<(), ProjectError> {
+    let mut file = File::options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(mode.unwrap_or(DEFAULT_FILE_MODE))
+        .open(path)
         .change_context(ProjectError::GenFilesError)
         .attach_printable_lazy(|| {
             format!(
-                "Unable to open file {} for updating",
+                "Unable to create file {}",
                 path.to_str().unwrap_or("Unable to unwrap path")
             )
         })?;
+    // the mode above is masked by the umask and ignored for existing files
+    if let Some(mode) = mode {
+        file.set_permissions(fs::Permissions::from_mode(mode))
+            .change_context(ProjectError::GenFilesError)
+            .attach_printable_lazy(|| {
+                format!(
+                    "Unable to set the permissions of {}",
+                    path.to_str().unwrap_or_default()
+                )
+            })?;
+    }
 
     file.write_all(contents)
         .change_context(ProjectError::GenFilesError)
         .attach_printable_lazy(|| {
             format!(
-                "Unable to write updated contents to {}",
+                "Unable to write file contents to {}",
+                path.to_str().unwrap_or_default()
+            )
+        })?;
+
+    file.sync_all()
+        .change_context(ProjectError::GenFilesError)
+        .attach_printable_lazy(|| {
+            format!(
+                "Unable to sync file contents to disk {}",
                 path.to_str().unwrap_or_default()
             )
         })?;
@@ -571,15 +615,34 @@ pub fn trim_lines_left(input: &str) -> String {
     result
 }
 
+/// Escapes text so it can be placed inside a nix indented string (`'' ... ''`).
+///
+/// # Example
+///
+/// ```
+/// use nixblitzlib::utils::escape_nix_indented_string;
+///
+/// assert_eq!(escape_nix_indented_string("a '' b ${c}"), "a ''' b ''${c}");
+/// ```
+pub fn escape_nix_indented_string(input: &str) -> String {
+    input.replace("''", "'''").replace("${", "''${")
+}
+
 #[cfg(test)]
 mod tests {
-    use std::fs::{self, create_dir, create_dir_all, File};
+    use std::{
+        fs::{self, create_dir, create_dir_all, File},
+        os::unix::fs::PermissionsExt,
+        path::Path,
+    };
 
     use crate::{
         errors::ProjectError,
         utils::{
-            check_password_validity_confirm, create_file, safety_checks, trim_lines_left,
-            unix_hash_password, update_file,
+            bcrypt_hash_password, check_password_validity_confirm, create_file,
+            create_rpcauth_hmac, escape_nix_indented_string, generate_random_password, hmac_sha256,
+            safety_checks, to_hex, trim_lines_left, unix_hash_password, update_file,
+            write_file_atomic, SECRET_FILE_MODE,
         },
     };
     use sha_crypt::sha512_check;
@@ -595,6 +658,15 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_bcrypt_hash_password() {
+        const TEST_PW: &str = "my_strong_password";
+
+        let hashed = bcrypt_hash_password(TEST_PW).unwrap();
+        assert!(bcrypt::verify(TEST_PW, &hashed).unwrap());
+        assert!(!bcrypt::verify("wrong", &hashed).unwrap());
+    }
+
     #[test]
     fn test_check_password_sanity_confirm() {
         let main_password = "strong_password";
@@ -619,6 +691,27 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231, test case 2
+        let res = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            to_hex(&res),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_create_rpcauth_hmac() {
+        let pw = generate_random_password(32).unwrap();
+        assert_eq!(pw.len(), 64);
+
+        let res = create_rpcauth_hmac(&pw).unwrap();
+        let (salt, hmac) = res.split_once('$').unwrap();
+        assert_eq!(salt.len(), 32);
+        assert_eq!(hmac, to_hex(&hmac_sha256(salt.as_bytes(), pw.as_bytes())));
+    }
+
     #[test]
     fn safety_checks_non_existent_path() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -718,6 +811,61 @@ mod tests {
         assert_eq!(actual_contents, UPDATED_CONTENTS);
     }
 
+    #[test]
+    fn write_file_atomic_leaves_no_temp_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("atomic.json");
+        fs::write(&file_path, "old").unwrap();
+
+        let result = write_file_atomic(&file_path, "new".as_bytes(), None);
+        assert!(result.is_ok());
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "new");
+
+        let entries: Vec<_> = fs::read_dir(temp_dir.path()).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn write_file_atomic_keeps_the_mode() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("config.json");
+        let mode = |p: &Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+
+        // new files get the same mode as with fs::write
+        let umask_default = temp_dir.path().join("umask_default");
+        fs::write(&umask_default, "").unwrap();
+        write_file_atomic(&file_path, "new".as_bytes(), None).unwrap();
+        assert_eq!(mode(&file_path), mode(&umask_default));
+
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o640)).unwrap();
+        write_file_atomic(&file_path, "newer".as_bytes(), None).unwrap();
+        assert_eq!(mode(&file_path), 0o640);
+    }
+
+    #[test]
+    fn write_file_atomic_sets_the_secret_mode() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("secret");
+        let mode = |p: &Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+
+        write_file_atomic(&file_path, "new".as_bytes(), Some(SECRET_FILE_MODE)).unwrap();
+        assert_eq!(mode(&file_path), 0o600);
+
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_file_atomic(&file_path, "newer".as_bytes(), Some(SECRET_FILE_MODE)).unwrap();
+        assert_eq!(mode(&file_path), 0o600);
+    }
+
+    #[test]
+    fn write_file_atomic_fails_for_missing_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("missing/atomic.json");
+
+        let result = write_file_atomic(&file_path, "new".as_bytes(), None);
+        assert!(result.is_err());
+        assert!(!file_path.exists());
+    }
+
     #[test]
     fn overwrite_existing_file_with_force() {
         const FILE_NAME: &str = "test_file.txt";
@@ -767,6 +915,16 @@ mod tests {
         // assert!(res.is_ok());
     }
 
+    #[test]
+    fn test_escape_nix_indented_string() {
+        assert_eq!(escape_nix_indented_string("plain text"), "plain text");
+        assert_eq!(escape_nix_indented_string("it''s"), "it'''s");
+        assert_eq!(
+            escape_nix_indented_string("${pkgs.hello}"),
+            "''${pkgs.hello}"
+        );
+    }
+
     #[test]
     fn test_trim_lines_left() {
         let input = r#"
@@ -0,0 +1,424 @@
+use core::fmt;
+use std::{collections::BTreeMap, str::FromStr};
+
+use crate::{
+    app_option_data::option_data::{GetOptionId, OptionData, OptionId, ToOptionId},
+    apps::SupportedApps,
+    bitcoind::{BitcoindConfigOption, PruneOptions},
+    dependencies,
+    number_value::NumberValue,
+    project::Project,
+    variables::Variables,
+};
+
+/// Option names holding a directory that must not be shared between apps
+pub(crate) const DATA_DIR_OPTIONS: [&str; 2] = ["data_dir", "db_dir"];
+
+/// Apps that need the full block chain and can't run on a pruned node
+pub const FULL_CHAIN_APPS: [SupportedApps; 3] = [
+    SupportedApps::Electrs,
+    SupportedApps::Fulcrum,
+    SupportedApps::Mempool,
+];
+
+/// Groups of apps of which at most one can be enabled
+pub const EXCLUSIVE_APPS: [&[SupportedApps]; 1] = [&[
+    SupportedApps::Electrs,
+    SupportedApps::Fulcrum,
+    SupportedApps::ElectrumPersonalServer,
+]];
+
+/// The kind of resource multiple apps are trying to use
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConflictKind {
+    /// A TCP port
+    Port(u16),
+
+    /// A location served by nginx
+    NginxLocation(String),
+
+    /// A directory where an app stores its data
+    DataDir(String),
+
+    /// bitcoind prunes the block chain while other options need all of it
+    Prune,
+
+    /// Apps that serve the same purpose and can't run side by side
+    Exclusive,
+}
+
+/// A resource claimed by more than one option of the enabled apps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigConflict {
+    pub kind: ConflictKind,
+
+    /// The options claiming the resource
+    pub options: Vec<OptionId>,
+}
+
+impl fmt::Display for ConfigConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |options: &[OptionId]| {
+            options
+                .iter()
+                .map(|o| o.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let resource = match &self.kind {
+            ConflictKind::Port(p) => format!("Port {}", p),
+            ConflictKind::NginxLocation(l) => format!("Nginx location \"{}\"", l),
+            ConflictKind::DataDir(d) => format!("Data directory \"{}\"", d),
+            ConflictKind::Prune => {
+                // The first option is always the prune option itself
+                return write!(
+                    f,
+                    "Pruning is incompatible with {}",
+                    join(self.options.get(1..).unwrap_or_default())
+                );
+            }
+            ConflictKind::Exclusive => {
+                return write!(f, "Only one of {} can be enabled", join(&self.options));
+            }
+        };
+
+        write!(f, "{} is used by {}", resource, join(&self.options))
+    }
+}
+
+/// Scans all enabled apps for ports, nginx locations and data directories
+/// that are claimed more than once. Also reports a pruned node that is
+/// combined with the transaction index or apps needing the full block chain
+/// and mutually exclusive apps that are enabled together.
+///
+/// # Returns
+///
+/// The list of conflicts, empty if the configuration is fine.
+pub fn validate(project: &Project) -> Vec<ConfigConflict> {
+    let mut claims: BTreeMap<ConflictKind, Vec<OptionId>> = BTreeMap::new();
+    let mut enabled = vec![];
+    let vars = project.variables();
+
+    for id in 0..SupportedApps::as_string_list().len() {
+        let Some(app) = SupportedApps::from_id(id) else {
+            continue;
+        };
+        if !project.is_app_enabled(app) {
+            continue;
+        }
+        enabled.push(app);
+
+        let options = project.get_options_for(app);
+        for (kind, option) in collect_claims(&options, &vars) {
+            claims.entry(kind).or_default().push(option);
+        }
+    }
+
+    for route in project.proxy_routes() {
+        claims
+            .entry(ConflictKind::NginxLocation(route.path))
+            .or_default()
+            .push(route.option);
+    }
+
+    let mut res: Vec<ConfigConflict> = claims
+        .into_iter()
+        .filter(|(_, options)| options.len() > 1)
+        .map(|(kind, options)| ConfigConflict { kind, options })
+        .collect();
+
+    let (prune, tx_index) = prune_settings(&project.get_options_for(SupportedApps::BitcoinCore));
+    if let Some(conflict) = check_prune(prune, tx_index, &enabled) {
+        res.push(conflict);
+    }
+    res.extend(check_exclusive(&enabled));
+
+    res
+}
+
+/// Checks whether more than one app of an [EXCLUSIVE_APPS] group is enabled
+///
+/// # Returns
+///
+/// One conflict per group, listing the enable options of its enabled apps.
+pub fn check_exclusive(enabled: &[SupportedApps]) -> Vec<ConfigConflict> {
+    EXCLUSIVE_APPS
+        .iter()
+        .filter_map(|group| {
+            let options: Vec<OptionId> = group
+                .iter()
+                .filter(|a| enabled.contains(*a))
+                .filter_map(|a| dependencies::enable_option_id(*a))
+                .collect();
+
+            (options.len() > 1).then_some(ConfigConflict {
+                kind: ConflictKind::Exclusive,
+                options,
+            })
+        })
+        .collect()
+}
+
+/// Checks whether pruning clashes with the transaction index or with
+/// enabled apps that need the full block chain.
+///
+/// # Arguments
+///
+/// * `prune` - The prune mode of bitcoind
+/// * `tx_index` - Whether bitcoind maintains the transaction index
+/// * `enabled` - The enabled apps
+///
+/// # Returns
+///
+/// The conflict, with the prune option first, or `None` if there is none.
+pub fn check_prune(
+    prune: PruneOptions,
+    tx_index: bool,
+    enabled: &[SupportedApps],
+) -> Option<ConfigConflict> {
+    if prune == PruneOptions::Disable {
+        return None;
+    }
+
+    let mut options = vec![BitcoindConfigOption::Prune.to_option_id()];
+    if tx_index {
+        options.push(BitcoindConfigOption::TxIndex.to_option_id());
+    }
+    options.extend(
+        FULL_CHAIN_APPS
+            .iter()
+            .filter(|a| enabled.contains(*a))
+            .filter_map(|a| dependencies::enable_option_id(*a)),
+    );
+
+    if options.len() == 1 {
+        return None;
+    }
+
+    Some(ConfigConflict {
+        kind: ConflictKind::Prune,
+        options,
+    })
+}
+
+/// Extracts the prune mode and the tx index flag from the bitcoind options
+fn prune_settings(options: &[OptionData]) -> (PruneOptions, bool) {
+    let mut prune = PruneOptions::Disable;
+    let mut tx_index = false;
+    for option in options {
+        match option {
+            OptionData::StringList(l) if *l.id() == BitcoindConfigOption::Prune.to_option_id() => {
+                prune = PruneOptions::from_str(l.value()).unwrap_or(PruneOptions::Disable);
+            }
+            OptionData::Bool(b) if *b.id() == BitcoindConfigOption::TxIndex.to_option_id() => {
+                tx_index = b.value();
+            }
+            _ => (),
+        }
+    }
+
+    (prune, tx_index)
+}
+
+/// Collects the resources the options of a single app claim
+///
+/// Data dirs are compared after resolving the project [Variables].
+fn collect_claims(options: &[OptionData], vars: &Variables) -> Vec<(ConflictKind, OptionId)> {
+    let mut res = vec![];
+
+    for option in options {
+        match option {
+            OptionData::Port(p) => {
+                if let NumberValue::U16(Some(port)) = p.value() {
+                    res.push((ConflictKind::Port(*port), p.id().clone()));
+                }
+            }
+            OptionData::TextEdit(t) if DATA_DIR_OPTIONS.contains(&t.id().option.as_str()) => {
+                let dir = normalize_path(&vars.substitute(t.value()));
+                if !dir.is_empty() {
+                    res.push((ConflictKind::DataDir(dir), t.id().clone()));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    res
+}
+
+/// Strips trailing slashes so "/var/lib/lnd/" and "/var/lib/lnd" are treated equally
+pub(crate) fn normalize_path(path: &str) -> String {
+    let trimmed = path.trim().trim_end_matches('/');
+    if trimmed.is_empty() && path.trim().starts_with('/') {
+        return "/".to_string();
+    }
+
+    trimmed.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{
+        app_option_data::{
+            bool_data::BoolOptionChangeData,
+            option_data::{OptionDataChangeNotification, ToOptionId},
+        },
+        blitz_api::BlitzApiConfigOption,
+        blitz_webui::BlitzWebUiConfigOption,
+        cln::ClnConfigOption,
+        defaults,
+        electrs::ElectrsConfigOption,
+        errors::ProjectError,
+        fulcrum::FulcrumConfigOption,
+        lnd::LndConfigOption,
+        utils::init_default_project,
+    };
+
+    use super::*;
+
+    fn set_bool(project: &mut Project, id: OptionId, value: bool) {
+        project.set_selected_app(id.app);
+        project
+            .on_option_changed(OptionDataChangeNotification::Bool(
+                BoolOptionChangeData::new(id, value),
+            ))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_default_project_is_valid() {
+        let temp_dir = tempdir().unwrap();
+        init_default_project(temp_dir.path(), Some(false)).unwrap();
+
+        let project = Project::load(temp_dir.path().to_path_buf()).unwrap();
+        assert!(validate(&project).is_empty());
+    }
+
+    #[test]
+    fn test_port_conflict() {
+        let temp_dir = tempdir().unwrap();
+        init_default_project(temp_dir.path(), Some(false)).unwrap();
+
+        let mut project = Project::load(temp_dir.path().to_path_buf()).unwrap();
+        set_bool(&mut project, LndConfigOption::Enable.to_option_id(), true);
+        set_bool(&mut project, ClnConfigOption::Enable.to_option_id(), true);
+
+        let conflicts = validate(&project);
+        assert_eq!(
+            conflicts,
+            vec![ConfigConflict {
+                kind: ConflictKind::Port(defaults::lnd::PORT),
+                options: vec![
+                    ClnConfigOption::Port.to_option_id(),
+                    LndConfigOption::Port.to_option_id()
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_nginx_location_conflict() {
+        let temp_dir = tempdir().unwrap();
+        init_default_project(temp_dir.path(), Some(false)).unwrap();
+
+        let mut project = Project::load(temp_dir.path().to_path_buf()).unwrap();
+        set_bool(
+            &mut project,
+            BlitzWebUiConfigOption::Enable.to_option_id(),
+            true,
+        );
+        set_bool(
+            &mut project,
+            BlitzApiConfigOption::NginxEnable.to_option_id(),
+            true,
+        );
+
+        // both would be served from "/"
+        project.set_selected_app(SupportedApps::WebUI);
+        let err = project
+            .on_option_changed(OptionDataChangeNotification::Bool(
+                BoolOptionChangeData::new(BlitzWebUiConfigOption::NginxEnable.to_option_id(), true),
+            ))
+            .unwrap_err();
+        match err.current_context() {
+            ProjectError::ConfigConflict(conflict) => {
+                assert_eq!(conflict.kind, ConflictKind::NginxLocation("/".to_string()));
+                assert_eq!(
+                    conflict.options,
+                    vec![
+                        BlitzApiConfigOption::NginxEnable.to_option_id(),
+                        BlitzWebUiConfigOption::NginxEnable.to_option_id(),
+                    ]
+                );
+            }
+            e => panic!("unexpected error {:?}", e),
+        }
+
+        // the rejected change is not kept
+        assert!(validate(&project).is_empty());
+        assert!(project
+            .proxy_routes()
+            .iter()
+            .all(|r| r.app != SupportedApps::WebUI));
+    }
+
+    #[test]
+    fn test_check_prune() {
+        let automatic = PruneOptions::Automatic { prune_at: 2048 };
+        assert!(check_prune(PruneOptions::Disable, true, &FULL_CHAIN_APPS).is_none());
+        assert!(check_prune(automatic, false, &[SupportedApps::LND]).is_none());
+
+        let conflict = check_prune(automatic, true, &[SupportedApps::Electrs]).unwrap();
+        assert_eq!(conflict.kind, ConflictKind::Prune);
+        assert_eq!(
+            conflict.options,
+            vec![
+                BitcoindConfigOption::Prune.to_option_id(),
+                BitcoindConfigOption::TxIndex.to_option_id(),
+                ElectrsConfigOption::Enable.to_option_id(),
+            ]
+        );
+        assert_eq!(
+            conflict.to_string(),
+            "Pruning is incompatible with Bitcoin Core: tx_index, Electrs: enable"
+        );
+    }
+
+    #[test]
+    fn test_check_exclusive() {
+        assert!(check_exclusive(&[SupportedApps::Electrs, SupportedApps::Mempool]).is_empty());
+
+        let conflicts = check_exclusive(&[SupportedApps::Fulcrum, SupportedApps::Electrs]);
+        assert_eq!(
+            conflicts,
+            vec![ConfigConflict {
+                kind: ConflictKind::Exclusive,
+                options: vec![
+                    ElectrsConfigOption::Enable.to_option_id(),
+                    FulcrumConfigOption::Enable.to_option_id(),
+                ],
+            }]
+        );
+        assert_eq!(
+            conflicts[0].to_string(),
+            "Only one of Electrs: enable, Fulcrum: enable can be enabled"
+        );
+
+        let conflicts = check_exclusive(&[
+            SupportedApps::ElectrumPersonalServer,
+            SupportedApps::Electrs,
+        ]);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].options.len(), 2);
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/var/lib/lnd/"), "/var/lib/lnd");
+        assert_eq!(normalize_path("/"), "/");
+        assert_eq!(normalize_path(""), "");
+    }
+}
@@ -0,0 +1,198 @@
+//! Project wide variables that can be used in text options.
+//!
+//! A value like `${DATA_ROOT}/bitcoind` is stored as is and resolved when the
+//! nix files are written, so the same layout can be reused on machines with
+//! different mount points. The variables are defined in the
+//! [NixBaseConfig](crate::nix_base_config::NixBaseConfig) as `NAME=value` entries.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use error_stack::{Report, Result};
+
+use crate::{errors::ProjectError, nix_base_config::NixBaseConfig};
+
+/// The variables of a project, by name
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Variables(BTreeMap<String, String>);
+
+impl Variables {
+    /// Parses `NAME=value` entries. Names consist of uppercase letters,
+    /// digits and underscores and must not start with a digit.
+    ///
+    /// # Errors
+    ///
+    /// [ProjectError::InvalidValue] listing all invalid entries
+    pub fn parse(option: &str, entries: &[String]) -> Result<Self, ProjectError> {
+        let mut vars = BTreeMap::new();
+        let mut issues = vec![];
+        for entry in entries {
+            match entry.split_once('=') {
+                Some((name, value)) if is_valid_name(name.trim()) => {
+                    if vars
+                        .insert(name.trim().to_string(), value.trim().to_string())
+                        .is_some()
+                    {
+                        issues.push(format!("{} is defined twice", name.trim()));
+                    }
+                }
+                _ => issues.push(format!("'{}' is not a NAME=value entry", entry)),
+            }
+        }
+
+        if !issues.is_empty() {
+            return Err(Report::new(ProjectError::InvalidValue(
+                option.to_string(),
+                issues,
+            )));
+        }
+
+        Ok(Self(vars))
+    }
+
+    /// Loads the variables of the project in the given work dir.
+    /// Returns no variables if the base config can't be read.
+    pub fn load(work_dir: &Path) -> Self {
+        fs::read_to_string(work_dir.join(crate::nix_base_config::JSON_FILE_NAME))
+            .ok()
+            .and_then(|json| NixBaseConfig::from_json(&json).ok())
+            .map(|config| config.variables())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(|v| v.as_str())
+    }
+
+    /// Makes sure all variables the value uses are defined
+    ///
+    /// # Errors
+    ///
+    /// [ProjectError::InvalidValue] listing the unknown variables
+    pub fn check(&self, option: &str, value: &str) -> Result<(), ProjectError> {
+        let issues: Vec<String> = used_names(value)
+            .into_iter()
+            .filter(|name| !self.0.contains_key(name))
+            .map(|name| format!("unknown variable ${{{}}}", name))
+            .collect();
+
+        if issues.is_empty() {
+            return Ok(());
+        }
+
+        Err(Report::new(ProjectError::InvalidValue(
+            option.to_string(),
+            issues,
+        )))
+    }
+
+    /// Replaces all `${NAME}` references to defined variables. Anything else,
+    /// like nix string interpolations, is left alone.
+    pub fn substitute(&self, text: &str) -> String {
+        let mut res = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("${") {
+            res.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            match after
+                .find('}')
+                .and_then(|end| self.get(&after[..end]).map(|v| (end, v)))
+            {
+                Some((end, value)) => {
+                    res.push_str(value);
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    res.push_str("${");
+                    rest = after;
+                }
+            }
+        }
+        res.push_str(rest);
+
+        res
+    }
+}
+
+/// Returns the names of all variables referenced in the value
+pub fn used_names(value: &str) -> Vec<String> {
+    let mut names = vec![];
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        rest = &rest[start + 2..];
+        if let Some(end) = rest.find('}') {
+            let name = &rest[..end];
+            if is_valid_name(name) && !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_uppercase() || c == '_' => {}
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> Variables {
+        Variables::parse(
+            "variables",
+            &["DATA_ROOT=/mnt/hdd".into(), " LOG_DIR = /var/log ".into()],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let vars = vars();
+        assert_eq!(vars.get("DATA_ROOT"), Some("/mnt/hdd"));
+        assert_eq!(vars.get("LOG_DIR"), Some("/var/log"));
+
+        let err = Variables::parse(
+            "variables",
+            &[
+                "lower=1".into(),
+                "NO_VALUE".into(),
+                "A=1".into(),
+                "A=2".into(),
+            ],
+        )
+        .unwrap_err();
+        match err.current_context() {
+            ProjectError::InvalidValue(_, issues) => assert_eq!(issues.len(), 3),
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let vars = vars();
+        assert!(vars.check("data_dir", "${DATA_ROOT}/bitcoind").is_ok());
+        assert!(vars.check("data_dir", "/var/lib/${cfg.user}").is_ok());
+        assert!(vars.check("data_dir", "${MISSING}/bitcoind").is_err());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let vars = vars();
+        assert_eq!(
+            vars.substitute("dataDir = \"${DATA_ROOT}/bitcoind\";"),
+            "dataDir = \"/mnt/hdd/bitcoind\";"
+        );
+        assert_eq!(
+            vars.substitute("${pkgs.openssl}/bin ${UNKNOWN} ${LOG_DIR}"),
+            "${pkgs.openssl}/bin ${UNKNOWN} /var/log"
+        );
+        assert_eq!(vars.substitute("${"), "${");
+    }
+}